@@ -0,0 +1,27 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::executor::block_on;
+use vaulta_simulator::backtest::BacktestEngine;
+use vaulta_simulator::Strategy;
+
+/// A year of the default seeded mock data
+fn engine() -> BacktestEngine {
+    BacktestEngine::new("2024-01-01", "2024-12-31", Strategy::balanced()).expect("valid backtest range")
+}
+
+fn backtest(c: &mut Criterion) {
+    // The mock data is seeded, so two runs over it agree
+    let first = block_on(engine().run()).expect("backtest failed");
+    let second = block_on(engine().run()).expect("backtest failed");
+    assert_eq!(first.total_return_pct, second.total_return_pct, "mock-data backtests should repeat");
+
+    c.bench_function("backtest_one_year_daily", |b| {
+        b.iter(|| block_on(engine().run()).expect("backtest failed"))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = backtest
+}
+criterion_main!(benches);
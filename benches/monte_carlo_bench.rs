@@ -0,0 +1,48 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::executor::block_on;
+use vaulta_simulator::monte_carlo::MonteCarloEngine;
+
+const SEED: u64 = 42;
+
+/// Sorted final values of a seeded stress test run on a pool of `threads` threads
+fn sorted_distribution(threads: usize, iterations: usize) -> Vec<f64> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("failed to build thread pool");
+    let mut engine = MonteCarloEngine::new(iterations, 1).with_seed(SEED);
+    let results = pool
+        .install(|| block_on(engine.run_stress_test(0.95)))
+        .expect("stress test failed");
+    
+    let mut distribution = results.distribution.expect("exact runs keep the distribution");
+    distribution.sort_by(f64::total_cmp);
+    distribution
+}
+
+fn stress_test(c: &mut Criterion) {
+    // The master seed alone determines the distribution, not the thread count
+    let single = sorted_distribution(1, 200);
+    for threads in [2, 4, 8] {
+        assert_eq!(
+            single,
+            sorted_distribution(threads, 200),
+            "distribution changed with {} threads",
+            threads
+        );
+    }
+    
+    c.bench_function("stress_test_1000_paths", |b| {
+        b.iter(|| {
+            let mut engine = MonteCarloEngine::new(1000, 1).with_seed(SEED);
+            block_on(engine.run_stress_test(0.95)).expect("stress test failed")
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = stress_test
+}
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use vaulta_simulator::simulator::{Simulator, SimulatorConfig};
+use vaulta_simulator::Strategy;
+
+const CAPITAL: f64 = 1_000_000.0;
+const STEPS: usize = 100;
+
+fn config() -> SimulatorConfig {
+    SimulatorConfig { seed: Some(42), ..SimulatorConfig::default() }
+}
+
+fn run(simulator: &mut Simulator) -> f64 {
+    for _ in 0..STEPS {
+        simulator.step().expect("step failed");
+    }
+    simulator.current_results().sharpe_ratio
+}
+
+/// One population evaluation, allocating a simulator per candidate or
+/// resetting one between them as `StrategyOptimizer::evaluate_population` does
+fn reset_vs_fresh(c: &mut Criterion) {
+    let candidates = [Strategy::conservative(), Strategy::balanced(), Strategy::aggressive(), Strategy::yield_maximizer()];
+
+    c.bench_function("population_fresh_simulators", |b| {
+        b.iter(|| {
+            candidates
+                .iter()
+                .map(|strategy| run(&mut Simulator::with_config(CAPITAL, strategy.clone(), config())))
+                .collect::<Vec<_>>()
+        })
+    });
+
+    c.bench_function("population_reset_simulator", |b| {
+        let mut simulator = Simulator::with_config(CAPITAL, Strategy::balanced(), config());
+        b.iter(|| {
+            candidates
+                .iter()
+                .map(|strategy| {
+                    simulator.reset(CAPITAL, strategy.clone());
+                    run(&mut simulator)
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = reset_vs_fresh
+}
+criterion_main!(benches);
@@ -0,0 +1,128 @@
+//! Feed the engines from a provider that only answers asynchronously, with a
+//! delay like a network source, and from a synchronous one through
+//! `SyncAdapter`, and print what each engine makes of it: the Monte Carlo
+//! universe described concurrently, a backtest on the provider's daily
+//! history, and `step_async` fetching each step's prices in one batch.
+//!
+//! ```text
+//! cargo run --example async_provider
+//! ```
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use time::OffsetDateTime;
+use vaulta_simulator::backtest::BacktestEngine;
+use vaulta_simulator::market::{AsyncMarketDataProvider, MarketDataProvider, MockMarketDataProvider, SyncAdapter};
+use vaulta_simulator::monte_carlo::MonteCarloEngine;
+use vaulta_simulator::types::AssetType;
+use vaulta_simulator::{SimulatorBuilder, Strategy};
+
+/// The mock market, answering each call after a delay and counting how many
+/// calls are in flight at once
+struct SlowFeed {
+    market: MockMarketDataProvider,
+    in_flight: AtomicUsize,
+    most_in_flight: AtomicUsize,
+    batches: AtomicUsize,
+}
+
+impl SlowFeed {
+    fn new() -> Self {
+        Self {
+            market: MockMarketDataProvider::new(),
+            in_flight: AtomicUsize::new(0),
+            most_in_flight: AtomicUsize::new(0),
+            batches: AtomicUsize::new(0),
+        }
+    }
+
+    async fn call<T>(&self, answer: impl FnOnce(&MockMarketDataProvider) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.most_in_flight.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        answer(&self.market)
+    }
+}
+
+#[async_trait]
+impl AsyncMarketDataProvider for SlowFeed {
+    async fn get_current_price(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.call(|market| MarketDataProvider::get_current_price(market, symbol)).await
+    }
+
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> anyhow::Result<Vec<Decimal>> {
+        self.call(|market| MarketDataProvider::get_historical_prices(market, symbol, days)).await
+    }
+
+    async fn get_volatility(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.call(|market| MarketDataProvider::get_volatility(market, symbol)).await
+    }
+
+    async fn get_yield_rate(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.call(|market| MarketDataProvider::get_yield_rate(market, symbol)).await
+    }
+
+    async fn get_expected_return(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.call(|market| MarketDataProvider::get_expected_return(market, symbol)).await
+    }
+
+    async fn get_asset_type(&self, symbol: &str) -> anyhow::Result<AssetType> {
+        self.call(|market| MarketDataProvider::get_asset_type(market, symbol)).await
+    }
+
+    /// One call for all of them, like a batch endpoint, which leaves out the
+    /// symbols it doesn't quote (MATIC, which the balanced strategy buys)
+    async fn get_prices_batch(&self, symbols: &[&str]) -> anyhow::Result<HashMap<String, Decimal>> {
+        self.batches.fetch_add(1, Ordering::SeqCst);
+        let quote = |market: &MockMarketDataProvider, symbol: &str| {
+            MarketDataProvider::get_current_price(market, symbol).ok().map(|price| (symbol.to_string(), price))
+        };
+        self.call(|market| Ok(symbols.iter().filter_map(|symbol| quote(market, symbol)).collect())).await
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let symbols = ["USDC", "ETH", "BTC", "SOL"];
+    let feed = SlowFeed::new();
+    let mock = SyncAdapter::new(MockMarketDataProvider::new());
+
+    // The universe is described before any path runs, asking for it all at once
+    for (name, provider) in [("async", &feed as &dyn AsyncMarketDataProvider), ("sync", &mock)] {
+        let mut engine = MonteCarloEngine::new(200, 1)
+            .with_steps(30)
+            .with_seed(9)
+            .with_universe_from_provider(provider, &symbols)
+            .await?;
+        let run = engine.run().await?;
+        println!("{:<5} universe: mean final value {:.2}", name, run.expected_value);
+    }
+    println!("async provider calls in flight at once: up to {}", feed.most_in_flight.load(Ordering::SeqCst));
+
+    // The backtest runs on the provider's daily history, ending today
+    let today = OffsetDateTime::now_utc().date();
+    let start = (today - time::Duration::days(20)).to_string();
+    let end = (today - time::Duration::days(1)).to_string();
+    let mut engine = BacktestEngine::new(&start, &end, Strategy::balanced())?
+        .with_provider(&mock, &["USDC", "ETH"])
+        .await?;
+    let bars = engine.market_data().len();
+    let backtest = engine.run().await?;
+    println!("backtest {} to {}: {} bars, return {:.4}%", start, end, bars, backtest.total_return_pct);
+
+    // The step itself stays synchronous: its prices come in one batch, fetched first
+    let mut simulator = SimulatorBuilder::new()
+        .strategy(Strategy::balanced())
+        .provider(MockMarketDataProvider::new())
+        .universe_from_provider(&symbols)
+        .build()?;
+    for _ in 0..5 {
+        simulator.step_async(&feed).await?;
+    }
+    println!("5 async steps, {} batch requests", feed.batches.load(Ordering::SeqCst));
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Run a simulation in a background task and print its events as they arrive.
+//!
+//! ```text
+//! cargo run --example event_stream
+//! ```
+
+use tokio::sync::broadcast::error::RecvError;
+use vaulta_simulator::events::SimEvent;
+use vaulta_simulator::{Simulator, Strategy};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut simulator = Simulator::builder()
+        .capital(1_000_000.0)
+        .strategy(Strategy::balanced())
+        .seed(42)
+        .build()?;
+    let mut events = simulator.events();
+
+    let run = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        for _ in 0..50 {
+            simulator.step()?;
+        }
+        Ok(simulator.finalize())
+    });
+
+    loop {
+        match events.recv().await {
+            Ok(SimEvent::StepCompleted(snapshot)) => {
+                println!("step {:>3}  value {:.2}", snapshot.step, snapshot.total_value);
+            }
+            Ok(SimEvent::DecisionExecuted(entry)) => {
+                println!(
+                    "          executed {} {:.2}",
+                    entry.decision.traded_symbol(),
+                    entry.decision.amount
+                );
+            }
+            Ok(SimEvent::DecisionRejected(entry)) => {
+                println!("          rejected {} ({:?})", entry.decision.traded_symbol(), entry.status);
+            }
+            Ok(SimEvent::ShockApplied(shock)) => {
+                println!("          shock {} {}%", shock.symbol, shock.pct_change);
+            }
+            Ok(SimEvent::Finalized(summary)) => {
+                println!(
+                    "finished after {} steps: final value {:.2}, sharpe {:.3}",
+                    summary.steps, summary.final_value, summary.sharpe_ratio
+                );
+            }
+            Err(RecvError::Lagged(skipped)) => println!("          (skipped {} events)", skipped),
+            Err(RecvError::Closed) => break,
+        }
+    }
+
+    run.await??;
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Print square-root price impact at a few order sizes, a buy's realized fill
+//! price in the simulator degrading as the order grows against a small pool,
+//! and the yield maximizer capping its order at what the pool absorbs instead
+//! of routing most of a $100M book into it.
+//!
+//! ```text
+//! cargo run --example liquidity_impact
+//! ```
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::collections::HashMap;
+use vaulta_simulator::backtest::{BacktestConfig, Slippage};
+use vaulta_simulator::fees::FlatBps;
+use vaulta_simulator::liquidity::PriceImpact;
+use vaulta_simulator::market::{describe_asset, MockMarketDataProvider};
+use vaulta_simulator::transactions::TradeSide;
+use vaulta_simulator::types::{Asset, AssetType};
+use vaulta_simulator::{SimulatorBuilder, Strategy};
+
+fn pool(symbol: &str, liquidity: Decimal) -> Asset {
+    Asset {
+        symbol: symbol.to_string(),
+        name: format!("Pool {}", symbol),
+        asset_type: AssetType::DeFiPool,
+        current_price: dec!(1),
+        volatility: Decimal::ZERO,
+        yield_rate: dec!(0.25),
+        expected_return: Decimal::ZERO,
+        bond: None,
+        liquidity: Some(liquidity),
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    // 0.1 * sqrt(share): 1% of the liquidity moves the price 1%, all of it 10%
+    let impact = PriceImpact::default();
+    let liquidity = dec!(10_000_000);
+    println!("{:>14} {:>10} {:>12} {:>12} {:>12}", "Order", "Impact", "Cost", "Buy fill", "Sell fill");
+    for order in [dec!(10_000), dec!(100_000), dec!(1_000_000), liquidity] {
+        println!(
+            "{:>14.2} {:>9.4}% {:>12.2} {:>12.4} {:>12.4}",
+            order,
+            impact.impact(order, liquidity) * 100.0,
+            impact.cost(order, liquidity),
+            impact.fill_price(dec!(50), order, liquidity, TradeSide::Buy),
+            impact.fill_price(dec!(50), order, liquidity, TradeSide::Sell)
+        );
+    }
+    let cap = impact.max_notional(liquidity, 0.01).unwrap_or_default();
+    println!("largest order within 1% impact: {:.2}", cap);
+
+    // The mock provider describes its symbols with plausible daily volumes
+    let sol = describe_asset(&MockMarketDataProvider::new(), "SOL")?;
+    println!("mock SOL daily volume: {:.0}", sol.liquidity.unwrap_or_default());
+
+    // Buying half the book in a $10M pool: the realized price per unit, impact
+    // included, rises with the order
+    println!("\n{:>14} {:>14} {:>12}", "Order", "Impact", "Fill price");
+    for capital in [10_000.0, 200_000.0, 2_000_000.0, 20_000_000.0] {
+        let mut simulator = SimulatorBuilder::new()
+            .capital(capital)
+            .strategy(Strategy::conservative())
+            .universe(vec![pool("POOL", liquidity)])
+            .fee_model(FlatBps::new(Decimal::ZERO))
+            .price_impact(impact)
+            .build()?;
+        simulator.rebalance_to(&HashMap::from([("POOL".to_string(), dec!(0.5))]))?;
+        let executed = simulator
+            .decision_log()
+            .last()
+            .ok_or_else(|| anyhow::anyhow!("the rebalance should buy POOL"))?;
+        let amount = executed.decision.amount;
+        let fill = (amount + executed.execution_cost) / amount;
+        println!("{:>14.2} {:>14.2} {:>12.6}", amount, executed.execution_cost, fill);
+    }
+
+    // Without liquidity data the yield maximizer puts 90% of $100M in one pool;
+    // with it, the order stops at 1% impact
+    let run = |with_impact: bool| -> anyhow::Result<Decimal> {
+        let mut builder = SimulatorBuilder::new()
+            .capital(100_000_000.0)
+            .strategy(Strategy::yield_maximizer())
+            .universe(vec![pool("MAX_YIELD", dec!(20_000_000))])
+            .seed(3);
+        if with_impact {
+            builder = builder.price_impact(impact);
+        }
+        let mut simulator = builder.build()?;
+        simulator.step()?;
+        Ok(simulator.portfolio().positions.get("MAX_YIELD").map_or(Decimal::ZERO, |p| p.current_value))
+    };
+    let (unconstrained, capped) = (run(false)?, run(true)?);
+    println!("\nyield_maximizer in MAX_YIELD: {:.0} without impact, {:.0} with", unconstrained, capped);
+
+    // Backtest slippage with the same impact against the day's dollar volume
+    let config = BacktestConfig { slippage: Slippage::SquareRoot(impact), ..BacktestConfig::default() };
+    println!(
+        "backtest slippage on 100,000: {:.2} at 1% of the day's volume, {:.2} with no bar",
+        config.slippage(dec!(100_000), Some(liquidity)),
+        config.slippage(dec!(100_000), None)
+    );
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Fetch a snapshot with `HttpMarketDataProvider` from a local server that
+//! answers with the canned CoinGecko responses in `examples/data/coingecko`,
+//! never the live API, and print what it holds.
+//!
+//! ```text
+//! cargo run --example live_data_fixtures --features live-data
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use vaulta_simulator::live_data::HttpMarketDataProvider;
+use vaulta_simulator::market::MarketDataProvider;
+
+/// The fixture answering `target`, if there is one
+fn fixture(fixtures: &Path, target: &str) -> Option<String> {
+    let (path, _) = target.split_once('?').unwrap_or((target, ""));
+    let file = if path == "/simple/price" {
+        "simple_price.json".to_string()
+    } else {
+        let id = path.strip_prefix("/coins/")?.strip_suffix("/market_chart")?;
+        format!("market_chart_{}.json", id)
+    };
+    std::fs::read_to_string(fixtures.join(file)).ok()
+}
+
+async fn serve(mut stream: TcpStream, fixtures: PathBuf) -> anyhow::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+    let (status, body) = match fixture(&fixtures, target) {
+        Some(body) => ("200 OK", body),
+        None => ("404 Not Found", r#"{"error":"coin not found"}"#.to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/data/coingecko");
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream, fixtures.clone()));
+        }
+    });
+
+    let provider = HttpMarketDataProvider::coingecko()
+        .with_base_url(&base_url)
+        .with_timeout(Duration::from_secs(2))
+        .with_retries(2, Duration::from_millis(10));
+    let symbols = ["BTC", "ETH", "USDC"];
+    let snapshot = provider.snapshot(&symbols, 10).await?;
+
+    println!("{:<6} {:>12} {:>12}  last closes", "symbol", "price", "volatility");
+    for symbol in symbols {
+        println!(
+            "{:<6} {:>12.2} {:>12.4}  {:?}",
+            symbol,
+            snapshot.get_current_price(symbol)?,
+            snapshot.get_volatility(symbol)?,
+            snapshot.get_historical_prices(symbol, 3)?
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Import the CSV fixtures into a SQLite bar store in a temporary file and
+//! print what it serves: each symbol's price and volatility, and a window of
+//! hourly bars.
+//!
+//! ```text
+//! cargo run --example sqlite_store --features sqlite
+//! ```
+
+use std::path::Path;
+use time::macros::datetime;
+use vaulta_simulator::market::MarketDataProvider;
+use vaulta_simulator::sqlite::SqliteDataSource;
+
+const FIXTURES: [&str; 3] = ["majors.csv", "sol_hourly.csv", "stablecoins.csv"];
+
+fn main() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("vaulta-sqlite-store-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/data/csv_provider");
+
+    let store = SqliteDataSource::open(dir.join("bars.db"))?;
+    let mut imported = 0;
+    for file in FIXTURES {
+        imported += store.ingest_csv(fixtures.join(file))?;
+    }
+    println!("imported {} bars", imported);
+
+    for symbol in store.symbols()? {
+        println!(
+            "{:<5} {:>3} bars  price {:>14}  volatility {:.6}",
+            symbol,
+            store.bar_count(&symbol)?,
+            store.get_current_price(&symbol)?,
+            store.get_volatility(&symbol)?
+        );
+    }
+
+    // A range reads only the bars inside it
+    let window = store.bars("SOL", datetime!(2024-01-10 02:00 UTC), datetime!(2024-01-10 05:00 UTC))?;
+    for bar in window {
+        println!("SOL {}  close {}", bar.timestamp, bar.close);
+    }
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -1,10 +1,392 @@
 use crate::types::*;
-use crate::simulator::Simulator;
-use crate::strategy::Strategy;
+use crate::bootstrap::BlockBootstrap;
+use crate::calendar::CALENDAR_DAYS_PER_YEAR;
+use crate::fees::{FeeModel, BPS};
+use crate::liquidity::PriceImpact;
+use crate::market::AsyncMarketDataProvider;
+use crate::data_source::{
+    resample, validate_market_data, validate_yields, BarFrequency, HistoricalDataSource, MockDataSource,
+    ProviderHistory,
+};
+use crate::market_view::PriceHistory;
+use crate::metrics::{self, ActiveReturns};
+use crate::simulator::{Benchmark, Simulator, SimulatorConfig, TerminalValuation, YieldIncome};
+use crate::strategy::{RoutingStrategy, Strategy};
+use crate::transactions::TradeSide;
 use anyhow::{Context, Result};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
-use time::OffsetDateTime;
-use tracing::info;
+use rust_decimal_macros::dec;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::ops::Bound;
+use std::sync::{Arc, RwLock};
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime};
+use tracing::{info, warn};
+
+/// Format of backtest start and end dates
+const DATE_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+/// Drawdowns reported in `BacktestResults::drawdowns`
+const DRAWDOWN_TABLE_LEN: usize = 5;
+
+/// Slippage charged on a trade's notional
+#[derive(Debug, Clone, PartialEq)]
+pub enum Slippage {
+    /// Fixed basis points of notional
+    Bps(Decimal),
+    /// `impact_bps` scaled by the share of the day's dollar volume (volume
+    /// times close) the trade takes, up to the full `impact_bps` for trades as
+    /// large as the day's volume or on days the symbol has no bar
+    VolumeShare { impact_bps: Decimal },
+    /// Square-root price impact against the day's dollar volume, charged as
+    /// if the trade took the whole of it on days the symbol has no bar
+    SquareRoot(PriceImpact),
+}
+
+/// What to do about days a symbol has no price, even after forward-filling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// Leave the days out, so the strategy never steps on them
+    DropDays,
+    /// Fail the backtest
+    Error,
+}
+
+/// What to do about initial-portfolio positions in symbols the market data has no price for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnpricedPositions {
+    /// Fail the backtest
+    Error,
+    /// Hold them at their stored price for the whole run
+    Freeze,
+}
+
+/// Trading frictions the engine charges on every executed trade, replacing the
+/// strategy's own cost estimates, and how the market data is prepared
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestConfig {
+    /// Commission in basis points of notional
+    pub commission_bps: Decimal,
+    /// Commission per trade, on top of `commission_bps`
+    pub commission_fixed: Decimal,
+    pub slippage: Slippage,
+    /// Length of the bars the backtest steps on; finer market data is resampled to it
+    pub bar_frequency: BarFrequency,
+    /// Most consecutive days without a bar a symbol's last close is carried over
+    pub max_fill_days: usize,
+    pub gap_policy: GapPolicy,
+    /// Close-to-close move, as a fraction, beyond which a bar is flagged as an outlier
+    pub outlier_threshold: Option<Decimal>,
+    /// Clip flagged closes to the outlier threshold instead of only counting them
+    pub clip_outliers: bool,
+    /// Days between strategy runs, starting on the first day; `None` runs it every day
+    pub rebalance_days: Option<u32>,
+    /// Log progress every this many stepped bars; `None` logs only the start
+    pub progress_every_days: Option<usize>,
+    /// Annual return the Sortino ratio counts shortfalls below, as a fraction
+    pub minimum_acceptable_return: f64,
+    /// Whether yield income is credited to cash or reinvested in the paying position
+    pub yield_income: YieldIncome,
+    pub unpriced_positions: UnpricedPositions,
+    /// Keep the simulator's full event log in the results
+    pub record_events: bool,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            commission_bps: dec!(10),
+            commission_fixed: Decimal::ZERO,
+            slippage: Slippage::Bps(dec!(5)),
+            bar_frequency: BarFrequency::Daily,
+            max_fill_days: 5,
+            gap_policy: GapPolicy::DropDays,
+            outlier_threshold: Some(dec!(0.5)),
+            clip_outliers: false,
+            rebalance_days: None,
+            progress_every_days: None,
+            minimum_acceptable_return: 0.0,
+            yield_income: YieldIncome::Cash,
+            unpriced_positions: UnpricedPositions::Error,
+            record_events: false,
+        }
+    }
+}
+
+impl BacktestConfig {
+    /// No commission and no slippage
+    pub fn frictionless() -> Self {
+        Self {
+            commission_bps: Decimal::ZERO,
+            commission_fixed: Decimal::ZERO,
+            slippage: Slippage::Bps(Decimal::ZERO),
+            ..Self::default()
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let slippage_bps = match &self.slippage {
+            Slippage::Bps(bps) => *bps,
+            Slippage::VolumeShare { impact_bps } => *impact_bps,
+            Slippage::SquareRoot(_) => Decimal::ZERO,
+        };
+        if self.commission_bps < Decimal::ZERO
+            || self.commission_fixed < Decimal::ZERO
+            || slippage_bps < Decimal::ZERO
+        {
+            return Err(anyhow::anyhow!("backtest commission and slippage can't be negative: {:?}", self));
+        }
+        if self.rebalance_days == Some(0) || self.progress_every_days == Some(0) {
+            return Err(anyhow::anyhow!("rebalance and progress intervals must be at least one day and one bar"));
+        }
+        if self.outlier_threshold.is_some_and(|threshold| threshold <= Decimal::ZERO) {
+            return Err(anyhow::anyhow!("outlier threshold must be positive, got {:?}", self.outlier_threshold));
+        }
+        Ok(())
+    }
+
+    /// Commission on a trade of `notional`
+    pub fn commission(&self, notional: Decimal) -> Decimal {
+        if notional.is_zero() {
+            return Decimal::ZERO;
+        }
+        self.commission_fixed + notional * self.commission_bps / BPS
+    }
+
+    /// Slippage on a trade of `notional` on a day with `dollar_volume` traded
+    /// in the symbol, if it had a bar
+    pub fn slippage(&self, notional: Decimal, dollar_volume: Option<Decimal>) -> Decimal {
+        match &self.slippage {
+            Slippage::Bps(bps) => notional * *bps / BPS,
+            Slippage::VolumeShare { impact_bps } => {
+                let share = dollar_volume
+                    .filter(|volume| *volume > Decimal::ZERO)
+                    .map_or(Decimal::ONE, |volume| (notional / volume).min(Decimal::ONE));
+                notional * *impact_bps * share / BPS
+            }
+            Slippage::SquareRoot(impact) => {
+                let volume = dollar_volume.filter(|volume| *volume > Decimal::ZERO).unwrap_or(notional);
+                impact.cost(notional, volume)
+            }
+        }
+    }
+}
+
+/// A strategy or cost parameter a backtest sweep varies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BacktestParameter {
+    /// Share of cash the strategy deploys on each allocation, in (0, 1]
+    AllocationFraction,
+    /// Days between strategy runs, a whole number; 0 runs it every bar
+    RebalanceDays,
+    /// Commission in basis points of notional
+    CommissionBps,
+    /// Fixed slippage in basis points of notional
+    SlippageBps,
+}
+
+impl BacktestParameter {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "allocation_fraction" | "allocation" => Ok(Self::AllocationFraction),
+            "rebalance_days" | "rebalance" => Ok(Self::RebalanceDays),
+            "commission_bps" | "commission" => Ok(Self::CommissionBps),
+            "slippage_bps" | "slippage" => Ok(Self::SlippageBps),
+            _ => Err(anyhow::anyhow!("Unknown backtest parameter: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AllocationFraction => "allocation_fraction",
+            Self::RebalanceDays => "rebalance_days",
+            Self::CommissionBps => "commission_bps",
+            Self::SlippageBps => "slippage_bps",
+        }
+    }
+
+    /// Set the parameter to `value` on the strategy or backtest config it belongs to
+    fn apply(&self, value: f64, strategy: Strategy, config: &mut BacktestConfig) -> Result<Strategy> {
+        let decimal = || Decimal::try_from(value).with_context(|| format!("{} = {} is out of range", self.name(), value));
+        match self {
+            Self::AllocationFraction => return strategy.with_allocation_fraction(decimal()?),
+            Self::RebalanceDays => {
+                if value < 0.0 || value.fract() != 0.0 || value > u32::MAX as f64 {
+                    return Err(anyhow::anyhow!("rebalance_days must be a whole number of days, got {}", value));
+                }
+                config.rebalance_days = (value > 0.0).then_some(value as u32);
+            }
+            Self::CommissionBps => config.commission_bps = decimal()?,
+            Self::SlippageBps => config.slippage = Slippage::Bps(decimal()?),
+        }
+        Ok(strategy)
+    }
+}
+
+/// A parameter and the values a backtest sweep runs it at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BacktestAxis {
+    pub parameter: BacktestParameter,
+    pub values: Vec<f64>,
+}
+
+impl BacktestAxis {
+    pub fn new(parameter: BacktestParameter, values: Vec<f64>) -> Self {
+        Self { parameter, values }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.values.is_empty() || self.values.iter().any(|value| !value.is_finite()) {
+            return Err(anyhow::anyhow!(
+                "{} needs a non-empty list of finite values, got {:?}",
+                self.parameter.name(),
+                self.values
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Summary metric of a backtest, as shown in a sweep's matrix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BacktestMetric {
+    TotalReturn,
+    AnnualizedReturn,
+    Sharpe,
+    Sortino,
+    MaxDrawdown,
+    WinRate,
+    /// Commission and slippage paid
+    Costs,
+    Turnover,
+}
+
+impl BacktestMetric {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "total_return" | "return" => Ok(Self::TotalReturn),
+            "annualized_return" | "annualized" => Ok(Self::AnnualizedReturn),
+            "sharpe" | "sharpe_ratio" => Ok(Self::Sharpe),
+            "sortino" | "sortino_ratio" => Ok(Self::Sortino),
+            "max_drawdown" | "drawdown" => Ok(Self::MaxDrawdown),
+            "win_rate" => Ok(Self::WinRate),
+            "costs" => Ok(Self::Costs),
+            "turnover" => Ok(Self::Turnover),
+            _ => Err(anyhow::anyhow!("Unknown backtest metric: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TotalReturn => "total_return",
+            Self::AnnualizedReturn => "annualized_return",
+            Self::Sharpe => "sharpe",
+            Self::Sortino => "sortino",
+            Self::MaxDrawdown => "max_drawdown",
+            Self::WinRate => "win_rate",
+            Self::Costs => "costs",
+            Self::Turnover => "turnover",
+        }
+    }
+
+    /// The metric's value in `cell`; `None` when undefined, as the Sortino
+    /// ratio is without downside
+    pub fn value(&self, cell: &BacktestSweepCell) -> Option<f64> {
+        match self {
+            Self::TotalReturn => Some(cell.total_return_pct),
+            Self::AnnualizedReturn => Some(cell.annualized_return_pct),
+            Self::Sharpe => Some(cell.sharpe_ratio),
+            Self::Sortino => cell.sortino_ratio,
+            Self::MaxDrawdown => Some(cell.max_drawdown_pct),
+            Self::WinRate => Some(cell.win_rate),
+            Self::Costs => cell.total_costs.to_f64(),
+            Self::Turnover => cell.turnover.to_f64(),
+        }
+    }
+}
+
+/// A grid of backtests over one or two parameters, run with
+/// [`BacktestEngine::sweep`] on the engine's data.
+///
+/// Every cell starts from the engine's strategy and config, or from `base`
+/// when one is set, with the axes' parameters applied on top.
+#[derive(Debug, Clone)]
+pub struct BacktestSweep {
+    x: BacktestAxis,
+    y: Option<BacktestAxis>,
+    base: Option<StrategyConfig>,
+}
+
+impl BacktestSweep {
+    pub fn new(x: BacktestAxis) -> Self {
+        Self { x, y: None, base: None }
+    }
+
+    /// Also vary a second parameter, making the results a matrix
+    pub fn with_y_axis(mut self, y: BacktestAxis) -> Self {
+        self.y = Some(y);
+        self
+    }
+
+    /// Start every cell from this strategy (by name) and rebalance frequency
+    pub fn with_base(mut self, base: StrategyConfig) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.x.validate()?;
+        if let Some(y) = &self.y {
+            y.validate()?;
+            if y.parameter == self.x.parameter {
+                return Err(anyhow::anyhow!("sweep axes must vary different parameters, both vary {}", y.parameter.name()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Strategy and config of the cell at `x` and `y`
+    fn cell(&self, strategy: &Strategy, config: &BacktestConfig, x: f64, y: Option<f64>) -> Result<(Strategy, BacktestConfig)> {
+        let mut config = config.clone();
+        let mut strategy = strategy.clone();
+        if let Some(base) = &self.base {
+            strategy = Strategy::from_name(&base.name)?;
+            config.rebalance_days = (base.rebalance_frequency_days > 0).then_some(base.rebalance_frequency_days);
+        }
+        strategy = self.x.parameter.apply(x, strategy, &mut config)?;
+        if let (Some(axis), Some(y)) = (&self.y, y) {
+            strategy = axis.parameter.apply(y, strategy, &mut config)?;
+        }
+        config.validate()?;
+        Ok((strategy, config))
+    }
+}
+
+/// A `BacktestConfig` charged as the simulator's fee model, against the
+/// dollar volume of the day being stepped
+#[derive(Debug)]
+struct BacktestCosts {
+    config: BacktestConfig,
+    dollar_volumes: RwLock<HashMap<String, Decimal>>,
+}
+
+impl BacktestCosts {
+    fn set_dollar_volumes(&self, volumes: HashMap<String, Decimal>) {
+        *self.dollar_volumes.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = volumes;
+    }
+}
+
+impl FeeModel for BacktestCosts {
+    fn fee(&self, decision: &RoutingDecision, _portfolio: &Portfolio) -> Decimal {
+        let volumes = self.dollar_volumes.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let dollar_volume = volumes.get(decision.traded_symbol()).copied();
+        self.config.commission(decision.amount) + self.config.slippage(decision.amount, dollar_volume)
+    }
+}
 
 /// Backtesting engine for historical strategy evaluation
 pub struct BacktestEngine {
@@ -12,50 +394,497 @@ pub struct BacktestEngine {
     end_date: OffsetDateTime,
     strategy: Strategy,
     market_data: Vec<MarketData>,
+    yields: Vec<YieldObservation>,
+    initial_portfolio: Option<Portfolio>,
+    config: BacktestConfig,
+    simulator_config: SimulatorConfig,
+    benchmark: Option<Benchmark>,
 }
 
 impl BacktestEngine {
-    /// Create a new backtest engine
+    /// Create a backtest engine over `start_date_str` to `end_date_str`
+    /// (`YYYY-MM-DD`, midnight UTC), one step per bar of the configured frequency
     pub fn new(
         start_date_str: &str,
         end_date_str: &str,
         strategy: Strategy,
     ) -> Result<Self> {
-        // Parse dates (simplified - in real implementation use proper parsing)
-        let start_date = OffsetDateTime::now_utc();
-        let end_date = start_date + time::Duration::days(365);
+        let start_date = parse_date(start_date_str).context("invalid start date")?;
+        let end_date = parse_date(end_date_str).context("invalid end date")?;
+        if start_date >= end_date {
+            return Err(anyhow::anyhow!(
+                "start date {} must be before end date {}",
+                start_date_str,
+                end_date_str
+            ));
+        }
         
-        // In a real implementation, we'd load historical market data
-        let market_data = Self::generate_mock_market_data(&start_date, &end_date)?;
+        // Seeded synthetic data until a real source is loaded with `with_data_source`
+        let market_data = MockDataSource::new().generate(&[], start_date, end_date)?;
         
         Ok(Self {
             start_date,
             end_date,
             strategy,
             market_data,
+            yields: vec![],
+            initial_portfolio: None,
+            config: BacktestConfig::default(),
+            simulator_config: SimulatorConfig::default(),
+            benchmark: None,
+        })
+    }
+
+    /// Charge `config`'s commission and slippage and prepare the data by its
+    /// rules instead of the defaults
+    pub fn with_config(mut self, config: BacktestConfig) -> Result<Self> {
+        config.validate()?;
+        self.config = config;
+        Ok(self)
+    }
+
+    pub fn config(&self) -> &BacktestConfig {
+        &self.config
+    }
+
+    /// Run over `market_data` instead of the generated mock data.
+    ///
+    /// Bars are resampled to the configured bar frequency, so hourly data can
+    /// run as daily or weekly bars; a symbol with no bar in some period keeps
+    /// its last close. The data must pass `validate_market_data` for the
+    /// backtest's range.
+    pub fn with_market_data(mut self, market_data: Vec<MarketData>) -> Result<Self> {
+        validate_market_data(&market_data, self.start_date, self.end_date)?;
+        self.market_data = market_data;
+        Ok(self)
+    }
+
+    /// Accrue income on held positions at the APY `yields` gives for each bar:
+    /// a symbol's last observation at or before the bar, else zero.
+    ///
+    /// With a yield series, positions earn only what it gives rather than the
+    /// yield the strategy quoted when opening them. Observations must be on or
+    /// before the end date and in time order per symbol.
+    pub fn with_yields(mut self, yields: Vec<YieldObservation>) -> Result<Self> {
+        validate_yields(&yields, self.end_date)?;
+        self.yields = yields;
+        Ok(self)
+    }
+
+    /// Run over `symbols` (all of the source's when empty) fetched from `source`
+    /// for the backtest's range, with the source's yield series if it has one
+    pub async fn with_data_source<S>(self, source: &S, symbols: &[&str]) -> Result<Self>
+    where
+        S: HistoricalDataSource + ?Sized,
+    {
+        let market_data = source
+            .fetch(symbols, self.start_date, self.end_date)
+            .await
+            .context("failed to fetch backtest market data")?;
+        let yields = source
+            .fetch_yields(symbols, self.start_date, self.end_date)
+            .await
+            .context("failed to fetch backtest yields")?;
+        let engine = self.with_market_data(market_data)?;
+        if yields.is_empty() {
+            return Ok(engine);
+        }
+        engine.with_yields(yields)
+    }
+
+    /// Run over `provider`'s daily history of `symbols` (the mock universe's
+    /// when empty), taken as ending today, with each symbol's current yield.
+    /// Sync providers plug in through [`SyncAdapter`](crate::market::SyncAdapter).
+    pub async fn with_provider<P>(self, provider: &P, symbols: &[&str]) -> Result<Self>
+    where
+        P: AsyncMarketDataProvider + ?Sized,
+    {
+        self.with_data_source(&ProviderHistory::new(provider), symbols).await
+    }
+
+    /// Market data the backtest runs over
+    pub fn market_data(&self) -> &[MarketData] {
+        &self.market_data
+    }
+
+    pub fn start_date(&self) -> OffsetDateTime {
+        self.start_date
+    }
+
+    pub fn end_date(&self) -> OffsetDateTime {
+        self.end_date
+    }
+
+    pub fn strategy(&self) -> &Strategy {
+        &self.strategy
+    }
+
+    /// This backtest's data and settings over `start_date` to `end_date` with `strategy`
+    pub(crate) fn window(&self, start_date: OffsetDateTime, end_date: OffsetDateTime, strategy: Strategy) -> Self {
+        Self {
+            start_date,
+            end_date,
+            strategy,
+            market_data: self.market_data.clone(),
+            yields: self.yields.clone(),
+            initial_portfolio: self.initial_portfolio.clone(),
+            config: self.config.clone(),
+            simulator_config: self.simulator_config.clone(),
+            benchmark: self.benchmark.clone(),
+        }
+    }
+
+    /// Run the strategy over the first `1 - oos_fraction` of the range and,
+    /// separately, over the rest, split on a whole day.
+    ///
+    /// Each segment prepares its data from bars up to its own end date, so
+    /// nothing after the split reaches the in-sample run.
+    pub async fn run_out_of_sample(&self, oos_fraction: f64) -> Result<OutOfSampleResults> {
+        if !(oos_fraction > 0.0 && oos_fraction < 1.0) {
+            return Err(anyhow::anyhow!("out-of-sample fraction must be between 0 and 1, got {}", oos_fraction));
+        }
+        let days = (self.end_date - self.start_date).whole_days();
+        let in_sample_days = (days as f64 * (1.0 - oos_fraction)).round() as i64;
+        if in_sample_days < 1 || in_sample_days >= days {
+            return Err(anyhow::anyhow!(
+                "a {} out-of-sample split of a {}-day range leaves a segment with no days",
+                oos_fraction,
+                days
+            ));
+        }
+        let split_date = self.start_date + Duration::days(in_sample_days);
+        
+        let in_sample = self.window(self.start_date, split_date, self.strategy.clone()).run().await?;
+        let out_of_sample = self.window(split_date, self.end_date, self.strategy.clone()).run().await?;
+        let degradation_ratio = (in_sample.sharpe_ratio > 0.0)
+            .then(|| out_of_sample.sharpe_ratio / in_sample.sharpe_ratio);
+        Ok(OutOfSampleResults {
+            split_date,
+            in_sample,
+            out_of_sample,
+            degradation_ratio,
+        })
+    }
+
+    /// Days after the start date, up to the end date, with at least one bar
+    pub(crate) fn data_days(&self) -> usize {
+        let (start, end) = (self.start_date.date(), self.end_date.date());
+        let mut dates: Vec<Date> = self
+            .market_data
+            .iter()
+            .map(|bar| bar.timestamp.date())
+            .filter(|date| *date > start && *date <= end)
+            .collect();
+        dates.sort();
+        dates.dedup();
+        dates.len()
+    }
+
+    /// Block bootstrap over this backtest's market data, for
+    /// `MonteCarloEngine::with_bootstrap`
+    pub fn bootstrap(&self, block_length: usize) -> Result<BlockBootstrap> {
+        BlockBootstrap::from_market_data(&self.market_data, block_length)
+    }
+
+    /// Compare the strategy against holding `benchmark` over the same data.
+    ///
+    /// The benchmark is bought with the initial capital at each symbol's close
+    /// on or before the start date (else its first close in the data) and held
+    /// without rebalancing.
+    pub fn with_benchmark(mut self, benchmark: Benchmark) -> Self {
+        self.benchmark = Some(benchmark);
+        self
+    }
+
+    /// Start from `portfolio`, such as a production book exported as JSON,
+    /// instead of all cash.
+    ///
+    /// Its positions are valued at the market data's prices on the start date
+    /// (else the first bar stepped on), not their stored prices. Positions in
+    /// symbols the data has no price for fail the backtest or are frozen at
+    /// their stored price, per `BacktestConfig::unpriced_positions`.
+    pub fn with_initial_portfolio(mut self, portfolio: Portfolio) -> Self {
+        self.initial_portfolio = Some(portfolio);
+        self
+    }
+
+    /// Value the book at the end of the backtest using the given method
+    pub fn with_terminal_valuation(mut self, valuation: TerminalValuation) -> Self {
+        self.simulator_config.terminal_valuation = valuation;
+        self
+    }
+
+    /// Run the strategy on every `window_days`-day window of the range, each
+    /// starting `stride_days` after the last, and summarize the spread of outcomes.
+    ///
+    /// The market data is aligned once for the whole range and each window
+    /// steps on its share of those bars. Windows without a bar to step on are
+    /// skipped.
+    pub async fn rolling(&self, window_days: usize, stride_days: usize) -> Result<RollingBacktestResults> {
+        if window_days == 0 || stride_days == 0 {
+            return Err(anyhow::anyhow!("rolling window and stride must each be at least one day"));
+        }
+        let days = (self.end_date - self.start_date).whole_days();
+        if window_days as i64 > days {
+            return Err(anyhow::anyhow!(
+                "a {}-day rolling window doesn't fit in the {}-day backtest range",
+                window_days,
+                days
+            ));
+        }
+        info!("Running {}-day rolling backtests from {} to {}, every {} days",
+              window_days, self.start_date.date(), self.end_date.date(), stride_days);
+        
+        let bars = self.bars();
+        let aligned = self.align(&bars)?;
+        let dollar_volumes = dollar_volumes(&bars);
+        let mut windows = vec![];
+        let mut skipped_windows = 0;
+        let mut start = self.start_date;
+        while start + Duration::days(window_days as i64) <= self.end_date {
+            let end = start + Duration::days(window_days as i64);
+            match aligned.window(start, end, self.config.bar_frequency) {
+                Some(prices) => {
+                    let results = self.simulate(&self.strategy, &self.config, start, end, prices, &dollar_volumes)?;
+                    windows.push(RollingWindow {
+                        start_date: start,
+                        end_date: end,
+                        total_return_pct: results.total_return_pct,
+                        annualized_return_pct: results.annualized_return_pct,
+                        sharpe_ratio: results.sharpe_ratio,
+                        max_drawdown_pct: results.max_drawdown_pct,
+                    });
+                }
+                None => {
+                    warn!("Skipping rolling window {} to {}: no bar has a price for every symbol",
+                          start.date(), end.date());
+                    skipped_windows += 1;
+                }
+            }
+            start += Duration::days(stride_days as i64);
+        }
+        
+        let worst_window = windows
+            .iter()
+            .min_by(|a, b| a.total_return_pct.total_cmp(&b.total_return_pct))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no rolling window has a bar to step on"))?;
+        let range = |metric: fn(&RollingWindow) -> f64| MetricRange::of(windows.iter().map(metric));
+        Ok(RollingBacktestResults {
+            strategy: self.strategy.name().to_string(),
+            window_days,
+            stride_days,
+            total_return: range(|window| window.total_return_pct),
+            sharpe_ratio: range(|window| window.sharpe_ratio),
+            max_drawdown: range(|window| window.max_drawdown_pct),
+            worst_window,
+            skipped_windows,
+            data_quality: aligned.report,
+            windows,
+        })
+    }
+
+    /// Rerun the backtest at each cost level, in basis points of notional
+    /// charged as commission with no fixed fee or slippage, and find where the
+    /// net return crosses zero.
+    ///
+    /// The market data is aligned once and every level keeps the configured
+    /// rebalance schedule, so levels differ only in what trading costs.
+    pub async fn cost_sensitivity(&self, levels: &[f64]) -> Result<CostSensitivity> {
+        if levels.is_empty() || levels.iter().any(|level| !level.is_finite() || *level < 0.0) {
+            return Err(anyhow::anyhow!("cost levels must be a non-empty list of non-negative basis points: {:?}", levels));
+        }
+        let mut levels = levels.to_vec();
+        levels.sort_by(f64::total_cmp);
+        levels.dedup();
+        
+        let bars = self.bars();
+        let aligned = self.align(&bars)?;
+        let dollar_volumes = dollar_volumes(&bars);
+        let mut points = vec![];
+        for cost_bps in levels {
+            let config = BacktestConfig {
+                commission_bps: Decimal::try_from(cost_bps).context("cost level out of range")?,
+                commission_fixed: Decimal::ZERO,
+                slippage: Slippage::Bps(Decimal::ZERO),
+                ..self.config.clone()
+            };
+            let results =
+                self.simulate(&self.strategy, &config, self.start_date, self.end_date, aligned.clone(), &dollar_volumes)?;
+            points.push(CostSensitivityPoint {
+                cost_bps,
+                total_return_pct: results.total_return_pct,
+                annualized_return_pct: results.annualized_return_pct,
+                sharpe_ratio: results.sharpe_ratio,
+                total_costs: results.total_commission + results.total_slippage,
+                turnover: results.turnover,
+            });
+        }
+        
+        // Interpolate between the first pair of levels whose returns straddle zero
+        let break_even_bps = points.windows(2).find_map(|pair| {
+            let (low, high) = (&pair[0], &pair[1]);
+            (low.total_return_pct >= 0.0 && high.total_return_pct < 0.0).then(|| {
+                let share = low.total_return_pct / (low.total_return_pct - high.total_return_pct);
+                low.cost_bps + share * (high.cost_bps - low.cost_bps)
+            })
+        });
+        Ok(CostSensitivity { points, break_even_bps })
+    }
+
+    /// Backtest every combination of `sweep`'s parameter values, in parallel,
+    /// on the data aligned once up front
+    pub async fn sweep(&self, sweep: &BacktestSweep) -> Result<BacktestSweepResults> {
+        sweep.validate()?;
+        let y_values: Vec<Option<f64>> = match &sweep.y {
+            Some(axis) => axis.values.iter().copied().map(Some).collect(),
+            None => vec![None],
+        };
+        let grid: Vec<(f64, Option<f64>)> = y_values
+            .iter()
+            .flat_map(|y| sweep.x.values.iter().map(move |x| (*x, *y)))
+            .collect();
+        // Catch bad values before spending time on any backtest
+        let cells = grid
+            .iter()
+            .map(|(x, y)| sweep.cell(&self.strategy, &self.config, *x, *y))
+            .collect::<Result<Vec<_>>>()?;
+        info!("Sweeping {} backtests from {} to {}", cells.len(), self.start_date.date(), self.end_date.date());
+        
+        let bars = self.bars();
+        let aligned = self.align(&bars)?;
+        let dollar_volumes = dollar_volumes(&bars);
+        let cells = grid
+            .par_iter()
+            .zip(cells.par_iter())
+            .map(|((x, y), (strategy, config))| {
+                let results =
+                    self.simulate(strategy, config, self.start_date, self.end_date, aligned.clone(), &dollar_volumes)?;
+                Ok(BacktestSweepCell {
+                    x: *x,
+                    y: *y,
+                    total_return_pct: results.total_return_pct,
+                    annualized_return_pct: results.annualized_return_pct,
+                    sharpe_ratio: results.sharpe_ratio,
+                    sortino_ratio: results.sortino_ratio,
+                    max_drawdown_pct: results.max_drawdown_pct,
+                    win_rate: results.win_rate,
+                    total_costs: results.total_commission + results.total_slippage,
+                    turnover: results.turnover,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(BacktestSweepResults {
+            x_parameter: sweep.x.parameter,
+            x_values: sweep.x.values.clone(),
+            y_parameter: sweep.y.as_ref().map(|axis| axis.parameter),
+            y_values: sweep.y.as_ref().map(|axis| axis.values.clone()).unwrap_or_default(),
+            cells,
         })
     }
 
     /// Run backtest
     pub async fn run(&mut self) -> Result<BacktestResults> {
         info!("Running backtest from {} to {}", self.start_date, self.end_date);
+        let bars = self.bars();
+        let aligned = self.align(&bars)?;
+        let dollar_volumes = dollar_volumes(&bars);
+        self.simulate(&self.strategy, &self.config, self.start_date, self.end_date, aligned, &dollar_volumes)
+    }
+
+    /// Step `strategy` over `aligned`'s bars from `start_date` to `end_date`,
+    /// charging costs per `config`
+    fn simulate(
+        &self,
+        strategy: &Strategy,
+        config: &BacktestConfig,
+        start_date: OffsetDateTime,
+        end_date: OffsetDateTime,
+        aligned: AlignedPrices,
+        dollar_volumes: &BTreeMap<OffsetDateTime, HashMap<String, Decimal>>,
+    ) -> Result<BacktestResults> {
+        // One step per bar of data, with the simulated clock on the bars' start times,
+        // paying the backtest's costs whatever the strategy quotes
+        let costs = Arc::new(BacktestCosts {
+            config: config.clone(),
+            dollar_volumes: RwLock::new(HashMap::new()),
+        });
+        let simulator_config = SimulatorConfig {
+            start_time: Some(start_date),
+            time_step: config.bar_frequency.duration(),
+            fee_model: Some(costs.clone()),
+            rebalance_interval: config.rebalance_days.map(|days| Duration::days(days.into())),
+            minimum_acceptable_return: config.minimum_acceptable_return,
+            yield_income: config.yield_income,
+            record_transactions: config.record_events,
+            ..self.simulator_config.clone()
+        };
+        let seeded = self
+            .initial_portfolio
+            .as_ref()
+            .map(|portfolio| SeededBook::open(portfolio, config.unpriced_positions, start_date, &aligned))
+            .transpose()?;
+        let mut simulator = match &seeded {
+            Some(seeded) => {
+                Simulator::from_portfolio_with_config(seeded.portfolio.clone(), strategy.clone(), simulator_config)
+            }
+            None => Simulator::with_config(1_000_000.0, strategy.clone(), simulator_config),
+        };
+        let initial_value = simulator.portfolio().total_value;
         
-        let initial_value = Decimal::from(1_000_000);
-        let mut simulator = Simulator::new(
-            initial_value.to_f64().unwrap_or(1_000_000.0),
-            self.strategy.clone(),
-        );
-        
-        // Simulate over historical period
-        let days = (self.end_date - self.start_date).whole_days() as usize;
+        // Step through the bars every symbol has a price in, at the aligned closes
+        let days = (end_date - start_date).whole_days() as usize;
+        let benchmark = self
+            .benchmark
+            .as_ref()
+            .map(|benchmark| BenchmarkBook::open(benchmark, initial_value, &aligned.opening, &aligned.days))
+            .transpose()?;
+        let mut comparison = benchmark.map(|book| BenchmarkComparison::new(book, initial_value));
+        // Everything the run steps on; each step's view cuts it off at the step's bar
+        let history = PriceHistory::from_closes(std::iter::once((&start_date, &aligned.opening)).chain(&aligned.days));
+        let yields = (!self.yields.is_empty()).then(|| YieldSchedule::new(&self.yields));
         
-        for day in 0..days.min(100) {
-            // Update market data for this day
-            // In real implementation, we'd use actual historical prices
-            simulator.step()?;
+        for (step, (time, prices)) in aligned.days.iter().enumerate() {
+            costs.set_dollar_volumes(dollar_volumes.get(time).cloned().unwrap_or_default());
+            let outcome = simulator.step_with_history_at(*time, prices, &history)?;
+            // Held positions accrue the rate prevailing at this bar over the next one
+            if let Some(yields) = &yields {
+                let held: Vec<String> = simulator.portfolio().positions.keys().cloned().collect();
+                for symbol in held {
+                    simulator.set_yield_rate(&symbol, yields.rate(&symbol, *time));
+                }
+            }
+            if config.progress_every_days.is_some_and(|every| (step + 1) % every == 0) {
+                info!(
+                    "Backtest at {}: bar {} of {}, value {:.2}",
+                    config.bar_frequency.label(*time),
+                    step + 1,
+                    aligned.days.len(),
+                    outcome.value_after
+                );
+            }
+            if let Some(comparison) = &mut comparison {
+                comparison.record(outcome.value_after, prices);
+            }
         }
         
-        let results = simulator.finalize();
+        // The simulator annualizes as if every bar of the range were stepped; use
+        // the rate the kept bars were actually stepped at
+        let periods_per_year = config.bar_frequency.periods_per_year() * aligned.days.len() as f64
+            / aligned.report.calendar_days.max(1) as f64;
+        let annualizer = (periods_per_year / simulator.config().periods_per_year()).sqrt();
+        let mut results = simulator.finalize();
+        let events = results.transactions.take();
+        let income_return_pct = if results.initial_value > Decimal::ZERO {
+            (results.income_earned / results.initial_value * Decimal::from(100)).to_f64().unwrap_or(0.0)
+        } else {
+            0.0
+        };
+        let equity_curve = equity_curve(&results.portfolio_history);
+        let (start, end) = (start_date.date(), end_date.date());
+        let monthly_returns = period_returns(&equity_curve, start, end, true);
+        let annual_returns = period_returns(&equity_curve, start, end, false);
+        let drawdowns = drawdown_episodes(&equity_curve, DRAWDOWN_TABLE_LEN);
         
         // Calculate additional metrics
         let annualized_return = self.calculate_annualized_return(
@@ -64,29 +893,202 @@ impl BacktestEngine {
             days,
         );
         
-        let volatility = results.volatility_pct;
-        let sharpe_ratio = results.sharpe_ratio;
+        let volatility = results.volatility_pct * annualizer;
+        let sharpe_ratio = results.sharpe_ratio * annualizer;
         let max_drawdown = results.max_drawdown_pct;
+        let step_returns: Vec<Decimal> = equity_curve
+            .windows(2)
+            .map(|pair| {
+                if pair[0].value > Decimal::ZERO { (pair[1].value - pair[0].value) / pair[0].value } else { Decimal::ZERO }
+            })
+            .collect();
+        let sortino_ratio = metrics::sortino_ratio(&step_returns, config.minimum_acceptable_return, periods_per_year);
+        let calmar_ratio = metrics::calmar_ratio(annualized_return, max_drawdown);
+        
+        let (mut total_commission, mut total_slippage, mut turnover) = (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+        for executed in results.decisions.iter().filter(|executed| executed.is_executed()) {
+            let commission = config.commission(executed.decision.amount);
+            total_commission += commission;
+            total_slippage += executed.execution_cost - commission;
+            turnover += executed.decision.amount;
+        }
+        
+        let trades = pair_fills(&results.fills);
+        let (win_rate, profit_factor) = trade_stats(&trades);
         
-        // Mock trade data
-        let trades = vec![];
-        let win_rate = 0.0;
-        let profit_factor = 0.0;
+        let benchmark_final = comparison.as_ref().map(|comparison| comparison.benchmark_value);
+        let benchmark_return_pct = benchmark_final
+            .map(|value| crate::utils::percentage_change(initial_value, value));
+        let alpha_pct = benchmark_final.map(|value| {
+            annualized_return - self.calculate_annualized_return(&initial_value, &value, days)
+        });
+        let beta = comparison.as_ref().and_then(BenchmarkComparison::beta);
+        let tracking_error = comparison
+            .as_ref()
+            .map(|comparison| comparison.active.tracking_error_pct(periods_per_year));
+        let information_ratio = comparison
+            .as_ref()
+            .filter(|_| tracking_error.is_some_and(|error| error > 0.0))
+            .map(|comparison| comparison.active.information_ratio(periods_per_year));
         
-        Ok(BacktestResults {
-            start_date: self.start_date,
-            end_date: self.end_date,
+        let initial_portfolio = seeded.map(|seeded| {
+            let closing = aligned.days.values().next_back();
+            seeded.contribution(closing, results.final_value - results.initial_value)
+        });
+        
+        let results = BacktestResults {
+            start_date,
+            end_date,
             initial_value: results.initial_value,
             final_value: results.final_value,
+            marked_final_value: results.marked_final_value,
+            terminal_liquidation_cost: results.terminal_liquidation_cost,
             total_return_pct: results.total_return_pct,
+            price_return_pct: results.total_return_pct - income_return_pct,
+            income_return_pct,
             annualized_return_pct: annualized_return,
             volatility_pct: volatility,
             sharpe_ratio,
+            sortino_ratio,
+            calmar_ratio,
             max_drawdown_pct: max_drawdown,
             win_rate,
             profit_factor,
             trades,
-        })
+            benchmark_return_pct,
+            alpha_pct,
+            beta,
+            tracking_error,
+            information_ratio,
+            total_commission,
+            total_slippage,
+            turnover,
+            data_quality: aligned.report,
+            equity_curve,
+            monthly_returns,
+            annual_returns,
+            drawdowns,
+            initial_portfolio,
+            events,
+        };
+        Ok(results)
+    }
+
+    /// The market data resampled to the configured bar frequency, with bars
+    /// laid end to end from the start date
+    fn bars(&self) -> Vec<MarketData> {
+        resample(&self.market_data, self.config.bar_frequency, self.start_date)
+    }
+
+    /// Align every symbol's closes in `bars` onto the backtest's bars.
+    ///
+    /// A symbol's last close is carried over up to `max_fill_days` days without
+    /// a bar; past that, or before its first bar, it has no price, and the bar
+    /// is dropped or fails the backtest per the gap policy. Closes that move
+    /// more than the outlier threshold from the previous one are counted and,
+    /// if configured, clipped to it.
+    fn align(&self, bars: &[MarketData]) -> Result<AlignedPrices> {
+        let frequency = self.config.bar_frequency;
+        let (start, end) = (self.start_date, self.end_date);
+        let calendar_days = bar_count(start, end, frequency);
+        let max_fill = Duration::days(self.config.max_fill_days as i64);
+        let closes = closes(bars);
+        let symbols: BTreeSet<&String> = closes.range(..=end).flat_map(|(_, bar)| bar.keys()).collect();
+        
+        let mut priced: BTreeMap<OffsetDateTime, HashMap<String, Decimal>> = BTreeMap::new();
+        let mut report = DataQualityReport { calendar_days, ..Default::default() };
+        for symbol in symbols {
+            let mut coverage = SymbolCoverage {
+                symbol: symbol.clone(),
+                bar_days: 0,
+                coverage_pct: 0.0,
+                gaps_filled: 0,
+                missing_days: 0,
+                outliers: 0,
+            };
+            let mut last: Option<(OffsetDateTime, Decimal)> = None;
+            let mut time = closes.keys().next().copied().unwrap_or(start).min(start);
+            while time <= end {
+                let in_range = time > start;
+                let price = match closes.get(&time).and_then(|bar| bar.get(symbol)) {
+                    Some(close) => {
+                        let mut close = *close;
+                        if let (Some(threshold), Some((_, previous))) = (self.config.outlier_threshold, last) {
+                            if previous > Decimal::ZERO && ((close - previous) / previous).abs() > threshold {
+                                coverage.outliers += usize::from(in_range);
+                                if self.config.clip_outliers {
+                                    let bound = previous * threshold;
+                                    close = close.clamp(previous - bound, previous + bound);
+                                }
+                            }
+                        }
+                        coverage.bar_days += usize::from(in_range);
+                        last = Some((time, close));
+                        Some(close)
+                    }
+                    None => match last {
+                        Some((last_time, close)) if time - last_time <= max_fill => {
+                            coverage.gaps_filled += usize::from(in_range);
+                            Some(close)
+                        }
+                        _ => None,
+                    },
+                };
+                match price {
+                    Some(price) if time >= start => {
+                        priced.entry(time).or_default().insert(symbol.clone(), price);
+                    }
+                    Some(_) => {}
+                    None if in_range => {
+                        if self.config.gap_policy == GapPolicy::Error {
+                            return Err(match last {
+                                Some((last_time, _)) => anyhow::anyhow!(
+                                    "{} has no price on {}: its last bar, on {}, is more than {} days earlier",
+                                    symbol,
+                                    frequency.label(time),
+                                    frequency.label(last_time),
+                                    self.config.max_fill_days
+                                ),
+                                None => anyhow::anyhow!(
+                                    "{} has no price on {}: its first bar is later",
+                                    symbol,
+                                    frequency.label(time)
+                                ),
+                            });
+                        }
+                        coverage.missing_days += 1;
+                    }
+                    None => {}
+                }
+                time = time
+                    .checked_add(frequency.duration())
+                    .context("backtest runs past the last representable date")?;
+            }
+            if calendar_days > 0 {
+                coverage.coverage_pct = coverage.bar_days as f64 / calendar_days as f64 * 100.0;
+            }
+            report.symbols.push(coverage);
+        }
+        
+        let opening = priced.remove(&start).unwrap_or_default();
+        let symbol_count = report.symbols.len();
+        let days: BTreeMap<OffsetDateTime, HashMap<String, Decimal>> = priced
+            .into_iter()
+            .filter(|(_, prices)| prices.len() == symbol_count)
+            .collect();
+        report.dropped_days = calendar_days - days.len();
+        if days.is_empty() {
+            return Err(anyhow::anyhow!(
+                "no {} bar from {} to {} has a price for every symbol in the market data",
+                frequency.name(),
+                frequency.label(start + frequency.duration()),
+                frequency.label(end)
+            ));
+        }
+        if report.dropped_days > 0 {
+            warn!("Dropped {} of {} backtest bars missing a price for some symbol", report.dropped_days, calendar_days);
+        }
+        Ok(AlignedPrices { opening, days, report })
     }
 
     fn calculate_annualized_return(
@@ -103,45 +1105,1404 @@ impl BacktestEngine {
         let total_return_f64 = total_return.to_f64().unwrap_or(0.0);
         
         // Annualize
-        let years = days as f64 / 365.0;
+        let years = days as f64 / CALENDAR_DAYS_PER_YEAR;
         if years > 0.0 {
             ((1.0 + total_return_f64).powf(1.0 / years) - 1.0) * 100.0
         } else {
             0.0
         }
     }
+}
 
-    fn generate_mock_market_data(
-        start: &OffsetDateTime,
-        end: &OffsetDateTime,
-    ) -> Result<Vec<MarketData>> {
-        let mut data = vec![];
-        let mut current = *start;
-        let mut price = Decimal::from(100);
-        
-        while current <= *end {
-            // Simple random walk for mock data
-            use rand::Rng;
-            let mut rng = rand::thread_rng();
-            let change_val = rng.gen_range(-0.02..0.02);
-            let change = Decimal::try_from(change_val).unwrap_or(Decimal::ZERO);
-            
-            price = price * (Decimal::ONE + change);
-            
-            data.push(MarketData {
-                timestamp: current,
-                symbol: "MOCK".to_string(),
-                price,
-                volume: Decimal::from(1_000_000),
-                high: price * Decimal::from(101) / Decimal::from(100),
-                low: price * Decimal::from(99) / Decimal::from(100),
-                open: price,
-                close: price,
-            });
-            
-            current = current + time::Duration::days(1);
+/// A backtest's market data aligned onto its bars
+#[derive(Clone)]
+struct AlignedPrices {
+    /// Prices at the start date, for the symbols that have one
+    opening: HashMap<String, Decimal>,
+    /// Every symbol's price in each bar after the start date the backtest steps on,
+    /// by the bar's start time
+    days: BTreeMap<OffsetDateTime, HashMap<String, Decimal>>,
+    report: DataQualityReport,
+}
+
+impl AlignedPrices {
+    /// The bars after `start` up to `end`, opening at the last aligned prices
+    /// at or before `start`; `None` when none of the bars is kept.
+    ///
+    /// The window's data quality report counts its own bars but leaves the
+    /// per-symbol coverage to the full range's report.
+    fn window(&self, start: OffsetDateTime, end: OffsetDateTime, frequency: BarFrequency) -> Option<Self> {
+        let days: BTreeMap<OffsetDateTime, HashMap<String, Decimal>> = self
+            .days
+            .range((Bound::Excluded(start), Bound::Included(end)))
+            .map(|(time, prices)| (*time, prices.clone()))
+            .collect();
+        if days.is_empty() {
+            return None;
+        }
+        let opening = self
+            .days
+            .range(..=start)
+            .next_back()
+            .map_or_else(|| self.opening.clone(), |(_, prices)| prices.clone());
+        let calendar_days = bar_count(start, end, frequency);
+        let report = DataQualityReport {
+            calendar_days,
+            dropped_days: calendar_days - days.len(),
+            symbols: vec![],
+        };
+        Some(Self { opening, days, report })
+    }
+}
+
+/// Closing price of each symbol by bar start; a later bar at the same time wins
+fn closes(bars: &[MarketData]) -> BTreeMap<OffsetDateTime, HashMap<String, Decimal>> {
+    let mut closes: BTreeMap<OffsetDateTime, HashMap<String, Decimal>> = BTreeMap::new();
+    for bar in bars {
+        closes
+            .entry(bar.timestamp)
+            .or_default()
+            .insert(bar.symbol.clone(), bar.close);
+    }
+    closes
+}
+
+/// Dollar volume (volume times close) of each symbol by bar start
+fn dollar_volumes(bars: &[MarketData]) -> BTreeMap<OffsetDateTime, HashMap<String, Decimal>> {
+    let mut volumes: BTreeMap<OffsetDateTime, HashMap<String, Decimal>> = BTreeMap::new();
+    for bar in bars {
+        *volumes
+            .entry(bar.timestamp)
+            .or_default()
+            .entry(bar.symbol.clone())
+            .or_default() += bar.volume * bar.close;
+    }
+    volumes
+}
+
+/// Whole bars after `start` up to `end`
+fn bar_count(start: OffsetDateTime, end: OffsetDateTime, frequency: BarFrequency) -> usize {
+    ((end - start).whole_seconds() / frequency.duration().whole_seconds()).max(0) as usize
+}
+
+/// An initial portfolio repriced for a backtest's first bar
+struct SeededBook {
+    portfolio: Portfolio,
+    /// Symbols held at their stored price
+    frozen: Vec<String>,
+}
+
+impl SeededBook {
+    /// `portfolio` at the aligned prices on `start` (else the first bar's),
+    /// freezing or rejecting positions without one per `policy`
+    fn open(portfolio: &Portfolio, policy: UnpricedPositions, start: OffsetDateTime, aligned: &AlignedPrices) -> Result<Self> {
+        let first = aligned.days.values().next();
+        let mut portfolio = portfolio.clone();
+        portfolio.timestamp = start;
+        let mut frozen = vec![];
+        for (symbol, position) in portfolio.positions.iter_mut() {
+            match aligned.opening.get(symbol).or_else(|| first.and_then(|prices| prices.get(symbol))) {
+                Some(price) => position.update_price(*price),
+                None if policy == UnpricedPositions::Freeze => frozen.push(symbol.clone()),
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "the initial portfolio holds {}, which the market data has no price for",
+                        symbol
+                    ))
+                }
+            }
+        }
+        if !frozen.is_empty() {
+            frozen.sort();
+            warn!("Holding initial positions without market data at their stored prices: {}", frozen.join(", "));
+        }
+        portfolio.update_total_value();
+        Ok(Self { portfolio, frozen })
+    }
+
+    /// Split `pnl` into the price change of the seeded positions, held
+    /// untouched to the `closing` prices, and the rest
+    fn contribution(self, closing: Option<&HashMap<String, Decimal>>, pnl: Decimal) -> InitialPortfolioContribution {
+        let positions_pnl = self
+            .portfolio
+            .positions
+            .iter()
+            .map(|(symbol, position)| {
+                let close = closing.and_then(|prices| prices.get(symbol)).unwrap_or(&position.asset.current_price);
+                position.quantity * (*close - position.asset.current_price)
+            })
+            .sum();
+        InitialPortfolioContribution {
+            positions_value: self.portfolio.positions_value(),
+            frozen_symbols: self.frozen,
+            positions_pnl,
+            decisions_pnl: pnl - positions_pnl,
+        }
+    }
+}
+
+/// Each symbol's yield observations, in time order
+struct YieldSchedule {
+    rates: HashMap<String, Vec<(OffsetDateTime, Decimal)>>,
+}
+
+impl YieldSchedule {
+    fn new(yields: &[YieldObservation]) -> Self {
+        let mut rates: HashMap<String, Vec<(OffsetDateTime, Decimal)>> = HashMap::new();
+        for observation in yields {
+            rates.entry(observation.symbol.clone()).or_default().push((observation.timestamp, observation.apy));
+        }
+        Self { rates }
+    }
+
+    /// `symbol`'s last observed rate at or before `time`; zero before its first
+    /// observation or without any
+    fn rate(&self, symbol: &str, time: OffsetDateTime) -> Decimal {
+        let Some(rates) = self.rates.get(symbol) else {
+            return Decimal::ZERO;
+        };
+        match rates.partition_point(|(observed, _)| *observed <= time) {
+            0 => Decimal::ZERO,
+            index => rates[index - 1].1,
+        }
+    }
+}
+
+/// Quantities of a passive benchmark bought at the start of a backtest
+struct BenchmarkBook {
+    quantities: Vec<(String, Decimal)>,
+    /// Purchase prices, which value a symbol until its first close
+    opening_prices: HashMap<String, Decimal>,
+}
+
+impl BenchmarkBook {
+    fn open(
+        benchmark: &Benchmark,
+        capital: Decimal,
+        prices: &HashMap<String, Decimal>,
+        closes: &BTreeMap<OffsetDateTime, HashMap<String, Decimal>>,
+    ) -> Result<Self> {
+        let mut quantities = vec![];
+        let mut opening_prices = HashMap::new();
+        for (symbol, weight) in benchmark.weights() {
+            let price = prices
+                .get(&symbol)
+                .or_else(|| closes.values().find_map(|day| day.get(&symbol)))
+                .copied()
+                .filter(|price| *price > Decimal::ZERO)
+                .ok_or_else(|| anyhow::anyhow!("benchmark symbol {} has no positive price in the market data", symbol))?;
+            quantities.push((symbol.clone(), capital * weight / price));
+            opening_prices.insert(symbol, price);
+        }
+        Ok(Self { quantities, opening_prices })
+    }
+
+    fn value(&self, prices: &HashMap<String, Decimal>) -> Decimal {
+        self.quantities
+            .iter()
+            .map(|(symbol, quantity)| {
+                let price = prices.get(symbol).unwrap_or(&self.opening_prices[symbol]);
+                *quantity * *price
+            })
+            .sum()
+    }
+}
+
+/// Per-step strategy and benchmark returns, accumulated as a backtest runs
+struct BenchmarkComparison {
+    book: BenchmarkBook,
+    strategy_value: Decimal,
+    benchmark_value: Decimal,
+    active: ActiveReturns,
+    /// Strategy and benchmark return of each step
+    returns: Vec<(f64, f64)>,
+}
+
+impl BenchmarkComparison {
+    fn new(book: BenchmarkBook, capital: Decimal) -> Self {
+        Self {
+            book,
+            strategy_value: capital,
+            benchmark_value: capital,
+            active: ActiveReturns::new(),
+            returns: vec![],
+        }
+    }
+
+    fn record(&mut self, strategy_value: Decimal, prices: &HashMap<String, Decimal>) {
+        let benchmark_value = self.book.value(prices);
+        let step_return = |now: Decimal, before: Decimal| {
+            if before > Decimal::ZERO { (now - before) / before } else { Decimal::ZERO }
+        };
+        let strategy_return = step_return(strategy_value, self.strategy_value);
+        let benchmark_return = step_return(benchmark_value, self.benchmark_value);
+        self.active.record(strategy_return, benchmark_return);
+        self.returns.push((
+            strategy_return.to_f64().unwrap_or(0.0),
+            benchmark_return.to_f64().unwrap_or(0.0),
+        ));
+        self.strategy_value = strategy_value;
+        self.benchmark_value = benchmark_value;
+    }
+
+    /// Slope of the regression of strategy returns on benchmark returns;
+    /// `None` when the benchmark's returns have no variance
+    fn beta(&self) -> Option<f64> {
+        if self.returns.len() < 2 {
+            return None;
+        }
+        let n = self.returns.len() as f64;
+        let mean_strategy = self.returns.iter().map(|(s, _)| s).sum::<f64>() / n;
+        let mean_benchmark = self.returns.iter().map(|(_, b)| b).sum::<f64>() / n;
+        let (covariance, variance) = self.returns.iter().fold((0.0, 0.0), |(cov, var), (s, b)| {
+            let deviation = b - mean_benchmark;
+            (cov + (s - mean_strategy) * deviation, var + deviation * deviation)
+        });
+        (variance > f64::EPSILON * n).then(|| covariance / variance)
+    }
+}
+
+/// Value, cash and drawdown at every snapshot
+fn equity_curve(history: &[PortfolioSnapshot]) -> Vec<EquityPoint> {
+    let mut peak = Decimal::ZERO;
+    history
+        .iter()
+        .map(|snapshot| {
+            peak = peak.max(snapshot.total_value);
+            let drawdown_pct = if peak > Decimal::ZERO {
+                ((peak - snapshot.total_value) / peak * Decimal::from(100)).to_f64().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            EquityPoint {
+                timestamp: snapshot.timestamp,
+                value: snapshot.total_value,
+                cash: snapshot.cash,
+                drawdown_pct,
+            }
+        })
+        .collect()
+}
+
+/// The `limit` deepest drawdowns of `curve`, deepest first, found in one
+/// pass: an episode runs from a peak to the first point back at or above it
+fn drawdown_episodes(curve: &[EquityPoint], limit: usize) -> Vec<DrawdownEpisode> {
+    let Some(first) = curve.first() else {
+        return vec![];
+    };
+    let mut episodes = vec![];
+    let (mut peak, mut trough) = (first, first);
+    let depth = |peak: &EquityPoint, trough: &EquityPoint| {
+        if peak.value > Decimal::ZERO {
+            ((peak.value - trough.value) / peak.value * Decimal::from(100)).to_f64().unwrap_or(0.0)
+        } else {
+            0.0
+        }
+    };
+    for point in &curve[1..] {
+        if point.value >= peak.value {
+            if trough.value < peak.value {
+                episodes.push(DrawdownEpisode {
+                    start: peak.timestamp,
+                    trough: trough.timestamp,
+                    recovery: Some(point.timestamp),
+                    depth_pct: depth(peak, trough),
+                    length_days: (point.timestamp - peak.timestamp).whole_days(),
+                });
+            }
+            (peak, trough) = (point, point);
+        } else if point.value < trough.value {
+            trough = point;
+        }
+    }
+    if trough.value < peak.value {
+        let end = curve[curve.len() - 1].timestamp;
+        episodes.push(DrawdownEpisode {
+            start: peak.timestamp,
+            trough: trough.timestamp,
+            recovery: None,
+            depth_pct: depth(peak, trough),
+            length_days: (end - peak.timestamp).whole_days(),
+        });
+    }
+    episodes.sort_by(|a, b| b.depth_pct.total_cmp(&a.depth_pct));
+    episodes.truncate(limit);
+    episodes
+}
+
+/// Return of each calendar month (or year) of `curve`, from the last value of
+/// the period before (the opening value for the first); periods without a
+/// point after the opening one are left out
+fn period_returns(curve: &[EquityPoint], start: Date, end: Date, monthly: bool) -> Vec<PeriodReturn> {
+    let period = |date: Date| (date.year(), monthly.then(|| u8::from(date.month())));
+    let Some((opening, points)) = curve.split_first() else {
+        return vec![];
+    };
+    let period_return = |(year, month): (i32, Option<u8>), from: Decimal, to: Decimal| {
+        // Cut short if the day before the start, or the day after the end, is in the period too
+        let in_period = |day: Date| period(day) == (year, month);
+        let starts_late = in_period(start) && start.previous_day().is_some_and(in_period);
+        let ends_early = in_period(end) && end.next_day().is_some_and(in_period);
+        PeriodReturn {
+            year,
+            month,
+            return_pct: crate::utils::percentage_change(from, to),
+            partial: starts_late || ends_early,
+        }
+    };
+    
+    let mut returns = vec![];
+    let mut base = opening.value;
+    let mut current: Option<((i32, Option<u8>), Decimal)> = None;
+    for point in points {
+        let key = period(point.timestamp.date());
+        if let Some((open, last)) = current.filter(|(open, _)| *open != key) {
+            returns.push(period_return(open, base, last));
+            base = last;
+        }
+        current = Some((key, point.value));
+    }
+    if let Some((open, last)) = current {
+        returns.push(period_return(open, base, last));
+    }
+    returns
+}
+
+/// Parse a `YYYY-MM-DD` date as midnight UTC
+fn parse_date(date: &str) -> Result<OffsetDateTime> {
+    let parsed = Date::parse(date.trim(), DATE_FORMAT)
+        .with_context(|| format!("'{}' is not a YYYY-MM-DD date", date))?;
+    Ok(parsed.midnight().assume_utc())
+}
+
+/// Pair each sell with the earliest open buys of its symbol (FIFO), splitting a
+/// lot that is only partly sold.
+///
+/// Closed trades come in exit order, one per lot an exit draws on, followed by
+/// the lots still open at the end (with no exit). PnL is on prices alone;
+/// execution costs go in `fees`, each fill's split by the quantity a trade
+/// takes from it. Sells with no open lot to draw on are ignored.
+pub fn pair_fills(fills: &[Fill]) -> Vec<Trade> {
+    // Open lots per symbol, oldest first, each with its position in `fills`
+    let mut open: HashMap<&str, VecDeque<(usize, &Fill, Decimal)>> = HashMap::new();
+    let mut trades = vec![];
+    
+    for (index, fill) in fills.iter().enumerate() {
+        if fill.quantity <= Decimal::ZERO {
+            continue;
+        }
+        let lots = open.entry(fill.symbol.as_str()).or_default();
+        if fill.side == TradeSide::Buy {
+            lots.push_back((index, fill, fill.quantity));
+            continue;
         }
         
-        Ok(data)
+        let mut remaining = fill.quantity;
+        while remaining > Decimal::ZERO {
+            let Some((_, entry, lot)) = lots.front_mut() else {
+                break;
+            };
+            let quantity = remaining.min(*lot);
+            trades.push(Trade {
+                entry_time: entry.timestamp,
+                exit_time: Some(fill.timestamp),
+                asset: fill.symbol.clone(),
+                quantity,
+                entry_price: entry.price,
+                exit_price: Some(fill.price),
+                pnl: Some((fill.price - entry.price) * quantity),
+                pnl_pct: Some(crate::utils::percentage_change(entry.price, fill.price)),
+                fees: fee_share(entry, quantity) + fee_share(fill, quantity),
+            });
+            remaining -= quantity;
+            *lot -= quantity;
+            if *lot <= Decimal::ZERO {
+                lots.pop_front();
+            }
+        }
+    }
+    
+    let mut still_open: Vec<_> = open.into_values().flatten().collect();
+    still_open.sort_by_key(|(index, _, _)| *index);
+    trades.extend(still_open.into_iter().map(|(_, entry, quantity)| Trade {
+        entry_time: entry.timestamp,
+        exit_time: None,
+        asset: entry.symbol.clone(),
+        quantity,
+        entry_price: entry.price,
+        exit_price: None,
+        pnl: None,
+        pnl_pct: None,
+        fees: fee_share(entry, quantity),
+    }));
+    trades
+}
+
+/// The part of `fill`'s fee carried by `quantity` of it
+fn fee_share(fill: &Fill, quantity: Decimal) -> Decimal {
+    if quantity >= fill.quantity {
+        fill.fee
+    } else {
+        fill.fee * quantity / fill.quantity
+    }
+}
+
+/// Win rate and profit factor over the closed trades in `trades`
+pub fn trade_stats(trades: &[Trade]) -> (f64, Option<f64>) {
+    let pnls: Vec<Decimal> = trades.iter().filter_map(|trade| trade.pnl).collect();
+    if pnls.is_empty() {
+        return (0.0, None);
+    }
+    let wins = pnls.iter().filter(|pnl| **pnl > Decimal::ZERO).count();
+    let gross_profit: Decimal = pnls.iter().filter(|pnl| **pnl > Decimal::ZERO).sum();
+    let gross_loss: Decimal = pnls.iter().filter(|pnl| **pnl < Decimal::ZERO).map(|pnl| -*pnl).sum();
+    let profit_factor = (gross_loss > Decimal::ZERO)
+        .then(|| (gross_profit / gross_loss).to_f64())
+        .flatten();
+    (wins as f64 / pnls.len() as f64, profit_factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{date, datetime};
+
+    /// Daily bars of `symbol` closing at `closes`, the first on 2024-01-01
+    fn daily_bars(symbol: &str, closes: &[Decimal]) -> Vec<MarketData> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(day, close)| MarketData {
+                timestamp: datetime!(2024-01-01 0:00 UTC) + Duration::days(day as i64),
+                symbol: symbol.to_string(),
+                price: *close,
+                volume: dec!(1000000),
+                high: *close,
+                low: *close,
+                open: *close,
+                close: *close,
+            })
+            .collect()
+    }
+
+    fn all_in(symbol: &str) -> Strategy {
+        Strategy::target_weight(HashMap::from([(symbol.to_string(), Decimal::ONE)]))
+    }
+
+    #[tokio::test]
+    async fn two_year_range_steps_every_day_of_it() {
+        let mut engine = BacktestEngine::new("2022-01-01", "2024-01-01", Strategy::conservative()).unwrap();
+        let results = engine.run().await.unwrap();
+
+        assert_eq!(results.start_date, datetime!(2022-01-01 0:00 UTC));
+        assert_eq!(results.end_date, datetime!(2024-01-01 0:00 UTC));
+        assert_eq!(results.data_quality.calendar_days, 730);
+        assert_eq!(results.data_quality.dropped_days, 0);
+        // The opening point and one per day stepped
+        assert_eq!(results.equity_curve.len(), 731);
+        assert_eq!(results.equity_curve.last().unwrap().timestamp, datetime!(2024-01-01 0:00 UTC));
+        // Annualized over the two years asked for
+        let growth = (results.final_value / results.initial_value).to_f64().unwrap();
+        let annualized = (growth.powf(CALENDAR_DAYS_PER_YEAR / 730.0) - 1.0) * 100.0;
+        assert!((results.annualized_return_pct - annualized).abs() < 1e-9);
+    }
+
+    #[test]
+    fn malformed_and_reversed_dates_are_rejected() {
+        let error = |start: &str, end: &str| {
+            let error = BacktestEngine::new(start, end, Strategy::conservative()).err().expect("should be rejected");
+            format!("{:#}", error)
+        };
+        assert!(error("2022-13-01", "2023-01-01").contains("'2022-13-01' is not a YYYY-MM-DD date"));
+        assert!(error("2022-01-01", "01/01/2023").contains("invalid end date"));
+        assert!(error("2023-01-01", "2022-01-01").contains("must be before end date"));
+        assert!(error("2023-01-01", "2023-01-01").contains("must be before end date"));
+        assert!(BacktestEngine::new(" 2022-01-01 ", "2022-01-02", Strategy::conservative()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn holding_through_rising_prices_earns_their_rise() {
+        // 100 on the start date, up 1 a day to 110
+        let closes: Vec<Decimal> = (100..=110).map(Decimal::from).collect();
+        let mut engine = BacktestEngine::new("2024-01-01", "2024-01-11", all_in("X"))
+            .unwrap()
+            .with_config(BacktestConfig::frictionless())
+            .unwrap()
+            .with_market_data(daily_bars("X", &closes))
+            .unwrap();
+        let results = engine.run().await.unwrap();
+
+        // Bought at the first bar's 101, keeping back the cash the strategy sized for its own cost estimate,
+        // and marked at 110
+        let cash = results.equity_curve[1].cash;
+        assert!(cash < dec!(2500));
+        let expected = cash + (dec!(1000000) - cash) * dec!(110) / dec!(101);
+        assert_eq!(results.final_value.round_dp(6), expected.round_dp(6));
+        assert!(results.total_return_pct > 8.8);
+        let curve: Vec<Decimal> = results.equity_curve.iter().map(|point| point.value).collect();
+        assert!(curve.windows(2).skip(1).all(|pair| pair[1] > pair[0]));
+    }
+
+    #[tokio::test]
+    async fn missing_days_carry_the_last_close() {
+        let mut bars = daily_bars("X", &[dec!(100), dec!(100), dec!(100), dec!(100), dec!(100)]);
+        bars.extend(daily_bars("Y", &[dec!(10), dec!(11), dec!(12), dec!(13), dec!(14)]));
+        // X has no bar on the 3rd
+        bars.remove(2);
+        let mut engine = BacktestEngine::new("2024-01-01", "2024-01-05", all_in("Y"))
+            .unwrap()
+            .with_config(BacktestConfig::frictionless())
+            .unwrap()
+            .with_market_data(bars)
+            .unwrap();
+        let results = engine.run().await.unwrap();
+
+        assert_eq!(results.data_quality.dropped_days, 0);
+        let x = results.data_quality.symbols.iter().find(|coverage| coverage.symbol == "X").unwrap();
+        assert_eq!((x.bar_days, x.gaps_filled), (3, 1));
+        assert_eq!(results.equity_curve.len(), 5);
+        let cash = results.equity_curve[1].cash;
+        let expected = cash + (dec!(1000000) - cash) * dec!(14) / dec!(11);
+        assert_eq!(results.final_value.round_dp(6), expected.round_dp(6));
+    }
+
+    fn fill(day: i64, symbol: &str, side: TradeSide, quantity: Decimal, price: Decimal, fee: Decimal) -> Fill {
+        Fill {
+            step: day as usize,
+            timestamp: datetime!(2024-01-01 0:00 UTC) + Duration::days(day),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price,
+            fee,
+        }
+    }
+
+    #[test]
+    fn fills_pair_first_in_first_out() {
+        use TradeSide::{Buy, Sell};
+        let fills = [
+            fill(1, "X", Buy, dec!(10), dec!(100), dec!(1)),
+            fill(2, "X", Buy, dec!(10), dec!(110), dec!(2)),
+            // Closes the first lot and half the second
+            fill(3, "X", Sell, dec!(15), dec!(120), dec!(3)),
+            fill(3, "Y", Buy, dec!(4), dec!(50), Decimal::ZERO),
+            fill(4, "Y", Sell, dec!(4), dec!(40), Decimal::ZERO),
+            // Nothing open to sell
+            fill(5, "Z", Sell, dec!(1), dec!(10), Decimal::ZERO),
+        ];
+        let trades = pair_fills(&fills);
+
+        let summary: Vec<_> = trades
+            .iter()
+            .map(|trade| (trade.asset.as_str(), trade.quantity, trade.entry_price, trade.pnl, trade.fees))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("X", dec!(10), dec!(100), Some(dec!(200)), dec!(3)),
+                ("X", dec!(5), dec!(110), Some(dec!(50)), dec!(2)),
+                ("Y", dec!(4), dec!(50), Some(dec!(-40)), Decimal::ZERO),
+                ("X", dec!(5), dec!(110), None, dec!(1)),
+            ]
+        );
+        assert_eq!(trades[0].exit_time, Some(datetime!(2024-01-04 0:00 UTC)));
+        assert_eq!(trades[0].pnl_pct, Some(20.0));
+        let open = &trades[3];
+        assert_eq!((open.exit_time, open.exit_price, open.pnl_pct), (None, None, None));
+
+        // The open lot is left out: two wins of 250 against a loss of 40
+        let (win_rate, profit_factor) = trade_stats(&trades);
+        assert!((win_rate - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(profit_factor, Some(6.25));
+    }
+
+    #[test]
+    fn trade_stats_without_closed_trades_or_losses() {
+        assert_eq!(trade_stats(&[]), (0.0, None));
+        let open_only = pair_fills(&[fill(1, "X", TradeSide::Buy, dec!(1), dec!(10), Decimal::ZERO)]);
+        assert_eq!(trade_stats(&open_only), (0.0, None));
+        let winner = pair_fills(&[
+            fill(1, "X", TradeSide::Buy, dec!(1), dec!(10), Decimal::ZERO),
+            fill(2, "X", TradeSide::Sell, dec!(1), dec!(12), Decimal::ZERO),
+        ]);
+        assert_eq!(trade_stats(&winner), (1.0, None));
+    }
+
+    fn backtest(strategy: Strategy, bars: Vec<MarketData>, end: &str) -> BacktestEngine {
+        BacktestEngine::new("2024-01-01", end, strategy)
+            .unwrap()
+            .with_config(BacktestConfig::frictionless())
+            .unwrap()
+            .with_market_data(bars)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn basket_benchmark_is_bought_at_the_opening_prices_and_held() {
+        let mut bars = daily_bars("X", &(100..=110).map(Decimal::from).collect::<Vec<_>>());
+        bars.extend(daily_bars("Y", &[dec!(10); 11]));
+        let benchmark = Benchmark::from_spec("X:0.6,Y:0.4").unwrap();
+        let results = backtest(all_in("X"), bars, "2024-01-11").with_benchmark(benchmark).run().await.unwrap();
+
+        // 60% of the book rose 10%
+        assert!((results.benchmark_return_pct.unwrap() - 6.0).abs() < 1e-9);
+        let benchmark_annualized = (1.06_f64.powf(CALENDAR_DAYS_PER_YEAR / 10.0) - 1.0) * 100.0;
+        let excess = results.annualized_return_pct - benchmark_annualized;
+        assert!((results.alpha_pct.unwrap() - excess).abs() < 1e-6);
+        assert!(results.beta.is_some_and(f64::is_finite));
+        assert!(results.tracking_error.unwrap() > 0.0);
+        assert!(results.information_ratio.is_some());
+    }
+
+    #[tokio::test]
+    async fn flat_benchmark_has_no_beta_or_information_ratio() {
+        let mut bars = daily_bars("X", &[dec!(100); 5]);
+        bars.extend(daily_bars("Y", &[dec!(10); 5]));
+        let benchmark = Benchmark::Symbol("Y".to_string());
+        let results = backtest(all_in("X"), bars, "2024-01-05").with_benchmark(benchmark).run().await.unwrap();
+
+        assert_eq!(results.benchmark_return_pct, Some(0.0));
+        assert_eq!(results.beta, None);
+        assert_eq!(results.tracking_error, Some(0.0));
+        assert_eq!(results.information_ratio, None);
+    }
+
+    #[test]
+    fn beta_regresses_strategy_on_benchmark_returns() {
+        let book = BenchmarkBook {
+            quantities: vec![("X".to_string(), Decimal::ONE)],
+            opening_prices: HashMap::from([("X".to_string(), dec!(100))]),
+        };
+        let mut comparison = BenchmarkComparison::new(book, dec!(100));
+        // Twice the benchmark's move every step
+        let mut strategy = dec!(100);
+        let mut previous = dec!(100);
+        for price in [dec!(101), dec!(99), dec!(102), dec!(100), dec!(103)] {
+            strategy *= Decimal::ONE + Decimal::TWO * (price - previous) / previous;
+            previous = price;
+            comparison.record(strategy, &HashMap::from([("X".to_string(), price)]));
+        }
+        assert!((comparison.beta().unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(comparison.benchmark_value, dec!(103));
+    }
+
+    #[tokio::test]
+    async fn commission_costs_about_turnover_times_its_rate() {
+        // X jumps once, far enough past the drift threshold to force one rebalance
+        let closes: Vec<Decimal> = (0..10).map(|day| if day < 5 { dec!(100) } else { dec!(125) }).collect();
+        let mut bars = daily_bars("X", &closes);
+        bars.extend(daily_bars("Y", &[dec!(100); 10]));
+        let free = backtest(halves(), bars.clone(), "2024-01-10").run().await.unwrap();
+        let config = BacktestConfig { commission_bps: dec!(20), ..BacktestConfig::frictionless() };
+        let charged = backtest(halves(), bars, "2024-01-10").with_config(config).unwrap().run().await.unwrap();
+
+        assert!(charged.turnover > free.initial_value);
+        assert_eq!(charged.total_commission.round_dp(12), (charged.turnover * dec!(0.002)).round_dp(12));
+        assert_eq!(free.total_commission, Decimal::ZERO);
+        let expected = (charged.turnover * dec!(0.002)).to_f64().unwrap();
+        let lost = (free.final_value - charged.final_value).to_f64().unwrap();
+        assert!((lost - expected).abs() < expected * 0.02, "lost {lost}, expected about {expected}");
+    }
+
+    #[test]
+    fn commission_adds_the_fixed_fee_to_nonzero_trades() {
+        let config =
+            BacktestConfig { commission_bps: dec!(10), commission_fixed: dec!(1), ..BacktestConfig::frictionless() };
+        assert_eq!(config.commission(dec!(10000)), dec!(11));
+        assert_eq!(config.commission(Decimal::ZERO), Decimal::ZERO);
+    }
+
+    #[test]
+    fn volume_share_slippage_scales_with_participation() {
+        let config = BacktestConfig {
+            slippage: Slippage::VolumeShare { impact_bps: dec!(100) },
+            ..BacktestConfig::frictionless()
+        };
+        // 10% of the day's volume pays a tenth of the full impact
+        assert_eq!(config.slippage(dec!(1000), Some(dec!(10000))), dec!(1));
+        // Participation caps at the whole day, and a missing bar counts as all of it
+        assert_eq!(config.slippage(dec!(1000), Some(dec!(500))), dec!(10));
+        assert_eq!(config.slippage(dec!(1000), None), dec!(10));
+        let flat = BacktestConfig { slippage: Slippage::Bps(dec!(5)), ..BacktestConfig::frictionless() };
+        assert_eq!(flat.slippage(dec!(1000), Some(dec!(1))), dec!(0.5));
+    }
+
+    fn aligned(bars: Vec<MarketData>, config: BacktestConfig) -> Result<AlignedPrices> {
+        let engine = backtest(all_in("X"), bars, "2024-01-10").with_config(config).unwrap();
+        engine.align(&engine.market_data)
+    }
+
+    #[test]
+    fn days_before_a_late_symbol_starts_are_dropped_or_fail() {
+        let mut bars = daily_bars("X", &[dec!(100); 10]);
+        bars.extend(daily_bars("Y", &[dec!(10); 10]).into_iter().skip(4));
+        let prices = aligned(bars.clone(), BacktestConfig::frictionless()).unwrap();
+
+        // Y's first bar is on day 5, so days 2 through 4 have no price for it
+        assert_eq!(prices.report.dropped_days, 3);
+        assert_eq!(prices.days.len(), 6);
+        assert!(prices.days.values().all(|day| day.len() == 2));
+        let y = prices.report.symbols.iter().find(|coverage| coverage.symbol == "Y").unwrap();
+        assert_eq!((y.bar_days, y.missing_days), (6, 3));
+        assert!((y.coverage_pct - 200.0 / 3.0).abs() < 1e-9);
+
+        let config = BacktestConfig { gap_policy: GapPolicy::Error, ..BacktestConfig::frictionless() };
+        let error = aligned(bars, config).err().unwrap().to_string();
+        assert!(error.contains("Y has no price on 2024-01-02: its first bar is later"), "{error}");
+    }
+
+    #[test]
+    fn gaps_are_filled_up_to_the_limit() {
+        // X has no bars on days 3 through 8
+        let bars: Vec<MarketData> = daily_bars("X", &(1..=10).map(Decimal::from).collect::<Vec<_>>())
+            .into_iter()
+            .filter(|bar| !(2..8).contains(&(bar.timestamp - datetime!(2024-01-01 0:00 UTC)).whole_days()))
+            .collect();
+        let config = BacktestConfig { max_fill_days: 2, ..BacktestConfig::frictionless() };
+        let prices = aligned(bars.clone(), config.clone()).unwrap();
+
+        let x = &prices.report.symbols[0];
+        assert_eq!((x.bar_days, x.gaps_filled, x.missing_days), (3, 2, 4));
+        assert_eq!(prices.report.dropped_days, 4);
+        assert_eq!(prices.days[&datetime!(2024-01-04 0:00 UTC)]["X"], dec!(2));
+        assert!(!prices.days.contains_key(&datetime!(2024-01-05 0:00 UTC)));
+
+        let config = BacktestConfig { gap_policy: GapPolicy::Error, ..config };
+        let error = aligned(bars, config).err().unwrap().to_string();
+        assert!(error.contains("X has no price on 2024-01-05: its last bar, on 2024-01-02"), "{error}");
+    }
+
+    #[test]
+    fn outliers_are_flagged_and_optionally_clipped() {
+        let mut closes = [dec!(100); 10];
+        closes[3] = dec!(300);
+        let flagged = aligned(daily_bars("X", &closes), BacktestConfig::frictionless()).unwrap();
+        // Both the spike and the fall back from it move more than 50%
+        assert_eq!(flagged.report.symbols[0].outliers, 2);
+        assert_eq!(flagged.days[&datetime!(2024-01-04 0:00 UTC)]["X"], dec!(300));
+
+        let config = BacktestConfig { clip_outliers: true, ..BacktestConfig::frictionless() };
+        let clipped = aligned(daily_bars("X", &closes), config).unwrap();
+        // Clipped to 150, the fall back to 100 is within the threshold
+        assert_eq!(clipped.report.symbols[0].outliers, 1);
+        assert_eq!(clipped.days[&datetime!(2024-01-04 0:00 UTC)]["X"], dec!(150));
+        assert_eq!(clipped.days[&datetime!(2024-01-05 0:00 UTC)]["X"], dec!(100));
+    }
+
+    fn point(timestamp: OffsetDateTime, value: Decimal) -> EquityPoint {
+        EquityPoint { timestamp, value, cash: Decimal::ZERO, drawdown_pct: 0.0 }
+    }
+
+    #[test]
+    fn edge_months_are_marked_partial() {
+        let curve = [
+            point(datetime!(2024-01-15 0:00 UTC), dec!(100)),
+            point(datetime!(2024-01-31 0:00 UTC), dec!(110)),
+            point(datetime!(2024-02-29 0:00 UTC), dec!(99)),
+            point(datetime!(2024-03-10 0:00 UTC), dec!(118.8)),
+        ];
+        let (start, end) = (curve[0].timestamp.date(), curve[3].timestamp.date());
+        let months = period_returns(&curve, start, end, true);
+
+        let summary: Vec<(Option<u8>, f64, bool)> =
+            months.iter().map(|month| (month.month, (month.return_pct * 1e9).round() / 1e9, month.partial)).collect();
+        assert_eq!(summary, vec![(Some(1), 10.0, true), (Some(2), -10.0, false), (Some(3), 20.0, true)]);
+        assert!(months.iter().all(|month| month.year == 2024));
+
+        let years = period_returns(&curve, start, end, false);
+        assert_eq!(years.len(), 1);
+        assert_eq!((years[0].month, years[0].partial), (None, true));
+        assert!((years[0].return_pct - 18.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn whole_months_and_year_boundaries_are_not_partial() {
+        let curve = [
+            point(datetime!(2023-12-01 0:00 UTC), dec!(100)),
+            point(datetime!(2023-12-31 0:00 UTC), dec!(105)),
+            point(datetime!(2024-01-31 0:00 UTC), dec!(84)),
+        ];
+        let months = period_returns(&curve, date!(2023-11-30), date!(2024-01-31), true);
+        let summary: Vec<(i32, bool)> = months.iter().map(|month| (month.year, month.partial)).collect();
+        assert_eq!(summary, [(2023, false), (2024, false)]);
+        assert!((months[1].return_pct + 20.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn equity_curve_is_written_with_drawdown_and_cash() {
+        let bars = daily_bars("X", &[dec!(100), dec!(120), dec!(90)]);
+        let results = backtest(all_in("X"), bars, "2024-01-03").run().await.unwrap();
+        let path = std::env::temp_dir().join(format!("vaulta-equity-{}.csv", uuid::Uuid::new_v4()));
+        results.write_csv(&path).unwrap();
+        let written = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let written = written.unwrap();
+        let rows: Vec<Vec<&str>> = written.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows[0], ["date", "value", "drawdown", "cash"]);
+        assert_eq!(rows.len(), results.equity_curve.len() + 1);
+        assert_eq!(rows[1][0], "2024-01-01");
+        let last = results.equity_curve.last().unwrap();
+        assert_eq!(rows[3][0], "2024-01-03");
+        assert_eq!(rows[3][1..], [last.value.to_string(), last.drawdown_pct.to_string(), last.cash.to_string()]);
+        assert!(last.drawdown_pct > 20.0);
+    }
+
+    #[tokio::test]
+    async fn monthly_rebalancing_trades_less_than_daily() {
+        let closes: Vec<Decimal> = (0..90).map(|day| if day % 2 == 0 { dec!(100) } else { dec!(130) }).collect();
+        let mut bars = daily_bars("X", &closes);
+        bars.extend(daily_bars("Y", &[dec!(100); 90]));
+        let charged = BacktestConfig { commission_bps: dec!(10), ..BacktestConfig::frictionless() };
+        let mut daily = backtest(halves(), bars.clone(), "2024-03-30").with_config(charged.clone()).unwrap();
+        let daily = daily.run().await.unwrap();
+        let monthly = BacktestConfig { rebalance_days: Some(30), ..charged };
+        let monthly = backtest(halves(), bars, "2024-03-30").with_config(monthly).unwrap().run().await.unwrap();
+
+        // Daily rebalancing trades back to 50/50 on every swing; monthly only three times
+        assert!(monthly.turnover * dec!(10) < daily.turnover, "{} vs {}", monthly.turnover, daily.turnover);
+        assert!(monthly.total_commission * dec!(10) < daily.total_commission);
+    }
+
+    #[tokio::test]
+    async fn out_of_sample_split_does_not_leak_later_data() {
+        // Up 1 a day for 20 days, then down
+        let closes: Vec<Decimal> = (0..30).map(|day| Decimal::from(100 + day.min(20) * 2 - day)).collect();
+        let mut crashed = closes.clone();
+        crashed[25] = dec!(1);
+        let results = backtest(all_in("X"), daily_bars("X", &closes), "2024-01-30");
+        let results = results.run_out_of_sample(0.3).await.unwrap();
+        let perturbed = backtest(all_in("X"), daily_bars("X", &crashed), "2024-01-30");
+        let perturbed = perturbed.run_out_of_sample(0.3).await.unwrap();
+
+        // 29 days split 70/30 on a whole day
+        assert_eq!(results.split_date, datetime!(2024-01-21 0:00 UTC));
+        assert_eq!(results.in_sample.end_date, results.out_of_sample.start_date);
+        // Bars after the split, outliers and all, don't change the in-sample run
+        assert_eq!(results.in_sample.final_value, perturbed.in_sample.final_value);
+        assert_eq!(perturbed.in_sample.data_quality.symbols[0].outliers, 0);
+        assert!(perturbed.out_of_sample.data_quality.symbols[0].outliers > 0);
+
+        assert!(results.in_sample.sharpe_ratio > 0.0 && results.out_of_sample.sharpe_ratio < 0.0);
+        let ratio = results.out_of_sample.sharpe_ratio / results.in_sample.sharpe_ratio;
+        assert_eq!(results.degradation_ratio, Some(ratio));
+    }
+
+    #[tokio::test]
+    async fn out_of_sample_fraction_must_leave_both_segments_days() {
+        let engine = backtest(all_in("X"), daily_bars("X", &[dec!(100); 5]), "2024-01-05");
+        for fraction in [0.0, 1.0, -0.2, f64::NAN, 0.01, 0.99] {
+            assert!(engine.run_out_of_sample(fraction).await.is_err(), "{} should be rejected", fraction);
+        }
+    }
+
+    /// `YYYY-MM-DD` of the day `days` after 2024-01-01
+    fn day(days: i64) -> String {
+        (datetime!(2024-01-01 0:00 UTC) + Duration::days(days)).date().to_string()
+    }
+
+    #[tokio::test]
+    async fn five_hundred_days_of_data_are_all_stepped() {
+        let closes: Vec<Decimal> = (0..=500).map(|day| dec!(100) + Decimal::from(day % 7)).collect();
+        let results = backtest(all_in("X"), daily_bars("X", &closes), &day(500)).run().await.unwrap();
+
+        assert_eq!(results.equity_curve.len(), 501);
+        assert_eq!(results.equity_curve.last().unwrap().timestamp.date().to_string(), day(500));
+        let growth = (results.final_value / results.initial_value).to_f64().unwrap();
+        let annualized = (growth.powf(CALENDAR_DAYS_PER_YEAR / 500.0) - 1.0) * 100.0;
+        assert!((results.annualized_return_pct - annualized).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn volatility_is_annualized_at_the_rate_bars_were_kept() {
+        let closes: Vec<Decimal> = (0..200).map(|day| if day % 2 == 0 { dec!(100) } else { dec!(110) }).collect();
+        let every_day = backtest(all_in("X"), daily_bars("X", &closes), &day(199)).run().await.unwrap();
+        // The same closes on every other day, with the days between dropped
+        let mut sparse = daily_bars("X", &closes);
+        for bar in &mut sparse {
+            bar.timestamp += bar.timestamp - datetime!(2024-01-01 0:00 UTC);
+        }
+        let config = BacktestConfig { max_fill_days: 0, ..BacktestConfig::frictionless() };
+        let mut every_other_day = backtest(all_in("X"), sparse, &day(398)).with_config(config).unwrap();
+        let every_other_day = every_other_day.run().await.unwrap();
+
+        assert_eq!(every_other_day.data_quality.dropped_days, 199);
+        assert_eq!(every_other_day.final_value, every_day.final_value);
+        let ratio = every_other_day.volatility_pct / every_day.volatility_pct;
+        assert!((ratio - 0.5_f64.sqrt()).abs() < 1e-9, "ratio {}", ratio);
+    }
+
+    #[tokio::test]
+    async fn rolling_windows_match_standalone_backtests() {
+        // Up 1 a day for 15 days, then down 2 a day
+        let closes: Vec<Decimal> = (0..=30).map(|day| Decimal::from(100 + day.min(15) * 3 - day * 2)).collect();
+        let engine = backtest(all_in("X"), daily_bars("X", &closes), "2024-01-31");
+        let rolling = engine.rolling(10, 5).await.unwrap();
+
+        let starts: Vec<i64> =
+            rolling.windows.iter().map(|window| (window.start_date - engine.start_date).whole_days()).collect();
+        assert_eq!(starts, [0, 5, 10, 15, 20]);
+        assert_eq!(rolling.skipped_windows, 0);
+        assert_eq!(rolling.worst_window.start_date, datetime!(2024-01-21 0:00 UTC));
+        for window in &rolling.windows {
+            let standalone = engine.window(window.start_date, window.end_date, all_in("X")).run().await.unwrap();
+            assert!((window.total_return_pct - standalone.total_return_pct).abs() < 1e-9);
+            assert!((window.max_drawdown_pct - standalone.max_drawdown_pct).abs() < 1e-9);
+        }
+
+        let mut returns: Vec<f64> = rolling.windows.iter().map(|window| window.total_return_pct).collect();
+        returns.sort_by(f64::total_cmp);
+        assert_eq!((rolling.total_return.min, rolling.total_return.median), (returns[0], returns[2]));
+        assert_eq!(rolling.total_return.max, returns[4]);
+        assert_eq!(rolling.total_return.min, rolling.worst_window.total_return_pct);
+    }
+
+    #[tokio::test]
+    async fn rolling_skips_windows_without_a_bar() {
+        // No bars on days 11 through 20
+        let bars: Vec<MarketData> = daily_bars("X", &[dec!(100); 31])
+            .into_iter()
+            .filter(|bar| !(11..=20).contains(&(bar.timestamp - datetime!(2024-01-01 0:00 UTC)).whole_days()))
+            .collect();
+        let config = BacktestConfig { max_fill_days: 0, ..BacktestConfig::frictionless() };
+        let engine = backtest(all_in("X"), bars, "2024-01-31").with_config(config).unwrap();
+        let rolling = engine.rolling(10, 5).await.unwrap();
+
+        assert_eq!(rolling.skipped_windows, 1);
+        assert_eq!(rolling.windows.len(), 4);
+        assert!(rolling.windows.iter().all(|window| window.start_date != datetime!(2024-01-11 0:00 UTC)));
+    }
+
+    #[tokio::test]
+    async fn rolling_windows_must_fit_the_range() {
+        let engine = backtest(all_in("X"), daily_bars("X", &[dec!(100); 11]), "2024-01-11");
+        assert!(engine.rolling(0, 1).await.is_err());
+        assert!(engine.rolling(5, 0).await.is_err());
+        assert!(engine.rolling(11, 1).await.is_err());
+        assert_eq!(engine.rolling(10, 1).await.unwrap().windows.len(), 1);
+    }
+
+    /// Equity points a day apart from 2024-01-01
+    fn daily_curve(values: &[Decimal]) -> Vec<EquityPoint> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(day, value)| point(datetime!(2024-01-01 0:00 UTC) + Duration::days(day as i64), *value))
+            .collect()
+    }
+
+    #[test]
+    fn drawdowns_are_ranked_by_depth_with_the_unrecovered_one_open() {
+        let curve = daily_curve(&[
+            dec!(100), dec!(90), dec!(95), dec!(100), dec!(110), dec!(104.5), dec!(110), dec!(120), dec!(84), dec!(90),
+        ]);
+        let episodes = drawdown_episodes(&curve, DRAWDOWN_TABLE_LEN);
+        let day = |days: i64| datetime!(2024-01-01 0:00 UTC) + Duration::days(days);
+
+        let summary: Vec<(OffsetDateTime, OffsetDateTime, Option<OffsetDateTime>, i64)> = episodes
+            .iter()
+            .map(|episode| (episode.start, episode.trough, episode.recovery, episode.length_days))
+            .collect();
+        assert_eq!(
+            summary,
+            [(day(7), day(8), None, 2), (day(0), day(1), Some(day(3)), 3), (day(4), day(5), Some(day(6)), 2)]
+        );
+        let depths: Vec<f64> = episodes.iter().map(|episode| (episode.depth_pct * 1e9).round() / 1e9).collect();
+        assert_eq!(depths, [30.0, 10.0, 5.0]);
+
+        assert_eq!(drawdown_episodes(&curve, 1), episodes[..1]);
+        assert!(drawdown_episodes(&daily_curve(&[dec!(1), dec!(2), dec!(2)]), 5).is_empty());
+        assert!(drawdown_episodes(&[], 5).is_empty());
+    }
+
+    #[tokio::test]
+    async fn backtest_sortino_and_calmar_come_from_the_shared_metrics() {
+        let closes = [dec!(100), dec!(104), dec!(98), dec!(103), dec!(101), dec!(108)];
+        let results = backtest(all_in("X"), daily_bars("X", &closes), "2024-01-06").run().await.unwrap();
+        let returns: Vec<Decimal> = results
+            .equity_curve
+            .windows(2)
+            .map(|pair| (pair[1].value - pair[0].value) / pair[0].value)
+            .collect();
+
+        let sortino = metrics::sortino_ratio(&returns, 0.0, CALENDAR_DAYS_PER_YEAR).unwrap();
+        assert!((results.sortino_ratio.unwrap() - sortino).abs() < 1e-9);
+        let calmar = results.annualized_return_pct / results.max_drawdown_pct;
+        assert!((results.calmar_ratio.unwrap() - calmar).abs() < 1e-9);
+
+        // Never falling, there is nothing to divide by
+        let rising = [dec!(100), dec!(101), dec!(102)];
+        let results = backtest(all_in("X"), daily_bars("X", &rising), "2024-01-03").run().await.unwrap();
+        assert_eq!((results.sortino_ratio, results.calmar_ratio), (None, None));
+    }
+
+    #[tokio::test]
+    async fn cost_sensitivity_finds_where_the_edge_breaks_even() {
+        // A 0.5% rise, bought once
+        let closes: Vec<Decimal> = (0..10).map(|day| dec!(100) + Decimal::from(day) / dec!(18)).collect();
+        let engine = backtest(all_in("X"), daily_bars("X", &closes), "2024-01-10");
+        let sensitivity = engine.cost_sensitivity(&[50.0, 0.0, 10.0, 25.0, 5.0, 5.0]).await.unwrap();
+
+        let levels: Vec<f64> = sensitivity.points.iter().map(|point| point.cost_bps).collect();
+        assert_eq!(levels, [0.0, 5.0, 10.0, 25.0, 50.0]);
+        assert!(sensitivity.points.windows(2).all(|pair| pair[1].total_return_pct < pair[0].total_return_pct));
+        assert_eq!(sensitivity.points[0].total_costs, Decimal::ZERO);
+
+        // Each level is the backtest run at that commission
+        let config = BacktestConfig { commission_bps: dec!(25), ..BacktestConfig::frictionless() };
+        let mut at_25 = engine.window(engine.start_date, engine.end_date, all_in("X")).with_config(config).unwrap();
+        let at_25 = at_25.run().await.unwrap();
+        assert_eq!(sensitivity.points[3].total_return_pct, at_25.total_return_pct);
+
+        let (low, high) = (&sensitivity.points[3], &sensitivity.points[4]);
+        assert!(low.total_return_pct > 0.0 && high.total_return_pct < 0.0);
+        let share = low.total_return_pct / (low.total_return_pct - high.total_return_pct);
+        let break_even = sensitivity.break_even_bps.unwrap();
+        assert!((break_even - (25.0 + 25.0 * share)).abs() < 1e-9);
+        assert!(break_even > 40.0 && break_even < 50.0, "{}", break_even);
+
+        let json = serde_json::to_value(&sensitivity).unwrap();
+        assert_eq!(json["points"].as_array().unwrap().len(), 5);
+        assert_eq!(json["break_even_bps"].as_f64(), Some(break_even));
+    }
+
+    #[tokio::test]
+    async fn cost_sensitivity_needs_valid_levels_and_may_never_break_even() {
+        let engine = backtest(all_in("X"), daily_bars("X", &[dec!(100), dec!(100), dec!(150)]), "2024-01-03");
+        for levels in [&[][..], &[-1.0], &[5.0, f64::NAN], &[f64::INFINITY]] {
+            assert!(engine.cost_sensitivity(levels).await.is_err(), "{:?} should be rejected", levels);
+        }
+        let sensitivity = engine.cost_sensitivity(&[0.0, 100.0]).await.unwrap();
+        assert_eq!(sensitivity.break_even_bps, None);
+    }
+
+    #[tokio::test]
+    async fn hourly_data_steps_at_the_configured_frequency() {
+        // Two weeks of hourly bars drifting up
+        let hourly: Vec<MarketData> = (0..=14 * 24)
+            .map(|hour| {
+                let close = dec!(100) + Decimal::from(hour) / dec!(100);
+                MarketData {
+                    timestamp: datetime!(2024-01-01 0:00 UTC) + Duration::hours(hour),
+                    ..daily_bars("X", &[close]).remove(0)
+                }
+            })
+            .collect();
+        let run = |bar_frequency| {
+            let config = BacktestConfig { bar_frequency, ..BacktestConfig::frictionless() };
+            backtest(all_in("X"), hourly.clone(), "2024-01-15").with_config(config).unwrap()
+        };
+
+        let by_hour = run(BarFrequency::Hourly).run().await.unwrap();
+        let by_day = run(BarFrequency::Daily).run().await.unwrap();
+        let by_week = run(BarFrequency::Weekly).run().await.unwrap();
+        assert_eq!(by_hour.equity_curve.len(), 14 * 24 + 1);
+        assert_eq!(by_day.equity_curve.len(), 15);
+        assert_eq!(by_week.equity_curve.len(), 3);
+        assert_eq!(by_hour.data_quality.calendar_days, 14 * 24);
+        // Bars are stamped with their start and close on their last hour
+        let daily = run(BarFrequency::Daily);
+        let days = daily.align(&daily.bars()).unwrap().days;
+        assert_eq!(days[&datetime!(2024-01-02 0:00 UTC)]["X"], dec!(100.47));
+        let weekly = run(BarFrequency::Weekly);
+        let weeks = weekly.align(&weekly.bars()).unwrap().days;
+        assert_eq!(weeks[&datetime!(2024-01-08 0:00 UTC)]["X"], dec!(103.35));
+    }
+
+    #[tokio::test]
+    async fn resampled_hourly_data_backtests_like_daily_data() {
+        // ETH swings between 2000 and 2800 every three days and drifts within each day
+        let mut hourly = vec![];
+        for hour in 0..21 * 24 {
+            let eth = if (hour / 72) % 2 == 0 { dec!(2000) } else { dec!(2800) } + Decimal::from(hour % 24) * dec!(3);
+            let btc = dec!(40000) + Decimal::from((hour * 7) % 50);
+            for (symbol, close) in [("USDC", dec!(1)), ("ETH", eth), ("BTC", btc)] {
+                hourly.push(MarketData {
+                    timestamp: datetime!(2024-01-01 0:00 UTC) + Duration::hours(hour),
+                    symbol: symbol.to_string(),
+                    price: close,
+                    volume: Decimal::from(1_000 + hour % 24 * 10),
+                    high: close * dec!(1.002),
+                    low: close * dec!(0.998),
+                    open: close * dec!(0.999),
+                    close,
+                });
+            }
+        }
+        // The same bars aggregated by hand: first open, highest high, lowest low, last close, summed volume
+        let mut daily: Vec<MarketData> = vec![];
+        for bar in &hourly {
+            let midnight = bar.timestamp.date().midnight().assume_utc();
+            match daily.iter_mut().find(|day| day.timestamp == midnight && day.symbol == bar.symbol) {
+                Some(day) => {
+                    day.high = day.high.max(bar.high);
+                    day.low = day.low.min(bar.low);
+                    day.close = bar.close;
+                    day.price = bar.price;
+                    day.volume += bar.volume;
+                }
+                None => daily.push(MarketData { timestamp: midnight, ..bar.clone() }),
+            }
+        }
+        let run = |bars: Vec<MarketData>| async move {
+            let config = BacktestConfig {
+                bar_frequency: BarFrequency::Daily,
+                slippage: Slippage::VolumeShare { impact_bps: dec!(25) },
+                ..BacktestConfig::default()
+            };
+            let strategy = Strategy::from_name("target_weight").unwrap();
+            let mut engine = BacktestEngine::new("2024-01-01", "2024-01-21", strategy)
+                .unwrap()
+                .with_market_data(bars)
+                .unwrap()
+                .with_config(config)
+                .unwrap();
+            engine.run().await.unwrap()
+        };
+        let resampled = run(hourly).await;
+        let by_day = run(daily).await;
+
+        // Down to the volume-scaled slippage, within the rounding of summing positions in another order
+        let close = |a: Decimal, b: Decimal| (a - b).abs() < dec!(0.000001);
+        assert_eq!(resampled.equity_curve.len(), 21);
+        assert_eq!(resampled.equity_curve.len(), by_day.equity_curve.len());
+        for (a, b) in resampled.equity_curve.iter().zip(&by_day.equity_curve) {
+            assert_eq!(a.timestamp, b.timestamp);
+            assert!(close(a.value, b.value), "{}: {} vs {}", a.timestamp, a.value, b.value);
+        }
+        assert!(resampled.total_slippage > Decimal::ZERO);
+        assert!(close(resampled.total_slippage, by_day.total_slippage));
+        assert!(close(resampled.turnover, by_day.turnover));
+        assert!((resampled.sharpe_ratio - by_day.sharpe_ratio).abs() < 1e-9);
+    }
+
+    fn apy(day: &str, symbol: &str, apy: Decimal) -> YieldObservation {
+        YieldObservation { timestamp: parse_date(day).unwrap(), symbol: symbol.to_string(), apy }
+    }
+
+    #[tokio::test]
+    async fn flat_bond_at_five_percent_returns_its_yield() {
+        let run = |yield_income| async move {
+            let config = BacktestConfig { yield_income, ..BacktestConfig::frictionless() };
+            backtest(all_in("B"), daily_bars("B", &[dec!(100); 366]), "2024-12-31")
+                .with_config(config)
+                .unwrap()
+                .with_yields(vec![apy("2024-01-01", "B", dec!(0.05))])
+                .unwrap()
+                .run()
+                .await
+                .unwrap()
+        };
+        let credited = run(YieldIncome::Cash).await;
+        let reinvested = run(YieldIncome::Reinvest).await;
+
+        // All of it income, on the share of the book the strategy invests
+        assert!(credited.total_return_pct > 4.9 && credited.total_return_pct <= 5.0, "{}", credited.total_return_pct);
+        assert!(credited.price_return_pct.abs() < 1e-9);
+        assert!((credited.income_return_pct - credited.total_return_pct).abs() < 1e-9);
+        // Reinvesting compounds it
+        assert!(reinvested.total_return_pct > credited.total_return_pct + 0.1);
+        assert!(reinvested.price_return_pct.abs() < 1e-9);
+    }
+
+    #[test]
+    fn yield_schedule_holds_each_rate_until_the_next() {
+        let schedule = YieldSchedule::new(&[
+            apy("2024-01-10", "B", dec!(0.04)),
+            apy("2024-02-01", "B", dec!(0.06)),
+            apy("2024-01-01", "C", dec!(0.03)),
+        ]);
+        let rate = |symbol, day| schedule.rate(symbol, parse_date(day).unwrap());
+        assert_eq!(rate("B", "2024-01-09"), Decimal::ZERO);
+        assert_eq!(rate("B", "2024-01-10"), dec!(0.04));
+        assert_eq!(rate("B", "2024-01-31"), dec!(0.04));
+        assert_eq!(rate("B", "2024-06-01"), dec!(0.06));
+        assert_eq!(rate("C", "2024-06-01"), dec!(0.03));
+        assert_eq!(rate("D", "2024-06-01"), Decimal::ZERO);
+    }
+
+    #[test]
+    fn yields_after_the_end_are_rejected() {
+        let engine = backtest(all_in("B"), daily_bars("B", &[dec!(100); 3]), "2024-01-03");
+        assert!(engine.with_yields(vec![apy("2024-01-04", "B", dec!(0.05))]).is_err());
+    }
+
+    /// A book of `cash` and `units` of each symbol, stored at `price`
+    fn book(cash: Decimal, holdings: &[(&str, Decimal, Decimal)]) -> Portfolio {
+        let invested: Decimal = holdings.iter().map(|(_, units, price)| units * price).sum();
+        let mut portfolio = Portfolio::new(cash + invested);
+        for (symbol, units, price) in holdings {
+            let asset = Asset {
+                symbol: symbol.to_string(),
+                name: symbol.to_string(),
+                asset_type: AssetType::Crypto,
+                current_price: *price,
+                volatility: Decimal::ZERO,
+                yield_rate: Decimal::ZERO,
+                expected_return: Decimal::ZERO,
+                bond: None,
+                liquidity: None,
+            };
+            portfolio.add_position(Position::new(asset, *units, *price));
+        }
+        portfolio
+    }
+
+    #[tokio::test]
+    async fn seeded_positions_are_repriced_and_their_pnl_split_out() {
+        // Stored at 50, X opens the backtest at 80 and ends at 100
+        let closes: Vec<Decimal> = (0..=4).map(|day| dec!(80) + Decimal::from(day * 5)).collect();
+        let seeded = book(Decimal::ZERO, &[("X", dec!(100), dec!(50))]);
+        let results = backtest(all_in("X"), daily_bars("X", &closes), "2024-01-05")
+            .with_initial_portfolio(seeded)
+            .run()
+            .await
+            .unwrap();
+
+        let contribution = results.initial_portfolio.unwrap();
+        assert_eq!(results.initial_value, dec!(8000));
+        assert_eq!(contribution.positions_value, dec!(8000));
+        assert_eq!(contribution.positions_pnl, dec!(2000));
+        // Already all in X, the strategy never trades
+        assert_eq!(contribution.decisions_pnl, Decimal::ZERO);
+        assert_eq!(results.final_value, dec!(10000));
+    }
+
+    #[tokio::test]
+    async fn buying_beside_a_seeded_position_is_a_decision() {
+        let mut bars = daily_bars("X", &[dec!(100), dec!(100), dec!(90), dec!(80)]);
+        bars.extend(daily_bars("Y", &[dec!(10), dec!(10), dec!(11), dec!(12)]));
+        let seeded = book(dec!(1000), &[("X", dec!(10), dec!(100))]);
+        let results = backtest(all_in("Y"), bars, "2024-01-04").with_initial_portfolio(seeded).run().await.unwrap();
+
+        let contribution = results.initial_portfolio.unwrap();
+        assert_eq!(contribution.positions_pnl, dec!(-200));
+        // The cash went into Y, up 20%, less what the strategy holds back
+        let decisions = contribution.decisions_pnl;
+        assert!(decisions > dec!(199) && decisions <= dec!(200), "{}", decisions);
+        assert_eq!(contribution.positions_pnl + decisions, results.final_value - results.initial_value);
+    }
+
+    #[tokio::test]
+    async fn unpriced_seeded_positions_fail_or_freeze() {
+        let seeded = || book(dec!(100), &[("X", dec!(1), dec!(100)), ("Z", dec!(2), dec!(50))]);
+        let bars = || daily_bars("X", &[dec!(100), dec!(110), dec!(120)]);
+        let error = backtest(all_in("X"), bars(), "2024-01-03").with_initial_portfolio(seeded()).run().await;
+        assert!(error.unwrap_err().to_string().contains("holds Z, which the market data has no price for"));
+
+        let config = BacktestConfig { unpriced_positions: UnpricedPositions::Freeze, ..BacktestConfig::frictionless() };
+        let engine = backtest(all_in("X"), bars(), "2024-01-03").with_config(config).unwrap();
+        let results = engine.with_initial_portfolio(seeded()).run().await.unwrap();
+        let contribution = results.initial_portfolio.unwrap();
+        assert_eq!(contribution.frozen_symbols, ["Z"]);
+        assert_eq!(contribution.positions_value, dec!(200));
+        assert_eq!(results.initial_value, dec!(300));
+        // Z held at its stored price adds nothing
+        assert_eq!(contribution.positions_pnl, dec!(20));
+    }
+
+    #[tokio::test]
+    async fn open_trades_leave_their_exit_columns_empty() {
+        let mut engine = backtest(all_in("X"), daily_bars("X", &[dec!(100), dec!(100), dec!(110)]), "2024-01-03");
+        let results = engine.run().await.unwrap();
+        let path = std::env::temp_dir().join(format!("vaulta-tape-{}.csv", uuid::Uuid::new_v4()));
+        results.write_trades_csv(&path).unwrap();
+        let written = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let written = written.unwrap();
+        let rows: Vec<Vec<&str>> = written.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][0], "2024-01-02T00:00:00Z");
+        assert_eq!(rows[1][2], "X");
+        assert_eq!(rows[1][4], "100.00000000");
+        assert_eq!([rows[1][1], rows[1][5], rows[1][7], rows[1][8], rows[1][9]], [""; 5]);
+
+        // No event log unless one was recorded
+        let error = results.write_events_jsonl(&path).unwrap_err();
+        assert!(error.to_string().contains("record_events"));
+        assert!(!path.exists());
+    }
+
+    fn halves() -> Strategy {
+        Strategy::target_weight(HashMap::from([("X".to_string(), dec!(0.5)), ("Y".to_string(), dec!(0.5))]))
+    }
+
+    #[tokio::test]
+    async fn sweep_cells_match_standalone_backtests() {
+        let closes: Vec<Decimal> = (0..30).map(|day| if day % 2 == 0 { dec!(100) } else { dec!(130) }).collect();
+        let mut bars = daily_bars("X", &closes);
+        bars.extend(daily_bars("Y", &[dec!(100); 30]));
+        let engine = backtest(halves(), bars, "2024-01-30");
+        let sweep = BacktestSweep::new(BacktestAxis::new(BacktestParameter::CommissionBps, vec![0.0, 20.0]))
+            .with_y_axis(BacktestAxis::new(BacktestParameter::RebalanceDays, vec![0.0, 10.0]));
+        let results = engine.sweep(&sweep).await.unwrap();
+
+        let grid: Vec<(f64, Option<f64>)> = results.cells.iter().map(|cell| (cell.x, cell.y)).collect();
+        assert_eq!(grid, [(0.0, Some(0.0)), (20.0, Some(0.0)), (0.0, Some(10.0)), (20.0, Some(10.0))]);
+        for cell in &results.cells {
+            let config = BacktestConfig {
+                commission_bps: Decimal::try_from(cell.x).unwrap(),
+                rebalance_days: Some(cell.y.unwrap() as u32).filter(|days| *days > 0),
+                ..BacktestConfig::frictionless()
+            };
+            let mut standalone = engine.window(engine.start_date, engine.end_date, halves());
+            standalone = standalone.with_config(config).unwrap();
+            let standalone = standalone.run().await.unwrap();
+            assert_eq!(cell.total_return_pct, standalone.total_return_pct);
+            assert_eq!(cell.turnover, standalone.turnover);
+        }
+
+        let matrix = results.matrix(BacktestMetric::TotalReturn);
+        assert_eq!(matrix.len(), 2);
+        assert!(matrix.iter().all(|row| row.len() == 2 && row[1] < row[0]));
+        let path = std::env::temp_dir().join(format!("vaulta-sweep-{}.csv", uuid::Uuid::new_v4()));
+        results.write_csv(&path, BacktestMetric::Turnover).unwrap();
+        let written = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+        let written = written.unwrap();
+        let rows: Vec<Vec<&str>> = written.lines().map(|line| line.split(',').collect()).collect();
+        assert_eq!(rows[0], ["rebalance_days\\commission_bps", "0", "20"]);
+        assert_eq!(rows[2][0], "10");
+        assert_eq!(rows[2][1], results.cells[2].turnover.to_f64().unwrap().to_string());
+    }
+
+    #[tokio::test]
+    async fn bad_sweeps_are_rejected_before_running() {
+        let engine = backtest(halves(), daily_bars("X", &[dec!(100); 3]), "2024-01-03");
+        let axis = |parameter, values: &[f64]| BacktestAxis::new(parameter, values.to_vec());
+        let bad = [
+            BacktestSweep::new(axis(BacktestParameter::CommissionBps, &[])),
+            BacktestSweep::new(axis(BacktestParameter::CommissionBps, &[f64::NAN])),
+            BacktestSweep::new(axis(BacktestParameter::CommissionBps, &[-5.0])),
+            BacktestSweep::new(axis(BacktestParameter::RebalanceDays, &[1.5])),
+            // Target weights allocate by their targets, not a cash fraction
+            BacktestSweep::new(axis(BacktestParameter::AllocationFraction, &[0.5])),
+            BacktestSweep::new(axis(BacktestParameter::SlippageBps, &[1.0]))
+                .with_y_axis(axis(BacktestParameter::SlippageBps, &[2.0])),
+        ];
+        for sweep in &bad {
+            assert!(engine.sweep(sweep).await.is_err(), "{:?} should be rejected", sweep);
+        }
+        assert!(engine.sweep(&BacktestSweep::new(axis(BacktestParameter::CommissionBps, &[5.0]))).await.is_ok());
+    }
+
+    #[test]
+    fn sweep_parameters_and_metrics_round_trip_their_names() {
+        for parameter in [
+            BacktestParameter::AllocationFraction,
+            BacktestParameter::RebalanceDays,
+            BacktestParameter::CommissionBps,
+            BacktestParameter::SlippageBps,
+        ] {
+            assert_eq!(BacktestParameter::from_name(parameter.name()).unwrap(), parameter);
+        }
+        assert_eq!(BacktestParameter::from_name("Rebalance").unwrap(), BacktestParameter::RebalanceDays);
+        assert!(BacktestParameter::from_name("leverage").is_err());
+        for metric in [BacktestMetric::TotalReturn, BacktestMetric::Sortino, BacktestMetric::Costs] {
+            assert_eq!(BacktestMetric::from_name(metric.name()).unwrap(), metric);
+        }
+        assert!(BacktestMetric::from_name("alpha").is_err());
     }
 }
@@ -0,0 +1,274 @@
+//! Block-bootstrap price paths from historical returns.
+//!
+//! Instead of assuming a parametric walk, [`BlockBootstrap`] resamples
+//! overlapping blocks of observed log returns (the moving-block bootstrap).
+//! Blocks keep short-range autocorrelation, such as volatility clustering,
+//! within each block. Every symbol uses the same blocks, which preserves
+//! cross-asset correlation.
+
+use crate::types::{MarketData, PricePath};
+use anyhow::Result;
+use rand::Rng;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+/// Historical log returns per symbol, resampled in blocks into price paths
+#[derive(Debug, Clone)]
+pub struct BlockBootstrap {
+    /// Log returns per symbol, aligned so index `t` is the same period for every symbol
+    returns: HashMap<String, Vec<f64>>,
+    /// Price each path starts from per symbol
+    start_prices: HashMap<String, Decimal>,
+    block_length: usize,
+}
+
+impl BlockBootstrap {
+    /// Bootstrap from per-symbol log return series.
+    ///
+    /// Series are aligned on their most recent end and truncated to the
+    /// shortest, so every index refers to the same period across symbols.
+    pub fn from_returns(
+        returns: HashMap<String, Vec<f64>>,
+        start_prices: HashMap<String, Decimal>,
+        block_length: usize,
+    ) -> Result<Self> {
+        let periods = returns.values().map(Vec::len).min().unwrap_or(0);
+        if periods == 0 {
+            return Err(anyhow::anyhow!("Bootstrap needs at least one return per symbol"));
+        }
+        if block_length == 0 || block_length > periods {
+            return Err(anyhow::anyhow!(
+                "Block length must be between 1 and the {} periods of history, got {}",
+                periods,
+                block_length
+            ));
+        }
+        if let Some(symbol) = returns
+            .keys()
+            .find(|symbol| !matches!(start_prices.get(*symbol), Some(price) if *price > Decimal::ZERO))
+        {
+            return Err(anyhow::anyhow!("No positive start price for {}", symbol));
+        }
+        if returns.values().flatten().any(|r| !r.is_finite()) {
+            return Err(anyhow::anyhow!("Bootstrap returns must be finite"));
+        }
+        let returns = returns
+            .into_iter()
+            .map(|(symbol, series)| {
+                let recent = series[series.len() - periods..].to_vec();
+                (symbol, recent)
+            })
+            .collect();
+        Ok(Self {
+            returns,
+            start_prices,
+            block_length,
+        })
+    }
+
+    /// Bootstrap from market data: log returns of each symbol's closes in time
+    /// order, with paths starting from the latest close
+    pub fn from_market_data(data: &[MarketData], block_length: usize) -> Result<Self> {
+        let mut by_symbol: HashMap<&str, Vec<&MarketData>> = HashMap::new();
+        for bar in data {
+            by_symbol.entry(bar.symbol.as_str()).or_default().push(bar);
+        }
+
+        let mut returns = HashMap::new();
+        let mut start_prices = HashMap::new();
+        for (symbol, mut bars) in by_symbol {
+            bars.sort_by_key(|bar| bar.timestamp);
+            let series: Vec<f64> = bars
+                .windows(2)
+                .filter_map(|pair| {
+                    let previous = pair[0].close.to_f64()?;
+                    let current = pair[1].close.to_f64()?;
+                    (previous > 0.0 && current > 0.0).then(|| (current / previous).ln())
+                })
+                .collect();
+            if let Some(last) = bars.last() {
+                start_prices.insert(symbol.to_string(), last.close);
+            }
+            returns.insert(symbol.to_string(), series);
+        }
+        Self::from_returns(returns, start_prices, block_length)
+    }
+
+    pub fn block_length(&self) -> usize {
+        self.block_length
+    }
+
+    /// Periods of history available to resample
+    pub fn periods(&self) -> usize {
+        self.returns.values().map(Vec::len).next().unwrap_or(0)
+    }
+
+    pub fn symbols(&self) -> Vec<&str> {
+        self.returns.keys().map(String::as_str).collect()
+    }
+
+    /// A `steps`-long price path built from blocks starting at uniformly drawn periods
+    pub fn sample_path<R: Rng + ?Sized>(&self, steps: usize, rng: &mut R) -> PricePath {
+        let last_start = self.periods() - self.block_length;
+        let mut periods = Vec::with_capacity(steps);
+        while periods.len() < steps {
+            let start = rng.gen_range(0..=last_start);
+            let take = self.block_length.min(steps - periods.len());
+            periods.extend(start..start + take);
+        }
+
+        let mut path = PricePath::new("bootstrap");
+        for (symbol, series) in &self.returns {
+            let start = self.start_prices[symbol].to_f64().unwrap_or(0.0);
+            let mut log_price = start.ln();
+            let prices = periods
+                .iter()
+                .map(|&t| {
+                    log_price += series[t];
+                    Decimal::try_from(log_price.exp()).unwrap_or(Decimal::ZERO)
+                })
+                .collect();
+            path.prices.insert(symbol.clone(), prices);
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monte_carlo::{MonteCarloEngine, ScenarioSource};
+    use crate::strategy::Strategy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use rust_decimal_macros::dec;
+
+    /// Returns that name their period: `t` is `(t + 1) / 1000` for X and its negation for Y
+    fn labelled(periods: usize, block_length: usize) -> BlockBootstrap {
+        let x: Vec<f64> = (0..periods).map(|t| (t + 1) as f64 / 1000.0).collect();
+        let y: Vec<f64> = x.iter().map(|r| -r).collect();
+        BlockBootstrap::from_returns(
+            HashMap::from([("X".to_string(), x), ("Y".to_string(), y)]),
+            HashMap::from([("X".to_string(), dec!(100)), ("Y".to_string(), dec!(50))]),
+            block_length,
+        )
+        .unwrap()
+    }
+
+    /// Source periods a path's steps were drawn from, read back off its prices
+    fn periods_of(path: &PricePath, symbol: &str, start: f64, sign: f64) -> Vec<usize> {
+        let mut previous = start;
+        path.prices[symbol]
+            .iter()
+            .map(|price| {
+                let price = price.to_f64().unwrap();
+                let period = (sign * (price / previous).ln() * 1000.0).round() as usize - 1;
+                previous = price;
+                period
+            })
+            .collect()
+    }
+
+    #[test]
+    fn paths_are_built_from_contiguous_blocks_of_history() {
+        let bootstrap = labelled(50, 5);
+        assert_eq!(bootstrap.periods(), 50);
+        assert_eq!(bootstrap.block_length(), 5);
+        let mut rng = StdRng::seed_from_u64(9);
+        for _ in 0..20 {
+            let path = bootstrap.sample_path(23, &mut rng);
+            assert_eq!(path.steps(), 23);
+            let periods = periods_of(&path, "X", 100.0, 1.0);
+            // Every symbol resamples the same periods
+            assert_eq!(periods_of(&path, "Y", 50.0, -1.0), periods);
+            // Four full blocks, then the three steps left
+            let blocks: Vec<&[usize]> = periods.chunks(5).collect();
+            assert_eq!(blocks.iter().map(|block| block.len()).collect::<Vec<_>>(), vec![5, 5, 5, 5, 3]);
+            for block in blocks {
+                assert!(block.windows(2).all(|pair| pair[1] == pair[0] + 1), "{:?}", block);
+                assert!(block[0] + 5 <= 50);
+            }
+        }
+    }
+
+    #[test]
+    fn a_block_as_long_as_the_history_replays_it() {
+        let bootstrap = labelled(10, 10);
+        let path = bootstrap.sample_path(10, &mut StdRng::seed_from_u64(1));
+        assert_eq!(periods_of(&path, "X", 100.0, 1.0), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn series_align_on_their_most_recent_periods() {
+        let bootstrap = BlockBootstrap::from_returns(
+            HashMap::from([("X".to_string(), vec![0.5, 0.001, 0.002]), ("Y".to_string(), vec![0.003, 0.004])]),
+            HashMap::from([("X".to_string(), dec!(1)), ("Y".to_string(), dec!(1))]),
+            2,
+        )
+        .unwrap();
+        assert_eq!(bootstrap.periods(), 2);
+        let path = bootstrap.sample_path(2, &mut StdRng::seed_from_u64(1));
+        let x = path.prices["X"][1].to_f64().unwrap();
+        assert!((x - 0.003f64.exp()).abs() < 1e-12, "{}", x);
+    }
+
+    #[test]
+    fn invalid_histories_are_rejected() {
+        let build = |returns: Vec<f64>, start: Decimal, block_length: usize| {
+            BlockBootstrap::from_returns(
+                HashMap::from([("X".to_string(), returns)]),
+                HashMap::from([("X".to_string(), start)]),
+                block_length,
+            )
+        };
+        assert!(build(vec![0.01, 0.02], dec!(1), 2).is_ok());
+        assert!(build(vec![], dec!(1), 1).is_err());
+        assert!(build(vec![0.01, 0.02], dec!(1), 0).is_err());
+        assert!(build(vec![0.01, 0.02], dec!(1), 3).is_err());
+        assert!(build(vec![0.01, f64::NAN], dec!(1), 1).is_err());
+        assert!(build(vec![0.01, 0.02], Decimal::ZERO, 1).is_err());
+    }
+
+    #[test]
+    fn market_data_bootstraps_from_closes_in_time_order() {
+        let start = time::macros::datetime!(2024-01-01 0:00 UTC);
+        let bar = |day: i64, close: Decimal| MarketData {
+            timestamp: start + time::Duration::days(day),
+            symbol: "X".to_string(),
+            price: close,
+            volume: Decimal::ZERO,
+            high: close,
+            low: close,
+            open: close,
+            close,
+        };
+        // Out of order on purpose
+        let data = [bar(2, dec!(110)), bar(0, dec!(100)), bar(1, dec!(120))];
+        let bootstrap = BlockBootstrap::from_market_data(&data, 2).unwrap();
+        assert_eq!(bootstrap.periods(), 2);
+        // Replays 100 -> 120 -> 110 from the latest close of 110
+        let path = bootstrap.sample_path(2, &mut StdRng::seed_from_u64(1));
+        let prices: Vec<f64> = path.prices["X"].iter().map(|price| price.to_f64().unwrap()).collect();
+        assert!((prices[0] - 132.0).abs() < 1e-9 && (prices[1] - 121.0).abs() < 1e-9, "{:?}", prices);
+    }
+
+    #[tokio::test]
+    async fn monte_carlo_records_the_bootstrap_source() {
+        let mut engine = MonteCarloEngine::builder()
+            .iterations(16)
+            .steps(20)
+            .strategy(Strategy::target_weight(HashMap::from([("X".to_string(), Decimal::ONE)])))
+            .bootstrap(labelled(50, 5))
+            .build()
+            .unwrap();
+        let results = engine.run().await.unwrap();
+        assert_eq!(results.scenario_source, ScenarioSource::Bootstrap);
+        assert_eq!(results.iterations, 16);
+        assert_eq!(results.steps_per_iteration, 20);
+        assert_eq!(results.scenarios_generated, 0);
+        // Resampled paths differ from one another
+        let finals = results.distribution.unwrap();
+        assert!(finals.iter().any(|value| *value != finals[0]));
+    }
+}
@@ -0,0 +1,443 @@
+//! Fluent construction of a [`Simulator`].
+//!
+//! ```rust,no_run
+//! use vaulta_simulator::{SimulatorBuilder, Strategy};
+//!
+//! let mut simulator = SimulatorBuilder::new()
+//!     .capital(5_000_000.0)
+//!     .strategy(Strategy::risk_parity())
+//!     .seed(7)
+//!     .build()?;
+//!
+//! simulator.step()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Pricing new positions from a market data provider, with hourly steps and
+//! interest on idle cash:
+//!
+//! ```rust,no_run
+//! use rust_decimal_macros::dec;
+//! use vaulta_simulator::market::MockMarketDataProvider;
+//! use vaulta_simulator::{RiskParameters, SimulatorBuilder, Strategy};
+//!
+//! let simulator = SimulatorBuilder::new()
+//!     .capital(1_000_000.0)
+//!     .strategy(Strategy::balanced())
+//!     .provider(MockMarketDataProvider::new())
+//!     .risk_parameters(RiskParameters {
+//!         max_leverage: 2.0,
+//!         ..RiskParameters::default()
+//!     })
+//!     .time_step(time::Duration::hours(1))
+//!     .cash_rate(dec!(0.04))
+//!     .build()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::calendar::{TradingCalendar, TradingSession};
+use crate::fees::FeeModel;
+use crate::liquidity::PriceImpact;
+use crate::market::MarketDataProvider;
+use crate::scenarios::RegimeModel;
+use crate::shocks::{JumpConfig, ShockDistribution};
+use crate::simulator::{
+    Benchmark, BoxedProvider, CircuitBreaker, DecisionFailurePolicy, HistoryPolicy, Simulator,
+    SimulatorConfig, TradingRules, YieldIncome,
+};
+use crate::strategy::Strategy;
+use crate::types::*;
+use crate::yield_curve::YieldCurve;
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+
+/// Builder for [`Simulator`]
+pub struct SimulatorBuilder {
+    capital: f64,
+    strategy: Option<Strategy>,
+    provider: Option<BoxedProvider>,
+    /// Universe symbols to describe from the provider at build time
+    universe_symbols: Vec<String>,
+    config: SimulatorConfig,
+}
+
+impl SimulatorBuilder {
+    /// Defaults: $1M capital, no provider, and `SimulatorConfig::default()`
+    pub fn new() -> Self {
+        Self {
+            capital: 1_000_000.0,
+            strategy: None,
+            provider: None,
+            universe_symbols: vec![],
+            config: SimulatorConfig::default(),
+        }
+    }
+
+    pub fn capital(mut self, capital: f64) -> Self {
+        self.capital = capital;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Market data provider used to price and describe newly opened positions
+    pub fn provider<P>(mut self, provider: P) -> Self
+    where
+        P: MarketDataProvider + Send + Sync + 'static,
+    {
+        self.provider = Some(Box::new(provider));
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    pub fn risk_parameters(mut self, risk_parameters: RiskParameters) -> Self {
+        self.config.risk_parameters = risk_parameters;
+        self
+    }
+
+    /// Simulated time at step 0 (defaults to now)
+    pub fn start_time(mut self, start_time: OffsetDateTime) -> Self {
+        self.config.start_time = Some(start_time);
+        self
+    }
+
+    /// Simulated time covered by one step
+    pub fn time_step(mut self, time_step: Duration) -> Self {
+        self.config.time_step = time_step;
+        self
+    }
+
+    /// Skip closed days and annualize over trading days
+    pub fn calendar(mut self, calendar: TradingCalendar) -> Self {
+        self.config.calendar = Some(calendar);
+        self
+    }
+
+    /// Override whether `symbol` trades 24/7 or only on calendar days
+    pub fn session(mut self, symbol: &str, session: TradingSession) -> Self {
+        self.config.sessions.insert(symbol.to_string(), session);
+        self
+    }
+
+    /// Shape of every asset's per-step price shock
+    pub fn shock_distribution(mut self, distribution: ShockDistribution) -> Self {
+        self.config.shock_distribution = distribution;
+        self
+    }
+
+    /// Use a different shock distribution for `symbol`, e.g. fatter tails for one volatile asset
+    pub fn shock_distribution_for(mut self, symbol: &str, distribution: ShockDistribution) -> Self {
+        self.config.shock_distributions.insert(symbol.to_string(), distribution);
+        self
+    }
+
+    /// Sudden price jumps on top of the diffusion, per asset type and market-wide
+    pub fn jumps(mut self, jumps: JumpConfig) -> Self {
+        self.config.jumps = jumps;
+        self
+    }
+
+    /// Switch the market between regimes with their own drift, volatility and correlation
+    pub fn regimes(mut self, regimes: RegimeModel) -> Self {
+        self.config.regimes = Some(regimes);
+        self
+    }
+
+    /// Annual interest rate earned on idle cash
+    pub fn cash_rate(mut self, cash_rate: Decimal) -> Self {
+        self.config.cash_rate = cash_rate;
+        self
+    }
+
+    /// Credit positions' yield income to cash or reinvest it
+    pub fn yield_income(mut self, treatment: YieldIncome) -> Self {
+        self.config.yield_income = treatment;
+        self
+    }
+
+    /// Which portfolio snapshots to keep in memory
+    pub fn history_policy(mut self, history_policy: HistoryPolicy) -> Self {
+        self.config.history_policy = history_policy;
+        self
+    }
+
+    /// Compute execution costs with `fee_model` instead of the strategy's quotes
+    pub fn fee_model<F>(mut self, fee_model: F) -> Self
+    where
+        F: FeeModel + 'static,
+    {
+        self.config.fee_model = Some(Arc::new(fee_model));
+        self
+    }
+
+    /// Charge `impact` on trades in symbols with known liquidity, on top of their execution cost
+    pub fn price_impact(mut self, impact: PriceImpact) -> Self {
+        self.config.price_impact = Some(impact);
+        self
+    }
+
+    /// Currency cash and values are reported in (default `USD`)
+    pub fn reporting_currency(mut self, currency: &str) -> Self {
+        self.config.reporting_currency = currency.to_string();
+        self
+    }
+
+    /// Opening cash held in `currency` alongside the reporting-currency capital
+    pub fn cash_balance(mut self, currency: &str, amount: Decimal) -> Self {
+        self.config.cash_balances.insert(currency.to_string(), amount);
+        self
+    }
+
+    /// Compare the strategy against a passive benchmark on the same price paths
+    pub fn benchmark(mut self, benchmark: Benchmark) -> Self {
+        self.config.benchmark = Some(benchmark);
+        self
+    }
+
+    /// How decisions that fail to execute are handled (default: skip them)
+    pub fn failure_policy(mut self, policy: DecisionFailurePolicy) -> Self {
+        self.config.failure_policy = policy;
+        self
+    }
+
+    /// Halt buying once drawdown from peak exceeds the breaker's threshold
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.config.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Reject decisions that would open more than `max` positions
+    pub fn max_positions(mut self, max: usize) -> Self {
+        self.config.max_positions = Some(max);
+        self
+    }
+
+    /// Minimum notional and lot size for `symbol`
+    pub fn trading_rules(mut self, symbol: &str, rules: TradingRules) -> Self {
+        self.config.trading_rules.insert(symbol.to_string(), rules);
+        self
+    }
+
+    /// Keep a full transaction log (off by default)
+    pub fn record_transactions(mut self, record: bool) -> Self {
+        self.config.record_transactions = record;
+        self
+    }
+
+    /// Store per-position detail in every snapshot (off by default)
+    pub fn record_positions(mut self, record: bool) -> Self {
+        self.config.record_positions = record;
+        self
+    }
+
+    /// Record each symbol's price path, exposed as `SimulationResults::price_history`
+    pub fn record_price_history(mut self, record: bool) -> Self {
+        self.config.record_price_history = record;
+        self
+    }
+
+    /// Run the strategy at most once per `interval` of simulated time
+    pub fn rebalance_interval(mut self, interval: Duration) -> Self {
+        self.config.rebalance_interval = Some(interval);
+        self
+    }
+
+    /// Count Sortino shortfalls below `rate` a year instead of zero
+    pub fn minimum_acceptable_return(mut self, rate: f64) -> Self {
+        self.config.minimum_acceptable_return = rate;
+        self
+    }
+
+        /// Track rolling Sharpe and volatility over the last `window` steps
+    pub fn rolling_window(mut self, window: usize) -> Self {
+        self.config.rolling_window = Some(window);
+        self
+    }
+
+    /// Assets priced and evolved from step 0, before any are held
+    pub fn universe(mut self, assets: Vec<Asset>) -> Self {
+        self.config.universe.extend(assets);
+        self
+    }
+
+    /// Pairwise return correlations for the correlated price shocks; missing pairs
+    /// use the default correlation
+    pub fn correlations(mut self, correlations: HashMap<(String, String), f64>) -> Self {
+        self.config.correlations.extend(correlations);
+        self
+    }
+
+    /// Reprice bond assets on `curve` under curve shifts, instead of the provider's curve
+    pub fn yield_curve(mut self, curve: YieldCurve) -> Self {
+        self.config.yield_curve = Some(curve);
+        self
+    }
+
+    /// Add universe assets described by the provider (price, volatility, yield) at build time
+    pub fn universe_from_provider(mut self, symbols: &[&str]) -> Self {
+        self.universe_symbols.extend(symbols.iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Replace the whole configuration, keeping capital, strategy, and provider
+    pub fn config(mut self, config: SimulatorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Validate the configuration and build the simulator
+    pub fn build(mut self) -> Result<Simulator> {
+        self.resolve_universe()?;
+        if self.config.yield_curve.is_none() {
+            self.config.yield_curve = self.provider.as_deref().and_then(|provider| provider.get_yield_curve().ok());
+        }
+        self.validate()?;
+        Ok(self.assemble())
+    }
+
+    /// Describe the provider-backed universe symbols as assets
+    fn resolve_universe(&mut self) -> Result<()> {
+        if self.universe_symbols.is_empty() {
+            return Ok(());
+        }
+        let provider = self
+            .provider
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("universe_from_provider requires a provider"))?;
+        for symbol in std::mem::take(&mut self.universe_symbols) {
+            let asset = crate::market::describe_asset(provider, &symbol)
+                .with_context(|| format!("Failed to describe universe asset {}", symbol))?;
+            self.config.universe.push(asset);
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.capital.is_finite() || self.capital < 0.0 {
+            return Err(anyhow::anyhow!(
+                "Initial capital must be a non-negative number, got {}",
+                self.capital
+            ));
+        }
+        if self.strategy.is_none() {
+            return Err(anyhow::anyhow!("A strategy is required"));
+        }
+        if self.config.time_step <= Duration::ZERO {
+            return Err(anyhow::anyhow!("Time step must be positive, got {}", self.config.time_step));
+        }
+        if matches!(
+            self.config.history_policy,
+            HistoryPolicy::EveryN(0) | HistoryPolicy::LastN(0)
+        ) {
+            return Err(anyhow::anyhow!("History policy size must be at least 1"));
+        }
+        if self.config.rolling_window == Some(0) {
+            return Err(anyhow::anyhow!("Rolling window must be at least 1 step"));
+        }
+        if self.config.risk_parameters.max_leverage <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "Max leverage must be positive, got {}",
+                self.config.risk_parameters.max_leverage
+            ));
+        }
+        if let Some(breaker) = &self.config.circuit_breaker {
+            if breaker.max_drawdown_pct.is_nan() || breaker.max_drawdown_pct <= 0.0 {
+                return Err(anyhow::anyhow!(
+                    "Circuit breaker drawdown must be positive, got {}",
+                    breaker.max_drawdown_pct
+                ));
+            }
+        }
+        for (symbol, rules) in &self.config.trading_rules {
+            if rules.min_notional < Decimal::ZERO {
+                return Err(anyhow::anyhow!("Minimum notional for {} must not be negative", symbol));
+            }
+            if rules.lot_size.is_some_and(|lot| lot <= Decimal::ZERO) {
+                return Err(anyhow::anyhow!("Lot size for {} must be positive", symbol));
+            }
+        }
+        if self.config.default_price_threshold < Decimal::ZERO {
+            return Err(anyhow::anyhow!(
+                "Default price threshold must not be negative, got {}",
+                self.config.default_price_threshold
+            ));
+        }
+        if self.config.cash_rate < Decimal::ZERO {
+            return Err(anyhow::anyhow!(
+                "Cash rate must not be negative, got {}",
+                self.config.cash_rate
+            ));
+        }
+        for asset in &self.config.universe {
+            if asset.current_price <= Decimal::ZERO {
+                return Err(anyhow::anyhow!(
+                    "Universe asset {} must have a positive price, got {}",
+                    asset.symbol,
+                    asset.current_price
+                ));
+            }
+        }
+        if !self.config.volatility_multiplier.is_finite() || self.config.volatility_multiplier < 0.0 {
+            return Err(anyhow::anyhow!(
+                "Volatility multiplier must be non-negative, got {}",
+                self.config.volatility_multiplier
+            ));
+        }
+        if !(-1.0..1.0).contains(&self.config.default_correlation) {
+            return Err(anyhow::anyhow!(
+                "Default correlation must be in [-1, 1), got {}",
+                self.config.default_correlation
+            ));
+        }
+        self.config.shock_distribution.validate()?;
+        for (symbol, distribution) in &self.config.shock_distributions {
+            distribution
+                .validate()
+                .with_context(|| format!("Invalid shock distribution for {}", symbol))?;
+        }
+        self.config.jumps.validate()?;
+        if let Some(regimes) = &self.config.regimes {
+            regimes.validate()?;
+        }
+        if self.config.max_positions == Some(0) {
+            return Err(anyhow::anyhow!("Max positions must be at least 1"));
+        }
+        for (currency, balance) in &self.config.cash_balances {
+            if *balance < Decimal::ZERO {
+                return Err(anyhow::anyhow!(
+                    "Cash balance in {} must not be negative, got {}",
+                    currency,
+                    balance
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build without validation; used by `Simulator::new` to keep its infallible signature
+    pub(crate) fn assemble(self) -> Simulator {
+        let portfolio = Simulator::opening_portfolio(self.capital, &self.config);
+        Simulator::from_parts(
+            portfolio,
+            self.strategy.unwrap_or_else(Strategy::balanced),
+            self.config,
+            self.provider,
+        )
+    }
+}
+
+impl Default for SimulatorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,63 @@
+use crate::types::*;
+use std::collections::HashSet;
+use time::{Date, Duration, OffsetDateTime, Weekday};
+
+/// Trading days in a year on a business-day calendar
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Calendar days in a year for assets that trade around the clock
+pub const CALENDAR_DAYS_PER_YEAR: f64 = 365.0;
+
+/// When an asset's price can move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradingSession {
+    /// Trades 24/7; prices move over closed days too
+    Continuous,
+    /// Trades only on calendar days; prices hold flat while the market is closed
+    CalendarBound,
+}
+
+impl TradingSession {
+    /// Default session for an asset type: crypto and on-chain assets never close
+    pub fn for_asset_type(asset_type: &AssetType) -> Self {
+        match asset_type {
+            AssetType::Crypto | AssetType::DeFiPool | AssetType::Stablecoin => Self::Continuous,
+            AssetType::RWABond | AssetType::RWACredit | AssetType::Other => Self::CalendarBound,
+        }
+    }
+}
+
+/// Weekday-only calendar with optional holidays
+#[derive(Debug, Clone, Default)]
+pub struct TradingCalendar {
+    holidays: HashSet<Date>,
+}
+
+impl TradingCalendar {
+    /// Monday to Friday, no holidays
+    pub fn weekdays() -> Self {
+        Self::default()
+    }
+
+    pub fn with_holidays(holidays: impl IntoIterator<Item = Date>) -> Self {
+        Self {
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn add_holiday(&mut self, date: Date) {
+        self.holidays.insert(date);
+    }
+
+    pub fn is_trading_day(&self, date: Date) -> bool {
+        !matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday) && !self.holidays.contains(&date)
+    }
+
+    /// The first trading moment at or after `time`, keeping the time of day
+    pub fn roll_forward(&self, mut time: OffsetDateTime) -> OffsetDateTime {
+        while !self.is_trading_day(time.date()) {
+            time += Duration::days(1);
+        }
+        time
+    }
+}
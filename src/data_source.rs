@@ -0,0 +1,653 @@
+//! Historical market data for backtests.
+//!
+//! A [`HistoricalDataSource`] returns bars for a set of symbols over a date
+//! range. `BacktestEngine` validates whatever a source returns with
+//! [`validate_market_data`], so sources kept outside this crate only need to
+//! implement `fetch`. The engine then aggregates the bars to the backtest's
+//! bar frequency with [`resample`].
+
+use crate::calendar::CALENDAR_DAYS_PER_YEAR;
+use crate::market::synthetic::{SymbolModel, SyntheticGenerator, DEFAULT_SYNTHETIC_SEED};
+use crate::market::MockMarketDataProvider;
+use crate::types::*;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, Duration, OffsetDateTime};
+
+/// Symbols `MockDataSource` covers when asked for none in particular
+pub const MOCK_SYMBOLS: [&str; 4] = ["USDC", "ETH", "BTC", "SOL"];
+
+/// Source of historical bars for a backtest
+#[async_trait]
+pub trait HistoricalDataSource: Send + Sync {
+    /// Bars for `symbols` dated from `start` to `end`, inclusive; no symbols
+    /// means every symbol the source has
+    async fn fetch(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<MarketData>>;
+
+    /// Yield series of the income-paying symbols among `symbols`, observed up
+    /// to `end`; none by default
+    async fn fetch_yields(
+        &self,
+        _symbols: &[&str],
+        _start: OffsetDateTime,
+        _end: OffsetDateTime,
+    ) -> Result<Vec<YieldObservation>> {
+        Ok(vec![])
+    }
+}
+
+/// Check bars a source returned for `start` to `end`: each dated within the
+/// range, each symbol's bars in time order, and every price positive
+pub fn validate_market_data(data: &[MarketData], start: OffsetDateTime, end: OffsetDateTime) -> Result<()> {
+    if data.is_empty() {
+        return Err(anyhow::anyhow!("backtest market data is empty"));
+    }
+    let mut last_seen: HashMap<&str, OffsetDateTime> = HashMap::new();
+    for bar in data {
+        let date = bar.timestamp.date();
+        if date < start.date() || date > end.date() {
+            return Err(anyhow::anyhow!(
+                "{} bar at {} is outside the backtest range {} to {}",
+                bar.symbol,
+                bar.timestamp,
+                start.date(),
+                end.date()
+            ));
+        }
+        if let Some(previous) = last_seen.insert(&bar.symbol, bar.timestamp) {
+            if bar.timestamp < previous {
+                return Err(anyhow::anyhow!(
+                    "{} bars are out of order: {} comes after {}",
+                    bar.symbol,
+                    bar.timestamp,
+                    previous
+                ));
+            }
+        }
+        let prices = [bar.price, bar.open, bar.high, bar.low, bar.close];
+        if prices.iter().any(|price| *price <= Decimal::ZERO) {
+            return Err(anyhow::anyhow!("{} bar at {} has a non-positive price", bar.symbol, bar.timestamp));
+        }
+        if bar.volume < Decimal::ZERO {
+            return Err(anyhow::anyhow!("{} bar at {} has negative volume", bar.symbol, bar.timestamp));
+        }
+    }
+    Ok(())
+}
+
+/// Check a yield series for a backtest ending at `end`: each observation on or
+/// before the end date and each symbol's observations in time order
+pub fn validate_yields(yields: &[YieldObservation], end: OffsetDateTime) -> Result<()> {
+    let mut last_seen: HashMap<&str, OffsetDateTime> = HashMap::new();
+    for observation in yields {
+        if observation.timestamp.date() > end.date() {
+            return Err(anyhow::anyhow!(
+                "{} yield at {} is after the backtest's end date {}",
+                observation.symbol,
+                observation.timestamp,
+                end.date()
+            ));
+        }
+        if let Some(previous) = last_seen.insert(&observation.symbol, observation.timestamp) {
+            if observation.timestamp < previous {
+                return Err(anyhow::anyhow!(
+                    "{} yields are out of order: {} comes after {}",
+                    observation.symbol,
+                    observation.timestamp,
+                    previous
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Length of the bars a backtest steps on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BarFrequency {
+    Hourly,
+    #[default]
+    Daily,
+    /// Seven days, counted from the backtest's start date
+    Weekly,
+}
+
+impl BarFrequency {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "hourly" | "hour" | "1h" => Ok(Self::Hourly),
+            "daily" | "day" | "1d" => Ok(Self::Daily),
+            "weekly" | "week" | "1w" => Ok(Self::Weekly),
+            _ => Err(anyhow::anyhow!("Unknown bar frequency: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hourly => "hourly",
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Hourly => Duration::hours(1),
+            Self::Daily => Duration::days(1),
+            Self::Weekly => Duration::weeks(1),
+        }
+    }
+
+    /// Bars in a calendar year, used to annualize per-bar statistics
+    pub fn periods_per_year(&self) -> f64 {
+        CALENDAR_DAYS_PER_YEAR * 86_400.0 / self.duration().as_seconds_f64()
+    }
+
+    /// Start of the bar `time` falls in, with bars laid end to end from `origin`
+    pub fn bar_start(&self, origin: OffsetDateTime, time: OffsetDateTime) -> OffsetDateTime {
+        let step = self.duration().whole_nanoseconds();
+        let bars = (time - origin).whole_nanoseconds().div_euclid(step);
+        origin + Duration::nanoseconds((bars * step) as i64)
+    }
+
+    /// `time` as a date, with the hour for hourly bars
+    pub fn label(&self, time: OffsetDateTime) -> String {
+        match self {
+            Self::Hourly => format!("{} {:02}:00", time.date(), time.hour()),
+            Self::Daily | Self::Weekly => time.date().to_string(),
+        }
+    }
+}
+
+/// Aggregate `data` into `frequency` bars laid from `origin`, stamped with
+/// their start time: the first open, highest high, lowest low, last close and
+/// summed volume of the bars that fall in each. Data already at the frequency
+/// only has its timestamps moved to the bar starts.
+///
+/// Each symbol's bars must be in time order, as `validate_market_data` checks.
+pub fn resample(data: &[MarketData], frequency: BarFrequency, origin: OffsetDateTime) -> Vec<MarketData> {
+    let mut bars: BTreeMap<(OffsetDateTime, &str), MarketData> = BTreeMap::new();
+    for bar in data {
+        let start = frequency.bar_start(origin, bar.timestamp);
+        bars.entry((start, bar.symbol.as_str()))
+            .and_modify(|aggregate| {
+                aggregate.high = aggregate.high.max(bar.high);
+                aggregate.low = aggregate.low.min(bar.low);
+                aggregate.close = bar.close;
+                aggregate.price = bar.price;
+                aggregate.volume += bar.volume;
+            })
+            .or_insert_with(|| MarketData { timestamp: start, ..bar.clone() });
+    }
+    bars.into_values().collect()
+}
+
+/// Seeded synthetic daily bars (see [`crate::market::synthetic`]), by default
+/// from `MockMarketDataProvider`'s prices, expected returns and volatilities.
+/// The same seed gives the same bars.
+#[derive(Debug, Clone)]
+pub struct MockDataSource {
+    seed: u64,
+    models: BTreeMap<String, SymbolModel>,
+}
+
+impl Default for MockDataSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockDataSource {
+    pub fn new() -> Self {
+        Self { seed: DEFAULT_SYNTHETIC_SEED, models: BTreeMap::new() }
+    }
+
+    /// Generate from `seed` instead of [`DEFAULT_SYNTHETIC_SEED`]
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generate `symbol` from `model` instead of the mock provider's figures
+    pub fn with_model(mut self, symbol: &str, model: SymbolModel) -> Result<Self> {
+        model.validate().with_context(|| format!("Invalid synthetic model for {}", symbol))?;
+        self.models.insert(symbol.to_string(), model);
+        Ok(self)
+    }
+
+    /// One bar a day per symbol from `start` to `end`, symbol by symbol
+    pub fn generate(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<MarketData>> {
+        let provider = MockMarketDataProvider::new();
+        let symbols = if symbols.is_empty() { &MOCK_SYMBOLS[..] } else { symbols };
+        let mut generator = SyntheticGenerator::new(self.seed);
+        for symbol in symbols {
+            let model = match self.models.get(*symbol) {
+                Some(model) => model.clone(),
+                None => SymbolModel::from_provider(&provider, symbol)?,
+            };
+            generator = generator.with_symbol(symbol, model)?;
+        }
+        generator.generate(start, end)
+    }
+}
+
+#[async_trait]
+impl HistoricalDataSource for MockDataSource {
+    async fn fetch(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<MarketData>> {
+        self.generate(symbols, start, end)
+    }
+}
+
+/// Daily bars from a market data provider's history, taken as ending today
+/// (UTC), each with the day's price as open, high, low and close and no
+/// volume. A symbol's current yield applies over the whole range.
+///
+/// Takes any [`AsyncMarketDataProvider`](crate::market::AsyncMarketDataProvider); wrap synchronous providers in
+/// [`SyncAdapter`](crate::market::SyncAdapter).
+pub struct ProviderHistory<'a, P: ?Sized> {
+    provider: &'a P,
+    today: Date,
+}
+
+impl<'a, P: crate::market::AsyncMarketDataProvider + ?Sized> ProviderHistory<'a, P> {
+    pub fn new(provider: &'a P) -> Self {
+        Self { provider, today: OffsetDateTime::now_utc().date() }
+    }
+
+    /// Date the last price of each history is for, instead of today
+    pub fn ending_on(mut self, today: Date) -> Self {
+        self.today = today;
+        self
+    }
+
+    /// Symbols given, or the mock universe's when none are
+    fn symbols<'s>(&self, symbols: &'s [&'s str]) -> &'s [&'s str] {
+        if symbols.is_empty() {
+            &MOCK_SYMBOLS[..]
+        } else {
+            symbols
+        }
+    }
+}
+
+#[async_trait]
+impl<P: crate::market::AsyncMarketDataProvider + ?Sized> HistoricalDataSource for ProviderHistory<'_, P> {
+    async fn fetch(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<MarketData>> {
+        let days = (self.today - start.date()).whole_days() + 1;
+        if days < 1 {
+            return Err(anyhow::anyhow!("The provider has no history after {}", self.today));
+        }
+        let mut data = vec![];
+        for symbol in self.symbols(symbols) {
+            let closes = self.provider.get_historical_prices(symbol, days as usize).await?;
+            let first = self.today - Duration::days(closes.len() as i64 - 1);
+            for (day, price) in closes.into_iter().enumerate() {
+                let timestamp = (first + Duration::days(day as i64)).midnight().assume_utc();
+                if timestamp.date() < start.date() || timestamp.date() > end.date() {
+                    continue;
+                }
+                data.push(MarketData {
+                    timestamp,
+                    symbol: symbol.to_string(),
+                    price,
+                    volume: Decimal::ZERO,
+                    high: price,
+                    low: price,
+                    open: price,
+                    close: price,
+                });
+            }
+        }
+        data.sort_by_key(|bar| bar.timestamp);
+        Ok(data)
+    }
+
+    async fn fetch_yields(
+        &self,
+        symbols: &[&str],
+        start: OffsetDateTime,
+        _end: OffsetDateTime,
+    ) -> Result<Vec<YieldObservation>> {
+        let mut yields = vec![];
+        for symbol in self.symbols(symbols) {
+            let apy = self.provider.get_yield_rate(symbol).await?;
+            if !apy.is_zero() {
+                yields.push(YieldObservation { timestamp: start, symbol: symbol.to_string(), apy });
+            }
+        }
+        Ok(yields)
+    }
+}
+
+/// Bars from a CSV file with a header row and columns `timestamp`, `symbol`,
+/// `open`, `high`, `low`, `close`, `volume` and, optionally, `price` (the
+/// close when missing) and `apy` (the symbol's yield from that bar on, as a
+/// fraction). Timestamps are `YYYY-MM-DD` dates (midnight UTC) or RFC 3339
+/// times.
+#[derive(Debug, Clone)]
+pub struct CsvDataSource {
+    path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvBar {
+    timestamp: String,
+    symbol: String,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+    #[serde(default)]
+    price: Option<Decimal>,
+    #[serde(default)]
+    apy: Option<Decimal>,
+}
+
+impl CsvDataSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Rows for `symbols` (all when empty) dated from `start` to `end`, in
+    /// time order, with each row's `apy`
+    async fn read(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<(MarketData, Option<Decimal>)>> {
+        let contents = tokio::fs::read(&self.path)
+            .await
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let mut rows = parse_csv_bars(&contents, &self.path)?;
+        rows.retain(|(bar, _)| {
+            (symbols.is_empty() || symbols.contains(&bar.symbol.as_str()))
+                && bar.timestamp.date() >= start.date()
+                && bar.timestamp.date() <= end.date()
+        });
+        rows.sort_by_key(|(bar, _)| bar.timestamp);
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl HistoricalDataSource for CsvDataSource {
+    async fn fetch(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<MarketData>> {
+        let rows = self.read(symbols, start, end).await?;
+        Ok(rows.into_iter().map(|(bar, _)| bar).collect())
+    }
+
+    async fn fetch_yields(&self, symbols: &[&str], start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<YieldObservation>> {
+        let rows = self.read(symbols, start, end).await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(bar, apy)| {
+                apy.map(|apy| YieldObservation { timestamp: bar.timestamp, symbol: bar.symbol, apy })
+            })
+            .collect())
+    }
+}
+
+/// Every row of a CSV in `CsvDataSource`'s format, read from `path`, in file
+/// order, with each row's `apy`
+pub(crate) fn parse_csv_bars(contents: &[u8], path: &Path) -> Result<Vec<(MarketData, Option<Decimal>)>> {
+    let mut reader = csv::Reader::from_reader(contents);
+    let mut rows = vec![];
+    for (row, bar) in reader.deserialize::<CsvBar>().enumerate() {
+        // The header is line 1
+        let line = row + 2;
+        let bar: CsvBar = bar.with_context(|| format!("{} line {}", path.display(), line))?;
+        let timestamp = parse_timestamp(&bar.timestamp).with_context(|| format!("{} line {}", path.display(), line))?;
+        let data = MarketData {
+            timestamp,
+            symbol: bar.symbol,
+            price: bar.price.unwrap_or(bar.close),
+            volume: bar.volume,
+            high: bar.high,
+            low: bar.low,
+            open: bar.open,
+            close: bar.close,
+        };
+        rows.push((data, bar.apy));
+    }
+    Ok(rows)
+}
+
+/// A `YYYY-MM-DD` date as midnight UTC, or an RFC 3339 time
+fn parse_timestamp(timestamp: &str) -> Result<OffsetDateTime> {
+    let timestamp = timestamp.trim();
+    if let Ok(date) = Date::parse(timestamp, format_description!("[year]-[month]-[day]")) {
+        return Ok(date.midnight().assume_utc());
+    }
+    OffsetDateTime::parse(timestamp, &Rfc3339)
+        .with_context(|| format!("'{}' is neither a YYYY-MM-DD date nor an RFC 3339 time", timestamp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::BacktestEngine;
+    use crate::strategy::Strategy;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    fn bar(timestamp: OffsetDateTime, symbol: &str, close: Decimal) -> MarketData {
+        MarketData {
+            timestamp,
+            symbol: symbol.to_string(),
+            price: close,
+            volume: dec!(10),
+            high: close,
+            low: close,
+            open: close,
+            close,
+        }
+    }
+
+    /// A source out-of-tree code might write, returning whatever it was given
+    struct FixedSource(Vec<MarketData>);
+
+    #[async_trait]
+    impl HistoricalDataSource for FixedSource {
+        async fn fetch(&self, _: &[&str], _: OffsetDateTime, _: OffsetDateTime) -> Result<Vec<MarketData>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn engine_validates_what_any_source_returns() {
+        let engine = || BacktestEngine::new("2024-01-01", "2024-01-03", Strategy::conservative()).unwrap();
+        let good = vec![
+            bar(datetime!(2024-01-01 0:00 UTC), "X", dec!(100)),
+            bar(datetime!(2024-01-02 0:00 UTC), "X", dec!(101)),
+        ];
+        let fetched = engine().with_data_source(&FixedSource(good.clone()), &[]).await.unwrap();
+        assert_eq!(fetched.market_data().len(), 2);
+
+        let reversed: Vec<MarketData> = good.into_iter().rev().collect();
+        let error = engine().with_data_source(&FixedSource(reversed), &[]).await.err().unwrap();
+        assert!(error.to_string().contains("X bars are out of order"), "{:#}", error);
+    }
+
+    #[test]
+    fn bad_bars_are_rejected() {
+        let (start, end) = (datetime!(2024-01-01 0:00 UTC), datetime!(2024-01-31 0:00 UTC));
+        let day = datetime!(2024-01-10 0:00 UTC);
+        let reject = |data: Vec<MarketData>, message: &str| {
+            let error = validate_market_data(&data, start, end).unwrap_err().to_string();
+            assert!(error.contains(message), "{:?} should mention {:?}", error, message);
+        };
+        reject(vec![], "empty");
+        reject(vec![bar(datetime!(2024-02-01 0:00 UTC), "X", dec!(1))], "outside the backtest range");
+        reject(vec![bar(day, "X", Decimal::ZERO)], "non-positive price");
+        reject(vec![MarketData { low: dec!(-1), ..bar(day, "X", dec!(1)) }], "non-positive price");
+        reject(vec![MarketData { volume: dec!(-1), ..bar(day, "X", dec!(1)) }], "negative volume");
+
+        // Different symbols may interleave; the end date counts as in range whatever the time
+        let interleaved = vec![
+            bar(day, "X", dec!(1)),
+            bar(day - Duration::days(1), "Y", dec!(1)),
+            bar(datetime!(2024-01-31 23:00 UTC), "X", dec!(1)),
+        ];
+        assert!(validate_market_data(&interleaved, start, end).is_ok());
+    }
+
+    #[test]
+    fn yields_must_end_by_the_end_date_in_order() {
+        let end = datetime!(2024-01-31 0:00 UTC);
+        let observation = |timestamp, apy| YieldObservation { timestamp, symbol: "X".to_string(), apy };
+        let late = [observation(datetime!(2024-02-01 0:00 UTC), dec!(0.05))];
+        assert!(validate_yields(&late, end).is_err());
+        let reversed = [
+            observation(datetime!(2024-01-10 0:00 UTC), dec!(0.05)),
+            observation(datetime!(2024-01-05 0:00 UTC), dec!(0.04)),
+        ];
+        assert!(validate_yields(&reversed, end).is_err());
+        assert!(validate_yields(&reversed[..1], end).is_ok());
+    }
+
+    #[tokio::test]
+    async fn csv_source_reads_dates_times_and_yields() {
+        let path = std::env::temp_dir().join(format!("vaulta-bars-{}.csv", uuid::Uuid::new_v4()));
+        let csv = "timestamp,symbol,open,high,low,close,volume,price,apy\n\
+                   2024-01-02,X,10,12,9,11,100,,0.05\n\
+                   2024-01-01T12:00:00Z,X,9,10,8,10,50,9.5,\n\
+                   2024-01-01,Y,1,1,1,1,5,,\n\
+                   2024-02-01,X,11,11,11,11,1,,\n";
+        std::fs::write(&path, csv).unwrap();
+        let source = CsvDataSource::new(&path);
+        let (start, end) = (datetime!(2024-01-01 0:00 UTC), datetime!(2024-01-31 0:00 UTC));
+        let bars = source.fetch(&["X"], start, end).await;
+        let yields = source.fetch_yields(&["X"], start, end).await;
+        let _ = std::fs::remove_file(&path);
+
+        // Only X in range, sorted by time, with price falling back to the close
+        let bars = bars.unwrap();
+        let summary: Vec<(OffsetDateTime, Decimal, Decimal)> =
+            bars.iter().map(|bar| (bar.timestamp, bar.price, bar.close)).collect();
+        let expected = [
+            (datetime!(2024-01-01 12:00 UTC), dec!(9.5), dec!(10)),
+            (datetime!(2024-01-02 0:00 UTC), dec!(11), dec!(11)),
+        ];
+        assert_eq!(summary, expected);
+        let yields = yields.unwrap();
+        assert_eq!(yields.len(), 1);
+        assert_eq!((yields[0].timestamp, yields[0].apy), (datetime!(2024-01-02 0:00 UTC), dec!(0.05)));
+    }
+
+    #[test]
+    fn malformed_csv_rows_name_their_line() {
+        let csv = b"timestamp,symbol,open,high,low,close,volume\n2024-01-01,X,1,1,1,1,1\nyesterday,X,1,1,1,1,1\n";
+        let error = parse_csv_bars(csv, Path::new("bars.csv")).unwrap_err();
+        assert!(format!("{:#}", error).starts_with("bars.csv line 3"), "{:#}", error);
+    }
+
+    #[test]
+    fn mock_source_is_seeded() {
+        let (start, end) = (datetime!(2024-01-01 0:00 UTC), datetime!(2024-01-10 0:00 UTC));
+        let closes = |source: MockDataSource| -> Vec<(String, Decimal)> {
+            let bars = source.generate(&["BTC", "ETH"], start, end).unwrap();
+            bars.into_iter().map(|bar| (bar.symbol, bar.close)).collect()
+        };
+        let bars = closes(MockDataSource::new());
+        assert_eq!(bars.len(), 20);
+        assert_eq!(bars, closes(MockDataSource::new()));
+        assert_ne!(bars, closes(MockDataSource::new().with_seed(7)));
+        let everything = MockDataSource::new().generate(&[], start, end).unwrap();
+        assert_eq!(everything.len(), 10 * MOCK_SYMBOLS.len());
+    }
+
+    #[test]
+    fn bar_frequencies_parse_annualize_and_label() {
+        assert_eq!(BarFrequency::from_name("1H").unwrap(), BarFrequency::Hourly);
+        assert_eq!(BarFrequency::from_name("day").unwrap(), BarFrequency::Daily);
+        assert_eq!(BarFrequency::from_name("weekly").unwrap(), BarFrequency::Weekly);
+        assert!(BarFrequency::from_name("monthly").is_err());
+
+        assert_eq!(BarFrequency::Daily.periods_per_year(), CALENDAR_DAYS_PER_YEAR);
+        assert_eq!(BarFrequency::Hourly.periods_per_year(), CALENDAR_DAYS_PER_YEAR * 24.0);
+        assert!((BarFrequency::Weekly.periods_per_year() - CALENDAR_DAYS_PER_YEAR / 7.0).abs() < 1e-12);
+
+        let time = datetime!(2024-03-05 7:30 UTC);
+        assert_eq!(BarFrequency::Hourly.label(time), "2024-03-05 07:00");
+        assert_eq!(BarFrequency::Weekly.label(time), "2024-03-05");
+        let hour = BarFrequency::Hourly.bar_start(datetime!(2024-03-05 0:00 UTC), time);
+        assert_eq!(hour, datetime!(2024-03-05 7:00 UTC));
+    }
+
+    #[test]
+    fn hourly_bars_resample_to_daily_ohlcv() {
+        let origin = datetime!(2024-01-01 0:00 UTC);
+        let hourly: Vec<MarketData> = [dec!(10), dec!(14), dec!(8), dec!(11)]
+            .into_iter()
+            .enumerate()
+            .map(|(hour, close)| bar(origin + Duration::hours(hour as i64 * 6), "X", close))
+            .collect();
+        let daily = resample(&hourly, BarFrequency::Daily, origin);
+
+        assert_eq!(daily.len(), 1);
+        let day = &daily[0];
+        assert_eq!(day.timestamp, origin);
+        assert_eq!((day.open, day.high, day.low, day.close), (dec!(10), dec!(14), dec!(8), dec!(11)));
+        assert_eq!(day.volume, dec!(40));
+        let weekly = BarFrequency::Weekly.bar_start(origin, datetime!(2024-01-10 13:00 UTC));
+        assert_eq!(weekly, datetime!(2024-01-08 0:00 UTC));
+    }
+
+    /// Closes 1 to 10, the last of them today's
+    struct TenCloses;
+
+    impl crate::market::MarketDataProvider for TenCloses {
+        fn get_current_price(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(dec!(10))
+        }
+
+        fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+            if symbol != "X" {
+                return Err(anyhow::anyhow!("Unknown symbol {}", symbol));
+            }
+            Ok((1..=10).skip(10usize.saturating_sub(days)).map(Decimal::from).collect())
+        }
+
+        fn get_volatility(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        fn get_yield_rate(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(dec!(0.05))
+        }
+    }
+
+    #[tokio::test]
+    async fn provider_history_dates_closes_back_from_today() {
+        let provider = crate::market::SyncAdapter::new(TenCloses);
+        let history = ProviderHistory::new(&provider).ending_on(time::macros::date!(2024-01-10));
+        let (start, end) = (datetime!(2024-01-03 00:00 UTC), datetime!(2024-01-05 00:00 UTC));
+        let bars = history.fetch(&["X"], start, end).await.unwrap();
+        let closes: Vec<(OffsetDateTime, Decimal)> = bars.iter().map(|bar| (bar.timestamp, bar.close)).collect();
+        assert_eq!(
+            closes,
+            [
+                (datetime!(2024-01-03 00:00 UTC), dec!(3)),
+                (datetime!(2024-01-04 00:00 UTC), dec!(4)),
+                (datetime!(2024-01-05 00:00 UTC), dec!(5))
+            ]
+        );
+        assert!(history.fetch(&["DOGE"], start, end).await.is_err());
+        // Nothing is dated after today
+        assert!(history.fetch(&["X"], start + Duration::days(30), end + Duration::days(30)).await.is_err());
+
+        // A backtest over the provider's history ending today
+        let today = OffsetDateTime::now_utc().date();
+        let start = (today - Duration::days(5)).to_string();
+        let engine = BacktestEngine::new(&start, &today.to_string(), Strategy::balanced())
+            .unwrap()
+            .with_provider(&provider, &["X"])
+            .await
+            .unwrap();
+        let closes: Vec<Decimal> = engine.market_data().iter().map(|bar| bar.close).collect();
+        assert_eq!(closes, [dec!(5), dec!(6), dec!(7), dec!(8), dec!(9), dec!(10)]);
+    }
+}
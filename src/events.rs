@@ -0,0 +1,41 @@
+use crate::types::*;
+use rust_decimal::Decimal;
+
+/// Buffered events per subscriber before slow receivers start lagging
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Progress events broadcast by a [`Simulator`](crate::Simulator)
+#[derive(Debug, Clone)]
+pub enum SimEvent {
+    StepCompleted(PortfolioSnapshot),
+    DecisionExecuted(ExecutedDecision),
+    DecisionRejected(ExecutedDecision),
+    ShockApplied(MarketShock),
+    Finalized(RunSummary),
+}
+
+/// Headline numbers sent when a run is finalized
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub steps: usize,
+    pub initial_value: Decimal,
+    pub final_value: Decimal,
+    pub total_return_pct: f64,
+    pub sharpe_ratio: f64,
+    pub max_drawdown_pct: f64,
+    pub halted_at_step: Option<usize>,
+}
+
+impl RunSummary {
+    pub fn from_results(steps: usize, results: &SimulationResults) -> Self {
+        Self {
+            steps,
+            initial_value: results.initial_value,
+            final_value: results.final_value,
+            total_return_pct: results.total_return_pct,
+            sharpe_ratio: results.sharpe_ratio,
+            max_drawdown_pct: results.max_drawdown_pct,
+            halted_at_step: results.halted_at_step,
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::types::*;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+const INDEX_FILE: &str = "index.jsonl";
+
+/// Kind of run recorded in the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExperimentKind {
+    Simulation,
+    Backtest,
+}
+
+/// Headline metrics kept in the index so queries don't need to open result files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadlineMetrics {
+    pub final_value: rust_decimal::Decimal,
+    pub total_return_pct: f64,
+    pub sharpe_ratio: f64,
+    pub max_drawdown_pct: f64,
+    pub volatility_pct: f64,
+}
+
+/// One entry of the experiment index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExperimentRecord {
+    pub id: String,
+    pub kind: ExperimentKind,
+    pub strategy: String,
+    pub created_at: OffsetDateTime,
+    pub metrics: HeadlineMetrics,
+    /// Result file, relative to the store directory
+    pub path: PathBuf,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Append-only index of simulation and backtest runs stored in a directory
+pub struct ExperimentStore {
+    dir: PathBuf,
+    records: Vec<ExperimentRecord>,
+}
+
+impl ExperimentStore {
+    /// Open (or create) a store rooted at `dir`
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create experiment store at {}", dir.display()))?;
+
+        let index_path = dir.join(INDEX_FILE);
+        let mut records = vec![];
+        if index_path.exists() {
+            let file = File::open(&index_path)
+                .with_context(|| format!("Failed to open {}", index_path.display()))?;
+            for (line_no, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record = serde_json::from_str(&line).with_context(|| {
+                    format!("Malformed index entry at {}:{}", index_path.display(), line_no + 1)
+                })?;
+                records.push(record);
+            }
+        }
+
+        Ok(Self { dir, records })
+    }
+
+    /// Write simulation results and index them
+    pub fn log_simulation(
+        &mut self,
+        strategy: &str,
+        results: &SimulationResults,
+        metadata: HashMap<String, String>,
+    ) -> Result<&ExperimentRecord> {
+        let metrics = HeadlineMetrics {
+            final_value: results.final_value,
+            total_return_pct: results.total_return_pct,
+            sharpe_ratio: results.sharpe_ratio,
+            max_drawdown_pct: results.max_drawdown_pct,
+            volatility_pct: results.volatility_pct,
+        };
+        self.log(ExperimentKind::Simulation, strategy, metrics, results, metadata)
+    }
+
+    /// Write backtest results and index them
+    pub fn log_backtest(
+        &mut self,
+        strategy: &str,
+        results: &BacktestResults,
+        metadata: HashMap<String, String>,
+    ) -> Result<&ExperimentRecord> {
+        let metrics = HeadlineMetrics {
+            final_value: results.final_value,
+            total_return_pct: results.total_return_pct,
+            sharpe_ratio: results.sharpe_ratio,
+            max_drawdown_pct: results.max_drawdown_pct,
+            volatility_pct: results.volatility_pct,
+        };
+        self.log(ExperimentKind::Backtest, strategy, metrics, results, metadata)
+    }
+
+    fn log<T: Serialize>(
+        &mut self,
+        kind: ExperimentKind,
+        strategy: &str,
+        metrics: HeadlineMetrics,
+        results: &T,
+        metadata: HashMap<String, String>,
+    ) -> Result<&ExperimentRecord> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let path = PathBuf::from(format!("{}.json", id));
+
+        let file = File::create(self.dir.join(&path))
+            .with_context(|| format!("Failed to create result file {}", path.display()))?;
+        serde_json::to_writer_pretty(file, results)?;
+
+        let record = ExperimentRecord {
+            id,
+            kind,
+            strategy: strategy.to_string(),
+            created_at: OffsetDateTime::now_utc(),
+            metrics,
+            path,
+            metadata,
+        };
+
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(INDEX_FILE))
+            .context("Failed to open experiment index")?;
+        // One write per entry, so stores appending to the same index at once can't interleave lines
+        let line = format!("{}\n", serde_json::to_string(&record)?);
+        index.write_all(line.as_bytes())?;
+
+        self.records.push(record);
+        Ok(self.records.last().expect("record was just pushed"))
+    }
+
+    /// All runs in the order they were logged
+    pub fn list(&self) -> &[ExperimentRecord] {
+        &self.records
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ExperimentRecord> {
+        self.records.iter().find(|r| r.id == id)
+    }
+
+    pub fn by_strategy(&self, strategy: &str) -> Vec<&ExperimentRecord> {
+        self.records
+            .iter()
+            .filter(|r| r.strategy.eq_ignore_ascii_case(strategy))
+            .collect()
+    }
+
+    /// Runs logged within `[start, end]`
+    pub fn in_date_range(&self, start: OffsetDateTime, end: OffsetDateTime) -> Vec<&ExperimentRecord> {
+        self.records
+            .iter()
+            .filter(|r| r.created_at >= start && r.created_at <= end)
+            .collect()
+    }
+
+    /// The `k` runs with the highest Sharpe ratio, best first
+    pub fn top_by_sharpe(&self, k: usize) -> Vec<&ExperimentRecord> {
+        let mut ranked: Vec<&ExperimentRecord> = self.records.iter().collect();
+        ranked.sort_by(|a, b| b.metrics.sharpe_ratio.total_cmp(&a.metrics.sharpe_ratio));
+        ranked.truncate(k);
+        ranked
+    }
+
+    /// Load the full results file for a run
+    pub fn load<T: serde::de::DeserializeOwned>(&self, id: &str) -> Result<T> {
+        let record = self
+            .get(id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown experiment: {}", id))?;
+        let file = File::open(self.dir.join(&record.path))
+            .with_context(|| format!("Failed to open result file for {}", id))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Strategy;
+    use crate::Simulator;
+
+    /// A fresh directory under the system temp dir, removed when dropped
+    struct TempStore(PathBuf);
+
+    impl TempStore {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("vaulta-experiments-{}", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempStore {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn results() -> SimulationResults {
+        let mut simulator = Simulator::new(1_000_000.0, Strategy::balanced());
+        simulator.step().expect("step failed");
+        simulator.current_results()
+    }
+
+    #[test]
+    fn logged_runs_round_trip_through_a_reopened_store() {
+        let dir = TempStore::new();
+        let results = results();
+        let metadata = HashMap::from([("seed".to_string(), "7".to_string())]);
+        let id = ExperimentStore::open(&dir.0)
+            .unwrap()
+            .log_simulation("balanced", &results, metadata.clone())
+            .unwrap()
+            .id
+            .clone();
+
+        let store = ExperimentStore::open(&dir.0).unwrap();
+        let record = store.get(&id).expect("the run should be indexed");
+        assert_eq!(record.kind, ExperimentKind::Simulation);
+        assert_eq!(record.metadata, metadata);
+        assert_eq!(record.metrics.final_value, results.final_value);
+        assert_eq!(store.by_strategy("BALANCED").len(), 1);
+        let loaded: SimulationResults = store.load(&id).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&results).unwrap());
+    }
+
+    #[test]
+    fn stores_appending_at_once_keep_every_entry_whole() {
+        let dir = TempStore::new();
+        let results = results();
+        std::thread::scope(|scope| {
+            for writer in 0..4 {
+                let (dir, results) = (&dir.0, &results);
+                scope.spawn(move || {
+                    let mut store = ExperimentStore::open(dir).unwrap();
+                    for _ in 0..25 {
+                        store.log_simulation(&format!("writer-{}", writer), results, HashMap::new()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let store = ExperimentStore::open(&dir.0).unwrap();
+        assert_eq!(store.list().len(), 100);
+        for writer in 0..4 {
+            assert_eq!(store.by_strategy(&format!("writer-{}", writer)).len(), 25);
+        }
+        let last = store.list().last().unwrap();
+        assert!(store.load::<SimulationResults>(&last.id).is_ok());
+    }
+
+    #[test]
+    fn corrupt_index_line_is_reported_with_its_line_number() {
+        let dir = TempStore::new();
+        ExperimentStore::open(&dir.0).unwrap().log_simulation("balanced", &results(), HashMap::new()).unwrap();
+        let mut index = OpenOptions::new().append(true).open(dir.0.join(INDEX_FILE)).unwrap();
+        index.write_all(b"\n{\"id\": \"truncated").unwrap();
+
+        let error = ExperimentStore::open(&dir.0).err().expect("a corrupt index should not open");
+        assert!(error.to_string().contains("index.jsonl:3"), "{}", error);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::types::*;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::fmt::Debug;
+
+/// Basis points per unit
+pub(crate) const BPS: Decimal = dec!(10000);
+
+/// Venue economics: the execution cost charged for a routing decision
+pub trait FeeModel: Debug + Send + Sync {
+    fn fee(&self, decision: &RoutingDecision, portfolio: &Portfolio) -> Decimal;
+}
+
+/// Fixed basis points of notional
+#[derive(Debug, Clone)]
+pub struct FlatBps {
+    pub bps: Decimal,
+}
+
+impl FlatBps {
+    pub fn new(bps: Decimal) -> Self {
+        Self { bps }
+    }
+}
+
+impl FeeModel for FlatBps {
+    fn fee(&self, decision: &RoutingDecision, _portfolio: &Portfolio) -> Decimal {
+        decision.amount * self.bps / BPS
+    }
+}
+
+/// Fixed charge per decision plus basis points of notional
+#[derive(Debug, Clone)]
+pub struct FixedPlusBps {
+    pub fixed: Decimal,
+    pub bps: Decimal,
+}
+
+impl FixedPlusBps {
+    pub fn new(fixed: Decimal, bps: Decimal) -> Self {
+        Self { fixed, bps }
+    }
+}
+
+impl FeeModel for FixedPlusBps {
+    fn fee(&self, decision: &RoutingDecision, _portfolio: &Portfolio) -> Decimal {
+        if decision.amount.is_zero() {
+            return Decimal::ZERO;
+        }
+        self.fixed + decision.amount * self.bps / BPS
+    }
+}
+
+/// Basis points that step down as the decision's notional grows
+#[derive(Debug, Clone)]
+pub struct TieredByVolume {
+    /// `(minimum notional, bps)` pairs, sorted by minimum notional
+    tiers: Vec<(Decimal, Decimal)>,
+}
+
+impl TieredByVolume {
+    /// The tier with the largest minimum at or below the notional applies;
+    /// notionals below every tier use the first tier's rate
+    pub fn new(mut tiers: Vec<(Decimal, Decimal)>) -> Self {
+        tiers.sort_by_key(|tier| tier.0);
+        Self { tiers }
+    }
+
+    pub fn bps_for(&self, notional: Decimal) -> Decimal {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|(min_notional, _)| notional >= *min_notional)
+            .or_else(|| self.tiers.first())
+            .map(|(_, bps)| *bps)
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+impl FeeModel for TieredByVolume {
+    fn fee(&self, decision: &RoutingDecision, _portfolio: &Portfolio) -> Decimal {
+        decision.amount * self.bps_for(decision.amount) / BPS
+    }
+}
@@ -7,7 +7,7 @@
 //!
 //! - **Monte Carlo Simulation**: Stress test strategies with thousands of scenarios
 //! - **Backtesting Engine**: Test strategies on historical market data
-//! - **Strategy Optimization**: Find optimal parameters using genetic algorithms
+//! - **Strategy Optimization**: Search a grid of strategy variants for the best allocation
 //! - **Risk Metrics**: Calculate VaR, CVaR, Sharpe ratio, and more
 //! - **Real-time Simulation**: Step-by-step capital routing simulation
 //!
@@ -25,19 +25,43 @@
 //!
 //! let results = simulator.finalize();
 //! println!("Final value: {}", results.final_value);
+//! # Ok::<(), anyhow::Error>(())
 //! ```
 
 pub mod backtest;
+pub mod bootstrap;
+pub mod builder;
+pub mod calendar;
+pub mod data_source;
+pub mod events;
+pub mod experiments;
+pub mod fees;
+pub mod liquidity;
+#[cfg(feature = "live-data")]
+pub mod live_data;
 pub mod market;
+pub mod market_view;
+pub mod metrics;
 pub mod monte_carlo;
 pub mod optimizer;
 pub mod portfolio;
+pub mod report;
 pub mod risk;
+pub mod scenarios;
+pub mod shocks;
 pub mod simulator;
+pub mod sobol;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod strategy;
+pub mod stress;
+pub mod transactions;
 pub mod types;
 pub mod utils;
+pub mod walk_forward;
+pub mod yield_curve;
 
+pub use builder::SimulatorBuilder;
 pub use simulator::Simulator;
 pub use strategy::Strategy;
 pub use types::*;
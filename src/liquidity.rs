@@ -0,0 +1,164 @@
+//! Liquidity and price impact.
+//!
+//! An asset's liquidity is its average daily dollar volume, or the quote-side
+//! depth of the pool it trades in. [`PriceImpact`] turns an order's size
+//! relative to that liquidity into an adverse move in its fill price, growing
+//! with the square root of the order's share: a trade of 1% of the liquidity
+//! moves the price a tenth as much as one of the whole of it.
+
+use crate::transactions::TradeSide;
+use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Impact of a trade the size of the whole liquidity, as a fraction of price
+pub const DEFAULT_IMPACT_COEFFICIENT: f64 = 0.1;
+
+/// Square-root price impact: `coefficient * sqrt(notional / liquidity)`, as a
+/// fraction of price, capped at the whole price
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriceImpact {
+    coefficient: f64,
+}
+
+impl Default for PriceImpact {
+    fn default() -> Self {
+        Self { coefficient: DEFAULT_IMPACT_COEFFICIENT }
+    }
+}
+
+impl PriceImpact {
+    pub fn new(coefficient: f64) -> Result<Self> {
+        if !(coefficient.is_finite() && coefficient >= 0.0) {
+            return Err(anyhow::anyhow!("Price impact coefficient must be non-negative, got {}", coefficient));
+        }
+        Ok(Self { coefficient })
+    }
+
+    pub fn coefficient(&self) -> f64 {
+        self.coefficient
+    }
+
+    /// Adverse price move of trading `notional` against `liquidity`, as a fraction
+    /// of price; zero for empty orders or non-positive liquidity
+    pub fn impact(&self, notional: Decimal, liquidity: Decimal) -> f64 {
+        if notional <= Decimal::ZERO || liquidity <= Decimal::ZERO {
+            return 0.0;
+        }
+        let share = (notional / liquidity).to_f64().unwrap_or(f64::INFINITY);
+        (self.coefficient * share.sqrt()).min(1.0)
+    }
+
+    /// What the impact costs on a trade of `notional`
+    pub fn cost(&self, notional: Decimal, liquidity: Decimal) -> Decimal {
+        let impact = Decimal::try_from(self.impact(notional, liquidity)).unwrap_or(Decimal::ZERO);
+        notional * impact
+    }
+
+    /// Average price a trade of `notional` at quoted `price` fills at: above it
+    /// for buys, below it for sells
+    pub fn fill_price(&self, price: Decimal, notional: Decimal, liquidity: Decimal, side: TradeSide) -> Decimal {
+        let impact = Decimal::try_from(self.impact(notional, liquidity)).unwrap_or(Decimal::ZERO);
+        match side {
+            TradeSide::Buy => price * (Decimal::ONE + impact),
+            TradeSide::Sell => price * (Decimal::ONE - impact),
+        }
+    }
+
+    /// Largest notional whose impact stays within `max_impact`; `None` when
+    /// impact is free at any size
+    pub fn max_notional(&self, liquidity: Decimal, max_impact: f64) -> Option<Decimal> {
+        if self.coefficient == 0.0 || max_impact >= 1.0 {
+            return None;
+        }
+        let share = (max_impact.max(0.0) / self.coefficient).powi(2);
+        Some(liquidity.max(Decimal::ZERO) * Decimal::try_from(share).unwrap_or(Decimal::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::FlatBps;
+    use crate::types::{Asset, AssetType};
+    use crate::{SimulatorBuilder, Strategy};
+    use proptest::prelude::*;
+    use rust_decimal_macros::dec;
+    use std::collections::HashMap;
+
+    #[test]
+    fn impact_grows_with_the_square_root_of_the_share() {
+        let impact = PriceImpact::default();
+        let liquidity = dec!(10_000_000);
+        assert!((impact.impact(dec!(100_000), liquidity) - 0.01).abs() < 1e-12);
+        assert!((impact.impact(liquidity, liquidity) - 0.1).abs() < 1e-12);
+        assert_eq!(impact.impact(liquidity * dec!(1000), liquidity), 1.0);
+        assert_eq!(impact.impact(Decimal::ZERO, liquidity), 0.0);
+        assert_eq!(impact.impact(dec!(100), Decimal::ZERO), 0.0);
+
+        assert_eq!(impact.cost(dec!(100_000), liquidity).round_dp(9), dec!(1000));
+        assert_eq!(impact.fill_price(dec!(50), dec!(100_000), liquidity, TradeSide::Buy).round_dp(9), dec!(50.5));
+        assert_eq!(impact.fill_price(dec!(50), dec!(100_000), liquidity, TradeSide::Sell).round_dp(9), dec!(49.5));
+        assert_eq!(impact.max_notional(liquidity, 0.01).unwrap().round_dp(6), dec!(100_000));
+        assert_eq!(impact.max_notional(liquidity, 1.0), None);
+        assert_eq!(PriceImpact::new(0.0).unwrap().max_notional(liquidity, 0.01), None);
+        assert!(PriceImpact::new(-0.1).is_err());
+        assert!(PriceImpact::new(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn simulated_fills_degrade_as_orders_grow() {
+        let liquidity = dec!(10_000_000);
+        let pool = Asset {
+            symbol: "POOL".to_string(),
+            name: "Pool".to_string(),
+            asset_type: AssetType::DeFiPool,
+            current_price: Decimal::ONE,
+            volatility: Decimal::ZERO,
+            yield_rate: dec!(0.25),
+            expected_return: Decimal::ZERO,
+            bond: None,
+            liquidity: Some(liquidity),
+        };
+        let mut last_fill = Decimal::ZERO;
+        for capital in [10_000.0, 200_000.0, 2_000_000.0, 20_000_000.0] {
+            let mut simulator = SimulatorBuilder::new()
+                .capital(capital)
+                .strategy(Strategy::conservative())
+                .universe(vec![pool.clone()])
+                .fee_model(FlatBps::new(Decimal::ZERO))
+                .price_impact(PriceImpact::default())
+                .build()
+                .unwrap();
+            simulator.rebalance_to(&HashMap::from([("POOL".to_string(), dec!(0.5))])).unwrap();
+            let executed = simulator.decision_log().last().unwrap();
+            let amount = executed.decision.amount;
+            // Price paid per unit received, impact included
+            let fill = (amount + executed.execution_cost) / amount;
+            let expected = PriceImpact::default().fill_price(Decimal::ONE, amount, liquidity, TradeSide::Buy);
+            assert!((fill - expected).abs() < dec!(0.000001), "{} vs {}", fill, expected);
+            assert!(fill > last_fill, "{} should fill worse than {}", amount, last_fill);
+            last_fill = fill;
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn fill_prices_are_monotonic_in_size(
+            small in 1u64..1_000_000_000,
+            extra in 1u64..1_000_000_000,
+            liquidity in 1_000u64..1_000_000_000,
+            coefficient in 0.01f64..1.0,
+        ) {
+            let impact = PriceImpact::new(coefficient).unwrap();
+            let (small, large) = (Decimal::from(small), Decimal::from(small + extra));
+            let (price, liquidity) = (dec!(100), Decimal::from(liquidity));
+            let buy = |size| impact.fill_price(price, size, liquidity, TradeSide::Buy);
+            let sell = |size| impact.fill_price(price, size, liquidity, TradeSide::Sell);
+            prop_assert!(buy(large) >= buy(small) && buy(small) > price);
+            prop_assert!(sell(large) <= sell(small) && sell(small) < price);
+            prop_assert!(sell(large) >= Decimal::ZERO);
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! Live market data over HTTP, behind the `live-data` feature.
+//!
+//! [`HttpMarketDataProvider`] fetches prices and daily price history from
+//! CoinGecko's public API (`/simple/price` and `/coins/{id}/market_chart`),
+//! mapping symbols to CoinGecko ids through a configurable table. Requests time
+//! out, and network errors, rate limiting and server errors are retried with
+//! exponential backoff. Failures come back as a [`LiveDataError`], which
+//! separates network trouble from unknown symbols and rate limits.
+//!
+//! The provider implements [`AsyncMarketDataProvider`], for `step_async` and
+//! the engines. For the synchronous [`MarketDataProvider`] the simulator
+//! describes assets with, [`HttpMarketDataProvider::snapshot`] pre-fetches a
+//! [`MarketSnapshot`].
+
+use crate::calendar::CALENDAR_DAYS_PER_YEAR;
+use crate::market::{AsyncMarketDataProvider, MarketDataProvider, DEFAULT_VOLATILITY_WINDOW};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use time::OffsetDateTime;
+use tracing::warn;
+
+/// CoinGecko's public API
+pub const COINGECKO_BASE_URL: &str = "https://api.coingecko.com/api/v3";
+
+/// CoinGecko ids of the symbols `HttpMarketDataProvider` knows out of the box
+const DEFAULT_IDS: [(&str, &str); 6] = [
+    ("BTC", "bitcoin"),
+    ("ETH", "ethereum"),
+    ("SOL", "solana"),
+    ("USDC", "usd-coin"),
+    ("USDT", "tether"),
+    ("DAI", "dai"),
+];
+
+/// Why a live data request failed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LiveDataError {
+    /// The request didn't complete: connection failure, timeout, or a body cut short
+    #[error("network error fetching {url}: {message}")]
+    Network { url: String, message: String },
+    /// The symbol has no id in the table, or the API doesn't know its id
+    #[error("unknown symbol {symbol}: {detail}")]
+    UnknownSymbol { symbol: String, detail: String },
+    /// The API kept answering 429 Too Many Requests
+    #[error("rate limited by {url}")]
+    RateLimited { url: String, retry_after: Option<Duration> },
+    /// Any other non-success status
+    #[error("{url} returned HTTP {status}")]
+    Status { url: String, status: u16 },
+    /// The response wasn't the JSON expected
+    #[error("unexpected response from {url}: {message}")]
+    Parse { url: String, message: String },
+}
+
+impl LiveDataError {
+    /// Whether retrying the request might succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network { .. } | Self::RateLimited { .. } => true,
+            Self::Status { status, .. } => *status >= 500,
+            Self::UnknownSymbol { .. } | Self::Parse { .. } => false,
+        }
+    }
+}
+
+/// `/coins/{id}/market_chart` response; each point is `[unix millis, value]`
+#[derive(Debug, Deserialize)]
+struct MarketChart {
+    prices: Vec<(f64, f64)>,
+}
+
+/// Prices and daily history from CoinGecko's HTTP API
+#[derive(Debug, Clone)]
+pub struct HttpMarketDataProvider {
+    client: reqwest::Client,
+    base_url: String,
+    vs_currency: String,
+    /// CoinGecko id per symbol
+    ids: BTreeMap<String, String>,
+    timeout: Duration,
+    max_retries: u32,
+    /// Wait before the first retry, doubling on each one after
+    backoff: Duration,
+}
+
+impl Default for HttpMarketDataProvider {
+    fn default() -> Self {
+        Self::coingecko()
+    }
+}
+
+impl HttpMarketDataProvider {
+    /// CoinGecko's public API, quoting in USD, with ids for common symbols
+    pub fn coingecko() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: COINGECKO_BASE_URL.to_string(),
+            vs_currency: "usd".to_string(),
+            ids: DEFAULT_IDS.iter().map(|(symbol, id)| (symbol.to_string(), id.to_string())).collect(),
+            timeout: Duration::from_secs(10),
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// Send requests to `base_url` instead, e.g. a mirror or a fixture server
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Quote prices in `vs_currency` (a CoinGecko currency code) instead of USD
+    pub fn with_vs_currency(mut self, vs_currency: impl Into<String>) -> Self {
+        self.vs_currency = vs_currency.into().to_lowercase();
+        self
+    }
+
+    /// Look `symbol` up as CoinGecko's `id`, adding or replacing its entry
+    pub fn with_symbol_id(mut self, symbol: impl Into<String>, id: impl Into<String>) -> Self {
+        self.ids.insert(symbol.into(), id.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retry failed requests up to `max_retries` times, waiting `backoff`
+    /// before the first retry and twice as long before each one after
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Current price of each of `symbols`, in one request
+    pub async fn prices(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>, LiveDataError> {
+        let ids = symbols.iter().map(|symbol| self.id(symbol)).collect::<Result<Vec<_>, _>>()?;
+        let url = format!("{}/simple/price", self.base_url);
+        let query = [("ids", ids.join(",")), ("vs_currencies", self.vs_currency.clone())];
+        let quotes: HashMap<String, HashMap<String, f64>> = self.get_json(&url, &query).await?;
+
+        symbols
+            .iter()
+            .zip(&ids)
+            .map(|(symbol, id)| {
+                let price = quotes
+                    .get(*id)
+                    .and_then(|quote| quote.get(&self.vs_currency))
+                    .ok_or_else(|| self.unlisted(symbol, id))?;
+                Ok((symbol.to_string(), self.decimal(&url, *price)?))
+            })
+            .collect()
+    }
+
+    /// Daily prices of `symbol` over the last `days` days, oldest first. The
+    /// last point is the latest price rather than a daily close.
+    pub async fn history(&self, symbol: &str, days: u32) -> Result<Vec<(OffsetDateTime, Decimal)>, LiveDataError> {
+        let id = self.id(symbol)?;
+        let url = format!("{}/coins/{}/market_chart", self.base_url, id);
+        let query = [
+            ("vs_currency", self.vs_currency.clone()),
+            ("days", days.to_string()),
+            ("interval", "daily".to_string()),
+        ];
+        let chart: MarketChart = match self.get_json(&url, &query).await {
+            // CoinGecko answers 404 for ids it doesn't have
+            Err(LiveDataError::Status { status: 404, .. }) => return Err(self.unlisted(symbol, id)),
+            result => result?,
+        };
+        chart
+            .prices
+            .iter()
+            .map(|(millis, price)| {
+                let timestamp = OffsetDateTime::from_unix_timestamp_nanos(*millis as i128 * 1_000_000)
+                    .map_err(|e| LiveDataError::Parse { url: url.clone(), message: e.to_string() })?;
+                Ok((timestamp, self.decimal(&url, *price)?))
+            })
+            .collect()
+    }
+
+    /// Fetch current prices and `days` of daily history for `symbols`, for
+    /// use as a synchronous provider
+    pub async fn snapshot(&self, symbols: &[&str], days: u32) -> Result<MarketSnapshot, LiveDataError> {
+        let prices = self.prices(symbols).await?;
+        let mut history = HashMap::new();
+        for symbol in symbols {
+            let closes = self.history(symbol, days).await?.into_iter().map(|(_, price)| price).collect();
+            history.insert(symbol.to_string(), closes);
+        }
+        Ok(MarketSnapshot {
+            taken_at: OffsetDateTime::now_utc(),
+            prices,
+            history,
+            yields: HashMap::new(),
+        })
+    }
+
+    fn id(&self, symbol: &str) -> Result<&str, LiveDataError> {
+        self.ids.get(symbol).map(String::as_str).ok_or_else(|| self.unknown(symbol))
+    }
+
+    fn unknown(&self, symbol: &str) -> LiveDataError {
+        let known = self.ids.keys().cloned().collect::<Vec<_>>().join(", ");
+        let detail = format!("no id for it; known symbols: {}", known);
+        LiveDataError::UnknownSymbol { symbol: symbol.to_string(), detail }
+    }
+
+    fn unlisted(&self, symbol: &str, id: &str) -> LiveDataError {
+        LiveDataError::UnknownSymbol { symbol: symbol.to_string(), detail: format!("the API has no coin {}", id) }
+    }
+
+    fn decimal(&self, url: &str, value: f64) -> Result<Decimal, LiveDataError> {
+        Decimal::try_from(value)
+            .ok()
+            .filter(|price| *price > Decimal::ZERO)
+            .ok_or_else(|| LiveDataError::Parse { url: url.to_string(), message: format!("bad price {}", value) })
+    }
+
+    /// GET `url` and parse its JSON, retrying what's worth retrying
+    async fn get_json<T: DeserializeOwned>(&self, url: &str, query: &[(&str, String)]) -> Result<T, LiveDataError> {
+        let mut attempt = 0;
+        loop {
+            let error = match self.try_get_json(url, query).await {
+                Ok(value) => return Ok(value),
+                Err(error) => error,
+            };
+            if !error.is_retryable() || attempt >= self.max_retries {
+                return Err(error);
+            }
+            let wait = match &error {
+                LiveDataError::RateLimited { retry_after: Some(retry_after), .. } => *retry_after,
+                _ => self.backoff * 2u32.saturating_pow(attempt),
+            };
+            warn!("{}; retrying in {:?}", error, wait);
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+
+    async fn try_get_json<T: DeserializeOwned>(&self, url: &str, query: &[(&str, String)]) -> Result<T, LiveDataError> {
+        let network = |e: reqwest::Error| LiveDataError::Network { url: url.to_string(), message: e.to_string() };
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(network)?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .map(Duration::from_secs);
+            return Err(LiveDataError::RateLimited { url: url.to_string(), retry_after });
+        }
+        if !status.is_success() {
+            return Err(LiveDataError::Status { url: url.to_string(), status: status.as_u16() });
+        }
+        let body = response.text().await.map_err(network)?;
+        serde_json::from_str(&body).map_err(|e| LiveDataError::Parse { url: url.to_string(), message: e.to_string() })
+    }
+}
+
+#[async_trait]
+impl AsyncMarketDataProvider for HttpMarketDataProvider {
+    async fn get_current_price(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        let mut prices = self.prices(&[symbol]).await?;
+        Ok(prices.remove(symbol).unwrap_or_default())
+    }
+
+    /// The last `days` daily prices (all there are, if fewer), oldest first
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> anyhow::Result<Vec<Decimal>> {
+        if days == 0 {
+            self.id(symbol)?;
+            return Ok(vec![]);
+        }
+        let history = self.history(symbol, u32::try_from(days).unwrap_or(u32::MAX)).await?;
+        let closes: Vec<Decimal> = history.into_iter().map(|(_, price)| price).collect();
+        Ok(closes[closes.len().saturating_sub(days)..].to_vec())
+    }
+
+    /// Estimated from the last `DEFAULT_VOLATILITY_WINDOW` days of daily prices
+    async fn get_volatility(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        let history = self.history(symbol, DEFAULT_VOLATILITY_WINDOW as u32).await?;
+        let closes: Vec<Decimal> = history.into_iter().map(|(_, price)| price).collect();
+        daily_volatility(symbol, &closes)
+    }
+
+    /// CoinGecko has no yields, so every known symbol yields nothing
+    async fn get_yield_rate(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.id(symbol)?;
+        Ok(Decimal::ZERO)
+    }
+
+    /// One `/simple/price` request for every symbol
+    async fn get_prices_batch(&self, symbols: &[&str]) -> anyhow::Result<HashMap<String, Decimal>> {
+        Ok(self.prices(symbols).await?)
+    }
+}
+
+/// Annualized standard deviation of the log returns between daily `closes`
+fn daily_volatility(symbol: &str, closes: &[Decimal]) -> anyhow::Result<Decimal> {
+    let volatility = crate::metrics::log_return_volatility(closes, CALENDAR_DAYS_PER_YEAR)
+        .ok_or_else(|| anyhow::anyhow!("{} needs at least three daily prices to estimate volatility", symbol))?;
+    Ok(Decimal::try_from(volatility)?)
+}
+
+/// Prices and daily history fetched at one moment, served synchronously.
+///
+/// Volatility is the annualized standard deviation of the daily history's log
+/// returns. CoinGecko has no yields, so every yield is zero unless set with
+/// `with_yield_rate`.
+#[derive(Debug, Clone)]
+pub struct MarketSnapshot {
+    pub taken_at: OffsetDateTime,
+    prices: HashMap<String, Decimal>,
+    /// Daily prices per symbol, oldest first
+    history: HashMap<String, Vec<Decimal>>,
+    yields: HashMap<String, Decimal>,
+}
+
+impl MarketSnapshot {
+    pub fn with_yield_rate(mut self, symbol: impl Into<String>, rate: Decimal) -> Self {
+        self.yields.insert(symbol.into(), rate);
+        self
+    }
+
+    fn price(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.prices.get(symbol).copied().ok_or_else(|| {
+            let mut known: Vec<&str> = self.prices.keys().map(String::as_str).collect();
+            known.sort();
+            anyhow::anyhow!("{} is not in the snapshot; it has {}", symbol, known.join(", "))
+        })
+    }
+}
+
+impl MarketDataProvider for MarketSnapshot {
+    fn get_current_price(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.price(symbol)
+    }
+
+    /// The last `days` daily prices (all there are, if fewer), oldest first
+    fn get_historical_prices(&self, symbol: &str, days: usize) -> anyhow::Result<Vec<Decimal>> {
+        self.price(symbol)?;
+        let history = self.history.get(symbol).map(Vec::as_slice).unwrap_or_default();
+        Ok(history[history.len().saturating_sub(days)..].to_vec())
+    }
+
+    fn get_volatility(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.price(symbol)?;
+        daily_volatility(symbol, self.history.get(symbol).map(Vec::as_slice).unwrap_or_default())
+    }
+
+    fn get_yield_rate(&self, symbol: &str) -> anyhow::Result<Decimal> {
+        self.price(symbol)?;
+        Ok(self.yields.get(symbol).copied().unwrap_or(Decimal::ZERO))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::prelude::ToPrimitive;
+    use rust_decimal_macros::dec;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn fixture(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/data/coingecko").join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    /// Serve `responses` (status line, body) in order, one per connection,
+    /// recording each request's target; returns the server's base URL
+    async fn replay(responses: Vec<(&'static str, String)>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let seen = targets.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let Ok((mut stream, _)) = listener.accept().await else { return };
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => request.extend_from_slice(&buf[..read]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                seen.lock().unwrap().push(request.split_whitespace().nth(1).unwrap_or_default().to_string());
+                let response = format!(
+                    "HTTP/1.1 {}\r\nRetry-After: 0\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (base_url, targets)
+    }
+
+    fn provider(base_url: &str) -> HttpMarketDataProvider {
+        HttpMarketDataProvider::coingecko()
+            .with_base_url(base_url)
+            .with_timeout(Duration::from_secs(2))
+            .with_retries(2, Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn rate_limited_requests_are_retried() {
+        let (base_url, targets) =
+            replay(vec![("429 Too Many Requests", String::new()), ("200 OK", fixture("simple_price.json"))]).await;
+        let prices = provider(&base_url).prices(&["BTC", "ETH"]).await.unwrap();
+
+        assert_eq!(prices["BTC"], dec!(43250.5));
+        assert_eq!(prices["ETH"], dec!(2280.25));
+        let targets = targets.lock().unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(targets[1].starts_with("/simple/price?ids=bitcoin%2Cethereum&vs_currencies=usd"), "{}", targets[1]);
+    }
+
+    #[tokio::test]
+    async fn errors_say_what_went_wrong() {
+        // A symbol missing from the table fails before any request
+        let error = provider("http://127.0.0.1:9").prices(&["DOGE"]).await.unwrap_err();
+        assert!(matches!(&error, LiveDataError::UnknownSymbol { symbol, .. } if symbol == "DOGE"));
+        assert!(!error.is_retryable());
+
+        let (base_url, _) = replay(vec![
+            ("404 Not Found", String::new()),
+            ("200 OK", "{}".to_string()),
+            ("200 OK", "<html>maintenance</html>".to_string()),
+        ])
+        .await;
+        let unlisted = provider(&base_url).with_symbol_id("XYZ", "xyz-coin");
+        let error = unlisted.history("XYZ", 10).await.unwrap_err();
+        assert!(matches!(&error, LiveDataError::UnknownSymbol { detail, .. } if detail.contains("xyz-coin")));
+        assert!(matches!(unlisted.prices(&["XYZ"]).await, Err(LiveDataError::UnknownSymbol { .. })));
+        assert!(matches!(unlisted.history("ETH", 10).await, Err(LiveDataError::Parse { .. })));
+
+        let (base_url, targets) = replay(vec![("429 Too Many Requests", String::new()); 3]).await;
+        let error = provider(&base_url).prices(&["BTC"]).await.unwrap_err();
+        assert!(matches!(error, LiveDataError::RateLimited { retry_after: Some(wait), .. } if wait.is_zero()));
+        assert_eq!(targets.lock().unwrap().len(), 3);
+
+        let offline = provider("http://127.0.0.1:9").with_retries(0, Duration::ZERO);
+        assert!(matches!(offline.prices(&["BTC"]).await, Err(LiveDataError::Network { .. })));
+    }
+
+    #[test]
+    fn only_transient_failures_are_retryable() {
+        let status = |status| LiveDataError::Status { url: String::new(), status };
+        assert!(status(503).is_retryable());
+        assert!(!status(400).is_retryable());
+        assert!(LiveDataError::RateLimited { url: String::new(), retry_after: None }.is_retryable());
+        assert!(!LiveDataError::Parse { url: String::new(), message: String::new() }.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn snapshots_serve_fetched_history() {
+        let (base_url, _) = replay(vec![
+            ("200 OK", fixture("simple_price.json")),
+            ("200 OK", fixture("market_chart_bitcoin.json")),
+            ("200 OK", fixture("market_chart_ethereum.json")),
+        ])
+        .await;
+        let snapshot = provider(&base_url).snapshot(&["BTC", "ETH"], 10).await.unwrap();
+        let snapshot = snapshot.with_yield_rate("ETH", dec!(0.035));
+
+        assert_eq!(snapshot.get_current_price("ETH").unwrap(), dec!(2280.25));
+        assert_eq!(snapshot.get_historical_prices("ETH", 3).unwrap(), [dec!(2000), dec!(2200), dec!(2000)]);
+        assert_eq!(snapshot.get_historical_prices("ETH", 100).unwrap().len(), 11);
+        // Ten returns of +/- ln(1.1): sample variance 10/9 ln(1.1)^2
+        let expected = (10.0f64 / 9.0).sqrt() * 1.1f64.ln() * 365f64.sqrt();
+        assert!((snapshot.get_volatility("ETH").unwrap().to_f64().unwrap() - expected).abs() < 1e-9);
+        assert!(snapshot.get_volatility("BTC").unwrap().to_f64().unwrap() < 1e-6);
+        assert_eq!(snapshot.get_yield_rate("ETH").unwrap(), dec!(0.035));
+        assert_eq!(snapshot.get_yield_rate("BTC").unwrap(), Decimal::ZERO);
+        let error = snapshot.get_current_price("SOL").unwrap_err().to_string();
+        assert_eq!(error, "SOL is not in the snapshot; it has BTC, ETH");
+    }
+
+    #[test]
+    fn market_charts_parse_from_millisecond_points() {
+        let chart: MarketChart = serde_json::from_str(&fixture("market_chart_ethereum.json")).unwrap();
+        assert_eq!(chart.prices.len(), 11);
+        assert_eq!(chart.prices[0], (1_704_067_200_000.0, 2000.0));
+        let provider = HttpMarketDataProvider::coingecko();
+        assert!(provider.decimal("url", 0.0).is_err());
+        assert!(provider.decimal("url", f64::NAN).is_err());
+        assert!(daily_volatility("ETH", &[dec!(1), dec!(2)]).is_err());
+    }
+}
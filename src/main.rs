@@ -1,12 +1,32 @@
 use clap::{Parser, Subcommand};
-use tracing::{info, error};
+use indicatif::{ProgressBar, ProgressStyle};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::info;
 use vaulta_simulator::{
-    backtest::BacktestEngine,
-    monte_carlo::MonteCarloEngine,
-    simulator::Simulator,
+    backtest::{
+        BacktestAxis, BacktestConfig, BacktestEngine, BacktestMetric, BacktestParameter, BacktestSweep, Slippage,
+        UnpricedPositions,
+    },
+    builder::SimulatorBuilder,
+    data_source::{BarFrequency, CsvDataSource},
+    experiments::{ExperimentRecord, ExperimentStore},
+    liquidity::PriceImpact,
+    market::MockMarketDataProvider,
+    monte_carlo::{MonteCarloEngine, SamplingMode, SweepParameter, SweepSpec, VarianceReduction},
+    optimizer::StrategyOptimizer,
+    shocks::ShockDistribution,
+    simulator::{Benchmark, Simulator, YieldIncome},
     strategy::Strategy,
+    stress::StressLibrary,
     types::*,
+    walk_forward::WalkForwardRunner,
 };
+#[cfg(feature = "sqlite")]
+use vaulta_simulator::sqlite::SqliteDataSource;
 
 #[derive(Parser)]
 #[command(name = "vaulta-simulator")]
@@ -16,7 +36,9 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once per run, so the size of the larger variants doesn't matter
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Run a single simulation with specified parameters
     Simulate {
@@ -29,6 +51,15 @@ enum Commands {
         /// Strategy name to use
         #[arg(short, long, default_value = "conservative")]
         strategy: String,
+        /// Record the run in an experiment store at this directory
+        #[arg(long)]
+        record: Option<PathBuf>,
+        /// Print a one-line summary after every step
+        #[arg(short, long)]
+        verbose: bool,
+        /// Price the mock market from this TOML file of [[asset]] tables
+        #[arg(long)]
+        universe: Option<PathBuf>,
     },
     /// Run Monte Carlo stress testing
     MonteCarlo {
@@ -41,6 +72,52 @@ enum Commands {
         /// Confidence level (0.0 to 1.0)
         #[arg(short, long, default_value = "0.95")]
         confidence: f64,
+        /// Strategy each path runs
+        #[arg(long, default_value = "balanced")]
+        strategy: String,
+        /// Initial capital per path
+        #[arg(long, default_value = "1000000.0")]
+        capital: f64,
+        /// Time steps per path
+        #[arg(long, default_value = "100")]
+        steps: usize,
+        /// Report the probability that a path's drawdown exceeds this percentage
+        #[arg(long)]
+        drawdown_threshold: Option<f64>,
+        /// Count a path as ruined if it ever falls below this percentage of capital
+        #[arg(long, default_value = "50.0")]
+        ruin_threshold: f64,
+        /// Histogram bins for the printed distribution; Freedman–Diaconis when omitted
+        #[arg(long)]
+        bins: Option<usize>,
+        /// Run paths in antithetic pairs to reduce the variance of the estimates
+        #[arg(long)]
+        antithetic: bool,
+        /// Also report statistics at these steps, e.g. 30,90,365
+        #[arg(long, value_delimiter = ',')]
+        horizons: Vec<usize>,
+        /// Report the probability of reaching this portfolio value, and when
+        #[arg(long)]
+        target: Option<f64>,
+        /// Report the probability of the portfolio value falling below this at any step
+        #[arg(long)]
+        floor: Option<f64>,
+        /// Write one row per iteration to this file (CSV, or Parquet for a
+        /// `.parquet` path when built with the `parquet` feature)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Draw normal price shocks from a Sobol sequence instead of the PRNG
+        #[arg(long)]
+        sobol: bool,
+        /// Draw price shocks from a Student's t with these degrees of freedom instead of a normal
+        #[arg(long)]
+        student_t: Option<f64>,
+        /// Estimate quantiles from streaming sketches instead of keeping every path
+        #[arg(long, conflicts_with = "output")]
+        memory_light: bool,
+        /// Price the mock market from this TOML file of [[asset]] tables
+        #[arg(long)]
+        universe: Option<PathBuf>,
     },
     /// Run backtesting on historical data
     Backtest {
@@ -51,11 +128,317 @@ enum Commands {
         #[arg(short, long)]
         end_date: String,
         /// Strategy name
-        #[arg(short, long, default_value = "balanced")]
+        #[arg(long, default_value = "balanced")]
         strategy: String,
+        /// Passive benchmark: a symbol (BTC) or weighted basket (BTC:0.6,USDC:0.4)
+        #[arg(long)]
+        benchmark: Option<String>,
+        /// CSV of bars (timestamp, symbol, open, high, low, close, volume), or a SQLite bar store
+        /// (.db, .sqlite) with the `sqlite` feature; mock data when omitted
+        #[arg(long)]
+        data: Option<PathBuf>,
+        /// Start from this portfolio (JSON) instead of all cash, valued at the data's prices
+        #[arg(long)]
+        initial_portfolio: Option<PathBuf>,
+        /// Hold initial positions the data has no prices for at their stored
+        /// prices instead of failing
+        #[arg(long, requires = "initial_portfolio")]
+        freeze_unpriced: bool,
+        /// Bar length to step on: hourly, daily or weekly; finer data is resampled
+        #[arg(long, default_value = "daily")]
+        bar_frequency: String,
+        /// Commission in basis points of notional [default: 10]
+        #[arg(long)]
+        commission_bps: Option<Decimal>,
+        /// Commission per trade, on top of --commission-bps [default: 0]
+        #[arg(long)]
+        commission_fixed: Option<Decimal>,
+        /// Slippage in basis points of notional [default: 5]
+        #[arg(long)]
+        slippage_bps: Option<Decimal>,
+        /// Slippage in basis points for a trade the size of the day's dollar
+        /// volume, scaled by the trade's share of it
+        #[arg(long, conflicts_with = "slippage_bps")]
+        volume_impact_bps: Option<Decimal>,
+        /// Square-root price impact against the day's dollar volume: the
+        /// impact, as a fraction of price, of a trade the size of the volume
+        #[arg(long, conflicts_with_all = ["slippage_bps", "volume_impact_bps"])]
+        sqrt_impact: Option<f64>,
+        /// Run the strategy every this many days instead of every bar
+        #[arg(long)]
+        rebalance_days: Option<u32>,
+        /// Annual return, as a fraction, the Sortino ratio counts shortfalls below
+        #[arg(long, default_value = "0.0")]
+        min_acceptable_return: f64,
+        /// Reinvest yield income (from an `apy` column in --data) in the paying position instead of crediting cash
+        #[arg(long)]
+        reinvest_yield: bool,
+        /// Log progress every this many bars
+        #[arg(long)]
+        progress_days: Option<usize>,
+        /// Hold out this fraction of the range, at the end, and report it separately
+        #[arg(long, conflicts_with_all = ["output", "record", "report", "trades", "events"])]
+        oos_split: Option<f64>,
+        /// Backtest every window of this many days instead and report the spread of outcomes
+        #[arg(long, conflicts_with_all = ["oos_split", "output", "record", "report", "trades", "events"])]
+        rolling_days: Option<usize>,
+        /// Rerun at each of these costs, in basis points, e.g. 0,5,10,25,50, and report
+        /// return, Sharpe and the break-even cost
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with_all = ["oos_split", "rolling_days", "output", "record", "report", "trades", "events"]
+        )]
+        cost_levels: Vec<f64>,
+        /// Days between the starts of consecutive rolling windows
+        #[arg(long, default_value = "30", requires = "rolling_days")]
+        rolling_stride_days: usize,
+        /// Write the equity curve (date, value, drawdown, cash) as CSV
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Write a self-contained report: Markdown for a `.md` path, HTML otherwise
+        #[arg(long)]
+        report: Option<PathBuf>,
+        /// Write one CSV row per trade: times, symbol, quantity, prices, fees, PnL and holding period
+        #[arg(long)]
+        trades: Option<PathBuf>,
+        /// Write every simulator event of the run as JSON lines
+        #[arg(long)]
+        events: Option<PathBuf>,
+        /// Record the run in an experiment store at this directory
+        #[arg(long)]
+        record: Option<PathBuf>,
+    },
+    /// Evaluate a strategy out of sample on rolling or anchored train/test folds
+    WalkForward {
+        /// Start date (YYYY-MM-DD)
+        #[arg(short, long)]
+        start_date: String,
+        /// End date (YYYY-MM-DD)
+        #[arg(short, long)]
+        end_date: String,
+        /// Strategy name
+        #[arg(long, default_value = "balanced")]
+        strategy: String,
+        /// Days in each training window
+        #[arg(long, default_value = "60")]
+        train_days: usize,
+        /// Days in each test window
+        #[arg(long, default_value = "30")]
+        test_days: usize,
+        /// Start every training window at the start date instead of rolling it
+        #[arg(long)]
+        anchored: bool,
+        /// Pick each fold's allocation fraction by training-window Sharpe ratio
+        #[arg(long)]
+        optimize: bool,
+        /// Write one CSV row per fold
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Backtest every combination of one or two parameters' values and print a metric's matrix
+    Sweep {
+        /// Start date (YYYY-MM-DD)
+        #[arg(short, long)]
+        start_date: String,
+        /// End date (YYYY-MM-DD)
+        #[arg(short, long)]
+        end_date: String,
+        /// Strategy name
+        #[arg(long, default_value = "balanced")]
+        strategy: String,
+        /// CSV of bars (timestamp, symbol, open, high, low, close, volume), or a SQLite bar store
+        /// (.db, .sqlite) with the `sqlite` feature; mock data when omitted
+        #[arg(long)]
+        data: Option<PathBuf>,
+        /// Parameter across the columns: allocation_fraction, rebalance_days, commission_bps or slippage_bps
+        #[arg(long)]
+        x: String,
+        /// Values of the column parameter, e.g. 0.25,0.5,0.75,1
+        #[arg(long, value_delimiter = ',', required = true)]
+        x_values: Vec<f64>,
+        /// Parameter down the rows, for a two-parameter sweep
+        #[arg(long, requires = "y_values")]
+        y: Option<String>,
+        /// Values of the row parameter
+        #[arg(long, value_delimiter = ',', requires = "y")]
+        y_values: Vec<f64>,
+        /// Metric to show: total_return, annualized_return, sharpe, sortino, max_drawdown,
+        /// win_rate, costs or turnover
+        #[arg(long, default_value = "sharpe")]
+        metric: String,
+        /// Write the metric's matrix as CSV, for a heatmap
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Compare strategies on identical Monte Carlo paths
+    Compare {
+        /// Strategies to compare (at least two)
+        #[arg(required = true, num_args = 2..)]
+        strategies: Vec<String>,
+        /// Number of Monte Carlo iterations
+        #[arg(short, long, default_value = "10000")]
+        iterations: usize,
+        /// Number of scenarios to generate
+        #[arg(short, long, default_value = "100")]
+        scenarios: usize,
+        /// Confidence level (0.0 to 1.0)
+        #[arg(short, long, default_value = "0.95")]
+        confidence: f64,
+        /// Initial capital per path
+        #[arg(long, default_value = "1000000.0")]
+        capital: f64,
+        /// Time steps per path
+        #[arg(long, default_value = "100")]
+        steps: usize,
+        /// Master seed; random when omitted
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Sweep one parameter over a range on shared seeded paths
+    Sensitivity {
+        /// Parameter to sweep: allocation_fraction, volatility_multiplier or fee_bps
+        parameter: String,
+        /// First value of the sweep
+        #[arg(long)]
+        from: f64,
+        /// Last value of the sweep
+        #[arg(long)]
+        to: f64,
+        /// Number of values from `from` to `to` inclusive
+        #[arg(long, default_value = "5")]
+        points: usize,
+        /// Strategy to run
+        #[arg(long, default_value = "balanced")]
+        strategy: String,
+        /// Number of Monte Carlo iterations per value
+        #[arg(short, long, default_value = "10000")]
+        iterations: usize,
+        /// Number of scenarios to generate
+        #[arg(short, long, default_value = "100")]
+        scenarios: usize,
+        /// Confidence level (0.0 to 1.0)
+        #[arg(short, long, default_value = "0.95")]
+        confidence: f64,
+        /// Initial capital per path
+        #[arg(long, default_value = "1000000.0")]
+        capital: f64,
+        /// Time steps per path
+        #[arg(long, default_value = "100")]
+        steps: usize,
+        /// Master seed; random when omitted
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Write one CSV row per swept value
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run named, deterministic stress scenarios
+    Stress {
+        /// Extra scenario definitions (TOML); same-named built-ins are replaced
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+        #[command(subcommand)]
+        command: StressCommands,
     },
     /// List available strategies
     Strategies,
+    /// Inspect recorded experiments
+    Experiments {
+        /// Experiment store directory
+        #[arg(short, long, default_value = "experiments")]
+        dir: PathBuf,
+        #[command(subcommand)]
+        command: ExperimentCommands,
+    },
+    /// Import CSV bars into a SQLite bar store for --data, creating it if needed
+    #[cfg(feature = "sqlite")]
+    Ingest {
+        /// Database to write to
+        #[arg(long)]
+        db: PathBuf,
+        /// CSV files of bars (timestamp, symbol, open, high, low, close, volume)
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum StressCommands {
+    /// List the available scenarios
+    List,
+    /// Run scenarios by name
+    Run {
+        /// Scenario names; all scenarios when omitted
+        names: Vec<String>,
+        /// Strategy to stress
+        #[arg(short, long, default_value = "balanced")]
+        strategy: String,
+        /// Initial capital
+        #[arg(short, long, default_value = "1000000.0")]
+        capital: f64,
+        /// Seed for the random walk underneath the scenario events
+        #[arg(long, default_value = "42")]
+        seed: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExperimentCommands {
+    /// List recorded runs, oldest first
+    List {
+        /// Only show runs for this strategy
+        #[arg(short, long)]
+        strategy: Option<String>,
+        /// Show only the top N runs by Sharpe ratio
+        #[arg(short, long)]
+        top: Option<usize>,
+    },
+    /// Show a single run
+    Show {
+        id: String,
+    },
+    /// Compare runs side by side
+    Compare {
+        ids: Vec<String>,
+    },
+}
+
+/// Backtest on the bars at `path`: a SQLite bar store for a `.db` or `.sqlite`
+/// file, CSV otherwise
+async fn with_data(engine: BacktestEngine, path: &Path) -> anyhow::Result<BacktestEngine> {
+    let sqlite = path.extension().is_some_and(|ext| ext == "db" || ext == "sqlite");
+    #[cfg(feature = "sqlite")]
+    if sqlite {
+        return engine.with_data_source(&SqliteDataSource::open(path)?, &[]).await;
+    }
+    if sqlite {
+        return Err(anyhow::anyhow!("SQLite data needs the `sqlite` feature"));
+    }
+    engine.with_data_source(&CsvDataSource::new(path), &[]).await
+}
+
+/// An optional ratio to four decimals, or "n/a"
+fn ratio(value: Option<f64>) -> String {
+    value.map_or("n/a".to_string(), |value| format!("{:.4}", value))
+}
+
+fn print_experiment_table(records: &[&ExperimentRecord]) {
+    println!(
+        "{:<36}  {:<10}  {:<16}  {:>12}  {:>8}  {:>8}",
+        "id", "kind", "strategy", "return %", "sharpe", "max dd %"
+    );
+    for record in records {
+        println!(
+            "{:<36}  {:<10}  {:<16}  {:>12.2}  {:>8.4}  {:>8.2}",
+            record.id,
+            format!("{:?}", record.kind),
+            record.strategy,
+            record.metrics.total_return_pct,
+            record.metrics.sharpe_ratio,
+            record.metrics.max_drawdown_pct,
+        );
+    }
 }
 
 #[tokio::main]
@@ -78,16 +461,35 @@ async fn main() -> anyhow::Result<()> {
             capital,
             steps,
             strategy,
+            record,
+            verbose,
+            universe,
         } => {
             info!("Running simulation with capital: {}, steps: {}, strategy: {}", 
                   capital, steps, strategy);
             
-            let strategy = Strategy::from_name(&strategy)?;
-            let mut simulator = Simulator::new(capital, strategy);
+            let strategy_name = strategy;
+            let strategy = Strategy::from_name(&strategy_name)?;
+            let mut simulator = match &universe {
+                Some(path) => {
+                    let mock = MockMarketDataProvider::from_toml(path)?;
+                    info!("Loaded {} assets from {}", mock.symbols().len(), path.display());
+                    SimulatorBuilder::new()
+                        .capital(capital)
+                        .strategy(strategy)
+                        .universe(mock.assets()?)
+                        .correlations(mock.correlations().clone())
+                        .provider(mock)
+                        .build()?
+                }
+                None => Simulator::new(capital, strategy),
+            };
             
             for step in 0..steps {
-                simulator.step()?;
-                if step % 10 == 0 {
+                let outcome = simulator.step()?;
+                if verbose {
+                    println!("{}", outcome);
+                } else if step % 10 == 0 {
                     info!("Step {}: Portfolio value = {:.2}", 
                           step, simulator.portfolio_value());
                 }
@@ -98,47 +500,629 @@ async fn main() -> anyhow::Result<()> {
             info!("Final portfolio value: {:.2}", results.final_value);
             info!("Total return: {:.2}%", results.total_return_pct);
             info!("Sharpe ratio: {:.4}", results.sharpe_ratio);
+            info!("Sortino ratio: {}, Calmar ratio: {}", ratio(results.sortino_ratio), ratio(results.calmar_ratio));
+            
+            if let Some(dir) = record {
+                let metadata = HashMap::from([
+                    ("capital".to_string(), capital.to_string()),
+                    ("steps".to_string(), steps.to_string()),
+                ]);
+                let mut store = ExperimentStore::open(&dir)?;
+                let entry = store.log_simulation(&strategy_name, &results, metadata)?;
+                info!("Recorded experiment {}", entry.id);
+            }
         }
         
         Commands::MonteCarlo {
             iterations,
             scenarios,
             confidence,
+            strategy,
+            capital,
+            steps,
+            drawdown_threshold,
+            ruin_threshold,
+            bins,
+            antithetic,
+            horizons,
+            target,
+            floor,
+            output,
+            sobol,
+            student_t,
+            memory_light,
+            universe,
         } => {
             info!("Running Monte Carlo stress test...");
             info!("Iterations: {}, Scenarios: {}, Confidence: {}", 
                   iterations, scenarios, confidence);
             
-            let mut engine = MonteCarloEngine::new(iterations, scenarios);
-            let results = engine.run_stress_test(confidence).await?;
+            let mut builder = MonteCarloEngine::builder()
+                .iterations(iterations)
+                .scenarios(scenarios)
+                .confidence(confidence)
+                .strategy(Strategy::from_name(&strategy)?)
+                .capital(capital)
+                .steps(steps)
+                .ruin_threshold(ruin_threshold);
+            if let Some(threshold) = drawdown_threshold {
+                builder = builder.drawdown_threshold(threshold);
+            }
+            if !horizons.is_empty() {
+                builder = builder.horizons(horizons);
+            }
+            if let Some(target) = target {
+                builder = builder.target(target);
+            }
+            if let Some(floor) = floor {
+                builder = builder.floor(floor);
+            }
+            if sobol {
+                builder = builder.sampling(SamplingMode::Sobol);
+            }
+            if antithetic {
+                builder = builder.variance_reduction(VarianceReduction::Antithetic);
+            }
+            if let Some(degrees_of_freedom) = student_t {
+                builder = builder.shock_distribution(ShockDistribution::student_t(degrees_of_freedom));
+            }
+            if memory_light {
+                builder = builder.memory_light();
+            }
+            if let Some(path) = &universe {
+                let mock = MockMarketDataProvider::from_toml(path)?;
+                info!("Loaded {} assets from {}", mock.symbols().len(), path.display());
+                builder = builder.universe(mock.assets()?).correlations(mock.correlations().clone());
+            }
+            
+            let bar = ProgressBar::new(iterations as u64);
+            bar.set_style(
+                ProgressStyle::with_template("{bar:40} {pos}/{len} paths ({eta} left)")?,
+            );
+            let progress_bar = bar.clone();
+            let cancel = Arc::new(AtomicBool::new(false));
+            let ctrl_c = cancel.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    ctrl_c.store(true, Ordering::Relaxed);
+                }
+            });
+            let mut engine = builder
+                .progress((iterations / 200).max(1), move |done, _| {
+                    progress_bar.set_position(done as u64)
+                })
+                .cancellation(cancel)
+                .build()?;
+            let results = engine.run().await?;
+            bar.finish_and_clear();
             
+            if results.incomplete {
+                info!("Cancelled; results cover {} of {} iterations", results.iterations, iterations);
+            }
             info!("Monte Carlo analysis complete!");
-            info!("Expected value: {:.2}", results.expected_value);
-            info!("Value at Risk ({}%): {:.2}", 
-                  confidence * 100.0, results.value_at_risk);
-            info!("Conditional VaR: {:.2}", results.conditional_var);
-            info!("Max drawdown: {:.2}%", results.max_drawdown_pct);
+            info!("Strategy: {}, capital: {:.2}, steps: {}, seed: {}",
+                  results.strategy, results.initial_capital, results.steps_per_iteration, results.seed);
+            info!("Expected value: {:.2} (standard error {:.2}), median {:.2}",
+                  results.expected_value, results.standard_error, results.median_value);
+            info!("Skewness {:.3}, excess kurtosis {:.3}, tail ratio {}",
+                  results.skewness, results.excess_kurtosis,
+                  results.tail_ratio.map_or("n/a".to_string(), |ratio| format!("{:.3}", ratio)));
+            info!("Value at Risk ({}%): {:.2} ({:.2}% of capital)", 
+                  confidence * 100.0, results.value_at_risk, results.var_pct);
+            info!("Conditional VaR: {:.2} ({:.2}% of capital)",
+                  results.conditional_var, results.cvar_pct);
+            info!("P(loss): {:.2}%", results.prob_of_loss * 100.0);
+            info!("P(ruin below {:.0}% of capital): {:.2}%",
+                  results.ruin_threshold_pct, results.prob_of_ruin * 100.0);
+            info!("Max drawdown: mean {:.2}%, median {:.2}%, p95 {:.2}%, worst {:.2}%",
+                  results.drawdown.mean_pct, results.drawdown.median_pct,
+                  results.drawdown.p95_pct, results.drawdown.worst_pct);
+            if let (Some(threshold), Some(probability)) =
+                (results.drawdown.threshold_pct, results.drawdown.exceed_probability)
+            {
+                info!("P(drawdown > {:.2}%): {:.2}%", threshold, probability * 100.0);
+            }
+            let durations = &results.drawdown_durations;
+            info!("Longest drawdown: mean {:.1}, median {:.1}, p95 {:.1}, worst {} steps; {:.2}% of steps underwater",
+                  durations.mean_longest_steps, durations.median_longest_steps,
+                  durations.p95_longest_steps, durations.worst_longest_steps, durations.mean_underwater_pct);
+            info!("Recovery from max drawdown: mean {} steps, P(unrecovered) {:.2}%",
+                  durations.mean_recovery_steps.map_or("n/a".to_string(), |steps| format!("{:.1}", steps)),
+                  durations.unrecovered_probability * 100.0);
+            for horizon in &results.horizons {
+                info!("Step {}: expected {:.2}, VaR {:.2} ({:.2}%), CVaR {:.2} ({:.2}%), mean dd {:.2}%, P(loss) {:.2}%",
+                      horizon.step, horizon.expected_value, horizon.value_at_risk, horizon.var_pct,
+                      horizon.conditional_var, horizon.cvar_pct, horizon.mean_drawdown_pct,
+                      horizon.prob_of_loss * 100.0);
+            }
+            if let Some(goal) = &results.goal {
+                if let (Some(target), Some(probability)) = (goal.target_value, goal.prob_hit_target) {
+                    info!("P(reach {:.2}): {:.2}%", target, probability * 100.0);
+                }
+                if let Some(time) = &goal.time_to_target {
+                    info!("Steps to target: mean {:.1}, median {:.1}, p90 {:.1}",
+                          time.mean_steps, time.median_steps, time.p90_steps);
+                }
+                if let (Some(floor), Some(probability)) = (goal.floor_value, goal.prob_breach_floor) {
+                    info!("P(fall below {:.2}): {:.2}%", floor, probability * 100.0);
+                }
+            }
+            if let Some(tail) = results.tail_summary(1.0 - confidence) {
+                info!("Worst {:.0}% of paths: mean return {:.2}%, Sharpe {:.2}, fees {:.2}, halted {:.2}%",
+                      (1.0 - confidence) * 100.0, tail.mean_return_pct, tail.mean_sharpe_ratio,
+                      tail.mean_fees, tail.halt_probability * 100.0);
+            }
+            
+            println!("\nFinal value distribution:");
+            print!("{}", results.histogram(bins).render(50));
+            
+            if let Some(path) = output {
+                let parquet = path.extension().is_some_and(|ext| ext == "parquet");
+                #[cfg(feature = "parquet")]
+                if parquet {
+                    results.write_parquet(&path)?;
+                    info!("Wrote {} paths to {}", results.paths.len(), path.display());
+                    return Ok(());
+                }
+                if parquet {
+                    return Err(anyhow::anyhow!("Parquet output needs the `parquet` feature"));
+                }
+                results.write_csv(&path)?;
+                info!("Wrote {} paths to {}", results.iterations, path.display());
+            }
         }
         
         Commands::Backtest {
             start_date,
             end_date,
             strategy,
+            benchmark,
+            data,
+            initial_portfolio,
+            freeze_unpriced,
+            bar_frequency,
+            commission_bps,
+            commission_fixed,
+            slippage_bps,
+            volume_impact_bps,
+            sqrt_impact,
+            rebalance_days,
+            min_acceptable_return,
+            reinvest_yield,
+            progress_days,
+            oos_split,
+            rolling_days,
+            rolling_stride_days,
+            cost_levels,
+            output,
+            report,
+            trades,
+            events,
+            record,
         } => {
             info!("Running backtest from {} to {} with strategy: {}", 
                   start_date, end_date, strategy);
             
-            let strategy = Strategy::from_name(&strategy)?;
+            let strategy_name = strategy;
+            let strategy = Strategy::from_name(&strategy_name)?;
             let mut engine = BacktestEngine::new(&start_date, &end_date, strategy)?;
+            if let Some(path) = &data {
+                engine = with_data(engine, path).await?;
+            }
+            if let Some(spec) = &benchmark {
+                engine = engine.with_benchmark(Benchmark::from_spec(spec)?);
+            }
+            if let Some(path) = &initial_portfolio {
+                engine = engine.with_initial_portfolio(Portfolio::read_json(path)?);
+            }
+            let mut config = BacktestConfig {
+                bar_frequency: BarFrequency::from_name(&bar_frequency)?,
+                ..BacktestConfig::default()
+            };
+            if let Some(bps) = commission_bps {
+                config.commission_bps = bps;
+            }
+            if let Some(fixed) = commission_fixed {
+                config.commission_fixed = fixed;
+            }
+            if let Some(bps) = slippage_bps {
+                config.slippage = Slippage::Bps(bps);
+            }
+            if let Some(impact_bps) = volume_impact_bps {
+                config.slippage = Slippage::VolumeShare { impact_bps };
+            }
+            if let Some(coefficient) = sqrt_impact {
+                config.slippage = Slippage::SquareRoot(PriceImpact::new(coefficient)?);
+            }
+            config.rebalance_days = rebalance_days;
+            config.minimum_acceptable_return = min_acceptable_return;
+            if reinvest_yield {
+                config.yield_income = YieldIncome::Reinvest;
+            }
+            if freeze_unpriced {
+                config.unpriced_positions = UnpricedPositions::Freeze;
+            }
+            config.progress_every_days = progress_days;
+            config.record_events = events.is_some();
+            engine = engine.with_config(config)?;
+            
+            if let Some(fraction) = oos_split {
+                let split = engine.run_out_of_sample(fraction).await?;
+                info!("Split at {}", split.split_date.date());
+                for (segment, results) in [("In-sample", &split.in_sample), ("Out-of-sample", &split.out_of_sample)] {
+                    info!("{}: return {:.2}%, annualized {:.2}%, Sharpe {:.4}, max drawdown {:.2}%",
+                          segment, results.total_return_pct, results.annualized_return_pct,
+                          results.sharpe_ratio, results.max_drawdown_pct);
+                }
+                info!("Degradation ratio (out-of-sample / in-sample Sharpe): {}",
+                      ratio(split.degradation_ratio));
+                return Ok(());
+            }
+            
+            if let Some(window_days) = rolling_days {
+                let rolling = engine.rolling(window_days, rolling_stride_days).await?;
+                info!("{} windows of {} days, every {} days ({} skipped)",
+                      rolling.windows.len(), rolling.window_days, rolling.stride_days, rolling.skipped_windows);
+                println!("{:<16}  {:>10}  {:>10}  {:>10}", "", "Min", "Median", "Max");
+                for (metric, range) in [
+                    ("Return %", rolling.total_return),
+                    ("Sharpe", rolling.sharpe_ratio),
+                    ("Max drawdown %", rolling.max_drawdown),
+                ] {
+                    println!("{:<16}  {:>10.2}  {:>10.2}  {:>10.2}", metric, range.min, range.median, range.max);
+                }
+                let worst = &rolling.worst_window;
+                info!("Worst window: {} to {}, return {:.2}%, max drawdown {:.2}%",
+                      worst.start_date.date(), worst.end_date.date(), worst.total_return_pct, worst.max_drawdown_pct);
+                return Ok(());
+            }
+            
+            if !cost_levels.is_empty() {
+                let sensitivity = engine.cost_sensitivity(&cost_levels).await?;
+                println!("{:>10}  {:>10}  {:>10}  {:>14}  {:>16}", "Cost bps", "Return %", "Sharpe", "Costs", "Turnover");
+                for point in &sensitivity.points {
+                    println!("{:>10.2}  {:>10.2}  {:>10.4}  {:>14.2}  {:>16.2}",
+                             point.cost_bps, point.total_return_pct, point.sharpe_ratio, point.total_costs, point.turnover);
+                }
+                info!("Break-even cost: {}",
+                      sensitivity.break_even_bps.map_or("n/a".to_string(), |bps| format!("{:.2}bps", bps)));
+                return Ok(());
+            }
             
             let results = engine.run().await?;
             
             info!("Backtest complete!");
             info!("Total return: {:.2}%", results.total_return_pct);
+            info!("Price return: {:.2}%, income return: {:.2}%", results.price_return_pct, results.income_return_pct);
             info!("Annualized return: {:.2}%", results.annualized_return_pct);
             info!("Volatility: {:.2}%", results.volatility_pct);
             info!("Sharpe ratio: {:.4}", results.sharpe_ratio);
+            info!("Sortino ratio: {}, Calmar ratio: {}", ratio(results.sortino_ratio), ratio(results.calmar_ratio));
             info!("Max drawdown: {:.2}%", results.max_drawdown_pct);
+            info!("Trades: {} ({} closed), win rate {:.2}%, profit factor {}",
+                  results.trades.len(),
+                  results.trades.iter().filter(|trade| trade.exit_time.is_some()).count(),
+                  results.win_rate * 100.0,
+                  results.profit_factor.map_or("n/a".to_string(), |factor| format!("{:.2}", factor)));
+            info!("Turnover: {:.2}, commission {:.2}, slippage {:.2}",
+                  results.turnover, results.total_commission, results.total_slippage);
+            if let Some(contribution) = &results.initial_portfolio {
+                info!("Initial positions worth {:.2}: P&L {:.2} held untouched, routing decisions {:.2}",
+                      contribution.positions_value, contribution.positions_pnl, contribution.decisions_pnl);
+                if !contribution.frozen_symbols.is_empty() {
+                    info!("Frozen at stored prices: {}", contribution.frozen_symbols.join(", "));
+                }
+            }
+            let quality = &results.data_quality;
+            info!("Data: {} of {} bars stepped, {} gaps filled, {} outliers",
+                  quality.calendar_days - quality.dropped_days,
+                  quality.calendar_days,
+                  quality.symbols.iter().map(|symbol| symbol.gaps_filled).sum::<usize>(),
+                  quality.symbols.iter().map(|symbol| symbol.outliers).sum::<usize>());
+            if let Some(benchmark_return) = results.benchmark_return_pct {
+                info!("Benchmark return: {:.2}%, alpha {:.2}%, beta {}",
+                      benchmark_return, results.alpha_pct.unwrap_or(0.0), ratio(results.beta));
+                info!("Tracking error: {:.2}%, information ratio {}",
+                      results.tracking_error.unwrap_or(0.0), ratio(results.information_ratio));
+            }
+            
+            for year in &results.annual_returns {
+                info!("{} return: {:.2}%{}", year.year, year.return_pct,
+                      if year.partial { " (partial year)" } else { "" });
+            }
+            
+            if !results.drawdowns.is_empty() {
+                println!("\n{:>8}  {:<10}  {:<10}  {:<10}  {:>6}", "Depth", "Start", "Trough", "Recovery", "Days");
+                for drawdown in &results.drawdowns {
+                    println!(
+                        "{:>7.2}%  {:<10}  {:<10}  {:<10}  {:>6}",
+                        drawdown.depth_pct,
+                        drawdown.start.date(),
+                        drawdown.trough.date(),
+                        drawdown.recovery.map_or("-".to_string(), |recovery| recovery.date().to_string()),
+                        drawdown.length_days
+                    );
+                }
+            }
+            
+            if let Some(path) = output {
+                results.write_csv(&path)?;
+                info!("Wrote {} equity curve points to {}", results.equity_curve.len(), path.display());
+            }
+            
+            if let Some(path) = report {
+                results.write_report(&path)?;
+                info!("Wrote report to {}", path.display());
+            }
+            
+            if let Some(path) = trades {
+                results.write_trades_csv(&path)?;
+                info!("Wrote {} trades to {}", results.trades.len(), path.display());
+            }
+            
+            if let Some(path) = events {
+                results.write_events_jsonl(&path)?;
+                info!("Wrote {} events to {}", results.events.as_ref().map_or(0, |log| log.len()), path.display());
+            }
+            
+            if let Some(dir) = record {
+                let metadata = HashMap::from([
+                    ("start_date".to_string(), start_date),
+                    ("end_date".to_string(), end_date),
+                ]);
+                let mut store = ExperimentStore::open(&dir)?;
+                let entry = store.log_backtest(&strategy_name, &results, metadata)?;
+                info!("Recorded experiment {}", entry.id);
+            }
+        }
+        
+        Commands::WalkForward {
+            start_date,
+            end_date,
+            strategy,
+            train_days,
+            test_days,
+            anchored,
+            optimize,
+            output,
+        } => {
+            info!("Running walk-forward analysis from {} to {} with strategy: {}",
+                  start_date, end_date, strategy);
+            
+            let engine = BacktestEngine::new(&start_date, &end_date, Strategy::from_name(&strategy)?)?;
+            let mut runner = WalkForwardRunner::new(engine, train_days, test_days)?;
+            if anchored {
+                runner = runner.anchored();
+            }
+            if optimize {
+                runner = runner.with_optimizer(StrategyOptimizer::new());
+            }
+            
+            let results = runner.run().await?;
+            
+            info!("Walk-forward complete: {} folds run, {} skipped",
+                  results.folds.len(), results.skipped_folds);
+            info!("Combined out-of-sample return: {:.2}%", results.combined_return_pct);
+            info!("Test Sharpe: mean {:.4}, std dev {:.4}, min {:.4}, max {:.4}",
+                  results.mean_sharpe, results.sharpe_std_dev, results.min_sharpe, results.max_sharpe);
+            
+            if let Some(path) = output {
+                results.write_csv(&path)?;
+                info!("Wrote {} folds to {}", results.folds.len(), path.display());
+            }
+        }
+        
+        Commands::Sweep {
+            start_date,
+            end_date,
+            strategy,
+            data,
+            x,
+            x_values,
+            y,
+            y_values,
+            metric,
+            output,
+        } => {
+            let metric = BacktestMetric::from_name(&metric)?;
+            let mut sweep = BacktestSweep::new(BacktestAxis::new(BacktestParameter::from_name(&x)?, x_values));
+            if let Some(y) = &y {
+                sweep = sweep.with_y_axis(BacktestAxis::new(BacktestParameter::from_name(y)?, y_values));
+            }
+            let mut engine = BacktestEngine::new(&start_date, &end_date, Strategy::from_name(&strategy)?)?;
+            if let Some(path) = &data {
+                engine = with_data(engine, path).await?;
+            }
+            let results = engine.sweep(&sweep).await?;
+            
+            let corner = match results.y_parameter {
+                Some(y) => format!("{} \\ {}", y.name(), results.x_parameter.name()),
+                None => results.x_parameter.name().to_string(),
+            };
+            print!("{:>34}", corner);
+            for value in &results.x_values {
+                print!("  {:>12}", value);
+            }
+            println!();
+            let labels: Vec<String> = match results.y_parameter {
+                Some(_) => results.y_values.iter().map(f64::to_string).collect(),
+                None => vec![metric.name().to_string()],
+            };
+            for (label, row) in labels.iter().zip(results.matrix(metric)) {
+                print!("{:>34}", label);
+                for value in row {
+                    print!("  {:>12}", value.map_or("-".to_string(), |value| format!("{:.4}", value)));
+                }
+                println!();
+            }
+            
+            if let Some(path) = output {
+                results.write_csv(&path, metric)?;
+                info!("Wrote {} matrix to {}", metric.name(), path.display());
+            }
+        }
+        
+        Commands::Compare {
+            strategies,
+            iterations,
+            scenarios,
+            confidence,
+            capital,
+            steps,
+            seed,
+        } => {
+            let strategies = strategies
+                .iter()
+                .map(|name| Strategy::from_name(name))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let mut builder = MonteCarloEngine::builder()
+                .iterations(iterations)
+                .scenarios(scenarios)
+                .confidence(confidence)
+                .capital(capital)
+                .steps(steps);
+            if let Some(seed) = seed {
+                builder = builder.seed(seed);
+            }
+            let mut engine = builder.build()?;
+            info!("Comparing {} strategies over {} shared paths (seed {})",
+                  strategies.len(), iterations, engine.seed());
+            let comparison = engine.compare(strategies, confidence).await?;
+            
+            println!(
+                "{:>4}  {:<16}  {:>16}  {:>16}  {:>14}  {:>14}  {:>8}  {:>10}",
+                "rank", "strategy", "expected value", "p5", "VaR", "CVaR", "P(loss)", "mean dd %"
+            );
+            for (rank, results) in comparison.ranked().into_iter().enumerate() {
+                println!(
+                    "{:>4}  {:<16}  {:>16.2}  {:>16.2}  {:>14.2}  {:>14.2}  {:>7.2}%  {:>10.2}",
+                    rank + 1,
+                    results.strategy,
+                    results.expected_value,
+                    results.percentiles.get(&5).copied().unwrap_or_default(),
+                    results.value_at_risk,
+                    results.conditional_var,
+                    results.prob_of_loss * 100.0,
+                    results.drawdown.mean_pct,
+                );
+            }
+            
+            println!("\nP(row beats column):");
+            let names: Vec<&str> = comparison.results.iter().map(|r| r.strategy.as_str()).collect();
+            print!("{:<16}", "");
+            for name in &names {
+                print!("  {:>14}", name);
+            }
+            println!();
+            for a in &names {
+                print!("{:<16}", a);
+                for b in &names {
+                    match comparison.pairwise(a, b) {
+                        Some(pair) => print!("  {:>13.1}%", pair.prob_a_beats_b * 100.0),
+                        None => print!("  {:>14}", "-"),
+                    }
+                }
+                println!();
+            }
+        }
+        
+        Commands::Sensitivity {
+            parameter,
+            from,
+            to,
+            points,
+            strategy,
+            iterations,
+            scenarios,
+            confidence,
+            capital,
+            steps,
+            seed,
+            output,
+        } => {
+            let spec = SweepSpec::new(SweepParameter::from_name(&parameter)?, from, to, points);
+            let mut builder = MonteCarloEngine::builder()
+                .iterations(iterations)
+                .scenarios(scenarios)
+                .confidence(confidence)
+                .strategy(Strategy::from_name(&strategy)?)
+                .capital(capital)
+                .steps(steps);
+            if let Some(seed) = seed {
+                builder = builder.seed(seed);
+            }
+            let mut engine = builder.build()?;
+            info!("Sweeping {} over {} values with {} paths each (seed {})",
+                  spec.parameter.name(), points, iterations, engine.seed());
+            let sweep = engine.sensitivity(spec, confidence).await?;
+            
+            println!(
+                "{:>22}  {:>16}  {:>14}  {:>14}  {:>8}  {:>10}",
+                sweep.parameter.name(), "expected value", "VaR", "CVaR", "P(loss)", "mean dd %"
+            );
+            for (value, summary) in &sweep.points {
+                println!(
+                    "{:>22.4}  {:>16.2}  {:>14.2}  {:>14.2}  {:>7.2}%  {:>10.2}",
+                    value,
+                    summary.expected_value,
+                    summary.value_at_risk,
+                    summary.conditional_var,
+                    summary.prob_of_loss * 100.0,
+                    summary.mean_drawdown_pct,
+                );
+            }
+            
+            if let Some(path) = output {
+                sweep.write_csv(&path)?;
+                info!("Wrote sweep to {}", path.display());
+            }
+        }
+        
+        Commands::Stress { file, command } => {
+            let mut library = StressLibrary::builtin();
+            if let Some(path) = file {
+                library.extend(StressLibrary::from_toml_file(&path)?);
+            }
+            
+            match command {
+                StressCommands::List => {
+                    for scenario in &library.scenarios {
+                        println!("  {:<24} {:>5} steps  {}", scenario.name, scenario.steps, scenario.description);
+                    }
+                }
+                StressCommands::Run {
+                    names,
+                    strategy,
+                    capital,
+                    seed,
+                } => {
+                    let names: Vec<&str> = if names.is_empty() {
+                        library.names()
+                    } else {
+                        names.iter().map(String::as_str).collect()
+                    };
+                    let engine = MonteCarloEngine::new(0, 0)
+                        .with_strategy(Strategy::from_name(&strategy)?)
+                        .with_initial_capital(capital)
+                        .with_seed(seed)
+                        .with_stress_scenarios(library.clone());
+                    
+                    println!(
+                        "{:<24}  {:>16}  {:>10}  {:>10}  {:>8}",
+                        "scenario", "final value", "return %", "max dd %", "sharpe"
+                    );
+                    for (name, results) in engine.run_named_scenarios(&names)? {
+                        println!(
+                            "{:<24}  {:>16.2}  {:>10.2}  {:>10.2}  {:>8.4}",
+                            name,
+                            results.final_value,
+                            results.total_return_pct,
+                            results.max_drawdown_pct,
+                            results.sharpe_ratio,
+                        );
+                    }
+                }
+            }
         }
         
         Commands::Strategies => {
@@ -147,6 +1131,55 @@ async fn main() -> anyhow::Result<()> {
                 println!("  - {}", strategy);
             }
         }
+        
+        Commands::Experiments { dir, command } => {
+            let store = ExperimentStore::open(&dir)?;
+            
+            match command {
+                ExperimentCommands::List { strategy, top } => {
+                    let mut records: Vec<&ExperimentRecord> = match strategy {
+                        Some(strategy) => store.by_strategy(&strategy),
+                        None => store.list().iter().collect(),
+                    };
+                    if let Some(k) = top {
+                        records.sort_by(|a, b| {
+                            b.metrics.sharpe_ratio.total_cmp(&a.metrics.sharpe_ratio)
+                        });
+                        records.truncate(k);
+                    }
+                    print_experiment_table(&records);
+                }
+                ExperimentCommands::Show { id } => {
+                    let record = store
+                        .get(&id)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown experiment: {}", id))?;
+                    println!("{}", serde_json::to_string_pretty(record)?);
+                }
+                ExperimentCommands::Compare { ids } => {
+                    let records = ids
+                        .iter()
+                        .map(|id| {
+                            store
+                                .get(id)
+                                .ok_or_else(|| anyhow::anyhow!("Unknown experiment: {}", id))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?;
+                    print_experiment_table(&records);
+                }
+            }
+        }
+        
+        #[cfg(feature = "sqlite")]
+        Commands::Ingest { db, files } => {
+            let store = SqliteDataSource::open(&db)?;
+            for path in &files {
+                let count = store.ingest_csv(path)?;
+                info!("Imported {} bars from {}", count, path.display());
+            }
+            for symbol in store.symbols()? {
+                println!("{:<12} {:>12} bars", symbol, store.bar_count(&symbol)?);
+            }
+        }
     }
 
     Ok(())
@@ -1,7 +1,23 @@
+use crate::calendar::CALENDAR_DAYS_PER_YEAR;
+use crate::data_source::parse_csv_bars;
 use crate::types::*;
-use anyhow::Result;
+use crate::yield_curve::{BondDetails, YieldCurve};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use tracing::{debug, info_span, warn, Instrument};
+
+pub mod recording;
+pub mod synthetic;
+pub mod volatility;
 
 /// Market data provider interface
 pub trait MarketDataProvider {
@@ -9,6 +25,216 @@ pub trait MarketDataProvider {
     fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>>;
     fn get_volatility(&self, symbol: &str) -> Result<Decimal>;
     fn get_yield_rate(&self, symbol: &str) -> Result<Decimal>;
+    
+    /// Annual expected price appreciation; defaults to zero drift
+    fn get_expected_return(&self, _symbol: &str) -> Result<Decimal> {
+        Ok(Decimal::ZERO)
+    }
+    
+    fn get_asset_type(&self, _symbol: &str) -> Result<AssetType> {
+        Ok(AssetType::Crypto)
+    }
+    
+    /// Units of `quote` one unit of `base` buys.
+    ///
+    /// Defaults to parity for a currency against itself and an error otherwise.
+    fn get_fx_rate(&self, base: &str, quote: &str) -> Result<Decimal> {
+        if base == quote {
+            Ok(Decimal::ONE)
+        } else {
+            Err(anyhow::anyhow!("FX rate not available for {}/{}", base, quote))
+        }
+    }
+    
+    /// Maturity and coupon of a bond-like asset; `None` for everything else
+    fn get_bond_details(&self, _symbol: &str) -> Result<Option<BondDetails>> {
+        Ok(None)
+    }
+    
+    /// Average daily dollar volume or pool depth; `None` when unknown, which
+    /// trades as infinitely liquid
+    fn get_liquidity(&self, _symbol: &str) -> Result<Option<Decimal>> {
+        Ok(None)
+    }
+    
+    /// Zero curve bond-like assets are discounted on
+    fn get_yield_curve(&self) -> Result<YieldCurve> {
+        Err(anyhow::anyhow!("Yield curve not available"))
+    }
+}
+
+/// Describe `symbol` from a provider's price, volatility, and yield
+pub fn describe_asset<P>(provider: &P, symbol: &str) -> Result<Asset>
+where
+    P: MarketDataProvider + ?Sized,
+{
+    Ok(Asset {
+        symbol: symbol.to_string(),
+        name: format!("Asset {}", symbol),
+        asset_type: provider.get_asset_type(symbol)?,
+        current_price: provider.get_current_price(symbol)?,
+        volatility: provider.get_volatility(symbol)?,
+        yield_rate: provider.get_yield_rate(symbol)?,
+        expected_return: provider.get_expected_return(symbol)?,
+        bond: provider.get_bond_details(symbol)?,
+        liquidity: provider.get_liquidity(symbol)?,
+    })
+}
+
+/// Async market data provider for network-backed sources.
+///
+/// Mirrors [`MarketDataProvider`], so engines can take sources that would
+/// otherwise block inside the runtime; [`SyncAdapter`] serves a synchronous
+/// provider through it.
+#[async_trait]
+pub trait AsyncMarketDataProvider: Send + Sync {
+    async fn get_current_price(&self, symbol: &str) -> Result<Decimal>;
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>>;
+    async fn get_volatility(&self, symbol: &str) -> Result<Decimal>;
+    async fn get_yield_rate(&self, symbol: &str) -> Result<Decimal>;
+    
+    /// Annual expected price appreciation; defaults to zero drift
+    async fn get_expected_return(&self, _symbol: &str) -> Result<Decimal> {
+        Ok(Decimal::ZERO)
+    }
+    
+    async fn get_asset_type(&self, _symbol: &str) -> Result<AssetType> {
+        Ok(AssetType::Crypto)
+    }
+    
+    /// Maximum number of in-flight requests issued by `get_prices_batch`
+    fn max_concurrency(&self) -> usize {
+        16
+    }
+    
+    /// Fetch prices for many symbols at once.
+    ///
+    /// The default implementation fans out concurrent `get_current_price` calls,
+    /// bounded by `max_concurrency`. Providers whose API has a real batch
+    /// endpoint should override this.
+    async fn get_prices_batch(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>> {
+        let span = info_span!("get_prices_batch", symbols = symbols.len());
+        
+        let fetches: Vec<_> = symbols
+            .iter()
+            .map(|symbol| {
+                let symbol = symbol.to_string();
+                async move {
+                    let started = Instant::now();
+                    let price = self.get_current_price(&symbol).await?;
+                    debug!(
+                        symbol = %symbol,
+                        latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+                        "Fetched price"
+                    );
+                    Ok::<_, anyhow::Error>((symbol, price))
+                }
+            })
+            .collect();
+        
+        stream::iter(fetches)
+            .buffer_unordered(self.max_concurrency().max(1))
+            .try_collect()
+            .instrument(span)
+            .await
+    }
+}
+
+/// [`describe_asset`] for an async provider, asking for each field at once.
+/// Bond details and liquidity, which the async trait doesn't carry, are left unset.
+pub async fn describe_asset_async<P>(provider: &P, symbol: &str) -> Result<Asset>
+where
+    P: AsyncMarketDataProvider + ?Sized,
+{
+    let (asset_type, current_price, volatility, yield_rate, expected_return) = futures::try_join!(
+        provider.get_asset_type(symbol),
+        provider.get_current_price(symbol),
+        provider.get_volatility(symbol),
+        provider.get_yield_rate(symbol),
+        provider.get_expected_return(symbol),
+    )?;
+    Ok(Asset {
+        symbol: symbol.to_string(),
+        name: format!("Asset {}", symbol),
+        asset_type,
+        current_price,
+        volatility,
+        yield_rate,
+        expected_return,
+        bond: None,
+        liquidity: None,
+    })
+}
+
+/// Describe each of `symbols` from an async provider, at most
+/// `max_concurrency` at a time, in the order given
+pub async fn describe_assets_async<P>(provider: &P, symbols: &[&str]) -> Result<Vec<Asset>>
+where
+    P: AsyncMarketDataProvider + ?Sized,
+{
+    stream::iter(symbols.iter().map(|symbol| describe_asset_async(provider, symbol)))
+        .buffered(provider.max_concurrency().max(1))
+        .try_collect()
+        .await
+}
+
+/// Serves a synchronous provider through [`AsyncMarketDataProvider`], for
+/// engines that take either kind.
+///
+/// Calls run inline on the runtime, which suits providers answering from
+/// memory; ones that wait on I/O should implement the async trait themselves.
+#[derive(Debug, Clone, Default)]
+pub struct SyncAdapter<P> {
+    inner: P,
+}
+
+impl<P> SyncAdapter<P> {
+    pub fn new(inner: P) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<P: MarketDataProvider + Send + Sync> AsyncMarketDataProvider for SyncAdapter<P> {
+    async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_current_price(symbol)
+    }
+    
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        self.inner.get_historical_prices(symbol, days)
+    }
+    
+    async fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_volatility(symbol)
+    }
+    
+    async fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_yield_rate(symbol)
+    }
+    
+    async fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_expected_return(symbol)
+    }
+    
+    async fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        self.inner.get_asset_type(symbol)
+    }
+    
+    /// One call after another; nothing is gained by fanning out inline calls
+    async fn get_prices_batch(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>> {
+        symbols
+            .iter()
+            .map(|symbol| self.inner.get_current_price(symbol).map(|price| (symbol.to_string(), price)))
+            .collect()
+    }
 }
 
 /// Mock market data provider for testing
@@ -16,6 +242,91 @@ pub struct MockMarketDataProvider {
     prices: HashMap<String, Decimal>,
     volatilities: HashMap<String, Decimal>,
     yields: HashMap<String, Decimal>,
+    expected_returns: HashMap<String, Decimal>,
+    asset_types: HashMap<String, AssetType>,
+    /// USD value of one unit of each currency
+    usd_rates: HashMap<String, Decimal>,
+    bonds: HashMap<String, BondDetails>,
+    /// Average daily dollar volume
+    liquidity: HashMap<String, Decimal>,
+    yield_curve: YieldCurve,
+    names: HashMap<String, String>,
+    correlations: HashMap<(String, String), f64>,
+}
+
+/// Highest volatility a universe file may give an asset
+const MAX_UNIVERSE_VOLATILITY: f64 = 10.0;
+
+/// A mock universe file: `[[asset]]` tables
+#[derive(Debug, Deserialize)]
+struct UniverseFile {
+    #[serde(default)]
+    asset: Vec<UniverseAsset>,
+}
+
+/// One `[[asset]]` table of a mock universe file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UniverseAsset {
+    symbol: String,
+    name: Option<String>,
+    #[serde(rename = "type", default = "default_universe_type")]
+    asset_type: AssetType,
+    price: f64,
+    volatility: f64,
+    #[serde(rename = "yield", default)]
+    yield_rate: f64,
+    #[serde(default)]
+    expected_return: f64,
+    liquidity: Option<f64>,
+    /// Return correlations with other symbols in the file
+    #[serde(default)]
+    correlations: BTreeMap<String, f64>,
+}
+
+fn default_universe_type() -> AssetType {
+    AssetType::Crypto
+}
+
+impl UniverseAsset {
+    fn validate(&self) -> Result<()> {
+        if self.symbol.trim().is_empty() {
+            return Err(anyhow::anyhow!("symbol must not be empty"));
+        }
+        if !(self.price.is_finite() && self.price > 0.0) {
+            return Err(anyhow::anyhow!("price must be positive, got {}", self.price));
+        }
+        if !(0.0..=MAX_UNIVERSE_VOLATILITY).contains(&self.volatility) {
+            return Err(anyhow::anyhow!(
+                "volatility must be in [0, {}], got {}",
+                MAX_UNIVERSE_VOLATILITY,
+                self.volatility
+            ));
+        }
+        if !(self.yield_rate.is_finite() && self.expected_return.is_finite()) {
+            return Err(anyhow::anyhow!("yield and expected_return must be finite numbers"));
+        }
+        if let Some(liquidity) = self.liquidity.filter(|liquidity| !(liquidity.is_finite() && *liquidity > 0.0)) {
+            return Err(anyhow::anyhow!("liquidity must be positive, got {}", liquidity));
+        }
+        if let Some((other, value)) = self.correlations.iter().find(|(_, value)| !(-1.0..=1.0).contains(*value)) {
+            return Err(anyhow::anyhow!("correlation with {} must be in [-1, 1], got {}", other, value));
+        }
+        if self.correlations.contains_key(&self.symbol) {
+            return Err(anyhow::anyhow!("correlation with itself is always 1"));
+        }
+        Ok(())
+    }
+}
+
+fn decimal(value: f64) -> Result<Decimal> {
+    Decimal::try_from(value).map_err(|e| anyhow::anyhow!("{} is not representable as a decimal: {}", value, e))
+}
+
+impl Default for MockMarketDataProvider {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockMarketDataProvider {
@@ -29,23 +340,162 @@ impl MockMarketDataProvider {
         prices.insert("ETH".to_string(), Decimal::from(2000));
         prices.insert("BTC".to_string(), Decimal::from(40000));
         prices.insert("SOL".to_string(), Decimal::from(100));
+        prices.insert("UST5Y".to_string(), Decimal::from(100));
         
         volatilities.insert("USDC".to_string(), Decimal::try_from(0.001).unwrap());
         volatilities.insert("ETH".to_string(), Decimal::try_from(0.05).unwrap());
         volatilities.insert("BTC".to_string(), Decimal::try_from(0.04).unwrap());
         volatilities.insert("SOL".to_string(), Decimal::try_from(0.06).unwrap());
+        volatilities.insert("UST5Y".to_string(), Decimal::try_from(0.005).unwrap());
         
         yields.insert("USDC".to_string(), Decimal::try_from(0.05).unwrap());
         yields.insert("ETH".to_string(), Decimal::try_from(0.08).unwrap());
         yields.insert("BTC".to_string(), Decimal::try_from(0.06).unwrap());
         yields.insert("SOL".to_string(), Decimal::try_from(0.10).unwrap());
+        yields.insert("UST5Y".to_string(), Decimal::try_from(0.0425).unwrap());
+        
+        // Stablecoins earn their yield as income; their price doesn't drift
+        let mut expected_returns = HashMap::new();
+        expected_returns.insert("USDC".to_string(), Decimal::ZERO);
+        expected_returns.insert("ETH".to_string(), Decimal::try_from(0.08).unwrap());
+        expected_returns.insert("BTC".to_string(), Decimal::try_from(0.06).unwrap());
+        expected_returns.insert("SOL".to_string(), Decimal::try_from(0.10).unwrap());
+        expected_returns.insert("UST5Y".to_string(), Decimal::ZERO);
+        
+        let mut asset_types = HashMap::new();
+        asset_types.insert("USDC".to_string(), AssetType::Stablecoin);
+        asset_types.insert("UST5Y".to_string(), AssetType::RWABond);
+        
+        // A five-year note paying its yield annually
+        let mut bonds = HashMap::new();
+        bonds.insert("UST5Y".to_string(), BondDetails::zero_coupon(5.0).with_coupon(0.0425, 1));
+        
+        let mut liquidity = HashMap::new();
+        liquidity.insert("USDC".to_string(), Decimal::from(5_000_000_000u64));
+        liquidity.insert("ETH".to_string(), Decimal::from(10_000_000_000u64));
+        liquidity.insert("BTC".to_string(), Decimal::from(20_000_000_000u64));
+        liquidity.insert("SOL".to_string(), Decimal::from(1_500_000_000u64));
+        liquidity.insert("UST5Y".to_string(), Decimal::from(250_000_000u64));
+        
+        let mut usd_rates = HashMap::new();
+        usd_rates.insert("USD".to_string(), Decimal::from(1));
+        usd_rates.insert("EUR".to_string(), Decimal::try_from(1.08).unwrap());
+        usd_rates.insert("GBP".to_string(), Decimal::try_from(1.27).unwrap());
         
         Self {
             prices,
             volatilities,
             yields,
+            expected_returns,
+            asset_types,
+            usd_rates,
+            bonds,
+            liquidity,
+            yield_curve: YieldCurve::default_usd(),
+            names: HashMap::new(),
+            correlations: HashMap::new(),
         }
     }
+
+    /// A mock whose universe is the `[[asset]]` tables of a TOML file
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read mock universe from {}", path.display()))?;
+        Self::from_toml_str(&toml).with_context(|| format!("In {}", path.display()))
+    }
+
+    /// A mock whose universe is the `[[asset]]` tables of `toml`, each with a
+    /// `symbol`, `price` and `volatility` and optionally a `name`, `type`,
+    /// `yield`, `expected_return`, `liquidity` and `correlations` table keyed
+    /// by other symbols in the file. FX rates and the yield curve are the
+    /// default mock's; nothing is a bond.
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        let file: UniverseFile = config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()
+            .context("Invalid mock universe")?;
+        if file.asset.is_empty() {
+            return Err(anyhow::anyhow!("A mock universe needs at least one [[asset]]"));
+        }
+        
+        let defaults = Self::new();
+        let mut mock = Self {
+            prices: HashMap::new(),
+            volatilities: HashMap::new(),
+            yields: HashMap::new(),
+            expected_returns: HashMap::new(),
+            asset_types: HashMap::new(),
+            usd_rates: defaults.usd_rates,
+            bonds: HashMap::new(),
+            liquidity: HashMap::new(),
+            yield_curve: defaults.yield_curve,
+            names: HashMap::new(),
+            correlations: HashMap::new(),
+        };
+        for asset in &file.asset {
+            asset.validate().with_context(|| format!("Invalid asset {}", asset.symbol))?;
+            let symbol = asset.symbol.clone();
+            if mock.prices.insert(symbol.clone(), decimal(asset.price)?).is_some() {
+                return Err(anyhow::anyhow!("Asset {} is listed twice", symbol));
+            }
+            mock.volatilities.insert(symbol.clone(), decimal(asset.volatility)?);
+            mock.yields.insert(symbol.clone(), decimal(asset.yield_rate)?);
+            mock.expected_returns.insert(symbol.clone(), decimal(asset.expected_return)?);
+            mock.asset_types.insert(symbol.clone(), asset.asset_type.clone());
+            if let Some(liquidity) = asset.liquidity {
+                mock.liquidity.insert(symbol.clone(), decimal(liquidity)?);
+            }
+            if let Some(name) = &asset.name {
+                mock.names.insert(symbol, name.clone());
+            }
+        }
+        for asset in &file.asset {
+            for (other, value) in &asset.correlations {
+                if !mock.prices.contains_key(other) {
+                    return Err(anyhow::anyhow!("Asset {} is correlated with unknown symbol {}", asset.symbol, other));
+                }
+                let (a, b) = if asset.symbol < *other { (&asset.symbol, other) } else { (other, &asset.symbol) };
+                let key = (a.clone(), b.clone());
+                if mock.correlations.get(&key).is_some_and(|existing| existing != value) {
+                    return Err(anyhow::anyhow!(
+                        "Assets {} and {} are given two different correlations",
+                        asset.symbol,
+                        other
+                    ));
+                }
+                mock.correlations.insert(key, *value);
+            }
+        }
+        Ok(mock)
+    }
+
+    /// Every symbol the mock prices, sorted
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut symbols: Vec<&str> = self.prices.keys().map(String::as_str).collect();
+        symbols.sort();
+        symbols
+    }
+
+    /// Every symbol described as an asset, under its configured name, sorted by symbol
+    pub fn assets(&self) -> Result<Vec<Asset>> {
+        self.symbols()
+            .into_iter()
+            .map(|symbol| {
+                let mut asset = describe_asset(self, symbol)?;
+                if let Some(name) = self.names.get(symbol) {
+                    asset.name = name.clone();
+                }
+                Ok(asset)
+            })
+            .collect()
+    }
+
+    /// Pairwise return correlations from the universe file, each pair once
+    pub fn correlations(&self) -> &HashMap<(String, String), f64> {
+        &self.correlations
+    }
 }
 
 impl MarketDataProvider for MockMarketDataProvider {
@@ -57,7 +507,7 @@ impl MarketDataProvider for MockMarketDataProvider {
     }
 
     fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
-        let base_price = self.get_current_price(symbol)?;
+        let base_price = MarketDataProvider::get_current_price(self, symbol)?;
         let mut prices = vec![base_price];
         
         // Generate historical prices with random walk
@@ -88,4 +538,1143 @@ impl MarketDataProvider for MockMarketDataProvider {
             .copied()
             .ok_or_else(|| anyhow::anyhow!("Yield not found for {}", symbol))
     }
+
+    fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        if let Some(expected_return) = self.expected_returns.get(symbol) {
+            return Ok(*expected_return);
+        }
+        match MarketDataProvider::get_asset_type(self, symbol)? {
+            AssetType::Stablecoin => Ok(Decimal::ZERO),
+            _ => Err(anyhow::anyhow!("Expected return not found for {}", symbol)),
+        }
+    }
+
+    fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        Ok(self.asset_types.get(symbol).cloned().unwrap_or(AssetType::Crypto))
+    }
+
+    fn get_fx_rate(&self, base: &str, quote: &str) -> Result<Decimal> {
+        let usd_rate = |currency: &str| {
+            self.usd_rates
+                .get(currency)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("FX rate not found for {}", currency))
+        };
+        Ok(usd_rate(base)? / usd_rate(quote)?)
+    }
+
+    fn get_bond_details(&self, symbol: &str) -> Result<Option<BondDetails>> {
+        Ok(self.bonds.get(symbol).cloned())
+    }
+
+    fn get_liquidity(&self, symbol: &str) -> Result<Option<Decimal>> {
+        Ok(self.liquidity.get(symbol).copied())
+    }
+
+    fn get_yield_curve(&self) -> Result<YieldCurve> {
+        Ok(self.yield_curve.clone())
+    }
+}
+
+#[async_trait]
+impl AsyncMarketDataProvider for MockMarketDataProvider {
+    async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_current_price(self, symbol)
+    }
+    
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        MarketDataProvider::get_historical_prices(self, symbol, days)
+    }
+    
+    async fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_volatility(self, symbol)
+    }
+    
+    async fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_yield_rate(self, symbol)
+    }
+    
+    async fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_expected_return(self, symbol)
+    }
+    
+    async fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        MarketDataProvider::get_asset_type(self, symbol)
+    }
+    
+    async fn get_prices_batch(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>> {
+        symbols
+            .iter()
+            .map(|symbol| {
+                MarketDataProvider::get_current_price(self, symbol)
+                    .map(|price| (symbol.to_string(), price))
+            })
+            .collect()
+    }
+}
+
+/// Sidecar file of static yields in a `CsvMarketDataProvider` directory
+pub const YIELDS_FILE: &str = "yields.csv";
+
+/// Trailing log returns `CsvMarketDataProvider` estimates volatility over unless configured
+pub const DEFAULT_VOLATILITY_WINDOW: usize = 30;
+
+/// Market data recorded in a directory of CSV files, so the simulator can run
+/// on realistic inputs.
+///
+/// Every `.csv` file in the directory but [`YIELDS_FILE`] holds bars in
+/// [`CsvDataSource`](crate::data_source::CsvDataSource)'s format, and together
+/// they make up the universe. A symbol's yield is its latest `apy`, else its
+/// `yield_rate` in the optional `yields.csv` (columns `symbol`, `yield_rate`);
+/// symbols with neither yield nothing. Volatility is annualized at the bars'
+/// average spacing over a year of calendar days.
+#[derive(Debug, Clone)]
+pub struct CsvMarketDataProvider {
+    /// Each symbol's bars, oldest first
+    bars: BTreeMap<String, Vec<MarketData>>,
+    yields: HashMap<String, Decimal>,
+    volatility_window: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct YieldRow {
+    symbol: String,
+    yield_rate: Decimal,
+}
+
+impl CsvMarketDataProvider {
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut paths = vec![];
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            let is_bars = path.extension().is_some_and(|extension| extension == "csv")
+                && path.file_name().is_some_and(|name| name != YIELDS_FILE);
+            if is_bars {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        
+        let mut bars: BTreeMap<String, Vec<MarketData>> = BTreeMap::new();
+        let mut apys: HashMap<String, (OffsetDateTime, Decimal)> = HashMap::new();
+        for path in &paths {
+            let contents = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            for (bar, apy) in parse_csv_bars(&contents, path)? {
+                if let Some(apy) = apy {
+                    let latest = apys.entry(bar.symbol.clone()).or_insert((bar.timestamp, apy));
+                    if bar.timestamp >= latest.0 {
+                        *latest = (bar.timestamp, apy);
+                    }
+                }
+                bars.entry(bar.symbol.clone()).or_default().push(bar);
+            }
+        }
+        if bars.is_empty() {
+            return Err(anyhow::anyhow!("No bars found in {}", dir.display()));
+        }
+        for (symbol, series) in &mut bars {
+            series.sort_by_key(|bar| bar.timestamp);
+            if let Some(pair) = series.windows(2).find(|pair| pair[0].timestamp == pair[1].timestamp) {
+                return Err(anyhow::anyhow!("{} has two bars at {}", symbol, pair[0].timestamp));
+            }
+            if let Some(bar) = series.iter().find(|bar| bar.price <= Decimal::ZERO || bar.close <= Decimal::ZERO) {
+                return Err(anyhow::anyhow!("{} bar at {} has a non-positive price", symbol, bar.timestamp));
+            }
+        }
+        
+        let mut yields = HashMap::new();
+        let yields_path = dir.join(YIELDS_FILE);
+        if yields_path.exists() {
+            let mut reader = csv::Reader::from_path(&yields_path)
+                .with_context(|| format!("Failed to read {}", yields_path.display()))?;
+            for (row, entry) in reader.deserialize::<YieldRow>().enumerate() {
+                // The header is line 1
+                let entry = entry.with_context(|| format!("{} line {}", yields_path.display(), row + 2))?;
+                yields.insert(entry.symbol, entry.yield_rate);
+            }
+        }
+        yields.extend(apys.into_iter().map(|(symbol, (_, apy))| (symbol, apy)));
+        
+        Ok(Self {
+            bars,
+            yields,
+            volatility_window: DEFAULT_VOLATILITY_WINDOW,
+        })
+    }
+
+    /// Estimate volatility over the trailing `window` log returns instead of the default
+    pub fn with_volatility_window(mut self, window: usize) -> Result<Self> {
+        if window < 2 {
+            return Err(anyhow::anyhow!("Volatility window must be at least two returns, got {}", window));
+        }
+        self.volatility_window = window;
+        Ok(self)
+    }
+
+    /// Every symbol with bars, in order
+    pub fn symbols(&self) -> Vec<&str> {
+        self.bars.keys().map(String::as_str).collect()
+    }
+
+    /// `symbol`'s bars, or an error listing the symbols there are
+    fn series(&self, symbol: &str) -> Result<&[MarketData]> {
+        self.bars.get(symbol).map(Vec::as_slice).ok_or_else(|| {
+            anyhow::anyhow!("Unknown symbol {}; available: {}", symbol, self.symbols().join(", "))
+        })
+    }
+}
+
+impl MarketDataProvider for CsvMarketDataProvider {
+    fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        let series = self.series(symbol)?;
+        Ok(series[series.len() - 1].price)
+    }
+
+    /// The last `days` closes (all there are, if fewer), oldest first
+    fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        let series = self.series(symbol)?;
+        Ok(series[series.len().saturating_sub(days)..].iter().map(|bar| bar.close).collect())
+    }
+
+    fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        let series = self.series(symbol)?;
+        bar_volatility(symbol, &series[series.len().saturating_sub(self.volatility_window + 1)..])
+    }
+
+    fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        self.series(symbol)?;
+        Ok(self.yields.get(symbol).copied().unwrap_or(Decimal::ZERO))
+    }
+}
+
+/// Volatility of the log returns between `bars` (oldest first), annualized at
+/// their average spacing over a year of calendar days
+pub(crate) fn bar_volatility(symbol: &str, bars: &[MarketData]) -> Result<Decimal> {
+    let too_few = || anyhow::anyhow!("{} needs at least three bars to estimate volatility, has {}", symbol, bars.len());
+    if bars.len() < 3 {
+        return Err(too_few());
+    }
+    let closes: Vec<Decimal> = bars.iter().map(|bar| bar.close).collect();
+    let span_days = (bars[bars.len() - 1].timestamp - bars[0].timestamp).as_seconds_f64() / 86_400.0;
+    let periods_per_year = CALENDAR_DAYS_PER_YEAR * (bars.len() - 1) as f64 / span_days;
+    let volatility = crate::metrics::log_return_volatility(&closes, periods_per_year).ok_or_else(too_few)?;
+    Decimal::try_from(volatility).with_context(|| format!("{} volatility {} is out of range", symbol, volatility))
+}
+
+/// Source of the current instant for [`CachedProvider`]'s price TTL, so tests
+/// can expire entries without sleeping
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// How long `CachedProvider` serves a current price before asking again unless configured
+pub const DEFAULT_PRICE_TTL: Duration = Duration::from_secs(60);
+
+/// Entries `CachedProvider` holds before evicting the least recently used unless configured
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Price(String),
+    History(String, usize),
+    Volatility(String),
+    Yield(String),
+}
+
+impl CacheKey {
+    fn symbol(&self) -> &str {
+        match self {
+            Self::Price(symbol) | Self::History(symbol, _) | Self::Volatility(symbol) | Self::Yield(symbol) => symbol,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CacheValue {
+    Scalar(Decimal),
+    Series(Vec<Decimal>),
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: CacheValue,
+    stored_at: Instant,
+    /// Tick of the last lookup, for least-recently-used eviction
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    tick: u64,
+}
+
+/// Memoizes another provider's answers, for backtests and optimizations that
+/// ask for the same series over and over.
+///
+/// Current prices expire after a TTL; historical series (per symbol and
+/// length), volatilities and yields are kept until invalidated or evicted.
+/// Errors are not cached. Expected returns, asset types, FX rates, bond
+/// details, liquidity and the yield curve pass straight through. When full,
+/// the least recently used entry makes room.
+pub struct CachedProvider<P> {
+    inner: P,
+    price_ttl: Duration,
+    max_entries: usize,
+    clock: Arc<dyn Clock>,
+    state: Mutex<CacheState>,
+}
+
+impl<P: MarketDataProvider> CachedProvider<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            price_ttl: DEFAULT_PRICE_TTL,
+            max_entries: DEFAULT_MAX_CACHE_ENTRIES,
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Serve a current price for `ttl` before asking the inner provider again
+    pub fn with_price_ttl(mut self, ttl: Duration) -> Self {
+        self.price_ttl = ttl;
+        self
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Result<Self> {
+        if max_entries == 0 {
+            return Err(anyhow::anyhow!("Cache must hold at least one entry"));
+        }
+        self.max_entries = max_entries;
+        Ok(self)
+    }
+
+    /// Read the time from `clock` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Number of cached answers
+    pub fn len(&self) -> usize {
+        self.state().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget everything cached for `symbol`
+    pub fn invalidate(&self, symbol: &str) {
+        self.state().entries.retain(|key, _| key.symbol() != symbol);
+    }
+
+    /// Forget everything
+    pub fn clear(&self) {
+        self.state().entries.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The cached answer for `key`, else `fetch`'s, cached if it succeeds
+    fn cached(&self, key: CacheKey, fetch: impl FnOnce(&P) -> Result<CacheValue>) -> Result<CacheValue> {
+        let now = self.clock.now();
+        {
+            let mut state = self.state();
+            state.tick += 1;
+            let tick = state.tick;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                let expired = matches!(key, CacheKey::Price(_))
+                    && now.saturating_duration_since(entry.stored_at) >= self.price_ttl;
+                if !expired {
+                    entry.last_used = tick;
+                    return Ok(entry.value.clone());
+                }
+            }
+        }
+
+        // Fetch without holding the lock, so a slow inner provider doesn't block other lookups
+        let value = fetch(&self.inner)?;
+        let mut state = self.state();
+        state.tick += 1;
+        let tick = state.tick;
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.max_entries {
+            let oldest = state.entries.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(key, CacheEntry { value: value.clone(), stored_at: now, last_used: tick });
+        Ok(value)
+    }
+
+    fn scalar(&self, key: CacheKey, fetch: impl FnOnce(&P) -> Result<Decimal>) -> Result<Decimal> {
+        match self.cached(key, |inner| fetch(inner).map(CacheValue::Scalar))? {
+            CacheValue::Scalar(value) => Ok(value),
+            CacheValue::Series(_) => unreachable!("scalar keys only cache scalars"),
+        }
+    }
+}
+
+impl<P: MarketDataProvider> MarketDataProvider for CachedProvider<P> {
+    fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        self.scalar(CacheKey::Price(symbol.to_string()), |inner| inner.get_current_price(symbol))
+    }
+
+    fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        let key = CacheKey::History(symbol.to_string(), days);
+        match self.cached(key, |inner| inner.get_historical_prices(symbol, days).map(CacheValue::Series))? {
+            CacheValue::Series(series) => Ok(series),
+            CacheValue::Scalar(_) => unreachable!("history keys only cache series"),
+        }
+    }
+
+    fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        self.scalar(CacheKey::Volatility(symbol.to_string()), |inner| inner.get_volatility(symbol))
+    }
+
+    fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        self.scalar(CacheKey::Yield(symbol.to_string()), |inner| inner.get_yield_rate(symbol))
+    }
+
+    fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_expected_return(symbol)
+    }
+
+    fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        self.inner.get_asset_type(symbol)
+    }
+
+    fn get_fx_rate(&self, base: &str, quote: &str) -> Result<Decimal> {
+        self.inner.get_fx_rate(base, quote)
+    }
+
+    fn get_bond_details(&self, symbol: &str) -> Result<Option<BondDetails>> {
+        self.inner.get_bond_details(symbol)
+    }
+
+    fn get_liquidity(&self, symbol: &str) -> Result<Option<Decimal>> {
+        self.inner.get_liquidity(symbol)
+    }
+
+    fn get_yield_curve(&self) -> Result<YieldCurve> {
+        self.inner.get_yield_curve()
+    }
+}
+
+/// Smallest eigenvalue `CorrelationMatrix::nearest_positive_definite` leaves
+const MIN_EIGENVALUE: f64 = 1e-6;
+
+/// Symmetric matrix of pairwise return correlations with ones on the diagonal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorrelationMatrix {
+    symbols: Vec<String>,
+    /// Row and column `i` belong to `symbols[i]`
+    values: Vec<Vec<f64>>,
+}
+
+impl CorrelationMatrix {
+    /// Check `values` is square, symmetric, one on the diagonal and within [-1, 1]
+    pub fn new(symbols: Vec<String>, values: Vec<Vec<f64>>) -> Result<Self> {
+        let n = symbols.len();
+        if values.len() != n || values.iter().any(|row| row.len() != n) {
+            return Err(anyhow::anyhow!("Correlation matrix for {} symbols must be {}x{}", n, n, n));
+        }
+        for i in 0..n {
+            if (values[i][i] - 1.0).abs() > 1e-9 {
+                return Err(anyhow::anyhow!("{} correlates {} with itself", symbols[i], values[i][i]));
+            }
+            for j in 0..i {
+                let value = values[i][j];
+                if !(-1.0..=1.0).contains(&value) || (value - values[j][i]).abs() > 1e-9 {
+                    return Err(anyhow::anyhow!(
+                        "Correlation of {} and {} must be symmetric and within [-1, 1], got {} and {}",
+                        symbols[i],
+                        symbols[j],
+                        value,
+                        values[j][i]
+                    ));
+                }
+            }
+        }
+        Ok(Self { symbols, values })
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    /// Rows in `symbols` order
+    pub fn values(&self) -> &[Vec<f64>] {
+        &self.values
+    }
+
+    /// Correlation of `a` and `b`, or `None` if either isn't in the matrix
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|symbol| symbol == a)?;
+        let j = self.symbols.iter().position(|symbol| symbol == b)?;
+        Some(self.values[i][j])
+    }
+
+    /// Covariance matrix for annualized `volatilities`, given in `symbols` order
+    pub fn to_covariance(&self, volatilities: &[f64]) -> Result<Vec<Vec<f64>>> {
+        if volatilities.len() != self.symbols.len() {
+            return Err(anyhow::anyhow!(
+                "Expected {} volatilities, one per symbol, got {}",
+                self.symbols.len(),
+                volatilities.len()
+            ));
+        }
+        Ok(self
+            .values
+            .iter()
+            .zip(volatilities)
+            .map(|(row, a)| row.iter().zip(volatilities).map(|(rho, b)| rho * a * b).collect())
+            .collect())
+    }
+
+    /// Whether the Cholesky factorization the correlated shocks use exists
+    pub fn is_positive_definite(&self) -> bool {
+        crate::utils::cholesky(&self.values).is_ok()
+    }
+
+    /// The matrix itself if positive definite, else a close correlation
+    /// matrix that is: eigenvalues below a small floor are raised to it and
+    /// the result is rescaled to a unit diagonal.
+    pub fn nearest_positive_definite(&self) -> Result<Self> {
+        if self.is_positive_definite() {
+            return Ok(self.clone());
+        }
+        let (eigenvalues, vectors) = crate::utils::symmetric_eigen(&self.values)?;
+        let n = self.symbols.len();
+        let clipped: Vec<f64> = eigenvalues.iter().map(|value| value.max(MIN_EIGENVALUE)).collect();
+        let rebuilt: Vec<Vec<f64>> = (0..n)
+            .map(|i| (0..n).map(|j| (0..n).map(|k| vectors[i][k] * clipped[k] * vectors[j][k]).sum()).collect())
+            .collect();
+        let values = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        if i == j {
+                            1.0
+                        } else {
+                            (rebuilt[i][j] / (rebuilt[i][i] * rebuilt[j][j]).sqrt()).clamp(-1.0, 1.0)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let repaired = Self { symbols: self.symbols.clone(), values };
+        if !repaired.is_positive_definite() {
+            return Err(anyhow::anyhow!("Could not repair the correlation matrix for {:?}", self.symbols));
+        }
+        Ok(repaired)
+    }
+
+    /// Set every pairwise correlation on `config`, for the correlated shocks
+    pub fn apply_to(&self, config: &mut crate::simulator::SimulatorConfig) {
+        for (i, a) in self.symbols.iter().enumerate() {
+            for (j, b) in self.symbols.iter().enumerate().skip(i + 1) {
+                config.set_correlation(a, b, self.values[i][j]);
+            }
+        }
+    }
+}
+
+/// Correlations of `symbols`' daily log returns over the last `window_days`
+/// returns.
+///
+/// Series shorter than the window are all truncated to the most recent span
+/// they share. A symbol whose price never moves has no defined correlation;
+/// it gets zero against everything else, with a warning.
+pub fn estimate_correlation<P>(provider: &P, symbols: &[&str], window_days: usize) -> Result<CorrelationMatrix>
+where
+    P: MarketDataProvider + ?Sized,
+{
+    if window_days < 2 {
+        return Err(anyhow::anyhow!("Correlation window must be at least two returns, got {}", window_days));
+    }
+    let mut series = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let prices = provider.get_historical_prices(symbol, window_days + 1)?;
+        if let Some(price) = prices.iter().find(|price| **price <= Decimal::ZERO) {
+            return Err(anyhow::anyhow!("{} has a non-positive price {}", symbol, price));
+        }
+        series.push(prices);
+    }
+    let common = series.iter().map(Vec::len).min().unwrap_or_default();
+    if common < 3 && !symbols.is_empty() {
+        return Err(anyhow::anyhow!("Need at least three common prices to estimate correlation, have {}", common));
+    }
+
+    let returns: Vec<Vec<f64>> = series
+        .iter()
+        .map(|prices| {
+            let recent: Vec<f64> = prices[prices.len() - common..]
+                .iter()
+                .map(|price| price.to_f64().unwrap_or_default())
+                .collect();
+            recent.windows(2).map(|pair| (pair[1] / pair[0]).ln()).collect()
+        })
+        .collect();
+    let deviations: Vec<Vec<f64>> = returns
+        .iter()
+        .map(|returns| {
+            let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+            returns.iter().map(|r| r - mean).collect()
+        })
+        .collect();
+    let norms: Vec<f64> = deviations.iter().map(|d| d.iter().map(|x| x * x).sum::<f64>().sqrt()).collect();
+    for (symbol, norm) in symbols.iter().zip(&norms) {
+        if *norm == 0.0 {
+            warn!("{} has constant returns over the window; treating it as uncorrelated", symbol);
+        }
+    }
+
+    let n = symbols.len();
+    let mut values = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        values[i][i] = 1.0;
+        for j in 0..i {
+            let correlation = if norms[i] == 0.0 || norms[j] == 0.0 {
+                0.0
+            } else {
+                let dot: f64 = deviations[i].iter().zip(&deviations[j]).map(|(a, b)| a * b).sum();
+                (dot / (norms[i] * norms[j])).clamp(-1.0, 1.0)
+            };
+            values[i][j] = correlation;
+            values[j][i] = correlation;
+        }
+    }
+    CorrelationMatrix::new(symbols.iter().map(|symbol| symbol.to_string()).collect(), values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Async feed whose symbols answer after their own delays, recording the
+    /// order answers come back in and the most requests it had in flight
+    struct DelayedFeed {
+        quotes: Vec<(&'static str, Decimal, u64)>,
+        concurrency: usize,
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+        answered: Mutex<Vec<String>>,
+    }
+
+    impl DelayedFeed {
+        fn new(quotes: Vec<(&'static str, Decimal, u64)>, concurrency: usize) -> Self {
+            Self {
+                quotes,
+                concurrency,
+                in_flight: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                answered: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AsyncMarketDataProvider for DelayedFeed {
+        async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(in_flight, Ordering::SeqCst);
+            let quote = self.quotes.iter().find(|(quoted, _, _)| *quoted == symbol);
+            let delay = quote.map_or(1, |(_, _, delay)| *delay);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            self.answered.lock().unwrap().push(symbol.to_string());
+            quote.map(|(_, price, _)| *price).ok_or_else(|| anyhow::anyhow!("No quote for {}", symbol))
+        }
+
+        async fn get_historical_prices(&self, symbol: &str, _days: usize) -> Result<Vec<Decimal>> {
+            Err(anyhow::anyhow!("No history for {}", symbol))
+        }
+
+        async fn get_volatility(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        async fn get_yield_rate(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        fn max_concurrency(&self) -> usize {
+            self.concurrency
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_prices_land_on_their_symbols_whatever_order_they_finish_in() {
+        // The first symbols asked for are the slowest to answer
+        let feed = DelayedFeed::new(
+            vec![("BTC", dec!(40000), 150), ("ETH", dec!(2000), 100), ("SOL", dec!(100), 50), ("USDC", dec!(1), 1)],
+            16,
+        );
+        let prices = feed.get_prices_batch(&["BTC", "ETH", "SOL", "USDC"]).await.unwrap();
+
+        assert_eq!(*feed.answered.lock().unwrap(), vec!["USDC", "SOL", "ETH", "BTC"]);
+        assert_eq!(prices.len(), 4);
+        for (symbol, price, _) in &feed.quotes {
+            assert_eq!(prices[*symbol], *price, "{}", symbol);
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_fails_when_any_symbol_fails() {
+        let feed = DelayedFeed::new(vec![("BTC", dec!(40000), 20), ("ETH", dec!(2000), 1)], 16);
+        let error = feed.get_prices_batch(&["BTC", "DOGE", "ETH"]).await.unwrap_err();
+
+        assert!(error.to_string().contains("DOGE"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn batch_keeps_to_max_concurrency() {
+        let symbols = ["A", "B", "C", "D", "E"];
+        let quotes = symbols.into_iter().zip(1..).map(|(symbol, price)| (symbol, Decimal::from(price), 10));
+        let feed = DelayedFeed::new(quotes.collect(), 2);
+        let prices = feed.get_prices_batch(&symbols).await.unwrap();
+
+        assert_eq!(prices.len(), 5);
+        assert_eq!(feed.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn empty_batch_is_empty() {
+        let feed = DelayedFeed::new(vec![], 16);
+        assert!(feed.get_prices_batch(&[]).await.unwrap().is_empty());
+    }
+
+    fn csv_fixtures() -> CsvMarketDataProvider {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/data/csv_provider");
+        CsvMarketDataProvider::from_dir(dir).unwrap()
+    }
+
+    /// Annualized volatility of `n` returns alternating +/- ln(1.1): their
+    /// sample variance is n / (n - 1) * ln(1.1)^2
+    fn alternating_volatility(n: f64, periods_per_year: f64) -> f64 {
+        (n / (n - 1.0)).sqrt() * 1.1f64.ln() * periods_per_year.sqrt()
+    }
+
+    fn volatility(provider: &CsvMarketDataProvider, symbol: &str) -> f64 {
+        provider.get_volatility(symbol).unwrap().to_f64().unwrap()
+    }
+
+    #[test]
+    fn csv_volatility_matches_closed_forms() {
+        let provider = csv_fixtures();
+        assert_eq!(provider.symbols(), ["BTC", "ETH", "SOL", "USDC"]);
+
+        // Ten daily ETH returns and eight hourly SOL ones; BTC compounds at a steady 1% a day
+        assert!((volatility(&provider, "ETH") - alternating_volatility(10.0, 365.0)).abs() < 1e-9);
+        assert!((volatility(&provider, "SOL") - alternating_volatility(8.0, 365.0 * 24.0)).abs() < 1e-9);
+        assert!(volatility(&provider, "BTC").abs() < 1e-9);
+        let windowed = provider.clone().with_volatility_window(4).unwrap();
+        assert!((volatility(&windowed, "ETH") - alternating_volatility(4.0, 365.0)).abs() < 1e-9);
+        assert!(provider.with_volatility_window(1).is_err());
+    }
+
+    #[test]
+    fn csv_prices_yields_and_unknown_symbols() {
+        let provider = csv_fixtures();
+        assert_eq!(provider.get_current_price("ETH").unwrap(), dec!(2000));
+        assert_eq!(provider.get_historical_prices("ETH", 3).unwrap(), [dec!(2000), dec!(2200), dec!(2000)]);
+        assert_eq!(provider.get_historical_prices("USDC", 100).unwrap().len(), 11);
+        // The latest apy beats the sidecar; the sidecar covers USDC; BTC has neither
+        assert_eq!(provider.get_yield_rate("ETH").unwrap(), dec!(0.04));
+        assert_eq!(provider.get_yield_rate("USDC").unwrap(), dec!(0.05));
+        assert_eq!(provider.get_yield_rate("BTC").unwrap(), Decimal::ZERO);
+
+        let error = provider.get_volatility("DOGE").unwrap_err().to_string();
+        assert!(error.contains("Unknown symbol DOGE; available: BTC, ETH, SOL, USDC"), "{}", error);
+    }
+
+    #[test]
+    fn bar_volatility_annualizes_at_the_bars_spacing() {
+        let bars = |spacing: time::Duration, closes: &[Decimal]| -> Vec<MarketData> {
+            closes
+                .iter()
+                .enumerate()
+                .map(|(index, close)| MarketData {
+                    timestamp: OffsetDateTime::UNIX_EPOCH + spacing * index as i32,
+                    symbol: "X".to_string(),
+                    price: *close,
+                    volume: Decimal::ZERO,
+                    high: *close,
+                    low: *close,
+                    open: *close,
+                    close: *close,
+                })
+                .collect()
+        };
+        let closes = [dec!(100), dec!(110), dec!(100), dec!(110), dec!(100)];
+        let daily = bar_volatility("X", &bars(time::Duration::days(1), &closes)).unwrap().to_f64().unwrap();
+        let weekly = bar_volatility("X", &bars(time::Duration::weeks(1), &closes)).unwrap().to_f64().unwrap();
+        assert!((daily - alternating_volatility(4.0, 365.0)).abs() < 1e-9);
+        assert!((daily / weekly - 7f64.sqrt()).abs() < 1e-9);
+
+        let error = bar_volatility("X", &bars(time::Duration::days(1), &closes[..2])).unwrap_err();
+        assert!(error.to_string().contains("needs at least three bars"));
+    }
+
+    /// Answers with the number of calls so far, counting them per method;
+    /// symbols starting with `BAD` fail
+    #[derive(Default)]
+    struct Counting {
+        calls: Mutex<HashMap<&'static str, usize>>,
+    }
+
+    impl Counting {
+        fn call(&self, method: &'static str, symbol: &str) -> Result<Decimal> {
+            let mut calls = self.calls.lock().unwrap();
+            let count = calls.entry(method).or_default();
+            *count += 1;
+            if symbol.starts_with("BAD") {
+                return Err(anyhow::anyhow!("no {}", symbol));
+            }
+            Ok(Decimal::from(*count))
+        }
+
+        fn calls(&self, method: &str) -> usize {
+            self.calls.lock().unwrap().get(method).copied().unwrap_or_default()
+        }
+    }
+
+    impl MarketDataProvider for Counting {
+        fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+            self.call("price", symbol)
+        }
+
+        fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+            Ok(vec![self.call("history", symbol)?; days])
+        }
+
+        fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+            self.call("volatility", symbol)
+        }
+
+        fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+            self.call("yield", symbol)
+        }
+    }
+
+    /// A clock that only moves when told to
+    struct ManualClock(Mutex<Instant>);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn cached_with_clock() -> (CachedProvider<Counting>, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock(Mutex::new(Instant::now())));
+        let cached = CachedProvider::new(Counting::default())
+            .with_price_ttl(Duration::from_secs(60))
+            .with_clock(clock.clone());
+        (cached, clock)
+    }
+
+    #[test]
+    fn repeated_queries_reach_the_inner_provider_once() {
+        let (cached, _) = cached_with_clock();
+        for _ in 0..3 {
+            assert_eq!(cached.get_current_price("ETH").unwrap(), dec!(1));
+            assert_eq!(cached.get_historical_prices("ETH", 5).unwrap(), vec![dec!(1); 5]);
+            assert_eq!(cached.get_volatility("ETH").unwrap(), dec!(1));
+            assert_eq!(cached.get_yield_rate("ETH").unwrap(), dec!(1));
+        }
+        for method in ["price", "history", "volatility", "yield"] {
+            assert_eq!(cached.inner().calls(method), 1, "{}", method);
+        }
+
+        // Another length or symbol is another entry
+        assert_eq!(cached.get_historical_prices("ETH", 3).unwrap(), vec![dec!(2); 3]);
+        assert_eq!(cached.get_volatility("BTC").unwrap(), dec!(2));
+        assert_eq!(cached.len(), 6);
+    }
+
+    #[test]
+    fn prices_are_fetched_again_after_the_ttl() {
+        let (cached, clock) = cached_with_clock();
+        cached.get_current_price("ETH").unwrap();
+        cached.get_volatility("ETH").unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(cached.get_current_price("ETH").unwrap(), dec!(1));
+        assert_eq!(cached.inner().calls("price"), 1);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cached.get_current_price("ETH").unwrap(), dec!(2));
+        assert_eq!(cached.get_current_price("ETH").unwrap(), dec!(2));
+        assert_eq!(cached.inner().calls("price"), 2);
+        // Only prices expire
+        clock.advance(Duration::from_secs(3600));
+        cached.get_volatility("ETH").unwrap();
+        assert_eq!(cached.inner().calls("volatility"), 1);
+    }
+
+    #[test]
+    fn invalidation_eviction_and_errors() {
+        let (cached, _) = cached_with_clock();
+        cached.get_yield_rate("ETH").unwrap();
+        cached.get_yield_rate("BTC").unwrap();
+        cached.invalidate("ETH");
+        cached.get_yield_rate("ETH").unwrap();
+        cached.get_yield_rate("BTC").unwrap();
+        assert_eq!(cached.inner().calls("yield"), 3);
+        cached.clear();
+        assert!(cached.is_empty());
+
+        // Failures aren't cached
+        assert!(cached.get_yield_rate("BAD").is_err());
+        assert!(cached.get_yield_rate("BAD").is_err());
+        assert_eq!(cached.inner().calls("yield"), 5);
+        assert!(cached.is_empty());
+
+        // A full cache drops the least recently used entry, here BTC
+        let small = CachedProvider::new(Counting::default()).with_max_entries(2).unwrap();
+        for symbol in ["ETH", "BTC", "ETH", "SOL", "ETH"] {
+            small.get_yield_rate(symbol).unwrap();
+        }
+        assert_eq!((small.len(), small.inner().calls("yield")), (2, 3));
+        small.get_yield_rate("BTC").unwrap();
+        assert_eq!(small.inner().calls("yield"), 4);
+        assert!(CachedProvider::new(Counting::default()).with_max_entries(0).is_err());
+    }
+
+    /// Fixed closes per symbol, oldest first
+    struct Series(HashMap<&'static str, Vec<Decimal>>);
+
+    impl MarketDataProvider for Series {
+        fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+            Ok(*self.0[symbol].last().unwrap())
+        }
+
+        fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+            let series = &self.0[symbol];
+            Ok(series[series.len().saturating_sub(days)..].to_vec())
+        }
+
+        fn get_volatility(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        fn get_yield_rate(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+    }
+
+    /// Closes from 100 following the log `returns`
+    fn closes(returns: &[f64]) -> Vec<Decimal> {
+        let mut price = 100.0f64;
+        let mut closes = vec![dec!(100)];
+        for r in returns {
+            price *= r.exp();
+            closes.push(Decimal::try_from(price).unwrap());
+        }
+        closes
+    }
+
+    #[test]
+    fn correlation_of_scaled_and_mirrored_returns() {
+        let returns = [0.01, -0.02, 0.015, 0.0, -0.01, 0.03, -0.005];
+        let scaled: Vec<f64> = returns.iter().map(|r| 2.0 * r).collect();
+        let mirrored: Vec<f64> = returns.iter().map(|r| -r).collect();
+        let provider = Series(HashMap::from([
+            ("A", closes(&returns)),
+            ("B", closes(&scaled)),
+            ("C", closes(&mirrored)),
+            ("FLAT", vec![dec!(5); 8]),
+        ]));
+
+        let matrix = estimate_correlation(&provider, &["A", "B", "C", "FLAT"], 7).unwrap();
+        assert!((matrix.get("A", "B").unwrap() - 1.0).abs() < 1e-9);
+        assert!((matrix.get("B", "C").unwrap() + 1.0).abs() < 1e-9);
+        // A price that never moves correlates with nothing rather than NaN
+        assert_eq!(matrix.get("A", "FLAT"), Some(0.0));
+        assert_eq!(matrix.get("FLAT", "FLAT"), Some(1.0));
+        assert_eq!(matrix.get("A", "Z"), None);
+    }
+
+    #[test]
+    fn correlation_truncates_to_the_common_window() {
+        // B shares only A's last four returns, which it follows exactly; A's
+        // earlier returns run the other way
+        let a = closes(&[0.05, -0.04, 0.06, 0.01, -0.02, 0.03, 0.0]);
+        let b = closes(&[0.01, -0.02, 0.03, 0.0]);
+        let provider = Series(HashMap::from([("A", a), ("B", b)]));
+        let matrix = estimate_correlation(&provider, &["A", "B"], 30).unwrap();
+        assert!((matrix.get("A", "B").unwrap() - 1.0).abs() < 1e-9);
+
+        assert!(estimate_correlation(&provider, &["A", "B"], 1).is_err());
+        let short = Series(HashMap::from([("A", vec![dec!(1), dec!(2)]), ("B", vec![dec!(1), dec!(2)])]));
+        assert!(estimate_correlation(&short, &["A", "B"], 5).is_err());
+        let broken = Series(HashMap::from([("A", vec![dec!(1), dec!(0), dec!(2)])]));
+        assert!(estimate_correlation(&broken, &["A"], 5).is_err());
+    }
+
+    #[test]
+    fn covariance_and_positive_definite_repair() {
+        let symbols = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+        let matrix = CorrelationMatrix::new(
+            symbols.clone(),
+            vec![vec![1.0, 0.5, 0.0], vec![0.5, 1.0, 0.0], vec![0.0, 0.0, 1.0]],
+        )
+        .unwrap();
+        let covariance = matrix.to_covariance(&[0.2, 0.4, 0.1]).unwrap();
+        assert!((covariance[0][1] - 0.04).abs() < 1e-12);
+        assert!((covariance[1][1] - 0.16).abs() < 1e-12);
+        assert_eq!(covariance[0][2], 0.0);
+        assert!(matrix.to_covariance(&[0.2]).is_err());
+        assert_eq!(matrix.nearest_positive_definite().unwrap(), matrix);
+
+        // A and B move with C but against each other, which no returns can do
+        let inconsistent = CorrelationMatrix::new(
+            symbols.clone(),
+            vec![vec![1.0, -0.9, 0.9], vec![-0.9, 1.0, 0.9], vec![0.9, 0.9, 1.0]],
+        )
+        .unwrap();
+        assert!(!inconsistent.is_positive_definite());
+        let repaired = inconsistent.nearest_positive_definite().unwrap();
+        assert!(repaired.is_positive_definite());
+        let values = repaired.values();
+        for (i, row) in values.iter().enumerate() {
+            assert_eq!(row[i], 1.0);
+            for (j, value) in row.iter().enumerate() {
+                assert_eq!(*value, values[j][i]);
+                assert_eq!(value.signum(), inconsistent.values()[i][j].signum());
+            }
+        }
+
+        assert!(CorrelationMatrix::new(symbols.clone(), vec![vec![1.0; 2]; 2]).is_err());
+        let asymmetric = vec![vec![1.0, 0.5, 0.0], vec![0.4, 1.0, 0.0], vec![0.0, 0.0, 1.0]];
+        assert!(CorrelationMatrix::new(symbols, asymmetric).is_err());
+    }
+
+    fn universe_error(fields: &str) -> String {
+        let toml = format!("[[asset]]\nsymbol = \"ETH\"\n{}\n", fields);
+        format!("{:#}", MockMarketDataProvider::from_toml_str(&toml).err().unwrap())
+    }
+
+    #[test]
+    fn universe_file_describes_the_mock() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/data/universe/assets.toml");
+        let mock = MockMarketDataProvider::from_toml(path).unwrap();
+        assert_eq!(mock.symbols(), ["BTC", "ETH", "MATIC", "SOL", "USDC"]);
+
+        let assets = mock.assets().unwrap();
+        let matic = assets.iter().find(|asset| asset.symbol == "MATIC").unwrap();
+        assert_eq!(matic.name, "Polygon");
+        assert_eq!((matic.current_price, matic.volatility), (dec!(0.8), dec!(1)));
+        assert_eq!(matic.liquidity, Some(dec!(300_000_000)));
+        assert_eq!(matic.asset_type, AssetType::Crypto);
+        let provider: &dyn MarketDataProvider = &mock;
+        assert_eq!(provider.get_asset_type("USDC").unwrap(), AssetType::Stablecoin);
+        // Omitted yields and expected returns are zero; the built-in universe is gone
+        assert_eq!(provider.get_yield_rate("BTC").unwrap(), Decimal::ZERO);
+        assert_eq!(provider.get_expected_return("USDC").unwrap(), Decimal::ZERO);
+        assert!(provider.get_current_price("UST5Y").is_err());
+        // Each pair once, in symbol order
+        assert_eq!(mock.correlations().len(), 6);
+        assert_eq!(mock.correlations().get(&("BTC".to_string(), "ETH".to_string())), Some(&0.8));
+        assert_eq!(mock.correlations().get(&("MATIC".to_string(), "SOL".to_string())), Some(&0.6));
+    }
+
+    #[test]
+    fn bad_universe_files_say_what_is_wrong() {
+        let cases = [
+            ("price = -5.0\nvolatility = 0.5", "Invalid asset ETH: price must be positive, got -5"),
+            ("price = 2000.0\nvolatility = 12.0", "Invalid asset ETH: volatility must be in [0, 10], got 12"),
+            ("price = 2000.0\nvolatility = 0.5\nliquidity = 0.0", "liquidity must be positive"),
+            ("price = 2000.0\nvolatility = 0.5\nvol = 0.5", "unknown field `vol`"),
+            ("price = 2000.0\nvolatility = 0.5\ntype = \"Meme\"", "Meme"),
+            ("volatility = 0.5", "price"),
+            ("price = 2000.0\nvolatility = 0.5\ncorrelations = { BTC = 0.8 }", "correlated with unknown symbol BTC"),
+            ("price = 2000.0\nvolatility = 0.5\ncorrelations = { ETH = 1.0 }", "itself"),
+            ("price = 2000.0\nvolatility = 0.5\nprice = 2100.0", "price"),
+        ];
+        for (fields, expected) in cases {
+            let error = universe_error(fields);
+            assert!(error.contains(expected), "{:?} should mention {:?}", error, expected);
+        }
+
+        let twice = "[[asset]]\nsymbol = \"ETH\"\nprice = 1.0\nvolatility = 0.5\n".repeat(2);
+        let error = MockMarketDataProvider::from_toml_str(&twice).err().unwrap().to_string();
+        assert_eq!(error, "Asset ETH is listed twice");
+        let clash = "[[asset]]\nsymbol = \"A\"\nprice = 1.0\nvolatility = 0.5\ncorrelations = { B = 0.5 }\n\
+                     [[asset]]\nsymbol = \"B\"\nprice = 1.0\nvolatility = 0.5\ncorrelations = { A = 0.4 }\n";
+        let error = MockMarketDataProvider::from_toml_str(clash).err().unwrap().to_string();
+        assert!(error.contains("two different correlations"), "{}", error);
+        assert!(MockMarketDataProvider::from_toml_str("").is_err());
+        let error = format!("{:#}", MockMarketDataProvider::from_toml("does/not/exist.toml").err().unwrap());
+        assert!(error.contains("does/not/exist.toml"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn sync_adapter_answers_as_its_provider() {
+        let mock = MockMarketDataProvider::new();
+        let adapter = SyncAdapter::new(MockMarketDataProvider::new());
+        let sync: &dyn MarketDataProvider = &mock;
+        for symbol in ["USDC", "ETH", "UST5Y"] {
+            assert_eq!(adapter.get_current_price(symbol).await.unwrap(), sync.get_current_price(symbol).unwrap());
+            assert_eq!(adapter.get_volatility(symbol).await.unwrap(), sync.get_volatility(symbol).unwrap());
+            assert_eq!(adapter.get_yield_rate(symbol).await.unwrap(), sync.get_yield_rate(symbol).unwrap());
+            assert_eq!(adapter.get_asset_type(symbol).await.unwrap(), sync.get_asset_type(symbol).unwrap());
+        }
+        let batch = adapter.get_prices_batch(&["ETH", "BTC"]).await.unwrap();
+        assert_eq!(batch["BTC"], sync.get_current_price("BTC").unwrap());
+        assert!(adapter.get_prices_batch(&["ETH", "DOGE"]).await.is_err());
+
+        // Described alike, but the async trait carries no bond or liquidity
+        let assets = describe_assets_async(&adapter, &["ETH", "UST5Y"]).await.unwrap();
+        assert_eq!(assets.iter().map(|asset| asset.symbol.as_str()).collect::<Vec<_>>(), ["ETH", "UST5Y"]);
+        for asset in &assets {
+            let described = describe_asset(&mock, &asset.symbol).unwrap();
+            assert_eq!(
+                (asset.current_price, asset.volatility, asset.yield_rate, &asset.asset_type),
+                (described.current_price, described.volatility, described.yield_rate, &described.asset_type)
+            );
+            assert!(asset.bond.is_none() && asset.liquidity.is_none());
+        }
+        assert!(describe_assets_async(&adapter, &["DOGE"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn async_assets_are_described_concurrently() {
+        let symbols = ["A", "B", "C", "D", "E"];
+        let quotes = symbols.into_iter().zip(1..).map(|(symbol, price)| (symbol, Decimal::from(price), 10));
+        let feed = DelayedFeed::new(quotes.collect(), 3);
+        let assets = describe_assets_async(&feed, &symbols).await.unwrap();
+
+        let prices: Vec<Decimal> = assets.iter().map(|asset| asset.current_price).collect();
+        assert_eq!(prices, [dec!(1), dec!(2), dec!(3), dec!(4), dec!(5)]);
+        assert_eq!(feed.peak.load(Ordering::SeqCst), 3);
+    }
 }
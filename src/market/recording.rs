@@ -0,0 +1,529 @@
+//! Record a provider's answers and replay them, so a run that depended on
+//! random or live market data can be reproduced exactly.
+//!
+//! [`RecordingProvider`] wraps any provider and appends every query and its
+//! answer, errors included, to a JSONL file. [`ReplayProvider`] answers from
+//! such a file: repeated queries get their recorded answers in order, the last
+//! one again once they run out, and queries never recorded are errors unless a
+//! fallback provider is set. Replaying a recording through a simulator with the
+//! same seed reproduces the recorded run.
+
+use super::{AsyncMarketDataProvider, MarketDataProvider};
+use crate::simulator::BoxedProvider;
+use crate::types::AssetType;
+use crate::yield_curve::{BondDetails, YieldCurve};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// One provider call, as recorded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Query {
+    CurrentPrice { symbol: String },
+    HistoricalPrices { symbol: String, days: usize },
+    Volatility { symbol: String },
+    YieldRate { symbol: String },
+    ExpectedReturn { symbol: String },
+    AssetType { symbol: String },
+    FxRate { base: String, quote: String },
+    BondDetails { symbol: String },
+    Liquidity { symbol: String },
+    YieldCurve,
+    /// An async batch price request
+    PricesBatch { symbols: Vec<String> },
+}
+
+impl Query {
+    /// The key answers are filed under
+    fn key(&self) -> Result<String> {
+        serde_json::to_string(self).context("Failed to serialize provider query")
+    }
+}
+
+/// What a provider call returned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Answer {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+/// One line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub query: Query,
+    pub answer: Answer,
+}
+
+/// Passes every call through to another provider, appending the query and
+/// its answer to a JSONL file.
+///
+/// Each line is flushed as it is written, so a run that crashes still leaves
+/// a usable recording. A line that can't be written fails the call.
+pub struct RecordingProvider<P> {
+    inner: P,
+    writer: Mutex<BufWriter<File>>,
+    calls: AtomicUsize,
+}
+
+impl<P> RecordingProvider<P> {
+    /// Record `inner`'s answers to `path`, replacing any file there
+    pub fn new(inner: P, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create provider recording {}", path.display()))?;
+        Ok(Self { inner, writer: Mutex::new(BufWriter::new(file)), calls: AtomicUsize::new(0) })
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Calls recorded so far
+    pub fn len(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Append `query` and `answer` to the recording, returning `answer`
+    fn record<T: Serialize>(&self, query: Query, answer: Result<T>) -> Result<T> {
+        let recorded = match &answer {
+            Ok(value) => Answer::Ok(serde_json::to_value(value).context("Failed to serialize provider answer")?),
+            Err(error) => Answer::Error(format!("{:#}", error)),
+        };
+        let line = serde_json::to_string(&RecordedCall { query, answer: recorded })?;
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writeln!(writer, "{}", line)
+            .and_then(|()| writer.flush())
+            .context("Failed to write provider recording")?;
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        answer
+    }
+}
+
+impl<P: MarketDataProvider> MarketDataProvider for RecordingProvider<P> {
+    fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::CurrentPrice { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_current_price(symbol))
+    }
+
+    fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        let query = Query::HistoricalPrices { symbol: symbol.to_string(), days };
+        self.record(query, self.inner.get_historical_prices(symbol, days))
+    }
+
+    fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::Volatility { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_volatility(symbol))
+    }
+
+    fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::YieldRate { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_yield_rate(symbol))
+    }
+
+    fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::ExpectedReturn { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_expected_return(symbol))
+    }
+
+    fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        let query = Query::AssetType { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_asset_type(symbol))
+    }
+
+    fn get_fx_rate(&self, base: &str, quote: &str) -> Result<Decimal> {
+        let query = Query::FxRate { base: base.to_string(), quote: quote.to_string() };
+        self.record(query, self.inner.get_fx_rate(base, quote))
+    }
+
+    fn get_bond_details(&self, symbol: &str) -> Result<Option<BondDetails>> {
+        let query = Query::BondDetails { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_bond_details(symbol))
+    }
+
+    fn get_liquidity(&self, symbol: &str) -> Result<Option<Decimal>> {
+        let query = Query::Liquidity { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_liquidity(symbol))
+    }
+
+    fn get_yield_curve(&self) -> Result<YieldCurve> {
+        self.record(Query::YieldCurve, self.inner.get_yield_curve())
+    }
+}
+
+#[async_trait]
+impl<P: AsyncMarketDataProvider> AsyncMarketDataProvider for RecordingProvider<P> {
+    async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::CurrentPrice { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_current_price(symbol).await)
+    }
+
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        let query = Query::HistoricalPrices { symbol: symbol.to_string(), days };
+        self.record(query, self.inner.get_historical_prices(symbol, days).await)
+    }
+
+    async fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::Volatility { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_volatility(symbol).await)
+    }
+
+    async fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::YieldRate { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_yield_rate(symbol).await)
+    }
+
+    async fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::ExpectedReturn { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_expected_return(symbol).await)
+    }
+
+    async fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        let query = Query::AssetType { symbol: symbol.to_string() };
+        self.record(query, self.inner.get_asset_type(symbol).await)
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency()
+    }
+
+    /// Recorded as one batch, so the inner provider's batching is kept
+    async fn get_prices_batch(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>> {
+        let query = Query::PricesBatch { symbols: symbols.iter().map(|symbol| symbol.to_string()).collect() };
+        self.record(query, self.inner.get_prices_batch(symbols).await)
+    }
+}
+
+/// Recorded answers to one query, handed out in order
+#[derive(Debug)]
+struct Answers {
+    answers: Vec<Answer>,
+    next: usize,
+}
+
+/// Answers queries from a [`RecordingProvider`]'s file
+pub struct ReplayProvider {
+    answers: Mutex<HashMap<String, Answers>>,
+    fallback: Option<BoxedProvider>,
+}
+
+impl ReplayProvider {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open provider recording {}", path.display()))?;
+        let mut answers: HashMap<String, Answers> = HashMap::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let call: RecordedCall = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid recorded call on line {} of {}", index + 1, path.display()))?;
+            answers
+                .entry(call.query.key()?)
+                .or_insert_with(|| Answers { answers: vec![], next: 0 })
+                .answers
+                .push(call.answer);
+        }
+        Ok(Self { answers: Mutex::new(answers), fallback: None })
+    }
+
+    /// Answer queries missing from the recording with `fallback` instead of an error
+    pub fn with_fallback<F>(mut self, fallback: F) -> Self
+    where
+        F: MarketDataProvider + Send + Sync + 'static,
+    {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// Distinct queries in the recording
+    pub fn len(&self) -> usize {
+        self.answers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The next recorded answer to `query`, or `None` if it was never recorded
+    fn recorded(&self, query: &Query) -> Result<Option<Answer>> {
+        let key = query.key()?;
+        let mut answers = self.answers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(entry) = answers.get_mut(&key) else {
+            return Ok(None);
+        };
+        let answer = entry.answers[entry.next.min(entry.answers.len() - 1)].clone();
+        entry.next += 1;
+        Ok(Some(answer))
+    }
+
+    /// `query`'s recorded answer, else the fallback's
+    fn answer<T: DeserializeOwned>(
+        &self,
+        query: Query,
+        fallback: impl FnOnce(&(dyn MarketDataProvider + Send + Sync)) -> Result<T>,
+    ) -> Result<T> {
+        match self.recorded(&query)? {
+            Some(answer) => decode(&query, answer),
+            None => match self.fallback.as_deref() {
+                Some(provider) => fallback(provider),
+                None => Err(anyhow::anyhow!("No recorded answer to {:?}", query)),
+            },
+        }
+    }
+}
+
+fn decode<T: DeserializeOwned>(query: &Query, answer: Answer) -> Result<T> {
+    match answer {
+        Answer::Ok(value) => serde_json::from_value(value)
+            .with_context(|| format!("Recorded answer to {:?} has the wrong shape", query)),
+        Answer::Error(message) => Err(anyhow::anyhow!(message)),
+    }
+}
+
+impl MarketDataProvider for ReplayProvider {
+    fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::CurrentPrice { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_current_price(symbol))
+    }
+
+    fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        let query = Query::HistoricalPrices { symbol: symbol.to_string(), days };
+        self.answer(query, |provider| provider.get_historical_prices(symbol, days))
+    }
+
+    fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::Volatility { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_volatility(symbol))
+    }
+
+    fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::YieldRate { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_yield_rate(symbol))
+    }
+
+    fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        let query = Query::ExpectedReturn { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_expected_return(symbol))
+    }
+
+    fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        let query = Query::AssetType { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_asset_type(symbol))
+    }
+
+    fn get_fx_rate(&self, base: &str, quote: &str) -> Result<Decimal> {
+        let query = Query::FxRate { base: base.to_string(), quote: quote.to_string() };
+        self.answer(query, |provider| provider.get_fx_rate(base, quote))
+    }
+
+    fn get_bond_details(&self, symbol: &str) -> Result<Option<BondDetails>> {
+        let query = Query::BondDetails { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_bond_details(symbol))
+    }
+
+    fn get_liquidity(&self, symbol: &str) -> Result<Option<Decimal>> {
+        let query = Query::Liquidity { symbol: symbol.to_string() };
+        self.answer(query, |provider| provider.get_liquidity(symbol))
+    }
+
+    fn get_yield_curve(&self) -> Result<YieldCurve> {
+        self.answer(Query::YieldCurve, |provider| provider.get_yield_curve())
+    }
+}
+
+#[async_trait]
+impl AsyncMarketDataProvider for ReplayProvider {
+    async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_current_price(self, symbol)
+    }
+
+    async fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        MarketDataProvider::get_historical_prices(self, symbol, days)
+    }
+
+    async fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_volatility(self, symbol)
+    }
+
+    async fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_yield_rate(self, symbol)
+    }
+
+    async fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        MarketDataProvider::get_expected_return(self, symbol)
+    }
+
+    async fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        MarketDataProvider::get_asset_type(self, symbol)
+    }
+
+    /// The recorded batch, else each symbol's recorded price
+    async fn get_prices_batch(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>> {
+        let query = Query::PricesBatch { symbols: symbols.iter().map(|symbol| symbol.to_string()).collect() };
+        if let Some(answer) = self.recorded(&query)? {
+            return decode(&query, answer);
+        }
+        symbols
+            .iter()
+            .map(|symbol| Ok((symbol.to_string(), MarketDataProvider::get_current_price(self, symbol)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::MockMarketDataProvider;
+    use crate::{SimulatorBuilder, Strategy};
+    use rand::Rng;
+    use std::path::PathBuf;
+    use time::macros::datetime;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("vaulta-recording-{}.jsonl", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// The mock market with up to 5% of noise on every price and volatility,
+    /// so no two runs against it see the same market
+    struct Jittery(MockMarketDataProvider);
+
+    fn jitter(value: Decimal) -> Result<Decimal> {
+        Ok(value * (Decimal::ONE + Decimal::try_from(rand::thread_rng().gen_range(-0.05..0.05))?))
+    }
+
+    impl MarketDataProvider for Jittery {
+        fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+            jitter(MarketDataProvider::get_current_price(&self.0, symbol)?)
+        }
+
+        fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+            MarketDataProvider::get_historical_prices(&self.0, symbol, days)
+        }
+
+        fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+            jitter(MarketDataProvider::get_volatility(&self.0, symbol)?)
+        }
+
+        fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+            MarketDataProvider::get_yield_rate(&self.0, symbol)
+        }
+
+        fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+            MarketDataProvider::get_expected_return(&self.0, symbol)
+        }
+
+        fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+            MarketDataProvider::get_asset_type(&self.0, symbol)
+        }
+    }
+
+    /// Final results of 30 balanced steps from a fixed time and seed, as JSON
+    fn run<P: MarketDataProvider + Send + Sync + 'static>(provider: P) -> String {
+        let mut simulator = SimulatorBuilder::new()
+            .capital(1_000_000.0)
+            .strategy(Strategy::balanced())
+            .provider(provider)
+            .universe_from_provider(&["BTC", "ETH"])
+            .start_time(datetime!(2024-01-01 00:00 UTC))
+            .seed(42)
+            .build()
+            .unwrap();
+        for _ in 0..30 {
+            simulator.step().unwrap();
+        }
+        serde_json::to_string(&simulator.finalize()).unwrap()
+    }
+
+    #[test]
+    fn replaying_a_session_reproduces_the_run() {
+        let file = TempFile::new();
+        let recorder = RecordingProvider::new(Jittery(MockMarketDataProvider::new()), &file.0).unwrap();
+        let recorded = run(recorder);
+        let lines = std::fs::read_to_string(&file.0).unwrap().lines().count();
+        assert!(lines > 0);
+
+        let replay = ReplayProvider::from_file(&file.0).unwrap();
+        assert!(!replay.is_empty() && replay.len() <= lines);
+        assert_eq!(run(replay), recorded);
+    }
+
+    #[test]
+    fn replay_answers_in_order_then_repeats_the_last() {
+        let file = TempFile::new();
+        let recorder = RecordingProvider::new(Jittery(MockMarketDataProvider::new()), &file.0).unwrap();
+        let quotes: Vec<Decimal> = (0..3).map(|_| recorder.get_current_price("ETH").unwrap()).collect();
+        assert!(recorder.get_current_price("DOGE").is_err());
+        assert_eq!(recorder.len(), 4);
+
+        let replay = ReplayProvider::from_file(&file.0).unwrap();
+        assert_eq!(replay.len(), 2);
+        let provider: &dyn MarketDataProvider = &replay;
+        let replayed: Vec<Decimal> = (0..4).map(|_| provider.get_current_price("ETH").unwrap()).collect();
+        assert_eq!(replayed[..3], quotes[..]);
+        assert_eq!(replayed[3], quotes[2]);
+
+        // Recorded errors come back even with a fallback; unseen queries fail unless there is one
+        assert_eq!(provider.get_current_price("DOGE").unwrap_err().to_string(), "Price not found for DOGE");
+        assert!(provider.get_volatility("ETH").unwrap_err().to_string().contains("No recorded answer"));
+        let replay = replay.with_fallback(MockMarketDataProvider::new());
+        let provider: &dyn MarketDataProvider = &replay;
+        assert!(provider.get_current_price("DOGE").is_err());
+        let mock: &dyn MarketDataProvider = &MockMarketDataProvider::new();
+        assert_eq!(provider.get_volatility("ETH").unwrap(), mock.get_volatility("ETH").unwrap());
+    }
+
+    #[tokio::test]
+    async fn async_batches_replay_whole() {
+        let file = TempFile::new();
+        let recorder = RecordingProvider::new(MockMarketDataProvider::new(), &file.0).unwrap();
+        let batch = recorder.get_prices_batch(&["BTC", "ETH"]).await.unwrap();
+        let single = AsyncMarketDataProvider::get_current_price(&recorder, "SOL").await.unwrap();
+
+        let replay = ReplayProvider::from_file(&file.0).unwrap();
+        assert_eq!(replay.get_prices_batch(&["BTC", "ETH"]).await.unwrap(), batch);
+        // An unrecorded batch is assembled from single recorded prices
+        assert_eq!(replay.get_prices_batch(&["SOL"]).await.unwrap()["SOL"], single);
+        assert!(replay.get_prices_batch(&["BTC"]).await.is_err());
+    }
+
+    #[test]
+    fn bad_recordings_are_rejected() {
+        let file = TempFile::new();
+        let curve = "{\"query\":{\"method\":\"yield_curve\"},\"answer\":{\"ok\":1}}\n";
+        std::fs::write(&file.0, format!("{}\nnot json\n", curve)).unwrap();
+        let error = format!("{:#}", ReplayProvider::from_file(&file.0).err().unwrap());
+        assert!(error.contains("line 3"), "{}", error);
+
+        std::fs::write(&file.0, curve).unwrap();
+        let replay = ReplayProvider::from_file(&file.0).unwrap();
+        let error = replay.get_yield_curve().unwrap_err().to_string();
+        assert!(error.contains("wrong shape"), "{}", error);
+        assert!(ReplayProvider::from_file(file.0.join("missing")).is_err());
+    }
+}
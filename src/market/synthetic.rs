@@ -0,0 +1,405 @@
+//! Seeded synthetic daily bars, for backtests without real data.
+//!
+//! [`SyntheticGenerator`] draws each symbol's daily log returns from its
+//! [`SymbolModel`]: geometric Brownian motion, or GARCH(1,1) variance so calm
+//! and turbulent stretches cluster as they do in real markets, with
+//! [`TrendSegment`]s overriding the drift over runs of days. Every symbol
+//! draws from its own stream, derived from the seed and the symbol, so a seed
+//! gives the same series whichever symbols are generated alongside, and a
+//! later end date only extends them.
+//!
+//! Bars are coherent: each opens at the previous close, and the high and low
+//! lie beyond both the open and the close.
+
+use super::MarketDataProvider;
+use crate::calendar::CALENDAR_DAYS_PER_YEAR;
+use crate::types::MarketData;
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use time::{Duration, OffsetDateTime};
+
+/// Seed `MockDataSource` generates from unless given another
+pub const DEFAULT_SYNTHETIC_SEED: u64 = 42;
+
+/// GARCH weight of the last day's squared shock in [`ReturnModel::garch`]
+pub const DEFAULT_GARCH_ALPHA: f64 = 0.08;
+
+/// GARCH weight of the last day's variance in [`ReturnModel::garch`]
+pub const DEFAULT_GARCH_BETA: f64 = 0.9;
+
+/// Highest annual volatility a model may have, as for the mock universe
+const MAX_VOLATILITY: f64 = 10.0;
+
+/// Volume of a bar with no move; bigger moves trade more
+const BASE_VOLUME: f64 = 1_000_000.0;
+
+/// Decimal places synthetic prices are rounded to
+const PRICE_DP: u32 = 8;
+
+/// How a symbol's daily volatility evolves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReturnModel {
+    /// Geometric Brownian motion: the same volatility every day
+    Gbm,
+    /// GARCH(1,1): each day's variance is `alpha` times the last day's squared
+    /// shock plus `beta` times the last day's variance, plus the rest of the
+    /// long-run variance. Shocks raise the next days' volatility, which decays
+    /// back at rate `beta`.
+    Garch { alpha: f64, beta: f64 },
+}
+
+impl ReturnModel {
+    /// GARCH(1,1) with [`DEFAULT_GARCH_ALPHA`] and [`DEFAULT_GARCH_BETA`]
+    pub fn garch() -> Self {
+        Self::Garch { alpha: DEFAULT_GARCH_ALPHA, beta: DEFAULT_GARCH_BETA }
+    }
+
+    /// Check GARCH's weights are non-negative and sum under one, so the
+    /// variance returns to its long-run level
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Garch { alpha, beta } if !(alpha >= 0.0 && beta >= 0.0 && alpha + beta < 1.0) => {
+                Err(anyhow::anyhow!("GARCH alpha and beta must be at least 0 and sum under 1, got {}, {}", alpha, beta))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for ReturnModel {
+    fn default() -> Self {
+        Self::garch()
+    }
+}
+
+/// A run of days with its own drift, such as a rally or a sell-off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrendSegment {
+    /// First day of the run, counting the first bar as day 0
+    pub from_day: usize,
+    /// Length of the run in days
+    pub days: usize,
+    /// Annual drift over the run, in place of the symbol's
+    pub drift: f64,
+}
+
+impl TrendSegment {
+    fn covers(&self, day: usize) -> bool {
+        day >= self.from_day && day - self.from_day < self.days
+    }
+}
+
+/// How one symbol's series is generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolModel {
+    /// Price the first bar opens at
+    pub start_price: Decimal,
+    /// Annual drift outside trend segments
+    pub drift: f64,
+    /// Annual volatility of the log returns; GARCH's long-run level
+    pub volatility: f64,
+    pub returns: ReturnModel,
+    /// Drift overrides; where segments overlap the first one listed applies
+    pub trends: Vec<TrendSegment>,
+}
+
+impl SymbolModel {
+    /// A symbol starting at `start_price`, with GARCH clustering at the default weights
+    pub fn new(start_price: Decimal, drift: f64, volatility: f64) -> Self {
+        Self { start_price, drift, volatility, returns: ReturnModel::default(), trends: vec![] }
+    }
+
+    /// The provider's current price, expected return and volatility for `symbol`
+    pub fn from_provider<P: MarketDataProvider + ?Sized>(provider: &P, symbol: &str) -> Result<Self> {
+        let figure = |value: Decimal| value.to_f64().unwrap_or(0.0);
+        Ok(Self::new(
+            provider.get_current_price(symbol)?,
+            figure(provider.get_expected_return(symbol)?),
+            figure(provider.get_volatility(symbol)?),
+        ))
+    }
+
+    /// Draw volatility from `returns` instead of GARCH at the default weights
+    pub fn with_returns(mut self, returns: ReturnModel) -> Self {
+        self.returns = returns;
+        self
+    }
+
+    /// Drift at `drift` a year for `days` days from day `from_day`
+    pub fn with_trend(mut self, from_day: usize, days: usize, drift: f64) -> Self {
+        self.trends.push(TrendSegment { from_day, days, drift });
+        self
+    }
+
+    /// Check the price is positive, the drifts finite, the volatility within
+    /// bounds and the return model's weights valid
+    pub fn validate(&self) -> Result<()> {
+        if self.start_price <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Start price must be positive, got {}", self.start_price));
+        }
+        if !(0.0..=MAX_VOLATILITY).contains(&self.volatility) {
+            return Err(anyhow::anyhow!(
+                "Volatility must be between 0 and {}, got {}",
+                MAX_VOLATILITY,
+                self.volatility
+            ));
+        }
+        if let Some(drift) = std::iter::once(self.drift)
+            .chain(self.trends.iter().map(|trend| trend.drift))
+            .find(|drift| !drift.is_finite())
+        {
+            return Err(anyhow::anyhow!("Drift must be finite, got {}", drift));
+        }
+        self.returns.validate()
+    }
+
+    fn drift_on(&self, day: usize) -> f64 {
+        self.trends.iter().find(|trend| trend.covers(day)).map_or(self.drift, |trend| trend.drift)
+    }
+}
+
+/// Seeded daily bars for a set of symbols, each from its own [`SymbolModel`]
+#[derive(Debug, Clone)]
+pub struct SyntheticGenerator {
+    seed: u64,
+    models: BTreeMap<String, SymbolModel>,
+}
+
+impl SyntheticGenerator {
+    /// A generator with no symbols yet
+    pub fn new(seed: u64) -> Self {
+        Self { seed, models: BTreeMap::new() }
+    }
+
+    /// Models for `symbols` from the provider's figures; see [`SymbolModel::from_provider`]
+    pub fn from_provider<P: MarketDataProvider + ?Sized>(provider: &P, symbols: &[&str], seed: u64) -> Result<Self> {
+        symbols.iter().try_fold(Self::new(seed), |generator, symbol| {
+            generator.with_symbol(symbol, SymbolModel::from_provider(provider, symbol)?)
+        })
+    }
+
+    /// Generate `symbol` from `model`, replacing any model it had
+    pub fn with_symbol(mut self, symbol: &str, model: SymbolModel) -> Result<Self> {
+        model.validate().with_context(|| format!("Invalid synthetic model for {}", symbol))?;
+        self.models.insert(symbol.to_string(), model);
+        Ok(self)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Symbols with a model, in order
+    pub fn symbols(&self) -> Vec<&str> {
+        self.models.keys().map(String::as_str).collect()
+    }
+
+    /// One bar a day per symbol from `start` to `end` inclusive, symbol by symbol
+    pub fn generate(&self, start: OffsetDateTime, end: OffsetDateTime) -> Result<Vec<MarketData>> {
+        if start > end {
+            return Err(anyhow::anyhow!("Synthetic data must start by its end, got {} to {}", start, end));
+        }
+        Ok(self.models.iter().flat_map(|(symbol, model)| self.series(symbol, model, start, end)).collect())
+    }
+
+    fn series(&self, symbol: &str, model: &SymbolModel, start: OffsetDateTime, end: OffsetDateTime) -> Vec<MarketData> {
+        let mut rng = StdRng::seed_from_u64(symbol_seed(self.seed, symbol));
+        let dt = 1.0 / CALENDAR_DAYS_PER_YEAR;
+        let long_run = model.volatility * model.volatility * dt;
+        let mut variance = long_run;
+        let mut open = model.start_price;
+        let mut bars = vec![];
+
+        for day in 0.. {
+            let timestamp = start + Duration::days(day as i64);
+            if timestamp > end {
+                break;
+            }
+            let shock = variance.sqrt() * rng.sample::<f64, _>(StandardNormal);
+            let log_return = model.drift_on(day) * dt - 0.5 * variance + shock;
+            let close = round_price(open * factor(log_return.exp())).unwrap_or(open);
+
+            // The high and low reach past the open and close by about half a
+            // day's volatility, further on volatile days
+            let reach = |rng: &mut StdRng| {
+                factor((0.5 * variance.sqrt() * rng.sample::<f64, _>(StandardNormal).abs()).exp())
+            };
+            let (top, bottom) = (open.max(close), open.min(close));
+            let high = round_price(top * reach(&mut rng)).map_or(top, |high| high.max(top));
+            let low = round_price(bottom / reach(&mut rng)).map_or(bottom, |low| low.min(bottom));
+            let size = if variance > 0.0 { shock.abs() / variance.sqrt() } else { 0.0 };
+            let volume = factor(BASE_VOLUME * (1.0 + size) * rng.gen_range(0.8..1.2)).round();
+
+            bars.push(MarketData {
+                timestamp,
+                symbol: symbol.to_string(),
+                price: close,
+                volume,
+                high,
+                low,
+                open,
+                close,
+            });
+
+            if let ReturnModel::Garch { alpha, beta } = model.returns {
+                variance = (1.0 - alpha - beta) * long_run + alpha * shock * shock + beta * variance;
+            }
+            open = close;
+        }
+        bars
+    }
+}
+
+/// The generator's seed mixed with an FNV-1a hash of the symbol, stable
+/// across builds unlike the standard library's hasher
+fn symbol_seed(seed: u64, symbol: &str) -> u64 {
+    let hash = symbol.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    seed ^ hash
+}
+
+fn factor(value: f64) -> Decimal {
+    Decimal::try_from(value).unwrap_or(Decimal::ONE)
+}
+
+/// `price` rounded for a bar; `None` if it rounds to nothing
+fn round_price(price: Decimal) -> Option<Decimal> {
+    Some(price.round_dp(PRICE_DP)).filter(|price| *price > Decimal::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    const START: OffsetDateTime = datetime!(2024-01-01 00:00 UTC);
+
+    fn generate(generator: &SyntheticGenerator, days: i64) -> Vec<MarketData> {
+        generator.generate(START, START + Duration::days(days - 1)).unwrap()
+    }
+
+    fn closes(bars: &[MarketData]) -> Vec<Decimal> {
+        bars.iter().map(|bar| bar.close).collect()
+    }
+
+    /// Log returns between consecutive closes
+    fn log_returns(bars: &[MarketData]) -> Vec<f64> {
+        bars.windows(2).map(|pair| (pair[1].close / pair[0].close).to_f64().unwrap().ln()).collect()
+    }
+
+    fn model() -> impl Strategy<Value = SymbolModel> {
+        (1u32..1_000_000, -1.0f64..1.0, 0.0f64..3.0, prop::bool::ANY).prop_map(|(price, drift, volatility, garch)| {
+            let returns = if garch { ReturnModel::garch() } else { ReturnModel::Gbm };
+            SymbolModel::new(Decimal::new(price as i64, 2), drift, volatility).with_returns(returns)
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn every_bar_is_coherent(seed in any::<u64>(), model in model(), days in 1i64..200) {
+            let generator = SyntheticGenerator::new(seed).with_symbol("X", model.clone()).unwrap();
+            let bars = generate(&generator, days);
+            prop_assert_eq!(bars.len(), days as usize);
+            prop_assert_eq!(bars[0].open, model.start_price);
+            for (day, bar) in bars.iter().enumerate() {
+                prop_assert_eq!(bar.timestamp, START + Duration::days(day as i64));
+                prop_assert!(bar.high >= bar.open.max(bar.close), "high {:?}", bar);
+                prop_assert!(bar.low <= bar.open.min(bar.close), "low {:?}", bar);
+                prop_assert!(bar.low > Decimal::ZERO && bar.volume > Decimal::ZERO, "{:?}", bar);
+                prop_assert_eq!(bar.price, bar.close);
+            }
+            for pair in bars.windows(2) {
+                prop_assert_eq!(pair[1].open, pair[0].close);
+            }
+        }
+
+        #[test]
+        fn the_same_seed_gives_the_same_series(seed in any::<u64>(), model in model(), days in 2i64..100) {
+            let alone = SyntheticGenerator::new(seed).with_symbol("X", model.clone()).unwrap();
+            let again = SyntheticGenerator::new(seed).with_symbol("X", model.clone()).unwrap();
+            let series = generate(&alone, days);
+            prop_assert_eq!(
+                serde_json::to_string(&series).unwrap(),
+                serde_json::to_string(&generate(&again, days)).unwrap()
+            );
+
+            // Other symbols alongside don't change it, and a later end only extends it
+            let crowded = again.with_symbol("A", SymbolModel::new(dec!(1), 0.0, 0.5)).unwrap();
+            let with_others: Vec<MarketData> =
+                generate(&crowded, days).into_iter().filter(|bar| bar.symbol == "X").collect();
+            prop_assert_eq!(closes(&with_others), closes(&series));
+            prop_assert_eq!(closes(&generate(&alone, days + 10)[..days as usize]), closes(&series));
+
+            let other_seed = SyntheticGenerator::new(seed.wrapping_add(1)).with_symbol("X", model.clone()).unwrap();
+            prop_assume!(model.volatility > 0.01);
+            prop_assert_ne!(closes(&generate(&other_seed, days)), closes(&series));
+        }
+    }
+
+    #[test]
+    fn trends_override_the_drift() {
+        // Without volatility the path is the drift alone
+        let model = SymbolModel::new(dec!(100), 0.0, 0.0).with_trend(10, 5, 3.65).with_trend(12, 10, -3.65);
+        let generator = SyntheticGenerator::new(1).with_symbol("X", model).unwrap();
+        let returns = log_returns(&generate(&generator, 30));
+        for (day, r) in returns.iter().enumerate() {
+            // Return `day` is made on bar `day + 1`; the first segment listed wins the overlap
+            let expected = match day + 1 {
+                10..=14 => 0.01,
+                15..=21 => -0.01,
+                _ => 0.0,
+            };
+            assert!((r - expected).abs() < 1e-6, "day {}: {} vs {}", day + 1, r, expected);
+        }
+    }
+
+    #[test]
+    fn garch_clusters_volatility_and_gbm_does_not() {
+        /// Lag-one autocorrelation of the squared returns
+        fn clustering(returns: &[f64]) -> f64 {
+            let squares: Vec<f64> = returns.iter().map(|r| r * r).collect();
+            let mean = squares.iter().sum::<f64>() / squares.len() as f64;
+            let deviations: Vec<f64> = squares.iter().map(|s| s - mean).collect();
+            let lagged: f64 = deviations.windows(2).map(|pair| pair[0] * pair[1]).sum();
+            lagged / deviations.iter().map(|d| d * d).sum::<f64>()
+        }
+        let series = |returns: ReturnModel| {
+            let model = SymbolModel::new(dec!(100), 0.0, 0.8).with_returns(returns);
+            generate(&SyntheticGenerator::new(7).with_symbol("X", model).unwrap(), 4000)
+        };
+        let garch = clustering(&log_returns(&series(ReturnModel::Garch { alpha: 0.15, beta: 0.8 })));
+        let gbm = clustering(&log_returns(&series(ReturnModel::Gbm)));
+        assert!(garch > 0.1, "GARCH clustering {}", garch);
+        assert!(gbm.abs() < 0.05, "GBM clustering {}", gbm);
+
+        // Both annualize to about the model's volatility
+        for returns in [ReturnModel::garch(), ReturnModel::Gbm] {
+            let closes = closes(&series(returns));
+            let volatility = crate::metrics::log_return_volatility(&closes, CALENDAR_DAYS_PER_YEAR).unwrap();
+            assert!((volatility - 0.8).abs() < 0.08, "{:?}: {}", returns, volatility);
+        }
+    }
+
+    #[test]
+    fn invalid_models_are_rejected() {
+        let generator = SyntheticGenerator::new(1);
+        let with = |model: SymbolModel| generator.clone().with_symbol("X", model).err().map(|e| format!("{:#}", e));
+        assert!(with(SymbolModel::new(dec!(0), 0.0, 0.5)).unwrap().contains("Start price must be positive"));
+        assert!(with(SymbolModel::new(dec!(1), 0.0, 11.0)).unwrap().contains("Volatility must be between 0 and 10"));
+        assert!(with(SymbolModel::new(dec!(1), f64::NAN, 0.5)).is_some());
+        assert!(with(SymbolModel::new(dec!(1), 0.0, 0.5).with_trend(0, 5, f64::INFINITY)).is_some());
+        let explosive = ReturnModel::Garch { alpha: 0.5, beta: 0.6 };
+        let error = with(SymbolModel::new(dec!(1), 0.0, 0.5).with_returns(explosive)).unwrap();
+        assert!(error.contains("Invalid synthetic model for X: GARCH alpha and beta"), "{}", error);
+        assert!(generator.generate(START, START - Duration::days(1)).is_err());
+        assert!(generator.generate(START, START).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+//! Volatility estimated from price history, for strategies that size
+//! positions by risk and need estimates that follow the data.
+//!
+//! [`realized_vol`] weighs the trailing window's log returns equally;
+//! [`ewma_vol`] is the RiskMetrics exponentially weighted estimate, which
+//! reacts faster to recent moves. [`EstimatedVolatilityProvider`] swaps either
+//! in for another provider's volatilities.
+
+use super::MarketDataProvider;
+use crate::calendar::CALENDAR_DAYS_PER_YEAR;
+use crate::types::AssetType;
+use crate::yield_curve::{BondDetails, YieldCurve};
+use anyhow::{Context, Result};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+/// RiskMetrics' decay for daily returns
+pub const DEFAULT_EWMA_LAMBDA: f64 = 0.94;
+
+/// Prices `EstimatedVolatilityProvider` feeds an EWMA estimate unless configured;
+/// at the default decay the oldest return's weight is negligible
+pub const DEFAULT_EWMA_HISTORY: usize = 250;
+
+/// Log returns of `prices`, oldest first; `None` with a non-positive price
+fn log_returns(prices: &[Decimal]) -> Option<Vec<f64>> {
+    let prices: Vec<f64> = prices
+        .iter()
+        .map(|price| price.to_f64().filter(|price| *price > 0.0))
+        .collect::<Option<_>>()?;
+    Some(prices.windows(2).map(|pair| (pair[1] / pair[0]).ln()).collect())
+}
+
+/// Annualized realized volatility over the last `window` log returns of
+/// `prices` (oldest first): their sample standard deviation, scaled by the
+/// square root of `periods_per_year`. `None` with fewer than two returns in
+/// the window or a non-positive price.
+pub fn realized_vol(prices: &[Decimal], window: usize, periods_per_year: f64) -> Option<f64> {
+    let recent = &prices[prices.len().saturating_sub(window + 1)..];
+    crate::metrics::log_return_volatility(recent, periods_per_year)
+}
+
+/// Annualized exponentially weighted volatility of `prices` (oldest first).
+///
+/// The variance is the mean of the squared log returns, the newest weighted
+/// one and each older one `lambda` times the one after it, taking returns to
+/// have zero mean as RiskMetrics does. At `lambda = 1` every return weighs the
+/// same. `None` without a return, with a non-positive price, or with `lambda`
+/// outside (0, 1].
+pub fn ewma_vol(prices: &[Decimal], lambda: f64, periods_per_year: f64) -> Option<f64> {
+    if !(lambda > 0.0 && lambda <= 1.0) {
+        return None;
+    }
+    let returns = log_returns(prices)?;
+    if returns.is_empty() {
+        return None;
+    }
+    let (mut weighted, mut total) = (0.0, 0.0);
+    let mut weight = 1.0;
+    for r in returns.iter().rev() {
+        weighted += weight * r * r;
+        total += weight;
+        weight *= lambda;
+    }
+    Some((weighted / total).sqrt() * periods_per_year.sqrt())
+}
+
+/// How `EstimatedVolatilityProvider` estimates volatility
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolatilityEstimator {
+    /// [`realized_vol`] over the trailing `window` returns
+    Realized { window: usize },
+    /// [`ewma_vol`] with decay `lambda` over the last `history` prices
+    Ewma { lambda: f64, history: usize },
+}
+
+impl VolatilityEstimator {
+    /// Check the window, decay and history can produce an estimate
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Realized { window } if window < 2 => {
+                Err(anyhow::anyhow!("Realized volatility window must be at least two returns, got {}", window))
+            }
+            Self::Ewma { lambda, .. } if !(lambda > 0.0 && lambda <= 1.0) => {
+                Err(anyhow::anyhow!("EWMA lambda must be in (0, 1], got {}", lambda))
+            }
+            Self::Ewma { history, .. } if history < 2 => {
+                Err(anyhow::anyhow!("EWMA history must be at least two prices, got {}", history))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Prices the estimate needs
+    fn history(&self) -> usize {
+        match *self {
+            Self::Realized { window } => window + 1,
+            Self::Ewma { history, .. } => history,
+        }
+    }
+
+    fn estimate(&self, prices: &[Decimal], periods_per_year: f64) -> Option<f64> {
+        match *self {
+            Self::Realized { window } => realized_vol(prices, window, periods_per_year),
+            Self::Ewma { lambda, .. } => ewma_vol(prices, lambda, periods_per_year),
+        }
+    }
+}
+
+/// Wraps another provider, answering `get_volatility` with an estimate from
+/// its `get_historical_prices` and passing everything else through.
+///
+/// Estimates are annualized at one price per day over a year of calendar days
+/// unless `with_periods_per_year` says otherwise.
+#[derive(Debug, Clone)]
+pub struct EstimatedVolatilityProvider<P> {
+    inner: P,
+    estimator: VolatilityEstimator,
+    periods_per_year: f64,
+}
+
+impl<P: MarketDataProvider> EstimatedVolatilityProvider<P> {
+    pub fn new(inner: P, estimator: VolatilityEstimator) -> Result<Self> {
+        estimator.validate()?;
+        Ok(Self { inner, estimator, periods_per_year: CALENDAR_DAYS_PER_YEAR })
+    }
+
+    /// Realized volatility over the trailing `window` daily returns
+    pub fn realized(inner: P, window: usize) -> Result<Self> {
+        Self::new(inner, VolatilityEstimator::Realized { window })
+    }
+
+    /// EWMA volatility with decay `lambda` over the last [`DEFAULT_EWMA_HISTORY`] prices
+    pub fn ewma(inner: P, lambda: f64) -> Result<Self> {
+        Self::new(inner, VolatilityEstimator::Ewma { lambda, history: DEFAULT_EWMA_HISTORY })
+    }
+
+    /// Annualize assuming `periods_per_year` prices a year, e.g. 252 for trading-day closes
+    pub fn with_periods_per_year(mut self, periods_per_year: f64) -> Result<Self> {
+        if !(periods_per_year.is_finite() && periods_per_year > 0.0) {
+            return Err(anyhow::anyhow!("Periods per year must be positive, got {}", periods_per_year));
+        }
+        self.periods_per_year = periods_per_year;
+        Ok(self)
+    }
+
+    pub fn estimator(&self) -> VolatilityEstimator {
+        self.estimator
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P: MarketDataProvider> MarketDataProvider for EstimatedVolatilityProvider<P> {
+    fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_current_price(symbol)
+    }
+
+    fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+        self.inner.get_historical_prices(symbol, days)
+    }
+
+    fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+        let prices = self.inner.get_historical_prices(symbol, self.estimator.history())?;
+        let volatility = self.estimator.estimate(&prices, self.periods_per_year).ok_or_else(|| {
+            anyhow::anyhow!("Cannot estimate {} volatility from {} positive prices", symbol, prices.len())
+        })?;
+        Decimal::try_from(volatility).with_context(|| format!("{} volatility {} is out of range", symbol, volatility))
+    }
+
+    fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_yield_rate(symbol)
+    }
+
+    fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+        self.inner.get_expected_return(symbol)
+    }
+
+    fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+        self.inner.get_asset_type(symbol)
+    }
+
+    fn get_fx_rate(&self, base: &str, quote: &str) -> Result<Decimal> {
+        self.inner.get_fx_rate(base, quote)
+    }
+
+    fn get_bond_details(&self, symbol: &str) -> Result<Option<BondDetails>> {
+        self.inner.get_bond_details(symbol)
+    }
+
+    fn get_liquidity(&self, symbol: &str) -> Result<Option<Decimal>> {
+        self.inner.get_liquidity(symbol)
+    }
+
+    fn get_yield_curve(&self) -> Result<YieldCurve> {
+        self.inner.get_yield_curve()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::cell::Cell;
+
+    /// Prices from 100 following the log `returns`
+    fn prices(returns: &[f64]) -> Vec<Decimal> {
+        let mut price = 100.0f64;
+        let mut prices = vec![Decimal::ONE_HUNDRED];
+        for r in returns {
+            price *= r.exp();
+            prices.push(Decimal::try_from(price).unwrap());
+        }
+        prices
+    }
+
+    /// Serves `prices` as every symbol's history, remembering how many days were asked for
+    struct History {
+        prices: Vec<Decimal>,
+        asked: Cell<usize>,
+    }
+
+    impl MarketDataProvider for History {
+        fn get_current_price(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(*self.prices.last().unwrap())
+        }
+
+        fn get_historical_prices(&self, _symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+            self.asked.set(days);
+            Ok(self.prices[self.prices.len().saturating_sub(days)..].to_vec())
+        }
+
+        fn get_volatility(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ONE)
+        }
+
+        fn get_yield_rate(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+    }
+
+    #[test]
+    fn realized_vol_of_a_small_series() {
+        let series = prices(&[0.1, -0.1, 0.2]);
+        // Mean 1/15; squared deviations sum to 0.14 / 3, over two degrees of freedom
+        let all = realized_vol(&series, 3, 1.0).unwrap();
+        assert!((all - (0.07f64 / 3.0).sqrt()).abs() < 1e-9);
+        // The last two returns, -0.1 and 0.2, deviate 0.15 each from their mean
+        assert!((realized_vol(&series, 2, 1.0).unwrap() - 0.045f64.sqrt()).abs() < 1e-9);
+        // Annualizing scales by the square root of the periods
+        assert!((realized_vol(&series, 3, 365.0).unwrap() - all * 365f64.sqrt()).abs() < 1e-9);
+        // A larger window uses what there is
+        assert_eq!(realized_vol(&series, 50, 1.0), realized_vol(&series, 3, 1.0));
+
+        assert_eq!(realized_vol(&series[..2], 3, 1.0), None);
+        assert_eq!(realized_vol(&[Decimal::ONE, Decimal::ZERO, Decimal::ONE], 2, 1.0), None);
+    }
+
+    #[test]
+    fn ewma_vol_of_a_small_series() {
+        let series = prices(&[0.1, -0.1, 0.2]);
+        // Weights 1, 0.5, 0.25 from the newest: (0.04 + 0.005 + 0.0025) / 1.75
+        let expected = (0.0475f64 / 1.75).sqrt();
+        assert!((ewma_vol(&series, 0.5, 1.0).unwrap() - expected).abs() < 1e-9);
+        // Equal weights give the root mean square
+        assert!((ewma_vol(&series, 1.0, 1.0).unwrap() - (0.06f64 / 3.0).sqrt()).abs() < 1e-9);
+        // A jump weighs more the faster the decay
+        let jump = prices(&[0.0, 0.0, 0.0, 0.1]);
+        assert!(ewma_vol(&jump, 0.5, 1.0).unwrap() > ewma_vol(&jump, 0.94, 1.0).unwrap());
+
+        assert_eq!(ewma_vol(&series, 0.0, 1.0), None);
+        assert_eq!(ewma_vol(&series, 1.5, 1.0), None);
+        assert_eq!(ewma_vol(&series[..1], 0.9, 1.0), None);
+    }
+
+    #[test]
+    fn provider_estimates_from_the_inner_history() {
+        let series = prices(&[0.3, 0.1, -0.1, 0.2]);
+        let history = History { prices: series.clone(), asked: Cell::new(0) };
+        let realized = EstimatedVolatilityProvider::realized(history, 3).unwrap();
+        let volatility = realized.get_volatility("X").unwrap().to_f64().unwrap();
+        assert_eq!(realized.inner().asked.get(), 4);
+        assert!((volatility - (0.07f64 / 3.0 * 365.0).sqrt()).abs() < 1e-9);
+
+        let weekly = realized.with_periods_per_year(52.0).unwrap();
+        let volatility = weekly.get_volatility("X").unwrap().to_f64().unwrap();
+        assert!((volatility - (0.07f64 / 3.0 * 52.0).sqrt()).abs() < 1e-9);
+        // Everything else passes through
+        assert_eq!(weekly.get_current_price("X").unwrap(), *series.last().unwrap());
+
+        let history = History { prices: series, asked: Cell::new(0) };
+        let ewma = EstimatedVolatilityProvider::ewma(history, 0.94).unwrap();
+        ewma.get_volatility("X").unwrap();
+        assert_eq!(ewma.inner().asked.get(), DEFAULT_EWMA_HISTORY);
+
+        let short = History { prices: prices(&[0.1]), asked: Cell::new(0) };
+        let short = EstimatedVolatilityProvider::realized(short, 5).unwrap();
+        assert!(short.get_volatility("X").is_err());
+        assert!(short.with_periods_per_year(0.0).is_err());
+    }
+
+    #[test]
+    fn estimators_validate_their_parameters() {
+        assert!(VolatilityEstimator::Realized { window: 1 }.validate().is_err());
+        assert!(VolatilityEstimator::Realized { window: 2 }.validate().is_ok());
+        assert!(VolatilityEstimator::Ewma { lambda: 0.0, history: 10 }.validate().is_err());
+        assert!(VolatilityEstimator::Ewma { lambda: 1.0, history: 10 }.validate().is_ok());
+        assert!(VolatilityEstimator::Ewma { lambda: 0.9, history: 1 }.validate().is_err());
+    }
+
+    proptest! {
+        /// With zero-mean returns the sample variance is n / (n - 1) times
+        /// the mean square, which EWMA reaches as lambda tends to one
+        #[test]
+        fn ewma_approaches_realized_vol_as_lambda_tends_to_one(
+            raw in prop::collection::vec(-0.05f64..0.05, 30..120),
+        ) {
+            let mean = raw.iter().sum::<f64>() / raw.len() as f64;
+            let returns: Vec<f64> = raw.iter().map(|r| r - mean).collect();
+            prop_assume!(returns.iter().any(|r| r.abs() > 1e-4));
+            let series = prices(&returns);
+            let n = returns.len() as f64;
+            let realized = realized_vol(&series, returns.len(), 365.0).unwrap() * ((n - 1.0) / n).sqrt();
+
+            let gap = |lambda: f64| (ewma_vol(&series, lambda, 365.0).unwrap() / realized - 1.0).abs();
+            prop_assert!(gap(1.0 - 1e-9) < 1e-6, "gap {}", gap(1.0 - 1e-9));
+            prop_assert!(gap(1.0 - 1e-9) <= gap(0.999) + 1e-9);
+        }
+    }
+}
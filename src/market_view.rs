@@ -0,0 +1,288 @@
+//! Point-in-time market data for strategies.
+//!
+//! A [`MarketView`] shows a strategy the market as of one moment: current
+//! prices and the bars of a [`PriceHistory`] up to that moment. Bars after it
+//! are cut off by the view itself, so a strategy reading history can't see the
+//! future however much of it asks for. When trades move prices, the view also
+//! shows each symbol's liquidity, so strategies can size orders to it.
+
+use crate::liquidity::PriceImpact;
+use crate::metrics::Moments;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use time::{Date, OffsetDateTime};
+
+/// Closes of each symbol in time order
+#[derive(Debug, Clone, Default)]
+pub struct PriceHistory {
+    bars: HashMap<String, Vec<(OffsetDateTime, Decimal)>>,
+}
+
+impl PriceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History of daily closes at midnight UTC on their dates, given in date order
+    pub fn from_daily_closes<'a>(days: impl IntoIterator<Item = (&'a Date, &'a HashMap<String, Decimal>)>) -> Self {
+        let mut history = Self::new();
+        for (date, prices) in days {
+            for (symbol, close) in prices {
+                history.push(symbol, date.midnight().assume_utc(), *close);
+            }
+        }
+        history
+    }
+
+    /// History of closes at their bar times, given in time order
+    pub fn from_closes<'a>(bars: impl IntoIterator<Item = (&'a OffsetDateTime, &'a HashMap<String, Decimal>)>) -> Self {
+        let mut history = Self::new();
+        for (timestamp, prices) in bars {
+            for (symbol, close) in prices {
+                history.push(symbol, *timestamp, *close);
+            }
+        }
+        history
+    }
+
+    /// Add `symbol`'s close at `timestamp`, which must not be before its last one
+    pub fn push(&mut self, symbol: &str, timestamp: OffsetDateTime, close: Decimal) {
+        let bars = self.bars.entry(symbol.to_string()).or_default();
+        debug_assert!(bars.last().is_none_or(|(last, _)| *last <= timestamp));
+        bars.push((timestamp, close));
+    }
+
+    /// Symbols with any history
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.bars.keys().map(|symbol| symbol.as_str())
+    }
+
+    /// `symbol`'s bars at or before `time`
+    fn bars_through(&self, symbol: &str, time: OffsetDateTime) -> &[(OffsetDateTime, Decimal)] {
+        let bars = self.bars.get(symbol).map_or(&[][..], |bars| bars.as_slice());
+        &bars[..bars.partition_point(|(timestamp, _)| *timestamp <= time)]
+    }
+}
+
+/// The market as a strategy may see it at one moment
+#[derive(Debug, Clone, Copy)]
+pub struct MarketView<'a> {
+    timestamp: OffsetDateTime,
+    prices: &'a HashMap<String, Decimal>,
+    history: Option<&'a PriceHistory>,
+    liquidity: Option<(&'a HashMap<String, Decimal>, PriceImpact)>,
+}
+
+impl<'a> MarketView<'a> {
+    /// `prices` at `timestamp`, with no history
+    pub fn new(timestamp: OffsetDateTime, prices: &'a HashMap<String, Decimal>) -> Self {
+        Self { timestamp, prices, history: None, liquidity: None }
+    }
+
+    /// Also show `history` up to the view's timestamp
+    pub fn with_history(mut self, history: &'a PriceHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Also show each symbol's `liquidity`, which trades move prices against by `impact`
+    pub fn with_liquidity(mut self, liquidity: &'a HashMap<String, Decimal>, impact: PriceImpact) -> Self {
+        self.liquidity = Some((liquidity, impact));
+        self
+    }
+
+    pub fn timestamp(&self) -> OffsetDateTime {
+        self.timestamp
+    }
+
+    /// Current price of every symbol
+    pub fn prices(&self) -> &'a HashMap<String, Decimal> {
+        self.prices
+    }
+
+    pub fn price(&self, symbol: &str) -> Option<Decimal> {
+        self.prices.get(symbol).copied()
+    }
+
+    /// Average daily dollar volume or pool depth `symbol` trades against, if known
+    pub fn liquidity(&self, symbol: &str) -> Option<Decimal> {
+        self.liquidity.and_then(|(liquidity, _)| liquidity.get(symbol).copied())
+    }
+
+    /// Price impact of trading `notional` of `symbol`, as a fraction of price;
+    /// `None` when trades don't move its price
+    pub fn price_impact(&self, symbol: &str, notional: Decimal) -> Option<f64> {
+        let (_, impact) = self.liquidity?;
+        Some(impact.impact(notional, self.liquidity(symbol)?))
+    }
+
+    /// Largest order in `symbol` that moves its price at most `max_impact`;
+    /// `None` when any size will do
+    pub fn max_notional(&self, symbol: &str, max_impact: f64) -> Option<Decimal> {
+        let (_, impact) = self.liquidity?;
+        impact.max_notional(self.liquidity(symbol)?, max_impact)
+    }
+
+    /// `symbol`'s last `bars` closes at or before the view's timestamp, oldest
+    /// first; fewer when the history is shorter
+    pub fn history(&self, symbol: &str, bars: usize) -> &'a [(OffsetDateTime, Decimal)] {
+        let Some(history) = self.history else {
+            return &[];
+        };
+        let visible = history.bars_through(symbol, self.timestamp);
+        &visible[visible.len().saturating_sub(bars)..]
+    }
+
+    /// Sample standard deviation of `symbol`'s last `bars` bar-to-bar returns,
+    /// not annualized; `None` with fewer than two returns in the history
+    pub fn realized_volatility(&self, symbol: &str, bars: usize) -> Option<f64> {
+        let closes = self.history(symbol, bars.saturating_add(1));
+        let mut moments = Moments::new();
+        for pair in closes.windows(2) {
+            let (previous, close) = (pair[0].1, pair[1].1);
+            if previous > Decimal::ZERO {
+                moments.push(((close - previous) / previous).to_f64().unwrap_or(0.0));
+            }
+        }
+        (moments.count() >= 2).then(|| moments.variance().sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::RoutingStrategy;
+    use crate::types::{Portfolio, RoutingDecision};
+    use anyhow::Result;
+    use rust_decimal_macros::dec;
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+    use time::macros::date;
+
+    /// Asks every view for all the history there is, recording the latest bar
+    /// and lowest close it was shown
+    #[derive(Default)]
+    struct PeekingStrategy {
+        seen: RefCell<Vec<(OffsetDateTime, OffsetDateTime, Decimal)>>,
+    }
+
+    impl RoutingStrategy for PeekingStrategy {
+        fn generate_routing_decisions(
+            &self,
+            _: &Portfolio,
+            _: &HashMap<String, Decimal>,
+        ) -> Result<Vec<RoutingDecision>> {
+            Ok(vec![])
+        }
+
+        fn generate_decisions(&self, _: &Portfolio, view: &MarketView) -> Result<Vec<RoutingDecision>> {
+            let everything = view.history("ETH", usize::MAX);
+            let latest = everything.last().map_or(OffsetDateTime::UNIX_EPOCH, |(timestamp, _)| *timestamp);
+            let lowest = everything.iter().map(|(_, close)| *close).min().unwrap_or(Decimal::ZERO);
+            self.seen.borrow_mut().push((view.timestamp(), latest, lowest));
+            Ok(vec![])
+        }
+
+        fn name(&self) -> &str {
+            "peeking"
+        }
+    }
+
+    /// 31 days of ETH closes, crashing to 500 on the last if `crash`
+    fn closes(crash: bool) -> BTreeMap<Date, HashMap<String, Decimal>> {
+        (0..=30)
+            .map(|day| {
+                let eth = if crash && day == 30 { dec!(500) } else { Decimal::from(2000 + 10 * (day % 3)) };
+                (date!(2024-01-01) + time::Duration::days(day), HashMap::from([("ETH".to_string(), eth)]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strategies_cannot_see_past_the_view() {
+        let days = closes(true);
+        let history = PriceHistory::from_daily_closes(&days);
+        let strategy = PeekingStrategy::default();
+        let portfolio = Portfolio::new(dec!(1000000));
+        for (date, prices) in &days {
+            let view = MarketView::new(date.midnight().assume_utc(), prices).with_history(&history);
+            strategy.generate_decisions(&portfolio, &view).unwrap();
+        }
+
+        let seen = strategy.seen.borrow();
+        assert_eq!(seen.len(), 31);
+        for (now, latest, lowest) in seen.iter() {
+            assert_eq!(latest, now);
+            // The crash shows up on its own day and no earlier
+            assert_eq!(*lowest == dec!(500), now.date() == date!(2024-01-31), "on {}", now);
+        }
+    }
+
+    #[test]
+    fn trailing_volatility_ignores_what_comes_next() {
+        let (crashing, calm) = (closes(true), closes(false));
+        let history = PriceHistory::from_daily_closes(&crashing);
+        let calm_history = PriceHistory::from_daily_closes(&calm);
+        for (date, prices) in crashing.range(..date!(2024-01-31)) {
+            let time = date.midnight().assume_utc();
+            let view = MarketView::new(time, prices).with_history(&history);
+            let calm_view = MarketView::new(time, prices).with_history(&calm_history);
+            assert_eq!(view.realized_volatility("ETH", 10), calm_view.realized_volatility("ETH", 10));
+        }
+    }
+
+    #[test]
+    fn history_is_the_trailing_bars() {
+        let days: BTreeMap<Date, HashMap<String, Decimal>> = [dec!(100), dec!(110), dec!(99)]
+            .into_iter()
+            .enumerate()
+            .map(|(day, close)| {
+                (date!(2024-01-01) + time::Duration::days(day as i64), HashMap::from([("X".to_string(), close)]))
+            })
+            .collect();
+        let history = PriceHistory::from_daily_closes(&days);
+        let prices = HashMap::new();
+        let view = MarketView::new(date!(2024-01-02).midnight().assume_utc(), &prices).with_history(&history);
+        let closes = |bars: &[(OffsetDateTime, Decimal)]| bars.iter().map(|(_, close)| *close).collect::<Vec<_>>();
+
+        assert_eq!(closes(view.history("X", 1)), [dec!(110)]);
+        assert_eq!(closes(view.history("X", 5)), [dec!(100), dec!(110)]);
+        assert!(view.history("Y", 5).is_empty());
+        assert!(MarketView::new(view.timestamp(), &prices).history("X", 5).is_empty());
+        // One return isn't enough for a volatility
+        assert_eq!(view.realized_volatility("X", 10), None);
+
+        // Returns of +10% and -10%: a sample standard deviation of 0.1 * sqrt(2)
+        let last_day = MarketView::new(date!(2024-01-03).midnight().assume_utc(), &prices).with_history(&history);
+        let volatility = last_day.realized_volatility("X", 10).unwrap();
+        assert!((volatility - 0.1 * 2_f64.sqrt()).abs() < 1e-12);
+    }
+
+    /// Sees only the current prices, through the default `generate_decisions`
+    struct PricesOnly(RefCell<Option<HashMap<String, Decimal>>>);
+
+    impl RoutingStrategy for PricesOnly {
+        fn generate_routing_decisions(
+            &self,
+            _: &Portfolio,
+            market_state: &HashMap<String, Decimal>,
+        ) -> Result<Vec<RoutingDecision>> {
+            *self.0.borrow_mut() = Some(market_state.clone());
+            Ok(vec![])
+        }
+
+        fn name(&self) -> &str {
+            "prices only"
+        }
+    }
+
+    #[test]
+    fn default_decisions_see_the_current_prices() {
+        let prices = HashMap::from([("ETH".to_string(), dec!(2000))]);
+        let strategy = PricesOnly(RefCell::new(None));
+        let view = MarketView::new(OffsetDateTime::UNIX_EPOCH, &prices);
+        strategy.generate_decisions(&Portfolio::new(dec!(1000)), &view).unwrap();
+        assert_eq!(strategy.0.borrow().as_ref(), Some(&prices));
+    }
+}
@@ -0,0 +1,1172 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Default periods per year used to annualize per-step statistics
+pub const PERIODS_PER_YEAR: f64 = 252.0;
+
+/// The `p` quantile of ascending `sorted`, interpolating linearly between
+/// order statistics (Hyndman–Fan type 7, as in R and NumPy): `p = 0` is the
+/// minimum, `p = 1` the maximum. Zero for an empty slice.
+pub fn quantile(sorted: &[f64], p: f64) -> f64 {
+    let Some((lower, upper, weight)) = quantile_position(sorted.len(), p) else {
+        return 0.0;
+    };
+    sorted[lower] + weight * (sorted[upper] - sorted[lower])
+}
+
+/// [`quantile`] over `Decimal`s
+pub fn quantile_decimal(sorted: &[Decimal], p: f64) -> Decimal {
+    let Some((lower, upper, weight)) = quantile_position(sorted.len(), p) else {
+        return Decimal::ZERO;
+    };
+    let weight = Decimal::try_from(weight).unwrap_or(Decimal::ZERO);
+    sorted[lower] + weight * (sorted[upper] - sorted[lower])
+}
+
+/// Order statistics bracketing the type-7 `p` quantile of `len` values, and
+/// the weight of the upper one
+fn quantile_position(len: usize, p: f64) -> Option<(usize, usize, f64)> {
+    if len == 0 {
+        return None;
+    }
+    let h = (len - 1) as f64 * p.clamp(0.0, 1.0);
+    let lower = h.floor() as usize;
+    Some((lower, (lower + 1).min(len - 1), h - lower as f64))
+}
+
+/// Annualized Sortino ratio of per-step `returns`: mean return in excess of
+/// the minimum acceptable return over the downside deviation. The minimum is
+/// an annual rate, spread evenly over `periods_per_year` steps; the downside
+/// deviation is the root mean square of shortfalls below it, over every step.
+/// `None` when no step falls short, rather than infinity.
+pub fn sortino_ratio(returns: &[Decimal], minimum_acceptable_return: f64, periods_per_year: f64) -> Option<f64> {
+    if returns.is_empty() || periods_per_year <= 0.0 {
+        return None;
+    }
+    let target = minimum_acceptable_return / periods_per_year;
+    let (mut excess, mut shortfall_squares) = (0.0, 0.0);
+    for step_return in returns {
+        let step_excess = step_return.to_f64().unwrap_or(0.0) - target;
+        excess += step_excess;
+        shortfall_squares += step_excess.min(0.0).powi(2);
+    }
+    let n = returns.len() as f64;
+    let downside_deviation = (shortfall_squares / n).sqrt();
+    (downside_deviation > 0.0).then(|| excess / n / downside_deviation * periods_per_year.sqrt())
+}
+
+/// Calmar ratio: annualized return over max drawdown, both in percent; `None`
+/// without a drawdown, rather than infinity
+pub fn calmar_ratio(annualized_return_pct: f64, max_drawdown_pct: f64) -> Option<f64> {
+    (max_drawdown_pct > 0.0).then(|| annualized_return_pct / max_drawdown_pct)
+}
+
+/// Annualized volatility of `closes`, oldest first: the sample standard
+/// deviation of their log returns, scaled by the square root of
+/// `periods_per_year`. `None` with fewer than two returns or a non-positive
+/// close.
+pub fn log_return_volatility(closes: &[Decimal], periods_per_year: f64) -> Option<f64> {
+    if closes.len() < 3 || closes.iter().any(|close| *close <= Decimal::ZERO) {
+        return None;
+    }
+    let closes: Vec<f64> = closes.iter().map(|close| close.to_f64()).collect::<Option<_>>()?;
+    let returns: Vec<f64> = closes.windows(2).map(|pair| (pair[1] / pair[0]).ln()).collect();
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt() * periods_per_year.sqrt())
+}
+
+/// Performance metrics maintained incrementally as portfolio values arrive,
+/// in memory that doesn't grow with the number of steps.
+///
+/// Returns and drawdown are computed in `Decimal`, so large books don't pick up
+/// binary floating-point rounding. Mean and variance of returns use Welford's
+/// algorithm (also in `Decimal`); Sharpe and volatility convert the variance to
+/// `f64` only for the final square root, since `Decimal` has no exact one. The
+/// Sortino downside deviation accumulates shortfalls below the minimum
+/// acceptable return as steps arrive, so that minimum is fixed up front. VaR
+/// and CVaR read the return distribution from a [`TDigest`], which is exact
+/// until runs outgrow its buffer and within its stated accuracy after.
+#[derive(Debug, Clone)]
+pub struct RunningMetrics {
+    periods_per_year: f64,
+    /// Annual rate the Sortino ratio measures shortfalls against
+    minimum_acceptable_return: f64,
+    initial_value: Option<Decimal>,
+    last_value: Option<Decimal>,
+    last_return: Option<Decimal>,
+    count: usize,
+    mean: Decimal,
+    m2: Decimal,
+    /// Sum of squared per-step shortfalls below the minimum acceptable return
+    shortfall_squares: f64,
+    /// Distribution of per-step returns, for VaR and CVaR
+    return_distribution: TDigest,
+    peak: Decimal,
+    max_drawdown_pct: Decimal,
+    /// Product of `1 + return` over every step: the time-weighted growth factor
+    growth: Decimal,
+    /// Consecutive steps below the peak, up to the latest one
+    underwater_run: usize,
+    longest_underwater: usize,
+    underwater_steps: usize,
+    drawdown_episodes: usize,
+    /// Step of the deepest trough so far, while it is still below its peak
+    max_drawdown_trough: Option<usize>,
+    max_drawdown_recovery: Option<usize>,
+}
+
+/// How long a value series spent below its running peak, in steps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrawdownDurations {
+    /// Longest run of consecutive steps below the peak, including one still open at the end
+    pub longest_steps: usize,
+    /// Mean length of a drawdown episode; zero if the series never fell below its peak
+    pub mean_steps: f64,
+    /// Share of steps spent below the peak, in percent
+    pub underwater_pct: f64,
+    /// Steps from the deepest trough back up to its peak: `Some(0)` without a
+    /// drawdown, `None` if the series never recovered
+    pub recovery_steps: Option<usize>,
+}
+
+impl Default for RunningMetrics {
+    fn default() -> Self {
+        Self::with_periods_per_year(PERIODS_PER_YEAR)
+    }
+}
+
+impl RunningMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Metrics annualized with `periods_per_year` steps per year
+    pub fn with_periods_per_year(periods_per_year: f64) -> Self {
+        Self {
+            periods_per_year,
+            minimum_acceptable_return: 0.0,
+            initial_value: None,
+            last_value: None,
+            last_return: None,
+            count: 0,
+            mean: Decimal::ZERO,
+            m2: Decimal::ZERO,
+            shortfall_squares: 0.0,
+            return_distribution: TDigest::default(),
+            peak: Decimal::ZERO,
+            max_drawdown_pct: Decimal::ZERO,
+            growth: Decimal::ONE,
+            underwater_run: 0,
+            longest_underwater: 0,
+            underwater_steps: 0,
+            drawdown_episodes: 0,
+            max_drawdown_trough: None,
+            max_drawdown_recovery: None,
+        }
+    }
+
+    /// Measure the Sortino ratio's shortfalls against `rate` a year instead of zero
+    pub fn with_minimum_acceptable_return(mut self, rate: f64) -> Self {
+        self.minimum_acceptable_return = rate;
+        self
+    }
+
+    pub fn periods_per_year(&self) -> f64 {
+        self.periods_per_year
+    }
+
+    /// Clear all state, keeping the annualization and minimum acceptable return
+    pub fn reset(&mut self) {
+        self.initial_value = None;
+        self.last_value = None;
+        self.last_return = None;
+        self.count = 0;
+        self.mean = Decimal::ZERO;
+        self.m2 = Decimal::ZERO;
+        self.shortfall_squares = 0.0;
+        self.return_distribution = TDigest::default();
+        self.peak = Decimal::ZERO;
+        self.max_drawdown_pct = Decimal::ZERO;
+        self.growth = Decimal::ONE;
+        self.underwater_run = 0;
+        self.longest_underwater = 0;
+        self.underwater_steps = 0;
+        self.drawdown_episodes = 0;
+        self.max_drawdown_trough = None;
+        self.max_drawdown_recovery = None;
+    }
+
+    /// Record the portfolio value at the end of a step
+    pub fn record(&mut self, value: Decimal) {
+        self.record_with_flow(value, Decimal::ZERO);
+    }
+
+    /// Record the value at the end of a step in which `flow` was deposited (or,
+    /// if negative, withdrawn).
+    ///
+    /// The flow is taken out of the step's return, and the drawdown peak moves
+    /// with it, so deposits and withdrawals don't count as performance.
+    pub fn record_with_flow(&mut self, value: Decimal, flow: Decimal) {
+        match self.last_value {
+            None => {
+                self.initial_value = Some(value);
+                self.peak = value;
+            }
+            Some(prev) => {
+                let step_return = if prev > Decimal::ZERO {
+                    (value - flow - prev) / prev
+                } else {
+                    Decimal::ZERO
+                };
+                self.peak = (self.peak + flow).max(Decimal::ZERO);
+                self.growth *= Decimal::ONE + step_return;
+
+                self.count += 1;
+                let delta = step_return - self.mean;
+                self.mean += delta / Decimal::from(self.count);
+                self.m2 += delta * (step_return - self.mean);
+                let step_return_f64 = step_return.to_f64().unwrap_or(0.0);
+                self.shortfall_squares += (step_return_f64 - self.step_target()).min(0.0).powi(2);
+                self.return_distribution.push(step_return_f64);
+                self.last_return = Some(step_return);
+            }
+        }
+        self.last_value = Some(value);
+
+        if value > self.peak {
+            self.peak = value;
+        }
+        let drawdown = self.drawdown_pct_decimal(value);
+        if drawdown > Decimal::ZERO {
+            if self.underwater_run == 0 {
+                self.drawdown_episodes += 1;
+            }
+            self.underwater_run += 1;
+            self.underwater_steps += 1;
+            self.longest_underwater = self.longest_underwater.max(self.underwater_run);
+        } else {
+            self.underwater_run = 0;
+            if let Some(trough) = self.max_drawdown_trough.take() {
+                self.max_drawdown_recovery = Some(self.count - trough);
+            }
+        }
+        if drawdown > self.max_drawdown_pct {
+            self.max_drawdown_pct = drawdown;
+            self.max_drawdown_trough = Some(self.count);
+            self.max_drawdown_recovery = None;
+        }
+    }
+
+    /// First value recorded
+    pub fn initial_value(&self) -> Option<Decimal> {
+        self.initial_value
+    }
+
+    /// Return of the latest step; `None` until two values are recorded
+    pub fn last_return(&self) -> Option<Decimal> {
+        self.last_return
+    }
+
+    /// Number of per-step returns recorded
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Compounded return over every recorded step, net of cash flows
+    pub fn time_weighted_return(&self) -> Decimal {
+        self.growth - Decimal::ONE
+    }
+
+    pub fn mean_return(&self) -> Decimal {
+        self.mean
+    }
+
+    /// Population variance of per-step returns
+    pub fn variance(&self) -> Decimal {
+        if self.count == 0 {
+            return Decimal::ZERO;
+        }
+        (self.m2 / Decimal::from(self.count)).max(Decimal::ZERO)
+    }
+
+    /// Population standard deviation of per-step returns (the one `f64` conversion)
+    pub fn std_dev(&self) -> f64 {
+        self.variance().to_f64().unwrap_or(0.0).sqrt()
+    }
+
+    /// Annualized Sharpe ratio (zero risk-free rate)
+    pub fn sharpe_ratio(&self) -> f64 {
+        let std_dev = self.std_dev();
+        if std_dev > 0.0 {
+            self.mean.to_f64().unwrap_or(0.0) / std_dev * self.periods_per_year.sqrt()
+        } else {
+            0.0
+        }
+    }
+
+    /// Annualized Sortino ratio over the minimum acceptable return, as
+    /// [`sortino_ratio`] would give over every recorded return
+    pub fn sortino_ratio(&self) -> Option<f64> {
+        if self.count == 0 || self.periods_per_year <= 0.0 {
+            return None;
+        }
+        let n = self.count as f64;
+        let excess = self.mean.to_f64().unwrap_or(0.0) - self.step_target();
+        let downside_deviation = (self.shortfall_squares / n).sqrt();
+        (downside_deviation > 0.0).then(|| excess / downside_deviation * self.periods_per_year.sqrt())
+    }
+
+    /// Minimum acceptable return spread over one step
+    fn step_target(&self) -> f64 {
+        if self.periods_per_year > 0.0 {
+            self.minimum_acceptable_return / self.periods_per_year
+        } else {
+            0.0
+        }
+    }
+
+    /// Time-weighted return compounded to a year, in percent
+    pub fn annualized_return_pct(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let growth = self.growth.to_f64().unwrap_or(1.0);
+        (growth.powf(self.periods_per_year / self.count as f64) - 1.0) * 100.0
+    }
+
+    /// Annualized return over max drawdown; see [`calmar_ratio`]
+    pub fn calmar_ratio(&self) -> Option<f64> {
+        calmar_ratio(self.annualized_return_pct(), self.max_drawdown_pct())
+    }
+
+    /// Annualized volatility in percent
+    pub fn volatility_pct(&self) -> f64 {
+        self.std_dev() * self.periods_per_year.sqrt() * 100.0
+    }
+
+    pub fn max_drawdown_pct(&self) -> f64 {
+        self.max_drawdown_pct.to_f64().unwrap_or(0.0)
+    }
+
+    /// Time spent in drawdown, counting the first recorded value as step 0
+    pub fn drawdown_durations(&self) -> DrawdownDurations {
+        DrawdownDurations {
+            longest_steps: self.longest_underwater,
+            mean_steps: if self.drawdown_episodes > 0 {
+                self.underwater_steps as f64 / self.drawdown_episodes as f64
+            } else {
+                0.0
+            },
+            underwater_pct: if self.count > 0 {
+                self.underwater_steps as f64 / self.count as f64 * 100.0
+            } else {
+                0.0
+            },
+            recovery_steps: match self.max_drawdown_trough {
+                Some(_) => None,
+                None => Some(self.max_drawdown_recovery.unwrap_or(0)),
+            },
+        }
+    }
+
+    /// Drawdown in percent that `value` would represent from the running peak
+    pub fn drawdown_pct_at(&self, value: Decimal) -> f64 {
+        self.drawdown_pct_decimal(value).to_f64().unwrap_or(0.0)
+    }
+
+    fn drawdown_pct_decimal(&self, value: Decimal) -> Decimal {
+        let peak = self.peak.max(value);
+        if peak > Decimal::ZERO {
+            (peak - value) / peak * dec!(100)
+        } else {
+            Decimal::ZERO
+        }
+    }
+
+    /// Historical VaR of one step's return, scaled to `current_value`
+    pub fn value_at_risk(&self, confidence: f64, current_value: Decimal) -> Decimal {
+        let var_return = self.return_distribution.quantile(1.0 - confidence);
+        current_value * Decimal::try_from(var_return).unwrap_or(Decimal::ZERO).abs()
+    }
+
+    /// Average one-step return at or below the VaR quantile, scaled to `current_value`
+    pub fn conditional_var(&self, confidence: f64, current_value: Decimal) -> Decimal {
+        let avg_tail_return = self.return_distribution.tail_mean(1.0 - confidence);
+        current_value * Decimal::try_from(avg_tail_return).unwrap_or(Decimal::ZERO).abs()
+    }
+}
+
+/// Welford mean and variance of active returns (strategy minus benchmark)
+#[derive(Debug, Clone, Default)]
+pub struct ActiveReturns {
+    count: usize,
+    mean: Decimal,
+    m2: Decimal,
+}
+
+impl ActiveReturns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn record(&mut self, strategy_return: Decimal, benchmark_return: Decimal) {
+        let active = strategy_return - benchmark_return;
+        self.count += 1;
+        let delta = active - self.mean;
+        self.mean += delta / Decimal::from(self.count);
+        self.m2 += delta * (active - self.mean);
+    }
+
+    fn std_dev(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        (self.m2 / Decimal::from(self.count)).max(Decimal::ZERO).to_f64().unwrap_or(0.0).sqrt()
+    }
+
+    /// Annualized standard deviation of active returns, in percent
+    pub fn tracking_error_pct(&self, periods_per_year: f64) -> f64 {
+        self.std_dev() * periods_per_year.sqrt() * 100.0
+    }
+
+    /// Annualized mean active return per unit of tracking error
+    pub fn information_ratio(&self, periods_per_year: f64) -> f64 {
+        let std_dev = self.std_dev();
+        if std_dev > 0.0 {
+            self.mean.to_f64().unwrap_or(0.0) / std_dev * periods_per_year.sqrt()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Mean, variance, skewness and kurtosis of a sample, accumulated in one pass.
+///
+/// Central moment sums are updated per value with the Welford/Terriberry
+/// recurrences, so there is no catastrophic cancellation from raw power sums
+/// and the values themselves needn't be kept.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Moments {
+    count: usize,
+    mean: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl Moments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, value: f64) {
+        let n1 = self.count as f64;
+        self.count += 1;
+        let n = self.count as f64;
+        let delta = value - self.mean;
+        let delta_n = delta / n;
+        let delta_n2 = delta_n * delta_n;
+        let term1 = delta * delta_n * n1;
+        self.mean += delta_n;
+        self.m4 += term1 * delta_n2 * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n2 * self.m2 - 4.0 * delta_n * self.m3;
+        self.m3 += term1 * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Unbiased sample variance
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+
+    /// Adjusted Fisher–Pearson sample skewness (G1, as in Excel and pandas);
+    /// zero with fewer than three values or no spread
+    pub fn skewness(&self) -> f64 {
+        let n = self.count as f64;
+        if self.count < 3 || self.m2 <= 0.0 {
+            return 0.0;
+        }
+        let g1 = n.sqrt() * self.m3 / self.m2.powf(1.5);
+        g1 * (n * (n - 1.0)).sqrt() / (n - 2.0)
+    }
+
+    /// Bias-adjusted sample excess kurtosis (G2, as in Excel and pandas); zero
+    /// with fewer than four values or no spread
+    pub fn excess_kurtosis(&self) -> f64 {
+        let n = self.count as f64;
+        if self.count < 4 || self.m2 <= 0.0 {
+            return 0.0;
+        }
+        let g2 = n * self.m4 / (self.m2 * self.m2) - 3.0;
+        ((n + 1.0) * g2 + 6.0) * (n - 1.0) / ((n - 2.0) * (n - 3.0))
+    }
+}
+
+impl FromIterator<f64> for Moments {
+    fn from_iter<I: IntoIterator<Item = f64>>(values: I) -> Self {
+        let mut moments = Self::new();
+        for value in values {
+            moments.push(value);
+        }
+        moments
+    }
+}
+
+/// Default t-digest compression: at most about 500 centroids, under 30 kB with the merge buffer
+pub const DEFAULT_COMPRESSION: f64 = 500.0;
+
+/// Streaming quantile sketch (the merging t-digest).
+///
+/// Values are buffered and periodically merged into weighted centroids whose
+/// size is bounded by the arcsine scale function, so centroids near the tails
+/// stay small while the body is summarized coarsely. Memory is `O(compression)` regardless of how many values are
+/// pushed, and quantiles interpolate between centroid centres with the same
+/// type-7 convention as [`quantile`], which it reproduces exactly while every
+/// centroid is a single value.
+///
+/// Against the exact computation on 100k samples from normal, lognormal and
+/// Student's t (3 degrees of freedom) distributions at the default
+/// compression, 1st–99th percentile estimates sit within 0.05 percentile
+/// points of the requested rank, and the mean of the lowest 5% within 0.1% of
+/// the sample's interquartile range.
+/// Where the distribution has a gap, as between shocked and unshocked Monte
+/// Carlo paths, a small rank error can still move the value noticeably.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TDigest {
+    compression: f64,
+    /// Merged centroids as `(mean, weight)`, ascending by mean
+    centroids: Vec<(f64, f64)>,
+    buffer: Vec<f64>,
+    count: usize,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl TDigest {
+    /// A sketch holding at most about `compression` centroids
+    pub fn new(compression: f64) -> Self {
+        Self {
+            compression: compression.max(10.0),
+            centroids: vec![],
+            buffer: vec![],
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Add a value; non-finite values are ignored
+    pub fn push(&mut self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+        if self.buffer.len() >= 5 * self.compression as usize {
+            self.merge_buffer();
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Fold buffered values into the centroids
+    pub fn flush(&mut self) {
+        if !self.buffer.is_empty() {
+            self.merge_buffer();
+        }
+    }
+
+    /// Estimated type-7 `p` quantile; zero when empty
+    pub fn quantile(&self, p: f64) -> f64 {
+        let digest = self.merged();
+        let centroids = &digest.centroids;
+        if centroids.is_empty() {
+            return 0.0;
+        }
+        // Rank of the quantile on the scale where centroid centres sit at
+        // cumulative weight minus half their own weight
+        let rank = p.clamp(0.0, 1.0) * (self.count - 1) as f64 + 0.5;
+        let mut cumulative = 0.0;
+        let mut previous = (self.min, 0.0);
+        for &(mean, weight) in centroids {
+            let centre = cumulative + weight / 2.0;
+            if rank <= centre {
+                return Self::interpolate(previous, (mean, centre), rank);
+            }
+            cumulative += weight;
+            previous = (mean, centre);
+        }
+        Self::interpolate(previous, (self.max, self.count as f64), rank)
+    }
+
+    /// Estimated share of values at or below `value`
+    pub fn cdf(&self, value: f64) -> f64 {
+        if self.count == 0 || value < self.min {
+            return 0.0;
+        }
+        if value >= self.max {
+            return 1.0;
+        }
+        let digest = self.merged();
+        let mut cumulative = 0.0;
+        let mut previous = (self.min, 0.0);
+        for &(mean, weight) in &digest.centroids {
+            let centre = cumulative + weight / 2.0;
+            if value < mean {
+                return Self::interpolate_rank(previous, (mean, centre), value) / self.count as f64;
+            }
+            cumulative += weight;
+            previous = (mean, centre);
+        }
+        Self::interpolate_rank(previous, (self.max, self.count as f64), value) / self.count as f64
+    }
+
+    /// Estimated mean of the values at or below the `p` quantile: the lowest
+    /// `p (n - 1) + 1` values, matching the exact tail of a type-7 quantile
+    /// without ties. Zero when empty
+    pub fn tail_mean(&self, p: f64) -> f64 {
+        let digest = self.merged();
+        if digest.centroids.is_empty() {
+            return 0.0;
+        }
+        let tail = (p.clamp(0.0, 1.0) * (self.count - 1) as f64).floor() + 1.0;
+        let mut taken = 0.0;
+        let mut sum = 0.0;
+        for &(mean, weight) in &digest.centroids {
+            let take = weight.min(tail - taken);
+            sum += take * mean;
+            taken += take;
+            if taken >= tail {
+                break;
+            }
+        }
+        sum / taken
+    }
+
+    fn interpolate((x0, r0): (f64, f64), (x1, r1): (f64, f64), rank: f64) -> f64 {
+        if r1 <= r0 {
+            return x1;
+        }
+        x0 + (x1 - x0) * ((rank - r0) / (r1 - r0)).clamp(0.0, 1.0)
+    }
+
+    fn interpolate_rank((x0, r0): (f64, f64), (x1, r1): (f64, f64), value: f64) -> f64 {
+        if x1 <= x0 {
+            return r1;
+        }
+        r0 + (r1 - r0) * ((value - x0) / (x1 - x0)).clamp(0.0, 1.0)
+    }
+
+    /// The sketch with its buffer merged, borrowing when there's nothing to merge
+    fn merged(&self) -> std::borrow::Cow<'_, Self> {
+        if self.buffer.is_empty() {
+            std::borrow::Cow::Borrowed(self)
+        } else {
+            let mut digest = self.clone();
+            digest.merge_buffer();
+            std::borrow::Cow::Owned(digest)
+        }
+    }
+
+    fn merge_buffer(&mut self) {
+        let mut incoming: Vec<(f64, f64)> = self.buffer.drain(..).map(|value| (value, 1.0)).collect();
+        incoming.append(&mut self.centroids);
+        incoming.sort_by(|a, b| a.0.total_cmp(&b.0));
+        
+        let total: f64 = incoming.iter().map(|(_, weight)| weight).sum();
+        let mut merged = Vec::with_capacity(self.compression as usize);
+        let mut iter = incoming.into_iter();
+        let Some(mut current) = iter.next() else {
+            return;
+        };
+        let mut cumulative = 0.0;
+        let mut limit = self.rank_limit(0.0, total);
+        for (mean, weight) in iter {
+            if cumulative + current.1 + weight <= limit {
+                current.0 += (mean - current.0) * weight / (current.1 + weight);
+                current.1 += weight;
+            } else {
+                cumulative += current.1;
+                merged.push(current);
+                limit = self.rank_limit(cumulative, total);
+                current = (mean, weight);
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// Largest cumulative weight a centroid starting at `cumulative` may reach,
+    /// from one unit of the arcsine scale `k(q) = δ/2π · asin(2q − 1)`
+    fn rank_limit(&self, cumulative: f64, total: f64) -> f64 {
+        use std::f64::consts::PI;
+        let q = cumulative / total;
+        let k = self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin() + 1.0;
+        let q_limit = if k >= self.compression / 4.0 {
+            1.0
+        } else {
+            ((2.0 * PI * k / self.compression).sin() + 1.0) / 2.0
+        };
+        q_limit * total
+    }
+}
+
+/// Statistics over the most recent `window` returns, updated in O(1) per value.
+///
+/// Running sums of returns and squared returns are adjusted as returns enter
+/// and leave the window, so nothing is re-scanned.
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    window: usize,
+    periods_per_year: f64,
+    values: VecDeque<Decimal>,
+    returns: VecDeque<Decimal>,
+    sum: Decimal,
+    sum_sq: Decimal,
+}
+
+/// Rolling statistics once a window is full
+#[derive(Debug, Clone, Copy)]
+pub struct RollingStats {
+    /// Compounded return over the window
+    pub rolling_return: Decimal,
+    /// Annualized volatility in percent
+    pub rolling_vol_pct: f64,
+    /// Annualized Sharpe ratio (zero risk-free rate)
+    pub rolling_sharpe: f64,
+}
+
+impl RollingWindow {
+    pub fn new(window: usize, periods_per_year: f64) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            periods_per_year,
+            values: VecDeque::with_capacity(window + 1),
+            returns: VecDeque::with_capacity(window),
+            sum: Decimal::ZERO,
+            sum_sq: Decimal::ZERO,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.returns.clear();
+        self.sum = Decimal::ZERO;
+        self.sum_sq = Decimal::ZERO;
+    }
+
+    /// Add a portfolio value, returning the window's statistics once it holds `window` returns
+    pub fn record(&mut self, value: Decimal) -> Option<RollingStats> {
+        if let Some(&prev) = self.values.back() {
+            let step_return = if prev > Decimal::ZERO {
+                (value - prev) / prev
+            } else {
+                Decimal::ZERO
+            };
+            self.returns.push_back(step_return);
+            self.sum += step_return;
+            self.sum_sq += step_return * step_return;
+
+            if self.returns.len() > self.window {
+                if let Some(old) = self.returns.pop_front() {
+                    self.sum -= old;
+                    self.sum_sq -= old * old;
+                }
+            }
+        }
+        self.values.push_back(value);
+        if self.values.len() > self.window + 1 {
+            self.values.pop_front();
+        }
+
+        if self.returns.len() < self.window {
+            return None;
+        }
+
+        let n = Decimal::from(self.window);
+        let mean = self.sum / n;
+        let variance = (self.sum_sq / n - mean * mean).max(Decimal::ZERO);
+        let std_dev = variance.to_f64().unwrap_or(0.0).sqrt();
+        let annualizer = self.periods_per_year.sqrt();
+
+        let first = self.values.front().copied().unwrap_or(value);
+        let rolling_return = if first > Decimal::ZERO {
+            value / first - Decimal::ONE
+        } else {
+            Decimal::ZERO
+        };
+
+        Some(RollingStats {
+            rolling_return,
+            rolling_vol_pct: std_dev * annualizer * 100.0,
+            rolling_sharpe: if std_dev > 0.0 {
+                mean.to_f64().unwrap_or(0.0) / std_dev * annualizer
+            } else {
+                0.0
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use proptest::prelude::*;
+    use rand::Rng;
+    use rand_distr::{Distribution, LogNormal, Normal, StudentT};
+
+    fn recorded(values: &[Decimal], minimum_acceptable_return: f64) -> RunningMetrics {
+        let mut metrics =
+            RunningMetrics::with_periods_per_year(4.0).with_minimum_acceptable_return(minimum_acceptable_return);
+        for value in values {
+            metrics.record(*value);
+        }
+        metrics
+    }
+
+    /// Values compounding through `returns` from 100
+    fn values(returns: &[Decimal]) -> Vec<Decimal> {
+        std::iter::once(dec!(100))
+            .chain(returns.iter().scan(dec!(100), |value, r| {
+                *value *= Decimal::ONE + r;
+                Some(*value)
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn running_sortino_matches_the_whole_series() {
+        let returns = [dec!(0.02), dec!(-0.01), dec!(0.03), dec!(-0.02), dec!(0.005)];
+        for minimum in [0.0, 0.04, -0.01] {
+            let metrics = recorded(&values(&returns), minimum);
+            let (running, exact) = (metrics.sortino_ratio().unwrap(), sortino_ratio(&returns, minimum, 4.0).unwrap());
+            assert!((running - exact).abs() < 1e-12, "{} vs {} at {}", running, exact, minimum);
+        }
+        assert_eq!(recorded(&values(&[dec!(0.01), dec!(0.02)]), 0.0).sortino_ratio(), None);
+    }
+
+    #[test]
+    fn sortino_and_calmar_match_hand_computed_values() {
+        let returns = [dec!(0.02), dec!(-0.01), dec!(0.03), dec!(-0.02), dec!(0.005)];
+        // Mean 0.005 over a downside deviation of sqrt((0.01² + 0.02²) / 5) = 0.01, times sqrt(4)
+        assert!((sortino_ratio(&returns, 0.0, 4.0).unwrap() - 1.0).abs() < 1e-12);
+        // 4% a year is 1% a step: mean excess -0.005, shortfalls of 0.02, 0.03 and 0.005
+        let expected = -0.005 / (0.001325_f64 / 5.0).sqrt() * 2.0;
+        assert!((sortino_ratio(&returns, 0.04, 4.0).unwrap() - expected).abs() < 1e-12);
+
+        assert_eq!(sortino_ratio(&[dec!(0.01), dec!(0.02)], 0.0, 4.0), None);
+        assert_eq!(sortino_ratio(&[], 0.0, 4.0), None);
+        assert_eq!(calmar_ratio(12.0, 4.0), Some(3.0));
+        assert_eq!(calmar_ratio(-6.0, 4.0), Some(-1.5));
+        assert_eq!(calmar_ratio(12.0, 0.0), None);
+    }
+
+    #[test]
+    fn short_run_var_and_cvar_are_exact() {
+        let returns = [dec!(0.01), dec!(-0.04), dec!(0.02), dec!(-0.01), dec!(0.03), dec!(-0.02), dec!(0.0)];
+        let metrics = recorded(&values(&returns), 0.0);
+        let mut sorted = returns.to_vec();
+        sorted.sort();
+
+        let var = quantile_decimal(&sorted, 0.2);
+        let value_at_risk = metrics.value_at_risk(0.8, dec!(1000));
+        assert!((value_at_risk - dec!(1000) * var.abs()).abs() < dec!(0.000001), "{}", value_at_risk);
+        // The lowest floor(0.2 * 6) + 1 = 2 returns, -4% and -2%
+        let conditional_var = metrics.conditional_var(0.8, dec!(1000));
+        assert!((conditional_var - dec!(30)).abs() < dec!(0.000001), "{}", conditional_var);
+    }
+
+    #[test]
+    fn long_run_var_stays_close_to_the_exact_quantile() {
+        let normal = Normal::new(0.0005, 0.02).unwrap();
+        let mut rng = StdRng::seed_from_u64(9);
+        let returns: Vec<Decimal> =
+            (0..50_000).map(|_| Decimal::try_from(normal.sample(&mut rng)).unwrap().round_dp(10)).collect();
+        let mut metrics = RunningMetrics::new();
+        let mut value = dec!(1000000);
+        metrics.record(value);
+        for r in &returns {
+            value *= Decimal::ONE + r;
+            metrics.record(value);
+        }
+        let mut sorted: Vec<f64> = returns.iter().map(|r| r.to_f64().unwrap()).collect();
+        sorted.sort_by(f64::total_cmp);
+
+        let estimated = metrics.value_at_risk(0.99, Decimal::ONE).to_f64().unwrap();
+        let exact = quantile(&sorted, 0.01).abs();
+        assert!((estimated / exact - 1.0).abs() < 0.01, "{} vs {}", estimated, exact);
+        assert_eq!(metrics.count(), 50_000);
+        assert_eq!(metrics.last_return(), returns.last().copied());
+    }
+
+    #[test]
+    fn reset_forgets_every_step() {
+        let mut metrics = recorded(&values(&[dec!(0.02), dec!(-0.01)]), 0.04);
+        metrics.reset();
+        assert_eq!(metrics.count(), 0);
+        assert_eq!(metrics.last_return(), None);
+        assert_eq!(metrics.sortino_ratio(), None);
+        assert_eq!(metrics.value_at_risk(0.95, dec!(1000)), Decimal::ZERO);
+
+        // The minimum acceptable return survives the reset
+        let again = values(&[dec!(0.02), dec!(-0.01)]);
+        for value in &again {
+            metrics.record(*value);
+        }
+        assert_eq!(metrics.sortino_ratio(), recorded(&again, 0.04).sortino_ratio());
+    }
+
+    fn durations(values: &[i64]) -> DrawdownDurations {
+        let values: Vec<Decimal> = values.iter().copied().map(Decimal::from).collect();
+        recorded(&values, 0.0).drawdown_durations()
+    }
+
+    #[test]
+    fn sawtooth_drawdowns_have_exact_durations() {
+        let teeth = |longest_steps, mean_steps, underwater_pct, recovery_steps| DrawdownDurations {
+            longest_steps,
+            mean_steps,
+            underwater_pct,
+            recovery_steps,
+        };
+        assert_eq!(durations(&[100, 90, 100, 90, 100]), teeth(1, 1.0, 50.0, Some(1)));
+        assert_eq!(durations(&[100, 95, 90, 85, 100, 95, 90, 85, 100]), teeth(3, 3.0, 75.0, Some(1)));
+        // The deepest tooth takes four steps to climb back, though a shallower one came first
+        assert_eq!(durations(&[100, 95, 100, 80, 85, 90, 95, 100, 110]), teeth(4, 2.5, 62.5, Some(4)));
+        assert_eq!(durations(&[100, 101, 102]), teeth(0, 0.0, 0.0, Some(0)));
+    }
+
+    #[test]
+    fn final_drawdown_left_open_has_no_recovery() {
+        // Every tooth peaks below 100, so the book never recovers
+        assert_eq!(
+            durations(&[100, 90, 95, 85, 92, 80]),
+            DrawdownDurations { longest_steps: 5, mean_steps: 5.0, underwater_pct: 100.0, recovery_steps: None }
+        );
+        // Recovering from a shallow drawdown doesn't count once a deeper one is still open
+        let open = durations(&[100, 95, 100, 70, 90]);
+        assert_eq!((open.longest_steps, open.recovery_steps), (2, None));
+    }
+
+    #[test]
+    fn ten_billion_book_keeps_its_cents() {
+        let values = [dec!(10000000000.03), dec!(10000000000.01), dec!(10000000000.04)];
+        let metrics = recorded(&values, 0.0);
+
+        // Two cents off the peak, exactly
+        let exact_drawdown = dec!(0.02) / dec!(10000000000.03) * dec!(100);
+        assert_eq!(metrics.max_drawdown_pct, exact_drawdown);
+        let naive_drawdown = (10000000000.03_f64 - 10000000000.01) / 10000000000.03 * 100.0;
+        let naive_error = (naive_drawdown - exact_drawdown.to_f64().unwrap()).abs();
+        assert!(naive_error > 1e-15, "f64 should lose the cents: {}", naive_error);
+
+        // A cent gained over the run
+        let exact_return = dec!(0.01) / dec!(10000000000.03);
+        assert!((metrics.time_weighted_return() - exact_return).abs() < dec!(1e-24));
+        let naive_return = 10000000000.04_f64 / 10000000000.01 * (10000000000.01 / 10000000000.03) - 1.0;
+        assert!((naive_return - exact_return.to_f64().unwrap()).abs() > 1e-20);
+    }
+
+    #[test]
+    fn quantiles_interpolate_between_order_statistics() {
+        let hundred: Vec<f64> = (1..=100).map(f64::from).collect();
+        // Type 7: position 0.95 * 99 = 94.05 from zero, between 95 and 96
+        assert!((quantile(&hundred, 0.95) - 95.05).abs() < 1e-12);
+        assert!((quantile(&hundred, 0.01) - 1.99).abs() < 1e-12);
+        assert_eq!(quantile(&hundred, 0.0), 1.0);
+        assert_eq!(quantile(&hundred, 1.0), 100.0);
+        assert_eq!(quantile(&hundred, 1.5), 100.0);
+        assert_eq!(quantile(&[7.0], 0.3), 7.0);
+        assert_eq!(quantile(&[], 0.5), 0.0);
+
+        let decimals: Vec<Decimal> = (1..=100).map(Decimal::from).collect();
+        // The weight passes through f64
+        assert_eq!(quantile_decimal(&decimals, 0.95).round_dp(12), dec!(95.05));
+        assert_eq!(quantile_decimal(&[], 0.5), Decimal::ZERO);
+    }
+
+    proptest! {
+        #[test]
+        fn quantiles_of_an_even_grid_are_exact(n in 2usize..500, p in 0.0f64..=1.0) {
+            let grid: Vec<f64> = (0..n).map(|i| i as f64 / (n - 1) as f64).collect();
+            prop_assert!((quantile(&grid, p) - p).abs() < 1e-12);
+        }
+
+        #[test]
+        fn quantiles_of_a_uniform_sample_approach_p(seed in any::<u64>(), p in 0.0f64..=1.0, q in 0.0f64..=1.0) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut sample: Vec<f64> = (0..10_000).map(|_| rng.gen::<f64>()).collect();
+            sample.sort_by(f64::total_cmp);
+            // Dvoretzky–Kiefer–Wolfowitz: off by 0.03 with probability under 1e-7
+            prop_assert!((quantile(&sample, p) - p).abs() < 0.03);
+            let (low, high) = if p <= q { (p, q) } else { (q, p) };
+            prop_assert!(quantile(&sample, low) <= quantile(&sample, high));
+        }
+    }
+
+    fn moments_of(values: impl IntoIterator<Item = f64>) -> Moments {
+        let mut moments = Moments::new();
+        for value in values {
+            moments.push(value);
+        }
+        moments
+    }
+
+    #[test]
+    fn moments_match_the_spreadsheet_estimators() {
+        // pandas: skew() 1.697056, kurt() 3.152
+        let moments = moments_of([1.0, 2.0, 3.0, 4.0, 10.0]);
+        assert_eq!(moments.count(), 5);
+        assert!((moments.mean() - 4.0).abs() < 1e-12);
+        assert!((moments.variance() - 12.5).abs() < 1e-12);
+        assert!((moments.skewness() - 1.697_056_274_847_714).abs() < 1e-9);
+        assert!((moments.excess_kurtosis() - 3.152).abs() < 1e-9);
+
+        assert_eq!(moments_of([1.0, 2.0]).skewness(), 0.0);
+        assert_eq!(moments_of([1.0, 2.0, 3.0]).excess_kurtosis(), 0.0);
+        assert_eq!(moments_of([5.0; 10]).skewness(), 0.0);
+    }
+
+    #[test]
+    fn normal_and_lognormal_samples_have_their_known_moments() {
+        let mut rng = StdRng::seed_from_u64(17);
+        // Offset far from zero: raw power sums would cancel catastrophically here
+        let normal = Normal::new(1e9, 2.0).unwrap();
+        let moments = moments_of((0..200_000).map(|_| normal.sample(&mut rng)));
+        assert!((moments.variance() - 4.0).abs() < 0.05);
+        assert!(moments.skewness().abs() < 0.03);
+        assert!(moments.excess_kurtosis().abs() < 0.06);
+
+        // sigma = 0.5: skewness (e^s2 + 2) sqrt(e^s2 - 1), excess kurtosis e^4s2 + 2e^3s2 + 3e^2s2 - 6
+        let s2: f64 = 0.25;
+        let skewness = (s2.exp() + 2.0) * (s2.exp() - 1.0).sqrt();
+        let kurtosis = (4.0 * s2).exp() + 2.0 * (3.0 * s2).exp() + 3.0 * (2.0 * s2).exp() - 6.0;
+        let lognormal = LogNormal::new(0.0, 0.5).unwrap();
+        let moments = moments_of((0..200_000).map(|_| lognormal.sample(&mut rng)));
+        assert!((moments.skewness() - skewness).abs() < 0.1, "skewness {} vs {}", moments.skewness(), skewness);
+        assert!((moments.excess_kurtosis() - kurtosis).abs() < 1.0);
+    }
+
+    /// Share of `sorted` strictly below `value`, averaged with the share at or below it
+    fn rank(sorted: &[f64], value: f64) -> f64 {
+        let below = sorted.partition_point(|x| *x < value);
+        let at_or_below = sorted.partition_point(|x| *x <= value);
+        (below + at_or_below) as f64 / 2.0 / (sorted.len() - 1) as f64
+    }
+
+    /// Mean of the values at or below the exact type-7 `p` quantile
+    fn exact_tail_mean(sorted: &[f64], p: f64) -> f64 {
+        let cutoff = quantile(sorted, p);
+        let tail: Vec<f64> = sorted.iter().copied().take_while(|value| *value <= cutoff).collect();
+        tail.iter().sum::<f64>() / tail.len() as f64
+    }
+
+    #[test]
+    fn sketch_stays_within_its_documented_tolerance_on_100k_samples() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let normal = Normal::new(0.0, 1.0).unwrap();
+        let lognormal = LogNormal::new(0.0, 0.5).unwrap();
+        let student_t = StudentT::new(3.0).unwrap();
+        let samples: [(&str, Vec<f64>); 3] = [
+            ("normal", (0..100_000).map(|_| normal.sample(&mut rng)).collect()),
+            ("lognormal", (0..100_000).map(|_| lognormal.sample(&mut rng)).collect()),
+            ("t(3)", (0..100_000).map(|_| student_t.sample(&mut rng)).collect()),
+        ];
+
+        for (name, values) in samples {
+            let mut sketch = TDigest::default();
+            for &value in &values {
+                sketch.push(value);
+            }
+            sketch.flush();
+            let mut sorted = values;
+            sorted.sort_by(f64::total_cmp);
+
+            for p in [0.01, 0.05, 0.25, 0.50, 0.75, 0.95, 0.99] {
+                let error = (rank(&sorted, sketch.quantile(p)) - p).abs();
+                assert!(error < 0.0005, "{} p{}: rank error {}", name, p * 100.0, error);
+            }
+            let iqr = quantile(&sorted, 0.75) - quantile(&sorted, 0.25);
+            let tail_error = (sketch.tail_mean(0.05) - exact_tail_mean(&sorted, 0.05)).abs() / iqr;
+            assert!(tail_error < 0.001, "{}: tail mean off by {} of the IQR", name, tail_error);
+            assert_eq!(sketch.count(), 100_000);
+            assert_eq!((sketch.min(), sketch.max()), (sorted.first().copied(), sorted.last().copied()));
+        }
+    }
+
+    #[test]
+    fn sketch_of_a_few_values_is_exact() {
+        // Fewer values than the buffer holds, pushed out of order and never flushed
+        let mut rng = StdRng::seed_from_u64(5);
+        let values: Vec<f64> = (0..101).map(|_| rng.gen_range(-50.0..50.0)).collect();
+        let mut sketch = TDigest::default();
+        for &value in &values {
+            sketch.push(value);
+        }
+        sketch.push(f64::NAN);
+        let mut sorted = values;
+        sorted.sort_by(f64::total_cmp);
+
+        assert_eq!(sketch.count(), 101);
+        for p in (0..=100).map(|i| i as f64 / 100.0).chain([0.013, 0.333, 0.999]) {
+            assert!((sketch.quantile(p) - quantile(&sorted, p)).abs() < 1e-9, "p {}", p);
+            assert!((sketch.tail_mean(p) - exact_tail_mean(&sorted, p)).abs() < 1e-9, "p {}", p);
+        }
+        // Each value sits at the middle of its own unit of weight
+        for (i, &value) in sorted.iter().enumerate().skip(1).take(99) {
+            assert!((sketch.cdf(value) - (i as f64 + 0.5) / 101.0).abs() < 1e-12, "value {}", i);
+        }
+        let midpoint = (sorted[10] + sorted[11]) / 2.0;
+        assert!((sketch.cdf(midpoint) - 11.0 / 101.0).abs() < 1e-12);
+        assert_eq!(sketch.cdf(sorted[0] - 1.0), 0.0);
+        assert_eq!(sketch.cdf(sorted[100]), 1.0);
+
+        let empty = TDigest::default();
+        assert_eq!((empty.quantile(0.5), empty.tail_mean(0.5), empty.cdf(0.0)), (0.0, 0.0, 0.0));
+        assert_eq!(empty.min(), None);
+    }
+}
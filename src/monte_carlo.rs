@@ -1,26 +1,718 @@
+use crate::fees::FlatBps;
+use crate::market::{describe_assets_async, AsyncMarketDataProvider};
+use crate::metrics::{quantile, Moments, TDigest};
 use crate::types::*;
-use crate::simulator::Simulator;
-use crate::strategy::Strategy;
-use anyhow::Result;
+use crate::bootstrap::BlockBootstrap;
+use crate::scenarios::{RegimeModel, Scenario, ScenarioDistribution};
+use crate::shocks::{JumpConfig, ShockDistribution};
+use crate::simulator::{CircuitBreaker, Simulator, SimulatorConfig};
+use crate::sobol::{QuasiRandomDraws, SobolSequence};
+use crate::strategy::{RoutingStrategy, Strategy};
+use crate::stress::{ScenarioDefinition, StressLibrary};
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use time::Duration;
 use tracing::info;
 
-/// Monte Carlo engine for stress testing strategies
+/// Iterations between progress log lines
+const PROGRESS_INTERVAL: usize = 1000;
+
+/// Mixed into the master seed for scenario generation and assignment, so
+/// those draws are independent of the paths' price walks
+const SCENARIO_SALT: u64 = 0x5CE7_A210_5CE7_A210;
+
+/// Mixed into the master seed for bootstrap block draws
+const BOOTSTRAP_SALT: u64 = 0xB007_57A4_B007_57A4;
+
+/// Mixed into the master seed for the Sobol sequence's digital shift
+const SOBOL_SALT: u64 = 0x50B0_150B_0150_B015;
+
+/// Sobol dimensions reserved per step; paths drawing more shocks per step
+/// than this run out of coordinates early and fall back to the RNG
+const SOBOL_SYMBOLS_PER_STEP: usize = 16;
+
+/// Percentiles of the final value reported in `MonteCarloResults::percentiles`
+const REPORTED_PERCENTILES: [u8; 7] = [1, 5, 25, 50, 75, 95, 99];
+
+/// Paths per worker thread a `PathStream` runs at a time
+const PATHS_PER_THREAD: usize = 8;
+
+/// Confidence level `MonteCarloEngine::run` uses unless configured otherwise
+const DEFAULT_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Receives `(completed, total)` path counts during a run
+pub type ProgressCallback = Box<dyn FnMut(usize, usize) + Send>;
+
+/// Default ruin level, in percent of initial capital
+pub const DEFAULT_RUIN_THRESHOLD_PCT: f64 = 50.0;
+
+/// Edges of the crisis-share buckets in `MonteCarloResults::crisis_exposure`;
+/// the last bucket includes paths spent entirely in crisis
+const CRISIS_SHARE_EDGES: [f64; 5] = [0.0, 0.1, 0.25, 0.5, 1.0];
+
+/// Level Monte Carlo VaR and CVaR losses are measured from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VarReference {
+    /// Loss relative to the capital each path started with
+    #[default]
+    InitialCapital,
+    /// Loss relative to the mean final value (the "relative" VaR)
+    ExpectedValue,
+}
+
+/// What produced a Monte Carlo run's price paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScenarioSource {
+    /// The simulator's parametric price walk (GBM plus configured jumps, regimes and scenarios)
+    #[default]
+    Parametric,
+    /// Block-bootstrapped historical returns
+    Bootstrap,
+    /// Price paths supplied by the caller
+    Supplied,
+}
+
+/// How paths' random draws are chosen to reduce the variance of estimates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VarianceReduction {
+    /// Every path draws independently
+    #[default]
+    None,
+    /// Paths run in pairs from one seed, the second with every diffusion shock
+    /// negated. For near-linear payoffs the pair's errors largely cancel.
+    /// Bootstrap shock distributions are mirrored too, which flips their skew
+    Antithetic,
+}
+
+/// Where paths' normal price shocks come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SamplingMode {
+    #[default]
+    PseudoRandom,
+    /// Path `i` takes its shocks from point `i` of a digitally shifted Sobol
+    /// sequence, one coordinate per shock (steps × assets dimensions). Converges
+    /// faster than pseudo-random sampling for smooth payoffs
+    Sobol,
+}
+
+/// Engine parameter a sensitivity sweep varies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SweepParameter {
+    /// Share of cash the strategy deploys on each allocation, in (0, 1]
+    AllocationFraction,
+    /// Multiplier on every asset's volatility, applied on top of any scenario's
+    VolatilityMultiplier,
+    /// Flat execution fee in basis points, replacing the strategy's quoted costs
+    FeeBps,
+}
+
+impl SweepParameter {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "allocation_fraction" | "allocation" => Ok(Self::AllocationFraction),
+            "volatility_multiplier" | "volatility" | "vol" => Ok(Self::VolatilityMultiplier),
+            "fee_bps" | "fee" => Ok(Self::FeeBps),
+            _ => Err(anyhow::anyhow!("Unknown sweep parameter: {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AllocationFraction => "allocation_fraction",
+            Self::VolatilityMultiplier => "volatility_multiplier",
+            Self::FeeBps => "fee_bps",
+        }
+    }
+}
+
+/// A parameter and the evenly spaced values a sensitivity sweep evaluates it at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SweepSpec {
+    pub parameter: SweepParameter,
+    pub start: f64,
+    pub end: f64,
+    /// Number of values from `start` to `end` inclusive; 1 evaluates `start` alone
+    pub steps: usize,
+}
+
+impl SweepSpec {
+    pub fn new(parameter: SweepParameter, start: f64, end: f64, steps: usize) -> Self {
+        Self { parameter, start, end, steps }
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        if self.steps == 1 {
+            return vec![self.start];
+        }
+        let increment = (self.end - self.start) / (self.steps - 1) as f64;
+        (0..self.steps)
+            .map(|i| if i + 1 == self.steps { self.end } else { self.start + increment * i as f64 })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.steps == 0 {
+            return Err(anyhow::anyhow!("Sweep needs at least one step"));
+        }
+        if !self.start.is_finite() || !self.end.is_finite() {
+            return Err(anyhow::anyhow!("Sweep range must be finite"));
+        }
+        let (low, high) = (self.start.min(self.end), self.start.max(self.end));
+        let valid = match self.parameter {
+            SweepParameter::AllocationFraction => low > 0.0 && high <= 1.0,
+            SweepParameter::VolatilityMultiplier | SweepParameter::FeeBps => low >= 0.0,
+        };
+        if !valid {
+            return Err(anyhow::anyhow!(
+                "Sweep range {}..{} is out of bounds for {}",
+                self.start,
+                self.end,
+                self.parameter.name()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Target and floor values for goal attainment statistics
+#[derive(Debug, Clone, Copy, Default)]
+struct Goal {
+    target_value: Option<f64>,
+    floor_value: Option<f64>,
+}
+
+impl Goal {
+    fn validate(&self) -> Result<()> {
+        for value in [self.target_value, self.floor_value].into_iter().flatten() {
+            if !value.is_finite() || value < 0.0 {
+                return Err(anyhow::anyhow!("Goal values must be non-negative, got {}", value));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a batch of paths was produced, for `MonteCarloEngine::aggregate`
+struct RunShape {
+    source: ScenarioSource,
+    /// Paths asked for; fewer complete when the run is cancelled
+    requested: usize,
+    steps_per_iteration: usize,
+    variance_reduction: VarianceReduction,
+    sampling: SamplingMode,
+}
+
+/// Monte Carlo engine for stress testing strategies.
+///
+/// Paths run in parallel on the rayon thread pool. Each path's simulator is
+/// seeded from the master seed and the path index alone, so a given seed
+/// produces the same distribution whatever the thread count or scheduling.
+///
+/// When `scenarios` is non-zero, that many market scenarios (drift shift,
+/// volatility multiplier, correlation regime, optional shock) are sampled up
+/// front and every path runs under one of them, drawn at random.
+///
+/// Configure one with [`MonteCarloEngine::builder`]:
+///
+/// ```rust,no_run
+/// use vaulta_simulator::monte_carlo::{MonteCarloEngine, VarianceReduction};
+/// use vaulta_simulator::Strategy;
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let mut engine = MonteCarloEngine::builder()
+///     .iterations(50_000)
+///     .scenarios(200)
+///     .strategy(Strategy::risk_parity())
+///     .capital(5_000_000.0)
+///     .steps(365)
+///     .horizons(vec![30, 90, 365])
+///     .variance_reduction(VarianceReduction::Antithetic)
+///     .confidence(0.99)
+///     .seed(7)
+///     .build()?;
+///
+/// let results = engine.run().await?;
+/// println!("99% VaR: {:.2}", results.value_at_risk);
+/// # Ok(())
+/// # }
+/// ```
 pub struct MonteCarloEngine {
     iterations: usize,
     scenarios: usize,
-    rng: rand::rngs::ThreadRng,
+    scenario_distribution: ScenarioDistribution,
+    variance_reduction: VarianceReduction,
+    sampling: SamplingMode,
+    seed: u64,
+    strategy: Strategy,
+    initial_capital: f64,
+    steps_per_iteration: usize,
+    drawdown_threshold_pct: Option<f64>,
+    ruin_threshold_pct: f64,
+    var_reference: VarReference,
+    goal: Goal,
+    /// Steps at which every path's value is checkpointed, ascending
+    horizons: Vec<usize>,
+    /// Resample historical returns instead of running the parametric walk
+    bootstrap: Option<Arc<BlockBootstrap>>,
+    /// Keep every path's full `SimulationResults`
+    detailed: bool,
+    /// Aggregate with streaming estimators instead of retaining per-path values
+    memory_light: bool,
+    /// Confidence level `run` reports VaR and CVaR at
+    confidence_level: f64,
+    stress_library: StressLibrary,
+    /// Invoked every `progress_interval` completed paths and once at the end
+    progress: Option<Mutex<ProgressCallback>>,
+    progress_interval: usize,
+    cancel: Option<Arc<AtomicBool>>,
+    simulator_config: SimulatorConfig,
 }
 
-impl MonteCarloEngine {
-    /// Create a new Monte Carlo engine
-    pub fn new(iterations: usize, scenarios: usize) -> Self {
+/// Builder for [`MonteCarloEngine`]; `build` validates the configuration as a whole
+pub struct MonteCarloEngineBuilder {
+    engine: MonteCarloEngine,
+}
+
+impl Default for MonteCarloEngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MonteCarloEngineBuilder {
+    /// Defaults: 10,000 paths over 100 scenarios, a random seed, and the
+    /// balanced strategy for 100 daily steps from $1M at 95% confidence
+    pub fn new() -> Self {
         Self {
-            iterations,
-            scenarios,
-            rng: rand::thread_rng(),
+            engine: MonteCarloEngine {
+                iterations: 10_000,
+                scenarios: 100,
+                scenario_distribution: ScenarioDistribution::default(),
+                variance_reduction: VarianceReduction::default(),
+                sampling: SamplingMode::default(),
+                seed: rand::random(),
+                strategy: Strategy::balanced(),
+                initial_capital: 1_000_000.0,
+                steps_per_iteration: 100,
+                drawdown_threshold_pct: None,
+                ruin_threshold_pct: DEFAULT_RUIN_THRESHOLD_PCT,
+                var_reference: VarReference::default(),
+                goal: Goal::default(),
+                horizons: vec![],
+                bootstrap: None,
+                detailed: false,
+                memory_light: false,
+                confidence_level: DEFAULT_CONFIDENCE_LEVEL,
+                stress_library: StressLibrary::builtin(),
+                progress: None,
+                progress_interval: PROGRESS_INTERVAL,
+                cancel: None,
+                simulator_config: SimulatorConfig::default(),
+            },
+        }
+    }
+
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.engine.iterations = iterations;
+        self
+    }
+
+    /// Market scenarios to sample up front; 0 runs every path under the base configuration
+    pub fn scenarios(mut self, scenarios: usize) -> Self {
+        self.engine.scenarios = scenarios;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.engine.seed = seed;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.engine.strategy = strategy;
+        self
+    }
+
+    /// Capital each path starts from
+    pub fn capital(mut self, initial_capital: f64) -> Self {
+        self.engine.initial_capital = initial_capital;
+        self
+    }
+
+    pub fn steps(mut self, steps_per_iteration: usize) -> Self {
+        self.engine.steps_per_iteration = steps_per_iteration;
+        self
+    }
+
+    /// Confidence level `MonteCarloEngine::run` reports VaR and CVaR at
+    pub fn confidence(mut self, confidence_level: f64) -> Self {
+        self.engine.confidence_level = confidence_level;
+        self
+    }
+
+    pub fn time_step(mut self, time_step: Duration) -> Self {
+        self.engine = self.engine.with_time_step(time_step);
+        self
+    }
+
+    pub fn scenario_distribution(mut self, distribution: ScenarioDistribution) -> Self {
+        self.engine = self.engine.with_scenario_distribution(distribution);
+        self
+    }
+
+    pub fn shock_distribution(mut self, distribution: ShockDistribution) -> Self {
+        self.engine = self.engine.with_shock_distribution(distribution);
+        self
+    }
+
+    pub fn variance_reduction(mut self, variance_reduction: VarianceReduction) -> Self {
+        self.engine = self.engine.with_variance_reduction(variance_reduction);
+        self
+    }
+
+    pub fn sampling(mut self, sampling: SamplingMode) -> Self {
+        self.engine = self.engine.with_sampling(sampling);
+        self
+    }
+
+    pub fn jumps(mut self, jumps: JumpConfig) -> Self {
+        self.engine = self.engine.with_jumps(jumps);
+        self
+    }
+
+    pub fn regimes(mut self, regimes: RegimeModel) -> Self {
+        self.engine = self.engine.with_regimes(regimes);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.engine = self.engine.with_circuit_breaker(circuit_breaker);
+        self
+    }
+
+    pub fn universe(mut self, assets: Vec<Asset>) -> Self {
+        self.engine = self.engine.with_universe(assets);
+        self
+    }
+
+    pub fn correlations(mut self, correlations: HashMap<(String, String), f64>) -> Self {
+        self.engine = self.engine.with_correlations(correlations);
+        self
+    }
+
+    pub fn bootstrap(mut self, bootstrap: BlockBootstrap) -> Self {
+        self.engine = self.engine.with_bootstrap(bootstrap);
+        self
+    }
+
+    pub fn stress_scenarios(mut self, library: StressLibrary) -> Self {
+        self.engine = self.engine.with_stress_scenarios(library);
+        self
+    }
+
+    /// Checkpoint steps, ascending and within `steps`
+    pub fn horizons(mut self, horizons: Vec<usize>) -> Self {
+        self.engine.horizons = horizons;
+        self
+    }
+
+    pub fn drawdown_threshold(mut self, threshold_pct: f64) -> Self {
+        self.engine = self.engine.with_drawdown_threshold(threshold_pct);
+        self
+    }
+
+    pub fn ruin_threshold(mut self, threshold_pct: f64) -> Self {
+        self.engine = self.engine.with_ruin_threshold(threshold_pct);
+        self
+    }
+
+    pub fn target(mut self, target_value: f64) -> Self {
+        self.engine = self.engine.with_target(target_value);
+        self
+    }
+
+    pub fn floor(mut self, floor_value: f64) -> Self {
+        self.engine = self.engine.with_floor(floor_value);
+        self
+    }
+
+    pub fn var_reference(mut self, reference: VarReference) -> Self {
+        self.engine = self.engine.with_var_reference(reference);
+        self
+    }
+
+    pub fn detailed_results(mut self) -> Self {
+        self.engine = self.engine.with_detailed_results();
+        self
+    }
+
+    pub fn memory_light(mut self) -> Self {
+        self.engine = self.engine.with_memory_light();
+        self
+    }
+
+    /// See [`MonteCarloEngine::with_progress`]
+    pub fn progress<F>(mut self, interval: usize, callback: F) -> Self
+    where
+        F: FnMut(usize, usize) + Send + 'static,
+    {
+        self.engine = self.engine.with_progress(interval, callback);
+        self
+    }
+
+    pub fn cancellation(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.engine = self.engine.with_cancellation(cancel);
+        self
+    }
+
+    pub fn build(self) -> Result<MonteCarloEngine> {
+        let engine = self.engine;
+        if engine.iterations == 0 {
+            return Err(anyhow::anyhow!("Monte Carlo needs at least one iteration"));
+        }
+        if !(engine.confidence_level > 0.0 && engine.confidence_level < 1.0) {
+            return Err(anyhow::anyhow!(
+                "Confidence level must be between 0 and 1, got {}",
+                engine.confidence_level
+            ));
+        }
+        if engine.steps_per_iteration == 0 {
+            return Err(anyhow::anyhow!("Paths need at least one step"));
         }
+        if engine.horizons.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(anyhow::anyhow!("Horizons must be strictly ascending, got {:?}", engine.horizons));
+        }
+        if let Some(&horizon) = engine.horizons.last().filter(|&&h| h > engine.steps_per_iteration) {
+            return Err(anyhow::anyhow!(
+                "Horizon {} is past the {} steps each path runs",
+                horizon,
+                engine.steps_per_iteration
+            ));
+        }
+        engine.validate()?;
+        Ok(engine)
+    }
+}
+
+impl MonteCarloEngine {
+    /// Create a new Monte Carlo engine with a random master seed, without
+    /// validating the configuration; prefer [`MonteCarloEngine::builder`].
+    ///
+    /// Each path runs the balanced strategy for 100 daily steps from $1M unless
+    /// configured otherwise.
+    pub fn new(iterations: usize, scenarios: usize) -> Self {
+        Self::builder().iterations(iterations).scenarios(scenarios).engine
+    }
+
+    pub fn builder() -> MonteCarloEngineBuilder {
+        MonteCarloEngineBuilder::new()
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_initial_capital(mut self, initial_capital: f64) -> Self {
+        self.initial_capital = initial_capital;
+        self
+    }
+
+    pub fn with_steps(mut self, steps_per_iteration: usize) -> Self {
+        self.steps_per_iteration = steps_per_iteration;
+        self
+    }
+
+    /// Distributions the engine's scenarios are sampled from
+    pub fn with_scenario_distribution(mut self, distribution: ScenarioDistribution) -> Self {
+        self.scenario_distribution = distribution;
+        self
+    }
+
+    /// Simulated time covered by one step of each path
+    pub fn with_time_step(mut self, time_step: Duration) -> Self {
+        self.simulator_config.time_step = time_step;
+        self
+    }
+
+    /// Report the probability that a path's drawdown exceeds `threshold_pct`
+    pub fn with_drawdown_threshold(mut self, threshold_pct: f64) -> Self {
+        self.drawdown_threshold_pct = Some(threshold_pct);
+        self
+    }
+
+    /// Count a path as ruined if its value ever falls below `threshold_pct`
+    /// percent of the initial capital (default 50)
+    pub fn with_ruin_threshold(mut self, threshold_pct: f64) -> Self {
+        self.ruin_threshold_pct = threshold_pct;
+        self
+    }
+
+    /// Also report statistics at each of these steps, from the same paths as
+    /// the end-of-run numbers
+    pub fn with_horizons(mut self, mut horizons: Vec<usize>) -> Self {
+        horizons.sort_unstable();
+        horizons.dedup();
+        self.horizons = horizons;
+        self
+    }
+
+    /// Report the probability of reaching `target_value` within the run, and
+    /// how many steps paths took to first reach it
+    pub fn with_target(mut self, target_value: f64) -> Self {
+        self.goal.target_value = Some(target_value);
+        self
+    }
+
+    /// Report the probability of a path's value falling below `floor_value` at any step
+    pub fn with_floor(mut self, floor_value: f64) -> Self {
+        self.goal.floor_value = Some(floor_value);
+        self
+    }
+
+    /// Build every path by block-bootstrapping historical returns rather than
+    /// from the parametric price walk. Scenarios, sampling and variance
+    /// reduction settings don't apply to bootstrapped paths
+    pub fn with_bootstrap(mut self, bootstrap: BlockBootstrap) -> Self {
+        self.bootstrap = Some(Arc::new(bootstrap));
+        self
+    }
+
+    /// Measure VaR and CVaR losses from `reference` (default: initial capital)
+    pub fn with_var_reference(mut self, reference: VarReference) -> Self {
+        self.var_reference = reference;
+        self
+    }
+
+    /// Keep each path's full `SimulationResults` in `MonteCarloResults::path_results`.
+    /// Every path's history is then held in memory until the run ends
+    pub fn with_detailed_results(mut self) -> Self {
+        self.detailed = true;
+        self
+    }
+
+    /// Aggregate in memory independent of the iteration count: moments and
+    /// counts are accumulated as paths complete, and VaR, CVaR and percentiles
+    /// are estimated from t-digest sketches (see `TDigest` for the accuracy).
+    /// Results then carry no `distribution` or per-path `paths`
+    pub fn with_memory_light(mut self) -> Self {
+        self.memory_light = true;
+        self
+    }
+
+    /// Call `callback(completed, total)` every `interval` completed paths and
+    /// when the run finishes. Paths finish on many threads, so calls are
+    /// serialized but `completed` may skip values between them.
+    pub fn with_progress<F>(mut self, interval: usize, callback: F) -> Self
+    where
+        F: FnMut(usize, usize) + Send + 'static,
+    {
+        self.progress = Some(Mutex::new(Box::new(callback)));
+        self.progress_interval = interval.max(1);
+        self
+    }
+
+    /// Stop starting new paths once `cancel` is set. The run then returns the
+    /// paths completed so far, with `MonteCarloResults::incomplete` set.
+    pub fn with_cancellation(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Add named stress scenarios alongside the built-in ones, replacing built-ins of the same name
+    pub fn with_stress_scenarios(mut self, library: StressLibrary) -> Self {
+        self.stress_library.extend(library);
+        self
+    }
+
+    /// Named stress scenarios `run_named_scenarios` can run
+    pub fn stress_library(&self) -> &StressLibrary {
+        &self.stress_library
+    }
+
+    /// Use a fixed master seed so runs are reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Shape of every asset's per-step price shock on each path; fat-tailed
+    /// choices widen VaR and CVaR relative to the normal default
+    pub fn with_shock_distribution(mut self, distribution: ShockDistribution) -> Self {
+        self.simulator_config.shock_distribution = distribution;
+        self
+    }
+
+    pub fn with_variance_reduction(mut self, variance_reduction: VarianceReduction) -> Self {
+        self.variance_reduction = variance_reduction;
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: SamplingMode) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Sudden price jumps on every path, on top of the diffusion
+    pub fn with_jumps(mut self, jumps: JumpConfig) -> Self {
+        self.simulator_config.jumps = jumps;
+        self
+    }
+
+    /// Switch every path between market regimes, e.g. [`RegimeModel::calm_crisis`]
+    pub fn with_regimes(mut self, regimes: RegimeModel) -> Self {
+        self.simulator_config.regimes = Some(regimes);
+        self
+    }
+
+    /// Halt each path's buying once its drawdown exceeds the breaker's threshold
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.simulator_config.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Price and evolve `assets` on every path from the first step
+    pub fn with_universe(mut self, assets: Vec<Asset>) -> Self {
+        self.simulator_config.universe = assets;
+        self
+    }
+
+    /// Price and evolve `symbols` as `provider` describes them, fetched once up
+    /// front so the paths themselves never wait on it. Sync providers plug in
+    /// through [`SyncAdapter`](crate::market::SyncAdapter).
+    pub async fn with_universe_from_provider<P>(self, provider: &P, symbols: &[&str]) -> Result<Self>
+    where
+        P: AsyncMarketDataProvider + ?Sized,
+    {
+        let assets = describe_assets_async(provider, symbols).await.context("Failed to describe the universe")?;
+        Ok(self.with_universe(assets))
+    }
+
+    /// Correlate every path's price shocks pairwise; missing pairs are uncorrelated
+    pub fn with_correlations(mut self, correlations: HashMap<(String, String), f64>) -> Self {
+        self.simulator_config.correlations = correlations;
+        self
+    }
+
+    /// Run the stress test at the configured confidence level
+    pub async fn run(&mut self) -> Result<MonteCarloResults> {
+        self.run_stress_test(self.confidence_level).await
+    }
+
+    pub fn confidence_level(&self) -> f64 {
+        self.confidence_level
     }
 
     /// Run Monte Carlo stress test
@@ -28,144 +720,2170 @@ impl MonteCarloEngine {
         &mut self,
         confidence_level: f64,
     ) -> Result<MonteCarloResults> {
-        info!("Starting Monte Carlo simulation with {} iterations", self.iterations);
-        
-        let mut final_values = Vec::with_capacity(self.iterations);
-        
-        // Run simulations in parallel batches
-        let batch_size = 100;
-        let batches = (self.iterations + batch_size - 1) / batch_size;
-        
-        for batch in 0..batches {
-            let start = batch * batch_size;
-            let end = (start + batch_size).min(self.iterations);
-            
-            let batch_results: Vec<f64> = (start..end)
-                .map(|i| {
-                    let result = self.run_single_simulation(i);
-                    result.unwrap_or(0.0)
-                })
-                .collect();
-            
-            final_values.extend(batch_results);
-            
-            if (batch + 1) % 10 == 0 {
-                info!("Completed {}/{} batches", batch + 1, batches);
-            }
-        }
-        
-        // Calculate statistics
-        let expected_value = self.calculate_expected_value(&final_values);
-        let value_at_risk = self.calculate_var(&final_values, confidence_level);
-        let conditional_var = self.calculate_cvar(&final_values, confidence_level);
-        let max_drawdown = self.calculate_max_drawdown_distribution(&final_values);
-        
-        // Calculate percentiles
-        let mut sorted = final_values.clone();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let percentiles = HashMap::from([
-            (5, self.percentile(&sorted, 0.05)),
-            (25, self.percentile(&sorted, 0.25)),
-            (50, self.percentile(&sorted, 0.50)),
-            (75, self.percentile(&sorted, 0.75)),
-            (95, self.percentile(&sorted, 0.95)),
-        ]);
-        
-        Ok(MonteCarloResults {
-            iterations: self.iterations,
-            expected_value,
-            value_at_risk,
-            conditional_var,
-            max_drawdown_pct: max_drawdown,
-            confidence_level,
-            distribution: final_values,
-            percentiles,
-        })
+        Ok(self.stream_paths()?.into_results(confidence_level))
     }
 
-    /// Run a single simulation iteration
-    fn run_single_simulation(&mut self, _seed: usize) -> Result<f64> {
-        let initial_capital = 1_000_000.0;
-        let strategy = Strategy::balanced();
-        let mut simulator = Simulator::new(initial_capital, strategy);
-        
-        // Run simulation for 100 steps
-        for _ in 0..100 {
-            simulator.step()?;
+    /// Evaluate the strategy against externally supplied price paths, e.g.
+    /// historical stress episodes, instead of generated ones.
+    ///
+    /// Each path runs to its own end through `Simulator::step_with_prices`;
+    /// symbols whose series has ended or is absent keep their last price.
+    /// Statistics are computed exactly as for `run_stress_test`, with one
+    /// iteration per path.
+    pub async fn run_with_paths(
+        &mut self,
+        paths: Vec<PricePath>,
+        confidence_level: f64,
+    ) -> Result<MonteCarloResults> {
+        Ok(self.stream_price_paths(paths)?.into_results(confidence_level))
+    }
+
+    /// The run's paths as they complete, for consumers that don't want to wait
+    /// for the whole run or hold the full distribution
+    pub fn stream_paths(&self) -> Result<PathStream<'_>> {
+        self.validate()?;
+        if let Some(&horizon) = self.horizons.last().filter(|&&h| h > self.steps_per_iteration) {
+            return Err(anyhow::anyhow!(
+                "Horizon {} is past the {} steps each path runs",
+                horizon,
+                self.steps_per_iteration
+            ));
+        }
+        if let Some(bootstrap) = &self.bootstrap {
+            info!(
+                "Starting bootstrap Monte Carlo with {} iterations of {} steps ({} strategy, {} periods in blocks of {})",
+                self.iterations,
+                self.steps_per_iteration,
+                self.strategy.name(),
+                bootstrap.periods(),
+                bootstrap.block_length()
+            );
+            let shape = RunShape {
+                source: ScenarioSource::Bootstrap,
+                requested: self.iterations,
+                steps_per_iteration: self.steps_per_iteration,
+                variance_reduction: VarianceReduction::None,
+                sampling: SamplingMode::PseudoRandom,
+            };
+            return Ok(PathStream::new(self, PathSource::Bootstrap(bootstrap.clone()), shape));
         }
+        self.simulator_config.shock_distribution.validate()?;
+        self.simulator_config.jumps.validate()?;
+        if let Some(regimes) = &self.simulator_config.regimes {
+            regimes.validate()?;
+        }
+        info!(
+            "Starting Monte Carlo simulation with {} iterations of {} steps ({} strategy)",
+            self.iterations,
+            self.steps_per_iteration,
+            self.strategy.name()
+        );
         
-        let results = simulator.finalize();
-        Ok(results.final_value.to_f64().unwrap_or(0.0))
+        let scenarios = self.scenario_distribution.generate(
+            self.scenarios,
+            self.steps_per_iteration,
+            &mut StdRng::seed_from_u64(self.seed ^ SCENARIO_SALT),
+        );
+        
+        let sobol = (self.sampling == SamplingMode::Sobol).then(|| {
+            Arc::new(SobolSequence::new(
+                self.steps_per_iteration.max(1) * SOBOL_SYMBOLS_PER_STEP,
+                self.seed ^ SOBOL_SALT,
+            ))
+        });
+        
+        let shape = RunShape {
+            source: ScenarioSource::Parametric,
+            requested: self.iterations,
+            steps_per_iteration: self.steps_per_iteration,
+            variance_reduction: self.variance_reduction,
+            sampling: self.sampling,
+        };
+        Ok(PathStream::new(self, PathSource::Generated { scenarios, sobol }, shape))
     }
 
-    fn calculate_expected_value(&self, values: &[f64]) -> Decimal {
-        if values.is_empty() {
-            return Decimal::ZERO;
-        }
+    /// [`stream_paths`](Self::stream_paths) over supplied price paths, as run by `run_with_paths`
+    pub fn stream_price_paths(&self, paths: Vec<PricePath>) -> Result<PathStream<'_>> {
+        self.validate()?;
+        info!(
+            "Evaluating {} strategy against {} supplied price paths",
+            self.strategy.name(),
+            paths.len()
+        );
         
-        let sum: f64 = values.iter().sum();
-        let mean = sum / values.len() as f64;
-        Decimal::try_from(mean).unwrap_or(Decimal::ZERO)
+        // Supplied paths are independent draws, whatever the engine's sampling settings
+        let shape = RunShape {
+            source: ScenarioSource::Supplied,
+            requested: paths.len(),
+            steps_per_iteration: paths.iter().map(PricePath::steps).max().unwrap_or(0),
+            variance_reduction: VarianceReduction::None,
+            sampling: SamplingMode::PseudoRandom,
+        };
+        Ok(PathStream::new(self, PathSource::Supplied(paths), shape))
     }
 
-    fn calculate_var(&self, values: &[f64], confidence: f64) -> Decimal {
-        if values.is_empty() {
-            return Decimal::ZERO;
+    /// Run every strategy over the same seeded paths and compare them.
+    ///
+    /// Paths depend only on the seed and their index, so each strategy sees
+    /// identical scenarios and price shocks (common random numbers): the
+    /// differences reported come from the strategies, not sampling noise.
+    pub async fn compare(
+        &mut self,
+        strategies: Vec<Strategy>,
+        confidence_level: f64,
+    ) -> Result<ComparisonResults> {
+        if strategies.len() < 2 {
+            return Err(anyhow::anyhow!("Comparison needs at least two strategies"));
         }
+        if self.memory_light {
+            return Err(anyhow::anyhow!("Comparison matches paths across runs; disable memory-light mode"));
+        }
+        let original = self.strategy.clone();
+        let mut results = Vec::with_capacity(strategies.len());
+        for strategy in strategies {
+            self.strategy = strategy;
+            let run = self.run_stress_test(confidence_level).await;
+            match run {
+                Ok(run) => results.push(run),
+                Err(e) => {
+                    self.strategy = original;
+                    return Err(e);
+                }
+            }
+        }
+        self.strategy = original;
         
-        let mut sorted = values.to_vec();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let index = ((1.0 - confidence) * sorted.len() as f64) as usize;
-        let var_value = sorted.get(index).copied().unwrap_or(0.0);
-        
-        Decimal::try_from(var_value).unwrap_or(Decimal::ZERO)
+        let mut pairwise = vec![];
+        for a in &results {
+            for b in &results {
+                if !std::ptr::eq(a, b) {
+                    pairwise.push(Self::head_to_head(a, b));
+                }
+            }
+        }
+        Ok(ComparisonResults { results, pairwise })
     }
 
-    fn calculate_cvar(&self, values: &[f64], confidence: f64) -> Decimal {
-        if values.is_empty() {
-            return Decimal::ZERO;
+    /// Run the stress test once per value of `spec`'s parameter.
+    ///
+    /// Every run uses the engine's seed, so each value sees the same scenarios
+    /// and price shocks and differences between points come from the parameter
+    /// alone. The engine's own configuration is restored afterwards.
+    pub async fn sensitivity(&mut self, spec: SweepSpec, confidence_level: f64) -> Result<SensitivityResults> {
+        spec.validate()?;
+        if spec.parameter == SweepParameter::AllocationFraction && self.strategy.allocation_fraction().is_none() {
+            return Err(anyhow::anyhow!(
+                "Strategy {} has no allocation fraction to sweep",
+                self.strategy.name()
+            ));
         }
-        
-        let mut sorted = values.to_vec();
-        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let var_index = ((1.0 - confidence) * sorted.len() as f64) as usize;
-        let tail_values: Vec<f64> = sorted[..var_index].to_vec();
-        
-        if tail_values.is_empty() {
-            return Decimal::ZERO;
+        let original_strategy = self.strategy.clone();
+        let original_config = self.simulator_config.clone();
+        let mut points = Vec::with_capacity(spec.steps);
+        let mut outcome = Ok(());
+        for value in spec.values() {
+            if let Err(e) = self.apply_sweep_value(spec.parameter, value) {
+                outcome = Err(e);
+                break;
+            }
+            match self.run_stress_test(confidence_level).await {
+                Ok(run) => points.push((value, MonteCarloSummary::from(&run))),
+                Err(e) => {
+                    outcome = Err(e);
+                    break;
+                }
+            }
         }
+        self.strategy = original_strategy;
+        self.simulator_config = original_config;
+        outcome?;
         
-        let avg_tail = tail_values.iter().sum::<f64>() / tail_values.len() as f64;
-        Decimal::try_from(avg_tail).unwrap_or(Decimal::ZERO)
+        Ok(SensitivityResults {
+            parameter: spec.parameter,
+            points,
+        })
     }
 
-    fn calculate_max_drawdown_distribution(&self, values: &[f64]) -> f64 {
-        if values.is_empty() {
-            return 0.0;
+    /// Set `parameter` to `value` for subsequent runs
+    fn apply_sweep_value(&mut self, parameter: SweepParameter, value: f64) -> Result<()> {
+        let decimal = Decimal::try_from(value)
+            .with_context(|| format!("Sweep value {} is not representable", value))?;
+        match parameter {
+            SweepParameter::AllocationFraction => {
+                self.strategy = self.strategy.clone().with_allocation_fraction(decimal)?;
+            }
+            SweepParameter::VolatilityMultiplier => {
+                self.simulator_config.volatility_multiplier = value;
+            }
+            SweepParameter::FeeBps => {
+                self.simulator_config.fee_model = Some(Arc::new(FlatBps::new(decimal)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Value statistics at each checkpoint. Failed paths count as total losses
+    fn horizon_stats(&self, tally: &RunTally, confidence_level: f64) -> Vec<HorizonStats> {
+        let paths = tally.paths.max(1) as f64;
+        self.horizons
+            .iter()
+            .zip(&tally.horizons)
+            .map(|(&step, horizon)| {
+                let mean = horizon.sum / paths;
+                let reference = match self.var_reference {
+                    VarReference::InitialCapital => self.initial_capital,
+                    VarReference::ExpectedValue => mean,
+                };
+                let (var_loss, cvar_loss) = horizon.values.var_cvar(confidence_level, reference);
+                let pct_of_reference = |loss: f64| if reference > 0.0 { loss / reference * 100.0 } else { 0.0 };
+                HorizonStats {
+                    step,
+                    expected_value: Decimal::try_from(mean).unwrap_or(Decimal::ZERO),
+                    value_at_risk: Decimal::try_from(var_loss).unwrap_or(Decimal::ZERO),
+                    conditional_var: Decimal::try_from(cvar_loss).unwrap_or(Decimal::ZERO),
+                    var_pct: pct_of_reference(var_loss),
+                    cvar_pct: pct_of_reference(cvar_loss),
+                    mean_drawdown_pct: horizon.drawdown_sum / paths,
+                    prob_of_loss: horizon.losing as f64 / paths,
+                }
+            })
+            .collect()
+    }
+
+    /// Compare two runs path by path, matching paths on their iteration number
+    fn head_to_head(a: &MonteCarloResults, b: &MonteCarloResults) -> PairwiseComparison {
+        let b_values: HashMap<usize, f64> = b.paths.iter().map(|path| (path.iteration, path.final_value)).collect();
+        let shared: Vec<(f64, f64)> = a
+            .paths
+            .iter()
+            .filter_map(|path| b_values.get(&path.iteration).map(|b_value| (path.final_value, *b_value)))
+            .collect();
+        let n = shared.len().max(1) as f64;
+        PairwiseComparison {
+            a: a.strategy.clone(),
+            b: b.strategy.clone(),
+            paths: shared.len(),
+            prob_a_beats_b: shared.iter().filter(|(a, b)| a > b).count() as f64 / n,
+            mean_difference: shared.iter().map(|(a, b)| a - b).sum::<f64>() / n,
+            cvar_difference: a.conditional_var - b.conditional_var,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !self.initial_capital.is_finite() || self.initial_capital <= 0.0 {
+            return Err(anyhow::anyhow!(
+                "Initial capital must be positive, got {}",
+                self.initial_capital
+            ));
+        }
+        if self.simulator_config.time_step <= Duration::ZERO {
+            return Err(anyhow::anyhow!("Time step must be positive"));
+        }
+        if !(0.0..=100.0).contains(&self.ruin_threshold_pct) {
+            return Err(anyhow::anyhow!(
+                "Ruin threshold must be between 0 and 100 percent, got {}",
+                self.ruin_threshold_pct
+            ));
+        }
+        self.goal.validate()?;
+        self.scenario_distribution.validate()?;
+        if self.memory_light && self.detailed {
+            return Err(anyhow::anyhow!("Detailed results need every path retained; disable memory-light mode"));
+        }
+        if self.horizons.first() == Some(&0) {
+            return Err(anyhow::anyhow!("Horizons must be at least one step"));
+        }
+        Ok(())
+    }
+
+    /// Statistics over completed paths; the run is incomplete if fewer than
+    /// the requested paths completed. `paths` is empty in memory-light mode
+    fn aggregate(
+        &self,
+        shape: &RunShape,
+        mut tally: RunTally,
+        paths: Vec<PathSummary>,
+        path_results: Vec<Option<SimulationResults>>,
+        confidence_level: f64,
+    ) -> MonteCarloResults {
+        let path_count = tally.paths;
+        let incomplete = path_count < shape.requested;
+        tally.close_pair();
+        let distribution = match &tally.final_values {
+            Sample::Exact(values) => Some(values.clone()),
+            Sample::Sketch(_) => None,
+        };
+        tally.final_values.finish();
+        tally.drawdowns.finish();
+        tally.longest_underwater.finish();
+        tally.recoveries.finish();
+        tally.hit_steps.finish();
+        for horizon in &mut tally.horizons {
+            horizon.values.finish();
+        }
+        if let Some(sketch) = &mut tally.sketch {
+            sketch.flush();
         }
         
-        // For simplicity, calculate average drawdown
-        // In a full implementation, we'd track drawdowns per simulation
-        let mean = values.iter().sum::<f64>() / values.len() as f64;
-        let initial = 1_000_000.0;
+        let mean = if path_count > 0 { tally.final_sum / path_count as f64 } else { 0.0 };
+        let expected_value = Decimal::try_from(mean).unwrap_or(Decimal::ZERO);
+        let reference = match self.var_reference {
+            VarReference::InitialCapital => self.initial_capital,
+            VarReference::ExpectedValue => mean,
+        };
+        let (var_loss, cvar_loss) = tally.final_values.var_cvar(confidence_level, reference);
+        let pct_of_reference = |loss: f64| if reference > 0.0 { loss / reference * 100.0 } else { 0.0 };
+        let drawdown = tally.drawdown_distribution();
+        let (regimes, crisis_exposure) = tally.regime_breakdown(self.simulator_config.regimes.as_ref());
+        let horizons = self.horizon_stats(&tally, confidence_level);
         
-        if initial > 0.0 {
-            ((initial - mean) / initial * 100.0).max(0.0)
-        } else {
-            0.0
+        let at = |p: f64| tally.final_values.quantile(p);
+        let percentiles = REPORTED_PERCENTILES
+            .iter()
+            .map(|&p| (p, Decimal::try_from(at(p as f64 / 100.0)).unwrap_or(Decimal::ZERO)))
+            .collect();
+        let tail_gain = at(0.95) - self.initial_capital;
+        let tail_loss = self.initial_capital - at(0.05);
+        
+        MonteCarloResults {
+            iterations: path_count,
+            incomplete,
+            expected_value,
+            median_value: Decimal::try_from(at(0.5)).unwrap_or(Decimal::ZERO),
+            skewness: tally.final_moments.skewness(),
+            excess_kurtosis: tally.final_moments.excess_kurtosis(),
+            tail_ratio: (tail_loss > 0.0).then(|| tail_gain.max(0.0) / tail_loss),
+            standard_error: tally.standard_error(),
+            scenario_source: shape.source,
+            variance_reduction: shape.variance_reduction,
+            sampling: shape.sampling,
+            value_at_risk: Decimal::try_from(var_loss).unwrap_or(Decimal::ZERO),
+            conditional_var: Decimal::try_from(cvar_loss).unwrap_or(Decimal::ZERO),
+            var_pct: pct_of_reference(var_loss),
+            cvar_pct: pct_of_reference(cvar_loss),
+            var_reference: self.var_reference,
+            max_drawdown_pct: drawdown.mean_pct,
+            confidence_level,
+            distribution,
+            sketch: tally.sketch.clone(),
+            percentiles,
+            halted_paths: tally.halted,
+            halt_probability: tally.share(tally.halted),
+            seed: self.seed,
+            strategy: self.strategy.name().to_string(),
+            initial_capital: self.initial_capital,
+            steps_per_iteration: shape.steps_per_iteration,
+            time_step_secs: self.simulator_config.time_step.whole_seconds(),
+            scenarios_generated: tally.scenario_classes.len(),
+            scenario_breakdown: tally.scenario_breakdown(),
+            drawdown,
+            drawdown_durations: tally.drawdown_duration_stats(),
+            prob_of_loss: tally.share(tally.losing),
+            prob_of_ruin: tally.share(tally.ruined),
+            ruin_threshold_pct: self.ruin_threshold_pct,
+            jump_probability: tally.share(tally.jumped),
+            loss_given_jump_pct: (tally.jumped > 0).then(|| tally.jump_loss_sum / tally.jumped as f64),
+            goal: tally.goal_stats(&self.goal),
+            horizons,
+            paths,
+            path_results,
+            regimes,
+            crisis_exposure,
         }
     }
 
-    fn percentile(&self, sorted: &[f64], p: f64) -> Decimal {
-        if sorted.is_empty() {
-            return Decimal::ZERO;
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    fn report_progress(&self, completed: usize, total: usize) {
+        if let Some(progress) = &self.progress {
+            if let Ok(mut callback) = progress.lock() {
+                callback(completed, total);
+            }
         }
-        
-        let index = (p * sorted.len() as f64) as usize;
-        let value = sorted.get(index.min(sorted.len() - 1)).copied().unwrap_or(0.0);
-        Decimal::try_from(value).unwrap_or(Decimal::ZERO)
+    }
+
+    /// Run each named stress scenario once, returning its full results rather than a distribution.
+    ///
+    /// Every scenario uses the same seed (the engine's first path seed), so
+    /// differences between them come from the scenarios alone.
+    pub fn run_named_scenarios(&self, names: &[&str]) -> Result<Vec<(String, SimulationResults)>> {
+        names
+            .iter()
+            .map(|name| {
+                let definition = self.stress_library.get(name).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown stress scenario {}; available: {}",
+                        name,
+                        self.stress_library.names().join(", ")
+                    )
+                })?;
+                let results = self
+                    .run_stress_scenario(definition)
+                    .with_context(|| format!("Stress scenario {} failed", name))?;
+                Ok((name.to_string(), results))
+            })
+            .collect()
+    }
+
+    fn run_stress_scenario(&self, definition: &ScenarioDefinition) -> Result<SimulationResults> {
+        definition.validate()?;
+        let mut config = SimulatorConfig {
+            seed: Some(path_seed(self.seed, 0)),
+            ..self.simulator_config.clone()
+        };
+        definition.configure(&mut config);
+        let mut simulator = Simulator::with_config(self.initial_capital, self.strategy.clone(), config);
+        for step in 1..=definition.steps {
+            definition.apply_events(&mut simulator, step)?;
+            simulator.step()?;
+        }
+        Ok(simulator.finalize())
+    }
+
+    /// Run one path from the `draw`th seed, mirrored if asked, under `scenario` if given
+    fn run_single_simulation(
+        &self,
+        draw: usize,
+        mirrored: bool,
+        quasi_random: Option<QuasiRandomDraws>,
+        scenario: Option<&Scenario>,
+    ) -> Result<(PathSummary, SimulationResults)> {
+        let mut config = SimulatorConfig {
+            seed: Some(path_seed(self.seed, draw)),
+            antithetic: mirrored,
+            quasi_random,
+            ..self.simulator_config.clone()
+        };
+        if let Some(scenario) = scenario {
+            scenario.configure(&mut config);
+        }
+        let mut simulator = Simulator::with_config(self.initial_capital, self.strategy.clone(), config);
+        if let Some(scenario) = scenario {
+            scenario.schedule_shocks(&mut simulator)?;
+        }
+        
+        let mut watch = PathWatch::new(simulator.portfolio_value(), self.goal.target_value, &self.horizons);
+        for step in 1..=self.steps_per_iteration {
+            simulator.step()?;
+            watch.observe(step, simulator.portfolio_value());
+        }
+        Ok(Self::summarize_path(simulator, watch))
+    }
+
+    /// Run path `index` through the supplied prices, one step per price
+    fn run_price_path(&self, index: usize, path: &PricePath) -> Result<(PathSummary, SimulationResults)> {
+        let config = SimulatorConfig {
+            seed: Some(path_seed(self.seed, index)),
+            ..self.simulator_config.clone()
+        };
+        let mut simulator = Simulator::with_config(self.initial_capital, self.strategy.clone(), config);
+        
+        let mut watch = PathWatch::new(simulator.portfolio_value(), self.goal.target_value, &self.horizons);
+        for step in 0..path.steps() {
+            simulator.step_with_prices(&path.prices_at(step))?;
+            watch.observe(step + 1, simulator.portfolio_value());
+        }
+        Ok(Self::summarize_path(simulator, watch))
+    }
+
+    /// Finalize a path's simulator, given what was seen while stepping
+    fn summarize_path(simulator: Simulator, mut watch: PathWatch) -> (PathSummary, SimulationResults) {
+        watch.close_horizons();
+        let results = simulator.finalize();
+        let final_value = results.final_value.to_f64().unwrap_or(0.0);
+        let summary = PathSummary {
+            final_value,
+            // The terminal liquidation haircut can take the final value below every marked step
+            min_value: watch.min_value.min(final_value),
+            target_hit_step: watch.target_hit_step,
+            horizon_values: watch.horizon_values,
+            horizon_drawdowns_pct: watch.horizon_drawdowns_pct,
+            max_drawdown_pct: results.max_drawdown_pct,
+            drawdown_durations: results.drawdown_durations,
+            volatility_pct: results.volatility_pct,
+            sharpe_ratio: results.sharpe_ratio,
+            fees: results.total_fees.to_f64().unwrap_or(0.0),
+            halted: results.halted_at_step.is_some(),
+            jumps: results.jumps,
+            regime_occupancy: results.regime_occupancy.clone(),
+            ..PathSummary::default()
+        };
+        (summary, results)
+    }
+
+}
+
+/// Values a path is watched for while it steps
+struct PathWatch<'a> {
+    min_value: f64,
+    peak: f64,
+    last_value: f64,
+    max_drawdown_pct: f64,
+    target_value: Option<f64>,
+    target_hit_step: Option<usize>,
+    /// Ascending checkpoint steps
+    horizons: &'a [usize],
+    horizon_values: Vec<f64>,
+    horizon_drawdowns_pct: Vec<f64>,
+}
+
+impl<'a> PathWatch<'a> {
+    fn new(initial_value: f64, target_value: Option<f64>, horizons: &'a [usize]) -> Self {
+        let mut watch = Self {
+            min_value: initial_value,
+            peak: initial_value,
+            last_value: initial_value,
+            max_drawdown_pct: 0.0,
+            target_value,
+            target_hit_step: None,
+            horizons,
+            horizon_values: Vec::with_capacity(horizons.len()),
+            horizon_drawdowns_pct: Vec::with_capacity(horizons.len()),
+        };
+        watch.observe(0, initial_value);
+        watch
+    }
+
+    /// Record the marked value after `step` steps
+    fn observe(&mut self, step: usize, value: f64) {
+        self.min_value = self.min_value.min(value);
+        self.peak = self.peak.max(value);
+        self.last_value = value;
+        if self.peak > 0.0 {
+            self.max_drawdown_pct = self.max_drawdown_pct.max((self.peak - value) / self.peak * 100.0);
+        }
+        if self.target_hit_step.is_none() && self.target_value.is_some_and(|target| value >= target) {
+            self.target_hit_step = Some(step);
+        }
+        while self.horizons.get(self.horizon_values.len()) == Some(&step) {
+            self.horizon_values.push(value);
+            self.horizon_drawdowns_pct.push(self.max_drawdown_pct);
+        }
+    }
+
+    /// Checkpoints past the end of a short path take its last value
+    fn close_horizons(&mut self) {
+        while self.horizon_values.len() < self.horizons.len() {
+            self.horizon_values.push(self.last_value);
+            self.horizon_drawdowns_pct.push(self.max_drawdown_pct);
+        }
+    }
+}
+
+/// Where a [`PathStream`]'s paths come from
+enum PathSource {
+    /// Generated from the engine's seed, scenarios and sampling settings
+    Generated {
+        scenarios: Vec<Scenario>,
+        sobol: Option<Arc<SobolSequence>>,
+    },
+    Supplied(Vec<PricePath>),
+    /// Path `i` is bootstrapped from its own seed, so paths are built only as they run
+    Bootstrap(Arc<BlockBootstrap>),
+}
+
+/// Monte Carlo paths, run lazily as they are consumed.
+///
+/// Whenever its buffer runs dry the stream runs the next chunk of paths in
+/// parallel (a few per worker thread), so at most one chunk of outcomes is
+/// held and nothing is in flight between calls to `next`: dropping the stream
+/// stops the run. Paths come in iteration order. A path whose simulation
+/// errored is yielded with `PathSummary::failed` set, since it counts as a
+/// total loss. Cancellation ends the stream after the current chunk.
+pub struct PathStream<'a> {
+    engine: &'a MonteCarloEngine,
+    source: PathSource,
+    shape: RunShape,
+    next_index: usize,
+    completed: AtomicUsize,
+    buffer: VecDeque<(PathSummary, Option<SimulationResults>)>,
+    finished: bool,
+}
+
+impl<'a> PathStream<'a> {
+    fn new(engine: &'a MonteCarloEngine, source: PathSource, shape: RunShape) -> Self {
+        Self {
+            engine,
+            source,
+            shape,
+            next_index: 0,
+            completed: AtomicUsize::new(0),
+            buffer: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    /// Paths the run was asked for
+    pub fn requested(&self) -> usize {
+        self.shape.requested
+    }
+
+    /// Drain the stream and compute the run's statistics from the paths it
+    /// yields. Paths already taken with `next` are not included
+    pub fn into_results(mut self, confidence_level: f64) -> MonteCarloResults {
+        let engine = self.engine;
+        let scenarios = match &self.source {
+            PathSource::Generated { scenarios, .. } => scenarios.as_slice(),
+            PathSource::Supplied(_) | PathSource::Bootstrap(_) => &[],
+        };
+        let mut tally = RunTally::new(engine, &self.shape, scenarios);
+        let mut paths = vec![];
+        let mut path_results = vec![];
+        while let Some((summary, results)) = self.next_path() {
+            tally.push(&summary);
+            if engine.detailed {
+                path_results.push(results);
+            }
+            if !engine.memory_light {
+                paths.push(summary);
+            }
+        }
+        engine.aggregate(&self.shape, tally, paths, path_results, confidence_level)
+    }
+
+    fn next_path(&mut self) -> Option<(PathSummary, Option<SimulationResults>)> {
+        if self.buffer.is_empty() && !self.finished {
+            self.run_chunk();
+        }
+        self.buffer.pop_front()
+    }
+
+    fn run_chunk(&mut self) {
+        let start = self.next_index;
+        let total = self.shape.requested;
+        if start >= total || self.engine.is_cancelled() {
+            self.finish();
+            return;
+        }
+        let end = (start + rayon::current_num_threads() * PATHS_PER_THREAD).min(total);
+        let chunk: Vec<(PathSummary, Option<SimulationResults>)> = (start..end)
+            .into_par_iter()
+            .filter_map(|i| (!self.engine.is_cancelled()).then(|| self.run_path(i)))
+            .collect();
+        self.next_index = end;
+        if chunk.is_empty() {
+            self.finish();
+        }
+        self.buffer.extend(chunk);
+    }
+
+    fn run_path(&self, i: usize) -> (PathSummary, Option<SimulationResults>) {
+        let engine = self.engine;
+        let (scenario, outcome) = match &self.source {
+            PathSource::Generated { scenarios, sobol } => {
+                // Antithetic pairs share a seed and a scenario
+                let (draw, mirrored) = match engine.variance_reduction {
+                    VarianceReduction::None => (i, false),
+                    VarianceReduction::Antithetic => (i / 2, i % 2 == 1),
+                };
+                let scenario = (!scenarios.is_empty())
+                    .then(|| (path_seed(engine.seed ^ SCENARIO_SALT, draw) % scenarios.len() as u64) as usize);
+                let quasi_random = sobol.as_ref().map(|sequence| QuasiRandomDraws {
+                    sequence: sequence.clone(),
+                    index: draw as u32,
+                });
+                let outcome =
+                    engine.run_single_simulation(draw, mirrored, quasi_random, scenario.map(|k| &scenarios[k]));
+                (scenario, outcome)
+            }
+            PathSource::Supplied(paths) => (None, engine.run_price_path(i, &paths[i])),
+            PathSource::Bootstrap(bootstrap) => {
+                let mut rng = StdRng::seed_from_u64(path_seed(engine.seed ^ BOOTSTRAP_SALT, i));
+                let path = bootstrap.sample_path(engine.steps_per_iteration, &mut rng);
+                (None, engine.run_price_path(i, &path))
+            }
+        };
+        let (summary, results) = match outcome {
+            Ok((summary, results)) => (summary, engine.detailed.then_some(results)),
+            Err(_) => (PathSummary::failed(i), None),
+        };
+        
+        let total = self.shape.requested;
+        let done = self.completed.fetch_add(1, Ordering::Relaxed) + 1;
+        if done.is_multiple_of(PROGRESS_INTERVAL) {
+            info!("Completed {}/{} iterations", done, total);
+        }
+        if done.is_multiple_of(engine.progress_interval) {
+            engine.report_progress(done, total);
+        }
+        (PathSummary { iteration: i, scenario, ..summary }, results)
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        let total = self.shape.requested;
+        let done = self.completed.load(Ordering::Relaxed);
+        if done < total {
+            info!("Monte Carlo run cancelled after {}/{} iterations", done, total);
+        } else if !total.is_multiple_of(self.engine.progress_interval) {
+            self.engine.report_progress(total, total);
+        }
+    }
+}
+
+impl Iterator for PathStream<'_> {
+    type Item = PathSummary;
+
+    fn next(&mut self) -> Option<PathSummary> {
+        self.next_path().map(|(summary, _)| summary)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.buffer.len() + self.shape.requested.saturating_sub(self.next_index);
+        (0, Some(remaining))
+    }
+}
+
+/// Seed for path `index`, mixed from the master seed with SplitMix64 so
+/// neighbouring paths get unrelated streams
+fn path_seed(master: u64, index: usize) -> u64 {
+    let mut z = master.wrapping_add((index as u64).wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Values a run reports quantiles of: every value, or a t-digest sketch of
+/// them in memory-light mode
+enum Sample {
+    Exact(Vec<f64>),
+    Sketch(TDigest),
+}
+
+impl Sample {
+    fn new(memory_light: bool) -> Self {
+        if memory_light {
+            Self::Sketch(TDigest::default())
+        } else {
+            Self::Exact(vec![])
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        match self {
+            Self::Exact(values) => values.push(value),
+            Self::Sketch(sketch) => sketch.push(value),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Exact(values) => values.len(),
+            Self::Sketch(sketch) => sketch.count(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sort the values, or merge the sketch's buffer, ready for quantiles
+    fn finish(&mut self) {
+        match self {
+            Self::Exact(values) => values.sort_by(|a, b| a.partial_cmp(b).unwrap()),
+            Self::Sketch(sketch) => sketch.flush(),
+        }
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        match self {
+            Self::Exact(sorted) => quantile(sorted, p),
+            Self::Sketch(sketch) => sketch.quantile(p),
+        }
+    }
+
+    /// Mean of the values at or below the `p` quantile
+    fn tail_mean(&self, p: f64) -> f64 {
+        match self {
+            Self::Exact(sorted) => {
+                let cutoff = quantile(sorted, p);
+                let tail: Vec<f64> = sorted.iter().copied().take_while(|value| *value <= cutoff).collect();
+                tail.iter().sum::<f64>() / tail.len() as f64
+            }
+            Self::Sketch(sketch) => sketch.tail_mean(p),
+        }
+    }
+
+    fn histogram(&self) -> Histogram {
+        match self {
+            Self::Exact(sorted) => Histogram::from_values(sorted, None, 0),
+            Self::Sketch(sketch) => Histogram::from_sketch(sketch, None, 0),
+        }
+    }
+
+    /// Losses below `reference` at the `1 - confidence` quantile (VaR) and
+    /// over the values at or below it (CVaR), floored at zero
+    fn var_cvar(&self, confidence: f64, reference: f64) -> (f64, f64) {
+        if self.is_empty() {
+            return (0.0, 0.0);
+        }
+        let var = (reference - self.quantile(1.0 - confidence)).max(0.0);
+        let cvar = (reference - self.tail_mean(1.0 - confidence)).max(0.0);
+        (var, cvar)
+    }
+}
+
+/// Path count, mean and worst final value of a group of paths
+#[derive(Debug, Clone, Copy)]
+struct OutcomeTally {
+    paths: usize,
+    sum: f64,
+    worst: f64,
+}
+
+impl Default for OutcomeTally {
+    fn default() -> Self {
+        Self {
+            paths: 0,
+            sum: 0.0,
+            worst: f64::INFINITY,
+        }
+    }
+}
+
+impl OutcomeTally {
+    fn push(&mut self, value: f64) {
+        self.paths += 1;
+        self.sum += value;
+        self.worst = self.worst.min(value);
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.paths as f64
+    }
+}
+
+/// Values and drawdowns at one horizon checkpoint
+struct HorizonTally {
+    sum: f64,
+    losing: usize,
+    drawdown_sum: f64,
+    values: Sample,
+}
+
+/// Running statistics over a run's paths, folded in one at a time.
+///
+/// Quantile-based figures come from `Sample`s, which keep every value unless
+/// the engine is memory-light; everything else is a count, sum or extreme,
+/// so a memory-light run holds nothing per path.
+struct RunTally {
+    initial_capital: f64,
+    ruin_level: f64,
+    drawdown_threshold_pct: Option<f64>,
+    floor_value: Option<f64>,
+    variance_reduction: VarianceReduction,
+    /// Classes of each generated scenario, by scenario index
+    scenario_classes: Vec<[&'static str; 3]>,
+    /// Regime count and the crisis regime, when a regime model is configured
+    regime_model: Option<(usize, usize)>,
+    
+    paths: usize,
+    final_sum: f64,
+    final_moments: Moments,
+    final_values: Sample,
+    /// Final values of paths that didn't fail, for the histogram of a memory-light run
+    sketch: Option<TDigest>,
+    /// Independent draws for the standard error: final values, or antithetic pair means
+    error_samples: Moments,
+    /// Antithetic pair still being filled: pair index, sum and count of its values
+    open_pair: Option<(usize, f64, usize)>,
+    drawdown_sum: f64,
+    drawdown_worst: f64,
+    drawdown_exceeded: usize,
+    drawdowns: Sample,
+    /// Paths that didn't fail, which the drawdown durations are taken over
+    completed: usize,
+    longest_underwater: Sample,
+    longest_underwater_sum: f64,
+    longest_underwater_worst: usize,
+    episode_steps_sum: f64,
+    underwater_pct_sum: f64,
+    recoveries: Sample,
+    recovery_sum: f64,
+    unrecovered: usize,
+    halted: usize,
+    losing: usize,
+    ruined: usize,
+    floor_breaches: usize,
+    jumped: usize,
+    jump_loss_sum: f64,
+    hit_steps: Sample,
+    hit_step_sum: f64,
+    classes: HashMap<&'static str, OutcomeTally>,
+    regime_share_sums: Vec<f64>,
+    crisis_buckets: Vec<OutcomeTally>,
+    horizons: Vec<HorizonTally>,
+}
+
+impl RunTally {
+    fn new(engine: &MonteCarloEngine, shape: &RunShape, scenarios: &[Scenario]) -> Self {
+        let light = engine.memory_light;
+        let regime_model = engine
+            .simulator_config
+            .regimes
+            .as_ref()
+            .map(|model| (model.regimes.len(), model.crisis()));
+        Self {
+            initial_capital: engine.initial_capital,
+            ruin_level: engine.initial_capital * engine.ruin_threshold_pct / 100.0,
+            drawdown_threshold_pct: engine.drawdown_threshold_pct,
+            floor_value: engine.goal.floor_value,
+            variance_reduction: shape.variance_reduction,
+            scenario_classes: scenarios.iter().map(Scenario::classes).collect(),
+            regime_model,
+            paths: 0,
+            final_sum: 0.0,
+            final_moments: Moments::new(),
+            final_values: Sample::new(light),
+            sketch: light.then(TDigest::default),
+            error_samples: Moments::new(),
+            open_pair: None,
+            drawdown_sum: 0.0,
+            drawdown_worst: 0.0,
+            drawdown_exceeded: 0,
+            drawdowns: Sample::new(light),
+            completed: 0,
+            longest_underwater: Sample::new(light),
+            longest_underwater_sum: 0.0,
+            longest_underwater_worst: 0,
+            episode_steps_sum: 0.0,
+            underwater_pct_sum: 0.0,
+            recoveries: Sample::new(light),
+            recovery_sum: 0.0,
+            unrecovered: 0,
+            halted: 0,
+            losing: 0,
+            ruined: 0,
+            floor_breaches: 0,
+            jumped: 0,
+            jump_loss_sum: 0.0,
+            hit_steps: Sample::new(light),
+            hit_step_sum: 0.0,
+            classes: HashMap::new(),
+            regime_share_sums: vec![0.0; regime_model.map_or(0, |(count, _)| count)],
+            crisis_buckets: vec![OutcomeTally::default(); CRISIS_SHARE_EDGES.len() - 1],
+            horizons: engine
+                .horizons
+                .iter()
+                .map(|_| HorizonTally {
+                    sum: 0.0,
+                    losing: 0,
+                    drawdown_sum: 0.0,
+                    values: Sample::new(light),
+                })
+                .collect(),
+        }
+    }
+
+    fn push(&mut self, path: &PathSummary) {
+        let value = path.final_value;
+        self.paths += 1;
+        self.final_sum += value;
+        self.final_moments.push(value);
+        self.final_values.push(value);
+        if let Some(sketch) = &mut self.sketch {
+            // Failed paths end at zero, and histograms leave them out
+            if value != 0.0 {
+                sketch.push(value);
+            }
+        }
+        match self.variance_reduction {
+            VarianceReduction::None => self.error_samples.push(value),
+            // Pairs arrive together, in iteration order
+            VarianceReduction::Antithetic => {
+                let pair = path.iteration / 2;
+                match &mut self.open_pair {
+                    Some((open, sum, count)) if *open == pair => {
+                        *sum += value;
+                        *count += 1;
+                    }
+                    _ => {
+                        self.close_pair();
+                        self.open_pair = Some((pair, value, 1));
+                    }
+                }
+            }
+        }
+        
+        let drawdown = path.max_drawdown_pct;
+        self.drawdown_sum += drawdown;
+        self.drawdown_worst = self.drawdown_worst.max(drawdown);
+        if self.drawdown_threshold_pct.is_some_and(|threshold| drawdown > threshold) {
+            self.drawdown_exceeded += 1;
+        }
+        self.drawdowns.push(drawdown);
+        if !path.failed {
+            let durations = &path.drawdown_durations;
+            self.completed += 1;
+            self.longest_underwater.push(durations.longest_steps as f64);
+            self.longest_underwater_sum += durations.longest_steps as f64;
+            self.longest_underwater_worst = self.longest_underwater_worst.max(durations.longest_steps);
+            self.episode_steps_sum += durations.mean_steps;
+            self.underwater_pct_sum += durations.underwater_pct;
+            match durations.recovery_steps {
+                Some(steps) => {
+                    self.recoveries.push(steps as f64);
+                    self.recovery_sum += steps as f64;
+                }
+                None => self.unrecovered += 1,
+            }
+        }
+        
+        self.halted += usize::from(path.halted);
+        self.losing += usize::from(value < self.initial_capital);
+        self.ruined += usize::from(path.min_value < self.ruin_level);
+        self.floor_breaches += usize::from(self.floor_value.is_some_and(|floor| path.min_value < floor));
+        if path.jumps > 0 {
+            self.jumped += 1;
+            self.jump_loss_sum += (self.initial_capital - value) / self.initial_capital * 100.0;
+        }
+        if let Some(step) = path.target_hit_step {
+            self.hit_steps.push(step as f64);
+            self.hit_step_sum += step as f64;
+        }
+        
+        if let Some(scenario) = path.scenario {
+            for class in self.scenario_classes[scenario] {
+                self.classes.entry(class).or_default().push(value);
+            }
+        }
+        if let Some((regime_count, crisis)) = self.regime_model {
+            let steps = path.regime_occupancy.iter().sum::<usize>().max(1) as f64;
+            let share = |i: usize| path.regime_occupancy.get(i).copied().unwrap_or(0) as f64 / steps;
+            for (i, sum) in self.regime_share_sums.iter_mut().enumerate().take(regime_count) {
+                *sum += share(i);
+            }
+            let crisis_share = share(crisis);
+            let last = CRISIS_SHARE_EDGES.len() - 2;
+            if let Some(bucket) = CRISIS_SHARE_EDGES.windows(2).enumerate().position(|(bucket, edges)| {
+                crisis_share >= edges[0] && (crisis_share < edges[1] || bucket == last)
+            }) {
+                self.crisis_buckets[bucket].push(value);
+            }
+        }
+        
+        for (k, horizon) in self.horizons.iter_mut().enumerate() {
+            // Failed paths count as total losses
+            let value = path.horizon_values.get(k).copied().unwrap_or(0.0);
+            horizon.sum += value;
+            horizon.losing += usize::from(value < self.initial_capital);
+            horizon.drawdown_sum += path.horizon_drawdowns_pct.get(k).copied().unwrap_or(100.0);
+            horizon.values.push(value);
+        }
+    }
+
+    fn close_pair(&mut self) {
+        if let Some((_, sum, count)) = self.open_pair.take() {
+            self.error_samples.push(sum / count as f64);
+        }
+    }
+
+    /// Share of all paths that `count` represents
+    fn share(&self, count: usize) -> f64 {
+        if self.paths > 0 {
+            count as f64 / self.paths as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Standard error of the mean final value, from independent draws
+    fn standard_error(&self) -> f64 {
+        let n = self.error_samples.count();
+        if n < 2 {
+            return 0.0;
+        }
+        (self.error_samples.variance() / n as f64).sqrt()
+    }
+
+    fn drawdown_distribution(&self) -> DrawdownDistribution {
+        let threshold_pct = self.drawdown_threshold_pct;
+        if self.drawdowns.is_empty() {
+            return DrawdownDistribution {
+                threshold_pct,
+                ..Default::default()
+            };
+        }
+        DrawdownDistribution {
+            mean_pct: self.drawdown_sum / self.paths as f64,
+            median_pct: self.drawdowns.quantile(0.50),
+            p95_pct: self.drawdowns.quantile(0.95),
+            worst_pct: self.drawdown_worst,
+            threshold_pct,
+            exceed_probability: threshold_pct.map(|_| self.share(self.drawdown_exceeded)),
+        }
+    }
+
+    fn drawdown_duration_stats(&self) -> DrawdownDurationStats {
+        if self.completed == 0 {
+            return DrawdownDurationStats::default();
+        }
+        let paths = self.completed as f64;
+        let recovered = !self.recoveries.is_empty();
+        DrawdownDurationStats {
+            mean_longest_steps: self.longest_underwater_sum / paths,
+            median_longest_steps: self.longest_underwater.quantile(0.50),
+            p95_longest_steps: self.longest_underwater.quantile(0.95),
+            worst_longest_steps: self.longest_underwater_worst,
+            mean_episode_steps: self.episode_steps_sum / paths,
+            mean_underwater_pct: self.underwater_pct_sum / paths,
+            mean_recovery_steps: recovered.then(|| self.recovery_sum / self.recoveries.len() as f64),
+            median_recovery_steps: recovered.then(|| self.recoveries.quantile(0.50)),
+            p95_recovery_steps: recovered.then(|| self.recoveries.quantile(0.95)),
+            unrecovered_probability: self.unrecovered as f64 / paths,
+        }
+    }
+
+    fn goal_stats(&self, goal: &Goal) -> Option<GoalStats> {
+        if goal.target_value.is_none() && goal.floor_value.is_none() {
+            return None;
+        }
+        let hits = self.hit_steps.len();
+        let time_to_target = (hits > 0).then(|| FirstPassageStats {
+            mean_steps: self.hit_step_sum / hits as f64,
+            median_steps: self.hit_steps.quantile(0.5),
+            p90_steps: self.hit_steps.quantile(0.9),
+            histogram: self.hit_steps.histogram(),
+        });
+        Some(GoalStats {
+            target_value: goal.target_value,
+            floor_value: goal.floor_value,
+            prob_hit_target: goal.target_value.map(|_| self.share(hits)),
+            time_to_target,
+            prob_breach_floor: goal.floor_value.map(|_| self.share(self.floor_breaches)),
+        })
+    }
+
+    fn scenario_breakdown(&self) -> HashMap<String, ScenarioClassStats> {
+        self.classes
+            .iter()
+            .map(|(class, tally)| {
+                let mean = tally.mean();
+                let stats = ScenarioClassStats {
+                    paths: tally.paths,
+                    probability: self.share(tally.paths),
+                    expected_value: Decimal::try_from(mean).unwrap_or(Decimal::ZERO),
+                    mean_return_pct: (mean - self.initial_capital) / self.initial_capital * 100.0,
+                    worst_value: Decimal::try_from(tally.worst).unwrap_or(Decimal::ZERO),
+                };
+                (class.to_string(), stats)
+            })
+            .collect()
+    }
+
+    /// Mean regime occupancy, and outcomes bucketed by time spent in the crisis regime
+    fn regime_breakdown(&self, model: Option<&RegimeModel>) -> (Vec<RegimeStats>, Vec<CrisisExposureStats>) {
+        let Some(model) = model else {
+            return (vec![], vec![]);
+        };
+        let stationary = model.stationary_distribution();
+        let regimes = model
+            .regimes
+            .iter()
+            .enumerate()
+            .map(|(i, regime)| RegimeStats {
+                name: regime.name.clone(),
+                mean_occupancy: if self.paths > 0 {
+                    self.regime_share_sums[i] / self.paths as f64
+                } else {
+                    0.0
+                },
+                stationary_probability: stationary.get(i).copied().unwrap_or(0.0),
+            })
+            .collect();
+        
+        let crisis_exposure = CRISIS_SHARE_EDGES
+            .windows(2)
+            .zip(&self.crisis_buckets)
+            .filter(|(_, tally)| tally.paths > 0)
+            .map(|(edges, tally)| CrisisExposureStats {
+                min_share: edges[0],
+                max_share: edges[1],
+                paths: tally.paths,
+                mean_return_pct: (tally.mean() - self.initial_capital) / self.initial_capital * 100.0,
+                worst_value: Decimal::try_from(tally.worst).unwrap_or(Decimal::ZERO),
+            })
+            .collect();
+        
+        (regimes, crisis_exposure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::{MarketDataProvider, MockMarketDataProvider, SyncAdapter};
+    use crate::metrics::DrawdownDurations;
+    use crate::shocks::JumpModel;
+    use rust_decimal_macros::dec;
+
+    fn engine(strategy: Strategy) -> MonteCarloEngine {
+        MonteCarloEngine::builder()
+            .iterations(64)
+            .scenarios(8)
+            .seed(7)
+            .strategy(strategy)
+            .capital(250_000.0)
+            .steps(30)
+            .time_step(Duration::hours(12))
+            .build()
+            .unwrap()
+    }
+
+    /// Builder for paths holding their whole book in one asset of `volatility`,
+    /// so no diversification thins the tails
+    fn all_in(volatility: Decimal) -> MonteCarloEngineBuilder {
+        let volatile = Asset {
+            symbol: "X".to_string(),
+            name: "X".to_string(),
+            asset_type: AssetType::Crypto,
+            current_price: Decimal::ONE,
+            volatility,
+            yield_rate: Decimal::ZERO,
+            expected_return: Decimal::ZERO,
+            bond: None,
+            liquidity: None,
+        };
+        MonteCarloEngine::builder()
+            .scenarios(1)
+            .strategy(Strategy::target_weight(HashMap::from([("X".to_string(), Decimal::ONE)])))
+            .universe(vec![volatile])
+    }
+
+    fn spread(results: &MonteCarloResults) -> f64 {
+        let at = |p: u8| results.percentiles[&p].to_f64().unwrap();
+        at(95) - at(5)
+    }
+
+    #[tokio::test]
+    async fn strategies_on_the_same_seed_give_different_distributions() {
+        let conservative = engine(Strategy::conservative()).run().await.unwrap();
+        let aggressive = engine(Strategy::aggressive()).run().await.unwrap();
+
+        assert_eq!(conservative.strategy, Strategy::conservative().name());
+        assert_eq!(aggressive.strategy, Strategy::aggressive().name());
+        for results in [&conservative, &aggressive] {
+            assert_eq!(results.iterations, 64);
+            assert_eq!(results.seed, 7);
+            assert_eq!(results.initial_capital, 250_000.0);
+            assert_eq!(results.steps_per_iteration, 30);
+            assert_eq!(results.time_step_secs, 12 * 3600);
+        }
+
+        let aggressive_finals: HashMap<usize, f64> =
+            aggressive.paths.iter().map(|path| (path.iteration, path.final_value)).collect();
+        let differing = conservative.paths.iter()
+            .filter(|path| aggressive_finals[&path.iteration] != path.final_value)
+            .count();
+        assert!(differing > 60, "only {} of 64 paths differ", differing);
+        assert!(
+            spread(&aggressive) > spread(&conservative),
+            "aggressive spread {} vs conservative {}",
+            spread(&aggressive),
+            spread(&conservative)
+        );
+    }
+
+    /// Aggregate hand-built paths as `engine` would its own
+    fn aggregated(engine: &MonteCarloEngine, paths: &[PathSummary], confidence_level: f64) -> MonteCarloResults {
+        let shape = RunShape {
+            source: ScenarioSource::Supplied,
+            requested: paths.len(),
+            steps_per_iteration: 10,
+            variance_reduction: VarianceReduction::None,
+            sampling: SamplingMode::PseudoRandom,
+        };
+        let mut tally = RunTally::new(engine, &shape, &[]);
+        for path in paths {
+            tally.push(path);
+        }
+        engine.aggregate(&shape, tally, vec![], vec![], confidence_level)
+    }
+
+    fn ending_at(finals: &[f64]) -> Vec<PathSummary> {
+        finals
+            .iter()
+            .enumerate()
+            .map(|(iteration, &final_value)| PathSummary {
+                iteration,
+                final_value,
+                min_value: final_value,
+                ..PathSummary::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drawdowns_are_taken_per_path_not_from_the_mean_outcome() {
+        let engine = MonteCarloEngine::builder()
+            .capital(100.0)
+            .drawdown_threshold(20.0)
+            .build()
+            .unwrap();
+        // Every path ends where it started; the one that halved along the way recovered
+        let paths: Vec<PathSummary> = [30.0, 0.0, 50.0, 10.0, 20.0]
+            .into_iter()
+            .enumerate()
+            .map(|(iteration, drawdown)| PathSummary {
+                iteration,
+                final_value: 100.0,
+                min_value: 100.0 - drawdown,
+                max_drawdown_pct: drawdown,
+                ..PathSummary::default()
+            })
+            .collect();
+        let results = aggregated(&engine, &paths, 0.95);
+
+        let drawdown = &results.drawdown;
+        assert!((drawdown.mean_pct - 22.0).abs() < 1e-12);
+        assert_eq!(results.max_drawdown_pct, drawdown.mean_pct);
+        assert_eq!(drawdown.median_pct, 20.0);
+        // Type-7 interpolation between 30 and 50
+        assert!((drawdown.p95_pct - 46.0).abs() < 1e-12);
+        assert_eq!(drawdown.worst_pct, 50.0);
+        assert_eq!(drawdown.threshold_pct, Some(20.0));
+        assert_eq!(drawdown.exceed_probability, Some(0.4));
+        assert_eq!(results.expected_value, Decimal::from(100));
+    }
+
+    #[test]
+    fn drawdown_durations_are_aggregated_over_completed_paths() {
+        let engine = MonteCarloEngine::builder().capital(100.0).build().unwrap();
+        let durations = [(4, 2.0, 40.0, Some(2)), (10, 5.0, 60.0, Some(6)), (1, 1.0, 20.0, None)];
+        let mut paths: Vec<PathSummary> = durations
+            .into_iter()
+            .enumerate()
+            .map(|(iteration, (longest_steps, mean_steps, underwater_pct, recovery_steps))| PathSummary {
+                iteration,
+                final_value: 100.0,
+                min_value: 100.0,
+                drawdown_durations: DrawdownDurations { longest_steps, mean_steps, underwater_pct, recovery_steps },
+                ..PathSummary::default()
+            })
+            .collect();
+        // A failed path has no drawdown history to measure
+        paths.push(PathSummary::failed(3));
+        let stats = aggregated(&engine, &paths, 0.95).drawdown_durations;
+
+        assert_eq!(stats.mean_longest_steps, 5.0);
+        assert_eq!(stats.median_longest_steps, 4.0);
+        // Type-7 interpolation between 4 and 10
+        assert!((stats.p95_longest_steps - 9.4).abs() < 1e-12);
+        assert_eq!(stats.worst_longest_steps, 10);
+        assert!((stats.mean_episode_steps - 8.0 / 3.0).abs() < 1e-12);
+        assert_eq!(stats.mean_underwater_pct, 40.0);
+        // Recovery times come from the two paths that recovered
+        assert_eq!(stats.mean_recovery_steps, Some(4.0));
+        assert_eq!(stats.median_recovery_steps, Some(4.0));
+        assert!((stats.p95_recovery_steps.unwrap() - 5.8).abs() < 1e-12);
+        assert!((stats.unrecovered_probability - 1.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn student_t_shocks_widen_the_tail_loss() {
+        let tail = |distribution: ShockDistribution| {
+            all_in(Decimal::ONE)
+                .iterations(2000)
+                .seed(11)
+                .steps(5)
+                .confidence(0.99)
+                .shock_distribution(distribution)
+                .build()
+                .unwrap()
+        };
+        let normal = tail(ShockDistribution::Normal).run().await.unwrap();
+        let fat = tail(ShockDistribution::student_t(3.0)).run().await.unwrap();
+
+        assert!(
+            fat.conditional_var > normal.conditional_var * Decimal::new(12, 1),
+            "t(3) CVaR {} vs normal {}",
+            fat.conditional_var,
+            normal.conditional_var
+        );
+        assert!(fat.value_at_risk > normal.value_at_risk * Decimal::new(105, 2));
+        assert!(fat.excess_kurtosis > normal.excess_kurtosis);
+    }
+
+    #[tokio::test]
+    async fn antithetic_pairs_shrink_the_standard_error() {
+        let run = |variance_reduction: VarianceReduction| {
+            all_in(dec!(0.5))
+                .iterations(400)
+                .seed(21)
+                .steps(10)
+                .variance_reduction(variance_reduction)
+                .build()
+                .unwrap()
+        };
+        let plain = run(VarianceReduction::None).run().await.unwrap();
+        let antithetic = run(VarianceReduction::Antithetic).run().await.unwrap();
+
+        assert_eq!(antithetic.iterations, 400);
+        assert_eq!(antithetic.variance_reduction, VarianceReduction::Antithetic);
+        // A nearly linear payoff: mirrored paths all but cancel each other's noise
+        assert!(
+            antithetic.standard_error < plain.standard_error * 0.5,
+            "antithetic {} vs plain {}",
+            antithetic.standard_error,
+            plain.standard_error
+        );
+        let error = (antithetic.expected_value - plain.expected_value).abs().to_f64().unwrap();
+        assert!(error < 4.0 * plain.standard_error);
+    }
+
+    #[tokio::test]
+    async fn sobol_estimates_agree_across_seeds_sooner_than_pseudo_random_ones() {
+        async fn estimates(sampling: SamplingMode) -> Vec<f64> {
+            let mut means = vec![];
+            for seed in 1..=8 {
+                // No scenarios, whose per-seed draws would swamp the sampling error
+                let builder = all_in(dec!(0.8)).scenarios(0).iterations(128).seed(seed).steps(4);
+                let mut engine = builder.sampling(sampling).build().unwrap();
+                means.push(engine.run().await.unwrap().expected_value.to_f64().unwrap());
+            }
+            means
+        }
+        let std_dev = |values: &[f64]| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+        };
+
+        // Eight independent randomizations of each at 128 paths
+        let sobol = std_dev(&estimates(SamplingMode::Sobol).await);
+        let pseudo = std_dev(&estimates(SamplingMode::PseudoRandom).await);
+        assert!(sobol < pseudo / 3.0, "Sobol spread {} vs pseudo-random {}", sobol, pseudo);
+    }
+
+    #[test]
+    fn var_and_cvar_are_losses_from_the_reference() {
+        // Ten paths from 1,000: 700, 750, ..., 1,150
+        let finals: Vec<f64> = (0..10).map(|i| 700.0 + 50.0 * i as f64).collect();
+        let paths = ending_at(&finals);
+        let engine = MonteCarloEngine::builder().capital(1000.0).build().unwrap();
+        let results = aggregated(&engine, &paths, 0.9);
+
+        // The 10% quantile sits 0.9 of the way from 700 to 750
+        assert_eq!(results.value_at_risk, Decimal::from(255));
+        assert_eq!(results.conditional_var, Decimal::from(300));
+        assert!((results.var_pct - 25.5).abs() < 1e-12);
+        assert!((results.cvar_pct - 30.0).abs() < 1e-12);
+        assert_eq!(results.var_reference, VarReference::InitialCapital);
+
+        // Relative to the mean of 925
+        let relative = MonteCarloEngine::builder()
+            .capital(1000.0)
+            .var_reference(VarReference::ExpectedValue)
+            .build()
+            .unwrap();
+        let results = aggregated(&relative, &paths, 0.9);
+        assert_eq!(results.value_at_risk, Decimal::from(180));
+        assert_eq!(results.conditional_var, Decimal::from(225));
+    }
+
+    #[test]
+    fn cvar_keeps_the_worst_path_at_high_confidence() {
+        let finals: Vec<f64> = (0..10).map(|i| 700.0 + 50.0 * i as f64).collect();
+        let engine = MonteCarloEngine::builder().capital(1000.0).build().unwrap();
+        let results = aggregated(&engine, &ending_at(&finals), 0.99);
+        assert_eq!(results.value_at_risk, Decimal::new(2955, 1));
+        assert_eq!(results.conditional_var, Decimal::from(300));
+    }
+
+    #[test]
+    fn gains_everywhere_report_no_loss() {
+        let engine = MonteCarloEngine::builder().capital(1000.0).build().unwrap();
+        let results = aggregated(&engine, &ending_at(&[1100.0, 1200.0, 1300.0]), 0.95);
+        assert_eq!(results.value_at_risk, Decimal::ZERO);
+        assert_eq!(results.conditional_var, Decimal::ZERO);
+        assert_eq!(results.var_pct, 0.0);
+    }
+
+    #[test]
+    fn percentile_map_interpolates_from_the_first_to_the_ninety_ninth() {
+        let finals: Vec<f64> = (1..=100).map(f64::from).collect();
+        let engine = MonteCarloEngine::builder().capital(50.0).build().unwrap();
+        let results = aggregated(&engine, &ending_at(&finals), 0.95);
+
+        let mut reported: Vec<u8> = results.percentiles.keys().copied().collect();
+        reported.sort();
+        assert_eq!(reported, vec![1, 5, 25, 50, 75, 95, 99]);
+        let at = |p: u8| results.percentiles[&p].to_f64().unwrap();
+        assert!((at(1) - 1.99).abs() < 1e-9);
+        assert!((at(50) - 50.5).abs() < 1e-9);
+        assert!((at(99) - 99.01).abs() < 1e-9);
+        assert_eq!(results.median_value, results.percentiles[&50]);
+    }
+
+    #[test]
+    fn long_right_tails_skew_positive_with_a_tail_ratio_above_one() {
+        // Most paths lose a little; a few win big
+        let finals = [90.0, 92.0, 94.0, 95.0, 96.0, 97.0, 98.0, 99.0, 150.0, 300.0];
+        let engine = MonteCarloEngine::builder().capital(100.0).build().unwrap();
+        let results = aggregated(&engine, &ending_at(&finals), 0.95);
+
+        assert!(results.skewness > 1.0);
+        assert!(results.excess_kurtosis > 0.0);
+        // p95 of 232.5 gains 132.5; p5 of 90.9 loses 9.1
+        assert!((results.tail_ratio.unwrap() - 132.5 / 9.1).abs() < 1e-9);
+        assert_eq!(results.median_value, Decimal::new(965, 1));
+        assert_eq!(results.expected_value, Decimal::new(1211, 1));
+
+        let all_gains = aggregated(&engine, &ending_at(&[110.0, 120.0]), 0.95);
+        assert_eq!(all_gains.tail_ratio, None);
+    }
+
+    #[test]
+    fn builder_rejects_invalid_configurations() {
+        let error = |builder: MonteCarloEngineBuilder| builder.build().err().expect("build should fail").to_string();
+        let builder = MonteCarloEngine::builder;
+
+        assert!(error(builder().iterations(0)).contains("at least one iteration"));
+        for confidence in [0.0, 1.0, -0.5, f64::NAN] {
+            assert!(error(builder().confidence(confidence)).contains("Confidence level"));
+        }
+        assert!(error(builder().steps(0)).contains("at least one step"));
+        assert!(error(builder().steps(50).horizons(vec![20, 10])).contains("strictly ascending"));
+        assert!(error(builder().steps(50).horizons(vec![10, 10])).contains("strictly ascending"));
+        assert!(error(builder().steps(50).horizons(vec![10, 60])).contains("past the 50 steps"));
+        assert!(error(builder().horizons(vec![0, 10])).contains("at least one step"));
+        assert!(error(builder().capital(0.0)).contains("Initial capital"));
+        assert!(error(builder().capital(f64::INFINITY)).contains("Initial capital"));
+        assert!(error(builder().time_step(Duration::ZERO)).contains("Time step"));
+        assert!(error(builder().ruin_threshold(120.0)).contains("Ruin threshold"));
+        assert!(error(builder().memory_light().detailed_results()).contains("memory-light"));
+
+        let engine = builder().iterations(5).steps(50).horizons(vec![10, 50]).confidence(0.99).build().unwrap();
+        assert_eq!(engine.confidence_level(), 0.99);
+        assert_eq!(engine.horizons, vec![10, 50]);
+    }
+
+    #[test]
+    fn new_takes_the_builder_defaults() {
+        let engine = MonteCarloEngine::new(5, 3);
+        assert_eq!((engine.iterations, engine.scenarios), (5, 3));
+        assert_eq!(engine.steps_per_iteration, 100);
+        assert_eq!(engine.initial_capital, 1_000_000.0);
+        assert_eq!(engine.confidence_level(), DEFAULT_CONFIDENCE_LEVEL);
+    }
+
+    /// The mock market answering natively through the async trait
+    struct AsyncMock(MockMarketDataProvider);
+
+    #[async_trait::async_trait]
+    impl AsyncMarketDataProvider for AsyncMock {
+        async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+            tokio::task::yield_now().await;
+            MarketDataProvider::get_current_price(&self.0, symbol)
+        }
+
+        async fn get_historical_prices(&self, symbol: &str, days: usize) -> Result<Vec<Decimal>> {
+            MarketDataProvider::get_historical_prices(&self.0, symbol, days)
+        }
+
+        async fn get_volatility(&self, symbol: &str) -> Result<Decimal> {
+            tokio::task::yield_now().await;
+            MarketDataProvider::get_volatility(&self.0, symbol)
+        }
+
+        async fn get_yield_rate(&self, symbol: &str) -> Result<Decimal> {
+            MarketDataProvider::get_yield_rate(&self.0, symbol)
+        }
+
+        async fn get_expected_return(&self, symbol: &str) -> Result<Decimal> {
+            MarketDataProvider::get_expected_return(&self.0, symbol)
+        }
+
+        async fn get_asset_type(&self, symbol: &str) -> Result<AssetType> {
+            MarketDataProvider::get_asset_type(&self.0, symbol)
+        }
+    }
+
+    #[tokio::test]
+    async fn async_and_sync_providers_describe_the_same_universe() {
+        let symbols = ["USDC", "ETH", "BTC", "SOL"];
+        let native = AsyncMock(MockMarketDataProvider::new());
+        let adapted = SyncAdapter::new(MockMarketDataProvider::new());
+        let mut values = vec![];
+        for provider in [&native as &dyn AsyncMarketDataProvider, &adapted] {
+            let mut engine = MonteCarloEngine::new(50, 1)
+                .with_steps(10)
+                .with_seed(9)
+                .with_universe_from_provider(provider, &symbols)
+                .await
+                .unwrap();
+            values.push(engine.run().await.unwrap().expected_value);
+        }
+        assert_eq!(values[0], values[1]);
+
+        let unknown = MonteCarloEngine::new(50, 1).with_universe_from_provider(&adapted, &["DOGE"]).await;
+        assert!(format!("{:#}", unknown.err().unwrap()).contains("Failed to describe the universe"));
+    }
+
+    #[tokio::test]
+    async fn csv_export_reads_back_as_the_run() {
+        let results = engine(Strategy::balanced()).run().await.unwrap();
+        let path = std::env::temp_dir().join(format!("vaulta-paths-{}.csv", uuid::Uuid::new_v4()));
+        results.write_csv(&path).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+
+        assert_eq!(rows.len(), results.paths.len());
+        for (row, summary) in rows.iter().zip(&results.paths) {
+            let number = |i: usize| row[i].parse::<f64>().unwrap();
+            assert_eq!(row[0].parse::<usize>().unwrap(), summary.iteration);
+            assert_eq!(number(1), summary.final_value);
+            assert_eq!(number(2), summary.max_drawdown_pct);
+            assert_eq!(number(3), summary.volatility_pct);
+            assert_eq!(number(4), summary.sharpe_ratio);
+            assert_eq!(number(5), summary.fees);
+            assert_eq!(row[6].parse::<bool>().unwrap(), summary.halted);
+            assert_eq!(row[7].parse::<usize>().ok(), summary.scenario);
+        }
+        let finals: Vec<f64> = rows.iter().map(|row| row[1].parse().unwrap()).collect();
+        assert_eq!(Some(&finals), results.distribution.as_ref());
+
+        // Without per-path statistics, only the distribution's values are written
+        let bare = MonteCarloResults { paths: vec![], ..results.clone() };
+        bare.write_csv(&path).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        let finals: Vec<f64> = rows.iter().map(|row| row[1].parse().unwrap()).collect();
+        assert_eq!(Some(&finals), results.distribution.as_ref());
+        assert!(rows.iter().all(|row| row.iter().skip(2).all(str::is_empty)));
+        std::fs::remove_file(&path).unwrap();
+
+        let light = engine(Strategy::balanced()).with_memory_light().run().await.unwrap();
+        let error = light.write_csv(&path).unwrap_err();
+        assert!(error.to_string().contains("Memory-light"), "{}", error);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn scenario_breakdown_groups_paths_by_class() {
+        let engine = MonteCarloEngine::builder().capital(100.0).build().unwrap();
+        let scenarios = [
+            Scenario { vol_multiplier: 2.0, correlation: 0.7, shocks: vec![(3, dec!(-20))], ..Scenario::default() },
+            Scenario { vol_multiplier: 0.5, ..Scenario::default() },
+            Scenario::default(),
+        ];
+        let shape = RunShape {
+            source: ScenarioSource::Parametric,
+            requested: 4,
+            steps_per_iteration: 10,
+            variance_reduction: VarianceReduction::None,
+            sampling: SamplingMode::PseudoRandom,
+        };
+        let mut tally = RunTally::new(&engine, &shape, &scenarios);
+        let outcomes = [(0, 80.0), (1, 110.0), (2, 100.0), (1, 120.0)];
+        for (iteration, (scenario, final_value)) in outcomes.into_iter().enumerate() {
+            tally.push(&PathSummary {
+                iteration,
+                final_value,
+                min_value: final_value,
+                scenario: Some(scenario),
+                ..PathSummary::default()
+            });
+        }
+        let results = engine.aggregate(&shape, tally, vec![], vec![], 0.95);
+
+        assert_eq!(results.scenarios_generated, 3);
+        let breakdown = &results.scenario_breakdown;
+        assert_eq!(breakdown.len(), 7);
+        let class = |name: &str| &breakdown[name];
+        for (name, paths, expected_value, mean_return_pct, worst_value) in [
+            ("high_vol", 1, dec!(80), -20.0, dec!(80)),
+            ("low_vol", 2, dec!(115), 15.0, dec!(110)),
+            ("normal_vol", 1, dec!(100), 0.0, dec!(100)),
+            ("high_correlation", 1, dec!(80), -20.0, dec!(80)),
+            ("low_correlation", 3, dec!(110), 10.0, dec!(100)),
+            ("shocked", 1, dec!(80), -20.0, dec!(80)),
+            ("no_shock", 3, dec!(110), 10.0, dec!(100)),
+        ] {
+            let stats = class(name);
+            assert_eq!(stats.paths, paths, "{}", name);
+            assert_eq!(stats.probability, paths as f64 / 4.0, "{}", name);
+            assert_eq!(stats.expected_value, expected_value, "{}", name);
+            assert!((stats.mean_return_pct - mean_return_pct).abs() < 1e-9, "{}", name);
+            assert_eq!(stats.worst_value, worst_value, "{}", name);
+        }
+    }
+
+    #[tokio::test]
+    async fn generated_scenarios_cover_every_path_once_per_dimension() {
+        let results = engine(Strategy::balanced()).run().await.unwrap();
+        assert_eq!(results.scenarios_generated, 8);
+        let breakdown = &results.scenario_breakdown;
+        let total = |classes: &[&str]| -> usize {
+            classes.iter().filter_map(|class| breakdown.get(*class)).map(|stats| stats.paths).sum()
+        };
+        assert_eq!(total(&["high_vol", "low_vol", "normal_vol"]), 64);
+        assert_eq!(total(&["high_correlation", "low_correlation"]), 64);
+        assert_eq!(total(&["shocked", "no_shock"]), 64);
+    }
+
+    #[test]
+    fn unsampleable_scenario_distributions_are_rejected() {
+        let build = |distribution: ScenarioDistribution| {
+            MonteCarloEngine::builder().scenario_distribution(distribution).build()
+        };
+        assert!(build(ScenarioDistribution::default()).is_ok());
+        let Err(error) = build(ScenarioDistribution { shock_std_pct: f64::INFINITY, ..Default::default() }) else {
+            panic!("an infinite shock spread was accepted");
+        };
+        assert!(error.to_string().contains("shock"), "{}", error);
+        assert!(build(ScenarioDistribution { drift_shift_mean: f64::NAN, ..Default::default() }).is_err());
+        assert!(build(ScenarioDistribution { shock_probability: 1.5, ..Default::default() }).is_err());
+    }
+
+    #[test]
+    fn failed_paths_are_counted_but_left_out_of_the_histogram() {
+        let engine = MonteCarloEngine::builder().capital(100.0).build().unwrap();
+        let mut paths = ending_at(&[90.0, 100.0, 110.0, 120.0]);
+        paths.push(PathSummary::failed(4));
+        paths.push(PathSummary::failed(5));
+        let results = aggregated(&engine, &paths, 0.95);
+
+        let histogram = results.histogram(Some(3));
+        assert_eq!(histogram.excluded, 2);
+        assert_eq!(histogram.counts, vec![1, 1, 2]);
+        assert_eq!(histogram.edges, vec![90.0, 100.0, 110.0, 120.0]);
+    }
+
+    #[tokio::test]
+    async fn progress_is_reported_every_interval_and_once_at_the_end() {
+        let reports = |interval: usize| {
+            let calls = Arc::new(Mutex::new(vec![]));
+            let recorded = calls.clone();
+            let engine = MonteCarloEngine::builder()
+                .iterations(64)
+                .steps(5)
+                .progress(interval, move |completed, total| recorded.lock().unwrap().push((completed, total)))
+                .build()
+                .unwrap();
+            (engine, calls)
+        };
+
+        let (mut engine, calls) = reports(10);
+        engine.run().await.unwrap();
+        // Workers race to the lock, so calls may arrive out of order
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort();
+        let expected: Vec<(usize, usize)> = [10, 20, 30, 40, 50, 60, 64].into_iter().map(|done| (done, 64)).collect();
+        assert_eq!(calls, expected);
+
+        // An interval that divides the run reports the last path only once
+        let (mut engine, calls) = reports(16);
+        engine.run().await.unwrap();
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort();
+        assert_eq!(calls, vec![(16, 64), (32, 64), (48, 64), (64, 64)]);
+    }
+
+    #[tokio::test]
+    async fn a_cancelled_run_returns_the_paths_completed_so_far() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let trigger = cancel.clone();
+        let mut engine = MonteCarloEngine::builder()
+            .iterations(5_000)
+            .steps(5)
+            .progress(1, move |completed, _| {
+                if completed >= 20 {
+                    trigger.store(true, Ordering::Relaxed);
+                }
+            })
+            .cancellation(cancel.clone())
+            .build()
+            .unwrap();
+        let results = engine.run().await.unwrap();
+
+        assert!(results.incomplete);
+        assert!((20..5_000).contains(&results.iterations), "{} paths completed", results.iterations);
+        assert_eq!(results.paths.len(), results.iterations);
+        let finals = results.distribution.as_ref().unwrap();
+        assert_eq!(finals.len(), results.iterations);
+        let mean = finals.iter().sum::<f64>() / finals.len() as f64;
+        assert!((results.expected_value.to_f64().unwrap() - mean).abs() < 1e-6);
+        let mut iterations: Vec<usize> = results.paths.iter().map(|path| path.iteration).collect();
+        iterations.sort();
+        iterations.dedup();
+        assert_eq!(iterations.len(), results.iterations);
+        assert!(iterations.iter().all(|&i| i < 5_000));
+
+        // Cancelled before it starts, a run completes nothing
+        let mut engine = MonteCarloEngine::builder().iterations(100).cancellation(cancel).build().unwrap();
+        let results = engine.run().await.unwrap();
+        assert!(results.incomplete);
+        assert_eq!(results.iterations, 0);
+        assert_eq!(results.expected_value, Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    async fn a_strategy_compared_with_itself_ties_on_every_path() {
+        let mut engine = engine(Strategy::conservative());
+        let comparison = engine.compare(vec![Strategy::balanced(), Strategy::balanced()], 0.95).await.unwrap();
+
+        let [first, second] = &comparison.results[..] else {
+            panic!("expected one run per strategy");
+        };
+        assert_eq!(first.distribution, second.distribution);
+        assert_eq!(comparison.pairwise.len(), 2);
+        for pair in &comparison.pairwise {
+            assert_eq!(pair.paths, 64);
+            // Ties count for neither side
+            assert_eq!(pair.prob_a_beats_b, 0.0);
+            assert_eq!(pair.mean_difference, 0.0);
+            assert_eq!(pair.cvar_difference, Decimal::ZERO);
+        }
+        assert_eq!(engine.strategy.name(), Strategy::conservative().name());
+    }
+
+    #[tokio::test]
+    async fn pairwise_comparisons_mirror_each_other() {
+        let mut engine = engine(Strategy::balanced());
+        let comparison = engine
+            .compare(vec![Strategy::conservative(), Strategy::aggressive()], 0.95)
+            .await
+            .unwrap();
+        let conservative = Strategy::conservative().name().to_string();
+        let aggressive = Strategy::aggressive().name().to_string();
+        let ab = comparison.pairwise(&conservative, &aggressive).unwrap();
+        let ba = comparison.pairwise(&aggressive, &conservative).unwrap();
+        assert_eq!(ab.mean_difference, -ba.mean_difference);
+        assert_eq!(ab.cvar_difference, -ba.cvar_difference);
+        assert!(ab.prob_a_beats_b + ba.prob_a_beats_b <= 1.0);
+        let expected = comparison.results[0].expected_value - comparison.results[1].expected_value;
+        assert!((ab.mean_difference - expected.to_f64().unwrap()).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn a_failed_comparison_restores_the_engine_strategy() {
+        // A horizon past the end of the run fails every strategy's run
+        let mut engine = engine(Strategy::conservative()).with_horizons(vec![100]);
+        let error = engine
+            .compare(vec![Strategy::balanced(), Strategy::aggressive()], 0.95)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Horizon 100"), "{}", error);
+        assert_eq!(engine.strategy.name(), Strategy::conservative().name());
+
+        assert!(engine.compare(vec![Strategy::balanced()], 0.95).await.is_err());
+        assert_eq!(engine.strategy.name(), Strategy::conservative().name());
+    }
+
+    #[tokio::test]
+    async fn a_stream_folded_by_hand_matches_the_stress_test() {
+        let mut engine = engine(Strategy::balanced());
+        let streamed: Vec<PathSummary> = engine.stream_paths().unwrap().collect();
+        let results = engine.run_stress_test(0.95).await.unwrap();
+
+        assert_eq!(streamed.len(), results.iterations);
+        assert_eq!(serde_json::to_value(&streamed).unwrap(), serde_json::to_value(&results.paths).unwrap());
+        assert!(streamed.iter().map(|path| path.iteration).eq(0..64));
+        let mean = streamed.iter().map(|path| path.final_value).sum::<f64>() / streamed.len() as f64;
+        assert_eq!(results.expected_value, Decimal::try_from(mean).unwrap());
+        let losing = streamed.iter().filter(|path| path.final_value < 250_000.0).count();
+        assert_eq!(results.prob_of_loss, losing as f64 / 64.0);
+    }
+
+    #[test]
+    fn dropping_a_stream_stops_the_run() {
+        let completed = Arc::new(AtomicUsize::new(0));
+        let counter = completed.clone();
+        let engine = MonteCarloEngine::builder()
+            .iterations(100_000)
+            .steps(5)
+            .progress(1, move |_, _| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .build()
+            .unwrap();
+
+        let mut stream = engine.stream_paths().unwrap();
+        assert_eq!(stream.requested(), 100_000);
+        assert!(stream.next().is_some());
+        drop(stream);
+        // Only the first chunk ran, and nothing keeps running after the drop
+        let chunk = rayon::current_num_threads() * PATHS_PER_THREAD;
+        let ran = completed.load(Ordering::Relaxed);
+        assert_eq!(ran, chunk.min(100_000));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(completed.load(Ordering::Relaxed), ran);
+    }
+
+    #[tokio::test]
+    async fn supplied_paths_run_to_their_own_ends() {
+        let mut engine = all_in(dec!(0.5)).capital(1000.0).horizons(vec![1, 3]).build().unwrap();
+        let paths = vec![
+            PricePath::new("rally").with_series("X", vec![dec!(2), dec!(4), dec!(3)]),
+            // X's series ends after two steps; the path runs on for Z's five
+            PricePath::new("uneven")
+                .with_series("X", vec![dec!(1.5), dec!(2)])
+                .with_series("Z", vec![Decimal::ONE; 5]),
+            // X never trades away from its listed price of 1
+            PricePath::new("absent").with_series("Z", vec![Decimal::ONE; 2]),
+        ];
+        let results = engine.run_with_paths(paths, 0.95).await.unwrap();
+
+        assert_eq!(results.iterations, 3);
+        assert_eq!(results.steps_per_iteration, 5);
+        assert_eq!(results.scenario_source, ScenarioSource::Supplied);
+        // The whole book goes into X at its first price, paying 20 bps on what it buys
+        let invested = 1000.0 / 1.002;
+        let expected = [invested * 3.0 / 2.0, invested * 2.0 / 1.5, invested];
+        for (path, expected) in results.paths.iter().zip(expected) {
+            assert!((path.final_value - expected).abs() < 1e-6, "path {}: {}", path.iteration, path.final_value);
+            assert!((path.min_value - invested).abs() < 1e-6);
+            assert!((path.fees - (1000.0 - invested)).abs() < 1e-6);
+            // By step 3 every path has stopped moving
+            assert!((path.horizon_values[1] - expected).abs() < 1e-6);
+        }
+        // From 4 down to 3
+        assert!((results.paths[0].max_drawdown_pct - 25.0).abs() < 1e-6);
+        let mean = expected.iter().sum::<f64>() / 3.0;
+        assert!((results.expected_value.to_f64().unwrap() - mean).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn the_last_horizon_matches_the_end_of_the_run() {
+        // Sorted and deduplicated
+        let mut engine = engine(Strategy::aggressive()).with_horizons(vec![30, 10, 20, 10]);
+        assert_eq!(engine.horizons, vec![10, 20, 30]);
+        let results = engine.run().await.unwrap();
+
+        let steps: Vec<usize> = results.horizons.iter().map(|horizon| horizon.step).collect();
+        assert_eq!(steps, vec![10, 20, 30]);
+        let last = &results.horizons[2];
+        assert_eq!(last.expected_value, results.expected_value);
+        assert_eq!(last.value_at_risk, results.value_at_risk);
+        assert_eq!(last.conditional_var, results.conditional_var);
+        assert_eq!(last.var_pct, results.var_pct);
+        assert_eq!(last.prob_of_loss, results.prob_of_loss);
+        assert!(
+            (last.mean_drawdown_pct - results.drawdown.mean_pct).abs() < 1e-9,
+            "{} vs {}",
+            last.mean_drawdown_pct,
+            results.drawdown.mean_pct
+        );
+        // Drawdown to date can only grow
+        assert!(results.horizons.windows(2).all(|pair| pair[0].mean_drawdown_pct <= pair[1].mean_drawdown_pct));
+    }
+
+    #[tokio::test]
+    async fn horizons_outside_the_run_are_rejected() {
+        let error = |horizons: Vec<usize>, steps: usize| {
+            let engine = engine(Strategy::balanced()).with_horizons(horizons).with_steps(steps);
+            engine.stream_paths().err().expect("run should fail").to_string()
+        };
+        assert!(error(vec![10, 31], 30).contains("Horizon 31 is past the 30 steps"));
+        assert!(error(vec![0, 10], 30).contains("at least one step"));
+        // Shortening the run after setting horizons leaves them out of range
+        assert!(error(vec![20], 10).contains("past the 10 steps"));
+    }
+
+    #[tokio::test]
+    async fn goal_attainment_on_known_paths() {
+        let mut engine = all_in(dec!(0.5)).capital(1000.0).target(1400.0).floor(900.0).build().unwrap();
+        // Everything goes into X at 1 on the first step, leaving 998 after costs
+        let path = |prices: &[Decimal]| PricePath::new("").with_series("X", prices.to_vec());
+        let paths = vec![
+            // Reaches 1497 on step 3, then gives some back
+            path(&[dec!(1), dec!(1.2), dec!(1.5), dec!(1.1)]),
+            // Dips to 798 before reaching 1597 on step 3
+            path(&[dec!(1), dec!(0.8), dec!(1.6)]),
+            // Reaches 1447 on step 2
+            path(&[dec!(1), dec!(1.45)]),
+            // Never recovers from 848
+            path(&[dec!(1), dec!(0.85), dec!(0.95), dec!(1), dec!(1)]),
+        ];
+        let results = engine.run_with_paths(paths, 0.95).await.unwrap();
+
+        let hits: Vec<Option<usize>> = results.paths.iter().map(|path| path.target_hit_step).collect();
+        assert_eq!(hits, vec![Some(3), Some(3), Some(2), None]);
+        let goal = results.goal.unwrap();
+        assert_eq!(goal.target_value, Some(1400.0));
+        assert_eq!(goal.floor_value, Some(900.0));
+        assert_eq!(goal.prob_hit_target, Some(0.75));
+        assert_eq!(goal.prob_breach_floor, Some(0.5));
+        let passage = goal.time_to_target.unwrap();
+        assert!((passage.mean_steps - 8.0 / 3.0).abs() < 1e-12);
+        assert_eq!(passage.median_steps, 3.0);
+        assert_eq!(passage.p90_steps, 3.0);
+        assert_eq!(passage.histogram.counts.iter().sum::<usize>(), 3);
+
+        // A target no path reaches has no passage times; without a floor there is no breach probability
+        let mut engine = all_in(dec!(0.5)).capital(1000.0).target(5000.0).build().unwrap();
+        let results = engine.run_with_paths(vec![path(&[dec!(1), dec!(2)])], 0.95).await.unwrap();
+        let goal = results.goal.unwrap();
+        assert_eq!(goal.prob_hit_target, Some(0.0));
+        assert!(goal.time_to_target.is_none());
+        assert_eq!(goal.prob_breach_floor, None);
+    }
+
+    /// Run `engine` and summarize it, for comparison against a sweep point
+    async fn summary(mut engine: MonteCarloEngine) -> serde_json::Value {
+        let results = engine.run_stress_test(0.95).await.unwrap();
+        serde_json::to_value(MonteCarloSummary::from(&results)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn each_sweep_point_reruns_the_same_paths_with_one_value_changed() {
+        let mut sweep = engine(Strategy::balanced());
+        let spec = SweepSpec::new(SweepParameter::AllocationFraction, 0.25, 0.75, 3);
+        let results = sweep.sensitivity(spec, 0.95).await.unwrap();
+        let values: Vec<f64> = results.points.iter().map(|(value, _)| *value).collect();
+        assert_eq!(values, vec![0.25, 0.5, 0.75]);
+        for (value, point) in &results.points {
+            let strategy = Strategy::balanced().with_allocation_fraction(Decimal::try_from(*value).unwrap()).unwrap();
+            assert_eq!(serde_json::to_value(point).unwrap(), summary(engine(strategy)).await);
+        }
+
+        let spec = SweepSpec::new(SweepParameter::VolatilityMultiplier, 0.5, 2.0, 2);
+        let results = sweep.sensitivity(spec, 0.95).await.unwrap();
+        for (value, point) in &results.points {
+            let mut fresh = engine(Strategy::balanced());
+            fresh.simulator_config.volatility_multiplier = *value;
+            assert_eq!(serde_json::to_value(point).unwrap(), summary(fresh).await);
+        }
+        // More volatility, wider tails
+        assert!(results.points[1].1.value_at_risk > results.points[0].1.value_at_risk);
+
+        let spec = SweepSpec::new(SweepParameter::FeeBps, 0.0, 100.0, 3);
+        let results = sweep.sensitivity(spec, 0.95).await.unwrap();
+        for (value, point) in &results.points {
+            let mut fresh = engine(Strategy::balanced());
+            fresh.simulator_config.fee_model = Some(Arc::new(FlatBps::new(Decimal::try_from(*value).unwrap())));
+            assert_eq!(serde_json::to_value(point).unwrap(), summary(fresh).await);
+        }
+        // The same paths, each paying more to trade
+        let expected: Vec<Decimal> = results.points.iter().map(|(_, point)| point.expected_value).collect();
+        assert!(expected.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", expected);
+
+        // The sweeps left the engine as they found it
+        assert_eq!(sweep.strategy.allocation_fraction(), Strategy::balanced().allocation_fraction());
+        assert_eq!(sweep.simulator_config.volatility_multiplier, 1.0);
+        assert!(sweep.simulator_config.fee_model.is_none());
+        assert_eq!(summary(sweep).await, summary(engine(Strategy::balanced())).await);
+    }
+
+    #[tokio::test]
+    async fn invalid_sweeps_are_rejected() {
+        let mut sweep = engine(Strategy::balanced());
+        for spec in [
+            SweepSpec::new(SweepParameter::AllocationFraction, 0.0, 0.5, 3),
+            SweepSpec::new(SweepParameter::AllocationFraction, 0.5, 1.5, 3),
+            SweepSpec::new(SweepParameter::VolatilityMultiplier, -1.0, 1.0, 3),
+            SweepSpec::new(SweepParameter::FeeBps, 0.0, f64::INFINITY, 3),
+            SweepSpec::new(SweepParameter::FeeBps, 0.0, 10.0, 0),
+        ] {
+            assert!(sweep.sensitivity(spec, 0.95).await.is_err());
+        }
+
+        let mut targets = all_in(dec!(0.5)).build().unwrap();
+        let spec = SweepSpec::new(SweepParameter::AllocationFraction, 0.25, 0.75, 3);
+        let error = targets.sensitivity(spec, 0.95).await.unwrap_err();
+        assert!(error.to_string().contains("no allocation fraction"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn sensitivity_csv_has_one_row_per_value() {
+        let mut sweep = engine(Strategy::balanced());
+        let spec = SweepSpec::new(SweepParameter::FeeBps, 10.0, 30.0, 3);
+        let results = sweep.sensitivity(spec, 0.95).await.unwrap();
+        let path = std::env::temp_dir().join(format!("vaulta-sensitivity-{}.csv", uuid::Uuid::new_v4()));
+        results.write_csv(&path).unwrap();
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        assert_eq!(&headers[0], "fee_bps");
+        assert_eq!(&headers[2], "expected_value");
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 3);
+        for (row, (value, point)) in rows.iter().zip(&results.points) {
+            assert_eq!(row[0].parse::<f64>().unwrap(), *value);
+            assert_eq!(row[1].parse::<usize>().unwrap(), point.iterations);
+            assert_eq!(row[2].parse::<Decimal>().unwrap(), point.expected_value);
+            assert_eq!(row[5].parse::<Decimal>().unwrap(), point.value_at_risk);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn loss_and_ruin_are_strict_at_their_thresholds() {
+        let engine = MonteCarloEngine::builder().capital(100.0).ruin_threshold(40.0).build().unwrap();
+        let path = |iteration: usize, min_value: f64, final_value: f64| PathSummary {
+            iteration,
+            final_value,
+            min_value,
+            ..PathSummary::default()
+        };
+        let paths = vec![
+            // Ends exactly where it started: no loss
+            path(0, 100.0, 100.0),
+            // Ends a hair below: a loss
+            path(1, 99.99, 99.99),
+            // Touches the ruin level of 40 without going below it, then recovers
+            path(2, 40.0, 120.0),
+            // Dips just below it, then recovers: ruined but not losing
+            path(3, 39.99, 110.0),
+            // Ruined and losing
+            path(4, 10.0, 60.0),
+        ];
+        let results = aggregated(&engine, &paths, 0.95);
+        assert_eq!(results.prob_of_loss, 0.4);
+        assert_eq!(results.prob_of_ruin, 0.4);
+        assert_eq!(results.ruin_threshold_pct, 40.0);
+
+        // A failed path ends at zero and counts as both
+        let mut paths = paths;
+        paths.push(PathSummary::failed(5));
+        let results = aggregated(&engine, &paths, 0.95);
+        assert_eq!(results.prob_of_loss, 0.5);
+        assert_eq!(results.prob_of_ruin, 0.5);
+
+        // A threshold of zero can never be breached; one of 100 catches any dip
+        let never = MonteCarloEngine::builder().capital(100.0).ruin_threshold(0.0).build().unwrap();
+        assert_eq!(aggregated(&never, &paths, 0.95).prob_of_ruin, 0.0);
+        let any_dip = MonteCarloEngine::builder().capital(100.0).ruin_threshold(100.0).build().unwrap();
+        assert_eq!(aggregated(&any_dip, &paths, 0.95).prob_of_ruin, 5.0 / 6.0);
+    }
+
+    #[test]
+    fn jump_statistics_cover_the_paths_that_jumped() {
+        let engine = MonteCarloEngine::builder().capital(100.0).build().unwrap();
+        let paths: Vec<PathSummary> = [(2, 70.0), (1, 110.0), (0, 90.0), (0, 100.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(iteration, (jumps, final_value))| PathSummary {
+                iteration,
+                final_value,
+                min_value: final_value,
+                jumps,
+                ..PathSummary::default()
+            })
+            .collect();
+        let results = aggregated(&engine, &paths, 0.95);
+        assert_eq!(results.jump_probability, 0.5);
+        // A 30% loss and a 10% gain
+        assert_eq!(results.loss_given_jump_pct, Some(10.0));
+
+        let calm = aggregated(&engine, &ending_at(&[90.0, 110.0]), 0.95);
+        assert_eq!(calm.jump_probability, 0.0);
+        assert_eq!(calm.loss_given_jump_pct, None);
+    }
+
+    #[tokio::test]
+    async fn a_certain_market_jump_lands_on_every_path() {
+        let crash = JumpModel::crash(1.0, 30.0, 0.0);
+        let jumps = JumpConfig::default().with_market(crash, vec![]);
+        // No scenarios, so the crash is the only price move
+        let builder = all_in(dec!(0)).scenarios(0).iterations(16).seed(7).steps(2);
+        let mut engine = builder.jumps(jumps).build().unwrap();
+        let results = engine.run().await.unwrap();
+        assert_eq!(results.jump_probability, 1.0);
+        // Bought after the first step's crash, then 30% lost to the second, plus trading costs
+        let loss = results.loss_given_jump_pct.unwrap();
+        assert!(loss > 30.0 && loss < 31.0, "{}", loss);
     }
 }
@@ -1,45 +1,173 @@
-use crate::types::*;
-use crate::simulator::Simulator;
+use crate::simulator::{Simulator, SimulatorConfig};
 use crate::strategy::Strategy;
 use anyhow::Result;
+use rayon::prelude::*;
 use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 
-/// Strategy optimizer using genetic algorithms
+/// Allocation fractions tried by [`StrategyOptimizer::candidates`]
+const ALLOCATION_GRID: [Decimal; 4] = [dec!(0.25), dec!(0.5), dec!(0.75), dec!(1.0)];
+
+/// Strategy optimizer searching a grid of strategy variants
 pub struct StrategyOptimizer {
-    population_size: usize,
-    generations: usize,
-    mutation_rate: f64,
+    initial_capital: f64,
+    steps: usize,
+    seed: u64,
+}
+
+impl Default for StrategyOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StrategyOptimizer {
     pub fn new() -> Self {
         Self {
-            population_size: 50,
-            generations: 100,
-            mutation_rate: 0.1,
+            initial_capital: 1_000_000.0,
+            steps: 100,
+            seed: 42,
         }
     }
-    
-    /// Optimize strategy parameters
+
+    /// The fittest of [`candidates`](Self::candidates), keeping the strategy
+    /// itself unless a variant beats it
     pub fn optimize(&self, initial_strategy: Strategy) -> Result<Strategy> {
-        // Simplified optimization
-        // In full implementation, we'd use genetic algorithms to evolve parameters
-        Ok(initial_strategy)
-    }
-    
-    /// Evaluate fitness of a strategy
-    fn evaluate_fitness(&self, strategy: &Strategy) -> f64 {
-        // Run simulation and calculate fitness based on Sharpe ratio
-        let initial_capital = 1_000_000.0;
-        let mut simulator = Simulator::new(initial_capital, strategy.clone());
-        
-        for _ in 0..100 {
+        let candidates = self.candidates(&initial_strategy);
+        let fitness = self.evaluate_population(&candidates);
+        let best = (1..fitness.len()).fold(0, |best, i| if fitness[i] > fitness[best] { i } else { best });
+        Ok(candidates.into_iter().nth(best).unwrap_or(initial_strategy))
+    }
+
+    /// Variants of `strategy` worth evaluating: the strategy itself, then one per
+    /// allocation fraction on a fixed grid when the strategy has one
+    pub fn candidates(&self, strategy: &Strategy) -> Vec<Strategy> {
+        let mut candidates = vec![strategy.clone()];
+        let Some(current) = strategy.allocation_fraction() else {
+            return candidates;
+        };
+        for fraction in ALLOCATION_GRID {
+            if fraction != current {
+                if let Ok(candidate) = strategy.clone().with_allocation_fraction(fraction) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Evaluate fitness for every candidate in parallel.
+    ///
+    /// Each worker thread keeps one simulator and resets it between candidates
+    /// instead of allocating a new one. All candidates share the same seed so
+    /// fitness differences come from the strategy, not the price paths.
+    pub fn evaluate_population(&self, candidates: &[Strategy]) -> Vec<f64> {
+        candidates
+            .par_iter()
+            .map_init(
+                || Simulator::with_config(self.initial_capital, Strategy::balanced(), self.simulator_config()),
+                |simulator, strategy| {
+                    simulator.reset(self.initial_capital, strategy.clone());
+                    self.run_fitness(simulator)
+                },
+            )
+            .collect()
+    }
+
+    fn run_fitness(&self, simulator: &mut Simulator) -> f64 {
+        for _ in 0..self.steps {
             if simulator.step().is_err() {
                 return 0.0;
             }
         }
-        
-        let results = simulator.finalize();
+
+        let results = simulator.current_results();
         results.sharpe_ratio.max(0.0)
     }
+
+    fn simulator_config(&self) -> SimulatorConfig {
+        SimulatorConfig {
+            seed: Some(self.seed),
+            ..SimulatorConfig::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SimulationResults;
+    use time::macros::datetime;
+
+    fn config() -> SimulatorConfig {
+        SimulatorConfig {
+            seed: Some(7),
+            start_time: Some(datetime!(2024-01-01 00:00 UTC)),
+            ..SimulatorConfig::default()
+        }
+    }
+
+    fn run(simulator: &mut Simulator, steps: usize) -> SimulationResults {
+        for _ in 0..steps {
+            simulator.step().expect("step failed");
+        }
+        simulator.current_results()
+    }
+
+    #[test]
+    fn reset_simulator_matches_a_fresh_one() {
+        let fresh = run(&mut Simulator::with_config(1_000_000.0, Strategy::balanced(), config()), 60);
+        assert!(fresh.income_earned > Decimal::ZERO, "the run should earn yield for the reset to clear");
+
+        let mut reused = Simulator::with_config(250_000.0, Strategy::aggressive(), config());
+        run(&mut reused, 90);
+        reused.reset(1_000_000.0, Strategy::balanced());
+        let reused = run(&mut reused, 60);
+        assert_eq!(serde_json::to_value(&fresh).unwrap(), serde_json::to_value(&reused).unwrap());
+    }
+
+    #[test]
+    fn one_reused_simulator_matches_fresh_ones_over_a_hundred_candidates() {
+        let optimizer = StrategyOptimizer::new();
+        let bases = [
+            Strategy::conservative(),
+            Strategy::balanced(),
+            Strategy::aggressive(),
+            Strategy::yield_maximizer(),
+            Strategy::risk_parity(),
+        ];
+        let pool: Vec<Strategy> = bases.iter().flat_map(|base| optimizer.candidates(base)).collect();
+
+        let mut reused = Simulator::with_config(1_000_000.0, Strategy::balanced(), config());
+        for evaluation in 0..100u64 {
+            let strategy = pool[evaluation as usize % pool.len()].clone();
+            let capital = 100_000.0 * (1 + evaluation % 7) as f64;
+            let seeded = SimulatorConfig { seed: Some(evaluation), ..config() };
+            let fresh = run(&mut Simulator::with_config(capital, strategy.clone(), seeded), 30);
+
+            reused.reset_with_seed(capital, strategy, evaluation);
+            let again = run(&mut reused, 30);
+            let (fresh, again) = (serde_json::to_value(&fresh).unwrap(), serde_json::to_value(&again).unwrap());
+            assert_eq!(fresh, again, "evaluation {}", evaluation);
+        }
+    }
+
+    #[test]
+    fn population_fitness_matches_one_at_a_time() {
+        let optimizer = StrategyOptimizer::new();
+        let candidates = optimizer.candidates(&Strategy::balanced());
+        assert!(candidates.len() > 1, "the balanced strategy should have allocation variants");
+        let alone: Vec<f64> =
+            candidates.iter().map(|strategy| optimizer.evaluate_population(std::slice::from_ref(strategy))[0]).collect();
+        assert_eq!(optimizer.evaluate_population(&candidates), alone);
+    }
+
+    #[test]
+    fn optimize_picks_the_fittest_candidate() {
+        let optimizer = StrategyOptimizer::new();
+        let candidates = optimizer.candidates(&Strategy::balanced());
+        let best = optimizer.evaluate_population(&candidates).into_iter().fold(f64::MIN, f64::max);
+        let chosen = optimizer.optimize(Strategy::balanced()).unwrap();
+        assert_eq!(optimizer.evaluate_population(&[chosen])[0], best);
+    }
 }
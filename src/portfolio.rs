@@ -1,5 +1,12 @@
+use crate::risk::RiskReport;
 use crate::types::*;
+use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use time::OffsetDateTime;
+use tracing::warn;
 
 /// Portfolio analysis and optimization utilities
 pub struct PortfolioAnalyzer;
@@ -11,9 +18,6 @@ impl PortfolioAnalyzer {
             return 0.0;
         }
         
-        let position_count = portfolio.positions.len();
-        let max_score = 1.0 - (1.0 / position_count as f64);
-        
         // Check position size distribution
         let position_sizes: Vec<f64> = portfolio.positions
             .values()
@@ -83,4 +87,384 @@ impl PortfolioAnalyzer {
         
         weighted_volatility
     }
+    
+    /// Weight of each position as a fraction of total portfolio value
+    pub fn exposure_breakdown(portfolio: &Portfolio) -> HashMap<String, Decimal> {
+        let total_value = portfolio.total_value;
+        if total_value <= Decimal::ZERO {
+            return HashMap::new();
+        }
+        
+        portfolio.positions
+            .iter()
+            .map(|(symbol, p)| (symbol.clone(), p.current_value / total_value))
+            .collect()
+    }
+    
+    /// Positions whose weight exceeds `max_position_size_pct` of total value
+    pub fn concentration_breaches(
+        portfolio: &Portfolio,
+        max_position_size_pct: f64,
+    ) -> Vec<ConcentrationBreach> {
+        let mut breaches: Vec<ConcentrationBreach> = Self::exposure_breakdown(portfolio)
+            .into_iter()
+            .filter_map(|(symbol, weight)| {
+                let weight_pct = weight.to_f64().unwrap_or(0.0) * 100.0;
+                (weight_pct > max_position_size_pct).then(|| ConcentrationBreach {
+                    symbol,
+                    weight_pct,
+                    limit_pct: max_position_size_pct,
+                    contributions: vec![],
+                })
+            })
+            .collect();
+        
+        breaches.sort_by(|a, b| b.weight_pct.partial_cmp(&a.weight_pct).unwrap());
+        breaches
+    }
+}
+
+/// A single book's share of a firm-level position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookContribution {
+    pub book: String,
+    pub quantity: Decimal,
+    pub value: Decimal,
+    pub share: Decimal,
+}
+
+/// Firm-level view over several portfolios with per-book drill-down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregatedPortfolio {
+    pub portfolio: Portfolio,
+    pub books: Vec<String>,
+    pub book_cash: HashMap<String, Decimal>,
+    pub contributions: HashMap<String, Vec<BookContribution>>,
+}
+
+/// Concentration limit violation with the books that make up the position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConcentrationBreach {
+    pub symbol: String,
+    pub weight_pct: f64,
+    pub limit_pct: f64,
+    pub contributions: Vec<BookContribution>,
+}
+
+/// Merge several books into a firm-level portfolio.
+///
+/// Same-symbol positions are netted into one position. Every book's quantity is
+/// valued at one mark, the price in the book with the latest timestamp, the
+/// first such book on a tie. Volatility, yields and other figures come from the
+/// first book holding the symbol. Books disagreeing on what the asset is (its
+/// name or type) are a conflict: the first book's asset is kept with a warning.
+/// Use [`aggregate_strict`] to reject conflicts.
+pub fn aggregate(books: &[(&str, &Portfolio)]) -> AggregatedPortfolio {
+    aggregate_with(books, false).expect("non-strict aggregation never fails")
+}
+
+/// Same as [`aggregate`], but errors on conflicting asset names or types
+pub fn aggregate_strict(books: &[(&str, &Portfolio)]) -> Result<AggregatedPortfolio> {
+    aggregate_with(books, true)
+}
+
+fn aggregate_with(books: &[(&str, &Portfolio)], strict: bool) -> Result<AggregatedPortfolio> {
+    let mut cash = Decimal::ZERO;
+    let mut foreign_cash: HashMap<String, Decimal> = HashMap::new();
+    let mut fx_rates = HashMap::new();
+    let mut book_cash = HashMap::new();
+    let mut timestamp = None;
+    let mut defaulted = vec![];
+    let mut merged: HashMap<String, Position> = HashMap::new();
+    let mut marks: HashMap<String, (OffsetDateTime, Decimal)> = HashMap::new();
+    let mut quantities: HashMap<String, Vec<(String, Decimal)>> = HashMap::new();
+    
+    for (name, book) in books {
+        cash += book.cash;
+        book_cash.insert(name.to_string(), book.cash);
+        for (currency, balance) in &book.foreign_cash {
+            *foreign_cash.entry(currency.clone()).or_default() += *balance;
+        }
+        for (currency, rate) in &book.fx_rates {
+            fx_rates.entry(currency.clone()).or_insert(*rate);
+        }
+        timestamp = timestamp.max(Some(book.timestamp));
+        defaulted.extend(book.defaulted.iter().cloned());
+        
+        for (symbol, position) in &book.positions {
+            let mark = (book.timestamp, position.asset.current_price);
+            marks
+                .entry(symbol.clone())
+                .and_modify(|latest| if mark.0 > latest.0 { *latest = mark })
+                .or_insert(mark);
+            
+            match merged.get_mut(symbol) {
+                Some(existing) => {
+                    if !same_asset(&existing.asset, &position.asset) {
+                        if strict {
+                            return Err(anyhow::anyhow!(
+                                "Conflicting asset name or type for {} in book {}",
+                                symbol,
+                                name
+                            ));
+                        }
+                        warn!("Conflicting asset name or type for {} in book {}, keeping first book's", symbol, name);
+                    }
+                    
+                    // Quantity-weighted average entry price
+                    let total_quantity = existing.quantity + position.quantity;
+                    if total_quantity > Decimal::ZERO {
+                        existing.entry_price = (existing.entry_price * existing.quantity
+                            + position.entry_price * position.quantity)
+                            / total_quantity;
+                    }
+                    existing.quantity = total_quantity;
+                }
+                None => {
+                    merged.insert(symbol.clone(), position.clone());
+                }
+            }
+            
+            quantities
+                .entry(symbol.clone())
+                .or_default()
+                .push((name.to_string(), position.quantity));
+        }
+    }
+    
+    let mut portfolio = Portfolio::new(cash);
+    if let Some(timestamp) = timestamp {
+        portfolio.timestamp = timestamp;
+    }
+    portfolio.defaulted = defaulted;
+    portfolio.foreign_cash = foreign_cash;
+    portfolio.fx_rates = fx_rates;
+    
+    let mut contributions = HashMap::new();
+    for (symbol, mut position) in merged {
+        let price = marks.get(&symbol).map_or(position.asset.current_price, |(_, price)| *price);
+        position.update_price(price);
+        
+        let book_contributions = quantities
+            .remove(&symbol)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(book, quantity)| BookContribution {
+                book,
+                quantity,
+                value: quantity * price,
+                share: if position.quantity > Decimal::ZERO {
+                    quantity / position.quantity
+                } else {
+                    Decimal::ZERO
+                },
+            })
+            .collect();
+        
+        contributions.insert(symbol.clone(), book_contributions);
+        portfolio.positions.insert(symbol, position);
+    }
+    portfolio.update_total_value();
+    
+    Ok(AggregatedPortfolio {
+        portfolio,
+        books: books.iter().map(|(name, _)| name.to_string()).collect(),
+        book_cash,
+        contributions,
+    })
+}
+
+/// Whether two books' assets are the same instrument; market figures such as
+/// the price may differ between books
+fn same_asset(a: &Asset, b: &Asset) -> bool {
+    a.name == b.name && a.asset_type == b.asset_type
+}
+
+impl AggregatedPortfolio {
+    /// Per-book breakdown of a firm-level position
+    pub fn drill_down(&self, symbol: &str) -> &[BookContribution] {
+        self.contributions
+            .get(symbol)
+            .map(|c| c.as_slice())
+            .unwrap_or(&[])
+    }
+    
+    /// Firm-level exposure per symbol as a fraction of total value
+    pub fn exposure_breakdown(&self) -> HashMap<String, Decimal> {
+        PortfolioAnalyzer::exposure_breakdown(&self.portfolio)
+    }
+    
+    /// Firm-level concentration breaches, each with its per-book drill-down
+    pub fn concentration_breaches(&self, max_position_size_pct: f64) -> Vec<ConcentrationBreach> {
+        let mut breaches = PortfolioAnalyzer::concentration_breaches(&self.portfolio, max_position_size_pct);
+        for breach in &mut breaches {
+            breach.contributions = self.drill_down(&breach.symbol).to_vec();
+        }
+        breaches
+    }
+    
+    /// Firm-level risk report whose concentration breaches drill down per book.
+    /// Fails for a confidence outside (0.5, 1), as [`RiskReport::new`] does.
+    pub fn risk_report(
+        &self,
+        confidence: f64,
+        time_horizon_days: usize,
+        max_position_size_pct: f64,
+    ) -> Result<RiskReport> {
+        Ok(RiskReport {
+            concentration_breaches: self.concentration_breaches(max_position_size_pct),
+            ..RiskReport::new(&self.portfolio, confidence, time_horizon_days, max_position_size_pct)?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use time::Duration;
+
+    fn asset(symbol: &str, price: Decimal) -> Asset {
+        Asset {
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            asset_type: AssetType::Crypto,
+            current_price: price,
+            volatility: dec!(0.6),
+            yield_rate: Decimal::ZERO,
+            expected_return: Decimal::ZERO,
+            bond: None,
+            liquidity: None,
+        }
+    }
+
+    fn book(cash: Decimal, positions: &[(&str, Decimal, Decimal)]) -> Portfolio {
+        let mut portfolio = Portfolio::new(cash);
+        for &(symbol, quantity, price) in positions {
+            portfolio.add_position(Position::new(asset(symbol, price), quantity, price));
+        }
+        // Cash left over after the positions were bought
+        portfolio.cash = cash;
+        portfolio.update_total_value();
+        portfolio
+    }
+
+    #[test]
+    fn same_symbol_positions_net_with_contributions_per_book() {
+        let a = book(dec!(1000), &[("ETH", dec!(100), dec!(2000))]);
+        let b = book(dec!(3000), &[("ETH", dec!(100), dec!(2000)), ("BTC", dec!(1), dec!(40000))]);
+        let firm = aggregate(&[("a", &a), ("b", &b)]);
+
+        assert_eq!(firm.portfolio.positions["ETH"].quantity, dec!(200));
+        assert_eq!(firm.portfolio.total_value, dec!(444000));
+        assert_eq!(firm.book_cash["b"], dec!(3000));
+        let eth = firm.drill_down("ETH");
+        assert_eq!(eth.len(), 2);
+        for contribution in eth {
+            assert_eq!(contribution.share, dec!(0.5));
+            assert_eq!(contribution.value, dec!(200000));
+        }
+        assert_eq!(firm.drill_down("BTC")[0].book, "b");
+        assert_eq!(firm.drill_down("BTC")[0].share, Decimal::ONE);
+        assert!(firm.drill_down("SOL").is_empty());
+    }
+
+    #[test]
+    fn firm_level_breach_where_no_book_breaches() {
+        // Each book holds ETH at 40% of its own value, but the stale book's ETH
+        // is revalued at the fresh book's mark, taking the firm's ETH past 50%
+        let mut stale = book(dec!(3000), &[("ETH", dec!(4), dec!(1000)), ("BTC", dec!(3), dec!(1000))]);
+        let fresh = book(dec!(1500), &[("ETH", dec!(1), dec!(2000)), ("SOL", dec!(3), dec!(500))]);
+        stale.timestamp = fresh.timestamp - Duration::days(1);
+        let firm = aggregate(&[("stale", &stale), ("fresh", &fresh)]);
+
+        assert!(PortfolioAnalyzer::concentration_breaches(&stale, 50.0).is_empty());
+        assert!(PortfolioAnalyzer::concentration_breaches(&fresh, 50.0).is_empty());
+        let breaches = firm.concentration_breaches(50.0);
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].symbol, "ETH");
+        assert!((breaches[0].weight_pct - 10.0 / 19.0 * 100.0).abs() < 1e-9);
+        let contributions: Vec<(&str, Decimal, Decimal)> = breaches[0]
+            .contributions
+            .iter()
+            .map(|c| (c.book.as_str(), c.value, c.share))
+            .collect();
+        assert_eq!(contributions, vec![("stale", dec!(8000), dec!(0.8)), ("fresh", dec!(2000), dec!(0.2))]);
+    }
+
+    #[test]
+    fn risk_report_carries_the_drill_down() {
+        let a = book(dec!(0), &[("ETH", dec!(3), dec!(1000)), ("BTC", dec!(3), dec!(1000))]);
+        let b = book(dec!(0), &[("ETH", dec!(3), dec!(1000)), ("SOL", dec!(3), dec!(1000))]);
+        let firm = aggregate(&[("a", &a), ("b", &b)]);
+        let report = firm.risk_report(0.95, 1, 40.0).unwrap();
+
+        assert_eq!(report.total_value, dec!(12000));
+        assert_eq!(report.exposures["ETH"], dec!(0.5));
+        assert!(report.value_at_risk > Decimal::ZERO);
+        assert_eq!(report.concentration_breaches.len(), 1);
+        assert_eq!(report.concentration_breaches[0].contributions.len(), 2);
+        let book_report = RiskReport::new(&a, 0.95, 1, 40.0).unwrap();
+        assert!(book_report.concentration_breaches.iter().all(|b| b.contributions.is_empty()));
+    }
+
+    #[test]
+    fn risk_report_scales_with_its_confidence() {
+        let a = book(dec!(0), &[("ETH", dec!(3), dec!(1000)), ("BTC", dec!(3), dec!(1000))]);
+        let firm = aggregate(&[("a", &a)]);
+        let at = |confidence| firm.risk_report(confidence, 252, 100.0).unwrap();
+
+        // 5% of value over a year at 95%
+        assert_eq!(at(0.95).value_at_risk.round_dp(8), dec!(300));
+        // z(0.99) / z(0.95) = 2.326348 / 1.644854
+        let ratio = (at(0.99).value_at_risk / at(0.95).value_at_risk).to_f64().unwrap();
+        assert!((ratio - 2.326_347_874 / 1.644_853_627).abs() < 1e-6, "{}", ratio);
+        for confidence in [0.9, 0.95, 0.99] {
+            let report = at(confidence);
+            assert!(report.conditional_var > report.value_at_risk, "at {}", confidence);
+        }
+        // Normal expected shortfall at 95% is 1.2540 times the VaR
+        let report = at(0.95);
+        let ratio = (report.conditional_var / report.value_at_risk).to_f64().unwrap();
+        assert!((ratio - 1.2540).abs() < 1e-4, "{}", ratio);
+    }
+
+    #[test]
+    fn risk_reports_reject_confidences_without_a_var() {
+        let a = book(dec!(0), &[("ETH", dec!(3), dec!(1000))]);
+        let firm = aggregate(&[("a", &a)]);
+        // 95.0 is a percentage typed where a fraction belongs
+        for confidence in [95.0, 1.0, 0.5, 0.2, f64::NAN] {
+            let error = firm.risk_report(confidence, 1, 100.0).unwrap_err();
+            assert!(error.to_string().contains("between 0.5 and 1"), "{}", error);
+            assert!(RiskReport::new(&a, confidence, 1, 100.0).is_err());
+        }
+    }
+
+    #[test]
+    fn price_differences_are_not_conflicts_and_the_latest_mark_wins() {
+        let mut stale = book(dec!(0), &[("ETH", dec!(1), dec!(2000))]);
+        let fresh = book(dec!(0), &[("ETH", dec!(3), dec!(2400))]);
+        stale.timestamp = fresh.timestamp - Duration::hours(1);
+        let firm = aggregate_strict(&[("stale", &stale), ("fresh", &fresh)]).unwrap();
+
+        let eth = &firm.portfolio.positions["ETH"];
+        assert_eq!(eth.asset.current_price, dec!(2400));
+        assert_eq!(eth.current_value, dec!(9600));
+        assert_eq!(eth.entry_price, dec!(2300));
+        let values: Vec<Decimal> = firm.drill_down("ETH").iter().map(|c| c.value).collect();
+        assert_eq!(values, vec![dec!(2400), dec!(7200)]);
+    }
+
+    #[test]
+    fn conflicting_identity_is_rejected_only_when_strict() {
+        let a = book(dec!(0), &[("ETH", dec!(1), dec!(2000))]);
+        let mut b = book(dec!(0), &[("ETH", dec!(1), dec!(2000))]);
+        b.positions.get_mut("ETH").unwrap().asset.asset_type = AssetType::DeFiPool;
+
+        assert!(aggregate_strict(&[("a", &a), ("b", &b)]).is_err());
+        let firm = aggregate(&[("a", &a), ("b", &b)]);
+        assert_eq!(firm.portfolio.positions["ETH"].asset.asset_type, AssetType::Crypto);
+        assert_eq!(firm.portfolio.positions["ETH"].quantity, dec!(2));
+    }
 }
@@ -0,0 +1,326 @@
+//! Self-contained backtest reports.
+//!
+//! [`render`] lays out a backtest's results as Markdown or HTML: a summary
+//! table (with the benchmark comparison when the backtest had one), a heatmap
+//! of monthly returns, the drawdown table, trade statistics and the equity
+//! curve as an inline SVG polyline. Neither format loads anything external.
+
+use crate::types::{BacktestResults, EquityPoint};
+use rust_decimal::prelude::ToPrimitive;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Size of the equity curve chart, in pixels
+const CHART_WIDTH: f64 = 640.0;
+const CHART_HEIGHT: f64 = 240.0;
+const CHART_PADDING: f64 = 8.0;
+
+/// Monthly return, in percent, shaded fully in the heatmap
+const HEATMAP_FULL_SCALE_PCT: f64 = 10.0;
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// Markdown for `.md` and `.markdown` paths, HTML for anything else
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("md" | "markdown") => Self::Markdown,
+            _ => Self::Html,
+        }
+    }
+}
+
+/// A table as a header row and body rows of cells, each body cell with an
+/// optional heatmap shade
+struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<(String, Option<f64>)>>,
+}
+
+impl Table {
+    fn new(header: &[&str]) -> Self {
+        Self { header: header.iter().map(|cell| cell.to_string()).collect(), rows: vec![] }
+    }
+
+    fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells.into_iter().map(|cell| (cell, None)).collect());
+    }
+}
+
+/// `results` as a complete Markdown or HTML document
+pub fn render(results: &BacktestResults, format: ReportFormat) -> String {
+    let title = format!("Backtest report: {} to {}", results.start_date.date(), results.end_date.date());
+    let mut sections: Vec<(&str, String)> = vec![("Summary", table(&summary(results), format))];
+    if !results.monthly_returns.is_empty() {
+        let mut returns = table(&monthly_heatmap(results), format);
+        if results.monthly_returns.iter().any(|month| month.partial) {
+            returns.push_str(&paragraph("Returns marked * cover only part of the month or year.", format));
+        }
+        sections.push(("Monthly Returns", returns));
+    }
+    if !results.drawdowns.is_empty() {
+        sections.push(("Drawdowns", table(&drawdowns(results), format)));
+    }
+    sections.push(("Trades", table(&trade_stats(results), format)));
+    if let Some(chart) = equity_chart(&results.equity_curve) {
+        sections.push(("Equity Curve", chart + "\n"));
+    }
+
+    let mut out = String::new();
+    match format {
+        ReportFormat::Markdown => {
+            let _ = writeln!(out, "# {}\n", title);
+            for (heading, body) in sections {
+                let _ = writeln!(out, "## {}\n\n{}", heading, body);
+            }
+        }
+        ReportFormat::Html => {
+            let _ = writeln!(out, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>", title);
+            out.push_str(
+                "<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;margin-bottom:1em}\
+                 th,td{border:1px solid #ccc;padding:4px 8px;text-align:right}th{background:#f4f4f4}</style>\n",
+            );
+            let _ = writeln!(out, "</head>\n<body>\n<h1>{}</h1>", title);
+            for (heading, body) in sections {
+                let _ = writeln!(out, "<h2>{}</h2>\n{}", heading, body);
+            }
+            out.push_str("</body>\n</html>\n");
+        }
+    }
+    out
+}
+
+fn summary(results: &BacktestResults) -> Table {
+    let mut table = Table::new(&["Metric", "Value"]);
+    let mut metric = |name: &str, value: String| table.row(vec![name.to_string(), value]);
+    metric("Initial value", format!("{:.2}", results.initial_value));
+    metric("Final value", format!("{:.2}", results.final_value));
+    metric("Total return", percent(results.total_return_pct));
+    metric("Price return", percent(results.price_return_pct));
+    metric("Income return", percent(results.income_return_pct));
+    metric("Annualized return", percent(results.annualized_return_pct));
+    metric("Volatility", percent(results.volatility_pct));
+    metric("Sharpe ratio", format!("{:.4}", results.sharpe_ratio));
+    metric("Sortino ratio", ratio(results.sortino_ratio));
+    metric("Calmar ratio", ratio(results.calmar_ratio));
+    metric("Max drawdown", percent(results.max_drawdown_pct));
+    metric("Turnover", format!("{:.2}", results.turnover));
+    metric("Commission", format!("{:.2}", results.total_commission));
+    metric("Slippage", format!("{:.2}", results.total_slippage));
+    if let Some(contribution) = &results.initial_portfolio {
+        metric("Initial positions value", format!("{:.2}", contribution.positions_value));
+        metric("Initial positions P&L", format!("{:.2}", contribution.positions_pnl));
+        metric("Decisions P&L", format!("{:.2}", contribution.decisions_pnl));
+    }
+    if let Some(benchmark_return) = results.benchmark_return_pct {
+        metric("Benchmark return", percent(benchmark_return));
+        metric("Alpha", results.alpha_pct.map_or("n/a".to_string(), percent));
+        metric("Beta", ratio(results.beta));
+        metric("Tracking error", results.tracking_error.map_or("n/a".to_string(), percent));
+        metric("Information ratio", ratio(results.information_ratio));
+    }
+    table
+}
+
+/// A row per year of monthly returns, shaded by sign and size, and the year's return
+fn monthly_heatmap(results: &BacktestResults) -> Table {
+    let mut header = vec!["Year"];
+    header.extend(MONTHS);
+    header.push("Year");
+    let mut table = Table::new(&header);
+    let mut years: Vec<i32> = results.monthly_returns.iter().map(|month| month.year).collect();
+    years.dedup();
+    for year in years {
+        let mut row = vec![(year.to_string(), None)];
+        for month in 1..=12u8 {
+            let cell = results
+                .monthly_returns
+                .iter()
+                .find(|period| period.year == year && period.month == Some(month))
+                .map_or((String::new(), None), |period| (period_cell(period.return_pct, period.partial), Some(period.return_pct)));
+            row.push(cell);
+        }
+        let annual = results.annual_returns.iter().find(|period| period.year == year);
+        row.push(annual.map_or((String::new(), None), |period| (period_cell(period.return_pct, period.partial), None)));
+        table.rows.push(row);
+    }
+    table
+}
+
+fn drawdowns(results: &BacktestResults) -> Table {
+    let mut table = Table::new(&["Depth", "Start", "Trough", "Recovery", "Days"]);
+    for drawdown in &results.drawdowns {
+        table.row(vec![
+            percent(drawdown.depth_pct),
+            drawdown.start.date().to_string(),
+            drawdown.trough.date().to_string(),
+            drawdown.recovery.map_or("-".to_string(), |recovery| recovery.date().to_string()),
+            drawdown.length_days.to_string(),
+        ]);
+    }
+    table
+}
+
+fn trade_stats(results: &BacktestResults) -> Table {
+    let closed = results.trades.iter().filter(|trade| trade.exit_time.is_some()).count();
+    let mut table = Table::new(&["Metric", "Value"]);
+    table.row(vec!["Trades".to_string(), results.trades.len().to_string()]);
+    table.row(vec!["Closed trades".to_string(), closed.to_string()]);
+    table.row(vec!["Win rate".to_string(), percent(results.win_rate * 100.0)]);
+    table.row(vec!["Profit factor".to_string(), results.profit_factor.map_or("n/a".to_string(), |factor| format!("{:.2}", factor))]);
+    table
+}
+
+/// The equity curve as an SVG polyline scaled to the chart; `None` with fewer than two points
+fn equity_chart(curve: &[EquityPoint]) -> Option<String> {
+    if curve.len() < 2 {
+        return None;
+    }
+    let values: Vec<f64> = curve.iter().map(|point| point.value.to_f64().unwrap_or(0.0)).collect();
+    let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = if high > low { high - low } else { 1.0 };
+    let (width, height) = (CHART_WIDTH - 2.0 * CHART_PADDING, CHART_HEIGHT - 2.0 * CHART_PADDING);
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let x = CHART_PADDING + width * index as f64 / (values.len() - 1) as f64;
+            let y = CHART_PADDING + height * (high - value) / range;
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect();
+    Some(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#fff\" stroke=\"#ccc\"/>\
+         <polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"1.5\" points=\"{points}\"/></svg>\n\
+         <p>Value from {low:.2} to {high:.2}</p>",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT,
+        points = points.join(" "),
+        low = low,
+        high = high,
+    ))
+}
+
+fn table(table: &Table, format: ReportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ReportFormat::Markdown => {
+            let _ = writeln!(out, "| {} |", table.header.join(" | "));
+            let _ = writeln!(out, "|{}", "---|".repeat(table.header.len()));
+            for row in &table.rows {
+                let cells: Vec<&str> = row.iter().map(|(cell, _)| cell.as_str()).collect();
+                let _ = writeln!(out, "| {} |", cells.join(" | "));
+            }
+        }
+        ReportFormat::Html => {
+            out.push_str("<table>\n<tr>");
+            for cell in &table.header {
+                let _ = write!(out, "<th>{}</th>", cell);
+            }
+            out.push_str("</tr>\n");
+            for row in &table.rows {
+                out.push_str("<tr>");
+                for (cell, shade) in row {
+                    match shade {
+                        Some(return_pct) => {
+                            let _ = write!(out, "<td style=\"background:{}\">{}</td>", heat(*return_pct), cell);
+                        }
+                        None => {
+                            let _ = write!(out, "<td>{}</td>", cell);
+                        }
+                    }
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+    }
+    out
+}
+
+fn paragraph(text: &str, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Markdown => format!("\n{}\n", text),
+        ReportFormat::Html => format!("<p>{}</p>\n", text),
+    }
+}
+
+/// Green for gains and red for losses, more opaque the larger the return
+fn heat(return_pct: f64) -> String {
+    let alpha = (return_pct.abs() / HEATMAP_FULL_SCALE_PCT).min(1.0) * 0.6;
+    let (red, green, blue) = if return_pct < 0.0 { (214, 39, 40) } else { (44, 160, 44) };
+    format!("rgba({},{},{},{:.2})", red, green, blue, alpha)
+}
+
+fn period_cell(return_pct: f64, partial: bool) -> String {
+    format!("{}{}", percent(return_pct), if partial { "*" } else { "" })
+}
+
+fn percent(value: f64) -> String {
+    format!("{:.2}%", value)
+}
+
+fn ratio(value: Option<f64>) -> String {
+    value.map_or("n/a".to_string(), |value| format!("{:.4}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert_eq!(ReportFormat::from_path(Path::new("out/report.md")), ReportFormat::Markdown);
+        assert_eq!(ReportFormat::from_path(Path::new("report.markdown")), ReportFormat::Markdown);
+        assert_eq!(ReportFormat::from_path(Path::new("report.html")), ReportFormat::Html);
+        assert_eq!(ReportFormat::from_path(Path::new("report")), ReportFormat::Html);
+    }
+
+    #[test]
+    fn tables_render_in_both_formats() {
+        let mut returns = Table::new(&["Year", "Jan"]);
+        returns.rows.push(vec![("2024".to_string(), None), (period_cell(-2.5, true), Some(-2.5))]);
+
+        assert_eq!(table(&returns, ReportFormat::Markdown), "| Year | Jan |\n|---|---|\n| 2024 | -2.50%* |\n");
+        assert_eq!(
+            table(&returns, ReportFormat::Html),
+            "<table>\n<tr><th>Year</th><th>Jan</th></tr>\n\
+             <tr><td>2024</td><td style=\"background:rgba(214,39,40,0.15)\">-2.50%*</td></tr>\n</table>\n"
+        );
+    }
+
+    #[test]
+    fn heat_saturates_at_full_scale() {
+        assert_eq!(heat(0.0), "rgba(44,160,44,0.00)");
+        assert_eq!(heat(5.0), "rgba(44,160,44,0.30)");
+        assert_eq!(heat(-40.0), "rgba(214,39,40,0.60)");
+        assert_eq!(ratio(None), "n/a");
+        assert_eq!(ratio(Some(1.23456)), "1.2346");
+    }
+
+    #[test]
+    fn equity_chart_spans_the_padded_box() {
+        let point = |day: i64, value| EquityPoint {
+            timestamp: datetime!(2024-01-01 0:00 UTC) + time::Duration::days(day),
+            value,
+            cash: dec!(0),
+            drawdown_pct: 0.0,
+        };
+        assert_eq!(equity_chart(&[point(0, dec!(100))]), None);
+
+        let chart = equity_chart(&[point(0, dec!(100)), point(1, dec!(150)), point(2, dec!(125))]).unwrap();
+        // The highest value at the top edge, the lowest at the bottom
+        assert!(chart.contains("points=\"8.0,232.0 320.0,8.0 632.0,120.0\""), "{}", chart);
+        assert!(chart.ends_with("<p>Value from 100.00 to 150.00</p>"));
+    }
+}
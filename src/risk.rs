@@ -1,34 +1,100 @@
+use crate::portfolio::{ConcentrationBreach, PortfolioAnalyzer};
 use crate::types::*;
+use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+use std::collections::HashMap;
+
+/// Loss, as a fraction of value, the simplified model puts at 95% confidence
+/// over a 252-day year; other confidence levels scale by their normal quantile
+const ANNUAL_VAR_95: f64 = 0.05;
+
+/// Value at risk, exposures and concentration breaches of a portfolio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskReport {
+    pub total_value: Decimal,
+    pub value_at_risk: Decimal,
+    pub conditional_var: Decimal,
+    /// Weight of each position as a fraction of total value
+    pub exposures: HashMap<String, Decimal>,
+    pub concentration_breaches: Vec<ConcentrationBreach>,
+}
+
+impl RiskReport {
+    /// Report on `portfolio` at `confidence`, flagging positions over `max_position_size_pct`.
+    /// Fails for a confidence outside (0.5, 1), where the model has no VaR.
+    /// For a firm-level report with per-book drill-down use
+    /// [`AggregatedPortfolio::risk_report`](crate::portfolio::AggregatedPortfolio::risk_report).
+    pub fn new(
+        portfolio: &Portfolio,
+        confidence: f64,
+        time_horizon_days: usize,
+        max_position_size_pct: f64,
+    ) -> Result<Self> {
+        if RiskCalculator::z_score(confidence).is_none() {
+            return Err(anyhow::anyhow!("Confidence must be between 0.5 and 1, got {}", confidence));
+        }
+        Ok(Self {
+            total_value: portfolio.total_value,
+            value_at_risk: RiskCalculator::value_at_risk(portfolio, confidence, time_horizon_days),
+            conditional_var: RiskCalculator::conditional_var(portfolio, confidence, time_horizon_days),
+            exposures: PortfolioAnalyzer::exposure_breakdown(portfolio),
+            concentration_breaches: PortfolioAnalyzer::concentration_breaches(portfolio, max_position_size_pct),
+        })
+    }
+}
 
 /// Risk calculation utilities
 pub struct RiskCalculator;
 
 impl RiskCalculator {
-    /// Calculate Value at Risk (VaR) for a portfolio
+    /// Calculate Value at Risk (VaR) for a portfolio.
+    ///
+    /// Parametric with normal returns: 5% of value at 95% confidence over a
+    /// year, scaled by the normal quantile of `confidence` and the square root
+    /// of the horizon. Zero for a confidence outside (0.5, 1).
     pub fn value_at_risk(
         portfolio: &Portfolio,
         confidence: f64,
         time_horizon_days: usize,
     ) -> Decimal {
+        let Some(z) = Self::z_score(confidence) else {
+            return Decimal::ZERO;
+        };
         // Simplified VaR calculation
         // In full implementation, we'd use historical simulation or parametric methods
-        let portfolio_risk = portfolio.total_value * Decimal::try_from(0.05).unwrap();
+        let sigma = ANNUAL_VAR_95 / Self::z_score(0.95).unwrap_or(1.0);
+        let portfolio_risk = portfolio.total_value * Decimal::try_from(sigma * z).unwrap_or(Decimal::ZERO);
         
         // Adjust for time horizon
         let time_factor = (time_horizon_days as f64 / 252.0).sqrt();
         portfolio_risk * Decimal::try_from(time_factor).unwrap_or(Decimal::ONE)
     }
     
-    /// Calculate Conditional VaR (Expected Shortfall)
+    /// Calculate Conditional VaR (Expected Shortfall): the mean loss beyond
+    /// the VaR under the same normal model, `VaR · φ(z) / ((1 − c) z)`
     pub fn conditional_var(
         portfolio: &Portfolio,
         confidence: f64,
         time_horizon_days: usize,
     ) -> Decimal {
-        // CVaR is typically 1.2-1.5x VaR
+        let Some(z) = Self::z_score(confidence) else {
+            return Decimal::ZERO;
+        };
         let var = Self::value_at_risk(portfolio, confidence, time_horizon_days);
-        var * Decimal::try_from(1.3).unwrap()
+        let standard = Normal::new(0.0, 1.0).expect("standard normal");
+        let ratio = standard.pdf(z) / ((1.0 - confidence) * z);
+        var * Decimal::try_from(ratio).unwrap_or(Decimal::ONE)
+    }
+    
+    /// Standard normal quantile of `confidence`, for confidences in (0.5, 1)
+    fn z_score(confidence: f64) -> Option<f64> {
+        if !(confidence > 0.5 && confidence < 1.0) {
+            return None;
+        }
+        Some(Normal::new(0.0, 1.0).ok()?.inverse_cdf(confidence))
     }
     
     /// Calculate maximum drawdown from portfolio history
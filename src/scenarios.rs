@@ -0,0 +1,487 @@
+use crate::simulator::{Simulator, SimulatorConfig};
+use anyhow::Result;
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Volatility multiplier above which a scenario counts as high-vol
+pub const HIGH_VOL_MULTIPLIER: f64 = 1.25;
+
+/// Volatility multiplier below which a scenario counts as low-vol
+pub const LOW_VOL_MULTIPLIER: f64 = 0.8;
+
+/// Default correlation at or above which a scenario counts as a high-correlation regime
+pub const HIGH_CORRELATION: f64 = 0.5;
+
+/// Market-wide conditions a simulation path runs under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Added to every asset's annual expected return
+    pub drift_shift: f64,
+    /// Scales every asset's volatility
+    pub vol_multiplier: f64,
+    /// Correlation between symbol pairs with no explicit correlation
+    pub correlation: f64,
+    /// Market-wide shocks as `(step, pct_change)`
+    #[serde(default)]
+    pub shocks: Vec<(usize, Decimal)>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            drift_shift: 0.0,
+            vol_multiplier: 1.0,
+            correlation: 0.0,
+            shocks: vec![],
+        }
+    }
+}
+
+impl Scenario {
+    /// Set the scenario's market conditions on a simulator configuration
+    pub fn configure(&self, config: &mut SimulatorConfig) {
+        config.drift_adjustment = self.drift_shift;
+        config.volatility_multiplier *= self.vol_multiplier;
+        config.default_correlation = self.correlation;
+    }
+
+    /// Schedule the scenario's shocks on a fresh simulator
+    pub fn schedule_shocks(&self, simulator: &mut Simulator) -> Result<()> {
+        for (step, pct_change) in &self.shocks {
+            simulator.schedule_shock(*step, crate::types::MarketShock::ALL_SYMBOLS, *pct_change)?;
+        }
+        Ok(())
+    }
+
+    /// Classes the scenario belongs to, one per dimension (volatility, correlation, shocks)
+    pub fn classes(&self) -> [&'static str; 3] {
+        let vol = if self.vol_multiplier > HIGH_VOL_MULTIPLIER {
+            "high_vol"
+        } else if self.vol_multiplier < LOW_VOL_MULTIPLIER {
+            "low_vol"
+        } else {
+            "normal_vol"
+        };
+        let correlation = if self.correlation >= HIGH_CORRELATION {
+            "high_correlation"
+        } else {
+            "low_correlation"
+        };
+        let shocks = if self.shocks.is_empty() { "no_shock" } else { "shocked" };
+        [vol, correlation, shocks]
+    }
+}
+
+/// Distributions scenarios are sampled from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioDistribution {
+    pub drift_shift_mean: f64,
+    pub drift_shift_std: f64,
+    /// Standard deviation of the log volatility multiplier; the median multiplier is 1
+    pub vol_multiplier_log_std: f64,
+    /// Correlation regimes, drawn with equal probability
+    pub correlation_regimes: Vec<f64>,
+    /// Chance that a scenario includes one market-wide shock
+    pub shock_probability: f64,
+    pub shock_mean_pct: f64,
+    pub shock_std_pct: f64,
+}
+
+impl Default for ScenarioDistribution {
+    fn default() -> Self {
+        Self {
+            drift_shift_mean: 0.0,
+            drift_shift_std: 0.05,
+            vol_multiplier_log_std: 0.3,
+            correlation_regimes: vec![0.0, 0.3, 0.7],
+            shock_probability: 0.1,
+            shock_mean_pct: -20.0,
+            shock_std_pct: 10.0,
+        }
+    }
+}
+
+impl ScenarioDistribution {
+    /// Check that every distribution can be sampled from
+    pub fn validate(&self) -> Result<()> {
+        if !self.drift_shift_mean.is_finite() || !self.shock_mean_pct.is_finite() {
+            return Err(anyhow::anyhow!("Scenario distribution means must be finite"));
+        }
+        for (name, std) in [
+            ("drift shift", self.drift_shift_std),
+            ("log volatility multiplier", self.vol_multiplier_log_std),
+            ("shock", self.shock_std_pct),
+        ] {
+            if !std.is_finite() || std < 0.0 {
+                return Err(anyhow::anyhow!(
+                    "Standard deviation of the {} must be finite and non-negative, got {}",
+                    name,
+                    std
+                ));
+            }
+        }
+        if let Some(correlation) = self.correlation_regimes.iter().find(|c| !(-1.0..1.0).contains(*c)) {
+            return Err(anyhow::anyhow!(
+                "Correlation regimes must be in [-1, 1), got {}",
+                correlation
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.shock_probability) {
+            return Err(anyhow::anyhow!(
+                "Shock probability must be in [0, 1], got {}",
+                self.shock_probability
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sample `count` scenarios for paths of `steps` steps
+    pub fn generate<R: Rng>(&self, count: usize, steps: usize, rng: &mut R) -> Vec<Scenario> {
+        let drift = Normal::new(self.drift_shift_mean, self.drift_shift_std.max(0.0))
+            .expect("standard deviation is non-negative");
+        let log_vol = Normal::new(0.0, self.vol_multiplier_log_std.max(0.0))
+            .expect("standard deviation is non-negative");
+        let shock = Normal::new(self.shock_mean_pct, self.shock_std_pct.max(0.0))
+            .expect("standard deviation is non-negative");
+        
+        (0..count)
+            .map(|_| {
+                let correlation = if self.correlation_regimes.is_empty() {
+                    0.0
+                } else {
+                    self.correlation_regimes[rng.gen_range(0..self.correlation_regimes.len())]
+                };
+                let mut shocks = vec![];
+                if steps > 0 && rng.gen_bool(self.shock_probability.clamp(0.0, 1.0)) {
+                    // Prices can fall at most 100%
+                    let pct_change = shock.sample(rng).max(-100.0);
+                    shocks.push((
+                        rng.gen_range(1..=steps),
+                        Decimal::try_from(pct_change).unwrap_or(Decimal::ZERO),
+                    ));
+                }
+                Scenario {
+                    drift_shift: drift.sample(rng),
+                    vol_multiplier: log_vol.sample(rng).exp(),
+                    correlation,
+                    shocks,
+                }
+            })
+            .collect()
+    }
+}
+
+/// One state of a [`RegimeModel`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Regime {
+    pub name: String,
+    /// Added to every asset's annual expected return while the regime lasts
+    pub drift_shift: f64,
+    /// Scales every asset's volatility while the regime lasts
+    pub vol_multiplier: f64,
+    /// Correlation between symbol pairs with no explicit correlation
+    pub correlation: f64,
+}
+
+impl Regime {
+    pub fn new(name: &str, drift_shift: f64, vol_multiplier: f64, correlation: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            drift_shift,
+            vol_multiplier,
+            correlation,
+        }
+    }
+}
+
+/// Markov chain over market regimes, stepped once per simulation step.
+///
+/// `transitions[i][j]` is the chance of moving from regime `i` to regime `j`
+/// on a step; each row sums to 1. Volatility clusters because the chain
+/// tends to stay where it is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegimeModel {
+    pub regimes: Vec<Regime>,
+    pub transitions: Vec<Vec<f64>>,
+    /// Regime at step 0
+    #[serde(default)]
+    pub initial: usize,
+}
+
+impl RegimeModel {
+    pub fn new(regimes: Vec<Regime>, transitions: Vec<Vec<f64>>) -> Self {
+        Self {
+            regimes,
+            transitions,
+            initial: 0,
+        }
+    }
+
+    /// Two states: a calm regime lasting about 100 steps and a crisis with
+    /// doubled volatility, falling prices and high correlation lasting about 20
+    pub fn calm_crisis() -> Self {
+        Self::new(
+            vec![
+                Regime::new("calm", 0.02, 0.8, 0.2),
+                Regime::new("crisis", -0.30, 2.0, 0.7),
+            ],
+            vec![vec![0.99, 0.01], vec![0.05, 0.95]],
+        )
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let n = self.regimes.len();
+        if n == 0 {
+            return Err(anyhow::anyhow!("Regime model needs at least one regime"));
+        }
+        if self.initial >= n {
+            return Err(anyhow::anyhow!(
+                "Initial regime {} is out of range for {} regimes",
+                self.initial,
+                n
+            ));
+        }
+        if self.transitions.len() != n || self.transitions.iter().any(|row| row.len() != n) {
+            return Err(anyhow::anyhow!("Transition matrix must be {}x{}", n, n));
+        }
+        for (i, row) in self.transitions.iter().enumerate() {
+            if row.iter().any(|p| !p.is_finite() || *p < 0.0) {
+                return Err(anyhow::anyhow!(
+                    "Transition probabilities from {} must be non-negative",
+                    self.regimes[i].name
+                ));
+            }
+            let total: f64 = row.iter().sum();
+            if (total - 1.0).abs() > 1e-9 {
+                return Err(anyhow::anyhow!(
+                    "Transition probabilities from {} sum to {}, not 1",
+                    self.regimes[i].name,
+                    total
+                ));
+            }
+        }
+        for regime in &self.regimes {
+            if !regime.vol_multiplier.is_finite() || regime.vol_multiplier < 0.0 {
+                return Err(anyhow::anyhow!(
+                    "Volatility multiplier of {} must be non-negative",
+                    regime.name
+                ));
+            }
+            if !(-1.0..1.0).contains(&regime.correlation) {
+                return Err(anyhow::anyhow!(
+                    "Correlation of {} must be in [-1, 1), got {}",
+                    regime.name,
+                    regime.correlation
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Regime after one step from `current`
+    pub fn next<R: Rng + ?Sized>(&self, current: usize, rng: &mut R) -> usize {
+        let Some(row) = self.transitions.get(current) else {
+            return current;
+        };
+        let draw: f64 = rng.gen();
+        let mut cumulative = 0.0;
+        for (next, p) in row.iter().enumerate() {
+            cumulative += p;
+            if draw < cumulative {
+                return next;
+            }
+        }
+        // Rounding left the row just short of 1
+        current
+    }
+
+    /// Index of the crisis regime: the one with the highest volatility multiplier
+    pub fn crisis(&self) -> usize {
+        self.regimes
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.vol_multiplier.total_cmp(&b.vol_multiplier))
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    /// Long-run share of steps spent in each regime, by power iteration from the uniform distribution
+    pub fn stationary_distribution(&self) -> Vec<f64> {
+        let n = self.regimes.len();
+        if n == 0 {
+            return vec![];
+        }
+        let mut pi = vec![1.0 / n as f64; n];
+        for _ in 0..10_000 {
+            let mut next = vec![0.0; n];
+            for (i, row) in self.transitions.iter().enumerate() {
+                for (j, p) in row.iter().enumerate() {
+                    next[j] += pi[i] * p;
+                }
+            }
+            let change: f64 = next.iter().zip(&pi).map(|(a, b)| (a - b).abs()).sum();
+            pi = next;
+            if change < 1e-12 {
+                break;
+            }
+        }
+        pi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn occupancy(model: &RegimeModel, steps: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut counts = vec![0usize; model.regimes.len()];
+        let mut regime = model.initial;
+        for _ in 0..steps {
+            regime = model.next(regime, &mut rng);
+            counts[regime] += 1;
+        }
+        counts.iter().map(|&count| count as f64 / steps as f64).collect()
+    }
+
+    #[test]
+    fn calm_crisis_spends_a_sixth_of_the_time_in_crisis() {
+        let model = RegimeModel::calm_crisis();
+        // Balance: 0.01 of calm flows out as 0.05 of crisis flows back
+        let stationary = model.stationary_distribution();
+        assert!((stationary[0] - 5.0 / 6.0).abs() < 1e-9);
+        assert!((stationary[1] - 1.0 / 6.0).abs() < 1e-9);
+        assert_eq!(model.crisis(), 1);
+
+        let observed = occupancy(&model, 500_000);
+        for (share, expected) in observed.iter().zip(&stationary) {
+            assert!((share - expected).abs() < 0.01, "occupancy {:?} vs {:?}", observed, stationary);
+        }
+    }
+
+    #[test]
+    fn three_state_occupancy_matches_the_stationary_distribution() {
+        let model = RegimeModel::new(
+            vec![
+                Regime::new("calm", 0.05, 0.7, 0.1),
+                Regime::new("choppy", 0.0, 1.2, 0.3),
+                Regime::new("crisis", -0.4, 2.5, 0.8),
+            ],
+            vec![vec![0.9, 0.08, 0.02], vec![0.2, 0.7, 0.1], vec![0.1, 0.3, 0.6]],
+        );
+        model.validate().unwrap();
+        let stationary = model.stationary_distribution();
+        assert!((stationary.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        // pi = pi P
+        for j in 0..3 {
+            let flowed: f64 = (0..3).map(|i| stationary[i] * model.transitions[i][j]).sum();
+            assert!((flowed - stationary[j]).abs() < 1e-9);
+        }
+
+        let observed = occupancy(&model, 500_000);
+        for (share, expected) in observed.iter().zip(&stationary) {
+            assert!((share - expected).abs() < 0.01, "occupancy {:?} vs {:?}", observed, stationary);
+        }
+    }
+
+    #[test]
+    fn malformed_models_are_rejected() {
+        let mut model = RegimeModel::calm_crisis();
+        model.transitions[0] = vec![0.9, 0.2];
+        assert!(model.validate().is_err());
+
+        let mut model = RegimeModel::calm_crisis();
+        model.transitions.pop();
+        assert!(model.validate().is_err());
+
+        let mut model = RegimeModel::calm_crisis();
+        model.initial = 2;
+        assert!(model.validate().is_err());
+
+        let mut model = RegimeModel::calm_crisis();
+        model.regimes[1].correlation = 1.0;
+        assert!(model.validate().is_err());
+    }
+
+    #[test]
+    fn sampled_scenarios_are_seeded_and_within_the_distribution() {
+        let distribution = ScenarioDistribution::default();
+        let sample = |seed: u64| distribution.generate(2_000, 30, &mut StdRng::seed_from_u64(seed));
+        let scenarios = sample(5);
+        assert_eq!(scenarios.len(), 2_000);
+        assert_eq!(scenarios, sample(5));
+        assert_ne!(scenarios, sample(6));
+
+        for scenario in &scenarios {
+            assert!(distribution.correlation_regimes.contains(&scenario.correlation));
+            assert!(scenario.vol_multiplier > 0.0);
+            assert!(scenario.shocks.len() <= 1);
+            for (step, pct_change) in &scenario.shocks {
+                assert!((1..=30).contains(step));
+                assert!(*pct_change >= Decimal::from(-100));
+            }
+        }
+        // Every regime is drawn, and about one scenario in ten is shocked
+        for regime in &distribution.correlation_regimes {
+            let share = scenarios.iter().filter(|s| s.correlation == *regime).count() as f64 / 2_000.0;
+            assert!((share - 1.0 / 3.0).abs() < 0.05, "regime {} drawn {}", regime, share);
+        }
+        let shocked = scenarios.iter().filter(|s| !s.shocks.is_empty()).count() as f64 / 2_000.0;
+        assert!((shocked - 0.1).abs() < 0.03, "{} shocked", shocked);
+        // The median volatility multiplier is 1
+        let below = scenarios.iter().filter(|s| s.vol_multiplier < 1.0).count() as f64 / 2_000.0;
+        assert!((below - 0.5).abs() < 0.05, "{} below 1", below);
+    }
+
+    #[test]
+    fn shock_probability_zero_and_one_are_exact() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let never = ScenarioDistribution { shock_probability: 0.0, ..Default::default() };
+        assert!(never.generate(200, 10, &mut rng).iter().all(|s| s.shocks.is_empty()));
+        let always = ScenarioDistribution { shock_probability: 1.0, ..Default::default() };
+        assert!(always.generate(200, 10, &mut rng).iter().all(|s| s.shocks.len() == 1));
+        // A path with no steps has nowhere to put a shock
+        assert!(always.generate(20, 0, &mut rng).iter().all(|s| s.shocks.is_empty()));
+
+        let flat = ScenarioDistribution { correlation_regimes: vec![], ..Default::default() };
+        assert!(flat.generate(50, 10, &mut rng).iter().all(|s| s.correlation == 0.0));
+    }
+
+    #[test]
+    fn classes_split_at_the_thresholds() {
+        let scenario = |vol_multiplier: f64, correlation: f64, shocked: bool| Scenario {
+            vol_multiplier,
+            correlation,
+            shocks: if shocked { vec![(1, Decimal::from(-10))] } else { vec![] },
+            ..Scenario::default()
+        };
+        assert_eq!(scenario(1.0, 0.0, false).classes(), ["normal_vol", "low_correlation", "no_shock"]);
+        assert_eq!(scenario(1.3, 0.7, true).classes(), ["high_vol", "high_correlation", "shocked"]);
+        assert_eq!(scenario(0.5, 0.3, false).classes(), ["low_vol", "low_correlation", "no_shock"]);
+        // Volatility bounds are exclusive; the correlation bound is inclusive
+        let boundary = scenario(HIGH_VOL_MULTIPLIER, HIGH_CORRELATION, false);
+        assert_eq!(boundary.classes()[..2], ["normal_vol", "high_correlation"]);
+        assert_eq!(scenario(LOW_VOL_MULTIPLIER, 0.0, false).classes()[0], "normal_vol");
+    }
+
+    #[test]
+    fn malformed_distributions_are_rejected() {
+        assert!(ScenarioDistribution::default().validate().is_ok());
+        let invalid = [
+            ScenarioDistribution { drift_shift_std: f64::INFINITY, ..Default::default() },
+            ScenarioDistribution { vol_multiplier_log_std: -0.1, ..Default::default() },
+            ScenarioDistribution { shock_std_pct: f64::NAN, ..Default::default() },
+            ScenarioDistribution { shock_mean_pct: f64::NEG_INFINITY, ..Default::default() },
+            ScenarioDistribution { correlation_regimes: vec![0.2, 1.0], ..Default::default() },
+            ScenarioDistribution { shock_probability: -0.1, ..Default::default() },
+            ScenarioDistribution { shock_probability: f64::NAN, ..Default::default() },
+        ];
+        for distribution in invalid {
+            assert!(distribution.validate().is_err(), "{:?}", distribution);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::types::AssetType;
+use anyhow::{Context, Result};
+use rand::Rng;
+use rand_distr::{Distribution, StandardNormal, StudentT};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Distribution of the per-step random shock in the GBM price walk.
+///
+/// Every variant is scaled to zero mean and unit variance, so an asset's
+/// volatility keeps its meaning and only the shape of the tails changes.
+/// `SimulatorConfig::shock_distribution` applies to every asset unless
+/// `SimulatorConfig::shock_distributions` overrides it for that symbol.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum ShockDistribution {
+    #[default]
+    Normal,
+    /// Student's t; the fewer the degrees of freedom the fatter the tails.
+    /// Must exceed 2 so the variance is finite.
+    StudentT { degrees_of_freedom: f64 },
+    /// Resampled with replacement from standardized historical returns,
+    /// keeping their skew and tails. Build with [`ShockDistribution::bootstrap`].
+    Bootstrap { shocks: Vec<f64> },
+}
+
+impl ShockDistribution {
+    pub fn student_t(degrees_of_freedom: f64) -> Self {
+        Self::StudentT { degrees_of_freedom }
+    }
+
+    /// Bootstrap from a series of per-step returns, standardized to zero mean and unit variance
+    pub fn bootstrap(returns: &[f64]) -> Result<Self> {
+        if returns.len() < 2 {
+            return Err(anyhow::anyhow!(
+                "Bootstrap needs at least 2 returns, got {}",
+                returns.len()
+            ));
+        }
+        if returns.iter().any(|r| !r.is_finite()) {
+            return Err(anyhow::anyhow!("Bootstrap returns must be finite"));
+        }
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+        if std_dev == 0.0 {
+            return Err(anyhow::anyhow!("Bootstrap returns must not all be equal"));
+        }
+        Ok(Self::Bootstrap {
+            shocks: returns.iter().map(|r| (r - mean) / std_dev).collect(),
+        })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Normal => Ok(()),
+            Self::StudentT { degrees_of_freedom } => {
+                if degrees_of_freedom.is_nan() || *degrees_of_freedom <= 2.0 {
+                    return Err(anyhow::anyhow!(
+                        "Student's t degrees of freedom must exceed 2, got {}",
+                        degrees_of_freedom
+                    ));
+                }
+                Ok(())
+            }
+            Self::Bootstrap { shocks } => {
+                if shocks.is_empty() {
+                    return Err(anyhow::anyhow!("Bootstrap shock series is empty"));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Draw one shock with zero mean and unit variance
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match self {
+            Self::Normal => rng.sample(StandardNormal),
+            Self::StudentT { degrees_of_freedom } => {
+                let nu = *degrees_of_freedom;
+                match StudentT::new(nu) {
+                    // A t variate has variance nu / (nu - 2)
+                    Ok(t) => t.sample(rng) * ((nu - 2.0) / nu).sqrt(),
+                    Err(_) => rng.sample(StandardNormal),
+                }
+            }
+            Self::Bootstrap { shocks } => {
+                if shocks.is_empty() {
+                    return rng.sample(StandardNormal);
+                }
+                shocks[rng.gen_range(0..shocks.len())]
+            }
+        }
+    }
+}
+
+/// Sudden price jumps, Merton style: on a jump the price is multiplied by
+/// `exp(y)` with `y ~ N(mean_log_size, std_log_size)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JumpModel {
+    /// Chance of a jump on any one step
+    pub probability: f64,
+    /// Mean log price change on a jump; `ln(0.7)` is a typical 30% crash
+    pub mean_log_size: f64,
+    pub std_log_size: f64,
+}
+
+impl JumpModel {
+    pub fn new(probability: f64, mean_log_size: f64, std_log_size: f64) -> Self {
+        Self {
+            probability,
+            mean_log_size,
+            std_log_size,
+        }
+    }
+
+    /// Crashes averaging `mean_drop_pct` percent (e.g. 30.0), with the given log-size dispersion
+    pub fn crash(probability: f64, mean_drop_pct: f64, std_log_size: f64) -> Self {
+        Self::new(probability, (1.0 - mean_drop_pct / 100.0).max(f64::MIN_POSITIVE).ln(), std_log_size)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(0.0..=1.0).contains(&self.probability) {
+            return Err(anyhow::anyhow!(
+                "Jump probability must be in [0, 1], got {}",
+                self.probability
+            ));
+        }
+        if !self.mean_log_size.is_finite() || !self.std_log_size.is_finite() || self.std_log_size < 0.0 {
+            return Err(anyhow::anyhow!(
+                "Jump size must have a finite mean and non-negative standard deviation"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Log price change of this step's jump, or `None` if there is none
+    pub fn draw<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<f64> {
+        if !rng.gen_bool(self.probability.clamp(0.0, 1.0)) {
+            return None;
+        }
+        let z: f64 = rng.sample(StandardNormal);
+        Some(self.mean_log_size + self.std_log_size * z)
+    }
+}
+
+/// Jump components layered on the diffusion.
+///
+/// Each asset type's jumps hit its assets independently (a stablecoin depeg
+/// doesn't depeg every stablecoin). The market-wide jump hits every asset of
+/// the listed types on the same step with the same size, so correlated
+/// assets crash together.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JumpConfig {
+    #[serde(default)]
+    pub by_asset_type: HashMap<AssetType, JumpModel>,
+    #[serde(default)]
+    pub market: Option<MarketJump>,
+}
+
+/// A jump that hits many assets at once
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketJump {
+    pub model: JumpModel,
+    /// Asset types the jump hits; empty hits every asset
+    #[serde(default)]
+    pub asset_types: Vec<AssetType>,
+}
+
+impl MarketJump {
+    pub fn hits(&self, asset_type: &AssetType) -> bool {
+        self.asset_types.is_empty() || self.asset_types.contains(asset_type)
+    }
+}
+
+impl JumpConfig {
+    pub fn is_empty(&self) -> bool {
+        self.by_asset_type.is_empty() && self.market.is_none()
+    }
+
+    /// Independent jumps for every asset of `asset_type`
+    pub fn with_asset_type(mut self, asset_type: AssetType, model: JumpModel) -> Self {
+        self.by_asset_type.insert(asset_type, model);
+        self
+    }
+
+    /// A market-wide jump hitting `asset_types` (all assets if empty) together
+    pub fn with_market(mut self, model: JumpModel, asset_types: Vec<AssetType>) -> Self {
+        self.market = Some(MarketJump { model, asset_types });
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (asset_type, model) in &self.by_asset_type {
+            model
+                .validate()
+                .with_context(|| format!("Invalid jump model for {:?}", asset_type))?;
+        }
+        if let Some(market) = &self.market {
+            market.model.validate().context("Invalid market-wide jump model")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn draws(distribution: &ShockDistribution, count: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..count).map(|_| distribution.sample(&mut rng)).collect()
+    }
+
+    fn variance(shocks: &[f64]) -> f64 {
+        let mean = shocks.iter().sum::<f64>() / shocks.len() as f64;
+        shocks.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / shocks.len() as f64
+    }
+
+    fn beyond(shocks: &[f64], sigmas: f64) -> usize {
+        shocks.iter().filter(|x| x.abs() > sigmas).count()
+    }
+
+    #[test]
+    fn student_t_keeps_unit_variance_with_fatter_tails() {
+        let normal = draws(&ShockDistribution::Normal, 200_000);
+        let fat = draws(&ShockDistribution::student_t(5.0), 200_000);
+        assert!((variance(&normal) - 1.0).abs() < 0.02);
+        assert!((variance(&fat) - 1.0).abs() < 0.1, "t(5) variance {}", variance(&fat));
+
+        // Beyond four sigma: about 13 normal draws in 200,000, hundreds of t(3) ones
+        let fattest = draws(&ShockDistribution::student_t(3.0), 200_000);
+        assert!(beyond(&normal, 4.0) < 40);
+        assert!(beyond(&fattest, 4.0) > 10 * beyond(&normal, 4.0).max(1));
+    }
+
+    #[test]
+    fn bootstrap_resamples_standardized_returns() {
+        let bootstrap = ShockDistribution::bootstrap(&[0.01, 0.03, -0.02, 0.02]).unwrap();
+        let ShockDistribution::Bootstrap { shocks } = &bootstrap else { unreachable!() };
+        assert!(shocks.iter().sum::<f64>().abs() < 1e-12);
+        assert!((variance(shocks) - 1.0).abs() < 1e-12);
+
+        let drawn = draws(&bootstrap, 1000);
+        assert!(drawn.iter().all(|x| shocks.contains(x)));
+        assert!(shocks.iter().all(|x| drawn.contains(x)));
+    }
+
+    #[test]
+    fn invalid_distributions_are_rejected() {
+        assert!(ShockDistribution::student_t(2.0).validate().is_err());
+        assert!(ShockDistribution::student_t(f64::NAN).validate().is_err());
+        assert!(ShockDistribution::student_t(2.5).validate().is_ok());
+        assert!(ShockDistribution::bootstrap(&[0.01]).is_err());
+        assert!(ShockDistribution::bootstrap(&[0.01, 0.01]).is_err());
+        assert!(ShockDistribution::bootstrap(&[0.01, f64::INFINITY]).is_err());
+        assert!(ShockDistribution::Bootstrap { shocks: vec![] }.validate().is_err());
+    }
+
+    #[test]
+    fn jumps_land_at_their_probability_with_their_size() {
+        let crash = JumpModel::crash(0.2, 30.0, 0.0);
+        assert!((crash.mean_log_size.exp() - 0.7).abs() < 1e-12);
+        let mut rng = StdRng::seed_from_u64(42);
+        let jumps: Vec<f64> = (0..100_000).filter_map(|_| crash.draw(&mut rng)).collect();
+        assert!((jumps.len() as f64 / 100_000.0 - 0.2).abs() < 0.01, "{} jumps", jumps.len());
+        assert!(jumps.iter().all(|jump| *jump == crash.mean_log_size));
+
+        assert!(JumpModel::new(0.0, -0.3, 0.1).draw(&mut rng).is_none());
+        assert!(JumpModel::new(1.0, -0.3, 0.1).draw(&mut rng).is_some());
+    }
+
+    #[test]
+    fn invalid_jump_models_are_rejected() {
+        assert!(JumpModel::new(1.5, -0.3, 0.1).validate().is_err());
+        assert!(JumpModel::new(0.1, f64::NAN, 0.1).validate().is_err());
+        assert!(JumpModel::new(0.1, -0.3, -0.1).validate().is_err());
+        let config = JumpConfig::default()
+            .with_asset_type(AssetType::Crypto, JumpModel::new(0.1, -0.3, 0.1))
+            .with_market(JumpModel::new(2.0, -0.3, 0.1), vec![]);
+        let error = config.validate().unwrap_err();
+        assert!(format!("{:#}", error).contains("market-wide"), "{:#}", error);
+
+        let market = MarketJump { model: JumpModel::new(0.1, -0.3, 0.0), asset_types: vec![AssetType::Crypto] };
+        assert!(market.hits(&AssetType::Crypto));
+        assert!(!market.hits(&AssetType::Stablecoin));
+        assert!(MarketJump { asset_types: vec![], ..market }.hits(&AssetType::Stablecoin));
+    }
+}
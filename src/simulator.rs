@@ -1,147 +1,2295 @@
+use crate::builder::SimulatorBuilder;
+use crate::calendar::{TradingCalendar, TradingSession, CALENDAR_DAYS_PER_YEAR, TRADING_DAYS_PER_YEAR};
+use crate::events::{RunSummary, SimEvent, EVENT_CHANNEL_CAPACITY};
+use crate::fees::FeeModel;
+use crate::liquidity::PriceImpact;
+use crate::market::{AsyncMarketDataProvider, MarketDataProvider};
+use crate::market_view::{MarketView, PriceHistory};
+use crate::metrics::{ActiveReturns, RollingWindow, RunningMetrics};
+use crate::scenarios::{Regime, RegimeModel};
+use crate::shocks::{JumpConfig, ShockDistribution};
+use crate::sobol::QuasiRandomDraws;
+use crate::strategy::{rebalance_decisions, RoutingStrategy};
+use crate::transactions::{TradeSide, TransactionEntry, TransactionLog};
 use crate::types::*;
+use crate::yield_curve::YieldCurve;
 use anyhow::{Context, Result};
-use rust_decimal::Decimal;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
 use rust_decimal_macros::dec;
-use std::collections::HashMap;
-use time::OffsetDateTime;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use time::{Duration, OffsetDateTime};
+use tokio::sync::broadcast;
+
+/// Seconds in the 365-day year used to convert elapsed time to a year fraction
+const SECONDS_PER_YEAR: f64 = CALENDAR_DAYS_PER_YEAR * 86_400.0;
+
+/// Floor that keeps simulated prices strictly positive
+const MIN_PRICE: Decimal = dec!(0.000000000001);
+
+/// Margin borrowing settings
+#[derive(Debug, Clone)]
+pub struct MarginConfig {
+    /// Annual interest rate charged on the borrowed balance
+    pub borrow_rate: Decimal,
+    /// Minimum equity / positions value ratio before positions are force-liquidated
+    pub maintenance_margin: Decimal,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            borrow_rate: dec!(0.08),
+            maintenance_margin: dec!(0.25),
+        }
+    }
+}
+
+/// Limits on how often and how much a strategy may trade
+///
+/// Limits are applied in order: per-symbol cooldown, then the per-step decision
+/// count (in the order the strategy emitted them), then the per-step notional
+/// cap. The notional cap is allocated largest decision first; the decision that
+/// crosses the cap is clipped to the remaining budget and the rest are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct ThrottleConfig {
+    /// Minimum number of steps between decisions targeting the same symbol
+    pub cooldown_steps: Option<usize>,
+    /// Maximum traded notional per step as a fraction of portfolio value
+    pub max_step_notional_pct: Option<Decimal>,
+    /// Maximum number of decisions executed per step
+    pub max_decisions_per_step: Option<usize>,
+}
+
+/// Passive portfolio the strategy is compared against.
+///
+/// The benchmark is bought with the initial capital at step 0 and held without
+/// rebalancing. Its symbols follow the same simulated price paths and shocks as
+/// the strategy's, and are priced and described by the provider when one is set.
+#[derive(Debug, Clone)]
+pub enum Benchmark {
+    /// Hold a single symbol
+    Symbol(String),
+    /// 60% `growth`, 40% `income`
+    SixtyForty { growth: String, income: String },
+    /// Symbols and initial weights summing to one; build with [`Benchmark::basket`]
+    Basket(Vec<(String, Decimal)>),
+}
+
+impl Benchmark {
+    /// A basket of positively weighted symbols whose weights sum to one
+    pub fn basket(weights: Vec<(String, Decimal)>) -> Result<Self> {
+        if weights.is_empty() {
+            return Err(anyhow::anyhow!("benchmark basket is empty"));
+        }
+        if let Some((symbol, weight)) = weights.iter().find(|(_, weight)| *weight <= Decimal::ZERO) {
+            return Err(anyhow::anyhow!("benchmark weight for {} must be positive, got {}", symbol, weight));
+        }
+        let total: Decimal = weights.iter().map(|(_, weight)| *weight).sum();
+        if (total - Decimal::ONE).abs() > dec!(0.000001) {
+            return Err(anyhow::anyhow!("benchmark weights must sum to 1, got {}", total));
+        }
+        Ok(Self::Basket(weights))
+    }
+
+    /// Parse `SYMBOL` or a basket `SYMBOL:WEIGHT,SYMBOL:WEIGHT,...`
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if !spec.contains(':') {
+            if spec.is_empty() || spec.contains(',') {
+                return Err(anyhow::anyhow!("benchmark '{}' must be SYMBOL or SYMBOL:WEIGHT,...", spec));
+            }
+            return Ok(Self::Symbol(spec.to_string()));
+        }
+        let weights = spec
+            .split(',')
+            .map(|part| {
+                let (symbol, weight) = part
+                    .split_once(':')
+                    .ok_or_else(|| anyhow::anyhow!("benchmark component '{}' must be SYMBOL:WEIGHT", part))?;
+                let weight = weight
+                    .trim()
+                    .parse::<Decimal>()
+                    .with_context(|| format!("invalid benchmark weight '{}'", weight))?;
+                Ok((symbol.trim().to_string(), weight))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::basket(weights)
+    }
+
+    /// Symbols and their initial weights
+    pub fn weights(&self) -> Vec<(String, Decimal)> {
+        match self {
+            Self::Symbol(symbol) => vec![(symbol.clone(), Decimal::ONE)],
+            Self::SixtyForty { growth, income } => {
+                vec![(growth.clone(), dec!(0.6)), (income.clone(), dec!(0.4))]
+            }
+            Self::Basket(weights) => weights.clone(),
+        }
+    }
+}
+
+/// Stops new buying once drawdown from the running peak exceeds a threshold
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// Drawdown from peak, in percent, that trips the breaker
+    pub max_drawdown_pct: f64,
+    /// Sell every position to cash when the breaker trips
+    pub liquidate: bool,
+}
+
+/// Summary of one step, cheap to build from counters the simulator already keeps
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct StepOutcome {
+    pub step: usize,
+    /// Portfolio value at the end of the previous step
+    pub value_before: Decimal,
+    pub value_after: Decimal,
+    pub decisions_executed: usize,
+    pub decisions_rejected: usize,
+    pub fees_paid: Decimal,
+    /// Drawdown from the running peak after the step, in percent
+    pub drawdown_pct: f64,
+    /// Step at which the circuit breaker tripped, if it has; buys are no longer executed
+    pub halted_at: Option<usize>,
+}
+
+impl StepOutcome {
+    pub fn is_halted(&self) -> bool {
+        self.halted_at.is_some()
+    }
+
+    pub fn value_change(&self) -> Decimal {
+        self.value_after - self.value_before
+    }
+}
+
+impl std::fmt::Display for StepOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "step {:>5}  value {:>16.2} -> {:>16.2}  executed {:>3}  rejected {:>3}  fees {:>10.2}  drawdown {:>6.2}%",
+            self.step,
+            self.value_before,
+            self.value_after,
+            self.decisions_executed,
+            self.decisions_rejected,
+            self.fees_paid,
+            self.drawdown_pct,
+        )?;
+        if let Some(at_step) = self.halted_at {
+            write!(f, "  halted at step {}", at_step)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counters captured at the start of a step, diffed into its `StepOutcome`
+#[derive(Debug, Clone, Copy)]
+struct StepMark {
+    value: Decimal,
+    decisions: usize,
+    fees: Decimal,
+}
+
+/// Venue order constraints for one symbol
+#[derive(Debug, Clone, Default)]
+pub struct TradingRules {
+    /// Decisions whose notional (after lot rounding) is below this are rejected
+    pub min_notional: Decimal,
+    /// Quantity step; quantities are rounded down to a multiple of it
+    pub lot_size: Option<Decimal>,
+}
+
+/// How the book is valued at the end of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerminalValuation {
+    /// Positions are marked at their last simulated price
+    #[default]
+    MarkToMarket,
+    /// Positions are valued at what selling all of them at the final prices
+    /// would realize after the fees the raise-cash waterfall pays. With
+    /// `use_depth`, each sale also pays its price impact: the configured
+    /// `price_impact` model's, or a constant-product pool's without one.
+    LiquidateAll { use_depth: bool },
+}
+
+/// Which portfolio snapshots the simulator keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryPolicy {
+    /// Keep a snapshot for every step
+    #[default]
+    Full,
+    /// Keep the initial snapshot and every Nth step after it
+    EveryN(usize),
+    /// Keep only the most recent N snapshots
+    LastN(usize),
+}
+
+/// What happens to the income positions earn from their yield
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YieldIncome {
+    /// Credit it to cash
+    #[default]
+    Cash,
+    /// Buy more of the paying position at its current price
+    Reinvest,
+}
+
+/// What the simulator does with a decision that fails to execute
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecisionFailurePolicy {
+    /// Log the rejection and return the error from the step
+    Abort,
+    /// Log the rejection and carry on with the remaining decisions
+    #[default]
+    Skip,
+    /// Retry failed buys sized to the cash on hand less fees; skip anything else
+    ScaleDown,
+}
+
+/// Simulator configuration
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    pub risk_parameters: RiskParameters,
+    /// Enables borrowing up to `risk_parameters.max_leverage` times equity
+    pub margin: Option<MarginConfig>,
+    /// Global decision throttling
+    pub throttle: ThrottleConfig,
+    /// Throttling overrides keyed by strategy name
+    pub strategy_throttles: HashMap<String, ThrottleConfig>,
+    pub terminal_valuation: TerminalValuation,
+    /// Quote-side depth of the pool each symbol trades in, overriding its
+    /// asset's liquidity; symbols with neither are treated as infinitely liquid
+    pub market_depth: HashMap<String, Decimal>,
+    /// Price impact charged on top of the execution cost of trades in symbols
+    /// with known liquidity; `None` fills every size at the quoted price
+    pub price_impact: Option<PriceImpact>,
+    /// Pairwise return correlations; missing pairs are uncorrelated
+    pub correlations: HashMap<(String, String), f64>,
+    /// Correlation for symbol pairs missing from `correlations`
+    pub default_correlation: f64,
+    /// Added to every asset's annual expected return in the price walk
+    pub drift_adjustment: f64,
+    /// Scales every asset's volatility in the price walk
+    pub volatility_multiplier: f64,
+    /// Shape of every asset's per-step price shock
+    pub shock_distribution: ShockDistribution,
+    /// Negate every diffusion shock, giving the antithetic mirror of the path the
+    /// same seed draws otherwise. Jumps and regime switches are not mirrored
+    pub antithetic: bool,
+    /// Take normal shocks from successive coordinates of a Sobol point instead of
+    /// the RNG; non-normal shocks, and draws beyond the sequence's dimensions, still use the RNG
+    pub quasi_random: Option<QuasiRandomDraws>,
+    /// Shock distribution overrides keyed by symbol
+    pub shock_distributions: HashMap<String, ShockDistribution>,
+    /// Sudden price jumps on top of the diffusion; empty by default
+    pub jumps: JumpConfig,
+    /// Markov regimes stepped every step; each regime's drift shift and volatility
+    /// multiplier stack on the settings above, and its correlation replaces
+    /// `default_correlation`
+    pub regimes: Option<RegimeModel>,
+    /// RNG seed; `None` seeds from entropy
+    pub seed: Option<u64>,
+    /// Simulated time at step 0; `None` starts from the portfolio's timestamp
+    pub start_time: Option<OffsetDateTime>,
+    /// Simulated time covered by one step
+    pub time_step: Duration,
+    /// Business-day calendar; when set, the clock skips closed days and
+    /// statistics annualize over 252 trading days instead of 365
+    pub calendar: Option<TradingCalendar>,
+    /// Session overrides keyed by symbol; others follow their asset type
+    pub sessions: HashMap<String, TradingSession>,
+    /// Annual interest rate earned on idle cash
+    pub cash_rate: Decimal,
+    /// Where positions' yield income goes
+    pub yield_income: YieldIncome,
+    /// Snapshot retention; metrics stay exact under every policy
+    pub history_policy: HistoryPolicy,
+    /// Window length, in steps, for rolling Sharpe/volatility; `None` disables them
+    pub rolling_window: Option<usize>,
+    /// Fee charged on each leg of `Simulator::rebalance_to`, as a fraction of notional
+    pub rebalance_cost_rate: Decimal,
+    /// Currency the portfolio's cash and values are reported in
+    pub reporting_currency: String,
+    /// Opening cash held in other currencies, converted at the provider's FX rates
+    pub cash_balances: HashMap<String, Decimal>,
+    /// Passive comparison portfolio; `None` disables benchmark metrics
+    pub benchmark: Option<Benchmark>,
+    /// Handling of decisions that fail to execute (e.g. insufficient cash)
+    pub failure_policy: DecisionFailurePolicy,
+    /// Drawdown halt; `None` never halts
+    pub circuit_breaker: Option<CircuitBreaker>,
+    /// Assets priced and evolved from step 0, whether or not any are held
+    pub universe: Vec<Asset>,
+    /// Zero curve bond assets are repriced on under curve shifts; `None`
+    /// discounts each bond at a flat curve at its own yield
+    pub yield_curve: Option<YieldCurve>,
+    /// Most open positions; decisions opening one more are rejected. `None` is unlimited
+    pub max_positions: Option<usize>,
+    /// Order constraints keyed by symbol; missing symbols are unconstrained
+    pub trading_rules: HashMap<String, TradingRules>,
+    /// Prices every executed decision; `None` keeps the execution cost each
+    /// strategy quotes (and `rebalance_cost_rate` for rebalances)
+    pub fee_model: Option<Arc<dyn FeeModel>>,
+    /// Least simulated time between strategy runs; prices, accruals and risk
+    /// checks still update every step. `None` runs the strategy every step
+    pub rebalance_interval: Option<Duration>,
+    /// Annual return the Sortino ratio counts shortfalls below, as a fraction
+    pub minimum_acceptable_return: f64,
+    /// Record every price update, execution, fee, liquidation, and shock
+    pub record_transactions: bool,
+    /// Store per-position detail in every snapshot (multiplies history memory)
+    pub record_positions: bool,
+    /// Record every symbol's price at every step, regardless of `history_policy`
+    pub record_price_history: bool,
+    /// Prices at or below this mark an asset as defaulted and write it off
+    pub default_price_threshold: Decimal,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            risk_parameters: RiskParameters::default(),
+            margin: None,
+            throttle: ThrottleConfig::default(),
+            strategy_throttles: HashMap::new(),
+            terminal_valuation: TerminalValuation::default(),
+            market_depth: HashMap::new(),
+            price_impact: None,
+            correlations: HashMap::new(),
+            default_correlation: 0.0,
+            drift_adjustment: 0.0,
+            volatility_multiplier: 1.0,
+            shock_distribution: ShockDistribution::default(),
+            antithetic: false,
+            quasi_random: None,
+            shock_distributions: HashMap::new(),
+            jumps: JumpConfig::default(),
+            regimes: None,
+            seed: None,
+            start_time: None,
+            time_step: Duration::days(1),
+            calendar: None,
+            sessions: HashMap::new(),
+            cash_rate: Decimal::ZERO,
+            yield_income: YieldIncome::default(),
+            history_policy: HistoryPolicy::Full,
+            rolling_window: None,
+            rebalance_cost_rate: dec!(0.002),
+            reporting_currency: CASH_SYMBOL.to_string(),
+            cash_balances: HashMap::new(),
+            benchmark: None,
+            failure_policy: DecisionFailurePolicy::default(),
+            circuit_breaker: None,
+            universe: vec![],
+            yield_curve: None,
+            max_positions: None,
+            trading_rules: HashMap::new(),
+            fee_model: None,
+            rebalance_interval: None,
+            minimum_acceptable_return: 0.0,
+            record_transactions: false,
+            record_positions: false,
+            record_price_history: false,
+            default_price_threshold: dec!(0.00000001),
+        }
+    }
+}
+
+impl SimulatorConfig {
+    /// Length of one step as a fraction of a (trading) year
+    pub fn dt(&self) -> f64 {
+        self.time_step.as_seconds_f64() / (self.days_per_year() * 86_400.0)
+    }
+
+    /// 252 with a trading calendar, 365 without
+    pub fn days_per_year(&self) -> f64 {
+        if self.calendar.is_some() {
+            TRADING_DAYS_PER_YEAR
+        } else {
+            CALENDAR_DAYS_PER_YEAR
+        }
+    }
+
+    /// Steps per year, used to annualize per-step statistics
+    pub fn periods_per_year(&self) -> f64 {
+        let dt = self.dt();
+        if dt > 0.0 {
+            1.0 / dt
+        } else {
+            crate::metrics::PERIODS_PER_YEAR
+        }
+    }
+
+    /// Whether `asset` trades around the clock or only on calendar days
+    pub fn session(&self, asset: &Asset) -> TradingSession {
+        self.sessions
+            .get(&asset.symbol)
+            .copied()
+            .unwrap_or_else(|| TradingSession::for_asset_type(&asset.asset_type))
+    }
+
+    /// Shock distribution for `symbol`: its override, else the global one
+    pub fn shock_distribution(&self, symbol: &str) -> &ShockDistribution {
+        self.shock_distributions.get(symbol).unwrap_or(&self.shock_distribution)
+    }
+
+    /// Set the correlation between two symbols (order doesn't matter)
+    pub fn set_correlation(&mut self, a: &str, b: &str, correlation: f64) {
+        self.correlations.insert((a.to_string(), b.to_string()), correlation);
+    }
+
+    /// Correlation between two symbols, 1.0 on the diagonal and `default_correlation` if unset
+    pub fn correlation(&self, a: &str, b: &str) -> f64 {
+        self.correlation_or(a, b, self.default_correlation)
+    }
+
+    /// Correlation between two symbols, 1.0 on the diagonal and `default` if unset
+    pub fn correlation_or(&self, a: &str, b: &str, default: f64) -> f64 {
+        if a == b {
+            return 1.0;
+        }
+        self.correlations
+            .get(&(a.to_string(), b.to_string()))
+            .or_else(|| self.correlations.get(&(b.to_string(), a.to_string())))
+            .copied()
+            .unwrap_or(default)
+    }
+}
+
+/// Market data source used by the simulator for asset metadata
+pub type BoxedProvider = Box<dyn MarketDataProvider + Send + Sync>;
 
 /// Main simulator engine for capital routing
 pub struct Simulator {
     portfolio: Portfolio,
     strategy: crate::strategy::Strategy,
+    config: SimulatorConfig,
+    provider: Option<BoxedProvider>,
     step_count: usize,
-    portfolio_history: Vec<PortfolioSnapshot>,
+    clock: OffsetDateTime,
+    step_elapsed: Duration,
+    portfolio_history: VecDeque<PortfolioSnapshot>,
+    metrics: RunningMetrics,
+    rolling: Option<RollingWindow>,
+    benchmark: Option<Portfolio>,
+    benchmark_metrics: RunningMetrics,
+    active_returns: ActiveReturns,
+    rolling_metrics: Vec<RollingMetrics>,
     market_state: HashMap<String, Decimal>,
+    trades: Vec<Trade>,
+    fills: Vec<Fill>,
+    decision_log: Vec<ExecutedDecision>,
+    last_traded: HashMap<String, usize>,
+    peak_leverage: f64,
+    margin_calls: usize,
+    jumps: usize,
+    fees_paid: Decimal,
+    /// Yield income positions have earned
+    income_earned: Decimal,
+    halted_at: Option<usize>,
+    /// When the strategy last ran
+    last_rebalanced: Option<OffsetDateTime>,
+    rng: StdRng,
+    /// Factor keyed by the symbols and the default correlation it was built for
+    cholesky_cache: Option<(Vec<String>, f64, Vec<Vec<f64>>)>,
+    /// Current regime, when a regime model is configured
+    regime: usize,
+    /// Steps spent in each regime
+    regime_occupancy: Vec<usize>,
+    /// Next Sobol coordinate to draw, when quasi-random draws are configured
+    quasi_dimension: usize,
+    scheduled_shocks: Vec<MarketShock>,
+    scheduled_cash_flows: Vec<CashFlow>,
+    cash_flows: Vec<CashFlow>,
+    /// Tradable assets keyed by symbol, with their latest simulated prices
+    universe: HashMap<String, Asset>,
+    price_history: HashMap<String, Vec<Decimal>>,
+    price_history_start: HashMap<String, usize>,
+    /// Net cash flow applied in the current step, taken out of its return
+    step_cash_flow: Decimal,
+    applied_shocks: Vec<MarketShock>,
+    transaction_log: Option<TransactionLog>,
+    events: Option<broadcast::Sender<SimEvent>>,
 }
 
 impl Simulator {
     /// Create a new simulator with initial capital and strategy
     pub fn new(initial_capital: f64, strategy: crate::strategy::Strategy) -> Self {
-        let portfolio = Portfolio::new(
-            Decimal::try_from(initial_capital).unwrap_or(Decimal::ZERO)
-        );
+        SimulatorBuilder::new()
+            .capital(initial_capital)
+            .strategy(strategy)
+            .assemble()
+    }
+
+    /// Start configuring a simulator
+    pub fn builder() -> SimulatorBuilder {
+        SimulatorBuilder::new()
+    }
+
+    /// Create a new simulator with an explicit configuration
+    pub fn with_config(
+        initial_capital: f64,
+        strategy: crate::strategy::Strategy,
+        config: SimulatorConfig,
+    ) -> Self {
+        let portfolio = Self::opening_portfolio(initial_capital, &config);
         
-        Self {
+        Self::from_portfolio_with_config(portfolio, strategy, config)
+    }
+
+    /// `initial_capital` in the reporting currency plus the configured foreign cash
+    pub(crate) fn opening_portfolio(initial_capital: f64, config: &SimulatorConfig) -> Portfolio {
+        let mut portfolio = Portfolio::new(Decimal::try_from(initial_capital).unwrap_or(Decimal::ZERO));
+        portfolio.reporting_currency = config.reporting_currency.clone();
+        for (currency, balance) in &config.cash_balances {
+            portfolio.credit_cash(currency, *balance);
+        }
+        portfolio
+    }
+
+    /// Start a simulation from an existing portfolio
+    pub fn from_portfolio(portfolio: Portfolio, strategy: crate::strategy::Strategy) -> Self {
+        Self::from_portfolio_with_config(portfolio, strategy, SimulatorConfig::default())
+    }
+
+    /// Start a simulation from an existing portfolio with an explicit configuration.
+    ///
+    /// Market state is seeded from each position's current price, and the seeded
+    /// book is recorded as the initial snapshot that returns are measured against.
+    pub fn from_portfolio_with_config(
+        portfolio: Portfolio,
+        strategy: crate::strategy::Strategy,
+        config: SimulatorConfig,
+    ) -> Self {
+        Self::from_parts(portfolio, strategy, config, None)
+    }
+
+    pub(crate) fn from_parts(
+        mut portfolio: Portfolio,
+        strategy: crate::strategy::Strategy,
+        config: SimulatorConfig,
+        provider: Option<BoxedProvider>,
+    ) -> Self {
+        portfolio.max_positions = config.max_positions;
+        portfolio.update_total_value();
+        let clock = config.start_time.unwrap_or(portfolio.timestamp);
+        portfolio.timestamp = clock;
+        
+        let market_state = portfolio.positions
+            .iter()
+            .map(|(symbol, position)| (symbol.clone(), position.asset.current_price))
+            .collect();
+        
+        let rng = Self::make_rng(config.seed);
+        let transaction_log = config.record_transactions.then(TransactionLog::new);
+        let metrics = RunningMetrics::with_periods_per_year(config.periods_per_year())
+            .with_minimum_acceptable_return(config.minimum_acceptable_return);
+        let step_elapsed = config.time_step;
+        let benchmark_metrics = RunningMetrics::with_periods_per_year(config.periods_per_year());
+        let rolling = config
+            .rolling_window
+            .map(|window| RollingWindow::new(window, config.periods_per_year()));
+        let regime = config.regimes.as_ref().map_or(0, |model| model.initial);
+        let regime_occupancy = config.regimes.as_ref().map_or(vec![], |model| vec![0; model.regimes.len()]);
+        let mut simulator = Self {
             portfolio,
             strategy,
+            config,
+            provider,
             step_count: 0,
-            portfolio_history: vec![],
-            market_state: HashMap::new(),
+            clock,
+            step_elapsed,
+            portfolio_history: VecDeque::new(),
+            metrics,
+            rolling,
+            benchmark: None,
+            benchmark_metrics,
+            active_returns: ActiveReturns::new(),
+            rolling_metrics: vec![],
+            market_state,
+            trades: vec![],
+            fills: vec![],
+            decision_log: vec![],
+            last_traded: HashMap::new(),
+            peak_leverage: 0.0,
+            margin_calls: 0,
+            jumps: 0,
+            fees_paid: Decimal::ZERO,
+            income_earned: Decimal::ZERO,
+            halted_at: None,
+            last_rebalanced: None,
+            rng,
+            cholesky_cache: None,
+            regime,
+            regime_occupancy,
+            quasi_dimension: 0,
+            scheduled_shocks: vec![],
+            scheduled_cash_flows: vec![],
+            cash_flows: vec![],
+            step_cash_flow: Decimal::ZERO,
+            universe: HashMap::new(),
+            price_history: HashMap::new(),
+            price_history_start: HashMap::new(),
+            applied_shocks: vec![],
+            transaction_log,
+            events: None,
+        };
+        simulator.seed_universe();
+        simulator.refresh_fx_rates();
+        simulator.benchmark = simulator.build_benchmark();
+        simulator.peak_leverage = simulator.leverage();
+        simulator.record_snapshot();
+        simulator
+    }
+
+    /// Update the portfolio's FX rates for every foreign currency it holds from the provider.
+    ///
+    /// Currencies the provider can't quote keep their last known rate.
+    fn refresh_fx_rates(&mut self) {
+        let Some(provider) = self.provider.as_deref() else {
+            return;
+        };
+        let reporting = self.portfolio.reporting_currency.clone();
+        let currencies: Vec<String> = self.portfolio.foreign_cash.keys().cloned().collect();
+        for currency in currencies {
+            if let Ok(rate) = provider.get_fx_rate(&currency, &reporting) {
+                self.portfolio.set_fx_rate(&currency, rate);
+            }
+        }
+        self.portfolio.update_total_value();
+    }
+
+    /// Buy the configured benchmark with the book's starting value
+    fn build_benchmark(&mut self) -> Option<Portfolio> {
+        let benchmark = self.config.benchmark.clone()?;
+        let mut portfolio = Portfolio::new(self.portfolio.total_value);
+        portfolio.timestamp = self.clock;
+        
+        for (symbol, weight) in benchmark.weights() {
+            let asset = self.new_asset(&symbol, Decimal::ZERO);
+            if asset.current_price <= Decimal::ZERO {
+                continue;
+            }
+            let price = asset.current_price;
+            let quantity = self.portfolio.total_value * weight / price;
+            self.market_state.entry(symbol).or_insert(price);
+            portfolio.add_position(Position::new(asset, quantity, price));
+        }
+        Some(portfolio)
+    }
+
+    /// Load the configured universe, adding its prices to market state (held positions keep theirs)
+    fn seed_universe(&mut self) {
+        self.universe = self.config
+            .universe
+            .iter()
+            .map(|asset| (asset.symbol.clone(), asset.clone()))
+            .collect();
+        for asset in self.universe.values() {
+            self.market_state.entry(asset.symbol.clone()).or_insert(asset.current_price);
+        }
+    }
+
+    /// Asset description for a symbol entering the book: the universe's entry,
+    /// else the provider's data when one is set
+    fn new_asset(&self, symbol: &str, fallback_yield: Decimal) -> Asset {
+        if let Some(asset) = self.universe.get(symbol) {
+            return Asset {
+                current_price: self.quote_price(symbol),
+                ..asset.clone()
+            };
+        }
+        let provider = self.provider.as_deref();
+        Asset {
+            symbol: symbol.to_string(),
+            name: format!("Asset {}", symbol),
+            asset_type: provider
+                .and_then(|p| p.get_asset_type(symbol).ok())
+                .unwrap_or(crate::types::AssetType::Crypto),
+            current_price: self.quote_price(symbol),
+            volatility: provider
+                .and_then(|p| p.get_volatility(symbol).ok())
+                .unwrap_or(dec!(0.02)),
+            yield_rate: provider
+                .and_then(|p| p.get_yield_rate(symbol).ok())
+                .unwrap_or(fallback_yield),
+            expected_return: provider
+                .and_then(|p| p.get_expected_return(symbol).ok())
+                .unwrap_or(Decimal::ZERO),
+            bond: provider.and_then(|p| p.get_bond_details(symbol).ok().flatten()),
+            liquidity: provider.and_then(|p| p.get_liquidity(symbol).ok().flatten()),
+        }
+    }
+
+    /// Configuration the simulator was built with
+    pub fn config(&self) -> &SimulatorConfig {
+        &self.config
+    }
+
+    /// The book as of the last step
+    pub fn portfolio(&self) -> &Portfolio {
+        &self.portfolio
+    }
+
+    /// Description of `symbol` as held, else as known to the universe, at its current price
+    pub fn asset(&self, symbol: &str) -> Option<Asset> {
+        if let Some(position) = self.portfolio.positions.get(symbol) {
+            return Some(position.asset.clone());
+        }
+        self.universe.get(symbol).map(|asset| Asset { current_price: self.quote_price(symbol), ..asset.clone() })
+    }
+
+    /// Liquidity `symbol` trades against: its configured market depth, else
+    /// the held or universe asset's, else the provider's
+    pub fn liquidity(&self, symbol: &str) -> Option<Decimal> {
+        if let Some(depth) = self.config.market_depth.get(symbol) {
+            return Some(*depth);
+        }
+        self.asset(symbol)
+            .and_then(|asset| asset.liquidity)
+            .or_else(|| self.provider.as_deref().and_then(|p| p.get_liquidity(symbol).ok().flatten()))
+    }
+
+    /// Liquidity of every configured, held, or universe symbol that has one
+    fn known_liquidity(&self) -> HashMap<String, Decimal> {
+        let mut liquidity: HashMap<String, Decimal> = self.known_assets()
+            .into_iter()
+            .filter_map(|asset| Some((asset.symbol, asset.liquidity?)))
+            .collect();
+        liquidity.extend(self.config.market_depth.iter().map(|(symbol, depth)| (symbol.clone(), *depth)));
+        liquidity
+    }
+
+    /// Every held or universe asset, one per symbol, sorted by symbol
+    pub fn known_assets(&self) -> Vec<Asset> {
+        let mut symbols: Vec<&String> = self.portfolio.positions.keys().chain(self.universe.keys()).collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols.into_iter().filter_map(|symbol| self.asset(symbol)).collect()
+    }
+
+    fn make_rng(seed: Option<u64>) -> StdRng {
+        match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
         }
     }
 
+    /// Reset to a fresh run with new capital and strategy, keeping the configuration.
+    ///
+    /// Collections are cleared rather than reallocated, so a reused simulator keeps
+    /// its history capacity. The result must be indistinguishable from a freshly
+    /// constructed simulator; keep this in sync with `from_portfolio_with_config`.
+    pub fn reset(&mut self, initial_capital: f64, strategy: crate::strategy::Strategy) {
+        let opening = Self::opening_portfolio(initial_capital, &self.config);
+        self.portfolio.positions.clear();
+        self.portfolio.cash = opening.cash;
+        self.portfolio.reporting_currency = opening.reporting_currency;
+        self.portfolio.foreign_cash = opening.foreign_cash;
+        self.portfolio.fx_rates.clear();
+        self.portfolio.borrowed = Decimal::ZERO;
+        self.portfolio.defaulted.clear();
+        self.portfolio.max_positions = self.config.max_positions;
+        self.clock = self.config.start_time.unwrap_or_else(OffsetDateTime::now_utc);
+        self.step_elapsed = self.config.time_step;
+        self.portfolio.timestamp = self.clock;
+        self.portfolio.update_total_value();
+        
+        self.strategy = strategy;
+        self.step_count = 0;
+        self.portfolio_history.clear();
+        self.metrics.reset();
+        self.benchmark_metrics.reset();
+        self.active_returns.reset();
+        if let Some(rolling) = &mut self.rolling {
+            rolling.clear();
+        }
+        self.rolling_metrics.clear();
+        self.market_state.clear();
+        self.seed_universe();
+        self.trades.clear();
+        self.fills.clear();
+        self.decision_log.clear();
+        self.last_traded.clear();
+        self.scheduled_shocks.clear();
+        self.scheduled_cash_flows.clear();
+        self.cash_flows.clear();
+        self.step_cash_flow = Decimal::ZERO;
+        self.price_history.clear();
+        self.price_history_start.clear();
+        self.applied_shocks.clear();
+        if let Some(log) = &mut self.transaction_log {
+            log.clear();
+        }
+        self.margin_calls = 0;
+        self.jumps = 0;
+        self.regime = self.config.regimes.as_ref().map_or(0, |model| model.initial);
+        self.regime_occupancy.iter_mut().for_each(|steps| *steps = 0);
+        self.quasi_dimension = 0;
+        self.fees_paid = Decimal::ZERO;
+        self.income_earned = Decimal::ZERO;
+        self.halted_at = None;
+        self.last_rebalanced = None;
+        self.rng = Self::make_rng(self.config.seed);
+        self.refresh_fx_rates();
+        self.benchmark = self.build_benchmark();
+        self.peak_leverage = self.leverage();
+        self.record_snapshot();
+    }
+
+    /// [`reset`](Self::reset) onto a new seed, so one simulator can serve runs on different paths
+    pub fn reset_with_seed(&mut self, initial_capital: f64, strategy: crate::strategy::Strategy, seed: u64) {
+        self.config.seed = Some(seed);
+        self.reset(initial_capital, strategy);
+    }
+
     /// Execute one simulation step
-    pub fn step(&mut self) -> Result<()> {
-        self.step_count += 1;
+    pub fn step(&mut self) -> Result<StepOutcome> {
+        let mark = self.mark();
+        self.advance_clock();
         
         // Update market prices (simulated)
         self.update_market_prices()?;
         
-        // Get routing decisions from strategy
-        let decisions = self.strategy.generate_routing_decisions(
-            &self.portfolio,
-            &self.market_state,
-        )?;
+        self.route_and_record(mark, None)
+    }
+
+    fn mark(&self) -> StepMark {
+        StepMark {
+            value: self.portfolio.total_value,
+            decisions: self.decision_log.len(),
+            fees: self.fees_paid,
+        }
+    }
+
+    /// Subscribe to progress events.
+    ///
+    /// The channel is created on the first call, so simulators nobody listens to
+    /// pay nothing. Sending never blocks `step`: a receiver that falls more than
+    /// `EVENT_CHANNEL_CAPACITY` events behind gets `RecvError::Lagged` and skips ahead.
+    pub fn events(&mut self) -> broadcast::Receiver<SimEvent> {
+        self.events
+            .get_or_insert_with(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Broadcast an event if anyone has subscribed; `make` only runs when needed
+    fn emit(&self, make: impl FnOnce() -> SimEvent) {
+        if let Some(events) = &self.events {
+            // An error only means every receiver has been dropped
+            let _ = events.send(make());
+        }
+    }
+
+    /// Current simulated time
+    pub fn current_time(&self) -> OffsetDateTime {
+        self.clock
+    }
+
+    /// Move to the next step, advancing the simulated clock by one time step
+    /// and past any closed days on the calendar
+    fn advance_clock(&mut self) {
+        let mut next = self.clock + self.config.time_step;
+        if let Some(calendar) = &self.config.calendar {
+            next = calendar.roll_forward(next);
+        }
+        self.advance_clock_to(next);
+    }
+
+    /// Move to the next step with the simulated clock at `time`
+    fn advance_clock_to(&mut self, time: OffsetDateTime) {
+        self.step_count += 1;
+        self.step_elapsed = time - self.clock;
+        self.clock = time;
+        self.portfolio.timestamp = self.clock;
+    }
+
+    /// Calendar time covered by the current step as a fraction of a 365-day year
+    fn elapsed_years(&self) -> f64 {
+        self.step_elapsed.as_seconds_f64() / SECONDS_PER_YEAR
+    }
+
+    /// Schedule a price shock of `pct_change` percent for `symbol` at `step`.
+    ///
+    /// Use `MarketShock::ALL_SYMBOLS` (`*`) for a market-wide move. Shocks apply
+    /// after that step's normal price update, before the strategy runs, and
+    /// several shocks may target the same step.
+    pub fn schedule_shock(&mut self, step: usize, symbol: &str, pct_change: Decimal) -> Result<()> {
+        if step <= self.step_count {
+            return Err(anyhow::anyhow!(
+                "Cannot schedule shock for step {}: simulation is already at step {}",
+                step,
+                self.step_count
+            ));
+        }
         
-        // Execute routing decisions
-        for decision in decisions {
-            self.execute_routing(decision)?;
+        self.scheduled_shocks.push(MarketShock {
+            step,
+            symbol: symbol.to_string(),
+            pct_change,
+        });
+        Ok(())
+    }
+
+    /// Schedule a deposit (positive `amount`) or withdrawal (negative) of
+    /// reporting-currency cash for a future step.
+    ///
+    /// Cash flows apply after that step's shocks, before the strategy runs.
+    /// A withdrawal larger than the cash on hand sells every position pro rata,
+    /// paying fees, and is capped at the cash the book can raise.
+    pub fn schedule_cash_flow(&mut self, step: usize, amount: Decimal) -> Result<()> {
+        if step <= self.step_count {
+            return Err(anyhow::anyhow!(
+                "Cannot schedule cash flow for step {}: simulation is already at step {}",
+                step,
+                self.step_count
+            ));
         }
         
-        // Update portfolio value
-        self.portfolio.update_total_value();
+        self.scheduled_cash_flows.push(CashFlow { step, amount });
+        Ok(())
+    }
+
+    /// Move the income yield of `symbol` (or every symbol, for `MarketShock::ALL_SYMBOLS`) by `delta`
+    pub fn shift_yield(&mut self, symbol: &str, delta: Decimal) {
+        let applies = |candidate: &str| symbol == MarketShock::ALL_SYMBOLS || candidate == symbol;
+        let benchmark = self.benchmark.iter_mut().flat_map(|b| b.positions.iter_mut());
+        for (held, position) in self.portfolio.positions.iter_mut().chain(benchmark) {
+            if applies(held) {
+                position.asset.yield_rate += delta;
+            }
+        }
+        for (known, asset) in self.universe.iter_mut() {
+            if applies(known) {
+                asset.yield_rate += delta;
+            }
+        }
+    }
+
+    /// Set the income yield of `symbol` to `rate` wherever it is held or known,
+    /// replacing the yield its position was opened with
+    pub fn set_yield_rate(&mut self, symbol: &str, rate: Decimal) {
+        let benchmark = self.benchmark.iter_mut().flat_map(|b| b.positions.iter_mut());
+        for (held, position) in self.portfolio.positions.iter_mut().chain(benchmark) {
+            if held == symbol {
+                position.asset.yield_rate = rate;
+            }
+        }
+        if let Some(asset) = self.universe.get_mut(symbol) {
+            asset.yield_rate = rate;
+        }
+    }
+
+    /// Symbols of every held, benchmark, or universe asset of `asset_type`, sorted
+    pub fn symbols_of_type(&self, asset_type: &AssetType) -> Vec<String> {
+        let benchmark = self.benchmark.iter().flat_map(|b| b.positions.values());
+        let mut symbols: Vec<String> = self.portfolio.positions
+            .values()
+            .chain(benchmark)
+            .map(|position| &position.asset)
+            .chain(self.universe.values())
+            .filter(|asset| &asset.asset_type == asset_type)
+            .map(|asset| asset.symbol.clone())
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    /// Deposits and withdrawals applied so far
+    pub fn cash_flows(&self) -> &[CashFlow] {
+        &self.cash_flows
+    }
+
+    fn apply_cash_flows(&mut self) {
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scheduled_cash_flows)
+            .into_iter()
+            .partition(|flow| flow.step == self.step_count);
+        self.scheduled_cash_flows = pending;
         
-        // Record snapshot
-        self.record_snapshot();
+        for flow in due {
+            let mut amount = flow.amount;
+            if amount < Decimal::ZERO {
+                let shortfall = -amount - self.portfolio.cash;
+                if shortfall > Decimal::ZERO {
+                    self.raise_cash(shortfall);
+                }
+                amount = amount.max(-self.portfolio.cash.max(Decimal::ZERO));
+            }
+            
+            self.portfolio.cash += amount;
+            self.portfolio.update_total_value();
+            self.step_cash_flow += amount;
+            if let Some(log) = &mut self.transaction_log {
+                log.push(TransactionEntry::CashFlow {
+                    step: flow.step,
+                    timestamp: self.clock,
+                    symbol: self.portfolio.reporting_currency.clone(),
+                    amount,
+                });
+            }
+            self.cash_flows.push(CashFlow { step: flow.step, amount });
+        }
+    }
+
+    /// Sell every position pro rata to raise `shortfall` in cash after fees
+    fn raise_cash(&mut self, shortfall: Decimal) {
+        let positions_value = self.portfolio.positions_value();
+        if positions_value <= Decimal::ZERO {
+            return;
+        }
+        let cost_rate = self.config.rebalance_cost_rate;
+        let gross = if cost_rate < Decimal::ONE {
+            (shortfall / (Decimal::ONE - cost_rate)).min(positions_value)
+        } else {
+            positions_value
+        };
+        let fraction = gross / positions_value;
         
-        Ok(())
+        let mut symbols: Vec<String> = self.portfolio.positions.keys().cloned().collect();
+        symbols.sort();
+        for symbol in symbols {
+            let value = self.portfolio.positions[&symbol].current_value;
+            let mut decision = self.cash_raising_sell(symbol, value * fraction);
+            decision.execution_cost = self.execution_cost(&decision, decision.amount * cost_rate);
+            match self.execute_sell(&decision) {
+                Ok(()) => self.record_executed(decision, DecisionStatus::Executed),
+                Err(e) => self.reject(decision, e.to_string()),
+            }
+        }
     }
 
-    /// Update market prices based on volatility and random walk
-    fn update_market_prices(&mut self) -> Result<()> {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
+    /// Sale of `amount` of `symbol` for cash, not yet costed
+    fn cash_raising_sell(&self, symbol: String, amount: Decimal) -> RoutingDecision {
+        RoutingDecision {
+            timestamp: self.clock,
+            source_asset: symbol,
+            target_asset: CASH_SYMBOL.to_string(),
+            amount,
+            expected_yield: Decimal::ZERO,
+            risk_score: 0.0,
+            execution_cost: Decimal::ZERO,
+            source_currency: None,
+        }
+    }
+
+    /// Apply shocks scheduled for the current step
+    fn apply_scheduled_shocks(&mut self) {
+        let (due, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.scheduled_shocks)
+            .into_iter()
+            .partition(|shock| shock.step == self.step_count);
+        self.scheduled_shocks = pending;
+        
+        for shock in due {
+            if let Some(log) = &mut self.transaction_log {
+                log.push(TransactionEntry::Shock {
+                    step: shock.step,
+                    timestamp: self.clock,
+                    symbol: shock.symbol.clone(),
+                    pct_change: shock.pct_change,
+                });
+            }
+            
+            let factor = Decimal::ONE + shock.pct_change / Decimal::from(100);
+            let mut prices = HashMap::new();
+            for (symbol, price) in &self.market_state {
+                if shock.applies_to(symbol) {
+                    prices.insert(symbol.clone(), (*price * factor).max(MIN_PRICE));
+                }
+            }
+            for (symbol, position) in &self.portfolio.positions {
+                if shock.applies_to(symbol) && !prices.contains_key(symbol) {
+                    prices.insert(symbol.clone(), (position.asset.current_price * factor).max(MIN_PRICE));
+                }
+            }
+            
+            self.apply_prices(&prices);
+            self.emit(|| SimEvent::ShockApplied(shock.clone()));
+            self.applied_shocks.push(shock);
+        }
+    }
+
+    /// Trades so far: entries from executed decisions and closed forced liquidations
+    pub fn trades(&self) -> &[Trade] {
+        &self.trades
+    }
+
+    /// Every routing decision seen so far with its outcome
+    pub fn decision_log(&self) -> &[ExecutedDecision] {
+        &self.decision_log
+    }
+
+    /// Execute one step using prices fetched from an async provider.
+    ///
+    /// All symbols the simulator tracks are requested in a single batch call
+    /// instead of one await per symbol.
+    pub async fn step_async<P>(&mut self, provider: &P) -> Result<StepOutcome>
+    where
+        P: AsyncMarketDataProvider + ?Sized,
+    {
+        let mut symbols: Vec<&str> = self.market_state.keys().map(|s| s.as_str()).collect();
+        for symbol in self.portfolio.positions.keys() {
+            if !self.market_state.contains_key(symbol) {
+                symbols.push(symbol);
+            }
+        }
+        
+        let prices = provider
+            .get_prices_batch(&symbols)
+            .await
+            .context("Failed to fetch batch prices")?;
+        
+        self.step_with_prices(&prices)
+    }
+
+    /// Execute one step using the supplied prices instead of the simulated price walk.
+    ///
+    /// Prices are applied to positions and market state (new symbols become
+    /// visible to the strategy), then decisions, execution, and the snapshot run
+    /// as in `step`. Symbols missing from `prices` keep their last price.
+    pub fn step_with_prices(&mut self, prices: &HashMap<String, Decimal>) -> Result<StepOutcome> {
+        let mark = self.mark();
+        self.advance_clock();
+        self.apply_prices(prices);
+        self.route_and_record(mark, None)
+    }
+
+    /// `step_with_prices` with the clock moved to `time` rather than one time
+    /// step on, for data with missing periods; `time` must be after the current time
+    pub fn step_with_prices_at(&mut self, time: OffsetDateTime, prices: &HashMap<String, Decimal>) -> Result<StepOutcome> {
+        if time <= self.clock {
+            return Err(anyhow::anyhow!("Cannot step to {}: the clock is already at {}", time, self.clock));
+        }
+        let mark = self.mark();
+        self.advance_clock_to(time);
+        self.apply_prices(prices);
+        self.route_and_record(mark, None)
+    }
+
+    /// `step_with_prices_at`, showing the strategy `history` up to `time`
+    /// through its `MarketView`
+    pub fn step_with_history_at(
+        &mut self,
+        time: OffsetDateTime,
+        prices: &HashMap<String, Decimal>,
+        history: &PriceHistory,
+    ) -> Result<StepOutcome> {
+        if time <= self.clock {
+            return Err(anyhow::anyhow!("Cannot step to {}: the clock is already at {}", time, self.clock));
+        }
+        let mark = self.mark();
+        self.advance_clock_to(time);
+        self.apply_prices(prices);
+        self.route_and_record(mark, Some(history))
+    }
+
+    /// Apply externally supplied prices to positions and market state
+    fn apply_prices(&mut self, prices: &HashMap<String, Decimal>) {
+        // Symbol order, so the transaction log is the same run to run
+        let mut sorted: Vec<(&String, &Decimal)> = prices.iter().collect();
+        sorted.sort_by(|a, b| a.0.cmp(b.0));
+        for (symbol, price) in sorted {
+            let old_price = self.market_state.insert(symbol.clone(), *price);
+            if let Some(log) = &mut self.transaction_log {
+                let old_price = old_price
+                    .or_else(|| self.portfolio.positions.get(symbol).map(|p| p.asset.current_price))
+                    .unwrap_or(*price);
+                log.push(TransactionEntry::PriceUpdate {
+                    step: self.step_count,
+                    timestamp: self.clock,
+                    symbol: symbol.clone(),
+                    old_price,
+                    new_price: *price,
+                });
+            }
+        }
+        self.portfolio.update_prices(prices);
+        if let Some(benchmark) = &mut self.benchmark {
+            benchmark.update_prices(prices);
+        }
+        for (symbol, price) in prices {
+            if let Some(asset) = self.universe.get_mut(symbol) {
+                asset.current_price = *price;
+            }
+        }
+    }
+
+    /// Run strategy decisions, execute them, and record a snapshot; the
+    /// strategy sees `history` only up to the current time
+    fn route_and_record(&mut self, mark: StepMark, history: Option<&PriceHistory>) -> Result<StepOutcome> {
+        self.refresh_fx_rates();
+        self.apply_scheduled_shocks();
+        self.apply_cash_flows();
+        self.write_off_defaults();
+        self.accrue_cash_interest();
+        self.accrue_yield();
+        self.apply_margin();
+        
+        // Get routing decisions from strategy, if it is due to run
+        let decisions = if self.rebalance_due() {
+            self.last_rebalanced = Some(self.clock);
+            let liquidity = self.config.price_impact.map(|impact| (self.known_liquidity(), impact));
+            let mut view = MarketView::new(self.clock, &self.market_state);
+            if let Some(history) = history {
+                view = view.with_history(history);
+            }
+            if let Some((liquidity, impact)) = &liquidity {
+                view = view.with_liquidity(liquidity, *impact);
+            }
+            let decisions = self.strategy.generate_decisions(&self.portfolio, &view)?;
+            if let Some(log) = &mut self.transaction_log {
+                log.push(TransactionEntry::Rebalance {
+                    step: self.step_count,
+                    timestamp: self.clock,
+                    decisions: decisions.len(),
+                });
+            }
+            decisions
+        } else {
+            vec![]
+        };
+        
+        let mut decisions = decisions;
+        for decision in &mut decisions {
+            decision.timestamp = self.clock;
+        }
+        let decisions = self.reject_defaulted(decisions);
+        let decisions = self.reject_halted_buys(decisions);
+        let decisions = self.throttle_decisions(decisions);
+        
+        // Execute routing decisions
+        for mut decision in decisions {
+            if let Some(reason) = self.apply_trading_rules(&mut decision) {
+                self.reject(decision, reason);
+                continue;
+            }
+            if let Err(e) = self.check_position_limit(&decision) {
+                self.reject(decision, e.to_string());
+                continue;
+            }
+            decision.execution_cost = self.execution_cost(&decision, decision.execution_cost);
+            if let Err(e) = self.execute_routing(&decision) {
+                match self.config.failure_policy {
+                    DecisionFailurePolicy::Abort => {
+                        self.reject(decision, e.to_string());
+                        return Err(e);
+                    }
+                    DecisionFailurePolicy::ScaleDown if !decision.is_sell() => {
+                        self.scale_down(decision, e.to_string());
+                    }
+                    _ => self.reject(decision, e.to_string()),
+                }
+                continue;
+            }
+            self.record_executed(decision, DecisionStatus::Executed);
+        }
+        
+        // Update portfolio value
+        self.portfolio.update_total_value();
+        self.peak_leverage = self.peak_leverage.max(self.leverage());
+        self.check_circuit_breaker();
+        
+        // Record snapshot
+        self.record_snapshot();
+        
+        let decisions = &self.decision_log[mark.decisions..];
+        let decisions_executed = decisions.iter().filter(|d| d.is_executed()).count();
+        Ok(StepOutcome {
+            step: self.step_count,
+            value_before: mark.value,
+            value_after: self.portfolio.total_value,
+            decisions_executed,
+            decisions_rejected: decisions.len() - decisions_executed,
+            fees_paid: self.fees_paid - mark.fees,
+            drawdown_pct: self.metrics.drawdown_pct_at(self.portfolio.total_value),
+            halted_at: self.halted_at,
+        })
+    }
+
+    /// Step at which the circuit breaker tripped, if it has
+    pub fn halted_at(&self) -> Option<usize> {
+        self.halted_at
+    }
+
+    /// Trip the breaker if drawdown from peak exceeds the threshold
+    fn check_circuit_breaker(&mut self) {
+        let Some(breaker) = self.config.circuit_breaker.clone() else {
+            return;
+        };
+        if self.halted_at.is_some()
+            || self.metrics.drawdown_pct_at(self.portfolio.total_value) <= breaker.max_drawdown_pct
+        {
+            return;
+        }
+        
+        self.halted_at = Some(self.step_count);
+        if let Some(log) = &mut self.transaction_log {
+            log.push(TransactionEntry::Halt {
+                step: self.step_count,
+                timestamp: self.clock,
+                drawdown_pct: self.metrics.drawdown_pct_at(self.portfolio.total_value),
+            });
+        }
+        if breaker.liquidate {
+            let mut symbols: Vec<(String, Decimal)> = self.portfolio.positions
+                .iter()
+                .map(|(symbol, p)| (symbol.clone(), p.current_value))
+                .collect();
+            symbols.sort_by(|a, b| a.0.cmp(&b.0));
+            for (symbol, value) in symbols {
+                self.liquidate(&symbol, value);
+            }
+        }
+    }
+
+    /// Reject buy decisions once the circuit breaker has tripped
+    fn reject_halted_buys(&mut self, decisions: Vec<RoutingDecision>) -> Vec<RoutingDecision> {
+        if self.halted_at.is_none() {
+            return decisions;
+        }
+        let (sells, buys): (Vec<_>, Vec<_>) = decisions.into_iter().partition(|d| d.is_sell());
+        for decision in buys {
+            self.reject(decision, "Circuit breaker halted buying".to_string());
+        }
+        sells
+    }
+
+    /// Whether the strategy runs this step: on its first step, then once the
+    /// rebalance interval has passed since its last run
+    fn rebalance_due(&self) -> bool {
+        match (self.config.rebalance_interval, self.last_rebalanced) {
+            (Some(interval), Some(last)) => self.clock - last >= interval,
+            _ => true,
+        }
+    }
+
+    /// Execution cost for a decision: the fee model's charge, or `quoted` without
+    /// one, plus its price impact
+    fn execution_cost(&self, decision: &RoutingDecision, quoted: Decimal) -> Decimal {
+        self.fee(decision, quoted) + self.price_impact_cost(decision.traded_symbol(), decision.amount)
+    }
+
+    /// The fee model's charge for a decision, or `quoted` without one
+    fn fee(&self, decision: &RoutingDecision, quoted: Decimal) -> Decimal {
+        match &self.config.fee_model {
+            Some(fee_model) => fee_model.fee(decision, &self.portfolio),
+            None => quoted,
+        }
+    }
+
+    /// What trading `notional` of `symbol` moves its price against us, under
+    /// the configured price impact
+    pub fn price_impact_cost(&self, symbol: &str, notional: Decimal) -> Decimal {
+        match (self.config.price_impact, self.liquidity(symbol)) {
+            (Some(impact), Some(liquidity)) => impact.cost(notional, liquidity),
+            _ => Decimal::ZERO,
+        }
+    }
+
+    /// The largest buy the cash on hand pays for alongside `fee`, the fee charged
+    /// on the larger amount first asked for. Fees don't grow as the amount
+    /// shrinks, so the smaller buy's own fee still fits.
+    fn affordable_amount(&self, fee: Decimal) -> Decimal {
+        (self.portfolio.cash - fee).round_dp_with_strategy(8, RoundingStrategy::ToZero)
+    }
+
+    /// Retry a failed buy with the amount the cash on hand can pay for, fees included
+    fn scale_down(&mut self, mut decision: RoutingDecision, reason: String) {
+        let requested = decision.amount;
+        let fee = decision.execution_cost;
+        decision.amount = self.affordable_amount(fee);
+        if decision.amount <= Decimal::ZERO {
+            self.reject(decision, format!("{}; no cash to scale down to", reason));
+            return;
+        }
+        let quoted = fee * decision.amount / requested;
+        decision.execution_cost = self.execution_cost(&decision, quoted);
+        if let Some(rule) = self.apply_trading_rules(&mut decision) {
+            self.reject(decision, format!("{}; scaled-down amount rejected: {}", reason, rule));
+            return;
+        }
+        let quoted = fee * decision.amount / requested;
+        decision.execution_cost = self.execution_cost(&decision, quoted);
+        
+        match self.execute_routing(&decision) {
+            Ok(()) => self.record_executed(decision, DecisionStatus::ScaledDown { requested, reason }),
+            Err(e) => self.reject(decision, format!("{}; scaled-down retry failed: {}", reason, e)),
+        }
+    }
+
+    /// Log an executed (or scaled-down) decision and open a trade for buys
+    fn record_executed(&mut self, decision: RoutingDecision, status: DecisionStatus) {
+        self.fees_paid += decision.execution_cost;
+        self.last_traded.insert(decision.traded_symbol().to_string(), self.step_count);
+        if !decision.is_sell() {
+            self.record_entry_trade(&decision);
+        }
+        if let Some(log) = &mut self.transaction_log {
+            let timestamp = self.clock;
+            let symbol = decision.traded_symbol().to_string();
+            let side = if decision.is_sell() { TradeSide::Sell } else { TradeSide::Buy };
+            log.push(TransactionEntry::Decision {
+                step: self.step_count,
+                timestamp,
+                symbol: symbol.clone(),
+                side,
+                amount: decision.amount,
+            });
+            if !decision.execution_cost.is_zero() {
+                log.push(TransactionEntry::Fee {
+                    step: self.step_count,
+                    timestamp,
+                    symbol,
+                    amount: decision.execution_cost,
+                });
+            }
+        }
+        let entry = ExecutedDecision {
+            step: self.step_count,
+            execution_cost: decision.execution_cost,
+            decision,
+            status,
+        };
+        self.emit(|| SimEvent::DecisionExecuted(entry.clone()));
+        self.decision_log.push(entry);
+    }
+
+    /// Trade the book to `targets` (fractions of portfolio value) within the current step.
+    ///
+    /// Overweight positions are sold first, then underweight ones bought with the
+    /// proceeds, paying the fee model's charge (or `rebalance_cost_rate`) on both
+    /// legs. Buys are scaled down to fit the available cash, so cash never goes
+    /// negative. Positions without a target are left untouched. Throttle limits
+    /// do not apply. If any leg fails, the portfolio is restored and nothing is logged.
+    pub fn rebalance_to(&mut self, targets: &HashMap<String, Decimal>) -> Result<()> {
+        if let Some((symbol, weight)) = targets.iter().find(|(_, w)| **w < Decimal::ZERO) {
+            return Err(anyhow::anyhow!("Target weight for {} is negative: {}", symbol, weight));
+        }
+        let total_weight: Decimal = targets.values().sum();
+        if total_weight > Decimal::ONE {
+            return Err(anyhow::anyhow!("Target weights sum to {}, more than 1", total_weight));
+        }
+        
+        let cost_rate = self.config.rebalance_cost_rate;
+        let mut plan = rebalance_decisions(&self.portfolio, targets, cost_rate);
+        if self.halted_at.is_some() {
+            plan.retain(|d| d.is_sell());
+        }
+        let saved = self.portfolio.clone();
+        let saved_fills = self.fills.len();
+        
+        let mut executed = Vec::with_capacity(plan.len());
+        let mut rejected = vec![];
+        for mut decision in plan {
+            decision.execution_cost = self.execution_cost(&decision, decision.amount * cost_rate);
+            if !decision.is_sell() && decision.amount + decision.execution_cost > self.portfolio.cash {
+                // Proceeds and fees can differ from the plan; never spend more than we hold.
+                decision.amount = self.affordable_amount(decision.execution_cost);
+                decision.execution_cost = self.execution_cost(&decision, decision.amount * cost_rate);
+                if decision.amount <= Decimal::ZERO
+                    || decision.amount + decision.execution_cost > self.portfolio.cash
+                {
+                    continue;
+                }
+            }
+            if let Some(reason) = self.apply_trading_rules(&mut decision) {
+                rejected.push((decision, reason));
+                continue;
+            }
+            decision.execution_cost = self.execution_cost(&decision, decision.amount * cost_rate);
+            
+            if let Err(e) = self.execute_routing(&decision) {
+                self.portfolio = saved;
+                self.fills.truncate(saved_fills);
+                return Err(e.context("Rebalance failed; portfolio left unchanged"));
+            }
+            executed.push(decision);
+        }
+        
+        for decision in executed {
+            self.record_executed(decision, DecisionStatus::Executed);
+        }
+        for (decision, reason) in rejected {
+            self.reject(decision, reason);
+        }
+        self.portfolio.update_total_value();
+        self.peak_leverage = self.peak_leverage.max(self.leverage());
+        Ok(())
+    }
+
+    /// The regime the market is in, when a regime model is configured
+    pub fn current_regime(&self) -> Option<&Regime> {
+        self.config.regimes.as_ref()?.regimes.get(self.regime)
+    }
+
+    /// Move the regime chain one step and count the step toward the new regime
+    fn advance_regime(&mut self) {
+        let Some(model) = &self.config.regimes else {
+            return;
+        };
+        // Step 1 runs in the initial regime; transitions start after it
+        if self.step_count > 1 {
+            self.regime = model.next(self.regime, &mut self.rng);
+        }
+        if let Some(steps) = self.regime_occupancy.get_mut(self.regime) {
+            *steps += 1;
+        }
+    }
+
+    /// Update market prices based on volatility and random walk
+    fn update_market_prices(&mut self) -> Result<()> {
+        self.advance_regime();
+        let (regime_drift, regime_vol) = self
+            .current_regime()
+            .map_or((0.0, 1.0), |regime| (regime.drift_shift, regime.vol_multiplier));
+        
+        // Sorted so shocks map to symbols deterministically
+        let mut symbols: Vec<String> = self.portfolio.positions.keys().cloned().collect();
+        if let Some(benchmark) = &self.benchmark {
+            symbols.extend(benchmark.positions.keys().cloned());
+        }
+        symbols.extend(self.universe.keys().cloned());
+        symbols.sort();
+        symbols.dedup();
+        
+        let shocks = self.correlated_shocks(&symbols)?;
+        // One draw per step, shared by every asset the market-wide jump hits
+        let market_jump = self.config.jumps.market.as_ref().and_then(|market| market.model.draw(&mut self.rng));
+        if market_jump.is_some() {
+            self.jumps += 1;
+        }
+        
+        // Continuous assets move over all elapsed time; calendar-bound ones only over
+        // trading time, so they hold flat across closed days
+        let continuous_dt = self.elapsed_years();
+        let trading_dt = self.config.dt();
+        
+        for (symbol, random_shock) in symbols.iter().zip(shocks) {
+            // Unheld symbols walk on the benchmark's or the universe's asset parameters
+            let benchmark_position = self.benchmark.as_ref().and_then(|b| b.positions.get(symbol));
+            let Some(asset) = self.portfolio.positions
+                .get(symbol)
+                .or(benchmark_position)
+                .map(|p| &p.asset)
+                .or_else(|| self.universe.get(symbol))
+            else {
+                continue;
+            };
+            let current_price = asset.current_price;
+            let volatility = asset.volatility.to_f64().unwrap_or(0.0) * self.config.volatility_multiplier * regime_vol;
+            let drift = asset.expected_return.to_f64().unwrap_or(0.0) + self.config.drift_adjustment + regime_drift;
+            
+            // Geometric Brownian Motion: S * exp((mu - sigma^2 / 2) dt + sigma sqrt(dt) z)
+            let dt = match self.config.session(asset) {
+                TradingSession::Continuous => continuous_dt,
+                TradingSession::CalendarBound => trading_dt,
+            };
+            let mut log_return = (drift - 0.5 * volatility * volatility) * dt
+                + volatility * dt.sqrt() * random_shock;
+            
+            // Jumps land on top of the diffusion, whatever the session
+            if let Some(jump) = market_jump {
+                if self.config.jumps.market.as_ref().is_some_and(|market| market.hits(&asset.asset_type)) {
+                    log_return += jump;
+                }
+            }
+            if let Some(model) = self.config.jumps.by_asset_type.get(&asset.asset_type) {
+                if let Some(jump) = model.draw(&mut self.rng) {
+                    log_return += jump;
+                    self.jumps += 1;
+                }
+            }
+            let growth = Decimal::try_from(log_return.exp()).unwrap_or(Decimal::ONE);
+            
+            let new_price = (current_price * growth).max(MIN_PRICE);
+            if let Some(position) = self.portfolio.positions.get_mut(symbol) {
+                position.update_price(new_price);
+            }
+            if let Some(position) = self.benchmark.as_mut().and_then(|b| b.positions.get_mut(symbol)) {
+                position.update_price(new_price);
+            }
+            if let Some(asset) = self.universe.get_mut(symbol) {
+                asset.current_price = new_price;
+            }
+            
+            if let Some(log) = &mut self.transaction_log {
+                log.push(TransactionEntry::PriceUpdate {
+                    step: self.step_count,
+                    timestamp: self.clock,
+                    symbol: symbol.clone(),
+                    old_price: current_price,
+                    new_price,
+                });
+            }
+            
+            self.market_state.insert(symbol.clone(), new_price);
+        }
+        if let Some(benchmark) = &mut self.benchmark {
+            benchmark.update_total_value();
+        }
+        
+        Ok(())
+    }
+
+    /// Draw one unit-variance shock per symbol from its shock distribution,
+    /// correlated per the configured matrix.
+    ///
+    /// Non-normal shocks are mixed through the same Cholesky factor, which
+    /// preserves the linear correlations; the mixed marginals are close to,
+    /// but not exactly, the configured distribution.
+    fn correlated_shocks(&mut self, symbols: &[String]) -> Result<Vec<f64>> {
+        let sign = if self.config.antithetic { -1.0 } else { 1.0 };
+        let independent: Vec<f64> = symbols
+            .iter()
+            .map(|symbol| {
+                let distribution = self.config.shock_distribution(symbol);
+                let quasi = match (&self.config.quasi_random, distribution) {
+                    (Some(draws), ShockDistribution::Normal)
+                        if self.quasi_dimension < draws.sequence.dimensions() =>
+                    {
+                        let z = draws.sequence.normal(draws.index, self.quasi_dimension);
+                        self.quasi_dimension += 1;
+                        Some(z)
+                    }
+                    _ => None,
+                };
+                sign * quasi.unwrap_or_else(|| distribution.sample(&mut self.rng))
+            })
+            .collect();
+        
+        let default_correlation = self
+            .current_regime()
+            .map_or(self.config.default_correlation, |regime| regime.correlation);
+        if (self.config.correlations.is_empty() && default_correlation == 0.0) || symbols.len() < 2 {
+            return Ok(independent);
+        }
+        
+        let cached = matches!(
+            &self.cholesky_cache,
+            Some((cached, correlation, _)) if cached == symbols && *correlation == default_correlation
+        );
+        if !cached {
+            let matrix: Vec<Vec<f64>> = symbols
+                .iter()
+                .map(|a| {
+                    symbols
+                        .iter()
+                        .map(|b| self.config.correlation_or(a, b, default_correlation))
+                        .collect()
+                })
+                .collect();
+            let factor = crate::utils::cholesky(&matrix).with_context(|| {
+                format!("Correlation matrix for {:?} is not positive definite", symbols)
+            })?;
+            self.cholesky_cache = Some((symbols.to_vec(), default_correlation, factor));
+        }
+        
+        let (_, _, factor) = self.cholesky_cache.as_ref().expect("cholesky factor was just cached");
+        Ok(factor
+            .iter()
+            .map(|row| row.iter().zip(&independent).map(|(l, z)| l * z).sum())
+            .collect())
+    }
+
+    /// Write off every asset whose price has fallen to the default threshold.
+    ///
+    /// Held positions lose their full value and move to `Portfolio::defaulted`;
+    /// the symbol leaves market state so strategies no longer see it.
+    fn write_off_defaults(&mut self) {
+        let threshold = self.config.default_price_threshold;
+        let mut collapsed: Vec<String> = self.market_state
+            .iter()
+            .filter(|(_, price)| **price <= threshold)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        for (symbol, position) in &self.portfolio.positions {
+            if position.asset.current_price <= threshold && !collapsed.contains(symbol) {
+                collapsed.push(symbol.clone());
+            }
+        }
+        collapsed.sort();
+        
+        for symbol in collapsed {
+            self.market_state.remove(&symbol);
+            let defaulted = self.portfolio.write_off(&symbol, self.step_count);
+            let now = self.clock;
+            
+            if let Some(log) = &mut self.transaction_log {
+                log.push(TransactionEntry::WriteOff {
+                    step: self.step_count,
+                    timestamp: now,
+                    symbol: symbol.clone(),
+                    quantity: defaulted.quantity,
+                    price: defaulted.last_price,
+                    value: defaulted.written_off,
+                });
+            }
+            
+            if defaulted.quantity > Decimal::ZERO {
+                self.record_fill(&symbol, TradeSide::Sell, defaulted.quantity, Decimal::ZERO, Decimal::ZERO);
+                self.trades.push(Trade {
+                    entry_time: now,
+                    exit_time: Some(now),
+                    asset: symbol,
+                    quantity: defaulted.quantity,
+                    entry_price: defaulted.entry_price,
+                    exit_price: Some(Decimal::ZERO),
+                    pnl: Some(-defaulted.entry_price * defaulted.quantity),
+                    pnl_pct: Some(-100.0),
+                    fees: Decimal::ZERO,
+                });
+            }
+        }
+    }
+
+    /// Reject decisions that trade defaulted symbols
+    fn reject_defaulted(&mut self, decisions: Vec<RoutingDecision>) -> Vec<RoutingDecision> {
+        let (live, defaulted): (Vec<_>, Vec<_>) = decisions
+            .into_iter()
+            .partition(|d| !self.portfolio.is_defaulted(d.traded_symbol()));
+        for decision in defaulted {
+            self.reject(decision, "Asset has defaulted".to_string());
+        }
+        live
+    }
+
+    /// Apply the throttle limits, logging rejected decisions and returning the rest
+    fn throttle_decisions(&mut self, decisions: Vec<RoutingDecision>) -> Vec<RoutingDecision> {
+        let throttle = self.config
+            .strategy_throttles
+            .get(self.strategy.name())
+            .unwrap_or(&self.config.throttle)
+            .clone();
+        
+        let mut accepted = Vec::with_capacity(decisions.len());
+        let mut symbols_this_step: Vec<String> = vec![];
+        
+        for decision in decisions {
+            if let Some(cooldown) = throttle.cooldown_steps {
+                let symbol = decision.traded_symbol();
+                let last = self.last_traded.get(symbol).copied();
+                let cooling = symbols_this_step.iter().any(|s| s == symbol)
+                    || last.is_some_and(|last| self.step_count - last < cooldown);
+                if cooling {
+                    self.reject(decision, format!("Symbol in {}-step cooldown", cooldown));
+                    continue;
+                }
+            }
+            
+            if throttle.max_decisions_per_step.is_some_and(|max| accepted.len() >= max) {
+                self.reject(decision, "Per-step decision count cap reached".to_string());
+                continue;
+            }
+            
+            symbols_this_step.push(decision.traded_symbol().to_string());
+            accepted.push(decision);
+        }
+        
+        let Some(max_notional_pct) = throttle.max_step_notional_pct else {
+            return accepted;
+        };
+        
+        // Allocate the notional budget largest-first, keeping emission order for execution
+        let mut remaining = (self.portfolio.total_value * max_notional_pct).max(Decimal::ZERO);
+        let mut order: Vec<usize> = (0..accepted.len()).collect();
+        order.sort_by(|&a, &b| accepted[b].amount.cmp(&accepted[a].amount).then(a.cmp(&b)));
+        
+        let mut keep = vec![true; accepted.len()];
+        for index in order {
+            let decision = &mut accepted[index];
+            if decision.amount <= remaining {
+                remaining -= decision.amount;
+            } else if remaining > Decimal::ZERO {
+                let scale = remaining / decision.amount;
+                decision.amount = remaining;
+                decision.execution_cost *= scale;
+                remaining = Decimal::ZERO;
+            } else {
+                keep[index] = false;
+            }
+        }
+        
+        let mut result = Vec::with_capacity(accepted.len());
+        for (decision, keep) in accepted.into_iter().zip(keep) {
+            if keep {
+                result.push(decision);
+            } else {
+                self.reject(decision, "Per-step notional cap reached".to_string());
+            }
+        }
+        result
+    }
+
+    /// Open a trade for an executed buy decision
+    fn record_entry_trade(&mut self, decision: &RoutingDecision) {
+        let Some(position) = self.portfolio.positions.get(&decision.target_asset) else {
+            return;
+        };
+        let price = position.asset.current_price;
+        if price <= Decimal::ZERO {
+            return;
+        }
+        
+        let quantity = decision.amount / price;
+        self.record_fill(&decision.target_asset, TradeSide::Buy, quantity, price, decision.execution_cost);
+        self.trades.push(Trade {
+            entry_time: decision.timestamp,
+            exit_time: None,
+            asset: decision.target_asset.clone(),
+            quantity,
+            entry_price: price,
+            exit_price: None,
+            pnl: None,
+            pnl_pct: None,
+            fees: decision.execution_cost,
+        });
+    }
+
+    fn record_fill(&mut self, symbol: &str, side: TradeSide, quantity: Decimal, price: Decimal, fee: Decimal) {
+        self.fills.push(Fill {
+            step: self.step_count,
+            timestamp: self.clock,
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price,
+            fee,
+        });
+    }
+
+    /// Round a decision down to its symbol's lot size, returning a reason if it
+    /// must be rejected. Cash not spent because of rounding stays in the book.
+    fn apply_trading_rules(&self, decision: &mut RoutingDecision) -> Option<String> {
+        let symbol = decision.traded_symbol();
+        let rules = self.config.trading_rules.get(symbol)?;
+        let price = self.quote_price(symbol);
+        if price <= Decimal::ZERO {
+            return None;
+        }
+        
+        // Closing a whole position is always allowed, whatever its quantity
+        let full_exit = decision.is_sell()
+            && self.portfolio.positions
+                .get(symbol)
+                .is_some_and(|p| decision.amount >= p.current_value);
+        
+        if let Some(lot_size) = rules.lot_size.filter(|lot| *lot > Decimal::ZERO) {
+            if !full_exit {
+                let lots = (decision.amount / price / lot_size).floor();
+                if lots.is_zero() {
+                    return Some(format!("Quantity rounds to zero at lot size {}", lot_size));
+                }
+                let amount = lots * lot_size * price;
+                if amount < decision.amount {
+                    decision.execution_cost = decision.execution_cost * amount / decision.amount;
+                    decision.amount = amount;
+                }
+            }
+        }
+        
+        if !full_exit && decision.amount < rules.min_notional {
+            return Some(format!(
+                "Notional {} below minimum of {}",
+                decision.amount.round_dp(2),
+                rules.min_notional
+            ));
+        }
+        None
+    }
+
+    /// Best available price for a symbol: the held position, market state, then the provider
+    fn quote_price(&self, symbol: &str) -> Decimal {
+        if let Some(position) = self.portfolio.positions.get(symbol) {
+            return position.asset.current_price;
+        }
+        let provider = self.provider.as_deref();
+        self.market_state
+            .get(symbol)
+            .copied()
+            .or_else(|| provider.and_then(|p| p.get_current_price(symbol).ok()))
+            .unwrap_or(dec!(1.0))
+    }
+
+    /// Record a decision that was not executed
+    fn reject(&mut self, decision: RoutingDecision, reason: String) {
+        if let Some(log) = &mut self.transaction_log {
+            log.push(TransactionEntry::Rejected {
+                step: self.step_count,
+                timestamp: self.clock,
+                symbol: decision.traded_symbol().to_string(),
+                side: if decision.is_sell() { TradeSide::Sell } else { TradeSide::Buy },
+                amount: decision.amount,
+                reason: reason.clone(),
+            });
+        }
+        let entry = ExecutedDecision {
+            step: self.step_count,
+            decision,
+            status: DecisionStatus::Rejected { reason },
+            execution_cost: Decimal::ZERO,
+        };
+        self.emit(|| SimEvent::DecisionRejected(entry.clone()));
+        self.decision_log.push(entry);
+    }
+
+    /// Error if `decision` would open a position beyond `max_positions`
+    fn check_position_limit(&self, decision: &RoutingDecision) -> Result<()> {
+        let Some(max) = self.config.max_positions else {
+            return Ok(());
+        };
+        if decision.is_sell()
+            || self.portfolio.positions.contains_key(&decision.target_asset)
+            || self.portfolio.open_positions() < max
+        {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!("Position limit of {} reached", max))
+    }
+
+    /// Execute a capital routing decision
+    fn execute_routing(&mut self, decision: &RoutingDecision) -> Result<()> {
+        if decision.is_sell() {
+            return self.execute_sell(decision);
+        }
+        self.check_position_limit(decision)?;
+        
+        // Buys paid from another currency exchange it into reporting cash first
+        if let Some(currency) = decision.source_currency.as_deref() {
+            self.portfolio
+                .convert_to_reporting(currency, decision.amount + decision.execution_cost)?;
+        }
+        
+        // Check if we have enough capital, borrowing the shortfall in margin mode
+        if decision.amount > self.portfolio.cash {
+            if self.config.margin.is_none() {
+                return Err(anyhow::anyhow!("Insufficient cash for routing decision"));
+            }
+            self.borrow_for(decision)?;
+        }
+        
+        // Check if target asset exists in portfolio
+        if let Some(position) = self.portfolio.positions.get_mut(&decision.target_asset) {
+            // Add to existing position
+            let additional_quantity = decision.amount / position.asset.current_price;
+            position.quantity += additional_quantity;
+            position.current_value += decision.amount;
+            self.portfolio.cash -= decision.amount;
+        } else {
+            // Create new position, taking asset data from the provider when one is set
+            let asset = self.new_asset(&decision.target_asset, decision.expected_yield);
+            let current_price = asset.current_price;
+            if current_price <= self.config.default_price_threshold {
+                return Err(anyhow::anyhow!(
+                    "Cannot open {} at price {}",
+                    decision.target_asset,
+                    current_price
+                ));
+            }
+            
+            let quantity = decision.amount / current_price;
+            let position = Position::new(asset, quantity, current_price);
+            self.portfolio.add_position(position);
+        }
+        
+        // Deduct execution cost
+        self.portfolio.cash -= decision.execution_cost;
+        self.portfolio.update_total_value();
+        
+        Ok(())
+    }
+
+    /// Sell `decision.amount` worth of the source position into cash
+    fn execute_sell(&mut self, decision: &RoutingDecision) -> Result<()> {
+        let symbol = decision.source_asset.as_str();
+        let position = self.portfolio.positions
+            .get(symbol)
+            .ok_or_else(|| anyhow::anyhow!("No position in {} to sell", symbol))?;
+        let price = position.asset.current_price;
+        if price <= Decimal::ZERO {
+            return Err(anyhow::anyhow!("Cannot sell {} at price {}", symbol, price));
+        }
+        
+        let quantity = if decision.amount >= position.current_value {
+            position.quantity
+        } else {
+            decision.amount / price
+        };
+        if self.portfolio.reduce_position(symbol, quantity).is_some() {
+            self.record_fill(symbol, TradeSide::Sell, quantity, price, decision.execution_cost);
+        }
+        
+        self.portfolio.cash -= decision.execution_cost;
+        self.portfolio.update_total_value();
+        
+        Ok(())
+    }
+
+    /// Borrow the cash shortfall for a decision, respecting max leverage
+    fn borrow_for(&mut self, decision: &RoutingDecision) -> Result<()> {
+        let shortfall = decision.amount + decision.execution_cost - self.portfolio.cash;
+        let equity = self.portfolio.total_value - decision.execution_cost;
+        let gross_after = self.portfolio.positions_value() + decision.amount;
+        let max_leverage = Decimal::try_from(self.config.risk_parameters.max_leverage)
+            .unwrap_or(Decimal::ONE);
+        
+        if equity <= Decimal::ZERO || gross_after > equity * max_leverage {
+            return Err(anyhow::anyhow!(
+                "Routing decision would exceed max leverage of {}x",
+                max_leverage
+            ));
+        }
+        
+        self.portfolio.borrowed += shortfall;
+        self.portfolio.cash += shortfall;
+        Ok(())
+    }
+
+    /// Current gross leverage (positions value / equity)
+    pub fn leverage(&self) -> f64 {
+        if self.portfolio.total_value <= Decimal::ZERO {
+            return 0.0;
+        }
+        (self.portfolio.positions_value() / self.portfolio.total_value)
+            .to_f64()
+            .unwrap_or(0.0)
+    }
+
+    /// Credit interest on idle cash at the configured cash rate
+    fn accrue_cash_interest(&mut self) {
+        if self.config.cash_rate.is_zero() || self.portfolio.cash <= Decimal::ZERO {
+            return;
+        }
+        let dt = Decimal::try_from(self.elapsed_years()).unwrap_or(Decimal::ZERO);
+        self.portfolio.cash += self.portfolio.cash * self.config.cash_rate * dt;
+        self.portfolio.update_total_value();
+    }
+
+    /// Credit each position's income yield to cash or reinvest it, per the
+    /// configured `yield_income` (the benchmark's too)
+    fn accrue_yield(&mut self) {
+        let dt = Decimal::try_from(self.elapsed_years()).unwrap_or(Decimal::ZERO);
+        let reinvest = self.config.yield_income == YieldIncome::Reinvest;
+        let accrue = |portfolio: &mut Portfolio| {
+            if reinvest { portfolio.reinvest_yield(dt) } else { portfolio.accrue_yield(dt) }
+        };
+        self.income_earned += accrue(&mut self.portfolio);
+        if let Some(benchmark) = &mut self.benchmark {
+            accrue(benchmark);
+        }
+    }
+
+    /// Accrue interest on the borrowed balance and liquidate if under maintenance margin
+    fn apply_margin(&mut self) {
+        let Some(margin) = self.config.margin.clone() else {
+            return;
+        };
+        
+        if self.portfolio.borrowed > Decimal::ZERO {
+            let dt = Decimal::try_from(self.elapsed_years()).unwrap_or(Decimal::ZERO);
+            self.portfolio.borrowed += self.portfolio.borrowed * margin.borrow_rate * dt;
+            self.portfolio.update_total_value();
+        }
+        
+        let positions_value = self.portfolio.positions_value();
+        if self.portfolio.borrowed <= Decimal::ZERO || positions_value <= Decimal::ZERO {
+            return;
+        }
+        
+        let equity = self.portfolio.total_value;
+        if equity >= positions_value * margin.maintenance_margin {
+            return;
+        }
+        
+        self.margin_calls += 1;
+        
+        // Selling at market leaves equity unchanged, so the book must shrink to equity / margin
+        let target_value = if equity > Decimal::ZERO && margin.maintenance_margin > Decimal::ZERO {
+            equity / margin.maintenance_margin
+        } else {
+            Decimal::ZERO
+        };
+        let mut to_sell = positions_value - target_value;
+        
+        let mut by_size: Vec<(String, Decimal)> = self.portfolio.positions
+            .iter()
+            .map(|(symbol, p)| (symbol.clone(), p.current_value))
+            .collect();
+        by_size.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        
+        for (symbol, value) in by_size {
+            if to_sell <= Decimal::ZERO {
+                break;
+            }
+            let sell_value = value.min(to_sell);
+            self.liquidate(&symbol, sell_value);
+            to_sell -= sell_value;
+        }
+    }
+
+    /// Force-sell `value` worth of a position, repay debt, and log the trade
+    fn liquidate(&mut self, symbol: &str, value: Decimal) {
+        let Some(position) = self.portfolio.positions.get(symbol) else {
+            return;
+        };
+        let price = position.asset.current_price;
+        let entry_price = position.entry_price;
+        let quantity = if value >= position.current_value || price <= Decimal::ZERO {
+            position.quantity
+        } else {
+            value / price
+        };
         
-        for (symbol, position) in &mut self.portfolio.positions {
-            let current_price = position.asset.current_price;
-            let volatility = position.asset.volatility;
-            
-            // Geometric Brownian Motion for price evolution
-            let dt = 1.0 / 365.0; // Daily time step
-            let drift = position.asset.yield_rate;
-            let random_shock = rng.gen::<f64>() - 0.5; // Random walk component
-            
-            let dt_decimal = Decimal::try_from(dt).unwrap_or(Decimal::ZERO);
-            let drift_term = drift * dt_decimal;
-            let shock_term = Decimal::try_from(random_shock * dt.sqrt()).unwrap_or(Decimal::ZERO) * volatility;
-            let price_change = drift_term + shock_term;
-            
-            let new_price = current_price * (Decimal::ONE + price_change);
-            position.update_price(new_price);
-            
-            self.market_state.insert(symbol.clone(), new_price);
+        let Some(proceeds) = self.portfolio.reduce_position(symbol, quantity) else {
+            return;
+        };
+        self.record_fill(symbol, TradeSide::Sell, quantity, price, Decimal::ZERO);
+        let repayment = proceeds.min(self.portfolio.borrowed);
+        self.portfolio.borrowed -= repayment;
+        self.portfolio.cash -= repayment;
+        self.portfolio.update_total_value();
+        
+        let now = self.clock;
+        if let Some(log) = &mut self.transaction_log {
+            log.push(TransactionEntry::Liquidation {
+                step: self.step_count,
+                timestamp: now,
+                symbol: symbol.to_string(),
+                quantity,
+                price,
+                proceeds,
+            });
         }
         
-        Ok(())
+        let pnl = (price - entry_price) * quantity;
+        self.trades.push(Trade {
+            entry_time: now,
+            exit_time: Some(now),
+            asset: symbol.to_string(),
+            quantity,
+            entry_price,
+            exit_price: Some(price),
+            pnl: Some(pnl),
+            pnl_pct: Some(crate::utils::percentage_change(entry_price, price)),
+            fees: Decimal::ZERO,
+        });
     }
 
-    /// Execute a capital routing decision
-    fn execute_routing(&mut self, decision: RoutingDecision) -> Result<()> {
-        // Check if we have enough capital
-        if decision.amount > self.portfolio.cash {
-            return Err(anyhow::anyhow!("Insufficient cash for routing decision"));
+    /// Append this step's price for every known symbol, starting new symbols' histories
+    fn record_prices(&mut self) {
+        let mut prices: HashMap<&String, Decimal> = self.market_state.iter().map(|(s, p)| (s, *p)).collect();
+        for (symbol, position) in &self.portfolio.positions {
+            prices.entry(symbol).or_insert(position.asset.current_price);
         }
         
-        // Check if target asset exists in portfolio
-        if let Some(position) = self.portfolio.positions.get_mut(&decision.target_asset) {
-            // Add to existing position
-            let additional_quantity = decision.amount / position.asset.current_price;
-            position.quantity += additional_quantity;
-            position.current_value += decision.amount;
-        } else {
-            // Create new position
-            // In a real implementation, we'd fetch asset data from market
-            let asset = Asset {
-                symbol: decision.target_asset.clone(),
-                name: format!("Asset {}", decision.target_asset),
-                asset_type: crate::types::AssetType::Crypto,
-                current_price: self.market_state
-                    .get(&decision.target_asset)
-                    .copied()
-                    .unwrap_or(dec!(1.0)),
-                volatility: dec!(0.02),
-                yield_rate: decision.expected_yield,
-            };
-            
-            let quantity = decision.amount / asset.current_price;
-            let position = Position::new(asset, quantity, asset.current_price);
-            self.portfolio.add_position(position);
+        for (symbol, price) in prices {
+            if !self.price_history.contains_key(symbol) {
+                self.price_history_start.insert(symbol.clone(), self.step_count);
+            }
+            self.price_history.entry(symbol.clone()).or_default().push(price);
+        }
+        // Symbols that dropped out of the market carry their last price forward
+        let step = self.step_count;
+        for (symbol, history) in &mut self.price_history {
+            let start = self.price_history_start.get(symbol).copied().unwrap_or(step);
+            while start + history.len() <= step {
+                match history.last().copied() {
+                    Some(last) => history.push(last),
+                    None => break,
+                }
+            }
         }
-        
-        // Deduct execution cost
-        self.portfolio.cash -= decision.execution_cost;
-        
-        Ok(())
     }
 
     /// Record current portfolio state
     fn record_snapshot(&mut self) {
-        let positions_value: Decimal = self
-            .portfolio
-            .positions
-            .values()
-            .map(|p| p.current_value)
-            .sum();
+        let positions_value = self.portfolio.positions_value();
         
         let snapshot = PortfolioSnapshot {
-            timestamp: OffsetDateTime::now_utc(),
+            step: self.step_count,
+            timestamp: self.clock,
             total_value: self.portfolio.total_value,
-            cash: self.portfolio.cash,
+            cash: self.portfolio.cash_value(),
             positions_value,
             positions_count: self.portfolio.positions.len(),
+            cash_balances: if self.portfolio.foreign_cash.is_empty() {
+                HashMap::new()
+            } else {
+                self.portfolio.cash_balances()
+            },
+            positions: self.config.record_positions.then(|| self.position_snapshots()),
         };
         
-        self.portfolio_history.push(snapshot);
+        if self.config.record_price_history {
+            self.record_prices();
+        }
+        self.metrics.record_with_flow(snapshot.total_value, self.step_cash_flow);
+        self.step_cash_flow = Decimal::ZERO;
+        if let Some(benchmark) = &mut self.benchmark {
+            benchmark.timestamp = self.clock;
+            self.benchmark_metrics.record(benchmark.total_value);
+            let strategy_return = self.metrics.last_return();
+            let benchmark_return = self.benchmark_metrics.last_return();
+            if let (Some(s), Some(b)) = (strategy_return, benchmark_return) {
+                self.active_returns.record(s, b);
+            }
+        }
+        self.emit(|| SimEvent::StepCompleted(snapshot.clone()));
+        if let Some(rolling) = &mut self.rolling {
+            let stats = rolling.record(snapshot.total_value);
+            self.rolling_metrics.push(RollingMetrics {
+                step: self.step_count,
+                rolling_return: stats.map(|s| s.rolling_return),
+                rolling_vol: stats.map(|s| s.rolling_vol_pct),
+                rolling_sharpe: stats.map(|s| s.rolling_sharpe),
+                current_drawdown: self.metrics.drawdown_pct_at(snapshot.total_value),
+            });
+        }
+        
+        match self.config.history_policy {
+            HistoryPolicy::Full => self.portfolio_history.push_back(snapshot),
+            HistoryPolicy::EveryN(n) => {
+                if self.step_count.is_multiple_of(n.max(1)) {
+                    self.portfolio_history.push_back(snapshot);
+                }
+            }
+            HistoryPolicy::LastN(n) => {
+                if n == 0 {
+                    return;
+                }
+                if self.portfolio_history.len() == n {
+                    self.portfolio_history.pop_front();
+                }
+                self.portfolio_history.push_back(snapshot);
+            }
+        }
+    }
+
+    /// Per-position detail for the current snapshot
+    fn position_snapshots(&self) -> HashMap<String, PositionSnapshot> {
+        let total_value = self.portfolio.total_value;
+        self.portfolio.positions
+            .iter()
+            .map(|(symbol, position)| {
+                let weight = if total_value > Decimal::ZERO {
+                    position.current_value / total_value
+                } else {
+                    Decimal::ZERO
+                };
+                let snapshot = PositionSnapshot {
+                    quantity: position.quantity,
+                    price: position.asset.current_price,
+                    value: position.current_value,
+                    weight,
+                };
+                (symbol.clone(), snapshot)
+            })
+            .collect()
     }
 
     /// Get current portfolio value
@@ -149,207 +2297,843 @@ impl Simulator {
         self.portfolio.total_value.to_f64().unwrap_or(0.0)
     }
 
+    /// Results computed over the history collected so far, without ending the run
+    pub fn current_results(&self) -> SimulationResults {
+        let mut results = self.summarize();
+        results.portfolio_history = self.portfolio_history.iter().cloned().collect();
+        results.decisions = self.decision_log.clone();
+        results.trades = self.trades.clone();
+        results.fills = self.fills.clone();
+        results.shocks = self.applied_shocks.clone();
+        results.rolling_metrics = self.rolling_metrics.clone();
+        results.transactions = self.transaction_log.clone();
+        results.price_history = self.price_history.clone();
+        results.price_history_start = self.price_history_start.clone();
+        results
+    }
+
     /// Finalize simulation and return results
     pub fn finalize(mut self) -> SimulationResults {
         self.portfolio.update_total_value();
         
-        let initial_value = self.portfolio_history
-            .first()
-            .map(|s| s.total_value)
-            .unwrap_or(Decimal::ZERO);
-        let final_value = self.portfolio.total_value;
+        let mut results = self.summarize();
+        if let Some(events) = &self.events {
+            let summary = RunSummary::from_results(self.step_count, &results);
+            let _ = events.send(SimEvent::Finalized(summary));
+        }
+        
+        results.portfolio_history = self.portfolio_history.into();
+        results.decisions = self.decision_log;
+        results.trades = self.trades;
+        results.fills = self.fills;
+        results.shocks = self.applied_shocks;
+        results.rolling_metrics = self.rolling_metrics;
+        results.transactions = self.transaction_log;
+        results.price_history = self.price_history;
+        results.price_history_start = self.price_history_start;
+        results
+    }
+
+    /// Compute result metrics, leaving the per-step logs empty for the caller to fill
+    fn summarize(&self) -> SimulationResults {
+        let initial_value = self.metrics.initial_value().unwrap_or(Decimal::ZERO);
+        let marked_final_value = self.portfolio.total_value;
+        let terminal_liquidation_cost = self.terminal_liquidation_cost();
+        let final_value = marked_final_value - terminal_liquidation_cost;
         
-        let total_return = final_value - initial_value;
-        let total_return_pct = if initial_value > Decimal::ZERO {
-            (total_return / initial_value * Decimal::from(100)).to_f64().unwrap_or(0.0)
+        let net_cash_flow: Decimal = self.cash_flows.iter().map(|flow| flow.amount).sum();
+        let total_return = final_value - initial_value - net_cash_flow;
+        let total_return_pct = if self.cash_flows.is_empty() {
+            if initial_value > Decimal::ZERO {
+                (total_return / initial_value * Decimal::from(100)).to_f64().unwrap_or(0.0)
+            } else {
+                0.0
+            }
         } else {
-            0.0
+            // Chain the per-step returns, then apply any terminal liquidation haircut
+            let haircut = if marked_final_value > Decimal::ZERO {
+                final_value / marked_final_value
+            } else {
+                Decimal::ONE
+            };
+            let growth = (Decimal::ONE + self.metrics.time_weighted_return()) * haircut;
+            ((growth - Decimal::ONE) * Decimal::from(100)).to_f64().unwrap_or(0.0)
         };
         
-        // Calculate Sharpe ratio
-        let sharpe_ratio = self.calculate_sharpe_ratio();
-        
-        // Calculate max drawdown
-        let max_drawdown_pct = self.calculate_max_drawdown();
+        let sharpe_ratio = self.metrics.sharpe_ratio();
+        let max_drawdown_pct = self.metrics.max_drawdown_pct();
+        let volatility_pct = self.metrics.volatility_pct();
         
-        // Calculate volatility
-        let volatility_pct = self.calculate_volatility();
-        
-        // Calculate VaR (simplified)
-        let value_at_risk = self.calculate_var(0.95);
-        let conditional_var = self.calculate_cvar(0.95);
+        // Historical one-step VaR scaled to the current book
+        let value_at_risk = self.metrics.value_at_risk(0.95, self.portfolio.total_value);
+        let conditional_var = self.metrics.conditional_var(0.95, self.portfolio.total_value);
         
         SimulationResults {
             initial_value,
             final_value,
+            marked_final_value,
+            terminal_liquidation_cost,
             total_return,
             total_return_pct,
             sharpe_ratio,
+            sortino_ratio: self.metrics.sortino_ratio(),
+            calmar_ratio: self.metrics.calmar_ratio(),
             max_drawdown_pct,
+            drawdown_durations: self.metrics.drawdown_durations(),
             volatility_pct,
             value_at_risk,
             conditional_var,
-            portfolio_history: self.portfolio_history,
-        }
-    }
-
-    fn calculate_sharpe_ratio(&self) -> f64 {
-        if self.portfolio_history.len() < 2 {
-            return 0.0;
-        }
-        
-        let returns: Vec<f64> = self.portfolio_history
-            .windows(2)
-            .map(|w| {
-                let prev = w[0].total_value.to_f64().unwrap_or(0.0);
-                let curr = w[1].total_value.to_f64().unwrap_or(0.0);
-                if prev > 0.0 {
-                    (curr - prev) / prev
+            peak_leverage: self.peak_leverage,
+            margin_calls: self.margin_calls,
+            cash_flows: self.cash_flows.clone(),
+            net_cash_flow,
+            money_weighted_return_pct: self.money_weighted_return_pct(initial_value, final_value),
+            benchmark_final_value: self.benchmark.as_ref().map(|b| b.total_value),
+            excess_return_pct: self.benchmark.as_ref().map(|b| {
+                let initial = self.benchmark_metrics.initial_value().unwrap_or(Decimal::ZERO);
+                let benchmark_return_pct = if initial > Decimal::ZERO {
+                    ((b.total_value - initial) / initial * Decimal::from(100)).to_f64().unwrap_or(0.0)
                 } else {
                     0.0
-                }
-            })
-            .collect();
-        
-        if returns.is_empty() {
-            return 0.0;
+                };
+                total_return_pct - benchmark_return_pct
+            }),
+            tracking_error: self.benchmark
+                .as_ref()
+                .map(|_| self.active_returns.tracking_error_pct(self.metrics.periods_per_year())),
+            information_ratio: self.benchmark
+                .as_ref()
+                .map(|_| self.active_returns.information_ratio(self.metrics.periods_per_year())),
+            halted_at_step: self.halted_at,
+            total_fees: self.fees_paid,
+            income_earned: self.income_earned,
+            defaulted_assets: self.portfolio.defaulted.len(),
+            jumps: self.jumps,
+            regime_occupancy: self.regime_occupancy.clone(),
+            portfolio_history: vec![],
+            decisions: vec![],
+            trades: vec![],
+            fills: vec![],
+            shocks: vec![],
+            rolling_metrics: vec![],
+            price_history: HashMap::new(),
+            price_history_start: HashMap::new(),
+            transactions: None,
         }
+    }
+
+    /// Annualized IRR of the initial value, every cash flow, and the final value
+    fn money_weighted_return_pct(&self, initial_value: Decimal, final_value: Decimal) -> Option<f64> {
+        let mut flows = vec![(0.0, -initial_value.to_f64()?)];
+        flows.extend(
+            self.cash_flows
+                .iter()
+                .map(|flow| (flow.step as f64, -flow.amount.to_f64().unwrap_or(0.0))),
+        );
+        flows.push((self.step_count as f64, final_value.to_f64()?));
         
-        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
-        let variance = returns.iter()
-            .map(|r| (r - mean).powi(2))
-            .sum::<f64>() / returns.len() as f64;
-        let std_dev = variance.sqrt();
+        let per_step = crate::utils::internal_rate_of_return(&flows)?;
+        let annualized = ((1.0 + per_step).powf(self.metrics.periods_per_year()) - 1.0) * 100.0;
+        annualized.is_finite().then_some(annualized)
+    }
+
+    /// Difference between the marked book and what liquidating it would realize:
+    /// each position sold whole for cash and charged the fee the raise-cash
+    /// waterfall would, plus its price impact when `use_depth` is set
+    fn terminal_liquidation_cost(&self) -> Decimal {
+        let TerminalValuation::LiquidateAll { use_depth } = self.config.terminal_valuation else {
+            return Decimal::ZERO;
+        };
         
-        if std_dev > 0.0 {
-            mean / std_dev * (252.0_f64).sqrt() // Annualized Sharpe
-        } else {
-            0.0
+        let cost_rate = self.config.rebalance_cost_rate;
+        self.portfolio.positions_in_symbol_order()
+            .filter(|position| position.current_value > Decimal::ZERO)
+            .map(|position| {
+                let symbol = &position.asset.symbol;
+                let value = position.current_value;
+                let decision = self.cash_raising_sell(symbol.clone(), value);
+                let fee = self.fee(&decision, value * cost_rate);
+                let impact = match (use_depth, self.config.price_impact, self.liquidity(symbol)) {
+                    (true, Some(impact), Some(liquidity)) => impact.cost(value, liquidity),
+                    // Constant-product pool: selling `value` into depth `d` yields value * d / (d + value)
+                    (true, None, Some(depth)) if depth > Decimal::ZERO => value - value * depth / (depth + value),
+                    _ => Decimal::ZERO,
+                };
+                (fee + impact).min(value)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shocks::JumpModel;
+    use crate::transactions::TransactionEntry;
+    use time::macros::datetime;
+
+    fn throttled(throttle: ThrottleConfig) -> SimulatorConfig {
+        SimulatorConfig {
+            seed: Some(11),
+            start_time: Some(datetime!(2024-01-01 00:00 UTC)),
+            record_transactions: true,
+            strategy_throttles: HashMap::from([("yield_maximizer".to_string(), throttle)]),
+            ..SimulatorConfig::default()
         }
     }
 
-    fn calculate_max_drawdown(&self) -> f64 {
-        if self.portfolio_history.is_empty() {
-            return 0.0;
+    /// Steps of the executed decisions and of the rejections whose reason contains `reason`
+    fn yield_maximizer_run(config: SimulatorConfig, reason: &str) -> (Vec<(usize, String)>, usize) {
+        let mut simulator = Simulator::with_config(1_000_000.0, crate::strategy::Strategy::yield_maximizer(), config);
+        for _ in 0..100 {
+            simulator.step().expect("step failed");
         }
-        
-        let values: Vec<f64> = self.portfolio_history
+        let results = simulator.current_results();
+        let entries = results.transaction_log().expect("transactions are recorded").entries();
+        let executed = entries
             .iter()
-            .map(|s| s.total_value.to_f64().unwrap_or(0.0))
+            .filter_map(|entry| match entry {
+                TransactionEntry::Decision { step, symbol, .. } => Some((*step, symbol.clone())),
+                _ => None,
+            })
             .collect();
-        
-        let mut max_value = values[0];
-        let mut max_drawdown = 0.0;
-        
-        for &value in &values {
-            if value > max_value {
-                max_value = value;
+        let rejected = entries
+            .iter()
+            .filter(|entry| matches!(entry, TransactionEntry::Rejected { reason: why, .. } if why.contains(reason)))
+            .count();
+        (executed, rejected)
+    }
+
+    #[test]
+    fn yield_maximizer_trades_a_symbol_at_most_once_per_cooldown() {
+        let (unthrottled, _) = yield_maximizer_run(throttled(ThrottleConfig::default()), "cooldown");
+        let cooldown = ThrottleConfig { cooldown_steps: Some(10), ..ThrottleConfig::default() };
+        let (executed, rejected) = yield_maximizer_run(throttled(cooldown), "10-step cooldown");
+
+        assert!(unthrottled.len() > executed.len(), "the cooldown should cut the churn");
+        assert!(rejected > 0, "the yield maximizer should hit the cooldown");
+        let mut last: HashMap<&str, usize> = HashMap::new();
+        for (step, symbol) in &executed {
+            if let Some(previous) = last.insert(symbol, *step) {
+                assert!(step - previous >= 10, "{} traded at steps {} and {}", symbol, previous, step);
+            }
+        }
+    }
+
+    #[test]
+    fn notional_cap_keeps_the_largest_decisions_and_clips_the_one_that_crosses_it() {
+        let throttle = ThrottleConfig { max_step_notional_pct: Some(dec!(0.5)), ..ThrottleConfig::default() };
+        let config = SimulatorConfig { throttle, ..SimulatorConfig::default() };
+        let burst = || {
+            [("A", dec!(100)), ("B", dec!(300)), ("C", dec!(250)), ("D", dec!(300)), ("E", dec!(50))]
+                .map(|(symbol, amount)| routing(CASH_SYMBOL, symbol, amount, amount / dec!(100)))
+                .to_vec()
+        };
+
+        for _ in 0..2 {
+            // A budget of 500: B takes 300, D ties with B but comes later and is clipped to 200
+            let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(1000), config.clone());
+            let kept = simulator.throttle_decisions(burst());
+            let kept: Vec<_> =
+                kept.iter().map(|d| (d.target_asset.as_str(), d.amount, d.execution_cost.round_dp(8))).collect();
+            assert_eq!(kept, [("B", dec!(300), dec!(3)), ("D", dec!(200), dec!(2))]);
+
+            let rejected: Vec<_> = simulator
+                .decision_log
+                .iter()
+                .map(|entry| match &entry.status {
+                    DecisionStatus::Rejected { reason } => (entry.decision.target_asset.as_str(), reason.as_str()),
+                    status => panic!("unexpected {:?}", status),
+                })
+                .collect();
+            let reason = "Per-step notional cap reached";
+            assert_eq!(rejected, [("A", reason), ("C", reason), ("E", reason)]);
+        }
+    }
+
+    /// An asset priced at 1 that doesn't move or pay
+    fn asset(symbol: &str, asset_type: AssetType) -> Asset {
+        Asset {
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            asset_type,
+            current_price: Decimal::ONE,
+            volatility: Decimal::ZERO,
+            yield_rate: Decimal::ZERO,
+            expected_return: Decimal::ZERO,
+            bond: None,
+            liquidity: None,
+        }
+    }
+
+    /// A simulator holding `units` of `asset` and no cash, so the conservative
+    /// strategy never trades
+    fn holding_asset(asset: Asset, units: Decimal, config: SimulatorConfig) -> Simulator {
+        let mut portfolio = Portfolio::new(units * asset.current_price);
+        portfolio.add_position(Position::new(asset.clone(), units, asset.current_price));
+        Simulator::from_parts(portfolio, crate::strategy::Strategy::conservative(), config, None)
+    }
+
+    /// A simulator holding `units` of `symbol` at a price of 1 and no cash
+    fn holding(symbol: &str, asset_type: AssetType, units: Decimal, config: SimulatorConfig) -> Simulator {
+        holding_asset(asset(symbol, asset_type), units, config)
+    }
+
+    /// Log returns of a lone held asset's price over `steps` daily steps
+    fn walked_log_returns(
+        volatility: Decimal,
+        expected_return: Decimal,
+        steps: usize,
+        config: SimulatorConfig,
+    ) -> Vec<f64> {
+        let walked = Asset { volatility, expected_return, ..asset("X", AssetType::Crypto) };
+        let mut simulator = holding_asset(walked, Decimal::ONE, config);
+        let mut last = Decimal::ONE;
+        let mut returns = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            simulator.step().expect("step failed");
+            let price = simulator.portfolio.positions["X"].asset.current_price;
+            assert!(price > Decimal::ZERO, "GBM prices stay positive");
+            returns.push((price / last).to_f64().unwrap().ln());
+            last = price;
+        }
+        returns
+    }
+
+    #[test]
+    fn gbm_log_returns_have_the_configured_drift_and_volatility() {
+        let (volatility, drift, steps) = (0.5, 0.2, 20_000);
+        let config = SimulatorConfig { seed: Some(3), ..SimulatorConfig::default() };
+        let returns = walked_log_returns(dec!(0.5), dec!(0.2), steps, config);
+        let dt = SimulatorConfig::default().dt();
+        let mean = returns.iter().sum::<f64>() / steps as f64;
+        let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (steps - 1) as f64).sqrt();
+
+        let expected_std = volatility * dt.sqrt();
+        assert!((std_dev / expected_std - 1.0).abs() < 0.03, "{} vs {}", std_dev, expected_std);
+        let expected_mean = (drift - 0.5 * volatility * volatility) * dt;
+        let standard_error = expected_std / (steps as f64).sqrt();
+        assert!((mean - expected_mean).abs() < 4.0 * standard_error, "{} vs {}", mean, expected_mean);
+    }
+
+    #[test]
+    fn gbm_prices_stay_positive_under_extreme_volatility() {
+        // With write-offs off, only the price floor keeps the collapsing price above zero
+        let config = SimulatorConfig {
+            seed: Some(3),
+            default_price_threshold: Decimal::ZERO,
+            ..SimulatorConfig::default()
+        };
+        let returns = walked_log_returns(dec!(8), Decimal::ZERO, 2_000, config);
+        assert!(returns.iter().all(|r| r.is_finite()));
+        assert!(returns.iter().sum::<f64>() < -20.0, "the price should have collapsed toward the floor");
+    }
+
+    fn liquidating(use_depth: bool, cost_rate: Decimal) -> SimulatorConfig {
+        SimulatorConfig {
+            terminal_valuation: TerminalValuation::LiquidateAll { use_depth },
+            rebalance_cost_rate: cost_rate,
+            market_depth: HashMap::from([("THIN".to_string(), dec!(100000))]),
+            ..SimulatorConfig::default()
+        }
+    }
+
+    #[test]
+    fn thin_pool_realizes_well_below_its_mark() {
+        let simulator = holding("THIN", AssetType::DeFiPool, dec!(1000000), liquidating(true, Decimal::ZERO));
+        let results = simulator.current_results();
+
+        assert_eq!(results.marked_final_value, dec!(1000000));
+        assert_eq!(results.terminal_liquidation_cost.round_dp(2), dec!(909090.91));
+        assert_eq!(results.final_value, results.marked_final_value - results.terminal_liquidation_cost);
+
+        let undepthed = holding("THIN", AssetType::DeFiPool, dec!(1000000), liquidating(false, Decimal::ZERO));
+        assert_eq!(undepthed.current_results().terminal_liquidation_cost, Decimal::ZERO);
+    }
+
+    #[test]
+    fn fee_free_stablecoin_book_realizes_its_mark() {
+        let simulator = holding("USDC", AssetType::Stablecoin, dec!(1000000), liquidating(true, Decimal::ZERO));
+        let results = simulator.current_results();
+
+        assert_eq!(results.final_value, results.marked_final_value);
+        assert_eq!(results.terminal_liquidation_cost, Decimal::ZERO);
+    }
+
+    #[test]
+    fn liquidation_pays_the_raise_cash_fees() {
+        let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(1000000), liquidating(false, dec!(0.002)));
+        let cost = simulator.current_results().terminal_liquidation_cost;
+        assert_eq!(cost, dec!(2000));
+
+        // Selling the whole book through the waterfall pays the same fees
+        simulator.raise_cash(dec!(1000000));
+        assert!(simulator.portfolio.positions.values().all(|position| position.quantity.is_zero()));
+        assert_eq!(simulator.fees_paid, cost);
+    }
+
+    fn run_with_history(history_policy: HistoryPolicy) -> SimulationResults {
+        let config = SimulatorConfig { seed: Some(5), history_policy, ..SimulatorConfig::default() };
+        let mut simulator = Simulator::with_config(1_000_000.0, crate::strategy::Strategy::balanced(), config);
+        for _ in 0..100 {
+            simulator.step().expect("step failed");
+        }
+        simulator.finalize()
+    }
+
+    #[test]
+    fn current_results_agree_with_finalize_at_the_same_step() {
+        // The simulator isn't Clone; two seeded runs stepped alike stand in for a clone
+        let run = || {
+            let config = SimulatorConfig {
+                seed: Some(13),
+                start_time: Some(datetime!(2024-01-01 00:00 UTC)),
+                benchmark: Some(Benchmark::Symbol("BTC".to_string())),
+                rolling_window: Some(10),
+                record_transactions: true,
+                ..SimulatorConfig::default()
+            };
+            let mut simulator = Simulator::with_config(1_000_000.0, crate::strategy::Strategy::balanced(), config);
+            for _ in 0..60 {
+                simulator.step().expect("step failed");
             }
-            let drawdown = (max_value - value) / max_value * 100.0;
-            if drawdown > max_drawdown {
-                max_drawdown = drawdown;
+            simulator
+        };
+        let current = run().current_results();
+        let last = run().finalize();
+
+        assert_eq!(current.initial_value, last.initial_value);
+        assert_eq!(current.final_value, last.final_value);
+        assert_eq!(current.marked_final_value, last.marked_final_value);
+        assert_eq!(current.terminal_liquidation_cost, last.terminal_liquidation_cost);
+        assert_eq!(current.total_return, last.total_return);
+        assert_eq!(current.total_return_pct, last.total_return_pct);
+        assert_eq!(current.sharpe_ratio, last.sharpe_ratio);
+        assert_eq!(current.sortino_ratio, last.sortino_ratio);
+        assert_eq!(current.calmar_ratio, last.calmar_ratio);
+        assert_eq!(current.max_drawdown_pct, last.max_drawdown_pct);
+        assert_eq!(current.drawdown_durations, last.drawdown_durations);
+        assert_eq!(current.volatility_pct, last.volatility_pct);
+        assert_eq!(current.value_at_risk, last.value_at_risk);
+        assert_eq!(current.conditional_var, last.conditional_var);
+        assert_eq!(current.peak_leverage, last.peak_leverage);
+        assert_eq!(current.net_cash_flow, last.net_cash_flow);
+        assert_eq!(current.money_weighted_return_pct, last.money_weighted_return_pct);
+        assert_eq!(current.benchmark_final_value, last.benchmark_final_value);
+        assert_eq!(current.excess_return_pct, last.excess_return_pct);
+        assert_eq!(current.tracking_error, last.tracking_error);
+        assert_eq!(current.information_ratio, last.information_ratio);
+        assert_eq!(current.total_fees, last.total_fees);
+        assert_eq!(current.income_earned, last.income_earned);
+        assert_eq!(current.portfolio_history.len(), last.portfolio_history.len());
+        assert_eq!(current.decisions.len(), last.decisions.len());
+        assert_eq!(current.trades.len(), last.trades.len());
+        assert_eq!(current.fills.len(), last.fills.len());
+        assert_eq!(current.rolling_metrics.len(), last.rolling_metrics.len());
+        assert_eq!(
+            current.transaction_log().map(|log| log.entries().len()),
+            last.transaction_log().map(|log| log.entries().len())
+        );
+        assert!(current.benchmark_final_value.is_some() && !current.decisions.is_empty());
+    }
+
+    #[test]
+    fn bounded_history_keeps_the_metrics_of_the_full_run() {
+        let full = run_with_history(HistoryPolicy::Full);
+        let every = run_with_history(HistoryPolicy::EveryN(10));
+        let last = run_with_history(HistoryPolicy::LastN(7));
+
+        assert_eq!(full.portfolio_history.len(), 101);
+        let steps: Vec<usize> = every.portfolio_history.iter().map(|snapshot| snapshot.step).collect();
+        assert_eq!(steps, (0..=100).step_by(10).collect::<Vec<_>>());
+        assert_eq!(last.portfolio_history.len(), 7);
+        assert_eq!(last.portfolio_history.last().map(|snapshot| snapshot.step), Some(100));
+        for results in [&every, &last] {
+            assert_eq!(results.final_value, full.final_value);
+            assert_eq!(results.sharpe_ratio, full.sharpe_ratio);
+            assert_eq!(results.sortino_ratio, full.sortino_ratio);
+            assert_eq!(results.max_drawdown_pct, full.max_drawdown_pct);
+            assert_eq!(results.value_at_risk, full.value_at_risk);
+        }
+    }
+
+    #[test]
+    fn collapsed_asset_is_written_off_and_no_longer_traded() {
+        let config = SimulatorConfig { record_transactions: true, ..SimulatorConfig::default() };
+        let mut simulator = holding("X", AssetType::DeFiPool, dec!(1000), config);
+        simulator.schedule_shock(1, "X", dec!(-99.9)).unwrap();
+        simulator.schedule_shock(2, "X", dec!(-100)).unwrap();
+
+        // A 99.9% fall leaves a price well above the default threshold
+        simulator.step().unwrap();
+        assert_eq!(simulator.portfolio.positions["X"].asset.current_price, dec!(0.001));
+        assert!(simulator.portfolio.defaulted.is_empty());
+
+        simulator.step().unwrap();
+        assert!(simulator.portfolio.positions.is_empty());
+        let defaulted = &simulator.portfolio.defaulted[0];
+        assert_eq!((defaulted.symbol.as_str(), defaulted.quantity, defaulted.step), ("X", dec!(1000), 2));
+        assert_eq!(simulator.portfolio.total_value, Decimal::ZERO);
+        assert_eq!(simulator.trades().last().and_then(|trade| trade.pnl), Some(dec!(-1000)));
+
+        let buy = RoutingDecision {
+            timestamp: simulator.clock,
+            source_asset: CASH_SYMBOL.to_string(),
+            target_asset: "X".to_string(),
+            amount: dec!(100),
+            expected_yield: Decimal::ZERO,
+            risk_score: 0.0,
+            execution_cost: Decimal::ZERO,
+            source_currency: None,
+        };
+        assert!(simulator.reject_defaulted(vec![buy]).is_empty());
+        let results = simulator.current_results();
+        let entries = results.transaction_log().unwrap().entries();
+        assert!(entries.iter().any(|entry| matches!(entry, TransactionEntry::WriteOff { step: 2, .. })));
+        assert!(entries.iter().any(
+            |entry| matches!(entry, TransactionEntry::Rejected { reason, .. } if reason == "Asset has defaulted")
+        ));
+    }
+
+    fn routing(source: &str, target: &str, amount: Decimal, execution_cost: Decimal) -> RoutingDecision {
+        RoutingDecision {
+            timestamp: OffsetDateTime::UNIX_EPOCH,
+            source_asset: source.to_string(),
+            target_asset: target.to_string(),
+            amount,
+            expected_yield: Decimal::ZERO,
+            risk_score: 0.0,
+            execution_cost,
+            source_currency: None,
+        }
+    }
+
+    #[test]
+    fn trading_rules_round_to_lots_and_enforce_the_minimum() {
+        let rules = TradingRules { min_notional: dec!(100), lot_size: Some(dec!(10)) };
+        let trading_rules = HashMap::from([("X".to_string(), rules)]);
+        let config = SimulatorConfig { trading_rules, ..SimulatorConfig::default() };
+        // 1,003 units at 2.5: not a whole number of lots
+        let priced = Asset { current_price: dec!(2.5), ..asset("X", AssetType::Crypto) };
+        let simulator = holding_asset(priced, dec!(1003), config);
+
+        // 260 buys 104 units, rounded down to 100 with the fee scaled along
+        let mut buy = routing(CASH_SYMBOL, "X", dec!(260), dec!(2.6));
+        assert_eq!(simulator.apply_trading_rules(&mut buy), None);
+        assert_eq!((buy.amount, buy.execution_cost), (dec!(250), dec!(2.5)));
+
+        let mut dust = routing(CASH_SYMBOL, "X", dec!(20), Decimal::ZERO);
+        assert!(simulator.apply_trading_rules(&mut dust).unwrap().contains("rounds to zero"));
+        // Two lots, 50 of notional, is under the minimum
+        let mut small = routing(CASH_SYMBOL, "X", dec!(60), Decimal::ZERO);
+        assert!(simulator.apply_trading_rules(&mut small).unwrap().contains("below minimum"));
+
+        // Closing the whole position is allowed, odd lot and all
+        let mut exit = routing("X", CASH_SYMBOL, dec!(2507.5), Decimal::ZERO);
+        assert_eq!(simulator.apply_trading_rules(&mut exit), None);
+        assert_eq!(exit.amount, dec!(2507.5));
+        // A partial sell is held to the same lots
+        let mut trim = routing("X", CASH_SYMBOL, dec!(1012.5), Decimal::ZERO);
+        assert_eq!(simulator.apply_trading_rules(&mut trim), None);
+        assert_eq!(trim.amount, dec!(1000));
+
+        // Symbols without rules trade any size
+        let mut other = routing(CASH_SYMBOL, "Y", dec!(0.01), Decimal::ZERO);
+        assert_eq!(simulator.apply_trading_rules(&mut other), None);
+        assert_eq!(other.amount, dec!(0.01));
+    }
+
+    #[test]
+    fn deposits_into_a_flat_market_are_not_performance() {
+        // A minimum notional no deposit reaches keeps the strategy from redeploying, and paying fees on, the cash
+        let rules = TradingRules { min_notional: dec!(1000000000), lot_size: None };
+        let config = SimulatorConfig {
+            trading_rules: HashMap::from([("USDC".to_string(), rules)]),
+            ..SimulatorConfig::default()
+        };
+        let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(1000000), config);
+        simulator.schedule_cash_flow(3, dec!(500000)).unwrap();
+        simulator.schedule_cash_flow(7, dec!(2000000)).unwrap();
+        for _ in 0..10 {
+            simulator.step().unwrap();
+        }
+        let results = simulator.current_results();
+
+        assert_eq!(results.net_cash_flow, dec!(2500000));
+        assert_eq!(results.total_fees, Decimal::ZERO);
+        assert_eq!(results.final_value, dec!(3500000));
+        assert_eq!(results.total_return, Decimal::ZERO);
+        assert!(results.total_return_pct.abs() < 1e-9, "time-weighted return {}", results.total_return_pct);
+        assert!(results.money_weighted_return_pct.unwrap().abs() < 1e-6);
+    }
+
+    #[test]
+    fn withdrawal_beyond_cash_sells_every_position_pro_rata() {
+        let config = SimulatorConfig { rebalance_cost_rate: dec!(0.01), ..SimulatorConfig::default() };
+        let mut portfolio = Portfolio::new(dec!(3000));
+        for (symbol, units) in [("A", dec!(1000)), ("B", dec!(2000))] {
+            let held = asset(symbol, AssetType::Stablecoin);
+            portfolio.add_position(Position::new(held, units, Decimal::ONE));
+        }
+        let mut simulator = Simulator::from_parts(portfolio, crate::strategy::Strategy::conservative(), config, None);
+        simulator.schedule_cash_flow(1, dec!(-297)).unwrap();
+        simulator.step().unwrap();
+
+        // 300 gross sold, a tenth of each position, to net 297 after the 1% fee
+        let positions = &simulator.portfolio.positions;
+        assert_eq!(positions["A"].current_value, dec!(900));
+        assert_eq!(positions["B"].current_value, dec!(1800));
+        assert_eq!(simulator.fees_paid, dec!(3));
+        let flow = simulator.cash_flows()[0];
+        assert_eq!((flow.step, flow.amount), (1, dec!(-297)));
+        assert_eq!(simulator.portfolio.cash, Decimal::ZERO);
+    }
+
+    #[test]
+    fn withdrawal_is_capped_at_what_the_book_raises() {
+        let fee_free = SimulatorConfig { rebalance_cost_rate: Decimal::ZERO, ..SimulatorConfig::default() };
+        let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(100), fee_free);
+        simulator.schedule_cash_flow(1, dec!(-1000)).unwrap();
+        assert!(simulator.schedule_cash_flow(0, dec!(1)).is_err());
+        simulator.step().unwrap();
+
+        assert_eq!(simulator.cash_flows()[0].amount, dec!(-100));
+        assert_eq!(simulator.portfolio.total_value, Decimal::ZERO);
+    }
+
+    #[test]
+    fn failed_rebalance_leg_leaves_no_fills() {
+        // One open position allowed: selling half of A works, opening B does not
+        let config = SimulatorConfig { max_positions: Some(1), ..SimulatorConfig::default() };
+        let mut simulator = holding("A", AssetType::Stablecoin, dec!(1000), config);
+        let before = simulator.portfolio.clone();
+        let targets = HashMap::from([("A".to_string(), dec!(0.5)), ("B".to_string(), dec!(0.5))]);
+
+        let error = simulator.rebalance_to(&targets).unwrap_err();
+        assert!(format!("{:#}", error).contains("Position limit of 1 reached"), "{:#}", error);
+        assert!(simulator.fills.is_empty(), "phantom fills {:?}", simulator.fills);
+        assert!(simulator.decision_log.is_empty());
+        assert_eq!(simulator.portfolio.cash, before.cash);
+        assert_eq!(simulator.portfolio.positions["A"].quantity, dec!(1000));
+    }
+
+    #[test]
+    fn regime_occupancy_converges_to_the_stationary_distribution() {
+        let model = RegimeModel::calm_crisis();
+        let stationary = model.stationary_distribution();
+        let config = SimulatorConfig { regimes: Some(model), seed: Some(5), ..SimulatorConfig::default() };
+        let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(1000), config);
+        for _ in 0..20_000 {
+            simulator.step().unwrap();
+        }
+
+        let occupancy = &simulator.current_results().regime_occupancy;
+        assert_eq!(occupancy.iter().sum::<usize>(), 20_000);
+        // Crisis spells last 20 steps, so 20,000 steps see only about 170 of them
+        let crisis_share = occupancy[1] as f64 / 20_000.0;
+        assert!((crisis_share - stationary[1]).abs() < 0.04, "crisis share {}", crisis_share);
+    }
+
+    #[test]
+    fn first_step_runs_in_the_initial_regime() {
+        // Every transition leads to calm, so only the initial regime can be crisis
+        let mut model = RegimeModel::calm_crisis();
+        model.transitions = vec![vec![1.0, 0.0], vec![1.0, 0.0]];
+        model.initial = 1;
+        let config = SimulatorConfig { regimes: Some(model), seed: Some(5), ..SimulatorConfig::default() };
+        let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(1000), config);
+
+        simulator.step().unwrap();
+        assert_eq!(simulator.current_results().regime_occupancy, [0, 1]);
+        simulator.step().unwrap();
+        assert_eq!(simulator.current_results().regime_occupancy, [1, 1]);
+    }
+
+    #[test]
+    fn benchmark_specs_parse_symbols_and_baskets() {
+        assert!(matches!(Benchmark::from_spec(" BTC ").unwrap(), Benchmark::Symbol(symbol) if symbol == "BTC"));
+        let basket = Benchmark::from_spec("BTC:0.6, USDC:0.4").unwrap();
+        assert_eq!(basket.weights(), vec![("BTC".to_string(), dec!(0.6)), ("USDC".to_string(), dec!(0.4))]);
+
+        for bad in ["", "BTC,ETH", "BTC:0.6,ETH:0.3", "BTC:-0.5,ETH:1.5", "BTC:lots", "BTC:0.5,ETH"] {
+            assert!(Benchmark::from_spec(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn strategy_runs_only_on_rebalance_dates() {
+        let config = SimulatorConfig { rebalance_interval: Some(Duration::days(3)), ..SimulatorConfig::default() };
+        let mut simulator = holding("USDC", AssetType::Stablecoin, dec!(1000), config);
+        let mut runs: Vec<OffsetDateTime> = vec![];
+        for _ in 0..8 {
+            simulator.step().unwrap();
+            let last = simulator.last_rebalanced.unwrap();
+            if runs.last() != Some(&last) {
+                runs.push(last);
             }
         }
-        
-        max_drawdown
+        let run_days: Vec<i64> = runs.iter().map(|run| (*run - runs[0]).whole_days()).collect();
+        assert_eq!(run_days, [0, 3, 6]);
     }
 
-    fn calculate_volatility(&self) -> f64 {
-        if self.portfolio_history.len() < 2 {
-            return 0.0;
+    /// Quotes every symbol asked for at 1 + n / 10 on its nth batch, leaving
+    /// out `missing`, and keeps the symbols of each batch
+    struct BatchFeed {
+        missing: &'static str,
+        batches: std::sync::Mutex<Vec<Vec<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncMarketDataProvider for BatchFeed {
+        async fn get_current_price(&self, symbol: &str) -> Result<Decimal> {
+            Err(anyhow::anyhow!("{} should come in a batch", symbol))
         }
-        
-        let returns: Vec<f64> = self.portfolio_history
-            .windows(2)
-            .map(|w| {
-                let prev = w[0].total_value.to_f64().unwrap_or(0.0);
-                let curr = w[1].total_value.to_f64().unwrap_or(0.0);
-                if prev > 0.0 {
-                    (curr - prev) / prev
-                } else {
-                    0.0
-                }
-            })
-            .collect();
-        
-        if returns.is_empty() {
-            return 0.0;
+
+        async fn get_historical_prices(&self, symbol: &str, _days: usize) -> Result<Vec<Decimal>> {
+            Err(anyhow::anyhow!("No history for {}", symbol))
+        }
+
+        async fn get_volatility(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        async fn get_yield_rate(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        async fn get_prices_batch(&self, symbols: &[&str]) -> Result<HashMap<String, Decimal>> {
+            let mut batches = self.batches.lock().unwrap();
+            batches.push(symbols.iter().map(|symbol| symbol.to_string()).collect());
+            let price = Decimal::ONE + Decimal::from(batches.len()) / dec!(10);
+            let quoted = symbols.iter().filter(|symbol| **symbol != self.missing);
+            Ok(quoted.map(|symbol| (symbol.to_string(), price)).collect())
         }
-        
-        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
-        let variance = returns.iter()
-            .map(|r| (r - mean).powi(2))
-            .sum::<f64>() / returns.len() as f64;
-        
-        variance.sqrt() * (252.0_f64).sqrt() * 100.0 // Annualized volatility in %
     }
 
-    fn calculate_var(&self, confidence: f64) -> Decimal {
-        if self.portfolio_history.is_empty() {
-            return Decimal::ZERO;
+    #[tokio::test]
+    async fn async_steps_fetch_their_prices_in_one_batch() {
+        let feed = BatchFeed { missing: "", batches: std::sync::Mutex::new(vec![]) };
+        let mut simulator = holding("X", AssetType::Crypto, dec!(1000), SimulatorConfig::default());
+        for step in 1..=3 {
+            simulator.step_async(&feed).await.unwrap();
+            let expected = Decimal::ONE + Decimal::from(step) / dec!(10);
+            assert_eq!(simulator.portfolio.positions["X"].asset.current_price, expected);
+            assert_eq!(simulator.market_state["X"], expected);
         }
-        
-        let returns: Vec<f64> = self.portfolio_history
-            .windows(2)
-            .map(|w| {
-                let prev = w[0].total_value.to_f64().unwrap_or(0.0);
-                let curr = w[1].total_value.to_f64().unwrap_or(0.0);
-                if prev > 0.0 {
-                    (curr - prev) / prev
-                } else {
-                    0.0
-                }
-            })
-            .collect();
-        
-        if returns.is_empty() {
-            return Decimal::ZERO;
+        let batches = feed.batches.lock().unwrap().clone();
+        assert_eq!(batches.len(), 3);
+        assert!(batches.iter().all(|batch| batch.contains(&"X".to_string())));
+
+        // A symbol the batch leaves out keeps its last price
+        let feed = BatchFeed { missing: "X", batches: std::sync::Mutex::new(vec![]) };
+        simulator.step_async(&feed).await.unwrap();
+        assert_eq!(simulator.portfolio.positions["X"].asset.current_price, dec!(1.3));
+    }
+
+    /// Quotes every symbol at 1 after a fixed latency, with room for all of a
+    /// step's requests in flight at once
+    struct LatencyFeed {
+        latency: std::time::Duration,
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncMarketDataProvider for LatencyFeed {
+        async fn get_current_price(&self, _symbol: &str) -> Result<Decimal> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(self.latency).await;
+            Ok(Decimal::ONE)
+        }
+
+        async fn get_historical_prices(&self, symbol: &str, _days: usize) -> Result<Vec<Decimal>> {
+            Err(anyhow::anyhow!("No history for {}", symbol))
+        }
+
+        async fn get_volatility(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        async fn get_yield_rate(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+
+        fn max_concurrency(&self) -> usize {
+            64
         }
-        
-        let mut sorted_returns = returns.clone();
-        sorted_returns.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let index = ((1.0 - confidence) * sorted_returns.len() as f64) as usize;
-        let var_return = sorted_returns.get(index).copied().unwrap_or(0.0);
-        
-        let current_value = self.portfolio.total_value;
-        current_value * Decimal::from_f64_retain(var_return.abs()).unwrap()
     }
 
-    fn calculate_cvar(&self, confidence: f64) -> Decimal {
-        if self.portfolio_history.is_empty() {
-            return Decimal::ZERO;
+    // Paused, the clock jumps straight to each sleep's end, so elapsed time is exact
+    #[tokio::test(start_paused = true)]
+    async fn batched_async_step_takes_one_latency_quantum() {
+        let mut portfolio = Portfolio::new(dec!(50));
+        for n in 0..50 {
+            let held = asset(&format!("S{:02}", n), AssetType::Stablecoin);
+            portfolio.add_position(Position::new(held, Decimal::ONE, Decimal::ONE));
         }
-        
-        let returns: Vec<f64> = self.portfolio_history
-            .windows(2)
-            .map(|w| {
-                let prev = w[0].total_value.to_f64().unwrap_or(0.0);
-                let curr = w[1].total_value.to_f64().unwrap_or(0.0);
-                if prev > 0.0 {
-                    (curr - prev) / prev
-                } else {
-                    0.0
-                }
-            })
-            .collect();
-        
-        if returns.is_empty() {
-            return Decimal::ZERO;
+        let mut simulator =
+            Simulator::from_parts(portfolio, crate::strategy::Strategy::conservative(), SimulatorConfig::default(), None);
+        let latency = std::time::Duration::from_millis(100);
+        let feed = LatencyFeed { latency, calls: std::sync::atomic::AtomicUsize::new(0) };
+
+        let started = tokio::time::Instant::now();
+        simulator.step_async(&feed).await.unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(feed.calls.load(std::sync::atomic::Ordering::SeqCst), 50);
+        // One after another, the 50 requests would take five seconds
+        assert_eq!(elapsed, latency);
+    }
+
+    /// A simulator holding one unit each of two cryptos and a stablecoin, none of which drift or diffuse
+    fn jump_book(jumps: JumpConfig) -> Simulator {
+        let mut portfolio = Portfolio::new(dec!(3));
+        let assets = [("BTC", AssetType::Crypto), ("ETH", AssetType::Crypto), ("USDC", AssetType::Stablecoin)];
+        for (symbol, asset_type) in assets {
+            portfolio.add_position(Position::new(asset(symbol, asset_type), Decimal::ONE, Decimal::ONE));
         }
-        
-        let mut sorted_returns = returns.clone();
-        sorted_returns.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        
-        let var_index = ((1.0 - confidence) * sorted_returns.len() as f64) as usize;
-        let tail_returns: Vec<f64> = sorted_returns[..var_index].to_vec();
-        
-        if tail_returns.is_empty() {
-            return Decimal::ZERO;
+        let config = SimulatorConfig { seed: Some(11), jumps, ..SimulatorConfig::default() };
+        Simulator::from_parts(portfolio, crate::strategy::Strategy::conservative(), config, None)
+    }
+
+    fn price(simulator: &Simulator, symbol: &str) -> Decimal {
+        simulator.portfolio.positions[symbol].asset.current_price
+    }
+
+    #[test]
+    fn a_market_jump_hits_every_listed_asset_on_the_same_step() {
+        let crash = JumpModel::new(0.3, 0.9f64.ln(), 0.0);
+        let mut simulator = jump_book(JumpConfig::default().with_market(crash, vec![AssetType::Crypto]));
+        let mut jumped_steps = 0;
+        for _ in 0..40 {
+            let before = price(&simulator, "BTC");
+            simulator.step().unwrap();
+            let after = price(&simulator, "BTC");
+            assert_eq!(price(&simulator, "ETH"), after);
+            assert_eq!(price(&simulator, "USDC"), Decimal::ONE);
+            if after == before {
+                continue;
+            }
+            jumped_steps += 1;
+            assert!(((after / before).to_f64().unwrap() - 0.9).abs() < 1e-9);
         }
-        
-        let avg_tail_loss = tail_returns.iter().sum::<f64>() / tail_returns.len() as f64;
-        let current_value = self.portfolio.total_value;
-        current_value * Decimal::from_f64_retain(avg_tail_loss.abs()).unwrap()
+        assert!((1..40).contains(&jumped_steps), "{} jumps", jumped_steps);
+        // One jump per step, however many assets it hits
+        assert_eq!(simulator.jumps, jumped_steps);
+    }
+
+    #[test]
+    fn per_type_jumps_hit_assets_independently() {
+        let crash = JumpModel::new(0.3, 0.9f64.ln(), 0.0);
+        let mut simulator = jump_book(JumpConfig::default().with_asset_type(AssetType::Crypto, crash));
+        let mut apart = 0;
+        for _ in 0..40 {
+            simulator.step().unwrap();
+            apart += usize::from(price(&simulator, "BTC") != price(&simulator, "ETH"));
+        }
+        assert!(apart > 0, "BTC and ETH always jumped together");
+        assert_eq!(price(&simulator, "USDC"), Decimal::ONE);
+        // Every jump takes 10% off one asset
+        let drops = |symbol: &str| (price(&simulator, symbol).to_f64().unwrap().log(0.9)).round() as usize;
+        assert_eq!(simulator.jumps, drops("BTC") + drops("ETH"));
     }
 }
@@ -0,0 +1,246 @@
+use statrs::distribution::{ContinuousCDF, Normal};
+use std::sync::Arc;
+
+/// Bits of precision in each coordinate
+const BITS: usize = 32;
+
+/// Digitally shifted Sobol low-discrepancy sequence.
+///
+/// Dimension 0 is the van der Corput sequence; later dimensions use primitive
+/// polynomials over GF(2) in increasing degree. Initial direction numbers
+/// are drawn pseudo-randomly (odd, as the construction requires) rather than
+/// taken from the Joe–Kuo tables, and every dimension is XORed with a random
+/// shift derived from the seed, so different seeds give independent
+/// randomizations of the same net.
+#[derive(Debug, Clone)]
+pub struct SobolSequence {
+    /// `directions[d][j]` is the direction number for bit `j` of the index in dimension `d`
+    directions: Vec<[u32; BITS]>,
+    shifts: Vec<u32>,
+}
+
+impl SobolSequence {
+    pub fn new(dimensions: usize, seed: u64) -> Self {
+        let mut state = seed;
+        let mut next = move || {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+
+        let mut directions = Vec::with_capacity(dimensions);
+        if dimensions > 0 {
+            let mut first = [0u32; BITS];
+            for (j, v) in first.iter_mut().enumerate() {
+                *v = 1 << (BITS - 1 - j);
+            }
+            directions.push(first);
+        }
+        let mut polynomials = PrimitivePolynomials::default();
+        while directions.len() < dimensions {
+            let polynomial = polynomials.next_polynomial();
+            let degree = (63 - polynomial.leading_zeros()) as usize;
+
+            // m_i odd and below 2^i for i = 1..=degree
+            let mut m = [0u64; BITS];
+            for (i, value) in m.iter_mut().enumerate().take(degree.min(BITS)) {
+                *value = (next() % (1u64 << i)) << 1 | 1;
+            }
+            for i in degree..BITS {
+                let mut value = m[i - degree] ^ (m[i - degree] << degree);
+                for k in 1..degree {
+                    if polynomial >> (degree - k) & 1 == 1 {
+                        value ^= m[i - k] << k;
+                    }
+                }
+                m[i] = value;
+            }
+
+            let mut dimension = [0u32; BITS];
+            for (j, v) in dimension.iter_mut().enumerate() {
+                *v = (m[j] << (BITS - 1 - j)) as u32;
+            }
+            directions.push(dimension);
+        }
+
+        let shifts = (0..dimensions).map(|_| next() as u32).collect();
+        Self { directions, shifts }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Coordinate `dimension` of point `index`, in (0, 1)
+    pub fn sample(&self, index: u32, dimension: usize) -> f64 {
+        let directions = &self.directions[dimension];
+        let mut x = self.shifts[dimension];
+        for (j, v) in directions.iter().enumerate() {
+            if index >> j & 1 == 1 {
+                x ^= v;
+            }
+        }
+        // Centre within the 2^-32 cell so the result is never 0 or 1
+        (x as f64 + 0.5) / (1u64 << BITS) as f64
+    }
+
+    /// Standard normal variate from coordinate `dimension` of point `index`
+    pub fn normal(&self, index: u32, dimension: usize) -> f64 {
+        let standard = Normal::new(0.0, 1.0).expect("unit normal is valid");
+        standard.inverse_cdf(self.sample(index, dimension))
+    }
+}
+
+/// Primitive polynomials over GF(2) in increasing degree, skipping `x + 1`
+/// (used implicitly by dimension 0). Bit `k` holds the coefficient of `x^k`.
+#[derive(Debug, Default)]
+struct PrimitivePolynomials {
+    last: u64,
+}
+
+impl PrimitivePolynomials {
+    fn next_polynomial(&mut self) -> u64 {
+        let mut candidate = self.last.max(0b11) + 2;
+        while !is_primitive(candidate) {
+            candidate += 2;
+        }
+        self.last = candidate;
+        candidate
+    }
+}
+
+/// Whether `polynomial` (constant term set) is primitive: `x` has order `2^degree - 1` modulo it
+fn is_primitive(polynomial: u64) -> bool {
+    let degree = 63 - polynomial.leading_zeros();
+    if degree == 0 || polynomial & 1 == 0 {
+        return false;
+    }
+    let order = (1u64 << degree) - 1;
+    if pow_x(order, polynomial) != 1 {
+        return false;
+    }
+    prime_factors(order)
+        .into_iter()
+        .all(|q| pow_x(order / q, polynomial) != 1)
+}
+
+/// `x^exponent` modulo `polynomial` over GF(2)
+fn pow_x(mut exponent: u64, polynomial: u64) -> u64 {
+    let mut result = 1u64;
+    let mut base = reduce(0b10, polynomial);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, polynomial);
+        }
+        base = mul_mod(base, base, polynomial);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn mul_mod(a: u64, b: u64, polynomial: u64) -> u64 {
+    let mut product = 0u64;
+    let mut a = a;
+    let mut b = b;
+    while b > 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        b >>= 1;
+        a = reduce(a << 1, polynomial);
+    }
+    reduce(product, polynomial)
+}
+
+fn reduce(mut value: u64, polynomial: u64) -> u64 {
+    let degree = 63 - polynomial.leading_zeros();
+    while value != 0 && 63 - value.leading_zeros() >= degree {
+        let shift = (63 - value.leading_zeros()) - degree;
+        value ^= polynomial << shift;
+    }
+    value
+}
+
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors = vec![];
+    let mut p = 2;
+    while p * p <= n {
+        if n.is_multiple_of(p) {
+            factors.push(p);
+            while n.is_multiple_of(p) {
+                n /= p;
+            }
+        }
+        p += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+/// Low-discrepancy source for one path's normal price shocks
+#[derive(Debug, Clone)]
+pub struct QuasiRandomDraws {
+    pub sequence: Arc<SobolSequence>,
+    /// Point of the sequence the path uses; its coordinates are the path's successive draws
+    pub index: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primitive_polynomials_come_in_increasing_degree() {
+        // x^2 + x + 1, x^3 + x + 1, x^3 + x^2 + 1, then the two degree-4 ones
+        let mut polynomials = PrimitivePolynomials::default();
+        let first: Vec<u64> = (0..5).map(|_| polynomials.next_polynomial()).collect();
+        assert_eq!(first, vec![0b111, 0b1011, 0b1101, 0b10011, 0b11001]);
+        // (x + 1)^2 and x^4 + x^3 + x^2 + x + 1, of order 5, are not primitive
+        assert!(!is_primitive(0b101));
+        assert!(!is_primitive(0b11111));
+    }
+
+    #[test]
+    fn every_dimension_puts_one_of_the_first_points_in_each_cell() {
+        let sequence = SobolSequence::new(24, 7);
+        assert_eq!(sequence.dimensions(), 24);
+        for dimension in 0..sequence.dimensions() {
+            let mut cells = [0; 64];
+            for index in 0..64 {
+                let x = sequence.sample(index, dimension);
+                assert!(x > 0.0 && x < 1.0);
+                cells[(x * 64.0) as usize] += 1;
+            }
+            assert!(cells.iter().all(|&count| count == 1), "dimension {} is not stratified", dimension);
+        }
+    }
+
+    #[test]
+    fn pairs_of_dimensions_are_stratified_together() {
+        let sequence = SobolSequence::new(6, 11);
+        for (a, b) in [(0, 1), (1, 2), (3, 5)] {
+            // 256 points, one in each cell of a 16 x 16 grid
+            let mut cells = [[0; 16]; 16];
+            for index in 0..256 {
+                let x = (sequence.sample(index, a) * 16.0) as usize;
+                let y = (sequence.sample(index, b) * 16.0) as usize;
+                cells[x][y] += 1;
+            }
+            assert!(cells.iter().flatten().all(|&count| count == 1), "dimensions {} and {}", a, b);
+        }
+    }
+
+    #[test]
+    fn normals_have_unit_moments() {
+        let sequence = SobolSequence::new(1, 3);
+        let draws: Vec<f64> = (0..4096).map(|index| sequence.normal(index, 0)).collect();
+        let mean = draws.iter().sum::<f64>() / draws.len() as f64;
+        let variance = draws.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / draws.len() as f64;
+        assert!(mean.abs() < 1e-3);
+        assert!((variance - 1.0).abs() < 0.01);
+    }
+}
@@ -1,9 +1,9 @@
+use crate::market_view::MarketView;
 use crate::types::*;
 use anyhow::Result;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use rust_decimal_macros::dec;
 use std::collections::HashMap;
-use time::OffsetDateTime;
 
 /// Strategy trait for capital routing decisions
 pub trait RoutingStrategy {
@@ -13,6 +13,12 @@ pub trait RoutingStrategy {
         market_state: &HashMap<String, Decimal>,
     ) -> Result<Vec<RoutingDecision>>;
     
+    /// Decisions given the market as of the current step, including trailing
+    /// history during backtests; by default, decisions on the current prices alone
+    fn generate_decisions(&self, portfolio: &Portfolio, view: &MarketView) -> Result<Vec<RoutingDecision>> {
+        self.generate_routing_decisions(portfolio, view.prices())
+    }
+    
     fn name(&self) -> &str;
 }
 
@@ -24,6 +30,7 @@ pub enum Strategy {
     Aggressive(AggressiveStrategy),
     YieldMaximizer(YieldMaximizerStrategy),
     RiskParity(RiskParityStrategy),
+    TargetWeight(TargetWeightStrategy),
 }
 
 impl Strategy {
@@ -47,6 +54,11 @@ impl Strategy {
         Self::RiskParity(RiskParityStrategy::new())
     }
     
+    /// Hold `targets` as fractions of portfolio value, rebalancing when they drift
+    pub fn target_weight(targets: HashMap<String, Decimal>) -> Self {
+        Self::TargetWeight(TargetWeightStrategy::new(targets))
+    }
+    
     pub fn from_name(name: &str) -> Result<Self> {
         match name.to_lowercase().as_str() {
             "conservative" => Ok(Self::conservative()),
@@ -54,12 +66,44 @@ impl Strategy {
             "aggressive" => Ok(Self::aggressive()),
             "yield_maximizer" | "yield" => Ok(Self::yield_maximizer()),
             "risk_parity" | "risk" => Ok(Self::risk_parity()),
+            "target_weight" | "target" => Ok(Self::TargetWeight(TargetWeightStrategy::default())),
             _ => Err(anyhow::anyhow!("Unknown strategy: {}", name)),
         }
     }
     
+    /// Share of cash the strategy deploys on each allocation, if it has one
+    pub fn allocation_fraction(&self) -> Option<Decimal> {
+        match self {
+            Self::Conservative(s) => Some(s.allocation_fraction),
+            Self::Balanced(s) => Some(s.allocation_fraction),
+            Self::Aggressive(s) => Some(s.allocation_fraction),
+            Self::YieldMaximizer(s) => Some(s.allocation_fraction),
+            Self::RiskParity(s) => Some(s.allocation_fraction),
+            Self::TargetWeight(_) => None,
+        }
+    }
+    
+    /// Deploy `fraction` of cash on each allocation instead of the strategy's default
+    pub fn with_allocation_fraction(mut self, fraction: Decimal) -> Result<Self> {
+        if fraction <= Decimal::ZERO || fraction > Decimal::ONE {
+            return Err(anyhow::anyhow!("Allocation fraction must be in (0, 1], got {}", fraction));
+        }
+        let slot = match &mut self {
+            Self::Conservative(s) => &mut s.allocation_fraction,
+            Self::Balanced(s) => &mut s.allocation_fraction,
+            Self::Aggressive(s) => &mut s.allocation_fraction,
+            Self::YieldMaximizer(s) => &mut s.allocation_fraction,
+            Self::RiskParity(s) => &mut s.allocation_fraction,
+            Self::TargetWeight(_) => {
+                return Err(anyhow::anyhow!("target_weight allocates by its targets, not a cash fraction"))
+            }
+        };
+        *slot = fraction;
+        Ok(self)
+    }
+    
     pub fn list_all() -> Vec<&'static str> {
-        vec!["conservative", "balanced", "aggressive", "yield_maximizer", "risk_parity"]
+        vec!["conservative", "balanced", "aggressive", "yield_maximizer", "risk_parity", "target_weight"]
     }
 }
 
@@ -75,6 +119,18 @@ impl RoutingStrategy for Strategy {
             Self::Aggressive(s) => s.generate_routing_decisions(portfolio, market_state),
             Self::YieldMaximizer(s) => s.generate_routing_decisions(portfolio, market_state),
             Self::RiskParity(s) => s.generate_routing_decisions(portfolio, market_state),
+            Self::TargetWeight(s) => s.generate_routing_decisions(portfolio, market_state),
+        }
+    }
+    
+    fn generate_decisions(&self, portfolio: &Portfolio, view: &MarketView) -> Result<Vec<RoutingDecision>> {
+        match self {
+            Self::Conservative(s) => s.generate_decisions(portfolio, view),
+            Self::Balanced(s) => s.generate_decisions(portfolio, view),
+            Self::Aggressive(s) => s.generate_decisions(portfolio, view),
+            Self::YieldMaximizer(s) => s.generate_decisions(portfolio, view),
+            Self::RiskParity(s) => s.generate_decisions(portfolio, view),
+            Self::TargetWeight(s) => s.generate_decisions(portfolio, view),
         }
     }
     
@@ -85,25 +141,32 @@ impl RoutingStrategy for Strategy {
             Self::Aggressive(s) => s.name(),
             Self::YieldMaximizer(s) => s.name(),
             Self::RiskParity(s) => s.name(),
+            Self::TargetWeight(s) => s.name(),
         }
     }
 }
 
 /// Conservative strategy: Low risk, stable assets
+#[derive(Debug, Clone)]
 pub struct ConservativeStrategy {
-    max_position_size: f64,
-    min_yield: f64,
+    /// Share of cash deployed on each allocation
+    allocation_fraction: Decimal,
 }
 
 impl ConservativeStrategy {
     pub fn new() -> Self {
         Self {
-            max_position_size: 0.15, // 15% max per position
-            min_yield: 0.03, // 3% minimum yield
+            allocation_fraction: dec!(0.3), // 30% of cash
         }
     }
 }
 
+impl Default for ConservativeStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RoutingStrategy for ConservativeStrategy {
     fn generate_routing_decisions(
         &self,
@@ -118,17 +181,18 @@ impl RoutingStrategy for ConservativeStrategy {
         }
         
         // Conservative allocation to stable assets
-        let allocation_amount = portfolio.cash * dec!(0.3); // 30% of cash
+        let allocation_amount = portfolio.cash * self.allocation_fraction;
         
         if allocation_amount > dec!(1000) {
             decisions.push(RoutingDecision {
-                timestamp: OffsetDateTime::now_utc(),
+                timestamp: portfolio.timestamp,
                 source_asset: "USD".to_string(),
                 target_asset: "USDC".to_string(),
                 amount: allocation_amount,
                 expected_yield: dec!(0.05), // 5% APY
                 risk_score: 0.1,
                 execution_cost: allocation_amount * dec!(0.001), // 0.1% fee
+                source_currency: None,
             });
         }
         
@@ -141,25 +205,31 @@ impl RoutingStrategy for ConservativeStrategy {
 }
 
 /// Balanced strategy: Diversified allocation
+#[derive(Debug, Clone)]
 pub struct BalancedStrategy {
-    max_position_size: f64,
-    target_positions: usize,
+    /// Share of cash deployed on each allocation, split evenly across the target assets
+    allocation_fraction: Decimal,
 }
 
 impl BalancedStrategy {
     pub fn new() -> Self {
         Self {
-            max_position_size: 0.25, // 25% max per position
-            target_positions: 5,
+            allocation_fraction: Decimal::ONE, // 20% per asset
         }
     }
 }
 
+impl Default for BalancedStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RoutingStrategy for BalancedStrategy {
     fn generate_routing_decisions(
         &self,
         portfolio: &Portfolio,
-        market_state: &HashMap<String, Decimal>,
+        _market_state: &HashMap<String, Decimal>,
     ) -> Result<Vec<RoutingDecision>> {
         let mut decisions = vec![];
         
@@ -169,20 +239,26 @@ impl RoutingStrategy for BalancedStrategy {
         }
         
         // Allocate to multiple assets
-        let allocation_per_asset = available_cash * dec!(0.2); // 20% per asset
-        
         let target_assets = vec!["USDC", "ETH", "BTC", "SOL", "MATIC"];
+        let allocation_per_asset =
+            available_cash * self.allocation_fraction / Decimal::from(target_assets.len());
         
+        let mut slots = portfolio.remaining_position_slots();
         for asset in target_assets {
+            if slots == Some(0) {
+                break;
+            }
             if !portfolio.positions.contains_key(asset) && allocation_per_asset > dec!(500) {
+                slots = slots.map(|n| n - 1);
                 decisions.push(RoutingDecision {
-                    timestamp: OffsetDateTime::now_utc(),
+                    timestamp: portfolio.timestamp,
                     source_asset: "USD".to_string(),
                     target_asset: asset.to_string(),
                     amount: allocation_per_asset,
                     expected_yield: dec!(0.08), // 8% expected yield
                     risk_score: 0.5,
                     execution_cost: allocation_per_asset * dec!(0.002), // 0.2% fee
+                    source_currency: None,
                 });
             }
         }
@@ -196,20 +272,26 @@ impl RoutingStrategy for BalancedStrategy {
 }
 
 /// Aggressive strategy: High risk, high reward
+#[derive(Debug, Clone)]
 pub struct AggressiveStrategy {
-    max_position_size: f64,
-    min_yield: f64,
+    /// Share of cash deployed on each allocation
+    allocation_fraction: Decimal,
 }
 
 impl AggressiveStrategy {
     pub fn new() -> Self {
         Self {
-            max_position_size: 0.4, // 40% max per position
-            min_yield: 0.15, // 15% minimum yield
+            allocation_fraction: dec!(0.6), // 60% of cash
         }
     }
 }
 
+impl Default for AggressiveStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RoutingStrategy for AggressiveStrategy {
     fn generate_routing_decisions(
         &self,
@@ -224,16 +306,17 @@ impl RoutingStrategy for AggressiveStrategy {
         }
         
         // Aggressive allocation to high-yield assets
-        let allocation_amount = available_cash * dec!(0.6); // 60% of cash
+        let allocation_amount = available_cash * self.allocation_fraction;
         
         decisions.push(RoutingDecision {
-            timestamp: OffsetDateTime::now_utc(),
+            timestamp: portfolio.timestamp,
             source_asset: "USD".to_string(),
             target_asset: "HIGH_YIELD_POOL".to_string(),
             amount: allocation_amount,
             expected_yield: dec!(0.20), // 20% APY
             risk_score: 0.8,
             execution_cost: allocation_amount * dec!(0.005), // 0.5% fee
+            source_currency: None,
         });
         
         Ok(decisions)
@@ -245,18 +328,29 @@ impl RoutingStrategy for AggressiveStrategy {
 }
 
 /// Yield maximizer: Always route to highest yield
+#[derive(Debug, Clone)]
 pub struct YieldMaximizerStrategy {
-    rebalance_threshold: f64,
+    /// Share of cash deployed on each allocation
+    allocation_fraction: Decimal,
+    /// Most an order may move its target's price, when the market view shows liquidity
+    max_price_impact: f64,
 }
 
 impl YieldMaximizerStrategy {
     pub fn new() -> Self {
         Self {
-            rebalance_threshold: 0.02, // 2% yield difference triggers rebalance
+            allocation_fraction: dec!(0.9), // 90% allocation
+            max_price_impact: 0.01, // 1% impact
         }
     }
 }
 
+impl Default for YieldMaximizerStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RoutingStrategy for YieldMaximizerStrategy {
     fn generate_routing_decisions(
         &self,
@@ -269,37 +363,63 @@ impl RoutingStrategy for YieldMaximizerStrategy {
         let available_cash = portfolio.cash;
         if available_cash > dec!(1000) {
             decisions.push(RoutingDecision {
-                timestamp: OffsetDateTime::now_utc(),
+                timestamp: portfolio.timestamp,
                 source_asset: "USD".to_string(),
                 target_asset: "MAX_YIELD".to_string(),
-                amount: available_cash * dec!(0.9), // 90% allocation
+                amount: available_cash * self.allocation_fraction,
                 expected_yield: dec!(0.25), // 25% APY
                 risk_score: 0.7,
                 execution_cost: available_cash * dec!(0.003), // 0.3% fee
+                source_currency: None,
             });
         }
         
         Ok(decisions)
     }
     
+    /// Orders are capped at what the target's liquidity absorbs within the
+    /// maximum price impact, keeping the rest in cash
+    fn generate_decisions(&self, portfolio: &Portfolio, view: &MarketView) -> Result<Vec<RoutingDecision>> {
+        let mut decisions = self.generate_routing_decisions(portfolio, view.prices())?;
+        for decision in &mut decisions {
+            let Some(cap) = view.max_notional(&decision.target_asset, self.max_price_impact) else {
+                continue;
+            };
+            if decision.amount > cap {
+                decision.execution_cost = decision.execution_cost * cap / decision.amount;
+                decision.amount = cap;
+            }
+        }
+        decisions.retain(|decision| decision.amount > Decimal::ZERO);
+        Ok(decisions)
+    }
+    
     fn name(&self) -> &str {
         "yield_maximizer"
     }
 }
 
 /// Risk parity: Equal risk contribution from each position
+#[derive(Debug, Clone)]
 pub struct RiskParityStrategy {
-    target_volatility: f64,
+    /// Share of cash deployed on each allocation, split evenly across the assets
+    allocation_fraction: Decimal,
 }
 
 impl RiskParityStrategy {
     pub fn new() -> Self {
         Self {
-            target_volatility: 0.10, // 10% target volatility
+            allocation_fraction: Decimal::ONE,
         }
     }
 }
 
+impl Default for RiskParityStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RoutingStrategy for RiskParityStrategy {
     fn generate_routing_decisions(
         &self,
@@ -315,18 +435,24 @@ impl RoutingStrategy for RiskParityStrategy {
         
         // Allocate equally across uncorrelated assets
         let assets = vec!["USDC", "ETH", "BTC", "SOL"];
-        let allocation_per_asset = available_cash / Decimal::from(assets.len());
+        let allocation_per_asset = available_cash * self.allocation_fraction / Decimal::from(assets.len());
         
+        let mut slots = portfolio.remaining_position_slots();
         for asset in assets {
+            if slots == Some(0) {
+                break;
+            }
             if !portfolio.positions.contains_key(asset) && allocation_per_asset > dec!(500) {
+                slots = slots.map(|n| n - 1);
                 decisions.push(RoutingDecision {
-                    timestamp: OffsetDateTime::now_utc(),
+                    timestamp: portfolio.timestamp,
                     source_asset: "USD".to_string(),
                     target_asset: asset.to_string(),
                     amount: allocation_per_asset,
                     expected_yield: dec!(0.10), // 10% expected yield
                     risk_score: 0.4,
                     execution_cost: allocation_per_asset * dec!(0.002), // 0.2% fee
+                    source_currency: None,
                 });
             }
         }
@@ -338,3 +464,137 @@ impl RoutingStrategy for RiskParityStrategy {
         "risk_parity"
     }
 }
+
+/// Target weight: hold fixed fractions of portfolio value, trimming winners and topping up laggards
+#[derive(Debug, Clone)]
+pub struct TargetWeightStrategy {
+    targets: HashMap<String, Decimal>,
+    drift_threshold: Decimal,
+    cost_rate: Decimal,
+}
+
+impl TargetWeightStrategy {
+    pub fn new(targets: HashMap<String, Decimal>) -> Self {
+        Self {
+            targets,
+            drift_threshold: dec!(0.05), // rebalance once any weight is 5 points off
+            cost_rate: dec!(0.002), // 0.2% fee on each side
+        }
+    }
+    
+    pub fn targets(&self) -> &HashMap<String, Decimal> {
+        &self.targets
+    }
+}
+
+impl Default for TargetWeightStrategy {
+    fn default() -> Self {
+        Self::new(HashMap::from([
+            ("USDC".to_string(), dec!(0.4)),
+            ("ETH".to_string(), dec!(0.3)),
+            ("BTC".to_string(), dec!(0.3)),
+        ]))
+    }
+}
+
+impl RoutingStrategy for TargetWeightStrategy {
+    fn generate_routing_decisions(
+        &self,
+        portfolio: &Portfolio,
+        _market_state: &HashMap<String, Decimal>,
+    ) -> Result<Vec<RoutingDecision>> {
+        if portfolio.total_value <= Decimal::ZERO {
+            return Ok(vec![]);
+        }
+        
+        let drifted = self.targets.iter().any(|(symbol, target)| {
+            let value = portfolio
+                .positions
+                .get(symbol)
+                .map(|p| p.current_value)
+                .unwrap_or(Decimal::ZERO);
+            (value / portfolio.total_value - target).abs() > self.drift_threshold
+        });
+        if !drifted {
+            return Ok(vec![]);
+        }
+        
+        Ok(rebalance_decisions(portfolio, &self.targets, self.cost_rate))
+    }
+    
+    fn name(&self) -> &str {
+        "target_weight"
+    }
+}
+
+/// Decisions that move `portfolio` toward `targets` (fractions of portfolio value).
+///
+/// Sells come first, then buys. Buys are scaled down and rounded toward zero so
+/// that their amounts plus fees fit in the cash left after the sells and their
+/// fees. Symbols without a target, defaulted symbols, and the cash symbol
+/// itself are left alone.
+pub fn rebalance_decisions(
+    portfolio: &Portfolio,
+    targets: &HashMap<String, Decimal>,
+    cost_rate: Decimal,
+) -> Vec<RoutingDecision> {
+    let total_value = portfolio.total_value;
+    if total_value <= Decimal::ZERO {
+        return vec![];
+    }
+    
+    let timestamp = portfolio.timestamp;
+    let decision = |source: &str, target: &str, amount: Decimal| RoutingDecision {
+        timestamp,
+        source_asset: source.to_string(),
+        target_asset: target.to_string(),
+        amount,
+        expected_yield: Decimal::ZERO,
+        risk_score: 0.0,
+        execution_cost: amount * cost_rate,
+        source_currency: None,
+    };
+    
+    // Sorted so the plan is deterministic
+    let mut symbols: Vec<&String> = targets
+        .keys()
+        .filter(|s| s.as_str() != CASH_SYMBOL && !portfolio.is_defaulted(s))
+        .collect();
+    symbols.sort();
+    
+    let mut sells = vec![];
+    let mut buys = vec![];
+    for symbol in symbols {
+        let target_value = total_value * targets[symbol].max(Decimal::ZERO);
+        let current_value = portfolio
+            .positions
+            .get(symbol)
+            .map(|p| p.current_value)
+            .unwrap_or(Decimal::ZERO);
+        
+        if current_value > target_value {
+            sells.push(decision(symbol, CASH_SYMBOL, current_value - target_value));
+        } else if target_value > current_value {
+            buys.push((symbol, target_value - current_value));
+        }
+    }
+    
+    let sell_proceeds: Decimal = sells.iter().map(|d| d.amount - d.execution_cost).sum();
+    let budget = portfolio.cash + sell_proceeds;
+    let wanted: Decimal = buys.iter().map(|(_, amount)| *amount * (Decimal::ONE + cost_rate)).sum();
+    let scale = if wanted <= budget {
+        Decimal::ONE
+    } else if budget > Decimal::ZERO {
+        (budget / wanted).round_dp_with_strategy(12, RoundingStrategy::ToZero)
+    } else {
+        Decimal::ZERO
+    };
+    
+    for (symbol, amount) in buys {
+        let amount = (amount * scale).round_dp_with_strategy(8, RoundingStrategy::ToZero);
+        if amount > Decimal::ZERO {
+            sells.push(decision(CASH_SYMBOL, symbol, amount));
+        }
+    }
+    sells
+}
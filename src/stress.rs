@@ -0,0 +1,517 @@
+//! Named, deterministic stress scenarios.
+//!
+//! A [`ScenarioDefinition`] is plain data: market conditions for the whole
+//! run plus a timeline of [`StressEvent`]s. Built-in definitions cover common
+//! historical episodes, and more can be loaded from TOML:
+//!
+//! ```toml
+//! [[scenarios]]
+//! name = "eth_flash_crash"
+//! description = "ETH gaps down 40% and half recovers"
+//! steps = 30
+//! vol_multiplier = 2.0
+//!
+//! [[scenarios.events]]
+//! kind = "gap"
+//! step = 5
+//! target = { symbol = "ETH" }
+//! pct_change = -40.0
+//!
+//! [[scenarios.events]]
+//! kind = "trend"
+//! step = 6
+//! steps = 10
+//! target = { symbol = "ETH" }
+//! pct_change = 30.0
+//! ```
+
+use crate::simulator::{Simulator, SimulatorConfig};
+use crate::types::{Asset, AssetType, MarketShock};
+use crate::yield_curve::flat_curve_for;
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Assets a stress event hits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StressTarget {
+    All,
+    Symbol(String),
+    /// Every asset of the type the simulator knows about when the event fires
+    AssetType(AssetType),
+}
+
+/// One entry in a stress scenario's timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StressEvent {
+    /// Instant price move of `pct_change` percent at `step`
+    Gap {
+        step: usize,
+        target: StressTarget,
+        pct_change: f64,
+    },
+    /// Price move of `pct_change` percent in total, compounded evenly over
+    /// `steps` steps starting at `step`
+    Trend {
+        step: usize,
+        steps: usize,
+        target: StressTarget,
+        pct_change: f64,
+    },
+    /// Income yields move by `bps` basis points at `step`; with a `duration`,
+    /// prices also fall by `duration × bps / 100` percent
+    YieldShift {
+        step: usize,
+        target: StressTarget,
+        bps: f64,
+        #[serde(default)]
+        duration: Option<f64>,
+    },
+    /// The yield curve moves `bps` basis points in parallel at `step`: yields
+    /// move with it, and assets with bond details are repriced by discounting
+    /// their cash flows on the shifted curve
+    CurveShift {
+        step: usize,
+        target: StressTarget,
+        bps: f64,
+    },
+}
+
+fn default_vol_multiplier() -> f64 {
+    1.0
+}
+
+/// A named stress scenario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Steps the scenario runs for
+    pub steps: usize,
+    /// Added to every asset's annual expected return
+    #[serde(default)]
+    pub drift_shift: f64,
+    /// Scales every asset's volatility; 0 removes the random walk entirely
+    #[serde(default = "default_vol_multiplier")]
+    pub vol_multiplier: f64,
+    /// Correlation between symbol pairs with no explicit correlation
+    #[serde(default)]
+    pub correlation: f64,
+    #[serde(default)]
+    pub events: Vec<StressEvent>,
+}
+
+impl ScenarioDefinition {
+    pub fn new(name: &str, description: &str, steps: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            steps,
+            drift_shift: 0.0,
+            vol_multiplier: 1.0,
+            correlation: 0.0,
+            events: vec![],
+        }
+    }
+
+    pub fn with_conditions(mut self, drift_shift: f64, vol_multiplier: f64, correlation: f64) -> Self {
+        self.drift_shift = drift_shift;
+        self.vol_multiplier = vol_multiplier;
+        self.correlation = correlation;
+        self
+    }
+
+    pub fn with_event(mut self, event: StressEvent) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    /// Crypto falls 75% over 180 steps in a high-volatility, high-correlation market
+    pub fn crypto_winter_2022() -> Self {
+        Self::new("crypto_winter_2022", "Crypto falls 75% over 180 steps with elevated volatility", 180)
+            .with_conditions(0.0, 1.5, 0.7)
+            .with_event(StressEvent::Trend {
+                step: 1,
+                steps: 180,
+                target: StressTarget::AssetType(AssetType::Crypto),
+                pct_change: -75.0,
+            })
+    }
+
+    /// Stablecoins gap to 0.88 and recover to par over 20 steps
+    pub fn usdc_depeg() -> Self {
+        Self::new("usdc_depeg", "Stablecoins gap to 0.88, then recover to par over 20 steps", 60)
+            .with_event(StressEvent::Gap {
+                step: 10,
+                target: StressTarget::AssetType(AssetType::Stablecoin),
+                pct_change: -12.0,
+            })
+            .with_event(StressEvent::Trend {
+                step: 11,
+                steps: 20,
+                target: StressTarget::AssetType(AssetType::Stablecoin),
+                pct_change: (1.0 / 0.88 - 1.0) * 100.0,
+            })
+    }
+
+    /// RWA bond yields jump 300bps, marking prices down at a duration of 5
+    pub fn rates_shock() -> Self {
+        Self::new("rates_shock", "RWA bond yields +300bps with a duration-5 price hit", 60)
+            .with_event(StressEvent::YieldShift {
+                step: 5,
+                target: StressTarget::AssetType(AssetType::RWABond),
+                bps: 300.0,
+                duration: Some(5.0),
+            })
+    }
+
+    /// A 2008-style crash: a 25% gap, then a further 30% slide as correlations go to one
+    pub fn gfc_2008() -> Self {
+        Self::new("gfc_2008", "25% market gap, then a 30% slide over 120 steps", 250)
+            .with_conditions(-0.3, 2.0, 0.8)
+            .with_event(StressEvent::Gap {
+                step: 20,
+                target: StressTarget::All,
+                pct_change: -25.0,
+            })
+            .with_event(StressEvent::Trend {
+                step: 21,
+                steps: 120,
+                target: StressTarget::All,
+                pct_change: -30.0,
+            })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.steps == 0 {
+            return Err(anyhow::anyhow!("Scenario {} must run at least one step", self.name));
+        }
+        if !self.vol_multiplier.is_finite() || self.vol_multiplier < 0.0 {
+            return Err(anyhow::anyhow!(
+                "Scenario {} volatility multiplier must be non-negative",
+                self.name
+            ));
+        }
+        if !(-1.0..1.0).contains(&self.correlation) {
+            return Err(anyhow::anyhow!(
+                "Scenario {} correlation must be in [-1, 1), got {}",
+                self.name,
+                self.correlation
+            ));
+        }
+        for event in &self.events {
+            let step = match event {
+                StressEvent::Gap { step, pct_change, .. } => {
+                    if *pct_change < -100.0 {
+                        return Err(anyhow::anyhow!("Scenario {} gap below -100%", self.name));
+                    }
+                    *step
+                }
+                StressEvent::Trend { step, steps, pct_change, .. } => {
+                    if *steps == 0 || *pct_change <= -100.0 {
+                        return Err(anyhow::anyhow!(
+                            "Scenario {} trend needs at least one step and a change above -100%",
+                            self.name
+                        ));
+                    }
+                    *step
+                }
+                StressEvent::YieldShift { step, .. } | StressEvent::CurveShift { step, .. } => *step,
+            };
+            if step == 0 {
+                return Err(anyhow::anyhow!("Scenario {} events must start at step 1 or later", self.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the scenario's market conditions on a simulator configuration
+    pub fn configure(&self, config: &mut SimulatorConfig) {
+        config.drift_adjustment = self.drift_shift;
+        config.volatility_multiplier *= self.vol_multiplier;
+        config.default_correlation = self.correlation;
+    }
+
+    /// Schedule this step's price moves and apply its yield shifts; call before stepping to `step`
+    pub fn apply_events(&self, simulator: &mut Simulator, step: usize) -> Result<()> {
+        for event in &self.events {
+            match event {
+                StressEvent::Gap { step: at, target, pct_change } if *at == step => {
+                    Self::shock(simulator, step, target, *pct_change)?;
+                }
+                StressEvent::Trend { step: start, steps, target, pct_change }
+                    if (*start..*start + *steps).contains(&step) =>
+                {
+                    let per_step = ((1.0 + pct_change / 100.0).powf(1.0 / *steps as f64) - 1.0) * 100.0;
+                    Self::shock(simulator, step, target, per_step)?;
+                }
+                StressEvent::YieldShift { step: at, target, bps, duration } if *at == step => {
+                    let delta = Decimal::try_from(bps / 10_000.0).unwrap_or(Decimal::ZERO);
+                    for symbol in Self::symbols(simulator, target) {
+                        simulator.shift_yield(&symbol, delta);
+                    }
+                    if let Some(duration) = duration {
+                        Self::shock(simulator, step, target, -(duration * bps / 100.0).min(100.0))?;
+                    }
+                }
+                StressEvent::CurveShift { step: at, target, bps } if *at == step => {
+                    let symbols = Self::symbols(simulator, target);
+                    for symbol in &symbols {
+                        for asset in Self::assets(simulator, symbol) {
+                            let Some(bond) = &asset.bond else { continue };
+                            let configured = simulator.config().yield_curve.clone();
+                            let curve = configured.unwrap_or_else(|| flat_curve_for(&asset));
+                            let pct_change = bond.pct_change(&curve, &curve.shifted(*bps));
+                            let pct_change = Decimal::try_from(pct_change).unwrap_or(Decimal::ZERO);
+                            simulator.schedule_shock(step, &asset.symbol, pct_change)?;
+                        }
+                    }
+                    let delta = Decimal::try_from(bps / 10_000.0).unwrap_or(Decimal::ZERO);
+                    for symbol in symbols {
+                        simulator.shift_yield(&symbol, delta);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn shock(simulator: &mut Simulator, step: usize, target: &StressTarget, pct_change: f64) -> Result<()> {
+        let pct_change = Decimal::try_from(pct_change).unwrap_or(Decimal::ZERO);
+        for symbol in Self::symbols(simulator, target) {
+            simulator.schedule_shock(step, &symbol, pct_change)?;
+        }
+        Ok(())
+    }
+
+    /// Assets a symbol from `symbols` stands for; every known asset for `MarketShock::ALL_SYMBOLS`
+    fn assets(simulator: &Simulator, symbol: &str) -> Vec<Asset> {
+        if symbol == MarketShock::ALL_SYMBOLS {
+            simulator.known_assets()
+        } else {
+            simulator.asset(symbol).into_iter().collect()
+        }
+    }
+
+    fn symbols(simulator: &Simulator, target: &StressTarget) -> Vec<String> {
+        match target {
+            StressTarget::All => vec![MarketShock::ALL_SYMBOLS.to_string()],
+            StressTarget::Symbol(symbol) => vec![symbol.clone()],
+            StressTarget::AssetType(asset_type) => simulator.symbols_of_type(asset_type),
+        }
+    }
+}
+
+/// A set of named stress scenarios
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StressLibrary {
+    #[serde(default)]
+    pub scenarios: Vec<ScenarioDefinition>,
+}
+
+impl StressLibrary {
+    /// The scenarios shipped with the simulator
+    pub fn builtin() -> Self {
+        Self {
+            scenarios: vec![
+                ScenarioDefinition::crypto_winter_2022(),
+                ScenarioDefinition::usdc_depeg(),
+                ScenarioDefinition::rates_shock(),
+                ScenarioDefinition::gfc_2008(),
+            ],
+        }
+    }
+
+    /// Parse scenarios from TOML: a `[[scenarios]]` array of definitions
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        let library: Self = config::Config::builder()
+            .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+            .build()?
+            .try_deserialize()
+            .context("Invalid stress scenario definitions")?;
+        for scenario in &library.scenarios {
+            scenario.validate()?;
+        }
+        Ok(library)
+    }
+
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read stress scenarios from {}", path.display()))?;
+        Self::from_toml_str(&toml).with_context(|| format!("In {}", path.display()))
+    }
+
+    /// Add `other`'s scenarios, replacing any with the same name
+    pub fn extend(&mut self, other: StressLibrary) {
+        for scenario in other.scenarios {
+            self.scenarios.retain(|existing| existing.name != scenario.name);
+            self.scenarios.push(scenario);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ScenarioDefinition> {
+        self.scenarios.iter().find(|scenario| scenario.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.scenarios.iter().map(|scenario| scenario.name.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monte_carlo::MonteCarloEngine;
+    use crate::types::{Portfolio, Position};
+    use rust_decimal::prelude::ToPrimitive;
+
+    /// The TOML example from the module docs
+    const FLASH_CRASH: &str = r#"
+        [[scenarios]]
+        name = "eth_flash_crash"
+        description = "ETH gaps down 40% and half recovers"
+        steps = 30
+        vol_multiplier = 2.0
+
+        [[scenarios.events]]
+        kind = "gap"
+        step = 5
+        target = { symbol = "ETH" }
+        pct_change = -40.0
+
+        [[scenarios.events]]
+        kind = "trend"
+        step = 6
+        steps = 10
+        target = { symbol = "ETH" }
+        pct_change = 30.0
+    "#;
+
+    /// A simulator holding one ETH at a price of 1 that only moves when shocked
+    fn holding_eth() -> Simulator {
+        let eth = Asset {
+            symbol: "ETH".to_string(),
+            name: "ETH".to_string(),
+            asset_type: AssetType::Crypto,
+            current_price: Decimal::ONE,
+            volatility: Decimal::ZERO,
+            yield_rate: Decimal::ZERO,
+            expected_return: Decimal::ZERO,
+            bond: None,
+            liquidity: None,
+        };
+        let mut portfolio = Portfolio::new(Decimal::ONE);
+        portfolio.add_position(Position::new(eth, Decimal::ONE, Decimal::ONE));
+        Simulator::from_parts(portfolio, crate::strategy::Strategy::conservative(), SimulatorConfig::default(), None)
+    }
+
+    #[test]
+    fn builtin_scenarios_are_valid() {
+        let library = StressLibrary::builtin();
+        assert_eq!(library.names(), vec!["crypto_winter_2022", "usdc_depeg", "rates_shock", "gfc_2008"]);
+        for scenario in &library.scenarios {
+            scenario.validate().unwrap();
+            assert!(!scenario.description.is_empty());
+            assert_eq!(library.get(&scenario.name), Some(scenario));
+        }
+        assert!(library.get("dotcom_2000").is_none());
+    }
+
+    #[test]
+    fn toml_scenarios_parse_into_definitions() {
+        let library = StressLibrary::from_toml_str(FLASH_CRASH).unwrap();
+        let expected = ScenarioDefinition::new("eth_flash_crash", "ETH gaps down 40% and half recovers", 30)
+            .with_conditions(0.0, 2.0, 0.0)
+            .with_event(StressEvent::Gap {
+                step: 5,
+                target: StressTarget::Symbol("ETH".to_string()),
+                pct_change: -40.0,
+            })
+            .with_event(StressEvent::Trend {
+                step: 6,
+                steps: 10,
+                target: StressTarget::Symbol("ETH".to_string()),
+                pct_change: 30.0,
+            });
+        assert_eq!(library.scenarios, vec![expected]);
+
+        // Loaded scenarios replace built-ins of the same name
+        let mut builtin = StressLibrary::builtin();
+        let renamed = FLASH_CRASH.replace("eth_flash_crash", "usdc_depeg");
+        builtin.extend(StressLibrary::from_toml_str(&renamed).unwrap());
+        assert_eq!(builtin.scenarios.len(), 4);
+        assert_eq!(builtin.get("usdc_depeg").unwrap().steps, 30);
+    }
+
+    #[test]
+    fn invalid_toml_scenarios_are_rejected() {
+        let cases = [
+            FLASH_CRASH.replace("pct_change = -40.0", "pct_change = -140.0"),
+            FLASH_CRASH.replace("step = 5", "step = 0"),
+            FLASH_CRASH.replace("steps = 30", "steps = 0"),
+            FLASH_CRASH.replace("steps = 10", "steps = 0"),
+            FLASH_CRASH.replace("kind = \"gap\"", "kind = \"teleport\""),
+        ];
+        for toml in cases {
+            assert!(StressLibrary::from_toml_str(&toml).is_err(), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn events_move_prices_on_their_steps() {
+        let scenario = StressLibrary::from_toml_str(FLASH_CRASH).unwrap().scenarios.remove(0);
+        let mut simulator = holding_eth();
+        let mut prices = vec![];
+        for step in 1..=scenario.steps {
+            scenario.apply_events(&mut simulator, step).unwrap();
+            simulator.step().unwrap();
+            prices.push(simulator.asset("ETH").unwrap().current_price.to_f64().unwrap());
+        }
+        assert!(prices[..4].iter().all(|price| *price == 1.0));
+        assert!((prices[4] - 0.6).abs() < 1e-9);
+        // 30% compounded over steps 6 to 15
+        let per_step = 1.3f64.powf(0.1);
+        for (k, price) in prices[5..15].iter().enumerate() {
+            assert!((price - 0.6 * per_step.powi(k as i32 + 1)).abs() < 1e-9, "step {}: {}", k + 6, price);
+        }
+        assert!(prices[15..].iter().all(|price| (price - 0.78).abs() < 1e-9));
+    }
+
+    #[test]
+    fn named_scenarios_run_deterministically() {
+        let mut library = StressLibrary::from_toml_str(FLASH_CRASH).unwrap();
+        library.extend(StressLibrary::builtin());
+        let engine = || {
+            let builder = MonteCarloEngine::builder().seed(3).capital(100_000.0);
+            builder.stress_scenarios(library.clone()).build().unwrap()
+        };
+        let names = ["eth_flash_crash", "usdc_depeg", "rates_shock"];
+        let first = engine().run_named_scenarios(&names).unwrap();
+        let second = engine().run_named_scenarios(&names).unwrap();
+
+        let ran: Vec<&str> = first.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(ran, names);
+        assert_eq!(first[0].1.initial_value, Decimal::from(100_000));
+        // Timestamps follow the wall clock; everything the prices drive repeats
+        for ((_, a), (_, b)) in first.iter().zip(&second) {
+            assert_eq!(a.final_value, b.final_value);
+            assert_eq!(a.max_drawdown_pct, b.max_drawdown_pct);
+            assert_eq!(a.volatility_pct, b.volatility_pct);
+            assert_eq!(a.total_fees, b.total_fees);
+        }
+    }
+
+    #[test]
+    fn unknown_scenario_names_list_the_available_ones() {
+        let engine = MonteCarloEngine::builder().build().unwrap();
+        let error = engine.run_named_scenarios(&["usdc_depeg", "dotcom_2000"]).unwrap_err().to_string();
+        assert!(error.contains("Unknown stress scenario dotcom_2000"), "{}", error);
+        assert!(error.contains("crypto_winter_2022, usdc_depeg, rates_shock, gfc_2008"), "{}", error);
+    }
+}
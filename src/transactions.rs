@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use time::OffsetDateTime;
+
+/// Direction of an executed decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// One event recorded by the simulator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum TransactionEntry {
+    /// A new price was applied to a symbol
+    PriceUpdate {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        old_price: Decimal,
+        new_price: Decimal,
+    },
+    /// A routing decision was executed
+    Decision {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        side: TradeSide,
+        amount: Decimal,
+    },
+    /// A routing decision was rejected and not executed
+    Rejected {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        side: TradeSide,
+        amount: Decimal,
+        reason: String,
+    },
+    /// The strategy ran and proposed `decisions` routing decisions
+    Rebalance {
+        step: usize,
+        timestamp: OffsetDateTime,
+        decisions: usize,
+    },
+    /// The circuit breaker tripped with the portfolio `drawdown_pct` below its peak
+    Halt {
+        step: usize,
+        timestamp: OffsetDateTime,
+        drawdown_pct: f64,
+    },
+    /// Execution cost paid for a decision
+    Fee {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        amount: Decimal,
+    },
+    /// Part or all of a position was force-sold to meet margin
+    Liquidation {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        quantity: Decimal,
+        price: Decimal,
+        proceeds: Decimal,
+    },
+    /// An asset's price collapsed and the position was written off
+    WriteOff {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        quantity: Decimal,
+        price: Decimal,
+        value: Decimal,
+    },
+    /// A scheduled shock was applied
+    Shock {
+        step: usize,
+        timestamp: OffsetDateTime,
+        symbol: String,
+        pct_change: Decimal,
+    },
+    /// A deposit (positive) or withdrawal (negative) of cash
+    CashFlow {
+        step: usize,
+        timestamp: OffsetDateTime,
+        /// Currency of the flow
+        symbol: String,
+        amount: Decimal,
+    },
+}
+
+impl TransactionEntry {
+    pub fn step(&self) -> usize {
+        match self {
+            Self::PriceUpdate { step, .. }
+            | Self::Decision { step, .. }
+            | Self::Rejected { step, .. }
+            | Self::Rebalance { step, .. }
+            | Self::Halt { step, .. }
+            | Self::Fee { step, .. }
+            | Self::Liquidation { step, .. }
+            | Self::WriteOff { step, .. }
+            | Self::Shock { step, .. }
+            | Self::CashFlow { step, .. } => *step,
+        }
+    }
+
+    pub fn timestamp(&self) -> OffsetDateTime {
+        match self {
+            Self::PriceUpdate { timestamp, .. }
+            | Self::Decision { timestamp, .. }
+            | Self::Rejected { timestamp, .. }
+            | Self::Rebalance { timestamp, .. }
+            | Self::Halt { timestamp, .. }
+            | Self::Fee { timestamp, .. }
+            | Self::Liquidation { timestamp, .. }
+            | Self::WriteOff { timestamp, .. }
+            | Self::Shock { timestamp, .. }
+            | Self::CashFlow { timestamp, .. } => *timestamp,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::PriceUpdate { symbol, .. }
+            | Self::Decision { symbol, .. }
+            | Self::Rejected { symbol, .. }
+            | Self::Fee { symbol, .. }
+            | Self::Liquidation { symbol, .. }
+            | Self::WriteOff { symbol, .. }
+            | Self::Shock { symbol, .. }
+            | Self::CashFlow { symbol, .. } => symbol,
+            Self::Rebalance { .. } | Self::Halt { .. } => "",
+        }
+    }
+
+    /// Short name of the event kind, as written to CSV
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::PriceUpdate { .. } => "price_update",
+            Self::Decision { side: TradeSide::Buy, .. } => "buy",
+            Self::Decision { side: TradeSide::Sell, .. } => "sell",
+            Self::Rejected { .. } => "rejected",
+            Self::Rebalance { .. } => "rebalance",
+            Self::Halt { .. } => "halt",
+            Self::Fee { .. } => "fee",
+            Self::Liquidation { .. } => "liquidation",
+            Self::WriteOff { .. } => "write_off",
+            Self::Shock { .. } => "shock",
+            Self::CashFlow { .. } => "cash_flow",
+        }
+    }
+
+    /// `(quantity, price, amount)` CSV columns; unused columns are empty
+    fn csv_amounts(&self) -> [Option<Decimal>; 3] {
+        match self {
+            Self::PriceUpdate { new_price, old_price, .. } => {
+                [None, Some(*new_price), Some(*new_price - *old_price)]
+            }
+            Self::Decision { amount, .. } | Self::Rejected { amount, .. } | Self::Fee { amount, .. } => {
+                [None, None, Some(*amount)]
+            }
+            Self::Rebalance { decisions, .. } => [None, None, Some(Decimal::from(*decisions))],
+            Self::Halt { drawdown_pct, .. } => [None, None, Decimal::try_from(*drawdown_pct).ok()],
+            Self::Liquidation { quantity, price, proceeds, .. } => [Some(*quantity), Some(*price), Some(*proceeds)],
+            Self::WriteOff { quantity, price, value, .. } => [Some(*quantity), Some(*price), Some(*value)],
+            Self::Shock { pct_change, .. } => [None, None, Some(*pct_change)],
+            Self::CashFlow { amount, .. } => [None, None, Some(*amount)],
+        }
+    }
+}
+
+/// Ordered record of everything the simulator did during a run.
+///
+/// Entries are appended as events happen, so they are ordered by step and,
+/// within a step, by the order the simulator processed them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionLog {
+    entries: Vec<TransactionEntry>,
+}
+
+impl TransactionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, entry: TransactionEntry) {
+        debug_assert!(
+            self.entries.last().is_none_or(|last| last.step() <= entry.step()),
+            "transaction log entries must be ordered by step"
+        );
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[TransactionEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Sum of all fees paid
+    pub fn total_fees(&self) -> Decimal {
+        self.entries
+            .iter()
+            .map(|entry| match entry {
+                TransactionEntry::Fee { amount, .. } => *amount,
+                _ => Decimal::ZERO,
+            })
+            .sum()
+    }
+
+    /// Write one row per entry: step, timestamp, kind, symbol, quantity, price, amount
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+
+        writer.write_record(["step", "timestamp", "kind", "symbol", "quantity", "price", "amount"])?;
+        for entry in &self.entries {
+            let timestamp = entry
+                .timestamp()
+                .format(&time::format_description::well_known::Rfc3339)?;
+            let [quantity, price, amount] = entry
+                .csv_amounts()
+                .map(|value| value.map(|v| v.to_string()).unwrap_or_default());
+            writer.write_record([
+                entry.step().to_string(),
+                timestamp,
+                entry.kind().to_string(),
+                entry.symbol().to_string(),
+                quantity,
+                price,
+                amount,
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write one JSON object per line, tagged with its `kind`, with keys in
+    /// alphabetical order and RFC 3339 timestamps
+    pub fn write_jsonl(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        for entry in &self.entries {
+            let mut object = serde_json::to_value(entry)?;
+            object["timestamp"] = entry
+                .timestamp()
+                .format(&time::format_description::well_known::Rfc3339)?
+                .into();
+            serde_json::to_writer(&mut writer, &object)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the entries as a JSON array
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        serde_json::to_writer_pretty(file, &self.entries)?;
+        Ok(())
+    }
+}
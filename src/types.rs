@@ -1,6 +1,13 @@
+use crate::backtest::{BacktestMetric, BacktestParameter};
+use crate::metrics::{DrawdownDurations, TDigest};
+use crate::monte_carlo::{SamplingMode, ScenarioSource, SweepParameter, VarReference, VarianceReduction};
+use crate::transactions::{TradeSide, TransactionLog};
+use crate::yield_curve::BondDetails;
+use anyhow::{Context, Result};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 use time::OffsetDateTime;
 
 /// Represents a financial asset in the simulation
@@ -11,10 +18,21 @@ pub struct Asset {
     pub asset_type: AssetType,
     pub current_price: Decimal,
     pub volatility: Decimal,
+    /// Annual income rate (e.g. a pool's APY), accrued to cash while held
     pub yield_rate: Decimal,
+    /// Annual expected price appreciation, used as the price walk's drift
+    #[serde(default)]
+    pub expected_return: Decimal,
+    /// Maturity and coupon of bond-like assets, for repricing under curve shifts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bond: Option<BondDetails>,
+    /// Average daily dollar volume, or the quote-side depth of the pool it
+    /// trades in; `None` is infinitely liquid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquidity: Option<Decimal>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AssetType {
     Crypto,
     DeFiPool,
@@ -66,9 +84,45 @@ impl Position {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Portfolio {
     pub positions: HashMap<String, Position>,
+    /// Cash in the reporting currency
     pub cash: Decimal,
+    /// Currency every value is reported in
+    #[serde(default = "default_reporting_currency")]
+    pub reporting_currency: String,
+    /// Cash held in other currencies, in each currency's own units
+    #[serde(default)]
+    pub foreign_cash: HashMap<String, Decimal>,
+    /// Reporting-currency value of one unit of each foreign currency
+    #[serde(default)]
+    pub fx_rates: HashMap<String, Decimal>,
+    /// Outstanding margin loan, netted out of `total_value`
+    #[serde(default)]
+    pub borrowed: Decimal,
     pub total_value: Decimal,
     pub timestamp: OffsetDateTime,
+    /// Assets written off after their price collapsed
+    #[serde(default)]
+    pub defaulted: Vec<DefaultedAsset>,
+    /// Most positions the simulator will let the book open; `None` is unlimited
+    #[serde(default)]
+    pub max_positions: Option<usize>,
+}
+
+/// Asset whose price collapsed to (near) zero, removed from active positions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefaultedAsset {
+    pub symbol: String,
+    /// Quantity held when written off; zero if the asset was not held
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+    pub last_price: Decimal,
+    /// Value lost, marked at the last price before the collapse was detected
+    pub written_off: Decimal,
+    pub step: usize,
+}
+
+fn default_reporting_currency() -> String {
+    CASH_SYMBOL.to_string()
 }
 
 impl Portfolio {
@@ -76,11 +130,25 @@ impl Portfolio {
         Self {
             positions: HashMap::new(),
             cash: initial_cash,
+            reporting_currency: default_reporting_currency(),
+            foreign_cash: HashMap::new(),
+            fx_rates: HashMap::new(),
+            borrowed: Decimal::ZERO,
             total_value: initial_cash,
             timestamp: OffsetDateTime::now_utc(),
+            defaulted: vec![],
+            max_positions: None,
         }
     }
 
+    /// A portfolio saved as JSON, such as a snapshot exported from production
+    pub fn read_json(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&contents).with_context(|| format!("{} is not a valid portfolio", path.display()))
+    }
+
     pub fn add_position(&mut self, position: Position) {
         let symbol = position.asset.symbol.clone();
         self.cash -= position.current_value;
@@ -98,13 +166,191 @@ impl Portfolio {
         }
     }
 
-    pub fn update_total_value(&mut self) {
-        let positions_value: Decimal = self
-            .positions
-            .values()
-            .map(|p| p.current_value)
+    /// Sell `quantity` of a position at its current price, returning the proceeds.
+    /// The position is removed once its quantity reaches zero.
+    pub fn reduce_position(&mut self, symbol: &str, quantity: Decimal) -> Option<Decimal> {
+        let position = self.positions.get_mut(symbol)?;
+        let quantity = quantity.min(position.quantity);
+        let proceeds = quantity * position.asset.current_price;
+        
+        position.quantity -= quantity;
+        position.current_value = position.quantity * position.asset.current_price;
+        if position.quantity <= Decimal::ZERO {
+            self.positions.remove(symbol);
+        }
+        
+        self.cash += proceeds;
+        self.update_total_value();
+        Some(proceeds)
+    }
+
+    /// Write a symbol down to zero and move it to the defaulted list
+    pub fn write_off(&mut self, symbol: &str, step: usize) -> DefaultedAsset {
+        let defaulted = match self.positions.remove(symbol) {
+            Some(position) => DefaultedAsset {
+                symbol: symbol.to_string(),
+                quantity: position.quantity,
+                entry_price: position.entry_price,
+                last_price: position.asset.current_price,
+                written_off: position.current_value,
+                step,
+            },
+            None => DefaultedAsset {
+                symbol: symbol.to_string(),
+                quantity: Decimal::ZERO,
+                entry_price: Decimal::ZERO,
+                last_price: Decimal::ZERO,
+                written_off: Decimal::ZERO,
+                step,
+            },
+        };
+        
+        self.defaulted.push(defaulted.clone());
+        self.update_total_value();
+        defaulted
+    }
+
+    pub fn is_defaulted(&self, symbol: &str) -> bool {
+        self.defaulted.iter().any(|d| d.symbol == symbol)
+    }
+
+    pub fn open_positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// How many more positions may be opened; `None` when unlimited
+    pub fn remaining_position_slots(&self) -> Option<usize> {
+        self.max_positions.map(|max| max.saturating_sub(self.open_positions()))
+    }
+
+    /// Credit `dt` years of each position's yield to cash, returning the income
+    pub fn accrue_yield(&mut self, dt: Decimal) -> Decimal {
+        let income: Decimal = self
+            .positions_in_symbol_order()
+            .map(|p| p.current_value * p.asset.yield_rate * dt)
             .sum();
-        self.total_value = self.cash + positions_value;
+        self.cash += income;
+        self.update_total_value();
+        income
+    }
+
+    /// Add `dt` years of each position's yield to the position itself, bought
+    /// at its current price, returning the income
+    pub fn reinvest_yield(&mut self, dt: Decimal) -> Decimal {
+        let mut income = Decimal::ZERO;
+        let mut symbols: Vec<String> = self.positions.keys().cloned().collect();
+        symbols.sort();
+        for symbol in symbols {
+            let Some(position) = self.positions.get_mut(&symbol) else { continue };
+            let earned = position.current_value * position.asset.yield_rate * dt;
+            if earned.is_zero() || position.asset.current_price <= Decimal::ZERO {
+                continue;
+            }
+            position.quantity += earned / position.asset.current_price;
+            position.current_value += earned;
+            income += earned;
+        }
+        self.update_total_value();
+        income
+    }
+
+    /// Sum of the positions' values, added in symbol order so the total comes
+    /// out the same to the last digit on every run
+    pub fn positions_value(&self) -> Decimal {
+        self.positions_in_symbol_order().map(|p| p.current_value).sum()
+    }
+
+    /// The positions sorted by symbol, for sums that must not depend on hash order
+    pub(crate) fn positions_in_symbol_order(&self) -> impl Iterator<Item = &Position> {
+        let mut positions: Vec<(&String, &Position)> = self.positions.iter().collect();
+        positions.sort_by(|a, b| a.0.cmp(b.0));
+        positions.into_iter().map(|(_, position)| position)
+    }
+
+    pub fn is_reporting_currency(&self, currency: &str) -> bool {
+        currency == self.reporting_currency
+    }
+
+    /// Cash held in `currency`, in that currency's units
+    pub fn cash_in(&self, currency: &str) -> Decimal {
+        if self.is_reporting_currency(currency) {
+            self.cash
+        } else {
+            self.foreign_cash.get(currency).copied().unwrap_or(Decimal::ZERO)
+        }
+    }
+
+    /// Add `amount` (negative to remove) to the `currency` balance
+    pub fn credit_cash(&mut self, currency: &str, amount: Decimal) {
+        if self.is_reporting_currency(currency) {
+            self.cash += amount;
+        } else {
+            *self.foreign_cash.entry(currency.to_string()).or_default() += amount;
+        }
+        self.update_total_value();
+    }
+
+    /// Reporting-currency value of one unit of `currency`; `None` until a rate is set
+    pub fn fx_rate(&self, currency: &str) -> Option<Decimal> {
+        if self.is_reporting_currency(currency) {
+            Some(Decimal::ONE)
+        } else {
+            self.fx_rates.get(currency).copied()
+        }
+    }
+
+    pub fn set_fx_rate(&mut self, currency: &str, rate: Decimal) {
+        if !self.is_reporting_currency(currency) {
+            self.fx_rates.insert(currency.to_string(), rate);
+        }
+    }
+
+    /// Every cash balance in its own units, reporting currency included
+    pub fn cash_balances(&self) -> HashMap<String, Decimal> {
+        let mut balances = self.foreign_cash.clone();
+        balances.insert(self.reporting_currency.clone(), self.cash);
+        balances
+    }
+
+    /// All cash converted into the reporting currency.
+    ///
+    /// Balances in a currency with no known rate are carried at par.
+    pub fn cash_value(&self) -> Decimal {
+        self.cash
+            + self
+                .foreign_cash
+                .iter()
+                .map(|(currency, balance)| *balance * self.fx_rate(currency).unwrap_or(Decimal::ONE))
+                .sum::<Decimal>()
+    }
+
+    /// Exchange enough `currency` into reporting cash to raise `value` (in the reporting currency)
+    pub fn convert_to_reporting(&mut self, currency: &str, value: Decimal) -> Result<()> {
+        if self.is_reporting_currency(currency) {
+            return Ok(());
+        }
+        let rate = self
+            .fx_rate(currency)
+            .filter(|rate| *rate > Decimal::ZERO)
+            .with_context(|| format!("No FX rate for {}/{}", currency, self.reporting_currency))?;
+        let needed = value / rate;
+        let available = self.cash_in(currency);
+        if needed > available {
+            return Err(anyhow::anyhow!(
+                "Insufficient {} cash: need {}, have {}",
+                currency,
+                needed,
+                available
+            ));
+        }
+        self.credit_cash(currency, -needed);
+        self.cash += value;
+        self.update_total_value();
+        Ok(())
+    }
+
+    pub fn update_total_value(&mut self) {
+        self.total_value = self.cash_value() + self.positions_value() - self.borrowed;
     }
 
     pub fn update_prices(&mut self, price_updates: &HashMap<String, Decimal>) {
@@ -121,37 +367,987 @@ impl Portfolio {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationResults {
     pub initial_value: Decimal,
+    /// Final value after terminal valuation (equal to `marked_final_value` when marking to market)
     pub final_value: Decimal,
+    pub marked_final_value: Decimal,
+    pub terminal_liquidation_cost: Decimal,
+    /// Gain over the run, excluding deposits and withdrawals
     pub total_return: Decimal,
+    /// Time-weighted return in percent; the plain final/initial return when no cash flowed
     pub total_return_pct: f64,
     pub sharpe_ratio: f64,
+    /// Annualized Sortino ratio over `SimulatorConfig::minimum_acceptable_return`;
+    /// `None` when no step fell short of it
+    #[serde(default)]
+    pub sortino_ratio: Option<f64>,
+    /// Annualized time-weighted return over max drawdown; `None` without a drawdown
+    #[serde(default)]
+    pub calmar_ratio: Option<f64>,
     pub max_drawdown_pct: f64,
+    /// How long the portfolio spent below its peak value
+    #[serde(default)]
+    pub drawdown_durations: DrawdownDurations,
     pub volatility_pct: f64,
     pub value_at_risk: Decimal,
     pub conditional_var: Decimal,
+    pub peak_leverage: f64,
+    pub margin_calls: usize,
+    /// Deposits (positive) and withdrawals (negative) applied during the run
+    #[serde(default)]
+    pub cash_flows: Vec<CashFlow>,
+    #[serde(default)]
+    pub net_cash_flow: Decimal,
+    /// Annualized money-weighted return (IRR) in percent; `None` if it has no solution
+    #[serde(default)]
+    pub money_weighted_return_pct: Option<f64>,
+    /// Value of the buy-and-hold benchmark at the end of the run
+    #[serde(default)]
+    pub benchmark_final_value: Option<Decimal>,
+    /// Strategy total return minus benchmark total return, in percentage points
+    #[serde(default)]
+    pub excess_return_pct: Option<f64>,
+    /// Annualized standard deviation of per-step active returns, in percent
+    #[serde(default)]
+    pub tracking_error: Option<f64>,
+    #[serde(default)]
+    pub information_ratio: Option<f64>,
+    /// Step at which the drawdown circuit breaker tripped
+    #[serde(default)]
+    pub halted_at_step: Option<usize>,
+    /// Execution costs paid on executed decisions
+    #[serde(default)]
+    pub total_fees: Decimal,
+    /// Yield income the portfolio's positions earned, credited or reinvested
+    #[serde(default)]
+    pub income_earned: Decimal,
+    /// Number of assets written off after their price collapsed
+    #[serde(default)]
+    pub defaulted_assets: usize,
+    /// Price jumps during the run: one per market-wide jump plus one per asset-level jump
+    #[serde(default)]
+    pub jumps: usize,
+    /// Steps spent in each regime of the configured regime model
+    #[serde(default)]
+    pub regime_occupancy: Vec<usize>,
     pub portfolio_history: Vec<PortfolioSnapshot>,
+    pub decisions: Vec<ExecutedDecision>,
+    pub trades: Vec<Trade>,
+    /// Every quantity bought or sold, in execution order
+    #[serde(default)]
+    pub fills: Vec<Fill>,
+    pub shocks: Vec<MarketShock>,
+    /// Rolling-window statistics per step, when a rolling window is configured
+    #[serde(default)]
+    pub rolling_metrics: Vec<RollingMetrics>,
+    /// Price path of every symbol seen, one entry per step from `price_history_start`;
+    /// recorded only when `SimulatorConfig::record_price_history` is set
+    #[serde(default)]
+    pub price_history: HashMap<String, Vec<Decimal>>,
+    /// Step at which each symbol's price history begins
+    #[serde(default)]
+    pub price_history_start: HashMap<String, usize>,
+    /// Full event log, present when the simulator was configured to record it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<TransactionLog>,
+}
+
+impl SimulationResults {
+    pub fn transaction_log(&self) -> Option<&TransactionLog> {
+        self.transactions.as_ref()
+    }
+
+    /// Kept snapshot for `step`, if the history policy retained it
+    pub fn snapshot_at(&self, step: usize) -> Option<&PortfolioSnapshot> {
+        self.portfolio_history.iter().find(|s| s.step == step)
+    }
+
+    /// Position detail for `symbol` at `step`; requires position recording
+    pub fn position_at(&self, step: usize, symbol: &str) -> Option<&PositionSnapshot> {
+        self.snapshot_at(step)?.positions.as_ref()?.get(symbol)
+    }
+
+    /// Weight of `symbol` at `step` (zero if not held); requires position recording
+    pub fn weight_at(&self, step: usize, symbol: &str) -> Option<Decimal> {
+        let positions = self.snapshot_at(step)?.positions.as_ref()?;
+        Some(positions.get(symbol).map(|p| p.weight).unwrap_or(Decimal::ZERO))
+    }
+
+    /// Price of `symbol` at `step`; requires price history recording
+    pub fn price_at(&self, step: usize, symbol: &str) -> Option<Decimal> {
+        let start = *self.price_history_start.get(symbol)?;
+        self.price_history.get(symbol)?.get(step.checked_sub(start)?).copied()
+    }
+
+    /// Write the portfolio history as CSV, with one weight column per symbol
+    /// ever held when position detail was recorded, and one price column per
+    /// symbol when price history was recorded (empty before the symbol appeared)
+    pub fn write_history_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        
+        let symbols: BTreeSet<&String> = self
+            .portfolio_history
+            .iter()
+            .filter_map(|s| s.positions.as_ref())
+            .flat_map(|positions| positions.keys())
+            .collect();
+        
+        let mut header: Vec<String> = [
+            "step",
+            "timestamp",
+            "total_value",
+            "cash",
+            "positions_value",
+            "positions_count",
+        ]
+        .iter()
+        .map(|h| h.to_string())
+        .collect();
+        header.extend(symbols.iter().map(|symbol| format!("weight_{}", symbol)));
+        let priced: BTreeSet<&String> = self.price_history.keys().collect();
+        header.extend(priced.iter().map(|symbol| format!("price_{}", symbol)));
+        writer.write_record(&header)?;
+        
+        for snapshot in &self.portfolio_history {
+            let mut row = vec![
+                snapshot.step.to_string(),
+                snapshot.timestamp.format(&time::format_description::well_known::Rfc3339)?,
+                snapshot.total_value.to_string(),
+                snapshot.cash.to_string(),
+                snapshot.positions_value.to_string(),
+                snapshot.positions_count.to_string(),
+            ];
+            for symbol in &symbols {
+                let weight = snapshot
+                    .positions
+                    .as_ref()
+                    .map(|positions| positions.get(*symbol).map(|p| p.weight).unwrap_or(Decimal::ZERO));
+                row.push(weight.map(|w| w.to_string()).unwrap_or_default());
+            }
+            for symbol in &priced {
+                let price = self.price_at(snapshot.step, symbol);
+                row.push(price.map(|p| p.to_string()).unwrap_or_default());
+            }
+            writer.write_record(&row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortfolioSnapshot {
+    #[serde(default)]
+    pub step: usize,
     pub timestamp: OffsetDateTime,
     pub total_value: Decimal,
     pub cash: Decimal,
     pub positions_value: Decimal,
     pub positions_count: usize,
+    /// Cash per currency in its own units; empty when only the reporting currency is held
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub cash_balances: HashMap<String, Decimal>,
+    /// Per-position detail, recorded only when `SimulatorConfig::record_positions` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positions: Option<HashMap<String, PositionSnapshot>>,
+}
+
+/// Rolling-window statistics at one step; window values are `None` until the window fills
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingMetrics {
+    pub step: usize,
+    pub rolling_return: Option<Decimal>,
+    pub rolling_vol: Option<f64>,
+    pub rolling_sharpe: Option<f64>,
+    /// Drawdown from the running peak, in percent
+    pub current_drawdown: f64,
+}
+
+/// One position as of a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionSnapshot {
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub value: Decimal,
+    /// Share of total portfolio value
+    pub weight: Decimal,
 }
 
 /// Monte Carlo simulation results
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonteCarloResults {
+    /// Paths completed; fewer than requested if the run was cancelled
     pub iterations: usize,
+    /// The run was cancelled before every requested path finished
+    #[serde(default)]
+    pub incomplete: bool,
     pub expected_value: Decimal,
+    /// Median final value
+    #[serde(default)]
+    pub median_value: Decimal,
+    /// Sample skewness of final values; negative for a long left tail
+    #[serde(default)]
+    pub skewness: f64,
+    /// Sample excess kurtosis of final values; zero for a normal distribution
+    #[serde(default)]
+    pub excess_kurtosis: f64,
+    /// 95th percentile gain over 5th percentile loss, both relative to
+    /// initial capital; `None` when the 5th percentile isn't a loss
+    #[serde(default)]
+    pub tail_ratio: Option<f64>,
+    /// Standard error of `expected_value`
+    #[serde(default)]
+    pub standard_error: f64,
+    /// Whether paths came from the parametric walk, a historical bootstrap or the caller
+    #[serde(default)]
+    pub scenario_source: ScenarioSource,
+    #[serde(default)]
+    pub variance_reduction: VarianceReduction,
+    #[serde(default)]
+    pub sampling: SamplingMode,
+    /// Loss at the `confidence_level` quantile of final values, measured from
+    /// `var_reference`; never negative
     pub value_at_risk: Decimal,
+    /// Mean loss over the paths at or beyond the VaR quantile (at least the
+    /// single worst path), measured from `var_reference`; never negative
     pub conditional_var: Decimal,
+    /// `value_at_risk` in percent of the reference level
+    #[serde(default)]
+    pub var_pct: f64,
+    /// `conditional_var` in percent of the reference level
+    #[serde(default)]
+    pub cvar_pct: f64,
+    #[serde(default)]
+    pub var_reference: VarReference,
     pub max_drawdown_pct: f64,
     pub confidence_level: f64,
-    pub distribution: Vec<f64>,
+    /// Every path's final value in iteration order; `None` for a memory-light run
+    #[serde(default)]
+    pub distribution: Option<Vec<f64>>,
+    /// Sketch of the final values of paths that didn't fail, kept by
+    /// memory-light runs in place of `distribution`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sketch: Option<TDigest>,
     pub percentiles: HashMap<u8, Decimal>,
+    /// Paths on which the drawdown circuit breaker tripped
+    #[serde(default)]
+    pub halted_paths: usize,
+    #[serde(default)]
+    pub halt_probability: f64,
+    /// Master seed the per-path seeds were derived from; rerunning with it reproduces the run
+    #[serde(default)]
+    pub seed: u64,
+    /// Strategy every path ran
+    #[serde(default)]
+    pub strategy: String,
+    #[serde(default)]
+    pub initial_capital: f64,
+    #[serde(default)]
+    pub steps_per_iteration: usize,
+    /// Simulated seconds per step
+    #[serde(default)]
+    pub time_step_secs: i64,
+    /// Number of distinct scenarios the paths were drawn from (0 when none were generated)
+    #[serde(default)]
+    pub scenarios_generated: usize,
+    /// Outcomes conditional on each scenario class, e.g. `high_vol` or `shocked`
+    #[serde(default)]
+    pub scenario_breakdown: HashMap<String, ScenarioClassStats>,
+    /// Distribution of each path's own peak-to-trough drawdown
+    #[serde(default)]
+    pub drawdown: DrawdownDistribution,
+    /// Distribution of each path's time underwater and recovery from its deepest drawdown
+    #[serde(default)]
+    pub drawdown_durations: DrawdownDurationStats,
+    /// Share of paths ending below the initial capital
+    #[serde(default)]
+    pub prob_of_loss: f64,
+    /// Share of paths whose value fell below `ruin_threshold_pct` of the initial
+    /// capital at any step
+    #[serde(default)]
+    pub prob_of_ruin: f64,
+    #[serde(default)]
+    pub ruin_threshold_pct: f64,
+    /// Share of paths with at least one price jump
+    #[serde(default)]
+    pub jump_probability: f64,
+    /// Mean loss, in percent of initial capital, over paths with at least one jump
+    /// (negative when those paths gained); `None` if no path jumped
+    #[serde(default)]
+    pub loss_given_jump_pct: Option<f64>,
+    /// Statistics at each requested horizon checkpoint, ascending
+    #[serde(default)]
+    pub horizons: Vec<HorizonStats>,
+    /// Goal attainment, when a target or floor was set
+    #[serde(default)]
+    pub goal: Option<GoalStats>,
+    /// Per-path statistics, in iteration order; every aggregate above is computed from these
+    #[serde(default)]
+    pub paths: Vec<PathSummary>,
+    /// Full results of each path, parallel to `paths` (`None` where the path
+    /// failed); only retained when the engine runs in detailed mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_results: Vec<Option<SimulationResults>>,
+    /// Occupancy of each regime, when a regime model is configured
+    #[serde(default)]
+    pub regimes: Vec<RegimeStats>,
+    /// Outcomes grouped by the share of steps each path spent in the crisis regime
+    #[serde(default)]
+    pub crisis_exposure: Vec<CrisisExposureStats>,
+}
+
+/// Monte Carlo results for several strategies run on the same seeded paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonResults {
+    /// One run per strategy, in the order given
+    pub results: Vec<MonteCarloResults>,
+    /// Every ordered pair of distinct strategies
+    pub pairwise: Vec<PairwiseComparison>,
+}
+
+/// Head-to-head statistics of strategy `a` against strategy `b` over shared paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairwiseComparison {
+    pub a: String,
+    pub b: String,
+    /// Paths both runs completed
+    pub paths: usize,
+    /// Share of shared paths on which `a` ended strictly above `b`
+    pub prob_a_beats_b: f64,
+    /// Mean of `a`'s final value minus `b`'s, path by path
+    pub mean_difference: f64,
+    /// `a`'s CVaR minus `b`'s; negative when `a` has the lighter tail
+    pub cvar_difference: Decimal,
+}
+
+impl ComparisonResults {
+    /// Runs ordered by expected value, best first
+    pub fn ranked(&self) -> Vec<&MonteCarloResults> {
+        let mut ranked: Vec<&MonteCarloResults> = self.results.iter().collect();
+        ranked.sort_by_key(|results| std::cmp::Reverse(results.expected_value));
+        ranked
+    }
+
+    pub fn pairwise(&self, a: &str, b: &str) -> Option<&PairwiseComparison> {
+        self.pairwise.iter().find(|pair| pair.a == a && pair.b == b)
+    }
+}
+
+/// Headline figures of a Monte Carlo run, without the per-path detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloSummary {
+    pub iterations: usize,
+    pub expected_value: Decimal,
+    pub median_value: Decimal,
+    pub standard_error: f64,
+    pub value_at_risk: Decimal,
+    pub conditional_var: Decimal,
+    pub var_pct: f64,
+    pub cvar_pct: f64,
+    pub mean_drawdown_pct: f64,
+    pub prob_of_loss: f64,
+    pub prob_of_ruin: f64,
+    pub halt_probability: f64,
+}
+
+impl From<&MonteCarloResults> for MonteCarloSummary {
+    fn from(results: &MonteCarloResults) -> Self {
+        Self {
+            iterations: results.iterations,
+            expected_value: results.expected_value,
+            median_value: results.median_value,
+            standard_error: results.standard_error,
+            value_at_risk: results.value_at_risk,
+            conditional_var: results.conditional_var,
+            var_pct: results.var_pct,
+            cvar_pct: results.cvar_pct,
+            mean_drawdown_pct: results.drawdown.mean_pct,
+            prob_of_loss: results.prob_of_loss,
+            prob_of_ruin: results.prob_of_ruin,
+            halt_probability: results.halt_probability,
+        }
+    }
+}
+
+/// Monte Carlo summaries at each value of a swept parameter, on shared seeded paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensitivityResults {
+    pub parameter: SweepParameter,
+    /// Parameter value and the run's summary, in sweep order
+    pub points: Vec<(f64, MonteCarloSummary)>,
+}
+
+impl SensitivityResults {
+    /// Write one row per swept value, headed by the parameter's name
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record([
+            self.parameter.name(),
+            "iterations",
+            "expected_value",
+            "median_value",
+            "standard_error",
+            "value_at_risk",
+            "conditional_var",
+            "var_pct",
+            "cvar_pct",
+            "mean_drawdown_pct",
+            "prob_of_loss",
+            "prob_of_ruin",
+            "halt_probability",
+        ])?;
+        for (value, summary) in &self.points {
+            writer.write_record([
+                value.to_string(),
+                summary.iterations.to_string(),
+                summary.expected_value.to_string(),
+                summary.median_value.to_string(),
+                summary.standard_error.to_string(),
+                summary.value_at_risk.to_string(),
+                summary.conditional_var.to_string(),
+                summary.var_pct.to_string(),
+                summary.cvar_pct.to_string(),
+                summary.mean_drawdown_pct.to_string(),
+                summary.prob_of_loss.to_string(),
+                summary.prob_of_ruin.to_string(),
+                summary.halt_probability.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Monte Carlo outcomes at one horizon checkpoint, measured like the end-of-run figures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HorizonStats {
+    pub step: usize,
+    pub expected_value: Decimal,
+    pub value_at_risk: Decimal,
+    pub conditional_var: Decimal,
+    pub var_pct: f64,
+    pub cvar_pct: f64,
+    /// Mean of each path's maximum drawdown up to the checkpoint
+    pub mean_drawdown_pct: f64,
+    pub prob_of_loss: f64,
+}
+
+/// Monte Carlo goal attainment: reaching a target value, and staying above a floor
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GoalStats {
+    pub target_value: Option<f64>,
+    pub floor_value: Option<f64>,
+    /// Share of paths whose value reached the target at some step
+    pub prob_hit_target: Option<f64>,
+    /// Steps to first reach the target, over the paths that did
+    pub time_to_target: Option<FirstPassageStats>,
+    /// Share of paths whose value fell below the floor at any step
+    pub prob_breach_floor: Option<f64>,
+}
+
+/// Distribution of first-passage times, in steps
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FirstPassageStats {
+    pub mean_steps: f64,
+    pub median_steps: f64,
+    pub p90_steps: f64,
+    pub histogram: Histogram,
+}
+
+/// How much time Monte Carlo paths spent in one market regime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeStats {
+    pub name: String,
+    /// Mean share of steps spent in the regime across paths
+    pub mean_occupancy: f64,
+    /// Long-run share implied by the transition matrix
+    pub stationary_probability: f64,
+}
+
+/// Monte Carlo outcomes over paths whose crisis-regime share of steps fell in `[min_share, max_share)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrisisExposureStats {
+    pub min_share: f64,
+    pub max_share: f64,
+    pub paths: usize,
+    pub mean_return_pct: f64,
+    pub worst_value: Decimal,
+}
+
+/// Spread of per-path maximum drawdowns, in percent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DrawdownDistribution {
+    pub mean_pct: f64,
+    pub median_pct: f64,
+    pub p95_pct: f64,
+    pub worst_pct: f64,
+    /// Threshold the exceedance probability was measured against, if one was set
+    pub threshold_pct: Option<f64>,
+    /// Share of paths whose drawdown exceeded `threshold_pct`
+    pub exceed_probability: Option<f64>,
+}
+
+/// Spread of how long paths spent in drawdown, over the paths that didn't fail
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DrawdownDurationStats {
+    /// Longest drawdown of each path, in steps
+    pub mean_longest_steps: f64,
+    pub median_longest_steps: f64,
+    pub p95_longest_steps: f64,
+    pub worst_longest_steps: usize,
+    /// Mean over paths of their mean drawdown episode length, in steps
+    pub mean_episode_steps: f64,
+    /// Mean share of steps a path spent below its peak, in percent
+    pub mean_underwater_pct: f64,
+    /// Steps from each path's deepest trough back to its peak, over the paths that recovered
+    pub mean_recovery_steps: Option<f64>,
+    pub median_recovery_steps: Option<f64>,
+    pub p95_recovery_steps: Option<f64>,
+    /// Share of paths still below the peak preceding their deepest trough at the end
+    pub unrecovered_probability: f64,
+}
+
+/// An externally supplied market path: a price series per symbol, one price per step
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PricePath {
+    #[serde(default)]
+    pub name: String,
+    pub prices: HashMap<String, Vec<Decimal>>,
+}
+
+impl PricePath {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            prices: HashMap::new(),
+        }
+    }
+
+    pub fn with_series(mut self, symbol: &str, prices: Vec<Decimal>) -> Self {
+        self.prices.insert(symbol.to_string(), prices);
+        self
+    }
+
+    /// Steps the path runs for: the length of its longest series
+    pub fn steps(&self) -> usize {
+        self.prices.values().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Prices at `step` of every series still running; ended series are left out
+    pub fn prices_at(&self, step: usize) -> HashMap<String, Decimal> {
+        self.prices
+            .iter()
+            .filter_map(|(symbol, series)| series.get(step).map(|price| (symbol.clone(), *price)))
+            .collect()
+    }
+}
+
+/// Compact outcome of one Monte Carlo path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathSummary {
+    /// Path number within the run
+    pub iteration: usize,
+    pub final_value: f64,
+    /// Lowest value at any step, including the start and the terminal valuation
+    #[serde(default)]
+    pub min_value: f64,
+    pub max_drawdown_pct: f64,
+    #[serde(default)]
+    pub drawdown_durations: DrawdownDurations,
+    /// Annualized realized volatility in percent
+    pub volatility_pct: f64,
+    #[serde(default)]
+    pub sharpe_ratio: f64,
+    /// Execution costs paid over the path
+    #[serde(default)]
+    pub fees: f64,
+    /// Whether the drawdown circuit breaker tripped
+    #[serde(default)]
+    pub halted: bool,
+    #[serde(default)]
+    pub jumps: usize,
+    /// Steps spent in each regime, when a regime model is configured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regime_occupancy: Vec<usize>,
+    /// Index of the generated scenario the path ran under
+    pub scenario: Option<usize>,
+    /// Step at which the value first reached the goal target (0 if it started there)
+    #[serde(default)]
+    pub target_hit_step: Option<usize>,
+    /// Value at each of the run's horizon checkpoints
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub horizon_values: Vec<f64>,
+    /// Maximum drawdown up to each horizon checkpoint, in percent of marked values
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub horizon_drawdowns_pct: Vec<f64>,
+    /// Whether the path's simulation errored; it then counts as a total loss
+    #[serde(default)]
+    pub failed: bool,
+}
+
+impl PathSummary {
+    /// A path whose simulation errored
+    pub fn failed(iteration: usize) -> Self {
+        Self {
+            iteration,
+            max_drawdown_pct: 100.0,
+            failed: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Mean outcomes over a subset of Monte Carlo paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionalSummary {
+    pub paths: usize,
+    /// Share of all paths in the subset
+    pub probability: f64,
+    pub mean_final_value: f64,
+    pub mean_return_pct: f64,
+    pub mean_max_drawdown_pct: f64,
+    pub mean_volatility_pct: f64,
+    pub mean_sharpe_ratio: f64,
+    pub mean_fees: f64,
+    /// Share of the subset on which the circuit breaker tripped
+    pub halt_probability: f64,
+}
+
+/// Most bins automatic binning will produce
+const MAX_HISTOGRAM_BINS: usize = 200;
+
+impl MonteCarloResults {
+    /// Write one row per iteration: final value, max drawdown, realized
+    /// volatility, Sharpe, fees, halt flag and scenario. Rows are written as they are formatted, so the
+    /// file is never held in memory. Results without per-path statistics
+    /// (e.g. deserialized from an older run) leave those columns empty.
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record([
+            "iteration",
+            "final_value",
+            "max_drawdown_pct",
+            "volatility_pct",
+            "sharpe_ratio",
+            "fees",
+            "halted",
+            "scenario",
+        ])?;
+        
+        if self.paths.is_empty() {
+            let Some(distribution) = &self.distribution else {
+                return Err(anyhow::anyhow!("Memory-light results carry no per-path values to write"));
+            };
+            for (iteration, final_value) in distribution.iter().enumerate() {
+                let mut record = vec![iteration.to_string(), final_value.to_string()];
+                record.resize(8, String::new());
+                writer.write_record(&record)?;
+            }
+        } else {
+            for summary in &self.paths {
+                writer.write_record([
+                    summary.iteration.to_string(),
+                    summary.final_value.to_string(),
+                    summary.max_drawdown_pct.to_string(),
+                    summary.volatility_pct.to_string(),
+                    summary.sharpe_ratio.to_string(),
+                    summary.fees.to_string(),
+                    summary.halted.to_string(),
+                    summary.scenario.map(|s| s.to_string()).unwrap_or_default(),
+                ])?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the reported percentiles of the final value as `percentile,value` rows
+    pub fn write_percentiles_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record(["percentile", "final_value"])?;
+        let mut percentiles: Vec<(&u8, &Decimal)> = self.percentiles.iter().collect();
+        percentiles.sort();
+        for (percentile, value) in percentiles {
+            writer.write_record([percentile.to_string(), value.to_string()])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the per-path statistics as Parquet, in row groups of 64k paths
+    #[cfg(feature = "parquet")]
+    pub fn write_parquet(&self, path: impl AsRef<Path>) -> Result<()> {
+        use arrow::array::{ArrayRef, BooleanArray, Float64Array, UInt64Array};
+        use arrow::datatypes::{DataType, Field, Schema};
+        use arrow::record_batch::RecordBatch;
+        use parquet::arrow::ArrowWriter;
+        use std::sync::Arc;
+        
+        const BATCH_ROWS: usize = 65_536;
+        
+        let path = path.as_ref();
+        if self.paths.is_empty() && self.iterations > 0 {
+            return Err(anyhow::anyhow!("Results carry no per-path statistics to write"));
+        }
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("iteration", DataType::UInt64, false),
+            Field::new("final_value", DataType::Float64, false),
+            Field::new("max_drawdown_pct", DataType::Float64, false),
+            Field::new("volatility_pct", DataType::Float64, false),
+            Field::new("sharpe_ratio", DataType::Float64, false),
+            Field::new("fees", DataType::Float64, false),
+            Field::new("halted", DataType::Boolean, false),
+            Field::new("scenario", DataType::UInt64, true),
+        ]));
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), None)?;
+        for chunk in self.paths.chunks(BATCH_ROWS) {
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(UInt64Array::from_iter_values(chunk.iter().map(|p| p.iteration as u64))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.final_value))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.max_drawdown_pct))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.volatility_pct))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.sharpe_ratio))),
+                Arc::new(Float64Array::from_iter_values(chunk.iter().map(|p| p.fees))),
+                Arc::new(BooleanArray::from(chunk.iter().map(|p| p.halted).collect::<Vec<_>>())),
+                Arc::new(UInt64Array::from(
+                    chunk.iter().map(|p| p.scenario.map(|s| s as u64)).collect::<Vec<_>>(),
+                )),
+            ];
+            writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+        }
+        writer.close()?;
+        Ok(())
+    }
+
+    /// The `n` paths with the lowest final values, worst first
+    pub fn worst_paths(&self, n: usize) -> Vec<&PathSummary> {
+        let mut paths: Vec<&PathSummary> = self.paths.iter().collect();
+        paths.sort_by(|a, b| a.final_value.total_cmp(&b.final_value));
+        paths.truncate(n);
+        paths
+    }
+
+    /// Mean outcomes over the paths matching `predicate`, e.g.
+    /// `|p| p.max_drawdown_pct > 20.0`; `None` if no path matches
+    pub fn summary_conditional_on<F>(&self, predicate: F) -> Option<ConditionalSummary>
+    where
+        F: Fn(&PathSummary) -> bool,
+    {
+        let matching: Vec<&PathSummary> = self.paths.iter().filter(|path| predicate(path)).collect();
+        self.summarize(&matching)
+    }
+
+    /// Mean outcomes over the worst `fraction` of paths by final value (at least one path)
+    pub fn tail_summary(&self, fraction: f64) -> Option<ConditionalSummary> {
+        let n = ((fraction.clamp(0.0, 1.0) * self.paths.len() as f64).ceil() as usize).max(1);
+        self.summarize(&self.worst_paths(n))
+    }
+
+    /// Full results of path `iteration`, when the run kept them
+    pub fn path_result(&self, iteration: usize) -> Option<&SimulationResults> {
+        let position = self.paths.binary_search_by_key(&iteration, |path| path.iteration).ok()?;
+        self.path_results.get(position)?.as_ref()
+    }
+
+    fn summarize(&self, paths: &[&PathSummary]) -> Option<ConditionalSummary> {
+        if paths.is_empty() {
+            return None;
+        }
+        let n = paths.len() as f64;
+        let mean = |f: fn(&PathSummary) -> f64| paths.iter().map(|path| f(path)).sum::<f64>() / n;
+        let mean_final_value = mean(|path| path.final_value);
+        Some(ConditionalSummary {
+            paths: paths.len(),
+            probability: n / self.paths.len() as f64,
+            mean_final_value,
+            mean_return_pct: if self.initial_capital > 0.0 {
+                (mean_final_value - self.initial_capital) / self.initial_capital * 100.0
+            } else {
+                0.0
+            },
+            mean_max_drawdown_pct: mean(|path| path.max_drawdown_pct),
+            mean_volatility_pct: mean(|path| path.volatility_pct),
+            mean_sharpe_ratio: mean(|path| path.sharpe_ratio),
+            mean_fees: mean(|path| path.fees),
+            halt_probability: paths.iter().filter(|path| path.halted).count() as f64 / n,
+        })
+    }
+
+    /// Histogram of final values with `bins` equal-width bins, or Freedman–Diaconis
+    /// binning when `None`.
+    ///
+    /// Failed iterations are recorded as 0.0; they are excluded (with a warning)
+    /// and counted in `Histogram::excluded`.
+    pub fn histogram(&self, bins: Option<usize>) -> Histogram {
+        let Some(distribution) = &self.distribution else {
+            return match &self.sketch {
+                Some(sketch) => Histogram::from_sketch(sketch, bins, self.iterations - sketch.count()),
+                None => Histogram::default(),
+            };
+        };
+        let values: Vec<f64> = distribution
+            .iter()
+            .copied()
+            .filter(|value| *value != 0.0 && value.is_finite())
+            .collect();
+        let excluded = distribution.len() - values.len();
+        if excluded > 0 {
+            tracing::warn!("Excluding {} failed iterations from the histogram", excluded);
+        }
+        Histogram::from_values(&values, bins, excluded)
+    }
+}
+
+/// Bin edges and counts of a distribution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Histogram {
+    /// `counts.len() + 1` ascending edges; bin `i` is `[edges[i], edges[i + 1])`, the last bin closed
+    pub edges: Vec<f64>,
+    pub counts: Vec<usize>,
+    /// Values left out (failed iterations)
+    pub excluded: usize,
+}
+
+impl Histogram {
+    pub fn from_values(values: &[f64], bins: Option<usize>, excluded: usize) -> Self {
+        if values.is_empty() {
+            return Self {
+                excluded,
+                ..Self::default()
+            };
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+        
+        // All values equal: one degenerate bin holding everything
+        if max == min {
+            return Self {
+                edges: vec![min, max],
+                counts: vec![sorted.len()],
+                excluded,
+            };
+        }
+        
+        let bins = bins.unwrap_or_else(|| Self::freedman_diaconis_bins(&sorted)).max(1);
+        let width = (max - min) / bins as f64;
+        let edges = Self::edges(min, max, bins);
+        let mut counts = vec![0; bins];
+        for value in &sorted {
+            let bin = (((value - min) / width) as usize).min(bins - 1);
+            counts[bin] += 1;
+        }
+        Self {
+            edges,
+            counts,
+            excluded,
+        }
+    }
+
+    /// Histogram of a sketched distribution, each bin's count estimated from
+    /// the sketch's CDF at its edges
+    pub fn from_sketch(sketch: &TDigest, bins: Option<usize>, excluded: usize) -> Self {
+        let (Some(min), Some(max)) = (sketch.min(), sketch.max()) else {
+            return Self {
+                excluded,
+                ..Self::default()
+            };
+        };
+        if max == min {
+            return Self {
+                edges: vec![min, max],
+                counts: vec![sketch.count()],
+                excluded,
+            };
+        }
+        
+        let n = sketch.count();
+        let bins = bins
+            .unwrap_or_else(|| {
+                let iqr = sketch.quantile(0.75) - sketch.quantile(0.25);
+                Self::bins_for_iqr(n, iqr, max - min)
+            })
+            .max(1);
+        let edges = Self::edges(min, max, bins);
+        // Rounded cumulative counts at each edge, pinned to 0 and n so the bins sum to n
+        let cumulative: Vec<usize> = edges
+            .iter()
+            .enumerate()
+            .map(|(i, &edge)| match i {
+                0 => 0,
+                i if i == bins => n,
+                _ => (sketch.cdf(edge) * n as f64).round() as usize,
+            })
+            .collect();
+        Self {
+            counts: cumulative.windows(2).map(|pair| pair[1].saturating_sub(pair[0])).collect(),
+            edges,
+            excluded,
+        }
+    }
+
+    /// `bins + 1` equal-width edges from `min` to exactly `max`
+    fn edges(min: f64, max: f64, bins: usize) -> Vec<f64> {
+        let width = (max - min) / bins as f64;
+        (0..=bins)
+            .map(|i| if i == bins { max } else { min + width * i as f64 })
+            .collect()
+    }
+
+    fn freedman_diaconis_bins(sorted: &[f64]) -> usize {
+        let n = sorted.len() as f64;
+        let quartile = |p: f64| sorted[((p * n) as usize).min(sorted.len() - 1)];
+        let iqr = quartile(0.75) - quartile(0.25);
+        Self::bins_for_iqr(sorted.len(), iqr, sorted[sorted.len() - 1] - sorted[0])
+    }
+
+    /// Bin count for bins of width `2 IQR / n^(1/3)`, falling back to Sturges' rule when the IQR is zero
+    fn bins_for_iqr(count: usize, iqr: f64, range: f64) -> usize {
+        let n = count as f64;
+        let bins = if iqr > 0.0 {
+            (range / (2.0 * iqr / n.cbrt())).ceil()
+        } else {
+            n.log2().ceil() + 1.0
+        };
+        (bins as usize).clamp(1, MAX_HISTOGRAM_BINS)
+    }
+
+    /// One line per bin with a bar scaled so the fullest bin is `width` characters
+    pub fn render(&self, width: usize) -> String {
+        let peak = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut out = String::new();
+        for (i, count) in self.counts.iter().enumerate() {
+            let bar = "#".repeat(count * width / peak);
+            out.push_str(&format!(
+                "{:>14.2} - {:<14.2} {:>7} {}\n",
+                self.edges[i],
+                self.edges[i + 1],
+                count,
+                bar
+            ));
+        }
+        out
+    }
+}
+
+/// Monte Carlo outcomes over the paths whose scenario falls in one class
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioClassStats {
+    pub paths: usize,
+    /// Share of all paths in this class
+    pub probability: f64,
+    pub expected_value: Decimal,
+    pub mean_return_pct: f64,
+    pub worst_value: Decimal,
 }
 
 /// Backtest results
@@ -161,14 +1357,477 @@ pub struct BacktestResults {
     pub end_date: OffsetDateTime,
     pub initial_value: Decimal,
     pub final_value: Decimal,
+    pub marked_final_value: Decimal,
+    pub terminal_liquidation_cost: Decimal,
     pub total_return_pct: f64,
+    /// The part of `total_return_pct` not from yield income
+    #[serde(default)]
+    pub price_return_pct: f64,
+    /// Yield income over the initial value, in percent; adds to
+    /// `price_return_pct` to give `total_return_pct`
+    #[serde(default)]
+    pub income_return_pct: f64,
     pub annualized_return_pct: f64,
     pub volatility_pct: f64,
     pub sharpe_ratio: f64,
+    /// Annualized Sortino ratio over `BacktestConfig::minimum_acceptable_return`;
+    /// `None` when no day fell short of it
+    #[serde(default)]
+    pub sortino_ratio: Option<f64>,
+    /// Annualized return over max drawdown; `None` without a drawdown
+    #[serde(default)]
+    pub calmar_ratio: Option<f64>,
     pub max_drawdown_pct: f64,
+    /// Share of closed trades with a positive PnL; zero without closed trades
     pub win_rate: f64,
-    pub profit_factor: f64,
+    /// Gross profit over gross loss of closed trades; `None` if none lost money
+    pub profit_factor: Option<f64>,
+    /// Trades paired FIFO from the run's fills: closed trades in exit order,
+    /// then lots still open at the end
     pub trades: Vec<Trade>,
+    /// Total return of the passive benchmark, when one was configured
+    #[serde(default)]
+    pub benchmark_return_pct: Option<f64>,
+    /// Annualized return minus the benchmark's annualized return, in percentage points
+    #[serde(default)]
+    pub alpha_pct: Option<f64>,
+    /// Regression slope of per-step strategy returns on benchmark returns;
+    /// `None` if the benchmark's returns have no variance
+    #[serde(default)]
+    pub beta: Option<f64>,
+    /// Annualized standard deviation of per-step active returns, in percent
+    #[serde(default)]
+    pub tracking_error: Option<f64>,
+    /// Annualized mean active return per unit of tracking error; `None` at zero tracking error
+    #[serde(default)]
+    pub information_ratio: Option<f64>,
+    /// Commission paid on executed trades
+    #[serde(default)]
+    pub total_commission: Decimal,
+    /// Slippage paid on executed trades
+    #[serde(default)]
+    pub total_slippage: Decimal,
+    /// Notional of every executed trade, buys and sells
+    #[serde(default)]
+    pub turnover: Decimal,
+    /// Coverage, gap filling and outliers in the market data the backtest ran on
+    #[serde(default)]
+    pub data_quality: DataQualityReport,
+    /// Value at the start and after every step
+    #[serde(default)]
+    pub equity_curve: Vec<EquityPoint>,
+    /// Return of each calendar month the backtest covers, in order
+    #[serde(default)]
+    pub monthly_returns: Vec<PeriodReturn>,
+    /// Return of each calendar year the backtest covers, in order
+    #[serde(default)]
+    pub annual_returns: Vec<PeriodReturn>,
+    /// Deepest drawdowns of the equity curve, deepest first
+    #[serde(default)]
+    pub drawdowns: Vec<DrawdownEpisode>,
+    /// P&L split between the starting book and the strategy, when the backtest
+    /// started from an existing portfolio
+    #[serde(default)]
+    pub initial_portfolio: Option<InitialPortfolioContribution>,
+    /// Every simulator event of the run, when `BacktestConfig::record_events` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<TransactionLog>,
+}
+
+/// How much of a backtest's P&L the portfolio it started from would have made
+/// on its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InitialPortfolioContribution {
+    /// Value of the starting positions at the first bar's prices
+    pub positions_value: Decimal,
+    /// Symbols held at their stored price because the market data doesn't have them
+    pub frozen_symbols: Vec<String>,
+    /// Price P&L of the starting positions had they been held untouched to the end
+    pub positions_pnl: Decimal,
+    /// The rest of the P&L: what the strategy's routing decisions added,
+    /// net of costs and including yield income
+    pub decisions_pnl: Decimal,
+}
+
+impl BacktestResults {
+    /// Write the equity curve, one row per point: date, value, drawdown
+    /// (percent below the running peak) and cash
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record(["date", "value", "drawdown", "cash"])?;
+        for point in &self.equity_curve {
+            writer.write_record([
+                point.timestamp.date().to_string(),
+                point.value.to_string(),
+                point.drawdown_pct.to_string(),
+                point.cash.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the trade tape, one row per trade in `trades`: entry_time, exit_time,
+    /// symbol, quantity, entry_price, exit_price, fees, pnl, pnl_pct and
+    /// holding_days.
+    ///
+    /// Times are RFC 3339, amounts have 8 decimals, `pnl_pct` 6 and
+    /// `holding_days` 4; the exit columns, `pnl`, `pnl_pct` and `holding_days`
+    /// are empty for trades still open.
+    pub fn write_trades_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record([
+            "entry_time",
+            "exit_time",
+            "symbol",
+            "quantity",
+            "entry_price",
+            "exit_price",
+            "fees",
+            "pnl",
+            "pnl_pct",
+            "holding_days",
+        ])?;
+        let rfc3339 = &time::format_description::well_known::Rfc3339;
+        let amount = |value: Decimal| format!("{:.8}", value);
+        for trade in &self.trades {
+            let exit_time = trade.exit_time.map(|time| time.format(rfc3339)).transpose()?;
+            let holding_days = trade
+                .exit_time
+                .map(|exit| format!("{:.4}", (exit - trade.entry_time).as_seconds_f64() / 86_400.0));
+            writer.write_record([
+                trade.entry_time.format(rfc3339)?,
+                exit_time.unwrap_or_default(),
+                trade.asset.clone(),
+                amount(trade.quantity),
+                amount(trade.entry_price),
+                trade.exit_price.map(amount).unwrap_or_default(),
+                amount(trade.fees),
+                trade.pnl.map(amount).unwrap_or_default(),
+                trade.pnl_pct.map(|pct| format!("{:.6}", pct)).unwrap_or_default(),
+                holding_days.unwrap_or_default(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the event log as JSON lines; errors if the run didn't record one
+    pub fn write_events_jsonl(&self, path: impl AsRef<Path>) -> Result<()> {
+        let events = self
+            .events
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No event log recorded; set BacktestConfig::record_events"))?;
+        events.write_jsonl(path)
+    }
+
+    /// A self-contained Markdown or HTML report of the results; see [`crate::report`]
+    pub fn render_report(&self, format: crate::report::ReportFormat) -> String {
+        crate::report::render(self, format)
+    }
+
+    /// Write `render_report` to `path`: Markdown for a `.md` path, HTML otherwise
+    pub fn write_report(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let format = crate::report::ReportFormat::from_path(path);
+        std::fs::write(path, self.render_report(format))
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Portfolio value at one point of a backtest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub timestamp: OffsetDateTime,
+    pub value: Decimal,
+    pub cash: Decimal,
+    /// Percent below the highest value so far
+    pub drawdown_pct: f64,
+}
+
+/// A fall from a peak of a backtest's equity curve and the climb back to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawdownEpisode {
+    /// The peak the drawdown fell from
+    pub start: OffsetDateTime,
+    pub trough: OffsetDateTime,
+    /// First point back at or above the peak; `None` if the backtest ended below it
+    pub recovery: Option<OffsetDateTime>,
+    /// Percent the trough is below the peak
+    pub depth_pct: f64,
+    /// Days from the peak to the recovery, or to the end of the backtest if unrecovered
+    pub length_days: i64,
+}
+
+/// Return over a calendar month or year of a backtest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeriodReturn {
+    pub year: i32,
+    /// 1 to 12; `None` for a whole year
+    pub month: Option<u8>,
+    pub return_pct: f64,
+    /// The backtest starts after the period's first day or ends before its last
+    pub partial: bool,
+}
+
+/// How well a backtest's market data covered its range
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DataQualityReport {
+    /// Bars after the start date, up to the end date; days for daily bars
+    pub calendar_days: usize,
+    /// Bars left out because some symbol had no price in them
+    pub dropped_days: usize,
+    /// One entry per symbol, sorted by symbol
+    pub symbols: Vec<SymbolCoverage>,
+}
+
+/// Coverage of one symbol over a backtest's bars
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolCoverage {
+    pub symbol: String,
+    /// Bars with data
+    pub bar_days: usize,
+    /// `bar_days` as a percentage of `calendar_days`
+    pub coverage_pct: f64,
+    /// Bars priced by carrying the last close forward
+    pub gaps_filled: usize,
+    /// Bars with no price, more than the fill limit past the last bar or before the first
+    pub missing_days: usize,
+    /// Bars whose close moved more than the outlier threshold from the previous one
+    pub outliers: usize,
+}
+
+/// A backtest run separately on its in-sample and held-out out-of-sample segments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutOfSampleResults {
+    /// End of the in-sample segment and start of the out-of-sample one
+    pub split_date: OffsetDateTime,
+    pub in_sample: BacktestResults,
+    pub out_of_sample: BacktestResults,
+    /// Out-of-sample Sharpe ratio over in-sample; `None` unless the in-sample Sharpe is positive
+    pub degradation_ratio: Option<f64>,
+}
+
+/// A backtest rerun at one trading cost level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostSensitivityPoint {
+    /// Cost of every trade, in basis points of notional
+    pub cost_bps: f64,
+    pub total_return_pct: f64,
+    pub annualized_return_pct: f64,
+    pub sharpe_ratio: f64,
+    /// Costs paid over the backtest
+    pub total_costs: Decimal,
+    pub turnover: Decimal,
+}
+
+/// How a backtest's results hold up as trading costs rise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostSensitivity {
+    /// One per cost level, cheapest first
+    pub points: Vec<CostSensitivityPoint>,
+    /// Cost, in basis points, at which the total return falls through zero,
+    /// interpolated between levels; `None` if it stays on one side
+    pub break_even_bps: Option<f64>,
+}
+
+/// Summary metrics of one backtest in a parameter sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestSweepCell {
+    /// Value of the sweep's first parameter
+    pub x: f64,
+    /// Value of the second parameter, when the sweep has one
+    pub y: Option<f64>,
+    pub total_return_pct: f64,
+    pub annualized_return_pct: f64,
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: Option<f64>,
+    pub max_drawdown_pct: f64,
+    pub win_rate: f64,
+    /// Commission and slippage paid
+    pub total_costs: Decimal,
+    pub turnover: Decimal,
+}
+
+/// Backtests over every combination of one or two parameters' values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestSweepResults {
+    pub x_parameter: BacktestParameter,
+    pub x_values: Vec<f64>,
+    pub y_parameter: Option<BacktestParameter>,
+    /// Empty for a one-parameter sweep
+    pub y_values: Vec<f64>,
+    /// One row per y value (a single row without one), each with a cell per
+    /// x value, in the order the values were given
+    pub cells: Vec<BacktestSweepCell>,
+}
+
+impl BacktestSweepResults {
+    /// `metric` laid out as `cells` is, one row per y value
+    pub fn matrix(&self, metric: BacktestMetric) -> Vec<Vec<Option<f64>>> {
+        self.cells
+            .chunks(self.x_values.len().max(1))
+            .map(|row| row.iter().map(|cell| metric.value(cell)).collect())
+            .collect()
+    }
+
+    /// Write `metric` as a matrix for a heatmap: a header row of x values,
+    /// then one row per y value led by that value. The corner cell names the
+    /// parameters (`y\x`), or the x parameter and the metric for a
+    /// one-parameter sweep. Undefined values are empty.
+    pub fn write_csv(&self, path: impl AsRef<Path>, metric: BacktestMetric) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let corner = match self.y_parameter {
+            Some(y) => format!("{}\\{}", y.name(), self.x_parameter.name()),
+            None => self.x_parameter.name().to_string(),
+        };
+        writer.write_record(std::iter::once(corner).chain(self.x_values.iter().map(f64::to_string)))?;
+        let labels: Vec<String> = match self.y_parameter {
+            Some(_) => self.y_values.iter().map(f64::to_string).collect(),
+            None => vec![metric.name().to_string()],
+        };
+        for (label, row) in labels.into_iter().zip(self.matrix(metric)) {
+            let values = row.into_iter().map(|value| value.map(|v| v.to_string()).unwrap_or_default());
+            writer.write_record(std::iter::once(label).chain(values))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// One window of a rolling backtest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingWindow {
+    pub start_date: OffsetDateTime,
+    pub end_date: OffsetDateTime,
+    pub total_return_pct: f64,
+    pub annualized_return_pct: f64,
+    pub sharpe_ratio: f64,
+    pub max_drawdown_pct: f64,
+}
+
+/// Minimum, median and maximum of a metric across rolling windows
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MetricRange {
+    pub min: f64,
+    pub median: f64,
+    pub max: f64,
+}
+
+impl MetricRange {
+    /// Range of `values`; all zero when there are none
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Self {
+        let mut sorted: Vec<f64> = values.into_iter().collect();
+        sorted.sort_by(f64::total_cmp);
+        Self {
+            min: crate::metrics::quantile(&sorted, 0.0),
+            median: crate::metrics::quantile(&sorted, 0.5),
+            max: crate::metrics::quantile(&sorted, 1.0),
+        }
+    }
+}
+
+/// A strategy backtested on every window of a fixed length across a range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingBacktestResults {
+    pub strategy: String,
+    pub window_days: usize,
+    /// Days between the starts of consecutive windows
+    pub stride_days: usize,
+    pub windows: Vec<RollingWindow>,
+    pub total_return: MetricRange,
+    pub sharpe_ratio: MetricRange,
+    pub max_drawdown: MetricRange,
+    /// Window with the lowest total return
+    pub worst_window: RollingWindow,
+    /// Windows left out for lack of a day to step on
+    pub skipped_windows: usize,
+    /// Coverage of the market data over the whole range
+    pub data_quality: DataQualityReport,
+}
+
+/// One train/test split of a walk-forward analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardFold {
+    pub index: usize,
+    pub train_start: OffsetDateTime,
+    pub train_end: OffsetDateTime,
+    pub test_start: OffsetDateTime,
+    pub test_end: OffsetDateTime,
+    /// Allocation fraction chosen on the training window; `None` for strategies without one
+    pub allocation_fraction: Option<Decimal>,
+    /// Sharpe ratio of the chosen strategy on the training window, when it was optimized there
+    pub train_sharpe: Option<f64>,
+    /// Out-of-sample results on the test window
+    pub test: BacktestResults,
+}
+
+/// Out-of-sample results of a walk-forward analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkForwardResults {
+    pub strategy: String,
+    pub anchored: bool,
+    pub optimized: bool,
+    pub folds: Vec<WalkForwardFold>,
+    /// Folds left out for lack of market data
+    pub skipped_folds: usize,
+    /// Test-window values chained end to end, starting from the initial capital
+    pub equity_curve: Vec<(OffsetDateTime, Decimal)>,
+    /// Total return of the chained test windows
+    pub combined_return_pct: f64,
+    pub mean_sharpe: f64,
+    /// Sample standard deviation of test-window Sharpe ratios; zero with fewer than two folds
+    pub sharpe_std_dev: f64,
+    pub min_sharpe: f64,
+    pub max_sharpe: f64,
+}
+
+impl WalkForwardResults {
+    /// Write one row per fold
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut writer = csv::Writer::from_path(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record([
+            "fold",
+            "train_start",
+            "train_end",
+            "test_start",
+            "test_end",
+            "allocation_fraction",
+            "train_sharpe",
+            "total_return_pct",
+            "annualized_return_pct",
+            "volatility_pct",
+            "sharpe_ratio",
+            "max_drawdown_pct",
+        ])?;
+        let date = |time: OffsetDateTime| time.date().to_string();
+        for fold in &self.folds {
+            writer.write_record([
+                fold.index.to_string(),
+                date(fold.train_start),
+                date(fold.train_end),
+                date(fold.test_start),
+                date(fold.test_end),
+                fold.allocation_fraction.map_or(String::new(), |fraction| fraction.to_string()),
+                fold.train_sharpe.map_or(String::new(), |sharpe| sharpe.to_string()),
+                fold.test.total_return_pct.to_string(),
+                fold.test.annualized_return_pct.to_string(),
+                fold.test.volatility_pct.to_string(),
+                fold.test.sharpe_ratio.to_string(),
+                fold.test.max_drawdown_pct.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -181,6 +1840,24 @@ pub struct Trade {
     pub exit_price: Option<Decimal>,
     pub pnl: Option<Decimal>,
     pub pnl_pct: Option<f64>,
+    /// Execution costs paid on the entry and exit fills, pro rata to the quantity
+    #[serde(default)]
+    pub fees: Decimal,
+}
+
+/// A quantity of one symbol bought or sold: executed decisions, forced
+/// liquidations, and write-offs (sold at zero)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub step: usize,
+    pub timestamp: OffsetDateTime,
+    pub symbol: String,
+    pub side: TradeSide,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    /// Execution cost paid on the fill
+    #[serde(default)]
+    pub fee: Decimal,
 }
 
 /// Market data point
@@ -196,6 +1873,15 @@ pub struct MarketData {
     pub close: Decimal,
 }
 
+/// Annual yield of a symbol from `timestamp` until its next observation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YieldObservation {
+    pub timestamp: OffsetDateTime,
+    pub symbol: String,
+    /// Annual percentage yield, as a fraction
+    pub apy: Decimal,
+}
+
 /// Risk parameters for a strategy
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RiskParameters {
@@ -230,6 +1916,77 @@ pub struct RoutingDecision {
     pub expected_yield: Decimal,
     pub risk_score: f64,
     pub execution_cost: Decimal,
+    /// Currency paying for a buy; `None` pays from reporting-currency cash
+    #[serde(default)]
+    pub source_currency: Option<String>,
+}
+
+/// Symbol strategies use for the cash leg of a routing decision
+pub const CASH_SYMBOL: &str = "USD";
+
+impl RoutingDecision {
+    /// A sale routes capital from a held asset back to cash
+    pub fn is_sell(&self) -> bool {
+        self.target_asset == CASH_SYMBOL && self.source_asset != CASH_SYMBOL
+    }
+
+    /// Symbol whose position the decision changes
+    pub fn traded_symbol(&self) -> &str {
+        if self.is_sell() {
+            &self.source_asset
+        } else {
+            &self.target_asset
+        }
+    }
+}
+
+/// Price shock applied on top of the normal price walk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketShock {
+    pub step: usize,
+    /// Symbol to shock, or `*` for every symbol in the market
+    pub symbol: String,
+    /// Price change in percent, e.g. -40 for a 40% drop
+    pub pct_change: Decimal,
+}
+
+impl MarketShock {
+    pub const ALL_SYMBOLS: &'static str = "*";
+
+    pub fn applies_to(&self, symbol: &str) -> bool {
+        self.symbol == Self::ALL_SYMBOLS || self.symbol == symbol
+    }
+}
+
+/// External deposit (positive) or withdrawal (negative) of reporting-currency cash
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct CashFlow {
+    pub step: usize,
+    pub amount: Decimal,
+}
+
+/// Outcome of a routing decision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DecisionStatus {
+    Executed,
+    /// Executed at a smaller amount than the strategy asked for
+    ScaledDown { requested: Decimal, reason: String },
+    Rejected { reason: String },
+}
+
+/// Routing decision together with what the simulator did with it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutedDecision {
+    pub step: usize,
+    pub decision: RoutingDecision,
+    pub status: DecisionStatus,
+    pub execution_cost: Decimal,
+}
+
+impl ExecutedDecision {
+    pub fn is_executed(&self) -> bool {
+        matches!(self.status, DecisionStatus::Executed | DecisionStatus::ScaledDown { .. })
+    }
 }
 
 /// Strategy configuration
@@ -242,3 +1999,60 @@ pub struct StrategyConfig {
     pub max_slippage_pct: f64,
     pub preferred_asset_types: Vec<AssetType>,
 }
+
+impl StrategyConfig {
+    /// `rebalance_frequency_days` as a `SimulatorConfig::rebalance_interval`; 0 runs every step
+    pub fn rebalance_interval(&self) -> Option<time::Duration> {
+        (self.rebalance_frequency_days > 0).then(|| time::Duration::days(self.rebalance_frequency_days.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_values_share_one_degenerate_bin() {
+        let histogram = Histogram::from_values(&[42.0; 7], None, 2);
+        assert_eq!(histogram.edges, vec![42.0, 42.0]);
+        assert_eq!(histogram.counts, vec![7]);
+        assert_eq!(histogram.excluded, 2);
+        // Asking for more bins doesn't split it
+        assert_eq!(Histogram::from_values(&[42.0; 7], Some(10), 0).counts, vec![7]);
+
+        let empty = Histogram::from_values(&[], None, 3);
+        assert!(empty.edges.is_empty() && empty.counts.is_empty());
+        assert_eq!(empty.excluded, 3);
+    }
+
+    #[test]
+    fn freedman_diaconis_sizes_bins_from_the_iqr() {
+        // IQR 50 over 100 values gives bins 50 * 2 / 100^(1/3) ≈ 21.5 wide: 5 across a range of 99
+        let values: Vec<f64> = (0..100).rev().map(f64::from).collect();
+        let histogram = Histogram::from_values(&values, None, 0);
+        assert_eq!(histogram.counts, vec![20; 5]);
+        assert_eq!(histogram.edges.len(), 6);
+        assert_eq!(histogram.edges[0], 0.0);
+        assert!((histogram.edges[1] - 19.8).abs() < 1e-12);
+        assert_eq!(histogram.edges[5], 99.0);
+
+        // With no spread between the quartiles, Sturges' rule gives ceil(log2 11) + 1 bins
+        let mut clustered = vec![1.0; 10];
+        clustered.push(5.0);
+        let histogram = Histogram::from_values(&clustered, None, 0);
+        assert_eq!(histogram.counts, vec![10, 0, 0, 0, 1]);
+
+        // A long tail is capped at the maximum bin count
+        let mut tailed: Vec<f64> = (0..1000).map(|i| f64::from(i % 10)).collect();
+        tailed.push(1e9);
+        assert_eq!(Histogram::from_values(&tailed, None, 0).counts.len(), MAX_HISTOGRAM_BINS);
+    }
+
+    #[test]
+    fn explicit_bins_close_the_last_bin_on_the_maximum() {
+        let histogram = Histogram::from_values(&[0.0, 1.0, 2.0, 3.0, 4.0], Some(4), 0);
+        assert_eq!(histogram.edges, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(histogram.counts, vec![1, 1, 1, 2]);
+        assert_eq!(Histogram::from_values(&[0.0, 4.0], Some(0), 0).counts, vec![2]);
+    }
+}
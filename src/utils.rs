@@ -1,6 +1,7 @@
-use rust_decimal::Decimal;
+//! Utility functions for the simulator
 
-/// Utility functions for the simulator
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 
 /// Convert f64 to Decimal safely
 pub fn f64_to_decimal(value: f64) -> Decimal {
@@ -25,3 +26,110 @@ pub fn format_currency(value: Decimal) -> String {
 pub fn format_percentage(value: f64) -> String {
     format!("{:.2}%", value)
 }
+
+/// Per-period internal rate of return of `(period, amount)` cash flows.
+///
+/// Solved by bisection over (-100%, 100%] per period; `None` when the net
+/// present value doesn't change sign over that range.
+pub fn internal_rate_of_return(flows: &[(f64, f64)]) -> Option<f64> {
+    let npv = |rate: f64| -> f64 {
+        flows.iter().map(|(period, amount)| amount / (1.0 + rate).powf(*period)).sum()
+    };
+    
+    let (mut low, mut high) = (-0.9999, 1.0);
+    let (mut npv_low, npv_high) = (npv(low), npv(high));
+    if !npv_low.is_finite() || !npv_high.is_finite() || npv_low.signum() == npv_high.signum() {
+        return None;
+    }
+    
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        let npv_mid = npv(mid);
+        if npv_mid.signum() == npv_low.signum() {
+            low = mid;
+            npv_low = npv_mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+/// Cholesky decomposition of a symmetric positive-definite matrix.
+///
+/// Returns the lower-triangular factor `L` with `L * L^T = matrix`, or an
+/// error if the matrix is not square or not positive definite.
+pub fn cholesky(matrix: &[Vec<f64>]) -> anyhow::Result<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    if matrix.iter().any(|row| row.len() != n) {
+        return Err(anyhow::anyhow!("Matrix must be square"));
+    }
+    
+    let mut lower = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                let diagonal = matrix[i][i] - sum;
+                if diagonal <= 0.0 {
+                    return Err(anyhow::anyhow!("Matrix is not positive definite"));
+                }
+                lower[i][j] = diagonal.sqrt();
+            } else {
+                lower[i][j] = (matrix[i][j] - sum) / lower[j][j];
+            }
+        }
+    }
+    
+    Ok(lower)
+}
+
+/// Eigenvalues and eigenvectors of a symmetric matrix, by cyclic Jacobi
+/// rotations.
+///
+/// Returns the eigenvalues and a matrix whose columns are the matching unit
+/// eigenvectors, or an error if the matrix is not square.
+pub fn symmetric_eigen(matrix: &[Vec<f64>]) -> anyhow::Result<(Vec<f64>, Vec<Vec<f64>>)> {
+    let n = matrix.len();
+    if matrix.iter().any(|row| row.len() != n) {
+        return Err(anyhow::anyhow!("Matrix must be square"));
+    }
+    
+    let mut a = matrix.to_vec();
+    let mut vectors: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    let scale: f64 = a.iter().flatten().map(|x| x * x).sum();
+    for _ in 0..100 {
+        let off_diagonal: f64 = (0..n).map(|i| (i + 1..n).map(|j| a[i][j] * a[i][j]).sum::<f64>()).sum();
+        if off_diagonal <= scale * 1e-30 {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // Rotate the (p, q) plane to zero a[p][q]
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (upper, lower) = a.split_at_mut(q);
+                for (pk, qk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    (*pk, *qk) = (c * *pk - s * *qk, s * *pk + c * *qk);
+                }
+                for row in vectors.iter_mut() {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+            }
+        }
+    }
+    
+    Ok(((0..n).map(|i| a[i][i]).collect(), vectors))
+}
@@ -0,0 +1,288 @@
+//! Walk-forward analysis over a backtest's date range.
+//!
+//! The range is split into consecutive folds, each a training window followed
+//! by a test window. Training windows either roll forward with the test
+//! windows or stay anchored at the start of the range. With an optimizer, the
+//! strategy variant with the best Sharpe ratio on each training window is the
+//! one evaluated on the following test window. Only test windows count towards
+//! the out-of-sample results.
+
+use crate::backtest::BacktestEngine;
+use crate::metrics::Moments;
+use crate::optimizer::StrategyOptimizer;
+use crate::strategy::{RoutingStrategy, Strategy};
+use crate::types::*;
+use anyhow::Result;
+use rust_decimal::Decimal;
+use time::{Duration, OffsetDateTime};
+use tracing::{info, warn};
+
+/// Fewest days with market data a train or test window needs to be run
+const MIN_WINDOW_DATA_DAYS: usize = 2;
+
+/// Rolling or anchored train/test evaluation of a backtest's strategy
+pub struct WalkForwardRunner {
+    engine: BacktestEngine,
+    train_days: i64,
+    test_days: i64,
+    anchored: bool,
+    optimizer: Option<StrategyOptimizer>,
+}
+
+impl WalkForwardRunner {
+    /// Folds of `train_days` training followed by `test_days` testing across
+    /// `engine`'s range, rolling forward by `test_days`
+    pub fn new(engine: BacktestEngine, train_days: usize, test_days: usize) -> Result<Self> {
+        if train_days == 0 || test_days == 0 {
+            return Err(anyhow::anyhow!("walk-forward train and test windows must be at least one day"));
+        }
+        let range_days = (engine.end_date() - engine.start_date()).whole_days();
+        if (train_days + test_days) as i64 > range_days {
+            return Err(anyhow::anyhow!(
+                "a {} day train window and {} day test window don't fit in the {} day backtest range",
+                train_days,
+                test_days,
+                range_days
+            ));
+        }
+        Ok(Self {
+            engine,
+            train_days: train_days as i64,
+            test_days: test_days as i64,
+            anchored: false,
+            optimizer: None,
+        })
+    }
+
+    /// Start every training window at the beginning of the range, so it grows fold by fold
+    pub fn anchored(mut self) -> Self {
+        self.anchored = true;
+        self
+    }
+
+    /// Choose the strategy for each test window from the optimizer's
+    /// candidates, by Sharpe ratio on the training window
+    pub fn with_optimizer(mut self, optimizer: StrategyOptimizer) -> Self {
+        self.optimizer = Some(optimizer);
+        self
+    }
+
+    /// Training start, test start (the end of training) and test end of
+    /// every fold that fits in the range
+    fn folds(&self) -> Vec<(OffsetDateTime, OffsetDateTime, OffsetDateTime)> {
+        let start = self.engine.start_date();
+        let end = self.engine.end_date();
+        let mut folds = vec![];
+        let mut test_start = start + Duration::days(self.train_days);
+        while test_start + Duration::days(self.test_days) <= end {
+            let train_start = if self.anchored {
+                start
+            } else {
+                test_start - Duration::days(self.train_days)
+            };
+            let test_end = test_start + Duration::days(self.test_days);
+            folds.push((train_start, test_start, test_end));
+            test_start = test_end;
+        }
+        folds
+    }
+
+    /// Run every fold and aggregate the test windows
+    pub async fn run(&self) -> Result<WalkForwardResults> {
+        let strategy = self.engine.strategy();
+        let mut folds = vec![];
+        let mut skipped_folds = 0;
+        let mut equity_curve: Vec<(OffsetDateTime, Decimal)> = vec![];
+        let mut equity = None;
+
+        for (index, (train_start, test_start, test_end)) in self.folds().into_iter().enumerate() {
+            let train_end = test_start;
+            let train = self.engine.window(train_start, train_end, strategy.clone());
+            let test = self.engine.window(test_start, test_end, strategy.clone());
+            let needs_training = self.optimizer.is_some();
+            if test.data_days() < MIN_WINDOW_DATA_DAYS
+                || (needs_training && train.data_days() < MIN_WINDOW_DATA_DAYS)
+            {
+                warn!(
+                    "Skipping walk-forward fold {} ({} to {}): not enough market data",
+                    index,
+                    train_start.date(),
+                    test_end.date()
+                );
+                skipped_folds += 1;
+                continue;
+            }
+
+            let (chosen, train_sharpe) = match &self.optimizer {
+                Some(optimizer) => {
+                    let mut best: Option<(Strategy, f64)> = None;
+                    for candidate in optimizer.candidates(strategy) {
+                        let sharpe = train.window(train_start, train_end, candidate.clone()).run().await?.sharpe_ratio;
+                        if !best.as_ref().is_some_and(|(_, best_sharpe)| sharpe <= *best_sharpe) {
+                            best = Some((candidate, sharpe));
+                        }
+                    }
+                    let (chosen, sharpe) = best.expect("candidates include the strategy itself");
+                    (chosen, Some(sharpe))
+                }
+                None => (strategy.clone(), None),
+            };
+
+            let allocation_fraction = chosen.allocation_fraction();
+            let results = test.window(test_start, test_end, chosen).run().await?;
+            info!(
+                "Fold {}: test {} to {}, return {:.2}%, Sharpe {:.4}",
+                index,
+                test_start.date(),
+                test_end.date(),
+                results.total_return_pct,
+                results.sharpe_ratio
+            );
+
+            // Chain the fold's values onto the end of the previous fold's
+            let opening = results.equity_curve.first().map_or(Decimal::ZERO, |point| point.value);
+            let base = *equity.get_or_insert(opening);
+            let skip_opening = usize::from(!equity_curve.is_empty());
+            if opening > Decimal::ZERO {
+                for point in results.equity_curve.iter().skip(skip_opening) {
+                    equity_curve.push((point.timestamp, base * point.value / opening));
+                }
+            }
+            if let Some((_, last)) = equity_curve.last() {
+                equity = Some(*last);
+            }
+
+            folds.push(WalkForwardFold {
+                index,
+                train_start,
+                train_end,
+                test_start,
+                test_end,
+                allocation_fraction,
+                train_sharpe,
+                test: results,
+            });
+        }
+
+        if folds.is_empty() {
+            return Err(anyhow::anyhow!("no walk-forward fold had enough market data to run"));
+        }
+
+        let mut sharpe = Moments::new();
+        for fold in &folds {
+            sharpe.push(fold.test.sharpe_ratio);
+        }
+        let sharpe_std_dev = sharpe.variance().sqrt();
+        let combined_return_pct = match (equity_curve.first(), equity_curve.last()) {
+            (Some((_, first)), Some((_, last))) => crate::utils::percentage_change(*first, *last),
+            _ => 0.0,
+        };
+
+        Ok(WalkForwardResults {
+            strategy: strategy.name().to_string(),
+            anchored: self.anchored,
+            optimized: self.optimizer.is_some(),
+            skipped_folds,
+            equity_curve,
+            combined_return_pct,
+            mean_sharpe: sharpe.mean(),
+            sharpe_std_dev,
+            min_sharpe: folds.iter().map(|fold| fold.test.sharpe_ratio).fold(f64::INFINITY, f64::min),
+            max_sharpe: folds.iter().map(|fold| fold.test.sharpe_ratio).fold(f64::NEG_INFINITY, f64::max),
+            folds,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::BacktestConfig;
+    use std::collections::HashMap;
+    use time::macros::datetime;
+
+    /// A 100-day backtest holding X, whose daily bars zigzag upwards for the first `data_days` days
+    fn engine(data_days: i64) -> BacktestEngine {
+        let bars = (0..=data_days)
+            .map(|day| {
+                let close = Decimal::from(100 + day + day % 3 * 2);
+                MarketData {
+                    timestamp: datetime!(2024-01-01 0:00 UTC) + Duration::days(day),
+                    symbol: "X".to_string(),
+                    price: close,
+                    volume: Decimal::from(1_000_000),
+                    high: close,
+                    low: close,
+                    open: close,
+                    close,
+                }
+            })
+            .collect();
+        let strategy = Strategy::target_weight(HashMap::from([("X".to_string(), Decimal::ONE)]));
+        BacktestEngine::new("2024-01-01", "2024-04-10", strategy)
+            .unwrap()
+            .with_config(BacktestConfig::frictionless())
+            .unwrap()
+            .with_market_data(bars)
+            .unwrap()
+    }
+
+    #[test]
+    fn rolling_folds_slide_and_anchored_folds_grow() {
+        let start = datetime!(2024-01-01 0:00 UTC);
+        let rolling = WalkForwardRunner::new(engine(100), 30, 10).unwrap().folds();
+        // 100 days: 30 of training, then seven 10-day tests
+        assert_eq!(rolling.len(), 7);
+        for (i, (train_start, test_start, test_end)) in rolling.iter().enumerate() {
+            let test_offset = Duration::days(30 + 10 * i as i64);
+            assert_eq!(*test_start, start + test_offset);
+            assert_eq!(*train_start, *test_start - Duration::days(30));
+            assert_eq!(*test_end, *test_start + Duration::days(10));
+        }
+
+        let anchored = WalkForwardRunner::new(engine(100), 30, 10).unwrap().anchored().folds();
+        assert!(anchored.iter().all(|(train_start, _, _)| *train_start == start));
+        let tests = |folds: &[(OffsetDateTime, OffsetDateTime, OffsetDateTime)]| {
+            folds.iter().map(|(_, test_start, test_end)| (*test_start, *test_end)).collect::<Vec<_>>()
+        };
+        assert_eq!(tests(&anchored), tests(&rolling));
+    }
+
+    #[test]
+    fn windows_must_fit_the_range() {
+        assert!(WalkForwardRunner::new(engine(100), 0, 10).is_err());
+        assert!(WalkForwardRunner::new(engine(100), 30, 0).is_err());
+        assert!(WalkForwardRunner::new(engine(100), 90, 11).is_err());
+        assert!(WalkForwardRunner::new(engine(100), 90, 10).is_ok());
+    }
+
+    #[tokio::test]
+    async fn folds_without_data_are_skipped_and_the_rest_chained() {
+        // Data stops after day 60: the tests from day 60 on have too little of it
+        let results = WalkForwardRunner::new(engine(60), 30, 10).unwrap().run().await.unwrap();
+        assert_eq!(results.folds.len(), 3);
+        assert_eq!(results.skipped_folds, 4);
+        assert!(!results.optimized);
+
+        let chained: f64 = results
+            .folds
+            .iter()
+            .map(|fold| 1.0 + fold.test.total_return_pct / 100.0)
+            .product();
+        assert!((results.combined_return_pct - (chained - 1.0) * 100.0).abs() < 1e-6);
+        let (first, _) = results.equity_curve[0];
+        assert_eq!(first, results.folds[0].test_start);
+        assert!(results.equity_curve.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        let sharpes: Vec<f64> = results.folds.iter().map(|fold| fold.test.sharpe_ratio).collect();
+        let mean = sharpes.iter().sum::<f64>() / 3.0;
+        assert!((results.mean_sharpe - mean).abs() < 1e-9);
+        assert_eq!(results.min_sharpe, sharpes.iter().copied().fold(f64::INFINITY, f64::min));
+    }
+
+    #[tokio::test]
+    async fn a_range_without_any_usable_fold_is_an_error() {
+        let error = WalkForwardRunner::new(engine(20), 30, 10).unwrap().run().await.unwrap_err();
+        assert!(error.to_string().contains("no walk-forward fold"));
+    }
+}
@@ -0,0 +1,331 @@
+//! Yield curves and bond repricing for RWA assets.
+//!
+//! A [`YieldCurve`] maps tenors in years to continuously compounded zero
+//! rates, interpolating between them. Bond-like assets carry [`BondDetails`]
+//! (maturity and coupon), from which their price moves under a curve shift
+//! follow by discounting the cash flows on both curves: a five-year zero loses
+//! about 5% when the curve rises 100bps.
+
+use crate::types::{Asset, Portfolio};
+use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// How a curve fills in rates between its tenors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Straight lines between zero rates
+    #[default]
+    Linear,
+    /// Straight lines between log discount factors, i.e. flat forward rates
+    LogLinear,
+}
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "linear" => Ok(Self::Linear),
+            "log_linear" => Ok(Self::LogLinear),
+            other => Err(anyhow::anyhow!("Unknown interpolation {}; expected linear or log_linear", other)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Linear => "linear",
+            Self::LogLinear => "log_linear",
+        }
+    }
+}
+
+/// Continuously compounded zero rates by tenor in years.
+///
+/// Rates are flat beyond the first and last tenors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct YieldCurve {
+    /// `(tenor, rate)` by increasing tenor
+    points: Vec<(f64, f64)>,
+    #[serde(default)]
+    interpolation: Interpolation,
+}
+
+impl YieldCurve {
+    /// A curve through `(tenor in years, annual rate)` points, in any order
+    pub fn new(mut points: Vec<(f64, f64)>) -> Result<Self> {
+        if points.is_empty() {
+            return Err(anyhow::anyhow!("A yield curve needs at least one point"));
+        }
+        let invalid = |(tenor, rate): &&(f64, f64)| !(tenor.is_finite() && *tenor > 0.0 && rate.is_finite());
+        if let Some((tenor, rate)) = points.iter().find(invalid) {
+            return Err(anyhow::anyhow!(
+                "Yield curve point ({}, {}) needs a positive tenor and a finite rate",
+                tenor,
+                rate
+            ));
+        }
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if let Some(pair) = points.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(anyhow::anyhow!("Yield curve has two rates at tenor {}", pair[0].0));
+        }
+        Ok(Self { points, interpolation: Interpolation::default() })
+    }
+
+    /// The same rate at every tenor
+    pub fn flat(rate: f64) -> Self {
+        Self { points: vec![(1.0, rate)], interpolation: Interpolation::default() }
+    }
+
+    /// A US Treasury-like curve, inverted at the front end, for the mock provider
+    pub fn default_usd() -> Self {
+        let points = vec![
+            (0.25, 0.0530),
+            (0.5, 0.0525),
+            (1.0, 0.0500),
+            (2.0, 0.0460),
+            (3.0, 0.0440),
+            (5.0, 0.0425),
+            (7.0, 0.0425),
+            (10.0, 0.0430),
+            (30.0, 0.0445),
+        ];
+        Self { points, interpolation: Interpolation::default() }
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// `(tenor, rate)` points by increasing tenor
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Zero rate at `tenor` years
+    pub fn rate(&self, tenor: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if tenor <= first.0 {
+            return first.1;
+        }
+        if tenor >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.partition_point(|(t, _)| *t < tenor);
+        let ((t0, r0), (t1, r1)) = (self.points[upper - 1], self.points[upper]);
+        let weight = (tenor - t0) / (t1 - t0);
+        match self.interpolation {
+            Interpolation::Linear => r0 + weight * (r1 - r0),
+            // Interpolate r * t, the negative log discount factor
+            Interpolation::LogLinear => (r0 * t0 + weight * (r1 * t1 - r0 * t0)) / tenor,
+        }
+    }
+
+    /// Present value of one unit paid in `tenor` years
+    pub fn discount_factor(&self, tenor: f64) -> f64 {
+        (-self.rate(tenor) * tenor).exp()
+    }
+
+    /// The curve with every rate moved by `bps` basis points
+    pub fn shifted(&self, bps: f64) -> Self {
+        Self {
+            points: self.points.iter().map(|(tenor, rate)| (*tenor, rate + bps / 10_000.0)).collect(),
+            interpolation: self.interpolation,
+        }
+    }
+}
+
+fn default_coupon_frequency() -> u32 {
+    1
+}
+
+/// Cash-flow terms of a bond-like asset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BondDetails {
+    /// Years to the final payment
+    pub maturity_years: f64,
+    /// Annual coupon as a fraction of face value; zero for a zero-coupon bond
+    #[serde(default)]
+    pub coupon_rate: f64,
+    /// Coupons a year
+    #[serde(default = "default_coupon_frequency")]
+    pub coupon_frequency: u32,
+}
+
+impl BondDetails {
+    pub fn zero_coupon(maturity_years: f64) -> Self {
+        Self { maturity_years, coupon_rate: 0.0, coupon_frequency: 1 }
+    }
+
+    pub fn with_coupon(mut self, coupon_rate: f64, coupon_frequency: u32) -> Self {
+        self.coupon_rate = coupon_rate;
+        self.coupon_frequency = coupon_frequency;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(self.maturity_years.is_finite() && self.maturity_years > 0.0) {
+            return Err(anyhow::anyhow!("Bond maturity must be positive, got {}", self.maturity_years));
+        }
+        if !(self.coupon_rate.is_finite() && self.coupon_rate >= 0.0) || self.coupon_frequency == 0 {
+            return Err(anyhow::anyhow!(
+                "Bond coupon must be non-negative and paid at least once a year, got {} x{}",
+                self.coupon_rate,
+                self.coupon_frequency
+            ));
+        }
+        Ok(())
+    }
+
+    /// `(years, amount)` of every remaining payment per unit of face value
+    pub fn cash_flows(&self) -> Vec<(f64, f64)> {
+        let period = 1.0 / self.coupon_frequency as f64;
+        let coupon = self.coupon_rate * period;
+        let mut flows = vec![];
+        let mut time = self.maturity_years;
+        // Coupons fall every period back from maturity
+        while time > 1e-9 {
+            flows.push((time, coupon));
+            time -= period;
+        }
+        flows.reverse();
+        if let Some(last) = flows.last_mut() {
+            last.1 += 1.0;
+        }
+        flows
+    }
+
+    /// Present value per unit of face value, discounted on `curve`
+    pub fn present_value(&self, curve: &YieldCurve) -> f64 {
+        self.cash_flows().iter().map(|(time, amount)| amount * curve.discount_factor(*time)).sum()
+    }
+
+    /// Percent price change per percentage point of parallel shift, measured
+    /// by moving `curve` a basis point each way
+    pub fn modified_duration(&self, curve: &YieldCurve) -> f64 {
+        let (down, up) = (self.present_value(&curve.shifted(-1.0)), self.present_value(&curve.shifted(1.0)));
+        (down - up) / (2.0 * self.present_value(curve) * 0.0001)
+    }
+
+    /// `price` marked from `curve` to `shifted`, scaling by the ratio of present values
+    pub fn reprice(&self, price: Decimal, curve: &YieldCurve, shifted: &YieldCurve) -> Decimal {
+        let ratio = self.present_value(shifted) / self.present_value(curve);
+        Decimal::try_from(ratio).map_or(price, |ratio| price * ratio)
+    }
+
+    /// Percent price change of moving from `curve` to `shifted`
+    pub fn pct_change(&self, curve: &YieldCurve, shifted: &YieldCurve) -> f64 {
+        (self.present_value(shifted) / self.present_value(curve) - 1.0) * 100.0
+    }
+}
+
+/// `asset`'s price marked from `curve` to `shifted`; `None` if it isn't a bond
+pub fn reprice_asset(asset: &Asset, curve: &YieldCurve, shifted: &YieldCurve) -> Option<Decimal> {
+    asset.bond.as_ref().map(|bond| bond.reprice(asset.current_price, curve, shifted))
+}
+
+/// Mark every bond position in `portfolio` from `curve` to `curve` shifted by
+/// `bps` basis points, moving their yields by the same amount, and return the
+/// change in total value
+pub fn mark_bonds_to_curve(portfolio: &mut Portfolio, curve: &YieldCurve, bps: f64) -> Decimal {
+    let before = portfolio.total_value;
+    let shifted = curve.shifted(bps);
+    let delta = Decimal::try_from(bps / 10_000.0).unwrap_or(Decimal::ZERO);
+    for position in portfolio.positions.values_mut() {
+        if let Some(price) = reprice_asset(&position.asset, curve, &shifted) {
+            position.update_price(price);
+            position.asset.yield_rate += delta;
+        }
+    }
+    portfolio.update_total_value();
+    portfolio.total_value - before
+}
+
+/// A flat curve at `asset`'s yield, for repricing without a configured curve
+pub(crate) fn flat_curve_for(asset: &Asset) -> YieldCurve {
+    YieldCurve::flat(asset.yield_rate.to_f64().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AssetType, Position};
+    use rust_decimal_macros::dec;
+
+    fn bond_asset(symbol: &str, price: Decimal, bond: Option<BondDetails>) -> Asset {
+        Asset {
+            symbol: symbol.to_string(),
+            name: format!("Asset {}", symbol),
+            asset_type: if bond.is_some() { AssetType::RWABond } else { AssetType::Crypto },
+            current_price: price,
+            volatility: Decimal::ZERO,
+            yield_rate: dec!(0.0425),
+            expected_return: Decimal::ZERO,
+            bond,
+            liquidity: None,
+        }
+    }
+
+    #[test]
+    fn five_year_duration_loses_about_five_percent_on_100bps() {
+        let curve = YieldCurve::default_usd();
+        let zero = BondDetails::zero_coupon(5.0);
+        assert!((zero.modified_duration(&curve) - 5.0).abs() < 1e-6);
+
+        // Exactly 1 - e^-0.05 under continuous compounding, about 4.9%
+        let change = zero.pct_change(&curve, &curve.shifted(100.0));
+        assert!((change + 5.0).abs() < 0.2, "{}", change);
+        assert!((change - ((-0.05f64).exp() - 1.0) * 100.0).abs() < 1e-9);
+        let repriced = zero.reprice(dec!(80), &curve, &curve.shifted(100.0)).to_f64().unwrap();
+        assert!((repriced - 80.0 * (-0.05f64).exp()).abs() < 1e-9);
+        // A shift down gains, a bit more than the same shift up loses
+        let gain = zero.pct_change(&curve, &curve.shifted(-100.0));
+        assert!(gain > -change && gain < 5.2);
+
+        // Coupons pull the duration below the maturity
+        let coupon = BondDetails::zero_coupon(5.0).with_coupon(0.05, 2);
+        assert_eq!(coupon.cash_flows().len(), 10);
+        assert_eq!(coupon.cash_flows()[9], (5.0, 1.025));
+        assert!((4.0..5.0).contains(&coupon.modified_duration(&curve)));
+    }
+
+    #[test]
+    fn marking_a_book_reprices_only_its_bonds() {
+        let curve = YieldCurve::default_usd();
+        let mut portfolio = Portfolio::new(Decimal::ZERO);
+        let zero = bond_asset("ZERO5", dec!(80), Some(BondDetails::zero_coupon(5.0)));
+        portfolio.add_position(Position::new(zero, dec!(1000), dec!(80)));
+        portfolio.add_position(Position::new(bond_asset("ETH", dec!(2000), None), dec!(10), dec!(2000)));
+        portfolio.update_total_value();
+
+        let change = mark_bonds_to_curve(&mut portfolio, &curve, 100.0).to_f64().unwrap();
+        assert!((change - 80_000.0 * ((-0.05f64).exp() - 1.0)).abs() < 1e-6);
+        assert_eq!(portfolio.positions["ETH"].asset.current_price, dec!(2000));
+        assert_eq!(portfolio.positions["ZERO5"].asset.yield_rate, dec!(0.0525));
+    }
+
+    #[test]
+    fn curves_interpolate_between_tenors() {
+        let curve = YieldCurve::new(vec![(2.0, 0.04), (1.0, 0.05)]).unwrap();
+        assert!((curve.rate(1.5) - 0.045).abs() < 1e-12);
+        assert_eq!(curve.rate(0.5), 0.05);
+        assert_eq!(curve.rate(10.0), 0.04);
+        // r * t runs from 0.05 to 0.08, so reaches 0.065 at 1.5y
+        let log_linear = curve.clone().with_interpolation(Interpolation::LogLinear);
+        assert!((log_linear.rate(1.5) - 0.065 / 1.5).abs() < 1e-12);
+        assert!((log_linear.discount_factor(1.5) - (-0.065f64).exp()).abs() < 1e-12);
+        assert!((curve.shifted(25.0).rate(1.5) - 0.0475).abs() < 1e-12);
+
+        assert!(YieldCurve::new(vec![]).is_err());
+        assert!(YieldCurve::new(vec![(1.0, 0.05), (1.0, 0.04)]).is_err());
+        assert!(YieldCurve::new(vec![(0.0, 0.05)]).is_err());
+        assert!(BondDetails::zero_coupon(0.0).validate().is_err());
+        assert!(BondDetails::zero_coupon(5.0).with_coupon(0.05, 0).validate().is_err());
+        assert_eq!(Interpolation::from_name("log_linear").unwrap().name(), "log_linear");
+    }
+}
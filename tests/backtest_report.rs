@@ -0,0 +1,67 @@
+//! The Markdown and HTML reports of a fixed backtest, with a BTC benchmark,
+//! against the golden files in `tests/golden`, so changes to the report
+//! layout show up as a diff. Set `UPDATE_GOLDEN=1` to rewrite the golden
+//! files after an intended change.
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::path::Path;
+use time::macros::datetime;
+use vaulta_simulator::backtest::BacktestEngine;
+use vaulta_simulator::report::ReportFormat;
+use vaulta_simulator::simulator::Benchmark;
+use vaulta_simulator::types::MarketData;
+use vaulta_simulator::Strategy;
+
+const DAYS: i64 = 75;
+
+/// Prices that climb, dip in February and recover into March
+fn market_data() -> Vec<MarketData> {
+    let start = datetime!(2024-01-10 0:00 UTC);
+    let mut data = vec![];
+    for (symbol, start_price) in [("USDC", dec!(1)), ("ETH", dec!(2000)), ("BTC", dec!(40000)), ("SOL", dec!(100))] {
+        let mut price = start_price;
+        for day in 0..=DAYS {
+            data.push(MarketData {
+                timestamp: start + time::Duration::days(day),
+                symbol: symbol.to_string(),
+                price,
+                volume: Decimal::from(1_000_000),
+                high: price,
+                low: price,
+                open: price,
+                close: price,
+            });
+            if symbol != "USDC" {
+                price *= if (25..40).contains(&day) { dec!(0.99) } else { dec!(1.004) };
+            }
+        }
+    }
+    data
+}
+
+#[tokio::test]
+async fn reports_match_the_golden_files() {
+    let results = BacktestEngine::new("2024-01-10", "2024-03-25", Strategy::balanced())
+        .unwrap()
+        .with_market_data(market_data())
+        .unwrap()
+        .with_benchmark(Benchmark::from_spec("BTC").unwrap())
+        .run()
+        .await
+        .unwrap();
+
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let reports = [(ReportFormat::Markdown, "backtest_report.md"), (ReportFormat::Html, "backtest_report.html")];
+    for (format, file) in reports {
+        let rendered = results.render_report(format);
+        let path = golden.join(file);
+        if update {
+            std::fs::write(&path, &rendered).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(rendered, expected, "{} differs from its golden file; rerun with UPDATE_GOLDEN=1 if intended", file);
+    }
+}
@@ -0,0 +1,81 @@
+//! The trade tape and event log of a fixed target-weight backtest against the
+//! golden files in `tests/golden`, so changes to the column order, formats or
+//! event shapes show up as a diff. Set `UPDATE_GOLDEN=1` to rewrite the golden
+//! files after an intended change.
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::path::Path;
+use time::macros::datetime;
+use vaulta_simulator::backtest::{BacktestConfig, BacktestEngine};
+use vaulta_simulator::types::{BacktestResults, MarketData};
+use vaulta_simulator::Strategy;
+
+const DAYS: i64 = 20;
+
+/// ETH swinging between 2000 and 2800 every four days, so the 40/30/30
+/// target-weight strategy has to sell and buy back
+fn market_data() -> Vec<MarketData> {
+    let start = datetime!(2024-01-01 0:00 UTC);
+    let mut data = vec![];
+    for day in 0..=DAYS {
+        let eth = if (day / 4) % 2 == 0 { dec!(2000) } else { dec!(2800) };
+        for (symbol, price) in [("USDC", dec!(1)), ("ETH", eth), ("BTC", dec!(40000))] {
+            data.push(MarketData {
+                timestamp: start + time::Duration::days(day),
+                symbol: symbol.to_string(),
+                price,
+                volume: Decimal::from(1_000_000),
+                high: price,
+                low: price,
+                open: price,
+                close: price,
+            });
+        }
+    }
+    data
+}
+
+/// Writes one export of the results to a path
+type Export = fn(&BacktestResults, &Path) -> anyhow::Result<()>;
+
+/// The file `write` produces for `results`
+fn render(results: &BacktestResults, write: Export) -> String {
+    let path = std::env::temp_dir().join(format!("vaulta-trade-tape-{}", uuid::Uuid::new_v4()));
+    write(results, &path).unwrap();
+    let rendered = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    rendered.unwrap()
+}
+
+#[tokio::test]
+async fn exports_match_the_golden_files() {
+    let results = BacktestEngine::new("2024-01-01", "2024-01-21", Strategy::from_name("target_weight").unwrap())
+        .unwrap()
+        .with_market_data(market_data())
+        .unwrap()
+        .with_config(BacktestConfig { record_events: true, ..BacktestConfig::default() })
+        .unwrap()
+        .run()
+        .await
+        .unwrap();
+    assert!(results.trades.iter().any(|trade| trade.exit_time.is_some()), "expected closed trades");
+    assert!(results.trades.iter().any(|trade| !trade.fees.is_zero()), "expected trades paying fees");
+
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let exports: [(&str, Export); 2] = [
+        ("backtest_trades.csv", |results, path| results.write_trades_csv(path)),
+        ("backtest_events.jsonl", |results, path| results.write_events_jsonl(path)),
+    ];
+    for (file, write) in exports {
+        let rendered = render(&results, write);
+        let path = golden.join(file);
+        if update {
+            std::fs::write(&path, &rendered).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(rendered, expected, "{} differs from its golden file; rerun with UPDATE_GOLDEN=1 if intended", file);
+    }
+}